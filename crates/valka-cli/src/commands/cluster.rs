@@ -0,0 +1,72 @@
+use anyhow::Result;
+use valka_proto::*;
+
+use super::Server;
+
+pub async fn status(server: &Server, queue: Option<&str>) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let response = client
+        .get_cluster_status(GetClusterStatusRequest {
+            queue_name: queue.unwrap_or_default().to_string(),
+        })
+        .await?
+        .into_inner();
+
+    println!("Node:      {}", response.node_id);
+    println!(
+        "Mode:      {}",
+        if response.clustered {
+            "clustered"
+        } else {
+            "single node"
+        }
+    );
+    if response.is_leader {
+        println!("Scheduler: leader since {}", response.leader_since);
+    } else {
+        println!("Scheduler: not leader");
+    }
+
+    println!();
+    println!(
+        "{:<38} {:<24} {:<6} {:<26}",
+        "NODE", "GRPC ADDR", "LIVE", "JOINED"
+    );
+    println!("{}", "-".repeat(96));
+    for member in &response.members {
+        let node = if member.is_self {
+            format!("{} *", member.node_id)
+        } else {
+            member.node_id.clone()
+        };
+        println!(
+            "{:<38} {:<24} {:<6} {:<26}",
+            node,
+            or_dash(&member.grpc_addr),
+            if member.live { "yes" } else { "no" },
+            or_dash(&member.joined_at),
+        );
+    }
+
+    if let Some(queue) = queue {
+        println!();
+        println!("Partitions of {queue}:");
+        println!("{:<10} {:<38} {:<24}", "PARTITION", "NODE", "GRPC ADDR");
+        println!("{}", "-".repeat(72));
+        for partition in &response.partitions {
+            println!(
+                "{:<10} {:<38} {:<24}",
+                partition.partition_id,
+                partition.node_id,
+                or_dash(&partition.grpc_addr),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn or_dash(value: &str) -> &str {
+    if value.is_empty() { "-" } else { value }
+}
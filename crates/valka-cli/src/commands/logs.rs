@@ -1,12 +1,11 @@
 use anyhow::Result;
 use futures::StreamExt;
-use tonic::transport::Channel;
-use valka_proto::api_service_client::ApiServiceClient;
 use valka_proto::*;
 
-pub async fn tail(server: &str, task_run_id: &str) -> Result<()> {
-    let channel = Channel::from_shared(server.to_string())?.connect().await?;
-    let mut client = ApiServiceClient::new(channel);
+use super::Server;
+
+pub async fn tail(server: &Server, task_run_id: &str) -> Result<()> {
+    let mut client = server.connect().await?;
 
     let response = client
         .subscribe_logs(SubscribeLogsRequest {
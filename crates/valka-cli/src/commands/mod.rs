@@ -1,2 +1,28 @@
+pub mod cluster;
 pub mod logs;
+pub mod queue;
+pub mod schedule;
 pub mod task;
+pub mod worker;
+
+use anyhow::Result;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::Channel;
+use valka_proto::api_service_client::ApiServiceClient;
+use valka_sdk::auth::BearerToken;
+
+/// The server's gRPC address and the API token to present, if any.
+pub struct Server {
+    pub addr: String,
+    pub token: Option<String>,
+}
+
+pub type ApiClient = ApiServiceClient<InterceptedService<Channel, BearerToken>>;
+
+impl Server {
+    pub async fn connect(&self) -> Result<ApiClient> {
+        let channel = Channel::from_shared(self.addr.clone())?.connect().await?;
+        let token = BearerToken::new(self.token.as_deref().unwrap_or_default());
+        Ok(ApiServiceClient::with_interceptor(channel, token))
+    }
+}
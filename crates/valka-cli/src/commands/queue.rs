@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+use valka_proto::*;
+
+use super::Server;
+
+pub async fn get(server: &Server, queue: &str) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let response = client
+        .get_queue_config(GetQueueConfigRequest {
+            queue_name: queue.to_string(),
+        })
+        .await?;
+
+    if let Some(config) = response.into_inner().config {
+        print_config(&config);
+    } else {
+        println!("Queue config not found");
+    }
+
+    Ok(())
+}
+
+/// Fields for `queue set`; `None` leaves the stored value unchanged.
+pub struct QueueConfigArgs {
+    pub owner_team: Option<String>,
+    pub contact: Option<String>,
+    pub runbook_url: Option<String>,
+    pub min_worker_version: Option<String>,
+    pub allow_ephemeral: Option<bool>,
+    pub template_input: Option<bool>,
+    pub fifo: Option<bool>,
+    pub default_max_retries: Option<i32>,
+    pub default_timeout_seconds: Option<i32>,
+    pub max_concurrency: Option<i32>,
+    pub paused: Option<bool>,
+    pub retention_days: Option<i32>,
+    pub dead_letter_enabled: Option<bool>,
+    pub max_dispatch_per_second: Option<i32>,
+    pub labels: Vec<String>,
+    pub clear_labels: bool,
+}
+
+pub async fn set(server: &Server, queue: &str, args: QueueConfigArgs) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let mut label_map = HashMap::new();
+    for label in &args.labels {
+        let Some((key, value)) = label.split_once('=') else {
+            bail!("Invalid label {label:?}, expected key=value");
+        };
+        label_map.insert(key.to_string(), value.to_string());
+    }
+
+    let response = client
+        .update_queue_config(UpdateQueueConfigRequest {
+            queue_name: queue.to_string(),
+            owner_team: args.owner_team,
+            contact: args.contact,
+            runbook_url: args.runbook_url,
+            labels: label_map,
+            replace_labels: args.clear_labels || !args.labels.is_empty(),
+            min_worker_version: args.min_worker_version,
+            allow_ephemeral: args.allow_ephemeral,
+            template_input: args.template_input,
+            fifo: args.fifo,
+            default_max_retries: args.default_max_retries,
+            default_timeout_seconds: args.default_timeout_seconds,
+            max_concurrency: args.max_concurrency,
+            paused: args.paused,
+            retention_days: args.retention_days,
+            dead_letter_enabled: args.dead_letter_enabled,
+            max_dispatch_per_second: args.max_dispatch_per_second,
+        })
+        .await?;
+
+    if let Some(config) = response.into_inner().config {
+        println!("Queue config updated:");
+        print_config(&config);
+    }
+
+    Ok(())
+}
+
+pub async fn pause(server: &Server, queue: &str) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    client
+        .pause_queue(PauseQueueRequest {
+            queue_name: queue.to_string(),
+        })
+        .await?;
+
+    println!("Queue {queue} paused");
+    Ok(())
+}
+
+pub async fn resume(server: &Server, queue: &str) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    client
+        .resume_queue(ResumeQueueRequest {
+            queue_name: queue.to_string(),
+        })
+        .await?;
+
+    println!("Queue {queue} resumed");
+    Ok(())
+}
+
+pub async fn list(server: &Server, owner_team: Option<String>) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let response = client
+        .list_queue_configs(ListQueueConfigsRequest {
+            owner_team: owner_team.unwrap_or_default(),
+        })
+        .await?;
+
+    let configs = response.into_inner().configs;
+    if configs.is_empty() {
+        println!("No queue configs found");
+        return Ok(());
+    }
+
+    println!("{:<24} {:<20} {:<30}", "QUEUE", "OWNER", "CONTACT");
+    println!("{}", "-".repeat(76));
+
+    for config in configs {
+        println!(
+            "{:<24} {:<20} {:<30}",
+            config.queue_name, config.owner_team, config.contact,
+        );
+    }
+
+    Ok(())
+}
+
+fn print_config(config: &QueueConfig) {
+    println!("  Queue:          {}", config.queue_name);
+    println!("  Owner team:     {}", config.owner_team);
+    println!("  Contact:        {}", config.contact);
+    println!("  Runbook:        {}", config.runbook_url);
+    if !config.min_worker_version.is_empty() {
+        println!("  Min worker:     {}", config.min_worker_version);
+    }
+    if config.allow_ephemeral {
+        println!("  Ephemeral:      allowed");
+    }
+    if config.template_input {
+        println!("  Input:          templated at dispatch");
+    }
+    if config.fifo {
+        println!("  Ordering:       FIFO per fifo_key");
+    }
+    if config.paused {
+        println!("  Paused:         yes");
+    }
+    if config.default_max_retries > 0 {
+        println!("  Max retries:    {} (default)", config.default_max_retries);
+    }
+    if config.default_timeout_seconds > 0 {
+        println!(
+            "  Timeout:        {}s (default)",
+            config.default_timeout_seconds
+        );
+    }
+    if config.max_concurrency > 0 {
+        println!("  Concurrency:    {} max", config.max_concurrency);
+    }
+    if config.retention_days > 0 {
+        println!("  Retention:      {} days", config.retention_days);
+    }
+    if config.max_dispatch_per_second > 0 {
+        println!(
+            "  Dispatch rate:  {} tasks/s per node",
+            config.max_dispatch_per_second
+        );
+    }
+    if !config.dead_letter_enabled {
+        println!("  Dead letters:   disabled (exhausted tasks stay FAILED)");
+    }
+    if config.scheduled_hold {
+        println!("  Scheduled:      held");
+    } else if config.release_max_per_tick > 0 {
+        println!(
+            "  Scheduled:      releasing {} per tick",
+            config.release_max_per_tick
+        );
+    }
+    if !config.labels.is_empty() {
+        let mut labels: Vec<_> = config.labels.iter().collect();
+        labels.sort();
+        println!("  Labels:");
+        for (key, value) in labels {
+            println!("    {key}={value}");
+        }
+    }
+    println!("  Updated:        {}", config.updated_at);
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use valka_proto::*;
+
+use super::Server;
+
+/// Fields for `schedule create`.
+pub struct ScheduleArgs {
+    pub cron: String,
+    pub queue: String,
+    pub name: String,
+    pub input: Option<String>,
+    pub priority: i32,
+    pub max_retries: i32,
+    pub timeout: i32,
+}
+
+pub async fn create(server: &Server, args: ScheduleArgs) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let response = client
+        .create_schedule(CreateScheduleRequest {
+            cron_expression: args.cron,
+            queue_name: args.queue,
+            task_name: args.name,
+            input: args.input.unwrap_or_default(),
+            priority: args.priority,
+            max_retries: args.max_retries,
+            timeout_seconds: args.timeout,
+        })
+        .await?;
+
+    if let Some(schedule) = response.into_inner().schedule {
+        println!("Schedule created:");
+        print_schedule(&schedule);
+    }
+
+    Ok(())
+}
+
+pub async fn list(server: &Server, queue: Option<String>) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let response = client
+        .list_schedules(ListSchedulesRequest {
+            queue_name: queue.unwrap_or_default(),
+        })
+        .await?;
+
+    let schedules = response.into_inner().schedules;
+    if schedules.is_empty() {
+        println!("No schedules found");
+        return Ok(());
+    }
+
+    println!(
+        "{:<38} {:<18} {:<20} {:<20} {:<26}",
+        "ID", "CRON", "QUEUE", "TASK", "NEXT FIRE"
+    );
+    println!("{}", "-".repeat(124));
+
+    for schedule in schedules {
+        println!(
+            "{:<38} {:<18} {:<20} {:<20} {:<26}",
+            schedule.id,
+            schedule.cron_expression,
+            schedule.queue_name,
+            schedule.task_name,
+            schedule.next_fire_at,
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn delete(server: &Server, schedule_id: &str) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    client
+        .delete_schedule(DeleteScheduleRequest {
+            schedule_id: schedule_id.to_string(),
+        })
+        .await?;
+
+    println!("Schedule {schedule_id} deleted");
+    Ok(())
+}
+
+fn print_schedule(schedule: &TaskSchedule) {
+    println!("  ID:          {}", schedule.id);
+    println!("  Cron:        {} (UTC)", schedule.cron_expression);
+    println!("  Queue:       {}", schedule.queue_name);
+    println!("  Task:        {}", schedule.task_name);
+    if !schedule.input.is_empty() {
+        println!("  Input:       {}", schedule.input);
+    }
+    println!("  Next fire:   {}", schedule.next_fire_at);
+    if !schedule.last_fired_at.is_empty() {
+        println!("  Last fired:  {}", schedule.last_fired_at);
+    }
+}
@@ -1,10 +1,10 @@
 use anyhow::Result;
-use tonic::transport::Channel;
-use valka_proto::api_service_client::ApiServiceClient;
 use valka_proto::*;
 
+use super::Server;
+
 pub async fn create(
-    server: &str,
+    server: &Server,
     queue: &str,
     name: &str,
     input: Option<String>,
@@ -12,7 +12,7 @@ pub async fn create(
     max_retries: i32,
     timeout: i32,
 ) -> Result<()> {
-    let mut client = connect(server).await?;
+    let mut client = server.connect().await?;
 
     let response = client
         .create_task(CreateTaskRequest {
@@ -25,6 +25,13 @@ pub async fn create(
             idempotency_key: String::new(),
             metadata: String::new(),
             scheduled_at: String::new(),
+            deadline_ms: 0,
+            deadline_in_ms: 0,
+            durability: String::new(),
+            fifo_key: String::new(),
+            callback_url: String::new(),
+            retry_backoff: None,
+            required_labels: Default::default(),
         })
         .await?;
 
@@ -36,8 +43,8 @@ pub async fn create(
     Ok(())
 }
 
-pub async fn get(server: &str, task_id: &str) -> Result<()> {
-    let mut client = connect(server).await?;
+pub async fn get(server: &Server, task_id: &str) -> Result<()> {
+    let mut client = server.connect().await?;
 
     let response = client
         .get_task(GetTaskRequest {
@@ -45,8 +52,12 @@ pub async fn get(server: &str, task_id: &str) -> Result<()> {
         })
         .await?;
 
-    if let Some(task) = response.into_inner().task {
+    let response = response.into_inner();
+    if let Some(task) = response.task {
         print_task(&task);
+        if let Some(plan) = response.retry_plan {
+            print_retry_plan(&plan);
+        }
     } else {
         println!("Task not found");
     }
@@ -55,12 +66,12 @@ pub async fn get(server: &str, task_id: &str) -> Result<()> {
 }
 
 pub async fn list(
-    server: &str,
+    server: &Server,
     queue: Option<String>,
     status: Option<String>,
     limit: i32,
 ) -> Result<()> {
-    let mut client = connect(server).await?;
+    let mut client = server.connect().await?;
 
     let status_enum = status.as_deref().map(status_str_to_proto).unwrap_or(0);
 
@@ -72,6 +83,7 @@ pub async fn list(
                 page_size: limit,
                 page_token: String::new(),
             }),
+            ..Default::default()
         })
         .await?;
 
@@ -101,8 +113,8 @@ pub async fn list(
     Ok(())
 }
 
-pub async fn cancel(server: &str, task_id: &str) -> Result<()> {
-    let mut client = connect(server).await?;
+pub async fn cancel(server: &Server, task_id: &str) -> Result<()> {
+    let mut client = server.connect().await?;
 
     let response = client
         .cancel_task(CancelTaskRequest {
@@ -118,9 +130,103 @@ pub async fn cancel(server: &str, task_id: &str) -> Result<()> {
     Ok(())
 }
 
-async fn connect(server: &str) -> Result<ApiServiceClient<Channel>> {
-    let channel = Channel::from_shared(server.to_string())?.connect().await?;
-    Ok(ApiServiceClient::new(channel))
+pub async fn reclaim(
+    server: &Server,
+    task_id: &str,
+    reason: &str,
+    immediate: bool,
+    admin_token: Option<String>,
+) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let mut request = tonic::Request::new(ReclaimTaskRequest {
+        task_id: task_id.to_string(),
+        reason: reason.to_string(),
+        immediate,
+    });
+    if let Some(token) = admin_token {
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {token}").parse()?);
+    }
+    let response = client.reclaim_task(request).await?.into_inner();
+
+    println!(
+        "Task reclaimed from worker {} (run {} abandoned)",
+        response.worker_id, response.abandoned_run_id
+    );
+    if !response.worker_notified {
+        println!("The worker is not connected to this node and was not sent a cancellation");
+    }
+    if let Some(task) = response.task {
+        print_task(&task);
+    }
+
+    Ok(())
+}
+
+pub async fn signal(
+    server: &Server,
+    task_id: &str,
+    name: &str,
+    payload: Option<String>,
+) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let response = client
+        .send_signal(SendSignalRequest {
+            task_id: task_id.to_string(),
+            signal_name: name.to_string(),
+            payload: payload.unwrap_or_default(),
+        })
+        .await?
+        .into_inner();
+
+    if response.delivered {
+        println!(
+            "Signal {} delivered to the task's worker",
+            response.signal_id
+        );
+    } else {
+        println!(
+            "Signal {} stored as PENDING; no connected worker is running the task",
+            response.signal_id
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn signals(server: &Server, task_id: &str, status: Option<String>) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let signals = client
+        .list_signals(ListSignalsRequest {
+            task_id: task_id.to_string(),
+            status: status.map(|s| s.to_uppercase()).unwrap_or_default(),
+        })
+        .await?
+        .into_inner()
+        .signals;
+    if signals.is_empty() {
+        println!("No signals found");
+        return Ok(());
+    }
+
+    println!(
+        "{:<38} {:<20} {:<14} {:<27} PAYLOAD",
+        "ID", "NAME", "STATUS", "CREATED"
+    );
+    println!("{}", "-".repeat(110));
+
+    for signal in signals {
+        println!(
+            "{:<38} {:<20} {:<14} {:<27} {}",
+            signal.id, signal.signal_name, signal.status, signal.created_at, signal.payload,
+        );
+    }
+
+    Ok(())
 }
 
 fn print_task(task: &TaskMeta) {
@@ -147,6 +253,25 @@ fn print_task(task: &TaskMeta) {
     println!("  Updated:        {}", task.updated_at);
 }
 
+fn print_retry_plan(plan: &RetryPlan) {
+    println!("  Retry plan:");
+    if plan.next_attempt_at.is_empty() {
+        println!("    Next attempt:   none (deadline comes first)");
+    } else {
+        println!("    Next attempt:   {}", plan.next_attempt_at);
+    }
+    println!("    Attempts left:  {}", plan.attempts_remaining);
+    for (i, at) in plan.schedule.iter().enumerate() {
+        println!("    #{:<14} {at}", i + 1);
+    }
+    let destination = if plan.dead_letter {
+        "dead letter queue"
+    } else {
+        "FAILED (deadline)"
+    };
+    println!("    Then:           {destination}");
+}
+
 fn status_str_to_proto(s: &str) -> i32 {
     match s.to_uppercase().as_str() {
         "PENDING" => 1,
@@ -157,6 +282,7 @@ fn status_str_to_proto(s: &str) -> i32 {
         "RETRY" => 6,
         "DEAD_LETTER" => 7,
         "CANCELLED" => 8,
+        "WAITING" => 9,
         _ => 0,
     }
 }
@@ -171,6 +297,7 @@ fn proto_status_to_str(status: i32) -> &'static str {
         6 => "RETRY",
         7 => "DEAD_LETTER",
         8 => "CANCELLED",
+        9 => "WAITING",
         _ => "UNKNOWN",
     }
 }
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use valka_proto::*;
+
+use super::Server;
+
+pub async fn list(server: &Server, local_only: bool) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let response = client
+        .list_workers(ListWorkersRequest { local_only })
+        .await?
+        .into_inner();
+
+    if response.workers.is_empty() {
+        println!("No workers connected");
+    } else {
+        println!(
+            "{:<38} {:<20} {:<14} {:<10} {:<24} {:<8} {:<20}",
+            "ID", "NAME", "NODE", "STATUS", "QUEUES", "ACTIVE", "LAST HEARTBEAT"
+        );
+        println!("{}", "-".repeat(140));
+
+        for worker in &response.workers {
+            println!(
+                "{:<38} {:<20} {:<14} {:<10} {:<24} {:<8} {:<20}",
+                worker.worker_id,
+                worker.worker_name,
+                short_id(&worker.node_id),
+                worker.status,
+                worker.queues.join(","),
+                format!("{}/{}", worker.active_tasks, worker.concurrency),
+                short_time(&worker.last_heartbeat),
+            );
+        }
+    }
+
+    for node in &response.unreachable_nodes {
+        eprintln!("warning: node {node} did not respond; its workers are not listed");
+    }
+
+    Ok(())
+}
+
+pub async fn drain(server: &Server, worker_id: &str, timeout_seconds: i32) -> Result<()> {
+    let mut client = server.connect().await?;
+
+    let response = client
+        .drain_worker(DrainWorkerRequest {
+            worker_id: worker_id.to_string(),
+            timeout_seconds,
+        })
+        .await?
+        .into_inner();
+
+    println!(
+        "Draining worker {worker_id} on node {} ({} active tasks)",
+        short_id(&response.node_id),
+        response.active_tasks
+    );
+    if timeout_seconds > 0 {
+        println!("Its stream will be closed in {timeout_seconds}s if tasks are still running");
+    }
+
+    Ok(())
+}
+
+/// Node ids are UUIDv7s, whose leading blocks are a timestamp; the last
+/// (random) block is enough to tell nodes apart.
+fn short_id(id: &str) -> &str {
+    id.rsplit('-').next().unwrap_or(id)
+}
+
+/// RFC3339 timestamp without the fractional seconds and offset.
+fn short_time(ts: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(ts)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| ts.to_string())
+}
@@ -12,6 +12,10 @@ struct Cli {
     /// gRPC server address
     #[arg(long, default_value = "http://[::1]:50051", global = true)]
     server: String,
+
+    /// API token (default: $VALKA_TOKEN)
+    #[arg(long, global = true)]
+    token: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -21,6 +25,16 @@ enum Commands {
         #[command(subcommand)]
         command: TaskCommands,
     },
+    /// Queue configuration
+    Queue {
+        #[command(subcommand)]
+        command: QueueCommands,
+    },
+    /// Recurring task schedules
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommands,
+    },
     /// Worker operations
     Worker {
         #[command(subcommand)]
@@ -89,16 +103,174 @@ enum TaskCommands {
         /// Task ID
         task_id: String,
     },
+    /// Take a RUNNING task back from its worker and re-dispatch it
+    Reclaim {
+        /// Task ID
+        task_id: String,
+        /// Why the task is being reclaimed (recorded on the abandoned run)
+        #[arg(long)]
+        reason: String,
+        /// Re-dispatch right away instead of going through retry backoff
+        #[arg(long)]
+        immediate: bool,
+        /// Server admin token (default: $VALKA_ADMIN_TOKEN)
+        #[arg(long)]
+        admin_token: Option<String>,
+    },
+    /// Send a signal to a task
+    Signal {
+        /// Task ID
+        task_id: String,
+        /// Signal name
+        #[arg(long)]
+        name: String,
+        /// Payload JSON
+        #[arg(long)]
+        payload: Option<String>,
+    },
+    /// List the signals sent to a task
+    Signals {
+        /// Task ID
+        task_id: String,
+        /// Filter by status (PENDING, DELIVERED or ACKNOWLEDGED)
+        #[arg(long)]
+        status: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueueCommands {
+    /// Show a queue's configuration
+    Get {
+        /// Queue name
+        queue: String,
+    },
+    /// Update a queue's ownership metadata (empty string clears a field)
+    Set {
+        /// Queue name
+        queue: String,
+        /// Owning team
+        #[arg(long)]
+        owner_team: Option<String>,
+        /// Slack channel or email
+        #[arg(long)]
+        contact: Option<String>,
+        /// Runbook URL
+        #[arg(long)]
+        runbook_url: Option<String>,
+        /// Minimum worker version (semver) allowed to take tasks; empty clears
+        #[arg(long)]
+        min_worker_version: Option<String>,
+        /// Allow tasks created with ephemeral durability
+        #[arg(long)]
+        allow_ephemeral: Option<bool>,
+        /// Substitute {{valka.*}} and {{meta.*}} placeholders in task input at dispatch
+        #[arg(long)]
+        template_input: Option<bool>,
+        /// Run tasks sharing a fifo_key one at a time, in creation order
+        #[arg(long)]
+        fifo: Option<bool>,
+        /// Max retries for tasks created without one; 0 clears
+        #[arg(long)]
+        default_max_retries: Option<i32>,
+        /// Timeout in seconds for tasks created without one; 0 clears
+        #[arg(long)]
+        default_timeout_seconds: Option<i32>,
+        /// Concurrency limit for the queue; 0 clears
+        #[arg(long)]
+        max_concurrency: Option<i32>,
+        /// Stop dispatching tasks from the queue
+        #[arg(long)]
+        paused: Option<bool>,
+        /// Days to keep finished tasks; 0 clears
+        #[arg(long)]
+        retention_days: Option<i32>,
+        /// Move exhausted tasks to the dead letter queue; false leaves them FAILED
+        #[arg(long)]
+        dead_letter_enabled: Option<bool>,
+        /// Tasks per second each node may dispatch from the queue; 0 clears
+        #[arg(long)]
+        max_dispatch_per_second: Option<i32>,
+        /// Label as key=value; replaces all existing labels (repeatable)
+        #[arg(long = "label")]
+        labels: Vec<String>,
+        /// Remove all labels
+        #[arg(long)]
+        clear_labels: bool,
+    },
+    /// Stop dispatching a queue's tasks; they stay PENDING until resumed
+    Pause {
+        /// Queue name
+        queue: String,
+    },
+    /// Resume dispatch of a paused queue
+    Resume {
+        /// Queue name
+        queue: String,
+    },
+    /// List queue configs
+    List {
+        /// Filter by owning team
+        #[arg(long)]
+        owner_team: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommands {
+    /// Create a task every time a cron expression fires
+    Create {
+        /// Cron expression in UTC, e.g. "*/5 * * * *"
+        #[arg(long)]
+        cron: String,
+        /// Queue name
+        #[arg(long)]
+        queue: String,
+        /// Task name
+        #[arg(long)]
+        name: String,
+        /// Input JSON
+        #[arg(long)]
+        input: Option<String>,
+        /// Priority
+        #[arg(long, default_value = "0")]
+        priority: i32,
+        /// Max retries (0 takes the queue's default)
+        #[arg(long, default_value = "0")]
+        max_retries: i32,
+        /// Timeout in seconds (0 takes the queue's default)
+        #[arg(long, default_value = "0")]
+        timeout: i32,
+    },
+    /// List schedules
+    List {
+        /// Filter by queue name
+        #[arg(long)]
+        queue: Option<String>,
+    },
+    /// Delete a schedule
+    Delete {
+        /// Schedule ID
+        schedule_id: String,
+    },
 }
 
 #[derive(Subcommand)]
 enum WorkerCommands {
     /// List connected workers
-    List,
+    List {
+        /// Only workers connected to the node the CLI talks to
+        #[arg(long)]
+        local: bool,
+    },
     /// Drain a worker (graceful shutdown)
     Drain {
         /// Worker ID
         worker_id: String,
+        /// Close the worker's stream after this many seconds even if tasks are
+        /// still running (0 waits for them)
+        #[arg(long, default_value = "0")]
+        timeout: i32,
     },
 }
 
@@ -113,8 +285,12 @@ enum LogCommands {
 
 #[derive(Subcommand)]
 enum ClusterCommands {
-    /// Show cluster status
-    Status,
+    /// Show cluster members, and a queue's partition owners if given
+    Status {
+        /// Also show which node owns each of this queue's partitions
+        #[arg(long)]
+        queue: Option<String>,
+    },
 }
 
 #[tokio::main]
@@ -124,6 +300,10 @@ async fn main() -> Result<()> {
         .init();
 
     let cli = Cli::parse();
+    let server = commands::Server {
+        addr: cli.server,
+        token: cli.token.or_else(|| std::env::var("VALKA_TOKEN").ok()),
+    };
 
     match cli.command {
         Commands::Task { command } => match command {
@@ -136,7 +316,7 @@ async fn main() -> Result<()> {
                 timeout,
             } => {
                 commands::task::create(
-                    &cli.server,
+                    &server,
                     &queue,
                     &name,
                     input,
@@ -147,32 +327,130 @@ async fn main() -> Result<()> {
                 .await?;
             }
             TaskCommands::Get { task_id } => {
-                commands::task::get(&cli.server, &task_id).await?;
+                commands::task::get(&server, &task_id).await?;
             }
             TaskCommands::List {
                 queue,
                 status,
                 limit,
             } => {
-                commands::task::list(&cli.server, queue, status, limit).await?;
+                commands::task::list(&server, queue, status, limit).await?;
             }
             TaskCommands::Cancel { task_id } => {
-                commands::task::cancel(&cli.server, &task_id).await?;
+                commands::task::cancel(&server, &task_id).await?;
+            }
+            TaskCommands::Reclaim {
+                task_id,
+                reason,
+                immediate,
+                admin_token,
+            } => {
+                let admin_token = admin_token.or_else(|| std::env::var("VALKA_ADMIN_TOKEN").ok());
+                commands::task::reclaim(&server, &task_id, &reason, immediate, admin_token).await?;
+            }
+            TaskCommands::Signal {
+                task_id,
+                name,
+                payload,
+            } => {
+                commands::task::signal(&server, &task_id, &name, payload).await?;
+            }
+            TaskCommands::Signals { task_id, status } => {
+                commands::task::signals(&server, &task_id, status).await?;
             }
         },
-        Commands::Worker { command } => match command {
-            WorkerCommands::List => {
-                println!("Worker list not yet implemented (requires admin RPC)");
+        Commands::Queue { command } => match command {
+            QueueCommands::Get { queue } => {
+                commands::queue::get(&server, &queue).await?;
             }
-            WorkerCommands::Drain {
-                worker_id: _worker_id,
+            QueueCommands::Set {
+                queue,
+                owner_team,
+                contact,
+                runbook_url,
+                min_worker_version,
+                allow_ephemeral,
+                template_input,
+                fifo,
+                default_max_retries,
+                default_timeout_seconds,
+                max_concurrency,
+                paused,
+                retention_days,
+                dead_letter_enabled,
+                max_dispatch_per_second,
+                labels,
+                clear_labels,
+            } => {
+                let args = commands::queue::QueueConfigArgs {
+                    owner_team,
+                    contact,
+                    runbook_url,
+                    min_worker_version,
+                    allow_ephemeral,
+                    template_input,
+                    fifo,
+                    default_max_retries,
+                    default_timeout_seconds,
+                    max_concurrency,
+                    paused,
+                    retention_days,
+                    dead_letter_enabled,
+                    max_dispatch_per_second,
+                    labels,
+                    clear_labels,
+                };
+                commands::queue::set(&server, &queue, args).await?;
+            }
+            QueueCommands::Pause { queue } => {
+                commands::queue::pause(&server, &queue).await?;
+            }
+            QueueCommands::Resume { queue } => {
+                commands::queue::resume(&server, &queue).await?;
+            }
+            QueueCommands::List { owner_team } => {
+                commands::queue::list(&server, owner_team).await?;
+            }
+        },
+        Commands::Schedule { command } => match command {
+            ScheduleCommands::Create {
+                cron,
+                queue,
+                name,
+                input,
+                priority,
+                max_retries,
+                timeout,
             } => {
-                println!("Worker drain not yet implemented (requires admin RPC)");
+                let args = commands::schedule::ScheduleArgs {
+                    cron,
+                    queue,
+                    name,
+                    input,
+                    priority,
+                    max_retries,
+                    timeout,
+                };
+                commands::schedule::create(&server, args).await?;
+            }
+            ScheduleCommands::List { queue } => {
+                commands::schedule::list(&server, queue).await?;
+            }
+            ScheduleCommands::Delete { schedule_id } => {
+                commands::schedule::delete(&server, &schedule_id).await?;
+            }
+        },
+        Commands::Worker { command } => match command {
+            WorkerCommands::List { local } => {
+                commands::worker::list(&server, local).await?;
+            }
+            WorkerCommands::Drain { worker_id, timeout } => {
+                commands::worker::drain(&server, &worker_id, timeout).await?;
             }
         },
         Commands::Logs { command } => match command {
             LogCommands::Tail { task_run_id } => {
-                commands::logs::tail(&cli.server, &task_run_id).await?;
+                commands::logs::tail(&server, &task_run_id).await?;
             }
         },
         Commands::Server { config: _config } => {
@@ -180,8 +458,8 @@ async fn main() -> Result<()> {
             println!("  valka-server [config-path]");
         }
         Commands::Cluster { command } => match command {
-            ClusterCommands::Status => {
-                println!("Cluster status not yet implemented");
+            ClusterCommands::Status { queue } => {
+                commands::cluster::status(&server, queue.as_deref()).await?;
             }
         },
     }
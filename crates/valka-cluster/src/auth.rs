@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
+use tonic::metadata::{AsciiMetadataValue, MetadataMap};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// Metadata key carrying the internal RPC token.
+pub const CLUSTER_TOKEN_HEADER: &str = "x-valka-cluster-token";
+
+const GOSSIP_KEY_INFO: &[u8] = b"valka gossip v1";
+const RPC_TOKEN_INFO: &[u8] = b"valka internal rpc v1";
+const RPC_TOKEN_LEN: usize = 32;
+
+/// Protection for traffic between nodes, keyed by the shared cluster secret
+/// and cluster_id: gossip datagrams are sealed with ChaCha20-Poly1305 and
+/// internal RPCs carry a token derived from the secret (never the secret
+/// itself). Without a secret nothing is sealed and every RPC is let through.
+#[derive(Clone, Default)]
+pub struct ClusterAuth {
+    keys: Option<Arc<Keys>>,
+}
+
+struct Keys {
+    gossip: LessSafeKey,
+    rpc_token: AsciiMetadataValue,
+    rng: SystemRandom,
+}
+
+struct TokenLen;
+
+impl hkdf::KeyType for TokenLen {
+    fn len(&self) -> usize {
+        RPC_TOKEN_LEN
+    }
+}
+
+impl ClusterAuth {
+    /// An empty or missing secret disables protection.
+    pub fn new(secret: Option<&str>, cluster_id: &str) -> Self {
+        let Some(secret) = secret.filter(|s| !s.is_empty()) else {
+            return Self::default();
+        };
+        let prk =
+            hkdf::Salt::new(hkdf::HKDF_SHA256, cluster_id.as_bytes()).extract(secret.as_bytes());
+        let gossip = prk
+            .expand(&[GOSSIP_KEY_INFO], &CHACHA20_POLY1305)
+            .map(UnboundKey::from)
+            .expect("HKDF output fits the AEAD key");
+        let mut token = [0u8; RPC_TOKEN_LEN];
+        prk.expand(&[RPC_TOKEN_INFO], TokenLen)
+            .and_then(|okm| okm.fill(&mut token))
+            .expect("HKDF output fits the token");
+        let rpc_token = hex::encode(token).parse().expect("hex is valid metadata");
+        Self {
+            keys: Some(Arc::new(Keys {
+                gossip: LessSafeKey::new(gossip),
+                rpc_token,
+                rng: SystemRandom::new(),
+            })),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.keys.is_some()
+    }
+
+    /// Seal a gossip payload as `nonce || ciphertext || tag`.
+    pub fn seal(&self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let Some(keys) = &self.keys else {
+            return Ok(payload.to_vec());
+        };
+        let mut nonce = [0u8; NONCE_LEN];
+        keys.rng
+            .fill(&mut nonce)
+            .map_err(|_| anyhow::anyhow!("failed to generate gossip nonce"))?;
+        let mut sealed =
+            Vec::with_capacity(NONCE_LEN + payload.len() + CHACHA20_POLY1305.tag_len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(payload);
+        let tag = keys
+            .gossip
+            .seal_in_place_separate_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed[NONCE_LEN..],
+            )
+            .map_err(|_| anyhow::anyhow!("failed to seal gossip message"))?;
+        sealed.extend_from_slice(tag.as_ref());
+        Ok(sealed)
+    }
+
+    /// Open a sealed gossip datagram in place. `None` if it was not sealed
+    /// with this cluster's key.
+    pub fn open<'a>(&self, datagram: &'a mut [u8]) -> Option<&'a [u8]> {
+        let Some(keys) = &self.keys else {
+            return Some(datagram);
+        };
+        if datagram.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = datagram.split_at_mut(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let plaintext = keys
+            .gossip
+            .open_in_place(nonce, Aad::empty(), ciphertext)
+            .ok()?;
+        Some(plaintext)
+    }
+
+    /// Whether internal RPC metadata carries this cluster's token.
+    pub fn allows(&self, metadata: &MetadataMap) -> bool {
+        let Some(keys) = &self.keys else {
+            return true;
+        };
+        metadata
+            .get(CLUSTER_TOKEN_HEADER)
+            .is_some_and(|given| constant_time_eq(given.as_bytes(), keys.rpc_token.as_bytes()))
+    }
+
+    /// Server-side check for the internal service; rejections are counted.
+    pub fn verify(&self, request: Request<()>) -> Result<Request<()>, Status> {
+        if self.allows(request.metadata()) {
+            Ok(request)
+        } else {
+            valka_core::metrics::record_cluster_auth_rejected("internal_rpc");
+            Err(Status::unauthenticated("Cluster token required"))
+        }
+    }
+}
+
+/// Attaches the token to outgoing internal RPCs.
+impl Interceptor for ClusterAuth {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(keys) = &self.keys {
+            request
+                .metadata_mut()
+                .insert(CLUSTER_TOKEN_HEADER, keys.rpc_token.clone());
+        }
+        Ok(request)
+    }
+}
+
+impl std::fmt::Debug for ClusterAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterAuth")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -3,7 +3,22 @@ use valka_core::NodeId;
 /// Events emitted by the cluster manager
 #[derive(Debug, Clone)]
 pub enum ClusterEvent {
-    NodeJoined { node_id: NodeId, grpc_addr: String },
-    NodeLeft { node_id: NodeId },
+    NodeJoined {
+        node_id: NodeId,
+        grpc_addr: String,
+    },
+    NodeLeft {
+        node_id: NodeId,
+    },
     PartitionsRebalanced,
+    LeadershipChanged {
+        node_id: NodeId,
+        is_leader: bool,
+    },
+    /// A node set a cluster key (`ClusterManager::set_key`), this one included
+    KeyChanged {
+        node_id: NodeId,
+        key: String,
+        value: String,
+    },
 }
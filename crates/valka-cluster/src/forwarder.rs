@@ -1,22 +1,42 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::hash_map::Entry;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use tokio::sync::RwLock;
+use serde::Serialize;
+use tokio::sync::{RwLock, oneshot};
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Channel;
-use tracing::{debug, warn};
+use tracing::{Instrument, debug, info_span, warn};
+
+use valka_core::{fault_point, trace_context};
+
+use crate::auth::ClusterAuth;
 
 use valka_proto::internal_service_client::InternalServiceClient;
-use valka_proto::{ForwardEventRequest, ForwardTaskRequest, LogEntry, RelayLogsRequest, TaskEvent};
+use valka_proto::{
+    DrainWorkerRequest, DrainWorkerResponse, ForwardCancelRequest, ForwardEventRequest,
+    ForwardSignalRequest, ForwardTaskRequest, ForwardTasksRequest, ListLocalWorkersRequest,
+    LogEntry, RelayLogsRequest, TaskEvent, TaskSignal, WorkerInfo,
+};
 
 const FAILURE_THRESHOLD: u32 = 3;
-const RECOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long an open circuit waits before letting a probe through, unless
+/// overridden with [`NodeForwarder::with_recovery_timeout`].
+pub const RECOVERY_TIMEOUT: Duration = Duration::from_secs(10);
 const FORWARD_RETRY_DELAY: Duration = Duration::from_millis(200);
+/// How long a batched forward waits for more tasks to the same node.
+const BATCH_LINGER: Duration = Duration::from_millis(10);
+/// Tasks that make a batch go out without waiting out the linger.
+const BATCH_MAX_TASKS: usize = 100;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum CircuitState {
     Closed,
     Open,
+    /// The cool-down has passed and one probe forward is in flight. Its
+    /// outcome closes the circuit or opens it for another cool-down.
     HalfOpen,
 }
 
@@ -24,6 +44,8 @@ pub struct NodeCircuit {
     pub state: CircuitState,
     pub failure_count: u32,
     pub last_failure: Option<Instant>,
+    /// When the current half-open probe was let through
+    pub probe_started: Option<Instant>,
 }
 
 impl Default for NodeCircuit {
@@ -32,15 +54,50 @@ impl Default for NodeCircuit {
             state: CircuitState::Closed,
             failure_count: 0,
             last_failure: None,
+            probe_started: None,
         }
     }
 }
 
+/// Publish how many circuits are not closed.
+fn report_open_circuits(circuits: &HashMap<String, NodeCircuit>) {
+    let open = circuits
+        .values()
+        .filter(|c| c.state != CircuitState::Closed)
+        .count();
+    valka_core::metrics::set_forward_open_circuits(open);
+}
+
+/// Cached channel and circuit breaker counts.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ForwarderStats {
+    pub cached_channels: usize,
+    /// Open and half-open circuits
+    pub open_circuits: usize,
+    /// `ForwardTask` and `ForwardTasks` RPCs sent so far
+    pub forward_rpcs: u64,
+}
+
+/// Tasks waiting to be forwarded to one node together.
+struct PendingBatch {
+    id: u64,
+    tasks: Vec<ForwardTaskRequest>,
+    waiters: Vec<oneshot::Sender<Result<bool, String>>>,
+}
+
+type InternalClient = InternalServiceClient<InterceptedService<Channel, ClusterAuth>>;
+
 /// gRPC client for inter-node RPCs, with connection caching and circuit breaker.
 #[derive(Clone)]
 pub struct NodeForwarder {
-    channels: Arc<RwLock<HashMap<String, InternalServiceClient<Channel>>>>,
+    channels: Arc<RwLock<HashMap<String, InternalClient>>>,
     circuits: Arc<RwLock<HashMap<String, NodeCircuit>>>,
+    auth: ClusterAuth,
+    recovery_timeout: Duration,
+    /// Batched forwards not sent yet, by node address
+    pending: Arc<Mutex<HashMap<String, PendingBatch>>>,
+    next_batch_id: Arc<AtomicU64>,
+    forward_rpcs: Arc<AtomicU64>,
 }
 
 impl NodeForwarder {
@@ -48,10 +105,27 @@ impl NodeForwarder {
         Self {
             channels: Arc::new(RwLock::new(HashMap::new())),
             circuits: Arc::new(RwLock::new(HashMap::new())),
+            auth: ClusterAuth::default(),
+            recovery_timeout: RECOVERY_TIMEOUT,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_batch_id: Arc::new(AtomicU64::new(0)),
+            forward_rpcs: Arc::new(AtomicU64::new(0)),
         }
     }
 
-    async fn get_client(&self, addr: &str) -> anyhow::Result<InternalServiceClient<Channel>> {
+    /// Send the cluster token with every internal RPC.
+    pub fn with_auth(mut self, auth: ClusterAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// How long an open circuit stays open before a probe forward is tried.
+    pub fn with_recovery_timeout(mut self, timeout: Duration) -> Self {
+        self.recovery_timeout = timeout;
+        self
+    }
+
+    async fn get_client(&self, addr: &str) -> anyhow::Result<InternalClient> {
         // Check cache first
         {
             let cache = self.channels.read().await;
@@ -68,7 +142,7 @@ impl NodeForwarder {
             .connect()
             .await?;
 
-        let client = InternalServiceClient::new(channel);
+        let client = InternalServiceClient::with_interceptor(channel, self.auth.clone());
 
         // Cache it
         {
@@ -80,20 +154,34 @@ impl NodeForwarder {
     }
 
     /// Check if a call to the given addr is allowed by the circuit breaker.
-    /// Returns true if allowed (Closed or HalfOpen), false if Open.
+    /// Closed lets every call through. Once an open circuit's cool-down has
+    /// passed it goes half-open and lets one probe through; other calls are
+    /// refused until the probe's outcome is recorded.
     async fn check_circuit(&self, addr: &str) -> bool {
         let mut circuits = self.circuits.write().await;
         let circuit = circuits.entry(addr.to_string()).or_default();
 
-        match circuit.state {
-            CircuitState::Closed => true,
-            CircuitState::HalfOpen => true,
+        let allowed = match circuit.state {
+            CircuitState::Closed => return true,
+            CircuitState::HalfOpen => {
+                // A probe whose caller went away never reports back
+                if circuit
+                    .probe_started
+                    .is_some_and(|started| started.elapsed() >= self.recovery_timeout)
+                {
+                    circuit.probe_started = Some(Instant::now());
+                    true
+                } else {
+                    false
+                }
+            }
             CircuitState::Open => {
                 // Check if recovery timeout has elapsed
                 if let Some(last_failure) = circuit.last_failure {
-                    if last_failure.elapsed() >= RECOVERY_TIMEOUT {
+                    if last_failure.elapsed() >= self.recovery_timeout {
                         circuit.state = CircuitState::HalfOpen;
-                        debug!(addr = addr, "Circuit breaker transitioning to half-open");
+                        circuit.probe_started = Some(Instant::now());
+                        debug!(addr = addr, "Circuit breaker half-open, probing node");
                         true
                     } else {
                         false
@@ -104,7 +192,9 @@ impl NodeForwarder {
                     true
                 }
             }
-        }
+        };
+        report_open_circuits(&circuits);
+        allowed
     }
 
     /// Record a successful call, resetting the circuit to Closed.
@@ -117,17 +207,24 @@ impl NodeForwarder {
             circuit.state = CircuitState::Closed;
             circuit.failure_count = 0;
             circuit.last_failure = None;
+            circuit.probe_started = None;
         }
+        report_open_circuits(&circuits);
     }
 
-    /// Record a failed call. Opens the circuit if failure threshold is reached.
+    /// Record a failed call. Opens the circuit if failure threshold is reached;
+    /// a failed probe reopens it for another cool-down.
     async fn record_failure(&self, addr: &str) {
         let mut circuits = self.circuits.write().await;
         let circuit = circuits.entry(addr.to_string()).or_default();
         circuit.failure_count += 1;
         circuit.last_failure = Some(Instant::now());
+        circuit.probe_started = None;
 
-        if circuit.failure_count >= FAILURE_THRESHOLD {
+        if circuit.state == CircuitState::HalfOpen {
+            debug!(addr = addr, "Circuit breaker probe failed, reopening");
+            circuit.state = CircuitState::Open;
+        } else if circuit.failure_count >= FAILURE_THRESHOLD {
             if circuit.state != CircuitState::Open {
                 warn!(
                     addr = addr,
@@ -138,6 +235,7 @@ impl NodeForwarder {
             }
             circuit.state = CircuitState::Open;
         }
+        report_open_circuits(&circuits);
     }
 
     /// Forward a task to the owning node for sync matching.
@@ -154,6 +252,8 @@ impl NodeForwarder {
             return Err(anyhow::anyhow!("Circuit breaker open for node {addr}"));
         }
 
+        let started = Instant::now();
+
         // First attempt
         let first_err = match self
             .do_forward_task(addr, task_id, queue_name, partition_id)
@@ -161,6 +261,10 @@ impl NodeForwarder {
         {
             Ok(accepted) => {
                 self.record_success(addr).await;
+                valka_core::metrics::record_forward_latency(
+                    queue_name,
+                    started.elapsed().as_secs_f64() * 1000.0,
+                );
                 return Ok(accepted);
             }
             Err(e) => {
@@ -179,6 +283,10 @@ impl NodeForwarder {
             {
                 Ok(accepted) => {
                     self.record_success(addr).await;
+                    valka_core::metrics::record_forward_latency(
+                        queue_name,
+                        started.elapsed().as_secs_f64() * 1000.0,
+                    );
                     return Ok(accepted);
                 }
                 Err(retry_err) => {
@@ -197,6 +305,7 @@ impl NodeForwarder {
     }
 
     /// Internal: perform the actual gRPC forward_task call.
+    /// Each attempt is its own span; its traceparent travels in the request metadata.
     async fn do_forward_task(
         &self,
         addr: &str,
@@ -204,21 +313,143 @@ impl NodeForwarder {
         queue_name: &str,
         partition_id: i32,
     ) -> anyhow::Result<bool> {
-        let mut client = self.get_client(addr).await?;
-        let resp = client
-            .forward_task(ForwardTaskRequest {
+        let span = info_span!("forward_task", task_id, queue = queue_name, addr);
+        async {
+            fault_point!("forwarder.forward_task", queue_name)?;
+            let mut client = self.get_client(addr).await?;
+            self.forward_rpcs.fetch_add(1, Ordering::Relaxed);
+            let mut request = tonic::Request::new(ForwardTaskRequest {
                 task_id: task_id.to_string(),
                 queue_name: queue_name.to_string(),
                 partition_id,
-            })
-            .await?;
-        debug!(
-            task_id = task_id,
-            addr = addr,
-            accepted = resp.get_ref().accepted,
-            "Task forwarded"
-        );
-        Ok(resp.get_ref().accepted)
+            });
+            trace_context::inject_metadata(request.metadata_mut());
+            let resp = client.forward_task(request).await?;
+            debug!(
+                task_id = task_id,
+                addr = addr,
+                accepted = resp.get_ref().accepted,
+                "Task forwarded"
+            );
+            Ok(resp.get_ref().accepted)
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Forward several tasks to their owning node in one call, with circuit
+    /// breaker protection but no retry. Whether each task was taken by a
+    /// waiting worker, in order.
+    pub async fn forward_tasks(
+        &self,
+        addr: &str,
+        tasks: Vec<ForwardTaskRequest>,
+    ) -> anyhow::Result<Vec<bool>> {
+        if tasks.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !self.check_circuit(addr).await {
+            return Err(anyhow::anyhow!("Circuit breaker open for node {addr}"));
+        }
+
+        let count = tasks.len();
+        let span = info_span!("forward_tasks", count, addr);
+        let result: anyhow::Result<Vec<bool>> = async {
+            let mut client = self.get_client(addr).await?;
+            self.forward_rpcs.fetch_add(1, Ordering::Relaxed);
+            let mut request = tonic::Request::new(ForwardTasksRequest { tasks });
+            trace_context::inject_metadata(request.metadata_mut());
+            let mut accepted = client.forward_tasks(request).await?.into_inner().accepted;
+            // A short answer leaves the rest to the owner's TaskReaders
+            accepted.resize(count, false);
+            debug!(addr = addr, count, "Tasks forwarded");
+            Ok(accepted)
+        }
+        .instrument(span)
+        .await;
+
+        match &result {
+            Ok(_) => self.record_success(addr).await,
+            Err(_) => self.record_failure(addr).await,
+        }
+        result
+    }
+
+    /// Forward a task as part of a batch: tasks for the same node within
+    /// 10ms, up to 100 of them, go out in one `ForwardTasks` call. Resolves
+    /// once the batch is answered, with whether this task was taken.
+    pub async fn forward_task_batched(
+        &self,
+        addr: &str,
+        task_id: &str,
+        queue_name: &str,
+        partition_id: i32,
+    ) -> anyhow::Result<bool> {
+        let (tx, rx) = oneshot::channel();
+        let full = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            let batch = match pending.entry(addr.to_string()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let id = self.next_batch_id.fetch_add(1, Ordering::Relaxed);
+                    let forwarder = self.clone();
+                    let addr = addr.to_string();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(BATCH_LINGER).await;
+                        forwarder.flush_batch(&addr, Some(id)).await;
+                    });
+                    entry.insert(PendingBatch {
+                        id,
+                        tasks: Vec::new(),
+                        waiters: Vec::new(),
+                    })
+                }
+            };
+            batch.tasks.push(ForwardTaskRequest {
+                task_id: task_id.to_string(),
+                queue_name: queue_name.to_string(),
+                partition_id,
+            });
+            batch.waiters.push(tx);
+            batch.tasks.len() >= BATCH_MAX_TASKS
+        };
+        if full {
+            self.flush_batch(addr, None).await;
+        }
+
+        match rx.await {
+            Ok(result) => result.map_err(anyhow::Error::msg),
+            Err(_) => Err(anyhow::anyhow!("Forward batch to {addr} was dropped")),
+        }
+    }
+
+    /// Send the node's pending batch. With an `id`, only if that batch is
+    /// still the pending one, i.e. it didn't already go out for being full.
+    async fn flush_batch(&self, addr: &str, id: Option<u64>) {
+        let batch = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            match pending.get(addr) {
+                Some(batch) if id.is_none_or(|id| batch.id == id) => pending.remove(addr),
+                _ => None,
+            }
+        };
+        let Some(batch) = batch else {
+            return;
+        };
+
+        match self.forward_tasks(addr, batch.tasks).await {
+            Ok(accepted) => {
+                for (waiter, accepted) in batch.waiters.into_iter().zip(accepted) {
+                    let _ = waiter.send(Ok(accepted));
+                }
+            }
+            Err(e) => {
+                let message = e.to_string();
+                for waiter in batch.waiters {
+                    let _ = waiter.send(Err(message.clone()));
+                }
+            }
+        }
     }
 
     /// Forward a task event to a peer node (best-effort, no retry).
@@ -230,6 +461,30 @@ impl NodeForwarder {
         Ok(())
     }
 
+    /// Ask a peer node to tell the worker running `task_id` that the task was
+    /// cancelled. Ok(false) if no worker there holds it (best-effort, no retry).
+    pub async fn forward_cancel(&self, addr: &str, task_id: &str) -> anyhow::Result<bool> {
+        let mut client = self.get_client(addr).await?;
+        let resp = client
+            .forward_cancel(ForwardCancelRequest {
+                task_id: task_id.to_string(),
+            })
+            .await?;
+        Ok(resp.into_inner().notified)
+    }
+
+    /// Ask a peer node to deliver a signal to the worker running its task.
+    /// Ok(false) if no worker there holds it (best-effort, no retry).
+    pub async fn forward_signal(&self, addr: &str, signal: TaskSignal) -> anyhow::Result<bool> {
+        let mut client = self.get_client(addr).await?;
+        let resp = client
+            .forward_signal(ForwardSignalRequest {
+                signal: Some(signal),
+            })
+            .await?;
+        Ok(resp.into_inner().delivered)
+    }
+
     /// Relay logs from a peer node for the given task_run_id (best-effort, no retry).
     pub async fn relay_logs(
         &self,
@@ -245,6 +500,30 @@ impl NodeForwarder {
         Ok(resp.into_inner())
     }
 
+    /// Ask a peer node for the workers connected to it (best-effort, no retry).
+    pub async fn list_workers(&self, addr: &str) -> anyhow::Result<Vec<WorkerInfo>> {
+        let mut client = self.get_client(addr).await?;
+        let resp = client
+            .list_local_workers(ListLocalWorkersRequest {})
+            .await?;
+        Ok(resp.into_inner().workers)
+    }
+
+    /// Ask a peer node to drain a worker. Ok(None) if the worker isn't
+    /// connected there (best-effort, no retry).
+    pub async fn drain_worker(
+        &self,
+        addr: &str,
+        request: DrainWorkerRequest,
+    ) -> anyhow::Result<Option<DrainWorkerResponse>> {
+        let mut client = self.get_client(addr).await?;
+        match client.drain_local_worker(request).await {
+            Ok(resp) => Ok(Some(resp.into_inner())),
+            Err(status) if status.code() == tonic::Code::NotFound => Ok(None),
+            Err(status) => Err(status.into()),
+        }
+    }
+
     /// Evict a cached channel and circuit state for a node (e.g., on NodeLeft).
     pub async fn remove_node(&self, addr: &str) {
         let mut cache = self.channels.write().await;
@@ -253,6 +532,23 @@ impl NodeForwarder {
 
         let mut circuits = self.circuits.write().await;
         circuits.remove(addr);
+        report_open_circuits(&circuits);
+    }
+
+    pub async fn stats(&self) -> ForwarderStats {
+        let cached_channels = self.channels.read().await.len();
+        let open_circuits = self
+            .circuits
+            .read()
+            .await
+            .values()
+            .filter(|c| c.state != CircuitState::Closed)
+            .count();
+        ForwarderStats {
+            cached_channels,
+            open_circuits,
+            forward_rpcs: self.forward_rpcs.load(Ordering::Relaxed),
+        }
     }
 
     /// Get the current circuit state for a node address (for testing/monitoring).
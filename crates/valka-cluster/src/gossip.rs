@@ -3,13 +3,71 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use chitchat::transport::UdpTransport;
-use chitchat::{ChitchatConfig, ChitchatHandle, ChitchatId, spawn_chitchat};
+use chitchat::{ChitchatConfig, ChitchatHandle, ChitchatId, FailureDetectorConfig, spawn_chitchat};
+use chrono::{DateTime, Utc};
 use tokio::sync::{RwLock, broadcast};
 use tracing::{info, warn};
-use valka_core::{GossipConfig, NodeId};
+use valka_core::{GossipConfig, NodeId, PartitionId};
 
+use crate::auth::ClusterAuth;
 use crate::events::ClusterEvent;
+use crate::partition::PartitionAssignment;
 use crate::ring::HashRing;
+use crate::transport::SealedUdpTransport;
+
+/// Gossiped node state keys under this prefix are the cluster KV; the rest
+/// (e.g. `grpc_addr`) belong to the cluster manager itself.
+const KV_PREFIX: &str = "kv:";
+
+/// Set in a node's gossiped state on its way out. Peers drop it from their
+/// rings right away instead of waiting for the failure detector.
+const LEAVING_KEY: &str = "leaving";
+
+/// Gossip rounds `leave` waits out so the marker gets out before the node
+/// goes quiet.
+const LEAVE_ROUNDS: u32 = 3;
+
+/// Which partitions this node owns, tracking the ring as membership changes.
+#[derive(Clone)]
+pub struct PartitionOwnership {
+    node_id: NodeId,
+    ring: Arc<RwLock<HashRing>>,
+    event_tx: broadcast::Sender<ClusterEvent>,
+}
+
+impl PartitionOwnership {
+    pub async fn owns_partition(&self, queue_name: &str, partition_id: i32) -> bool {
+        owns(&self.ring, &self.node_id, queue_name, partition_id).await
+    }
+
+    /// Cluster events; `PartitionsRebalanced` means ownership may have changed.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<ClusterEvent> {
+        self.event_tx.subscribe()
+    }
+}
+
+async fn owns(
+    ring: &RwLock<HashRing>,
+    node_id: &NodeId,
+    queue_name: &str,
+    partition_id: i32,
+) -> bool {
+    let ring = ring.read().await;
+    let key = format!("{queue_name}:{partition_id}");
+    ring.get_node(&key).map(|n| n == node_id.0).unwrap_or(true) // Single-node: always own
+}
+
+/// A cluster member as seen from this node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberDetails {
+    pub node_id: String,
+    /// Where peers forward its tasks; unknown until gossiped
+    pub grpc_addr: Option<String>,
+    /// Whether gossip currently considers the node alive
+    pub live: bool,
+    /// When this node saw it join (for this node itself, when it started)
+    pub joined_at: Option<DateTime<Utc>>,
+}
 
 /// Manages cluster membership via chitchat gossip protocol.
 /// In single-node mode, this owns all partitions and has no gossip.
@@ -18,9 +76,44 @@ pub struct ClusterManager {
     ring: Arc<RwLock<HashRing>>,
     members: Arc<RwLock<HashSet<String>>>,
     grpc_addrs: Arc<RwLock<HashMap<String, String>>>,
+    /// When each node was first seen joining; kept after it leaves
+    joined_at: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
     event_tx: broadcast::Sender<ClusterEvent>,
     num_partitions: i32,
+    auth: ClusterAuth,
     chitchat_handle: Option<ChitchatHandle>,
+    /// Time between gossip rounds; zero in single-node mode
+    gossip_interval: Duration,
+    /// Fewest members this node may see and still report ready
+    min_ready_members: usize,
+    /// When this node became scheduler leader; `None` while it isn't
+    leader_since: Arc<RwLock<Option<DateTime<Utc>>>>,
+    /// This node's cluster keys when there is no gossip to hold them
+    local_keys: Arc<RwLock<HashMap<String, String>>>,
+}
+
+/// Changes to one cluster key, from [`ClusterManager::watch_key`].
+pub struct KeyWatch {
+    key: String,
+    events: broadcast::Receiver<ClusterEvent>,
+}
+
+impl KeyWatch {
+    /// The next node to set the key and the value it set. `None` once the
+    /// cluster manager is gone. Changes missed while lagging are skipped.
+    pub async fn changed(&mut self) -> Option<(NodeId, String)> {
+        loop {
+            match self.events.recv().await {
+                Ok(ClusterEvent::KeyChanged {
+                    node_id,
+                    key,
+                    value,
+                }) if key == self.key => return Some((node_id, value)),
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
 }
 
 impl ClusterManager {
@@ -30,6 +123,7 @@ impl ClusterManager {
 
         let mut members = HashSet::new();
         members.insert(node_id.0.clone());
+        let joined_at = HashMap::from([(node_id.0.clone(), Utc::now())]);
 
         let (event_tx, _) = broadcast::channel(256);
 
@@ -38,9 +132,15 @@ impl ClusterManager {
             ring: Arc::new(RwLock::new(ring)),
             members: Arc::new(RwLock::new(members)),
             grpc_addrs: Arc::new(RwLock::new(HashMap::new())),
+            joined_at: Arc::new(RwLock::new(joined_at)),
             event_tx,
             num_partitions,
+            auth: ClusterAuth::default(),
             chitchat_handle: None,
+            gossip_interval: Duration::ZERO,
+            min_ready_members: 0,
+            leader_since: Arc::new(RwLock::new(None)),
+            local_keys: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -64,27 +164,33 @@ impl ClusterManager {
         let generation_id = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
 
         let chitchat_id = ChitchatId::new(node_id.0.clone(), generation_id, advertise_addr);
+        let gossip_interval = Duration::from_millis(gossip_config.gossip_interval_ms.max(1));
 
         let config = ChitchatConfig {
             chitchat_id,
             cluster_id: gossip_config.cluster_id.clone(),
-            gossip_interval: Duration::from_millis(500),
+            gossip_interval,
             listen_addr,
             seed_nodes: gossip_config.seed_nodes.clone(),
-            failure_detector_config: Default::default(),
+            failure_detector_config: FailureDetectorConfig {
+                phi_threshold: gossip_config.phi_threshold,
+                ..FailureDetectorConfig::default()
+            },
             marked_for_deletion_grace_period: Duration::from_secs(3600),
             catchup_callback: None,
-            extra_liveness_predicate: None,
+            // A leaving node drops out of the live set as soon as its marker arrives
+            extra_liveness_predicate: Some(Box::new(|state| state.get(LEAVING_KEY).is_none())),
         };
 
-        let handle = spawn_chitchat(
-            config,
-            vec![("grpc_addr".to_string(), grpc_addr.to_string())],
-            &UdpTransport,
-        )
-        .await?;
+        let auth = ClusterAuth::new(gossip_config.secret.as_deref(), &gossip_config.cluster_id);
+        let initial_kvs = vec![("grpc_addr".to_string(), grpc_addr.to_string())];
+        let handle = if auth.is_enabled() {
+            spawn_chitchat(config, initial_kvs, &SealedUdpTransport::new(auth.clone())).await?
+        } else {
+            spawn_chitchat(config, initial_kvs, &UdpTransport).await?
+        };
 
-        let mut ring = HashRing::new();
+        let mut ring = HashRing::new_with_vnodes(gossip_config.virtual_nodes);
         ring.add_node(&node_id.0);
 
         let mut members = HashSet::new();
@@ -92,6 +198,7 @@ impl ClusterManager {
 
         let mut grpc_addrs = HashMap::new();
         grpc_addrs.insert(node_id.0.clone(), grpc_addr.to_string());
+        let joined_at = HashMap::from([(node_id.0.clone(), Utc::now())]);
 
         let (event_tx, _) = broadcast::channel(256);
 
@@ -100,17 +207,25 @@ impl ClusterManager {
             ring: Arc::new(RwLock::new(ring)),
             members: Arc::new(RwLock::new(members)),
             grpc_addrs: Arc::new(RwLock::new(grpc_addrs)),
+            joined_at: Arc::new(RwLock::new(joined_at)),
             event_tx,
             num_partitions,
+            auth,
             chitchat_handle: Some(handle),
+            gossip_interval,
+            min_ready_members: gossip_config.min_ready_members,
+            leader_since: Arc::new(RwLock::new(None)),
+            local_keys: Arc::new(RwLock::new(HashMap::new())),
         };
 
         // Spawn background membership watcher
         manager.spawn_membership_watcher();
+        manager.subscribe_key_changes().await;
 
         info!(
             node_id = %manager.node_id,
             listen_addr = %listen_addr,
+            sealed = manager.auth.is_enabled(),
             "Cluster gossip started"
         );
 
@@ -123,6 +238,7 @@ impl ClusterManager {
         let ring = self.ring.clone();
         let members = self.members.clone();
         let grpc_addrs = self.grpc_addrs.clone();
+        let joined_at = self.joined_at.clone();
         let event_tx = self.event_tx.clone();
         let self_node_id = self.node_id.clone();
 
@@ -171,6 +287,13 @@ impl ClusterManager {
                 }
 
                 // Update ring
+                {
+                    let now = Utc::now();
+                    let mut joined_at = joined_at.write().await;
+                    for nid in &joined {
+                        joined_at.insert(nid.clone(), now);
+                    }
+                }
                 {
                     let mut ring = ring.write().await;
                     for nid in &joined {
@@ -216,6 +339,28 @@ impl ClusterManager {
         });
     }
 
+    /// Announce every cluster key set by any node, this one included, as
+    /// `ClusterEvent::KeyChanged`.
+    async fn subscribe_key_changes(&self) {
+        let handle = self.chitchat_handle.as_ref().expect("chitchat handle");
+        let event_tx = self.event_tx.clone();
+        handle
+            .with_chitchat(|chitchat| {
+                let event_tx = event_tx.clone();
+                chitchat
+                    .subscribe_event(KV_PREFIX, move |event| {
+                        let _ = event_tx.send(ClusterEvent::KeyChanged {
+                            node_id: NodeId(event.node.node_id.clone()),
+                            key: event.key.to_string(),
+                            value: event.value.to_string(),
+                        });
+                    })
+                    // Lives as long as chitchat
+                    .forever();
+            })
+            .await;
+    }
+
     pub fn node_id(&self) -> &NodeId {
         &self.node_id
     }
@@ -226,11 +371,17 @@ impl ClusterManager {
 
     /// Check if this node owns the given partition
     pub async fn owns_partition(&self, queue_name: &str, partition_id: i32) -> bool {
-        let ring = self.ring.read().await;
-        let key = format!("{queue_name}:{partition_id}");
-        ring.get_node(&key)
-            .map(|n| n == self.node_id.0)
-            .unwrap_or(true) // Single-node: always own
+        owns(&self.ring, &self.node_id, queue_name, partition_id).await
+    }
+
+    /// A handle on this node's partition ownership that doesn't keep the
+    /// manager itself alive.
+    pub fn partition_ownership(&self) -> PartitionOwnership {
+        PartitionOwnership {
+            node_id: self.node_id.clone(),
+            ring: self.ring.clone(),
+            event_tx: self.event_tx.clone(),
+        }
     }
 
     /// Returns None if we own the partition, else the owner's gRPC addr
@@ -260,15 +411,205 @@ impl ClusterManager {
     }
 
     /// Get all members
+    /// `gossip.min_ready_members`; always 0 in single-node mode.
+    pub fn min_ready_members(&self) -> usize {
+        self.min_ready_members
+    }
+
     pub async fn members(&self) -> HashSet<String> {
         self.members.read().await.clone()
     }
 
+    /// Live members followed by the nodes gossip has marked dead, each
+    /// ordered by node id.
+    pub async fn member_details(&self) -> Vec<MemberDetails> {
+        let members = self.members.read().await.clone();
+        let grpc_addrs = self.grpc_addrs.read().await.clone();
+        let joined_at = self.joined_at.read().await.clone();
+        let mut details: Vec<MemberDetails> = members
+            .iter()
+            .map(|node_id| MemberDetails {
+                node_id: node_id.clone(),
+                grpc_addr: grpc_addrs.get(node_id).cloned(),
+                live: true,
+                joined_at: joined_at.get(node_id).copied(),
+            })
+            .collect();
+
+        if let Some(handle) = &self.chitchat_handle {
+            let chitchat = handle.chitchat();
+            let guard = chitchat.lock().await;
+            for dead in guard.dead_nodes() {
+                // A restarted node is dead under its old generation
+                if details.iter().any(|d| d.node_id == dead.node_id) {
+                    continue;
+                }
+                details.push(MemberDetails {
+                    node_id: dead.node_id.clone(),
+                    grpc_addr: guard
+                        .node_state(dead)
+                        .and_then(|state| state.get("grpc_addr"))
+                        .map(str::to_string),
+                    live: false,
+                    joined_at: joined_at.get(&dead.node_id).copied(),
+                });
+            }
+        }
+
+        details.sort_by(|a, b| b.live.cmp(&a.live).then_with(|| a.node_id.cmp(&b.node_id)));
+        details
+    }
+
+    /// The node owning each of a queue's first `num_partitions` partitions,
+    /// by the current ring.
+    pub async fn partition_map(
+        &self,
+        queue_name: &str,
+        num_partitions: i32,
+    ) -> Vec<PartitionAssignment> {
+        let ring = self.ring.read().await;
+        (0..num_partitions)
+            .map(|partition_id| PartitionAssignment {
+                partition_id: PartitionId(partition_id),
+                queue_name: queue_name.to_string(),
+                owner_node_id: ring
+                    .get_node(&format!("{queue_name}:{partition_id}"))
+                    .unwrap_or_else(|| self.node_id.0.clone()),
+            })
+            .collect()
+    }
+
+    /// The first `n` distinct nodes on the ring for a partition: its owner,
+    /// then the standbys that would take it over in turn.
+    pub async fn partition_replicas(
+        &self,
+        queue_name: &str,
+        partition_id: i32,
+        n: usize,
+    ) -> Vec<String> {
+        let replicas = self
+            .ring
+            .read()
+            .await
+            .get_nodes(&format!("{queue_name}:{partition_id}"), n);
+        if replicas.is_empty() && n > 0 {
+            // Single-node: always own
+            return vec![self.node_id.0.clone()];
+        }
+        replicas
+    }
+
+    /// Set one of this node's cluster keys. Gossip carries it to the other
+    /// nodes, which read it with [`get_key`](Self::get_key) or follow it with
+    /// [`watch_key`](Self::watch_key) without a database round trip.
+    pub async fn set_key(&self, key: &str, value: &str) {
+        match &self.chitchat_handle {
+            Some(handle) => {
+                handle
+                    .with_chitchat(|chitchat| {
+                        chitchat
+                            .self_node_state()
+                            .set(format!("{KV_PREFIX}{key}"), value)
+                    })
+                    .await;
+            }
+            None => {
+                self.local_keys
+                    .write()
+                    .await
+                    .insert(key.to_string(), value.to_string());
+                let _ = self.event_tx.send(ClusterEvent::KeyChanged {
+                    node_id: self.node_id.clone(),
+                    key: key.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+    }
+
+    /// The value `node_id` last set for `key`, as far as gossip has brought
+    /// it here. `None` if it never set it or isn't known to be alive.
+    pub async fn get_key(&self, node_id: &str, key: &str) -> Option<String> {
+        let Some(handle) = &self.chitchat_handle else {
+            if node_id != self.node_id.0 {
+                return None;
+            }
+            return self.local_keys.read().await.get(key).cloned();
+        };
+        let chitchat = handle.chitchat();
+        let mut guard = chitchat.lock().await;
+        let key = format!("{KV_PREFIX}{key}");
+        if node_id == self.node_id.0 {
+            return guard.self_node_state().get(&key).map(str::to_string);
+        }
+        let live: Vec<ChitchatId> = guard
+            .live_nodes()
+            .filter(|id| id.node_id == node_id)
+            .cloned()
+            .collect();
+        live.iter()
+            .find_map(|id| guard.node_state(id)?.get(&key).map(str::to_string))
+    }
+
+    /// Follow changes to `key` on every node.
+    pub fn watch_key(&self, key: &str) -> KeyWatch {
+        KeyWatch {
+            key: key.to_string(),
+            events: self.event_tx.subscribe(),
+        }
+    }
+
+    /// Since when this node holds scheduler leadership, if it does.
+    pub async fn leader_since(&self) -> Option<DateTime<Utc>> {
+        *self.leader_since.read().await
+    }
+
+    /// Record whether this node holds scheduler leadership. A change is logged
+    /// and announced as `ClusterEvent::LeadershipChanged`.
+    pub async fn set_scheduler_leader(&self, is_leader: bool) {
+        let mut since = self.leader_since.write().await;
+        if since.is_some() == is_leader {
+            return;
+        }
+        *since = is_leader.then(Utc::now);
+        drop(since);
+
+        if is_leader {
+            info!(node_id = %self.node_id, "Gained scheduler leadership");
+        } else {
+            info!(node_id = %self.node_id, "Lost scheduler leadership");
+        }
+        let _ = self.event_tx.send(ClusterEvent::LeadershipChanged {
+            node_id: self.node_id.clone(),
+            is_leader,
+        });
+    }
+
+    /// Token and gossip keys derived from the cluster secret.
+    pub fn auth(&self) -> &ClusterAuth {
+        &self.auth
+    }
+
     /// Whether this manager is in clustered mode
     pub fn is_clustered(&self) -> bool {
         self.chitchat_handle.is_some()
     }
 
+    /// Announce that this node is leaving, so peers remove it from their rings
+    /// and rebalance now rather than once the failure detector gives up on it.
+    /// Returns after gossiping the marker for a few rounds; a no-op in
+    /// single-node mode.
+    pub async fn leave(&self) {
+        let Some(handle) = &self.chitchat_handle else {
+            return;
+        };
+        handle
+            .with_chitchat(|chitchat| chitchat.self_node_state().set(LEAVING_KEY, "true"))
+            .await;
+        info!(node_id = %self.node_id, "Leaving cluster");
+        tokio::time::sleep(self.gossip_interval * LEAVE_ROUNDS).await;
+    }
+
     /// Shutdown the gossip layer
     pub async fn shutdown(self) {
         if let Some(handle) = self.chitchat_handle {
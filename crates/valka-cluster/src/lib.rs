@@ -1,10 +1,14 @@
+pub mod auth;
 pub mod event_relay;
 pub mod events;
 pub mod forwarder;
 pub mod gossip;
 pub mod partition;
 pub mod ring;
+pub mod transport;
 
+pub use auth::ClusterAuth;
 pub use events::ClusterEvent;
 pub use forwarder::NodeForwarder;
-pub use gossip::ClusterManager;
+pub use gossip::{ClusterManager, KeyWatch, MemberDetails, PartitionOwnership};
+pub use partition::PartitionAssignment;
@@ -1,36 +1,71 @@
 use hashring::HashRing as HRing;
 
+/// Virtual nodes per member unless configured otherwise.
+pub const DEFAULT_VIRTUAL_NODES: usize = 64;
+
 /// Consistent hash ring for partition-to-node mapping
 pub struct HashRing {
     inner: HRing<String>,
+    vnodes: usize,
 }
 
 impl HashRing {
     pub fn new() -> Self {
+        Self::new_with_vnodes(DEFAULT_VIRTUAL_NODES)
+    }
+
+    /// A ring placing each node at `vnodes` points; at least one.
+    pub fn new_with_vnodes(vnodes: usize) -> Self {
         Self {
             inner: HRing::new(),
+            vnodes: vnodes.max(1),
         }
     }
 
     pub fn add_node(&mut self, node_id: &str) {
         // Add virtual nodes for better distribution
-        for i in 0..64 {
+        for i in 0..self.vnodes {
             self.inner.add(format!("{node_id}#vn{i}"));
         }
     }
 
     pub fn remove_node(&mut self, node_id: &str) {
-        for i in 0..64 {
+        for i in 0..self.vnodes {
             self.inner.remove(&format!("{node_id}#vn{i}"));
         }
     }
 
     pub fn get_node(&self, key: &str) -> Option<String> {
-        self.inner.get(&key.to_string()).map(|s| {
-            // Strip virtual node suffix
-            s.split('#').next().unwrap_or(s).to_string()
-        })
+        self.inner.get(&key.to_string()).map(|s| strip_vnode(s))
     }
+
+    /// Up to `n` distinct nodes for `key`: its owner first, then the nodes
+    /// met walking the ring clockwise from it.
+    pub fn get_nodes(&self, key: &str, n: usize) -> Vec<String> {
+        let mut nodes: Vec<String> = Vec::new();
+        if n == 0 {
+            return nodes;
+        }
+        let points = self
+            .inner
+            .get_with_replicas(&key.to_string(), self.inner.len())
+            .unwrap_or_default();
+        for point in &points {
+            let node = strip_vnode(point);
+            if !nodes.contains(&node) {
+                nodes.push(node);
+                if nodes.len() == n {
+                    break;
+                }
+            }
+        }
+        nodes
+    }
+}
+
+/// The node a virtual node point belongs to.
+fn strip_vnode(point: &str) -> String {
+    point.split('#').next().unwrap_or(point).to_string()
 }
 
 impl Default for HashRing {
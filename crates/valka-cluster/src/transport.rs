@@ -0,0 +1,67 @@
+use std::net::SocketAddr;
+
+use chitchat::transport::{Socket, Transport};
+use chitchat::{ChitchatMessage, Deserializable, Serializable};
+use tokio::net::UdpSocket;
+use tracing::{debug, warn};
+
+use crate::auth::ClusterAuth;
+
+/// Largest UDP payload over IPv4. Gossip state here is a few keys per node,
+/// so sealed messages stay far below it.
+const MAX_DATAGRAM_LEN: usize = 65_507;
+
+/// UDP gossip transport that seals every message with the cluster key and
+/// drops datagrams that fail to open, so nodes with another secret never
+/// see each other.
+pub struct SealedUdpTransport {
+    auth: ClusterAuth,
+}
+
+impl SealedUdpTransport {
+    pub fn new(auth: ClusterAuth) -> Self {
+        Self { auth }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for SealedUdpTransport {
+    async fn open(&self, listen_addr: SocketAddr) -> anyhow::Result<Box<dyn Socket>> {
+        let socket = UdpSocket::bind(listen_addr).await?;
+        Ok(Box::new(SealedUdpSocket {
+            socket,
+            auth: self.auth.clone(),
+            buf: vec![0; MAX_DATAGRAM_LEN],
+        }))
+    }
+}
+
+struct SealedUdpSocket {
+    socket: UdpSocket,
+    auth: ClusterAuth,
+    buf: Vec<u8>,
+}
+
+#[async_trait::async_trait]
+impl Socket for SealedUdpSocket {
+    async fn send(&mut self, to: SocketAddr, message: ChitchatMessage) -> anyhow::Result<()> {
+        let datagram = self.auth.seal(&message.serialize_to_vec())?;
+        self.socket.send_to(&datagram, to).await?;
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> anyhow::Result<(SocketAddr, ChitchatMessage)> {
+        loop {
+            let (len, from) = self.socket.recv_from(&mut self.buf).await?;
+            let Some(mut payload) = self.auth.open(&mut self.buf[..len]) else {
+                valka_core::metrics::record_cluster_auth_rejected("gossip");
+                debug!(%from, "Dropped gossip message that failed authentication");
+                continue;
+            };
+            match ChitchatMessage::deserialize(&mut payload) {
+                Ok(message) => return Ok((from, message)),
+                Err(e) => warn!(%from, error = %e, "Dropped malformed gossip message"),
+            }
+        }
+    }
+}
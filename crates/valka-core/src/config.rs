@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use figment::{
     Figment,
     providers::{Env, Format, Serialized, Toml},
@@ -17,11 +19,57 @@ pub struct ServerConfig {
     /// Skip migrations on startup. Follower nodes set this when the leader handles migrations.
     pub skip_migrations: bool,
     pub web_dir: String,
+    /// Verify partition routing against this fixture at startup and refuse to start on drift.
+    pub partition_fixture: Option<String>,
+    /// Bearer token for operator actions such as task reclaim. Unset leaves them open.
+    pub admin_token: Option<String>,
+    /// Time allowed on shutdown for connected workers to finish their tasks
+    /// before streams and listeners are closed.
+    pub shutdown_grace_secs: u64,
+    /// Server log output format; `--log-format` on the command line overrides it.
+    pub log_format: LogFormat,
+    /// Also write logs to this file, rotated daily (`<log_file>.YYYY-MM-DD`).
+    pub log_file: Option<String>,
     pub database: DatabaseConfig,
     pub gossip: GossipConfig,
     pub matching: MatchingConfig,
     pub scheduler: SchedulerConfig,
     pub log_ingester: LogIngesterConfig,
+    pub result_writer: ResultWriterConfig,
+    pub dispatcher: DispatcherConfig,
+    pub events: EventsConfig,
+    pub telemetry: TelemetryConfig,
+    pub metrics: MetricsConfig,
+    pub quotas: QuotaConfig,
+    pub webhooks: WebhooksConfig,
+    pub auth: AuthConfig,
+    pub retention: RetentionConfig,
+    pub limits: LimitsConfig,
+    pub blob_storage: BlobStorageConfig,
+}
+
+/// `pretty` is the human-readable fmt output; `json` writes one object per
+/// line with the fields of enclosing spans flattened into it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "unknown log format {other:?}, expected \"json\" or \"pretty\""
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,12 +78,47 @@ pub struct DatabaseConfig {
     pub acquire_timeout_secs: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GossipConfig {
     pub listen_addr: String,
     pub seed_nodes: Vec<String>,
     pub cluster_id: String,
     pub advertise_addr: Option<String>,
+    /// Shared cluster secret. When set, gossip is encrypted and authenticated
+    /// and internal RPCs must carry a token derived from it.
+    pub secret: Option<String>,
+    /// How long a peer's forwarding circuit stays open before a probe
+    /// forward is let through.
+    pub circuit_recovery_secs: u64,
+    /// How often this node gossips with a peer. Heartbeats travel with each
+    /// round, so it also sets how fast a silent node is noticed.
+    pub gossip_interval_ms: u64,
+    /// Phi accrual threshold above which a silent peer is declared dead.
+    /// Lower detects failures sooner but risks flagging slow peers.
+    pub phi_threshold: f64,
+    /// Points each node gets on the hash ring. More spread partitions more
+    /// evenly. Must be identical across all cluster nodes.
+    pub virtual_nodes: usize,
+    /// Report not ready while gossip sees fewer members than this, this node
+    /// included. 0 disables the check.
+    pub min_ready_members: usize,
+}
+
+impl std::fmt::Debug for GossipConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GossipConfig")
+            .field("listen_addr", &self.listen_addr)
+            .field("seed_nodes", &self.seed_nodes)
+            .field("cluster_id", &self.cluster_id)
+            .field("advertise_addr", &self.advertise_addr)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("circuit_recovery_secs", &self.circuit_recovery_secs)
+            .field("gossip_interval_ms", &self.gossip_interval_ms)
+            .field("phi_threshold", &self.phi_threshold)
+            .field("virtual_nodes", &self.virtual_nodes)
+            .field("min_ready_members", &self.min_ready_members)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,8 +127,22 @@ pub struct MatchingConfig {
     pub branching_factor: usize,
     pub max_buffer_per_partition: usize,
     pub task_reader_batch_size: i64,
+    /// Largest batch a TaskReader grows to while its polls keep coming back full.
+    pub task_reader_max_batch_size: i64,
     pub task_reader_poll_busy_ms: u64,
     pub task_reader_poll_idle_ms: u64,
+    /// How often per-queue worker rules (e.g. min_worker_version) are reloaded from PG. 0 disables.
+    pub queue_policy_refresh_ms: u64,
+    /// How often the TaskReader manager re-reads the queue registry in case a
+    /// new-queue notification was missed.
+    pub queue_discovery_fallback_ms: u64,
+    /// Matching/dispatch decisions kept for `/api/v1/debug/dispatch-decisions`. 0 disables.
+    pub decision_log_size: usize,
+    /// Record one in this many tasks' decisions (0 = only queues forced to full sampling).
+    pub decision_sample_rate: u64,
+    /// How long a task may sit in a partition buffer before it is spilled back
+    /// to PENDING for a TaskReader to pick up again. 0 keeps it until matched.
+    pub buffered_task_ttl_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,14 +151,229 @@ pub struct SchedulerConfig {
     pub lease_timeout_secs: i64,
     pub retry_base_delay_secs: u64,
     pub retry_max_delay_secs: u64,
+    /// How often RETRY tasks without a next attempt time get one.
+    pub retry_check_interval_secs: u64,
     pub dlq_check_interval_secs: u64,
     pub delayed_check_interval_secs: u64,
+    /// How often the usage rollup folds new activity into usage_daily. 0 disables.
+    pub usage_rollup_interval_secs: u64,
+    /// Activity newer than this is left for the next rollup so in-flight writes can commit.
+    pub usage_rollup_lag_secs: u64,
+    /// How often recurring task schedules are checked for due firings.
+    pub schedule_check_interval_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogIngesterConfig {
     pub batch_size: usize,
     pub flush_interval_ms: u64,
+    /// Flushed entries a live log stream may fall behind by before it
+    /// re-reads from PG instead.
+    pub stream_buffer_size: usize,
+}
+
+/// Batching of task result writes in the dispatcher.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultWriterConfig {
+    /// Max results written in one transaction.
+    pub batch_size: usize,
+    /// How long the first result of a batch waits for others to join it (ms).
+    pub flush_interval_ms: u64,
+}
+
+/// Worker sessions and liveness as the dispatcher manages them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DispatcherConfig {
+    /// How often workers heartbeat; sent to them in the HelloAck (seconds).
+    /// A worker silent for longer is logged as suspect.
+    pub heartbeat_interval_secs: u64,
+    /// A worker silent this long is declared dead and deregistered (seconds).
+    pub dead_after_secs: u64,
+    /// Turn away a hello whose worker_id already has a session here, instead
+    /// of closing the old session and carrying its tasks over.
+    pub reject_duplicate_workers: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventsConfig {
+    /// A status repeating the task's last emitted one within this window is coalesced. 0 disables.
+    pub dedup_window_ms: u64,
+    /// Max tasks tracked for coalescing (LRU).
+    pub dedup_capacity: usize,
+    /// Keep-alive comment sent on idle `/api/v1/events` streams so proxies
+    /// don't drop them. 0 disables.
+    pub sse_keep_alive_secs: u64,
+    /// Recent events replayed to SSE clients reconnecting with `Last-Event-ID`. 0 disables.
+    pub replay_buffer_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// How often internal channel/map sizes are sampled into `valka_internal_*` gauges. 0 disables.
+    pub interval_ms: u64,
+    /// OTLP/gRPC collector endpoint for trace export (e.g. `http://tempo:4317`). Unset disables export.
+    pub otlp_endpoint: Option<String>,
+    /// Fraction of new traces that are sampled; traces started upstream keep their decision.
+    pub sampling_ratio: f64,
+    pub service_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// Histogram bucket bounds in ms. An empty list renders the family as a summary.
+    pub dispatch_latency_buckets: Vec<f64>,
+    pub task_duration_buckets: Vec<f64>,
+    pub queue_wait_buckets: Vec<f64>,
+    pub forward_latency_buckets: Vec<f64>,
+    pub log_flush_latency_buckets: Vec<f64>,
+    /// Attach task_id exemplars to the dispatch, queue wait and execution histograms.
+    /// Only rendered when the scraper asks for OpenMetrics.
+    pub exemplars: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    /// How often cached quota counters are reset from PG counts. 0 loads them once
+    /// at startup, after which they only see this node's creations.
+    pub reconcile_interval_ms: u64,
+}
+
+/// Deletion of finished tasks and old logs by the scheduler leader. A TTL of 0 keeps rows forever.
+/// A queue's `retention_days`, when set, replaces the task TTLs for its tasks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Days a COMPLETED task is kept after its last update.
+    pub completed_task_ttl_days: u32,
+    /// Days a FAILED, DEAD_LETTER or CANCELLED task is kept after its last update.
+    pub failed_task_ttl_days: u32,
+    /// Days task and worker log lines are kept, whatever their task's state.
+    pub log_ttl_days: u32,
+    /// Max rows (tasks, or log lines) deleted per statement.
+    pub batch_size: usize,
+    /// How often the cleanup runs.
+    pub interval_secs: u64,
+}
+
+/// Bounds on what a task create may carry, enforced alike on REST and gRPC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// Longest `queue_name` or `task_name`, in bytes.
+    pub max_name_len: usize,
+    /// Largest `input`, and separately `metadata`, as serialized JSON.
+    pub max_payload_bytes: usize,
+    pub min_priority: i32,
+    pub max_priority: i32,
+    /// Highest `max_retries`; 0 still takes the queue's default.
+    pub max_retries: i32,
+    /// Longest `timeout_seconds`; 0 still takes the queue's default.
+    pub max_timeout_seconds: i32,
+    /// Largest task output recorded from a worker, as serialized JSON. A
+    /// larger one is dropped and its run marked `output_truncated`.
+    pub max_output_bytes: usize,
+    /// Longest error message recorded from a worker; longer ones are cut short.
+    pub max_error_message_bytes: usize,
+}
+
+/// Object store that task outputs and inputs too large for PG are offloaded
+/// to, leaving a `{"$blob": "<url>"}` reference in the row.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlobStorageConfig {
+    /// Where blobs go, e.g. `s3://bucket/valka` (needs the `s3` feature),
+    /// `file:///var/lib/valka/blobs` or `memory://`. Unset disables offload.
+    pub url: Option<String>,
+    /// Outputs and inputs over this many bytes of JSON are offloaded.
+    pub offload_threshold_bytes: usize,
+    /// Largest output or input the store takes.
+    pub max_blob_bytes: usize,
+    /// Store settings such as `aws_region`, `aws_endpoint` or
+    /// `aws_access_key_id`, over any `AWS_*` environment variables.
+    pub options: std::collections::BTreeMap<String, String>,
+}
+
+impl BlobStorageConfig {
+    /// Whether a store is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.url.as_deref().is_some_and(|url| !url.is_empty())
+    }
+}
+
+impl std::fmt::Debug for BlobStorageConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobStorageConfig")
+            .field("url", &self.url)
+            .field("offload_threshold_bytes", &self.offload_threshold_bytes)
+            .field("max_blob_bytes", &self.max_blob_bytes)
+            // Values may be credentials
+            .field("options", &self.options.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Delivery of task `callback_url` webhooks.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebhooksConfig {
+    /// Per-request timeout.
+    pub timeout_ms: u64,
+    /// Retries after a failed delivery, with exponential backoff from `retry_base_delay_ms`.
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    /// Deliveries waiting to be sent; callbacks beyond it are dropped with a warning.
+    pub queue_capacity: usize,
+    /// Deliveries in flight at once.
+    pub concurrency: usize,
+    /// When set, deliveries are signed with `valka-timestamp` and
+    /// `valka-signature` headers, as checked by `valka_sdk::webhooks`.
+    pub signing_secret: Option<String>,
+    /// Hosts (names or IP addresses) callbacks may reach even though they are
+    /// loopback, private or link-local. Every other such address is refused.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl std::fmt::Debug for WebhooksConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhooksConfig")
+            .field("timeout_ms", &self.timeout_ms)
+            .field("max_retries", &self.max_retries)
+            .field("retry_base_delay_ms", &self.retry_base_delay_ms)
+            .field("queue_capacity", &self.queue_capacity)
+            .field("concurrency", &self.concurrency)
+            .field(
+                "signing_secret",
+                &self.signing_secret.as_ref().map(|_| "<redacted>"),
+            )
+            .field("allowed_hosts", &self.allowed_hosts)
+            .finish()
+    }
+}
+
+/// API tokens. With none configured the API is open.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Bearer tokens for REST `/api/v1/*`, the gRPC ApiService and worker sessions.
+    pub tokens: Vec<String>,
+    /// More API tokens, one per line (blank lines and `#` comments skipped), read at startup.
+    pub token_file: Option<String>,
+    /// Tokens that only open worker sessions.
+    pub worker_tokens: Vec<String>,
+    /// Also require a token on `/healthz` and `/readyz`.
+    pub protect_health: bool,
+    /// Also require a token on `/metrics` and `/debug/internal`.
+    pub protect_metrics: bool,
+}
+
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("tokens", &format_args!("<{} redacted>", self.tokens.len()))
+            .field("token_file", &self.token_file)
+            .field(
+                "worker_tokens",
+                &format_args!("<{} redacted>", self.worker_tokens.len()),
+            )
+            .field("protect_health", &self.protect_health)
+            .field("protect_metrics", &self.protect_metrics)
+            .finish()
+    }
 }
 
 impl Default for ServerConfig {
@@ -75,11 +387,27 @@ impl Default for ServerConfig {
             migrate_only: false,
             skip_migrations: false,
             web_dir: "web/dist".to_string(),
+            partition_fixture: None,
+            admin_token: None,
+            shutdown_grace_secs: 30,
+            log_format: LogFormat::Pretty,
+            log_file: None,
             database: DatabaseConfig::default(),
             gossip: GossipConfig::default(),
             matching: MatchingConfig::default(),
             scheduler: SchedulerConfig::default(),
             log_ingester: LogIngesterConfig::default(),
+            result_writer: ResultWriterConfig::default(),
+            dispatcher: DispatcherConfig::default(),
+            events: EventsConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            metrics: MetricsConfig::default(),
+            quotas: QuotaConfig::default(),
+            webhooks: WebhooksConfig::default(),
+            auth: AuthConfig::default(),
+            retention: RetentionConfig::default(),
+            limits: LimitsConfig::default(),
+            blob_storage: BlobStorageConfig::default(),
         }
     }
 }
@@ -100,6 +428,12 @@ impl Default for GossipConfig {
             seed_nodes: vec![],
             cluster_id: "valka".to_string(),
             advertise_addr: None,
+            secret: None,
+            circuit_recovery_secs: 10,
+            gossip_interval_ms: 500,
+            phi_threshold: 8.0,
+            virtual_nodes: 64,
+            min_ready_members: 0,
         }
     }
 }
@@ -111,8 +445,14 @@ impl Default for MatchingConfig {
             branching_factor: 3,
             max_buffer_per_partition: 1000,
             task_reader_batch_size: 50,
+            task_reader_max_batch_size: 500,
             task_reader_poll_busy_ms: 10,
             task_reader_poll_idle_ms: 200,
+            queue_policy_refresh_ms: 5000,
+            queue_discovery_fallback_ms: 60000,
+            decision_log_size: 1000,
+            decision_sample_rate: 100,
+            buffered_task_ttl_ms: 30000,
         }
     }
 }
@@ -124,8 +464,12 @@ impl Default for SchedulerConfig {
             lease_timeout_secs: 60,
             retry_base_delay_secs: 1,
             retry_max_delay_secs: 3600,
+            retry_check_interval_secs: 10,
             dlq_check_interval_secs: 30,
             delayed_check_interval_secs: 5,
+            usage_rollup_interval_secs: 60,
+            usage_rollup_lag_secs: 30,
+            schedule_check_interval_secs: 1,
         }
     }
 }
@@ -135,6 +479,129 @@ impl Default for LogIngesterConfig {
         Self {
             batch_size: 100,
             flush_interval_ms: 500,
+            stream_buffer_size: 1024,
+        }
+    }
+}
+
+impl Default for ResultWriterConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 200,
+            flush_interval_ms: 5,
+        }
+    }
+}
+
+impl Default for DispatcherConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval_secs: 10,
+            dead_after_secs: 30,
+            reject_duplicate_workers: false,
+        }
+    }
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            dedup_window_ms: 1000,
+            dedup_capacity: 10_000,
+            sse_keep_alive_secs: 15,
+            replay_buffer_size: 1000,
+        }
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 5000,
+            otlp_endpoint: None,
+            sampling_ratio: 1.0,
+            service_name: "valka-server".to_string(),
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            dispatch_latency_buckets: vec![
+                1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0,
+            ],
+            task_duration_buckets: vec![
+                10.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0, 60000.0,
+                300000.0,
+            ],
+            queue_wait_buckets: vec![
+                10.0, 50.0, 100.0, 500.0, 1000.0, 5000.0, 10000.0, 30000.0, 60000.0, 300000.0,
+                900000.0, 3600000.0,
+            ],
+            forward_latency_buckets: vec![1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 1000.0],
+            log_flush_latency_buckets: vec![1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 1000.0],
+            exemplars: false,
+        }
+    }
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            reconcile_interval_ms: 30_000,
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            completed_task_ttl_days: 0,
+            failed_task_ttl_days: 0,
+            log_ttl_days: 0,
+            batch_size: 1000,
+            interval_secs: 3600,
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_name_len: 255,
+            max_payload_bytes: 256 * 1024,
+            min_priority: -1000,
+            max_priority: 1000,
+            max_retries: 100,
+            max_timeout_seconds: 7 * 24 * 3600,
+            max_output_bytes: 1024 * 1024,
+            max_error_message_bytes: 16 * 1024,
+        }
+    }
+}
+
+impl Default for BlobStorageConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            offload_threshold_bytes: 256 * 1024,
+            max_blob_bytes: 64 * 1024 * 1024,
+            options: Default::default(),
+        }
+    }
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 5000,
+            max_retries: 5,
+            retry_base_delay_ms: 1000,
+            queue_capacity: 10_000,
+            concurrency: 16,
+            signing_secret: None,
+            allowed_hosts: Vec::new(),
         }
     }
 }
@@ -151,4 +618,173 @@ impl ServerConfig {
 
         figment.extract()
     }
+
+    /// Check what `load` can't: addresses parse, partition counts and buffer
+    /// sizes are positive, task limits are consistent, and (when clustered)
+    /// gossip addresses are `host:port`. Returns every problem found, not
+    /// just the first.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let mut require = |ok: bool, message: String| {
+            if !ok {
+                errors.push(message);
+            }
+        };
+
+        for (field, addr) in [
+            ("grpc_addr", &self.grpc_addr),
+            ("http_addr", &self.http_addr),
+        ] {
+            require(
+                addr.parse::<SocketAddr>().is_ok(),
+                format!("{field} {addr:?} is not an ip:port address"),
+            );
+        }
+        require(
+            !self.database_url.trim().is_empty(),
+            "database_url is empty".to_string(),
+        );
+        require(
+            self.database.max_connections >= 1,
+            "database.max_connections must be at least 1".to_string(),
+        );
+
+        let matching = &self.matching;
+        require(
+            matching.num_partitions >= 1,
+            format!(
+                "matching.num_partitions must be at least 1, got {}",
+                matching.num_partitions
+            ),
+        );
+        require(
+            matching.branching_factor >= 1,
+            "matching.branching_factor must be at least 1".to_string(),
+        );
+        require(
+            matching.max_buffer_per_partition >= 1,
+            "matching.max_buffer_per_partition must be at least 1".to_string(),
+        );
+        require(
+            matching.task_reader_batch_size >= 1,
+            "matching.task_reader_batch_size must be at least 1".to_string(),
+        );
+        require(
+            matching.task_reader_max_batch_size >= matching.task_reader_batch_size,
+            "matching.task_reader_max_batch_size must not be below task_reader_batch_size"
+                .to_string(),
+        );
+        require(
+            self.log_ingester.batch_size >= 1,
+            "log_ingester.batch_size must be at least 1".to_string(),
+        );
+        require(
+            self.result_writer.batch_size >= 1,
+            "result_writer.batch_size must be at least 1".to_string(),
+        );
+        require(
+            self.dispatcher.heartbeat_interval_secs >= 1,
+            "dispatcher.heartbeat_interval_secs must be at least 1".to_string(),
+        );
+        require(
+            self.dispatcher.dead_after_secs >= 2 * self.dispatcher.heartbeat_interval_secs,
+            "dispatcher.dead_after_secs must be at least twice heartbeat_interval_secs".to_string(),
+        );
+        require(
+            self.retention.batch_size >= 1,
+            "retention.batch_size must be at least 1".to_string(),
+        );
+
+        let limits = &self.limits;
+        require(
+            limits.max_name_len >= 1,
+            "limits.max_name_len must be at least 1".to_string(),
+        );
+        require(
+            limits.max_payload_bytes >= 1,
+            "limits.max_payload_bytes must be at least 1".to_string(),
+        );
+        require(
+            limits.min_priority <= limits.max_priority,
+            "limits.min_priority must not be above max_priority".to_string(),
+        );
+        require(
+            limits.max_retries >= 0,
+            "limits.max_retries must not be negative".to_string(),
+        );
+        require(
+            limits.max_timeout_seconds >= 1,
+            "limits.max_timeout_seconds must be at least 1".to_string(),
+        );
+        require(
+            limits.max_output_bytes >= 1,
+            "limits.max_output_bytes must be at least 1".to_string(),
+        );
+        require(
+            limits.max_error_message_bytes >= 1,
+            "limits.max_error_message_bytes must be at least 1".to_string(),
+        );
+
+        // Offload must kick in before the inline limits reject the payload
+        let blobs = &self.blob_storage;
+        if blobs.is_enabled() {
+            let inline_limit = limits.max_payload_bytes.min(limits.max_output_bytes);
+            require(
+                blobs.offload_threshold_bytes <= inline_limit,
+                format!(
+                    "blob_storage.offload_threshold_bytes must not exceed {inline_limit}, \
+                     the lower of limits.max_payload_bytes and max_output_bytes"
+                ),
+            );
+            require(
+                blobs.max_blob_bytes > blobs.offload_threshold_bytes,
+                "blob_storage.max_blob_bytes must be above offload_threshold_bytes".to_string(),
+            );
+        }
+
+        // Gossip settings only matter once there are peers to talk to
+        let gossip = &self.gossip;
+        if !gossip.seed_nodes.is_empty() {
+            require(
+                gossip.listen_addr.parse::<SocketAddr>().is_ok(),
+                format!(
+                    "gossip.listen_addr {:?} is not an ip:port address",
+                    gossip.listen_addr
+                ),
+            );
+            for seed in &gossip.seed_nodes {
+                require(
+                    is_host_port(seed),
+                    format!("gossip.seed_nodes entry {seed:?} is not host:port"),
+                );
+            }
+            if let Some(advertise) = &gossip.advertise_addr {
+                require(
+                    is_host_port(advertise),
+                    format!("gossip.advertise_addr {advertise:?} is not host:port"),
+                );
+            }
+            require(
+                gossip.virtual_nodes >= 1,
+                "gossip.virtual_nodes must be at least 1".to_string(),
+            );
+            require(
+                gossip.phi_threshold > 0.0,
+                "gossip.phi_threshold must be positive".to_string(),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// `host:port` with a non-empty host (a name or an IP) and a valid port.
+fn is_host_port(value: &str) -> bool {
+    value
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
 }
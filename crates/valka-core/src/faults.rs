@@ -0,0 +1,181 @@
+//! Named fault injection points for tests.
+//!
+//! Code marks a point with [`fault_point!`](crate::fault_point), keyed by the
+//! queue it is working on:
+//!
+//! ```ignore
+//! fault_point!("dispatcher.record_dispatch.before_commit", &envelope.queue_name)?;
+//! ```
+//!
+//! Without the `fault-injection` feature the macro is `Ok(())` and costs
+//! nothing. With it, tests register a [`Fault`] for a point and queue (an
+//! error, a delay or a panic, optionally only N times) and read back how often
+//! the point was reached. Faults are matched by key so tests running in
+//! parallel on different queues don't see each other's faults.
+
+use thiserror::Error;
+
+/// Error returned from a point whose fault says to fail.
+#[derive(Debug, Clone, Error)]
+#[error("Injected fault at {point}")]
+pub struct InjectedFault {
+    pub point: &'static str,
+}
+
+impl From<InjectedFault> for sqlx::Error {
+    fn from(fault: InjectedFault) -> Self {
+        sqlx::Error::Protocol(fault.to_string())
+    }
+}
+
+/// Check a fault point. Evaluates to `Result<(), InjectedFault>`; must be
+/// used in async code.
+#[cfg(feature = "fault-injection")]
+#[macro_export]
+macro_rules! fault_point {
+    ($point:expr, $key:expr) => {
+        $crate::faults::check($point, $key).await
+    };
+}
+
+/// Check a fault point. Compiled out without the `fault-injection` feature.
+#[cfg(not(feature = "fault-injection"))]
+#[macro_export]
+macro_rules! fault_point {
+    ($point:expr, $key:expr) => {{
+        let _ = &$key;
+        ::core::result::Result::<(), $crate::faults::InjectedFault>::Ok(())
+    }};
+}
+
+#[cfg(feature = "fault-injection")]
+pub use registry::*;
+
+#[cfg(feature = "fault-injection")]
+mod registry {
+    use std::collections::HashMap;
+    use std::sync::{LazyLock, Mutex};
+    use std::time::Duration;
+
+    use super::InjectedFault;
+
+    static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Default::default);
+
+    #[derive(Default)]
+    struct Registry {
+        faults: HashMap<(&'static str, String), Fault>,
+        hits: HashMap<(&'static str, String), u64>,
+    }
+
+    /// What happens when a point is reached.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum FaultAction {
+        Error,
+        Delay(Duration),
+        Panic,
+    }
+
+    /// A fault registered with [`inject`].
+    #[derive(Debug, Clone)]
+    pub struct Fault {
+        action: FaultAction,
+        /// Hits left before the fault disarms; None for every hit
+        remaining: Option<u32>,
+    }
+
+    impl Fault {
+        pub fn error() -> Self {
+            Self::new(FaultAction::Error)
+        }
+
+        pub fn delay(delay: Duration) -> Self {
+            Self::new(FaultAction::Delay(delay))
+        }
+
+        pub fn panic() -> Self {
+            Self::new(FaultAction::Panic)
+        }
+
+        fn new(action: FaultAction) -> Self {
+            Self {
+                action,
+                remaining: None,
+            }
+        }
+
+        /// Fire on the next hit only.
+        pub fn once(self) -> Self {
+            self.times(1)
+        }
+
+        /// Fire on the next `n` hits, then let the point pass.
+        pub fn times(mut self, n: u32) -> Self {
+            self.remaining = Some(n);
+            self
+        }
+    }
+
+    /// Arm `fault` at `point` for `key`, replacing any fault already there.
+    /// Removed again when the guard drops.
+    #[must_use = "the fault is removed when the guard is dropped"]
+    pub fn inject(point: &'static str, key: &str, fault: Fault) -> FaultGuard {
+        let key = key.to_string();
+        lock().faults.insert((point, key.clone()), fault);
+        FaultGuard { point, key }
+    }
+
+    /// Times `point` was reached for `key`, faulted or not.
+    pub fn hits(point: &'static str, key: &str) -> u64 {
+        lock()
+            .hits
+            .get(&(point, key.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Disarms its fault on drop.
+    pub struct FaultGuard {
+        point: &'static str,
+        key: String,
+    }
+
+    impl Drop for FaultGuard {
+        fn drop(&mut self) {
+            lock()
+                .faults
+                .remove(&(self.point, std::mem::take(&mut self.key)));
+        }
+    }
+
+    /// Record a hit and apply the armed fault, if any. Use [`fault_point!`](crate::fault_point).
+    pub async fn check(point: &'static str, key: &str) -> Result<(), InjectedFault> {
+        let action = {
+            let mut registry = lock();
+            let id = (point, key.to_string());
+            *registry.hits.entry(id.clone()).or_default() += 1;
+            match registry.faults.get_mut(&id) {
+                Some(fault) if fault.remaining != Some(0) => {
+                    if let Some(n) = &mut fault.remaining {
+                        *n -= 1;
+                    }
+                    Some(fault.action)
+                }
+                _ => None,
+            }
+        };
+
+        match action {
+            None => Ok(()),
+            Some(FaultAction::Delay(delay)) => {
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            Some(FaultAction::Error) => Err(InjectedFault { point }),
+            Some(FaultAction::Panic) => panic!("Injected panic at {point} ({key})"),
+        }
+    }
+
+    fn lock() -> std::sync::MutexGuard<'static, Registry> {
+        REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,31 @@
+//! Worker labels and the requirements tasks place on them, for routing tasks
+//! to workers with particular capabilities (`gpu=true`, `region=eu`).
+
+use std::collections::BTreeMap;
+
+/// Key/value capabilities a worker declares, or that a task requires of the
+/// worker that runs it.
+pub type Labels = BTreeMap<String, String>;
+
+/// Most labels a task may require.
+pub const MAX_REQUIRED_LABELS: usize = 16;
+
+/// True if `labels` has every required key with the required value. Nothing
+/// required is satisfied by any worker.
+pub fn satisfies(labels: &Labels, required: &Labels) -> bool {
+    required
+        .iter()
+        .all(|(key, value)| labels.get(key) == Some(value))
+}
+
+pub fn validate_required(required: &Labels) -> Result<(), String> {
+    if required.len() > MAX_REQUIRED_LABELS {
+        return Err(format!(
+            "required_labels may have at most {MAX_REQUIRED_LABELS} entries"
+        ));
+    }
+    if required.keys().any(|key| key.is_empty()) {
+        return Err("required_labels keys must not be empty".to_string());
+    }
+    Ok(())
+}
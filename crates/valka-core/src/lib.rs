@@ -1,6 +1,12 @@
 pub mod config;
 pub mod error;
+pub mod faults;
+pub mod labels;
+pub mod limits;
 pub mod metrics;
+pub mod partitioning;
+pub mod retry;
+pub mod trace_context;
 pub mod types;
 
 pub use config::*;
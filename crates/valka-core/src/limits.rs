@@ -0,0 +1,108 @@
+//! Checks on task creates against `[limits]`, shared by the REST and gRPC
+//! create paths so both reject the same requests with the same messages,
+//! and the cut applied to what workers report back.
+
+use crate::config::LimitsConfig;
+
+/// The parts of a create request that `[limits]` bound.
+#[derive(Debug, Clone, Copy)]
+pub struct NewTask<'a> {
+    pub queue_name: &'a str,
+    pub task_name: &'a str,
+    pub input: Option<&'a serde_json::Value>,
+    pub metadata: Option<&'a serde_json::Value>,
+    pub priority: i32,
+    pub max_retries: i32,
+    pub timeout_seconds: i32,
+}
+
+/// Characters allowed in queue and task names besides ASCII letters and digits.
+const NAME_PUNCTUATION: &[char] = &['.', '_', '-', ':'];
+
+impl LimitsConfig {
+    /// Check `task` against the limits, with one message per offending field,
+    /// joined by `; `.
+    pub fn check_task(&self, task: &NewTask<'_>) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for (field, name) in [
+            ("queue_name", task.queue_name),
+            ("task_name", task.task_name),
+        ] {
+            if let Err(e) = self.check_name(field, name) {
+                errors.push(e);
+            }
+        }
+        for (field, value) in [("input", task.input), ("metadata", task.metadata)] {
+            // Measured as compact JSON, however the client sent it
+            let bytes = value.map_or(0, |v| v.to_string().len());
+            if bytes > self.max_payload_bytes {
+                errors.push(format!(
+                    "{field} is {bytes} bytes, over the {} byte limit",
+                    self.max_payload_bytes
+                ));
+            }
+        }
+        for (field, value, min, max) in [
+            (
+                "priority",
+                task.priority,
+                self.min_priority,
+                self.max_priority,
+            ),
+            ("max_retries", task.max_retries, 0, self.max_retries),
+            (
+                "timeout_seconds",
+                task.timeout_seconds,
+                0,
+                self.max_timeout_seconds,
+            ),
+        ] {
+            if !(min..=max).contains(&value) {
+                errors.push(format!(
+                    "{field} must be between {min} and {max}, got {value}"
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    fn check_name(&self, field: &str, name: &str) -> Result<(), String> {
+        if name.is_empty() {
+            return Err(format!("{field} is required"));
+        }
+        if name.len() > self.max_name_len {
+            return Err(format!(
+                "{field} is longer than {} characters",
+                self.max_name_len
+            ));
+        }
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || NAME_PUNCTUATION.contains(&c))
+        {
+            return Err(format!(
+                "{field} may only contain ASCII letters, digits, '.', '_', '-' and ':'"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Cut `s` to at most `max_bytes`, on a character boundary. Returns whether
+/// anything was cut.
+pub fn truncate_utf8(s: &mut String, max_bytes: usize) -> bool {
+    if s.len() <= max_bytes {
+        return false;
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s.truncate(end);
+    true
+}
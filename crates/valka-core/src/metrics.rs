@@ -1,5 +1,23 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+
 use metrics::{counter, gauge, histogram};
 
+use crate::MetricsConfig;
+
+pub const DISPATCH_LATENCY_MS: &str = "valka_dispatch_latency_ms";
+pub const TASK_DURATION_MS: &str = "valka_task_duration_ms";
+pub const QUEUE_WAIT_MS: &str = "valka_queue_wait_ms";
+pub const FORWARD_LATENCY_MS: &str = "valka_forward_latency_ms";
+pub const LOG_FLUSH_LATENCY_MS: &str = "valka_log_flush_latency_ms";
+pub const LOG_FLUSH_BATCH_SIZE: &str = "valka_log_flush_batch_size";
+
+/// Content type of the OpenMetrics exposition served by `/metrics` on request.
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
 pub fn record_task_created(queue: &str) {
     counter!("valka_tasks_created_total", "queue" => queue.to_string()).increment(1);
 }
@@ -20,20 +38,65 @@ pub fn record_task_dead_lettered(queue: &str) {
     counter!("valka_tasks_dead_lettered_total", "queue" => queue.to_string()).increment(1);
 }
 
-pub fn record_dispatch_latency(queue: &str, latency_ms: f64) {
-    histogram!("valka_dispatch_latency_ms", "queue" => queue.to_string()).record(latency_ms);
+pub fn record_lease_expired(queue: &str) {
+    counter!("valka_tasks_lease_expired_total", "queue" => queue.to_string()).increment(1);
+}
+
+pub fn record_event_suppressed(queue: &str) {
+    counter!("valka_events_suppressed_total", "queue" => queue.to_string()).increment(1);
 }
 
-pub fn record_task_duration(queue: &str, duration_ms: f64) {
-    histogram!("valka_task_duration_ms", "queue" => queue.to_string()).record(duration_ms);
+pub fn record_dispatch_latency(queue: &str, latency_ms: f64, task_id: &str) {
+    histogram!(DISPATCH_LATENCY_MS, "queue" => queue.to_string()).record(latency_ms);
+    record_exemplar(DISPATCH_LATENCY_MS, queue, latency_ms, task_id);
+}
+
+pub fn record_task_duration(queue: &str, duration_ms: f64, task_id: &str) {
+    histogram!(TASK_DURATION_MS, "queue" => queue.to_string()).record(duration_ms);
+    record_exemplar(TASK_DURATION_MS, queue, duration_ms, task_id);
+}
+
+/// Time from a task becoming dispatchable to its assignment to a worker.
+pub fn record_queue_wait(queue: &str, wait_ms: f64, task_id: &str) {
+    histogram!(QUEUE_WAIT_MS, "queue" => queue.to_string()).record(wait_ms);
+    record_exemplar(QUEUE_WAIT_MS, queue, wait_ms, task_id);
+}
+
+pub fn record_forward_latency(queue: &str, latency_ms: f64) {
+    histogram!(FORWARD_LATENCY_MS, "queue" => queue.to_string()).record(latency_ms);
+}
+
+/// One log ingester flush of `entries` rows; `kind` is "task" or "worker"
+/// depending on which log table was flushed.
+pub fn record_log_flush(kind: &'static str, entries: usize, latency_ms: f64) {
+    histogram!(LOG_FLUSH_LATENCY_MS, "kind" => kind).record(latency_ms);
+    histogram!(LOG_FLUSH_BATCH_SIZE, "kind" => kind).record(entries as f64);
 }
 
 pub fn set_active_workers(count: f64) {
     gauge!("valka_active_workers").set(count);
 }
 
-pub fn set_pending_tasks(queue: &str, count: f64) {
-    gauge!("valka_pending_tasks", "queue" => queue.to_string()).set(count);
+/// Tasks of a queue waiting to be dispatched, by `state`: "pending" (ready
+/// to run), "retry" (waiting for another attempt) or "scheduled" (held until
+/// a later time).
+pub fn set_pending_tasks(queue: &str, state: &'static str, count: f64) {
+    gauge!("valka_pending_tasks", "queue" => queue.to_string(), "state" => state).set(count);
+}
+
+/// How long the queue's longest-waiting ready task has waited; 0 when none
+/// is waiting.
+pub fn set_queue_oldest_pending_age(queue: &str, age_secs: f64) {
+    gauge!("valka_queue_oldest_pending_age_seconds", "queue" => queue.to_string()).set(age_secs);
+}
+
+/// In-memory matching state of one partition on this node.
+pub fn set_matching_partition(queue: &str, partition: i32, buffered: usize, waiting: usize) {
+    let partition = partition.to_string();
+    gauge!("valka_matching_buffered_tasks", "queue" => queue.to_string(), "partition" => partition.clone())
+        .set(buffered as f64);
+    gauge!("valka_matching_waiting_workers", "queue" => queue.to_string(), "partition" => partition)
+        .set(waiting as f64);
 }
 
 pub fn record_sync_match() {
@@ -48,6 +111,16 @@ pub fn set_cluster_members(count: f64) {
     gauge!("valka_cluster_members").set(count);
 }
 
+/// 1 while this node holds scheduler leadership, 0 otherwise.
+pub fn set_scheduler_leader(is_leader: bool) {
+    gauge!("valka_scheduler_is_leader").set(if is_leader { 1.0 } else { 0.0 });
+}
+
+/// 0 running, 1 quiescing, 2 draining, 3 stopped.
+pub fn set_shutdown_phase(phase: u8) {
+    gauge!("valka_shutdown_phase").set(phase as f64);
+}
+
 pub fn record_task_forwarded(queue: &str) {
     counter!("valka_tasks_forwarded_total", "queue" => queue.to_string()).increment(1);
 }
@@ -55,3 +128,273 @@ pub fn record_task_forwarded(queue: &str) {
 pub fn record_forward_circuit_open(addr: &str) {
     counter!("valka_forward_circuit_open_total", "addr" => addr.to_string()).increment(1);
 }
+
+/// Forwarder circuits currently open or half-open.
+pub fn set_forward_open_circuits(count: usize) {
+    gauge!("valka_forward_open_circuits").set(count as f64);
+}
+
+/// `channel` is `gossip` or `internal_rpc`.
+pub fn record_cluster_auth_rejected(channel: &'static str) {
+    counter!("valka_cluster_auth_rejected_total", "channel" => channel).increment(1);
+}
+
+/// `outcome` is `delivered`, `failed` (retries used up) or `dropped` (queue full).
+pub fn record_webhook_delivery(outcome: &'static str) {
+    counter!("valka_webhook_deliveries_total", "outcome" => outcome).increment(1);
+}
+
+/// `limit` is the quota field that rejected the creation.
+pub fn record_quota_rejected(key: &str, limit: &'static str) {
+    counter!("valka_quota_rejections_total", "key" => key.to_string(), "limit" => limit)
+        .increment(1);
+}
+
+/// `outcome` is "dispatched", "unmatched" (persisted as PENDING instead) or "write_failed".
+pub fn record_ephemeral_task(queue: &str, outcome: &'static str) {
+    counter!("valka_ephemeral_tasks_total", "queue" => queue.to_string(), "outcome" => outcome)
+        .increment(1);
+}
+
+/// Input templating at dispatch; `outcome` is "fast_path" (no placeholders),
+/// "rendered" or "unresolved".
+pub fn record_input_template(queue: &str, outcome: &'static str) {
+    counter!("valka_input_templates_total", "queue" => queue.to_string(), "outcome" => outcome)
+        .increment(1);
+}
+
+/// Tasks the dispatcher holds against a worker ("tracked") vs. those its last
+/// heartbeat reported ("reported"); a lasting gap means lost completions.
+pub fn set_worker_active_tasks(
+    worker_id: &str,
+    worker_name: &str,
+    tracked: usize,
+    reported: usize,
+) {
+    for (source, count) in [("tracked", tracked), ("reported", reported)] {
+        gauge!(
+            "valka_worker_active_tasks",
+            "worker_id" => worker_id.to_string(),
+            "worker_name" => worker_name.to_string(),
+            "source" => source
+        )
+        .set(count as f64);
+    }
+}
+
+pub fn record_worker_connected(worker_name: &str) {
+    counter!("valka_worker_connects_total", "worker_name" => worker_name.to_string()).increment(1);
+}
+
+/// `reason` is "closed" (the worker hung up), "error", "drained",
+/// "send_failed", "superseded" (a newer session of the same worker took
+/// over) or "rejected" (its worker_id already had a session).
+pub fn record_worker_disconnected(worker_name: &str, reason: &'static str) {
+    counter!(
+        "valka_worker_disconnects_total",
+        "worker_name" => worker_name.to_string(),
+        "reason" => reason
+    )
+    .increment(1);
+}
+
+/// A worker declared dead after `silent_secs` without a heartbeat.
+pub fn record_worker_declared_dead(worker_name: &str, silent_secs: f64) {
+    counter!(
+        "valka_workers_declared_dead_total",
+        "worker_name" => worker_name.to_string()
+    )
+    .increment(1);
+    histogram!("valka_worker_dead_silence_seconds").record(silent_secs);
+}
+
+pub fn record_task_assignment_sent(queue: &str) {
+    counter!("valka_task_assignments_sent_total", "queue" => queue.to_string()).increment(1);
+}
+
+/// A message could not be put on a worker's stream; `message` is
+/// "assignment" or "heartbeat_ack".
+pub fn record_worker_send_failure(message: &'static str) {
+    counter!("valka_worker_send_failures_total", "message" => message).increment(1);
+}
+
+/// Counts an open task event subscription in `valka_event_subscribers` until
+/// dropped; `transport` is "sse" or "grpc".
+#[must_use = "the subscriber is only counted while the guard is held"]
+pub fn track_event_subscriber(transport: &'static str) -> EventSubscriberGuard {
+    gauge!("valka_event_subscribers", "transport" => transport).increment(1.0);
+    EventSubscriberGuard { transport }
+}
+
+pub struct EventSubscriberGuard {
+    transport: &'static str,
+}
+
+impl Drop for EventSubscriberGuard {
+    fn drop(&mut self) {
+        gauge!("valka_event_subscribers", "transport" => self.transport).decrement(1.0);
+    }
+}
+
+/// `reason` is "heartbeat", "cancelled", "reclaimed" or "reaped".
+pub fn record_worker_slots_reclaimed(reason: &'static str, count: usize) {
+    counter!("valka_worker_slots_reclaimed_total", "reason" => reason).increment(count as u64);
+}
+
+/// `table` is "tasks", "task_runs", "task_logs", "worker_logs" or "dead_letter_queue".
+pub fn record_retention_deleted(table: &'static str, count: u64) {
+    counter!("valka_retention_deleted_rows_total", "table" => table).increment(count);
+}
+
+/// A result arrived for a run that was already closed (reaped or reclaimed).
+pub fn record_stale_result() {
+    counter!("valka_stale_results_total").increment(1);
+}
+
+/// Set a self-telemetry gauge; `name` is prefixed with `valka_internal_`.
+pub fn set_internal_gauge(name: &str, value: f64) {
+    gauge!(format!("valka_internal_{name}")).set(value);
+}
+
+// ─── Exemplars ──────────────────────────────────────────────────────
+//
+// metrics-exporter-prometheus has no exemplar support, so the most recent
+// observation per (histogram, queue, bucket) is kept here and spliced into
+// the OpenMetrics rendering.
+
+struct Exemplar {
+    task_id: String,
+    value: f64,
+    timestamp: f64,
+}
+
+#[derive(Default)]
+struct ExemplarStore {
+    /// Bucket bounds per histogram with exemplars enabled
+    buckets: HashMap<&'static str, Vec<f64>>,
+    /// (histogram, queue, bucket index) -> latest exemplar; index == bounds.len() is +Inf
+    latest: HashMap<(&'static str, String, usize), Exemplar>,
+}
+
+static EXEMPLARS_ENABLED: AtomicBool = AtomicBool::new(false);
+static EXEMPLARS: LazyLock<Mutex<ExemplarStore>> = LazyLock::new(Mutex::default);
+
+/// Histogram bucket overrides from config, as (metric name, bounds) pairs.
+pub fn histogram_buckets(config: &MetricsConfig) -> [(&'static str, &[f64]); 5] {
+    [
+        (DISPATCH_LATENCY_MS, &config.dispatch_latency_buckets),
+        (TASK_DURATION_MS, &config.task_duration_buckets),
+        (QUEUE_WAIT_MS, &config.queue_wait_buckets),
+        (FORWARD_LATENCY_MS, &config.forward_latency_buckets),
+        (LOG_FLUSH_LATENCY_MS, &config.log_flush_latency_buckets),
+    ]
+}
+
+/// Start (or stop) collecting exemplars for the dispatch, queue wait and
+/// execution histograms.
+pub fn configure_exemplars(config: &MetricsConfig) {
+    let mut store = EXEMPLARS.lock().unwrap_or_else(|e| e.into_inner());
+    store.latest.clear();
+    store.buckets = HashMap::from([
+        (
+            DISPATCH_LATENCY_MS,
+            sorted(&config.dispatch_latency_buckets),
+        ),
+        (TASK_DURATION_MS, sorted(&config.task_duration_buckets)),
+        (QUEUE_WAIT_MS, sorted(&config.queue_wait_buckets)),
+    ]);
+    store.buckets.retain(|_, bounds| !bounds.is_empty());
+    EXEMPLARS_ENABLED.store(config.exemplars, Ordering::Relaxed);
+}
+
+fn sorted(bounds: &[f64]) -> Vec<f64> {
+    let mut bounds = bounds.to_vec();
+    bounds.sort_by(f64::total_cmp);
+    bounds
+}
+
+fn record_exemplar(metric: &'static str, queue: &str, value: f64, task_id: &str) {
+    if !EXEMPLARS_ENABLED.load(Ordering::Relaxed) || task_id.is_empty() {
+        return;
+    }
+    let mut store = EXEMPLARS.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(bounds) = store.buckets.get(metric) else {
+        return;
+    };
+    let bucket = bounds.partition_point(|b| *b < value);
+    let timestamp = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+    store.latest.insert(
+        (metric, queue.to_string(), bucket),
+        Exemplar {
+            task_id: task_id.to_string(),
+            value,
+            timestamp,
+        },
+    );
+}
+
+/// Convert a Prometheus text rendering to OpenMetrics: counter families lose
+/// their `_total` suffix in metadata, blank lines are dropped, exemplars are
+/// attached to histogram buckets and the body ends with `# EOF`.
+pub fn to_openmetrics(text: &str) -> String {
+    let store = EXEMPLARS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut out = String::with_capacity(text.len() + 64);
+
+    for line in text.lines() {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(meta) = line
+            .strip_prefix("# TYPE ")
+            .or_else(|| line.strip_prefix("# HELP "))
+        {
+            let mut parts = meta.splitn(2, ' ');
+            let name = parts.next().unwrap_or_default();
+            let rest = parts.next().unwrap_or_default();
+            if let Some(family) = name.strip_suffix("_total") {
+                let _ = writeln!(out, "{} {family} {rest}", &line[..6]);
+                continue;
+            }
+        }
+
+        out.push_str(line);
+        if let Some(exemplar) = bucket_exemplar(&store, line) {
+            let _ = write!(
+                out,
+                " # {{task_id=\"{}\"}} {} {:.3}",
+                exemplar.task_id, exemplar.value, exemplar.timestamp
+            );
+        }
+        out.push('\n');
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn bucket_exemplar<'a>(store: &'a ExemplarStore, line: &str) -> Option<&'a Exemplar> {
+    if store.latest.is_empty() {
+        return None;
+    }
+    let (name, rest) = line.split_once("_bucket{")?;
+    let (metric, bounds) = store.buckets.get_key_value(name)?;
+    let labels = rest.split_once('}')?.0;
+
+    let mut queue = "";
+    let mut le = "";
+    for label in labels.split(',') {
+        if let Some(v) = label.strip_prefix("queue=\"") {
+            queue = v.trim_end_matches('"');
+        } else if let Some(v) = label.strip_prefix("le=\"") {
+            le = v.trim_end_matches('"');
+        }
+    }
+
+    let bucket = if le == "+Inf" {
+        bounds.len()
+    } else {
+        let le: f64 = le.parse().ok()?;
+        bounds.iter().position(|b| *b == le)?
+    };
+    store.latest.get(&(*metric, queue.to_string(), bucket))
+}
@@ -0,0 +1,140 @@
+//! Routing stability contract for [`partition_for_task`].
+//!
+//! Every PENDING task row stores the partition it was hashed to, so any change
+//! in `partition_for_task` output silently strands existing work on partitions
+//! nobody reads in the new layout. `fixtures/partition_routing.json` pins 1000
+//! `(queue, task_id, num_partitions) -> partition_id` triples; the contract test
+//! and the optional startup check (`partition_fixture` in ServerConfig) fail on
+//! any mismatch.
+//!
+//! If a change is intentional:
+//! 1. Regenerate the fixture:
+//!    `cargo test -p valka-tests regenerate_partition_fixture -- --ignored`
+//! 2. Ship a migration that recomputes `tasks.partition_id` for all
+//!    non-terminal tasks, and roll it out with every node on the new build.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::partition_for_task;
+
+/// Path of the checked-in routing fixture.
+pub const FIXTURE_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/fixtures/partition_routing.json"
+);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureEntry {
+    pub queue_name: String,
+    pub task_id: String,
+    pub num_partitions: i32,
+    pub partition_id: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub entry: FixtureEntry,
+    pub actual: i32,
+}
+
+#[derive(Debug, Error)]
+pub enum FixtureError {
+    #[error("Failed to read partition fixture: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid partition fixture: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("Partition fixture is empty")]
+    Empty,
+
+    #[error("partition_for_task drifted on {} of {total} fixture entries", mismatches.len())]
+    Drift {
+        mismatches: Vec<Mismatch>,
+        total: usize,
+    },
+}
+
+/// Recompute every entry with the current implementation.
+pub fn verify_entries(entries: &[FixtureEntry]) -> Result<usize, FixtureError> {
+    if entries.is_empty() {
+        return Err(FixtureError::Empty);
+    }
+
+    let mismatches: Vec<Mismatch> = entries
+        .iter()
+        .filter_map(|entry| {
+            let actual =
+                partition_for_task(&entry.queue_name, &entry.task_id, entry.num_partitions).0;
+            (actual != entry.partition_id).then(|| Mismatch {
+                entry: entry.clone(),
+                actual,
+            })
+        })
+        .collect();
+
+    if mismatches.is_empty() {
+        Ok(entries.len())
+    } else {
+        Err(FixtureError::Drift {
+            mismatches,
+            total: entries.len(),
+        })
+    }
+}
+
+/// Load a fixture file and verify it. Returns the number of entries checked.
+pub fn verify_fixture(path: impl AsRef<Path>) -> Result<usize, FixtureError> {
+    let entries = load_fixture(path)?;
+    verify_entries(&entries)
+}
+
+pub fn load_fixture(path: impl AsRef<Path>) -> Result<Vec<FixtureEntry>, FixtureError> {
+    let data = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Generate `count` entries from the current implementation, spread across
+/// several queue names and partition counts.
+#[cfg(feature = "fixture-gen")]
+pub fn generate_fixture(count: usize) -> Vec<FixtureEntry> {
+    const QUEUES: [&str; 8] = [
+        "default",
+        "emails",
+        "video.encode",
+        "billing-sync",
+        "q",
+        "",
+        "ünïcödé",
+        "a:b:c",
+    ];
+    const PARTITIONS: [i32; 6] = [1, 2, 4, 8, 16, 64];
+
+    (0..count)
+        .map(|i| {
+            let queue_name = QUEUES[i % QUEUES.len()].to_string();
+            let num_partitions = PARTITIONS[(i / QUEUES.len()) % PARTITIONS.len()];
+            let task_id = uuid::Uuid::now_v7().to_string();
+            let partition_id = partition_for_task(&queue_name, &task_id, num_partitions).0;
+            FixtureEntry {
+                queue_name,
+                task_id,
+                num_partitions,
+                partition_id,
+            }
+        })
+        .collect()
+}
+
+/// Write a freshly generated fixture to `path`.
+#[cfg(feature = "fixture-gen")]
+pub fn write_fixture(path: impl AsRef<Path>, count: usize) -> Result<(), FixtureError> {
+    let entries = generate_fixture(count);
+    let mut data = serde_json::to_vec_pretty(&entries)?;
+    data.push(b'\n');
+    std::fs::write(path, data)?;
+    Ok(())
+}
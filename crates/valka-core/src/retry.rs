@@ -0,0 +1,187 @@
+//! Retry backoff, shared by the scheduler that assigns retry times and the
+//! APIs that preview them, so the two can't disagree.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::SchedulerConfig;
+
+/// Multiplier of the scheduler's own backoff.
+pub const DEFAULT_RETRY_MULTIPLIER: f64 = 2.0;
+
+/// Longest `base_seconds` or `max_seconds` a task may ask for, the most the
+/// gRPC `RetryBackoff` fields can carry.
+pub const MAX_BACKOFF_SECONDS: u64 = u32::MAX as u64;
+
+/// Exponential backoff between attempts of a failing task:
+/// `base_delay_secs * multiplier^attempt_count`, capped at `max_delay_secs`.
+/// With `jitter`, each scheduled delay is drawn between half and all of that.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub base_delay_secs: u64,
+    pub max_delay_secs: u64,
+    pub multiplier: f64,
+    pub jitter: bool,
+}
+
+/// Backoff a task was created with. Unset fields use the scheduler's policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryBackoff {
+    pub base_seconds: Option<u64>,
+    pub max_seconds: Option<u64>,
+    pub multiplier: Option<f64>,
+    pub jitter: bool,
+}
+
+impl RetryBackoff {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.base_seconds == Some(0) {
+            return Err("retry_backoff.base_seconds must be positive".to_string());
+        }
+        if self.max_seconds == Some(0) {
+            return Err("retry_backoff.max_seconds must be positive".to_string());
+        }
+        if self.base_seconds.is_some_and(|s| s > MAX_BACKOFF_SECONDS) {
+            return Err(format!(
+                "retry_backoff.base_seconds must be at most {MAX_BACKOFF_SECONDS}"
+            ));
+        }
+        if self.max_seconds.is_some_and(|s| s > MAX_BACKOFF_SECONDS) {
+            return Err(format!(
+                "retry_backoff.max_seconds must be at most {MAX_BACKOFF_SECONDS}"
+            ));
+        }
+        if let (Some(base), Some(max)) = (self.base_seconds, self.max_seconds)
+            && max < base
+        {
+            return Err("retry_backoff.max_seconds must be at least base_seconds".to_string());
+        }
+        if self.multiplier.is_some_and(|m| !m.is_finite() || m < 1.0) {
+            return Err("retry_backoff.multiplier must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from_config(&SchedulerConfig::default())
+    }
+}
+
+/// Where a RETRY task is headed if every remaining attempt fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPlan {
+    /// `None` when the deadline leaves no room for another attempt.
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub attempts_remaining: i32,
+    /// Start of each remaining attempt that fits before the deadline,
+    /// assuming every attempt fails as soon as it starts.
+    pub schedule: Vec<DateTime<Utc>>,
+    /// Whether the task ends in the dead letter queue; false when its
+    /// deadline fails it first.
+    pub dead_letter: bool,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &SchedulerConfig) -> Self {
+        Self {
+            base_delay_secs: config.retry_base_delay_secs,
+            max_delay_secs: config.retry_max_delay_secs,
+            multiplier: DEFAULT_RETRY_MULTIPLIER,
+            jitter: false,
+        }
+    }
+
+    /// This policy with a task's own backoff settings applied over it.
+    pub fn with_backoff(&self, backoff: &RetryBackoff) -> Self {
+        Self {
+            base_delay_secs: backoff.base_seconds.unwrap_or(self.base_delay_secs),
+            max_delay_secs: backoff.max_seconds.unwrap_or(self.max_delay_secs),
+            multiplier: backoff.multiplier.unwrap_or(self.multiplier),
+            jitter: backoff.jitter || self.jitter,
+        }
+    }
+
+    /// `max_delay_secs` in milliseconds, the most a handler's own
+    /// `retry_after_ms` may ask for.
+    pub fn max_delay_ms(&self) -> i64 {
+        i64::try_from(self.max_delay_secs.saturating_mul(1000)).unwrap_or(i64::MAX)
+    }
+
+    /// Delay before the next attempt of a task that has run `attempt_count`
+    /// times, before any jitter.
+    pub fn delay(&self, attempt_count: i32) -> Duration {
+        let secs = self.base_delay_secs as f64 * self.multiplier.powi(attempt_count.max(0));
+        let capped = secs.min(self.max_delay_secs as f64);
+        Duration::milliseconds((capped * 1000.0) as i64)
+    }
+
+    /// The delay the scheduler actually waits: [`Self::delay`], with jitter
+    /// drawn if enabled.
+    pub fn jittered_delay(&self, attempt_count: i32) -> Duration {
+        let delay = self.delay(attempt_count);
+        if !self.jitter {
+            return delay;
+        }
+        let ms = delay.num_milliseconds() as f64;
+        Duration::milliseconds((ms / 2.0 + ms / 2.0 * unit_random()) as i64)
+    }
+
+    /// When a task that has run `attempt_count` times and just failed at
+    /// `failed_at` runs again, at the latest once jitter is drawn.
+    pub fn next_attempt_at(&self, attempt_count: i32, failed_at: DateTime<Utc>) -> DateTime<Utc> {
+        failed_at
+            .checked_add_signed(self.delay(attempt_count))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// Project the remaining attempts of a RETRY task. `scheduled_at` is the
+    /// retry time already assigned, if any. `None` once no attempts are left.
+    pub fn plan(
+        &self,
+        attempt_count: i32,
+        max_retries: i32,
+        scheduled_at: Option<DateTime<Utc>>,
+        deadline_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<RetryPlan> {
+        let attempts_remaining = max_retries - attempt_count;
+        if attempts_remaining <= 0 {
+            return None;
+        }
+
+        let mut schedule = Vec::with_capacity(attempts_remaining as usize);
+        let mut at = scheduled_at.unwrap_or_else(|| self.next_attempt_at(attempt_count, now));
+        for attempt in attempt_count..max_retries {
+            if attempt > attempt_count {
+                at = self.next_attempt_at(attempt, at);
+            }
+            // The scheduler fails a task instead of retrying it past its deadline
+            if deadline_at.is_some_and(|d| at >= d) {
+                break;
+            }
+            schedule.push(at);
+        }
+
+        Some(RetryPlan {
+            next_attempt_at: schedule.first().copied(),
+            attempts_remaining,
+            dead_letter: schedule.len() == attempts_remaining as usize,
+            schedule,
+        })
+    }
+}
+
+/// Uniform in [0, 1), from the random bits of a UUIDv7.
+fn unit_random() -> f64 {
+    let bytes = uuid::Uuid::now_v7().into_bytes();
+    let tail = u64::from_be_bytes(bytes[8..].try_into().unwrap_or_default());
+    // The low 62 bits are random; the top two hold the variant
+    (tail & ((1 << 62) - 1)) as f64 / (1u64 << 62) as f64
+}
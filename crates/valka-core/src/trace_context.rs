@@ -0,0 +1,60 @@
+//! W3C trace context propagation between the hops of a task: create →
+//! (forward) → dispatch → worker → result. Each hop carries the `traceparent`
+//! of the span that handed the task on; the receiving hop parents its own span
+//! under it. Without an OpenTelemetry layer installed spans carry no trace
+//! context, so nothing is propagated.
+
+use std::collections::HashMap;
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Header / metadata key of the W3C trace context.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// W3C traceparent of `span`, if it belongs to an exported trace.
+pub fn traceparent(span: &Span) -> Option<String> {
+    let cx = span.context();
+    if !cx.span().span_context().is_valid() {
+        return None;
+    }
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&cx, &mut carrier);
+    carrier.remove(TRACEPARENT)
+}
+
+/// W3C traceparent of the current span.
+pub fn current_traceparent() -> Option<String> {
+    traceparent(&Span::current())
+}
+
+/// Parent `span` under a remote traceparent. Empty or malformed values are ignored.
+pub fn set_parent(span: &Span, traceparent: &str) {
+    if traceparent.is_empty() {
+        return;
+    }
+    let carrier = HashMap::from([(TRACEPARENT.to_string(), traceparent.to_string())]);
+    let cx = TraceContextPropagator::new().extract(&carrier);
+    if cx.span().span_context().is_valid() {
+        let _ = span.set_parent(cx);
+    }
+}
+
+/// Adds the current traceparent to outgoing gRPC metadata.
+pub fn inject_metadata(metadata: &mut tonic::metadata::MetadataMap) {
+    if let Some(value) = current_traceparent().and_then(|tp| tp.parse().ok()) {
+        metadata.insert(TRACEPARENT, value);
+    }
+}
+
+/// Reads a traceparent from incoming gRPC metadata (empty if absent).
+pub fn extract_metadata(metadata: &tonic::metadata::MetadataMap) -> String {
+    metadata
+        .get(TRACEPARENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
@@ -2,6 +2,30 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 use uuid::Uuid;
 
+/// Error recorded when a task's deadline passes before a worker picks it up.
+pub const DEADLINE_EXCEEDED_BEFORE_DISPATCH: &str = "deadline exceeded before dispatch";
+
+/// Error recorded when a failed task's next attempt could not start before its deadline.
+pub const DEADLINE_EXCEEDED_BEFORE_RETRY: &str = "deadline exceeded before retry";
+
+/// Resolve a creation request's deadline to absolute epoch ms.
+///
+/// `deadline_ms` is absolute, `deadline_in_ms` is relative to `now_ms`; at most one may be set.
+pub fn resolve_deadline_ms(
+    deadline_ms: Option<i64>,
+    deadline_in_ms: Option<i64>,
+    now_ms: i64,
+) -> Result<Option<i64>, String> {
+    match (deadline_ms, deadline_in_ms) {
+        (Some(_), Some(_)) => Err("Only one of deadline_ms and deadline_in_ms may be set".into()),
+        (Some(abs), None) if abs <= 0 => Err(format!("Invalid deadline_ms: {abs}")),
+        (Some(abs), None) => Ok(Some(abs)),
+        (None, Some(rel)) if rel < 0 => Err(format!("Invalid deadline_in_ms: {rel}")),
+        (None, Some(rel)) => Ok(Some(now_ms.saturating_add(rel))),
+        (None, None) => Ok(None),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TaskId(pub String);
 
@@ -129,6 +153,8 @@ pub enum TaskStatus {
     Retry,
     DeadLetter,
     Cancelled,
+    /// Created with dependencies that have not all completed yet
+    Waiting,
 }
 
 impl TaskStatus {
@@ -142,6 +168,7 @@ impl TaskStatus {
             Self::Retry => "RETRY",
             Self::DeadLetter => "DEAD_LETTER",
             Self::Cancelled => "CANCELLED",
+            Self::Waiting => "WAITING",
         }
     }
 
@@ -155,6 +182,7 @@ impl TaskStatus {
             "RETRY" => Some(Self::Retry),
             "DEAD_LETTER" => Some(Self::DeadLetter),
             "CANCELLED" => Some(Self::Cancelled),
+            "WAITING" => Some(Self::Waiting),
             _ => None,
         }
     }
@@ -166,6 +194,42 @@ impl fmt::Display for TaskStatus {
     }
 }
 
+/// Whether a task is written to PG before it is offered to a worker.
+///
+/// Ephemeral tasks go straight to a waiting worker and their row is written
+/// behind the assignment; one lost in a crash is gone. Queues must opt in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    #[default]
+    Durable,
+    Ephemeral,
+}
+
+impl Durability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Durable => "durable",
+            Self::Ephemeral => "ephemeral",
+        }
+    }
+
+    /// Parse a request value; empty means durable.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "" | "durable" => Ok(Self::Durable),
+            "ephemeral" => Ok(Self::Ephemeral),
+            other => Err(format!("Unknown durability: {other}")),
+        }
+    }
+}
+
+impl fmt::Display for Durability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 /// Number of partitions per queue (default)
 pub const DEFAULT_PARTITIONS: i32 = 4;
 
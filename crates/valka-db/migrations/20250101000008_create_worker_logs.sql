@@ -0,0 +1,11 @@
+CREATE TABLE worker_logs (
+    id            BIGSERIAL PRIMARY KEY,
+    worker_id     TEXT NOT NULL,
+    timestamp_ms  BIGINT NOT NULL,
+    level         TEXT NOT NULL,
+    message       TEXT NOT NULL,
+    metadata      JSONB,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_worker_logs_worker ON worker_logs (worker_id, timestamp_ms);
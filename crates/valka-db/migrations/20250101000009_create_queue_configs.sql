@@ -0,0 +1,12 @@
+CREATE TABLE queue_configs (
+    queue_name   TEXT PRIMARY KEY,
+    owner_team   TEXT,
+    contact      TEXT,
+    runbook_url  TEXT,
+    labels       JSONB NOT NULL DEFAULT '{}',
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_queue_configs_owner_team ON queue_configs (owner_team)
+    WHERE owner_team IS NOT NULL;
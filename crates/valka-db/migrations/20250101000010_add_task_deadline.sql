@@ -0,0 +1,4 @@
+ALTER TABLE tasks ADD COLUMN deadline_at TIMESTAMPTZ;
+
+CREATE INDEX idx_tasks_deadline ON tasks (deadline_at)
+    WHERE deadline_at IS NOT NULL AND status IN ('PENDING', 'RETRY');
@@ -0,0 +1,2 @@
+-- Workers declaring a lower semver are not matched on the queue
+ALTER TABLE queue_configs ADD COLUMN min_worker_version TEXT;
@@ -0,0 +1,5 @@
+-- Hold promotion of scheduled/delayed tasks per queue; release_max_per_tick
+-- caps promotions per scheduler tick until the past-due backlog drains.
+ALTER TABLE queue_configs
+    ADD COLUMN scheduled_hold BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN release_max_per_tick INTEGER;
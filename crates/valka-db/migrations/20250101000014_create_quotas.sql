@@ -0,0 +1,12 @@
+CREATE TABLE quotas (
+    key                     TEXT PRIMARY KEY,
+    max_active_tasks        BIGINT,
+    max_creations_per_hour  BIGINT,
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Usage reconciliation counts per namespace (queue name up to the first '.')
+CREATE INDEX idx_tasks_namespace_active ON tasks (split_part(queue_name, '.', 1))
+    WHERE status IN ('PENDING', 'DISPATCHING', 'RUNNING', 'RETRY');
+CREATE INDEX idx_tasks_namespace_created ON tasks (split_part(queue_name, '.', 1), created_at);
@@ -0,0 +1,5 @@
+-- Ephemeral tasks skip the insert before sync match; their row is written
+-- after assignment, or as PENDING when no worker was waiting.
+ALTER TABLE tasks ADD COLUMN durability TEXT NOT NULL DEFAULT 'durable';
+
+ALTER TABLE queue_configs ADD COLUMN allow_ephemeral BOOLEAN NOT NULL DEFAULT FALSE;
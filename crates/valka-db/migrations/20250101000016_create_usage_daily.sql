@@ -0,0 +1,28 @@
+-- Per-day, per-queue usage for chargeback, maintained incrementally by the scheduler
+CREATE TABLE usage_daily (
+    date                DATE NOT NULL,
+    queue_name          TEXT NOT NULL,
+    namespace           TEXT NOT NULL,
+    owner_team          TEXT,
+    tasks_created       BIGINT NOT NULL DEFAULT 0,
+    tasks_completed     BIGINT NOT NULL DEFAULT 0,
+    tasks_failed        BIGINT NOT NULL DEFAULT 0,
+    total_execution_ms  BIGINT NOT NULL DEFAULT 0,
+    total_log_bytes     BIGINT NOT NULL DEFAULT 0,
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (date, queue_name)
+);
+
+-- Single row: activity before high_water_mark is already folded into usage_daily
+CREATE TABLE usage_rollup_state (
+    id               BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    high_water_mark  TIMESTAMPTZ NOT NULL
+);
+
+INSERT INTO usage_rollup_state (high_water_mark) VALUES ('1970-01-01T00:00:00Z');
+
+-- Rollup windows scan by time
+CREATE INDEX idx_tasks_created_at ON tasks (created_at);
+CREATE INDEX idx_task_runs_completed_at ON task_runs (completed_at)
+    WHERE completed_at IS NOT NULL;
+CREATE INDEX idx_task_logs_created_at ON task_logs (created_at);
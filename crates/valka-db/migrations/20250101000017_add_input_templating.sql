@@ -0,0 +1,2 @@
+-- Substitute {{valka.*}} / {{meta.*}} placeholders in task input at dispatch.
+ALTER TABLE queue_configs ADD COLUMN template_input BOOLEAN NOT NULL DEFAULT FALSE;
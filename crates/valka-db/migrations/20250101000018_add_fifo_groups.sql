@@ -0,0 +1,8 @@
+-- FIFO groups: on queues with fifo set, tasks sharing a fifo_key run one at a
+-- time in created_at order.
+ALTER TABLE tasks ADD COLUMN fifo_key TEXT;
+
+CREATE INDEX idx_tasks_fifo ON tasks (queue_name, fifo_key, created_at)
+    WHERE fifo_key IS NOT NULL;
+
+ALTER TABLE queue_configs ADD COLUMN fifo BOOLEAN NOT NULL DEFAULT FALSE;
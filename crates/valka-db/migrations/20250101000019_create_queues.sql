@@ -0,0 +1,19 @@
+-- Registry of queue names, written when a queue gets its first task. A new
+-- name is announced on the valka_queues channel so TaskReader managers start
+-- readers for it without scanning tasks.
+CREATE TABLE queues (
+    name        TEXT PRIMARY KEY,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO queues (name) SELECT DISTINCT queue_name FROM tasks;
+
+CREATE FUNCTION notify_new_queue() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('valka_queues', NEW.name);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER queues_notify_insert AFTER INSERT ON queues
+    FOR EACH ROW EXECUTE FUNCTION notify_new_queue();
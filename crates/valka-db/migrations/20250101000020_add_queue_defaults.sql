@@ -0,0 +1,8 @@
+-- Per-queue task defaults and limits. The defaults apply to creates that leave
+-- max_retries / timeout_seconds unset; a paused queue is not dequeued.
+ALTER TABLE queue_configs
+    ADD COLUMN default_max_retries INTEGER CHECK (default_max_retries >= 0),
+    ADD COLUMN default_timeout_seconds INTEGER CHECK (default_timeout_seconds > 0),
+    ADD COLUMN max_concurrency INTEGER CHECK (max_concurrency > 0),
+    ADD COLUMN paused BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN retention_days INTEGER CHECK (retention_days > 0);
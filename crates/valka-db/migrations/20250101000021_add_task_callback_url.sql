@@ -0,0 +1,2 @@
+-- Webhook POSTed with the task when it reaches a terminal state
+ALTER TABLE tasks ADD COLUMN callback_url TEXT;
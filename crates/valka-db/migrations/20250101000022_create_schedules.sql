@@ -0,0 +1,18 @@
+-- Recurring tasks. The scheduler leader creates a task from a schedule each
+-- time its cron expression fires; advancing next_fire_at in the same
+-- transaction as the insert keeps a firing from being repeated.
+CREATE TABLE schedules (
+    id               TEXT PRIMARY KEY,
+    cron_expression  TEXT NOT NULL,
+    queue_name       TEXT NOT NULL,
+    task_name        TEXT NOT NULL,
+    input            JSONB,
+    priority         INTEGER NOT NULL DEFAULT 0,
+    max_retries      INTEGER NOT NULL,
+    timeout_seconds  INTEGER NOT NULL,
+    next_fire_at     TIMESTAMPTZ NOT NULL,
+    last_fired_at    TIMESTAMPTZ,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_schedules_next_fire ON schedules (next_fire_at);
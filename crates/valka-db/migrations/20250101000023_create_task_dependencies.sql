@@ -0,0 +1,12 @@
+-- Parents a task waits on. A task created with dependencies sits in WAITING
+-- until every parent is COMPLETED (then PENDING), or is CANCELLED when a
+-- parent fails, is dead-lettered or cancelled.
+CREATE TABLE task_dependencies (
+    task_id     TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    depends_on  TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    PRIMARY KEY (task_id, depends_on)
+);
+
+CREATE INDEX idx_task_dependencies_parent ON task_dependencies (depends_on);
+
+CREATE INDEX idx_tasks_waiting ON tasks (created_at) WHERE status = 'WAITING';
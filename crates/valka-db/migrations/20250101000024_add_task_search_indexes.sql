@@ -0,0 +1,14 @@
+-- Indexes behind task search (GET /api/v1/tasks/search). created_at ranges
+-- use idx_tasks_created_at and idempotency keys idx_tasks_idempotency.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_tasks_status_created ON tasks (status, created_at);
+
+-- task_name prefix matches (LIKE 'prefix%') regardless of collation
+CREATE INDEX idx_tasks_task_name_prefix ON tasks (task_name text_pattern_ops);
+
+CREATE INDEX idx_tasks_priority_created ON tasks (priority DESC, created_at);
+
+-- Substring search on error_message (ILIKE '%text%')
+CREATE INDEX idx_tasks_error_message_trgm ON tasks USING gin (error_message gin_trgm_ops)
+    WHERE error_message IS NOT NULL;
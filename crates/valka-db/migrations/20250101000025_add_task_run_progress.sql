@@ -0,0 +1,3 @@
+-- Latest progress a handler reported for its run (percent 0-100)
+ALTER TABLE task_runs ADD COLUMN progress REAL;
+ALTER TABLE task_runs ADD COLUMN progress_message TEXT;
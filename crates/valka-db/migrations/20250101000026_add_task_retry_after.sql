@@ -0,0 +1,3 @@
+-- Delay a handler asked for before the next attempt; the retry processor
+-- uses it instead of backoff and clears it once the retry is scheduled
+ALTER TABLE tasks ADD COLUMN retry_after_ms BIGINT;
@@ -0,0 +1,6 @@
+-- Backoff a task was created with; NULL columns fall back to the scheduler's
+-- retry settings
+ALTER TABLE tasks ADD COLUMN retry_base_seconds BIGINT;
+ALTER TABLE tasks ADD COLUMN retry_max_seconds BIGINT;
+ALTER TABLE tasks ADD COLUMN retry_multiplier DOUBLE PRECISION;
+ALTER TABLE tasks ADD COLUMN retry_jitter BOOLEAN NOT NULL DEFAULT FALSE;
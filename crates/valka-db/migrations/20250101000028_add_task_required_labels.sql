@@ -0,0 +1,2 @@
+-- Labels a worker must carry to be handed the task, e.g. {"gpu": "true"}
+ALTER TABLE tasks ADD COLUMN required_labels JSONB NOT NULL DEFAULT '{}';
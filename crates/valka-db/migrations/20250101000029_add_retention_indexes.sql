@@ -0,0 +1,5 @@
+-- Retention cleanup scans terminal tasks and old worker logs by age
+CREATE INDEX idx_tasks_terminal_updated_at ON tasks (updated_at)
+    WHERE status IN ('COMPLETED', 'FAILED', 'DEAD_LETTER', 'CANCELLED');
+CREATE INDEX idx_worker_logs_created_at ON worker_logs (created_at);
+CREATE INDEX idx_dead_letter_task ON dead_letter_queue (task_id);
@@ -0,0 +1,5 @@
+-- Queues may opt out of the DLQ; their exhausted tasks stay FAILED
+ALTER TABLE queue_configs ADD COLUMN dead_letter_enabled BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Worker that ran the attempt which exhausted the task's retries
+ALTER TABLE dead_letter_queue ADD COLUMN worker_id TEXT;
@@ -0,0 +1,2 @@
+-- Cap on how many of a queue's tasks each node's TaskReaders dispatch per second
+ALTER TABLE queue_configs ADD COLUMN max_dispatch_per_second INTEGER;
@@ -0,0 +1,2 @@
+-- Set when the worker's output was over limits.max_output_bytes and not stored
+ALTER TABLE task_runs ADD COLUMN output_truncated BOOLEAN NOT NULL DEFAULT FALSE;
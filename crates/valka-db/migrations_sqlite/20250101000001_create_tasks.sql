@@ -0,0 +1,28 @@
+CREATE TABLE tasks (
+    id               TEXT PRIMARY KEY,
+    queue_name       TEXT NOT NULL,
+    task_name        TEXT NOT NULL,
+    partition_id     INTEGER NOT NULL,
+    status           TEXT NOT NULL DEFAULT 'PENDING',
+    input            TEXT,
+    priority         INTEGER NOT NULL DEFAULT 0,
+    max_retries      INTEGER NOT NULL DEFAULT 3,
+    attempt_count    INTEGER NOT NULL DEFAULT 0,
+    timeout_seconds  INTEGER NOT NULL DEFAULT 300,
+    idempotency_key  TEXT,
+    metadata         TEXT NOT NULL DEFAULT '{}',
+    scheduled_at     TEXT,
+    created_at       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Primary dequeue index (hot path)
+CREATE INDEX idx_tasks_dequeue ON tasks (queue_name, partition_id, created_at)
+    WHERE status = 'PENDING';
+
+-- Retry engine
+CREATE INDEX idx_tasks_retry ON tasks (scheduled_at) WHERE status = 'RETRY';
+
+-- Idempotency
+CREATE UNIQUE INDEX idx_tasks_idempotency ON tasks (idempotency_key)
+    WHERE idempotency_key IS NOT NULL;
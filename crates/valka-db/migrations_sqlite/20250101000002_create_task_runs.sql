@@ -0,0 +1,20 @@
+CREATE TABLE task_runs (
+    id               TEXT PRIMARY KEY,
+    task_id          TEXT NOT NULL REFERENCES tasks(id),
+    attempt_number   INTEGER NOT NULL,
+    worker_id        TEXT NOT NULL,
+    assigned_node_id TEXT NOT NULL,
+    status           TEXT NOT NULL DEFAULT 'RUNNING',
+    output           TEXT,
+    error_message    TEXT,
+    lease_expires_at TEXT NOT NULL,
+    started_at       TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at     TEXT,
+    last_heartbeat   TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (task_id, attempt_number)
+);
+
+CREATE INDEX idx_task_runs_lease ON task_runs (lease_expires_at)
+    WHERE status = 'RUNNING';
+
+CREATE INDEX idx_task_runs_task ON task_runs (task_id);
@@ -0,0 +1,11 @@
+CREATE TABLE task_logs (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_run_id   TEXT NOT NULL,
+    timestamp_ms  INTEGER NOT NULL,
+    level         TEXT NOT NULL,
+    message       TEXT NOT NULL,
+    metadata      TEXT,
+    created_at    TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_task_logs_run ON task_logs (task_run_id, timestamp_ms);
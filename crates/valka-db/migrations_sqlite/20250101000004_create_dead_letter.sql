@@ -0,0 +1,13 @@
+CREATE TABLE dead_letter_queue (
+    id            TEXT PRIMARY KEY,
+    task_id       TEXT NOT NULL REFERENCES tasks(id),
+    queue_name    TEXT NOT NULL,
+    task_name     TEXT NOT NULL,
+    input         TEXT,
+    error_message TEXT,
+    attempt_count INTEGER NOT NULL,
+    metadata      TEXT NOT NULL DEFAULT '{}',
+    created_at    TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_dead_letter_queue ON dead_letter_queue (queue_name, created_at);
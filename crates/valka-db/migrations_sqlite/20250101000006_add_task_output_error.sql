@@ -0,0 +1,3 @@
+-- Add output and error_message columns to tasks table for direct access
+ALTER TABLE tasks ADD COLUMN output TEXT;
+ALTER TABLE tasks ADD COLUMN error_message TEXT;
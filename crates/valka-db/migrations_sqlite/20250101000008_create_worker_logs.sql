@@ -0,0 +1,11 @@
+CREATE TABLE worker_logs (
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    worker_id     TEXT NOT NULL,
+    timestamp_ms  INTEGER NOT NULL,
+    level         TEXT NOT NULL,
+    message       TEXT NOT NULL,
+    metadata      TEXT,
+    created_at    TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_worker_logs_worker ON worker_logs (worker_id, timestamp_ms);
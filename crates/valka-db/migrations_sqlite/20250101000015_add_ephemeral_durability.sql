@@ -0,0 +1 @@
+ALTER TABLE tasks ADD COLUMN durability TEXT NOT NULL DEFAULT 'durable';
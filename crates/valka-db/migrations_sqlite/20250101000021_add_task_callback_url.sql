@@ -0,0 +1 @@
+ALTER TABLE tasks ADD COLUMN callback_url TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE task_runs ADD COLUMN progress REAL;
+ALTER TABLE task_runs ADD COLUMN progress_message TEXT;
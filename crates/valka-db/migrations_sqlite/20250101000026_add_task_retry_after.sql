@@ -0,0 +1 @@
+ALTER TABLE tasks ADD COLUMN retry_after_ms INTEGER;
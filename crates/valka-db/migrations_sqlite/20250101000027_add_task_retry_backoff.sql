@@ -0,0 +1,4 @@
+ALTER TABLE tasks ADD COLUMN retry_base_seconds INTEGER;
+ALTER TABLE tasks ADD COLUMN retry_max_seconds INTEGER;
+ALTER TABLE tasks ADD COLUMN retry_multiplier REAL;
+ALTER TABLE tasks ADD COLUMN retry_jitter BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+ALTER TABLE tasks ADD COLUMN required_labels TEXT NOT NULL DEFAULT '{}';
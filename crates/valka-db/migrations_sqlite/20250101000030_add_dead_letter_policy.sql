@@ -0,0 +1,2 @@
+-- Worker that ran the attempt which exhausted the task's retries
+ALTER TABLE dead_letter_queue ADD COLUMN worker_id TEXT;
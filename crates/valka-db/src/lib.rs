@@ -1,5 +1,7 @@
 pub mod migrations;
 pub mod pool;
 pub mod queries;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 pub use pool::DbPool;
@@ -1,5 +1,7 @@
 use sqlx::PgPool;
 
+use super::tasks::TaskRow;
+
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct DeadLetterRow {
     pub id: String,
@@ -11,8 +13,11 @@ pub struct DeadLetterRow {
     pub attempt_count: i32,
     pub metadata: serde_json::Value,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Worker of the attempt that exhausted the task's retries
+    pub worker_id: Option<String>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn insert_dead_letter(
     pool: &PgPool,
     id: &str,
@@ -21,13 +26,15 @@ pub async fn insert_dead_letter(
     task_name: &str,
     input: Option<&serde_json::Value>,
     error_message: Option<&str>,
+    worker_id: Option<&str>,
     attempt_count: i32,
     metadata: &serde_json::Value,
 ) -> Result<DeadLetterRow, sqlx::Error> {
     let row = sqlx::query_as::<_, DeadLetterRow>(
         r#"
-        INSERT INTO dead_letter_queue (id, task_id, queue_name, task_name, input, error_message, attempt_count, metadata)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        INSERT INTO dead_letter_queue (id, task_id, queue_name, task_name, input, error_message,
+                                       worker_id, attempt_count, metadata)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
         RETURNING *
         "#,
     )
@@ -37,6 +44,7 @@ pub async fn insert_dead_letter(
     .bind(task_name)
     .bind(input)
     .bind(error_message)
+    .bind(worker_id)
     .bind(attempt_count)
     .bind(metadata)
     .fetch_one(pool)
@@ -65,3 +73,119 @@ pub async fn list_dead_letters(
     .await?;
     Ok(rows)
 }
+
+pub async fn count_dead_letters(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM dead_letter_queue")
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_dead_letter(
+    pool: &PgPool,
+    id: &str,
+) -> Result<Option<DeadLetterRow>, sqlx::Error> {
+    let row = sqlx::query_as::<_, DeadLetterRow>("SELECT * FROM dead_letter_queue WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+/// Put a dead letter's task back to PENDING with a fresh attempt count and
+/// remove the dead letter. A task row that no longer exists is recreated from
+/// the dead letter on `partition_id`.
+///
+/// Returns None if the dead letter is gone or its task has left DEAD_LETTER.
+pub async fn requeue_dead_letter(
+    pool: &PgPool,
+    id: &str,
+    partition_id: i32,
+) -> Result<Option<TaskRow>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let Some(dl) = sqlx::query_as::<_, DeadLetterRow>(
+        "SELECT * FROM dead_letter_queue WHERE id = $1 FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let reset = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks
+        SET status = 'PENDING', attempt_count = 0, output = NULL, error_message = NULL,
+            scheduled_at = NULL, updated_at = NOW()
+        WHERE id = $1 AND status = 'DEAD_LETTER'
+        RETURNING *
+        "#,
+    )
+    .bind(&dl.task_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let task = match reset {
+        Some(task) => task,
+        None => {
+            let recreated = sqlx::query_as::<_, TaskRow>(
+                r#"
+                INSERT INTO tasks (id, queue_name, task_name, partition_id, input, metadata)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (id) DO NOTHING
+                RETURNING *
+                "#,
+            )
+            .bind(&dl.task_id)
+            .bind(&dl.queue_name)
+            .bind(&dl.task_name)
+            .bind(partition_id)
+            .bind(&dl.input)
+            .bind(&dl.metadata)
+            .fetch_optional(&mut *tx)
+            .await?;
+            // Present but not DEAD_LETTER: it has been re-run some other way
+            let Some(task) = recreated else {
+                return Ok(None);
+            };
+            task
+        }
+    };
+
+    sqlx::query("DELETE FROM dead_letter_queue WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(Some(task))
+}
+
+pub async fn delete_dead_letter(pool: &PgPool, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM dead_letter_queue WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete dead letters, optionally only those of one queue and/or created
+/// before `older_than`. Returns how many were deleted.
+pub async fn purge_dead_letters(
+    pool: &PgPool,
+    queue_name: Option<&str>,
+    older_than: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM dead_letter_queue
+        WHERE ($1::text IS NULL OR queue_name = $1)
+          AND ($2::timestamptz IS NULL OR created_at < $2)
+        "#,
+    )
+    .bind(queue_name)
+    .bind(older_than)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,167 @@
+use sqlx::PgPool;
+
+use crate::queries::tasks::{CreateTaskParams, TaskRow, insert_task};
+
+/// Why a task with dependencies could not be created.
+#[derive(Debug, thiserror::Error)]
+pub enum DependencyError {
+    #[error("Dependency not found: {0}")]
+    UnknownParent(String),
+    #[error("Dependency {task_id} already ended {status}")]
+    ParentEnded { task_id: String, status: String },
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// A parent of a task and the parent's current status.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DependencyRow {
+    pub depends_on: String,
+    pub status: String,
+}
+
+impl DependencyRow {
+    pub fn is_met(&self) -> bool {
+        self.status == "COMPLETED"
+    }
+}
+
+/// Tasks released to PENDING or cancelled by [`resolve_waiting_tasks`].
+#[derive(Debug, Default)]
+pub struct ResolvedDependents {
+    pub released: Vec<TaskRow>,
+    pub cancelled: Vec<TaskRow>,
+}
+
+/// Insert a task that runs once every task in `depends_on` has COMPLETED. It
+/// starts WAITING, or PENDING when they all already have. The parents are
+/// locked while checking them so one can't end between the check and the
+/// insert unseen.
+pub async fn create_dependent_task(
+    pool: &PgPool,
+    params: CreateTaskParams,
+    depends_on: &[String],
+) -> Result<TaskRow, DependencyError> {
+    let mut tx = pool.begin().await?;
+    let parents: Vec<DependencyRow> = sqlx::query_as(
+        r#"
+        SELECT id AS depends_on, status FROM tasks
+        WHERE id = ANY($1)
+        FOR SHARE
+        "#,
+    )
+    .bind(depends_on)
+    .fetch_all(&mut *tx)
+    .await?;
+    if let Some(missing) = depends_on
+        .iter()
+        .find(|id| !parents.iter().any(|p| &p.depends_on == *id))
+    {
+        return Err(DependencyError::UnknownParent(missing.clone()));
+    }
+    if let Some(ended) = parents
+        .iter()
+        .find(|p| matches!(p.status.as_str(), "FAILED" | "DEAD_LETTER" | "CANCELLED"))
+    {
+        return Err(DependencyError::ParentEnded {
+            task_id: ended.depends_on.clone(),
+            status: ended.status.clone(),
+        });
+    }
+
+    let mut task = insert_task(&mut *tx, &params).await?;
+    sqlx::query(
+        r#"
+        INSERT INTO task_dependencies (task_id, depends_on)
+        SELECT $1, unnest($2::text[])
+        "#,
+    )
+    .bind(&task.id)
+    .bind(depends_on)
+    .execute(&mut *tx)
+    .await?;
+    if !parents.iter().all(DependencyRow::is_met) {
+        task = sqlx::query_as::<_, TaskRow>(
+            "UPDATE tasks SET status = 'WAITING' WHERE id = $1 RETURNING *",
+        )
+        .bind(&task.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(task)
+}
+
+/// A task's parents, in the order they were created.
+pub async fn list_dependencies(
+    pool: &PgPool,
+    task_id: &str,
+) -> Result<Vec<DependencyRow>, sqlx::Error> {
+    sqlx::query_as::<_, DependencyRow>(
+        r#"
+        SELECT d.depends_on, p.status
+        FROM task_dependencies d
+        JOIN tasks p ON p.id = d.depends_on
+        WHERE d.task_id = $1
+        ORDER BY p.created_at, p.id
+        "#,
+    )
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Move WAITING tasks on: cancel those with a parent that failed, was
+/// dead-lettered or cancelled, and release to PENDING those whose parents
+/// have all COMPLETED. With `parent_id`, only that task's dependents are
+/// considered. A cancelled task's own dependents are left for the next call.
+pub async fn resolve_waiting_tasks(
+    pool: &PgPool,
+    parent_id: Option<&str>,
+) -> Result<ResolvedDependents, sqlx::Error> {
+    let cancelled = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks t SET status = 'CANCELLED',
+            error_message = 'Dependency ' || ended.depends_on || ' ended ' || ended.status,
+            updated_at = NOW()
+        FROM (
+            SELECT DISTINCT ON (d.task_id) d.task_id, d.depends_on, p.status
+            FROM tasks w
+            JOIN task_dependencies d ON d.task_id = w.id
+            JOIN tasks p ON p.id = d.depends_on
+            WHERE w.status = 'WAITING'
+              AND p.status IN ('FAILED', 'DEAD_LETTER', 'CANCELLED')
+              AND ($1::text IS NULL OR d.depends_on = $1)
+            ORDER BY d.task_id, d.depends_on
+        ) ended
+        WHERE t.id = ended.task_id AND t.status = 'WAITING'
+        RETURNING t.*
+        "#,
+    )
+    .bind(parent_id)
+    .fetch_all(pool)
+    .await?;
+
+    let released = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks t SET status = 'PENDING', updated_at = NOW()
+        WHERE t.status = 'WAITING'
+          AND ($1::text IS NULL
+               OR t.id IN (SELECT task_id FROM task_dependencies WHERE depends_on = $1))
+          AND NOT EXISTS (
+              SELECT 1 FROM task_dependencies d
+              JOIN tasks p ON p.id = d.depends_on
+              WHERE d.task_id = t.id AND p.status <> 'COMPLETED'
+          )
+        RETURNING t.*
+        "#,
+    )
+    .bind(parent_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ResolvedDependents {
+        released,
+        cancelled,
+    })
+}
@@ -1,5 +1,13 @@
 pub mod dead_letter;
+pub mod dependencies;
+pub mod queue_configs;
+pub mod queues;
+pub mod quotas;
+pub mod retention;
+pub mod schedules;
 pub mod signals;
 pub mod task_logs;
 pub mod task_runs;
 pub mod tasks;
+pub mod usage;
+pub mod worker_logs;
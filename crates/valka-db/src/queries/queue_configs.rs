@@ -0,0 +1,336 @@
+use std::collections::BTreeMap;
+
+use sqlx::PgPool;
+
+pub const MAX_OWNER_TEAM_LEN: usize = 128;
+pub const MAX_CONTACT_LEN: usize = 256;
+pub const MAX_RUNBOOK_URL_LEN: usize = 2048;
+pub const MAX_LABELS: usize = 64;
+pub const MAX_LABEL_KEY_LEN: usize = 63;
+pub const MAX_LABEL_VALUE_LEN: usize = 256;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct QueueConfigRow {
+    pub queue_name: String,
+    pub owner_team: Option<String>,
+    pub contact: Option<String>,
+    pub runbook_url: Option<String>,
+    pub labels: serde_json::Value,
+    pub min_worker_version: Option<String>,
+    /// Due scheduled/delayed tasks stay waiting while set
+    pub scheduled_hold: bool,
+    /// Promotion cap per scheduler tick while a release is ramping
+    pub release_max_per_tick: Option<i32>,
+    /// Tasks may be created with ephemeral durability
+    pub allow_ephemeral: bool,
+    /// Input placeholders are substituted at dispatch
+    pub template_input: bool,
+    /// Tasks sharing a fifo_key run one at a time, in creation order
+    pub fifo: bool,
+    /// Used by creates that leave max_retries / timeout_seconds unset
+    pub default_max_retries: Option<i32>,
+    pub default_timeout_seconds: Option<i32>,
+    pub max_concurrency: Option<i32>,
+    /// The TaskReader does not dequeue the queue while set
+    pub paused: bool,
+    pub retention_days: Option<i32>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Exhausted tasks move to the DLQ; when unset they stay FAILED
+    pub dead_letter_enabled: bool,
+    /// Tasks per second each node's TaskReaders may dispatch
+    pub max_dispatch_per_second: Option<i32>,
+}
+
+/// Partial update of a queue's config. `None` keeps the stored value, an empty
+/// string or a 0 clears it; `labels` replaces the whole map when set.
+#[derive(Debug, Clone, Default)]
+pub struct QueueOwnershipUpdate {
+    pub owner_team: Option<String>,
+    pub contact: Option<String>,
+    pub runbook_url: Option<String>,
+    pub labels: Option<BTreeMap<String, String>>,
+    /// Semver; workers declaring a lower version are not matched on the queue
+    pub min_worker_version: Option<String>,
+    pub allow_ephemeral: Option<bool>,
+    pub template_input: Option<bool>,
+    pub fifo: Option<bool>,
+    pub default_max_retries: Option<i32>,
+    pub default_timeout_seconds: Option<i32>,
+    pub max_concurrency: Option<i32>,
+    pub paused: Option<bool>,
+    pub retention_days: Option<i32>,
+    pub dead_letter_enabled: Option<bool>,
+    pub max_dispatch_per_second: Option<i32>,
+}
+
+impl QueueOwnershipUpdate {
+    /// Check lengths and the runbook URL format.
+    pub fn validate(&self) -> Result<(), String> {
+        check_len("owner_team", self.owner_team.as_deref(), MAX_OWNER_TEAM_LEN)?;
+        check_len("contact", self.contact.as_deref(), MAX_CONTACT_LEN)?;
+        check_len(
+            "runbook_url",
+            self.runbook_url.as_deref(),
+            MAX_RUNBOOK_URL_LEN,
+        )?;
+
+        if let Some(url) = self.runbook_url.as_deref().filter(|u| !u.is_empty()) {
+            validate_url(url)?;
+        }
+
+        if let Some(version) = self.min_worker_version.as_deref().filter(|v| !v.is_empty()) {
+            semver::Version::parse(version)
+                .map_err(|e| format!("min_worker_version is not valid semver: {e}"))?;
+        }
+
+        for (field, value) in [
+            ("default_max_retries", self.default_max_retries),
+            ("default_timeout_seconds", self.default_timeout_seconds),
+            ("max_concurrency", self.max_concurrency),
+            ("retention_days", self.retention_days),
+            ("max_dispatch_per_second", self.max_dispatch_per_second),
+        ] {
+            if value.is_some_and(|v| v < 0) {
+                return Err(format!("{field} must not be negative"));
+            }
+        }
+
+        if let Some(labels) = &self.labels {
+            if labels.len() > MAX_LABELS {
+                return Err(format!("at most {MAX_LABELS} labels are allowed"));
+            }
+            for (key, value) in labels {
+                if key.is_empty() || key.len() > MAX_LABEL_KEY_LEN {
+                    return Err(format!(
+                        "label key must be 1-{MAX_LABEL_KEY_LEN} characters: {key:?}"
+                    ));
+                }
+                if value.len() > MAX_LABEL_VALUE_LEN {
+                    return Err(format!(
+                        "label value for {key:?} exceeds {MAX_LABEL_VALUE_LEN} characters"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_len(field: &str, value: Option<&str>, max: usize) -> Result<(), String> {
+    match value {
+        Some(v) if v.chars().count() > max => Err(format!("{field} exceeds {max} characters")),
+        _ => Ok(()),
+    }
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| format!("runbook_url must be an http(s) URL: {url}"))?;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if host.is_empty() || url.chars().any(char::is_whitespace) {
+        return Err(format!("runbook_url is not a valid URL: {url}"));
+    }
+    Ok(())
+}
+
+pub async fn get_queue_config(
+    pool: &PgPool,
+    queue_name: &str,
+) -> Result<Option<QueueConfigRow>, sqlx::Error> {
+    sqlx::query_as::<_, QueueConfigRow>("SELECT * FROM queue_configs WHERE queue_name = $1")
+        .bind(queue_name)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Get configs for several queues at once (queues without a config are omitted)
+pub async fn get_queue_configs(
+    pool: &PgPool,
+    queue_names: &[String],
+) -> Result<Vec<QueueConfigRow>, sqlx::Error> {
+    if queue_names.is_empty() {
+        return Ok(vec![]);
+    }
+    sqlx::query_as::<_, QueueConfigRow>("SELECT * FROM queue_configs WHERE queue_name = ANY($1)")
+        .bind(queue_names)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn list_queue_configs(
+    pool: &PgPool,
+    owner_team: Option<&str>,
+) -> Result<Vec<QueueConfigRow>, sqlx::Error> {
+    sqlx::query_as::<_, QueueConfigRow>(
+        r#"
+        SELECT * FROM queue_configs
+        WHERE ($1::text IS NULL OR owner_team = $1)
+        ORDER BY queue_name
+        "#,
+    )
+    .bind(owner_team)
+    .fetch_all(pool)
+    .await
+}
+
+/// Create or partially update a queue's ownership metadata
+pub async fn upsert_queue_ownership(
+    pool: &PgPool,
+    queue_name: &str,
+    update: &QueueOwnershipUpdate,
+) -> Result<QueueConfigRow, sqlx::Error> {
+    let labels = update
+        .labels
+        .as_ref()
+        .map(|l| serde_json::to_value(l).unwrap_or_default());
+
+    sqlx::query_as::<_, QueueConfigRow>(
+        r#"
+        INSERT INTO queue_configs (queue_name, owner_team, contact, runbook_url, labels,
+                                   min_worker_version, allow_ephemeral, template_input, fifo,
+                                   default_max_retries, default_timeout_seconds, max_concurrency,
+                                   paused, retention_days, dead_letter_enabled,
+                                   max_dispatch_per_second)
+        VALUES ($1, NULLIF($2, ''), NULLIF($3, ''), NULLIF($4, ''), COALESCE($5, '{}'::jsonb),
+                NULLIF($6, ''), COALESCE($7, FALSE), COALESCE($8, FALSE), COALESCE($9, FALSE),
+                NULLIF($10, 0), NULLIF($11, 0), NULLIF($12, 0), COALESCE($13, FALSE),
+                NULLIF($14, 0), COALESCE($15, TRUE), NULLIF($16, 0))
+        ON CONFLICT (queue_name) DO UPDATE SET
+            owner_team = CASE WHEN $2::text IS NULL THEN queue_configs.owner_team ELSE NULLIF($2, '') END,
+            contact = CASE WHEN $3::text IS NULL THEN queue_configs.contact ELSE NULLIF($3, '') END,
+            runbook_url = CASE WHEN $4::text IS NULL THEN queue_configs.runbook_url ELSE NULLIF($4, '') END,
+            labels = COALESCE($5, queue_configs.labels),
+            min_worker_version = CASE WHEN $6::text IS NULL THEN queue_configs.min_worker_version
+                                      ELSE NULLIF($6, '') END,
+            allow_ephemeral = COALESCE($7, queue_configs.allow_ephemeral),
+            template_input = COALESCE($8, queue_configs.template_input),
+            fifo = COALESCE($9, queue_configs.fifo),
+            default_max_retries = CASE WHEN $10::int IS NULL THEN queue_configs.default_max_retries
+                                       ELSE NULLIF($10, 0) END,
+            default_timeout_seconds = CASE WHEN $11::int IS NULL THEN queue_configs.default_timeout_seconds
+                                           ELSE NULLIF($11, 0) END,
+            max_concurrency = CASE WHEN $12::int IS NULL THEN queue_configs.max_concurrency
+                                   ELSE NULLIF($12, 0) END,
+            paused = COALESCE($13, queue_configs.paused),
+            retention_days = CASE WHEN $14::int IS NULL THEN queue_configs.retention_days
+                                  ELSE NULLIF($14, 0) END,
+            dead_letter_enabled = COALESCE($15, queue_configs.dead_letter_enabled),
+            max_dispatch_per_second = CASE WHEN $16::int IS NULL
+                                           THEN queue_configs.max_dispatch_per_second
+                                           ELSE NULLIF($16, 0) END,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(queue_name)
+    .bind(update.owner_team.as_deref())
+    .bind(update.contact.as_deref())
+    .bind(update.runbook_url.as_deref())
+    .bind(labels)
+    .bind(update.min_worker_version.as_deref())
+    .bind(update.allow_ephemeral)
+    .bind(update.template_input)
+    .bind(update.fifo)
+    .bind(update.default_max_retries)
+    .bind(update.default_timeout_seconds)
+    .bind(update.max_concurrency)
+    .bind(update.paused)
+    .bind(update.retention_days)
+    .bind(update.dead_letter_enabled)
+    .bind(update.max_dispatch_per_second)
+    .fetch_one(pool)
+    .await
+}
+
+/// Whether exhausted tasks of the queue go to the DLQ. Queues without a config do.
+pub async fn dead_letter_enabled(pool: &PgPool, queue_name: &str) -> Result<bool, sqlx::Error> {
+    let enabled: Option<bool> =
+        sqlx::query_scalar("SELECT dead_letter_enabled FROM queue_configs WHERE queue_name = $1")
+            .bind(queue_name)
+            .fetch_optional(pool)
+            .await?;
+    Ok(enabled.unwrap_or(true))
+}
+
+/// Pause or resume dispatch of a queue's tasks.
+pub async fn set_paused(
+    pool: &PgPool,
+    queue_name: &str,
+    paused: bool,
+) -> Result<QueueConfigRow, sqlx::Error> {
+    sqlx::query_as::<_, QueueConfigRow>(
+        r#"
+        INSERT INTO queue_configs (queue_name, paused)
+        VALUES ($1, $2)
+        ON CONFLICT (queue_name) DO UPDATE SET
+            paused = $2,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(queue_name)
+    .bind(paused)
+    .fetch_one(pool)
+    .await
+}
+
+/// Hold or release promotion of due scheduled tasks for a queue. Releasing with
+/// `max_per_tick` ramps promotion until the past-due backlog drains.
+pub async fn set_scheduled_hold(
+    pool: &PgPool,
+    queue_name: &str,
+    held: bool,
+    max_per_tick: Option<i32>,
+) -> Result<QueueConfigRow, sqlx::Error> {
+    sqlx::query_as::<_, QueueConfigRow>(
+        r#"
+        INSERT INTO queue_configs (queue_name, scheduled_hold, release_max_per_tick)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (queue_name) DO UPDATE SET
+            scheduled_hold = $2,
+            release_max_per_tick = $3,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(queue_name)
+    .bind(held)
+    .bind(max_per_tick)
+    .fetch_one(pool)
+    .await
+}
+
+/// Queues released with a ramp that is still in progress
+pub async fn list_release_ramps(pool: &PgPool) -> Result<Vec<(String, i32)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, i32)>(
+        r#"
+        SELECT queue_name, release_max_per_tick FROM queue_configs
+        WHERE NOT scheduled_hold AND release_max_per_tick IS NOT NULL
+        ORDER BY queue_name
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Finish a release ramp, unless the queue was held or re-released meanwhile
+pub async fn end_release_ramp(
+    pool: &PgPool,
+    queue_name: &str,
+    max_per_tick: i32,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE queue_configs SET release_max_per_tick = NULL, updated_at = NOW()
+        WHERE queue_name = $1 AND NOT scheduled_hold AND release_max_per_tick = $2
+        "#,
+    )
+    .bind(queue_name)
+    .bind(max_per_tick)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
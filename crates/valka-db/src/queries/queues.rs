@@ -0,0 +1,19 @@
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+
+/// Channel the `queues` insert trigger notifies, with the new queue name as payload.
+pub const NEW_QUEUE_CHANNEL: &str = "valka_queues";
+
+/// Every queue that has ever had a task.
+pub async fn list_queue_names(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT name FROM queues ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+/// A listener subscribed to new queue notifications, on its own pool connection.
+pub async fn listen_for_new_queues(pool: &PgPool) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(NEW_QUEUE_CHANNEL).await?;
+    Ok(listener)
+}
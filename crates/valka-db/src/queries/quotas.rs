@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Task creation limits for one namespace. A `NULL` limit is unlimited.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct QuotaRow {
+    pub key: String,
+    /// Cap on PENDING/DISPATCHING/RUNNING/RETRY tasks
+    pub max_active_tasks: Option<i64>,
+    /// Cap on creations per clock hour
+    pub max_creations_per_hour: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub async fn list_quotas(pool: &PgPool) -> Result<Vec<QuotaRow>, sqlx::Error> {
+    sqlx::query_as::<_, QuotaRow>("SELECT * FROM quotas ORDER BY key")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_quota(pool: &PgPool, key: &str) -> Result<Option<QuotaRow>, sqlx::Error> {
+    sqlx::query_as::<_, QuotaRow>("SELECT * FROM quotas WHERE key = $1")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn upsert_quota(
+    pool: &PgPool,
+    key: &str,
+    max_active_tasks: Option<i64>,
+    max_creations_per_hour: Option<i64>,
+) -> Result<QuotaRow, sqlx::Error> {
+    sqlx::query_as::<_, QuotaRow>(
+        r#"
+        INSERT INTO quotas (key, max_active_tasks, max_creations_per_hour)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (key) DO UPDATE SET
+            max_active_tasks = EXCLUDED.max_active_tasks,
+            max_creations_per_hour = EXCLUDED.max_creations_per_hour,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(key)
+    .bind(max_active_tasks)
+    .bind(max_creations_per_hour)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete_quota(pool: &PgPool, key: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM quotas WHERE key = $1")
+        .bind(key)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Non-terminal tasks in a namespace, and tasks it created since `window_start`.
+/// A task's namespace is its queue name up to the first `.`.
+pub async fn namespace_usage(
+    pool: &PgPool,
+    key: &str,
+    window_start: DateTime<Utc>,
+) -> Result<(i64, i64), sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM tasks
+             WHERE split_part(queue_name, '.', 1) = $1
+               AND status IN ('PENDING', 'DISPATCHING', 'RUNNING', 'RETRY')),
+            (SELECT COUNT(*) FROM tasks
+             WHERE split_part(queue_name, '.', 1) = $1 AND created_at >= $2)
+        "#,
+    )
+    .bind(key)
+    .bind(window_start)
+    .fetch_one(pool)
+    .await
+}
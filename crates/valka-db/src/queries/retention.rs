@@ -0,0 +1,163 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// Rows removed by one [`delete_expired_tasks`] batch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DeletedTasks {
+    pub tasks: u64,
+    pub task_runs: u64,
+    pub task_logs: u64,
+    pub dead_letters: u64,
+}
+
+/// Delete up to `limit` tasks in one of `statuses` last updated before
+/// `before`, together with their runs, run logs and dead letter entries.
+/// Tasks on queues with their own `retention_days` are left to
+/// [`delete_expired_queue_tasks`]. Signals and dependency edges go with the
+/// task through `ON DELETE CASCADE`.
+pub async fn delete_expired_tasks(
+    pool: &PgPool,
+    statuses: &[&str],
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<DeletedTasks, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let ids: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM tasks
+        WHERE status = ANY($1) AND updated_at < $2
+          AND NOT EXISTS (
+              SELECT 1 FROM queue_configs q
+              WHERE q.queue_name = tasks.queue_name AND q.retention_days IS NOT NULL
+          )
+        ORDER BY updated_at
+        LIMIT $3
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(statuses)
+    .bind(before)
+    .bind(limit)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let deleted = delete_tasks(&mut tx, &ids).await?;
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+/// [`delete_expired_tasks`] for queues with `queue_configs.retention_days`
+/// set: up to `limit` tasks in one of `statuses` last updated more than that
+/// many days ago.
+pub async fn delete_expired_queue_tasks(
+    pool: &PgPool,
+    statuses: &[&str],
+    limit: i64,
+) -> Result<DeletedTasks, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let ids: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT t.id FROM tasks t
+        JOIN queue_configs q ON q.queue_name = t.queue_name
+        WHERE q.retention_days IS NOT NULL
+          AND t.status = ANY($1)
+          AND t.updated_at < NOW() - make_interval(days => q.retention_days)
+        ORDER BY t.updated_at
+        LIMIT $2
+        FOR UPDATE OF t SKIP LOCKED
+        "#,
+    )
+    .bind(statuses)
+    .bind(limit)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let deleted = delete_tasks(&mut tx, &ids).await?;
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+async fn delete_tasks(
+    tx: &mut Transaction<'_, Postgres>,
+    ids: &[String],
+) -> Result<DeletedTasks, sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(DeletedTasks::default());
+    }
+
+    let task_logs = sqlx::query(
+        r#"
+        DELETE FROM task_logs
+        WHERE task_run_id IN (SELECT id FROM task_runs WHERE task_id = ANY($1))
+        "#,
+    )
+    .bind(ids)
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    let task_runs = sqlx::query("DELETE FROM task_runs WHERE task_id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+
+    let dead_letters = sqlx::query("DELETE FROM dead_letter_queue WHERE task_id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+
+    let tasks = sqlx::query("DELETE FROM tasks WHERE id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+
+    Ok(DeletedTasks {
+        tasks,
+        task_runs,
+        task_logs,
+        dead_letters,
+    })
+}
+
+/// Delete up to `limit` task log lines written before `before`.
+pub async fn delete_expired_task_logs(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM task_logs
+        WHERE id IN (SELECT id FROM task_logs WHERE created_at < $1 LIMIT $2)
+        "#,
+    )
+    .bind(before)
+    .bind(limit)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Delete up to `limit` worker log lines written before `before`.
+pub async fn delete_expired_worker_logs(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM worker_logs
+        WHERE id IN (SELECT id FROM worker_logs WHERE created_at < $1 LIMIT $2)
+        "#,
+    )
+    .bind(before)
+    .bind(limit)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::queries::tasks::{CreateTaskParams, TaskRow, insert_task};
+
+/// A recurring task: `queue_name`/`task_name`/`input` are created as a task
+/// each time `cron_expression` fires.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ScheduleRow {
+    pub id: String,
+    pub cron_expression: String,
+    pub queue_name: String,
+    pub task_name: String,
+    pub input: Option<serde_json::Value>,
+    pub priority: i32,
+    pub max_retries: i32,
+    pub timeout_seconds: i32,
+    /// The next firing not yet turned into a task
+    pub next_fire_at: DateTime<Utc>,
+    /// The firing behind the most recent task
+    pub last_fired_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ScheduleRow {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "cron_expression": self.cron_expression,
+            "queue_name": self.queue_name,
+            "task_name": self.task_name,
+            "input": self.input,
+            "priority": self.priority,
+            "max_retries": self.max_retries,
+            "timeout_seconds": self.timeout_seconds,
+            "next_fire_at": self.next_fire_at.to_rfc3339(),
+            "last_fired_at": self.last_fired_at.map(|t| t.to_rfc3339()),
+            "created_at": self.created_at.to_rfc3339(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateScheduleParams {
+    pub id: String,
+    pub cron_expression: String,
+    pub queue_name: String,
+    pub task_name: String,
+    pub input: Option<serde_json::Value>,
+    pub priority: i32,
+    pub max_retries: i32,
+    pub timeout_seconds: i32,
+    pub next_fire_at: DateTime<Utc>,
+}
+
+pub async fn create_schedule(
+    pool: &PgPool,
+    params: CreateScheduleParams,
+) -> Result<ScheduleRow, sqlx::Error> {
+    sqlx::query_as::<_, ScheduleRow>(
+        r#"
+        INSERT INTO schedules (id, cron_expression, queue_name, task_name, input, priority,
+                               max_retries, timeout_seconds, next_fire_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING *
+        "#,
+    )
+    .bind(&params.id)
+    .bind(&params.cron_expression)
+    .bind(&params.queue_name)
+    .bind(&params.task_name)
+    .bind(&params.input)
+    .bind(params.priority)
+    .bind(params.max_retries)
+    .bind(params.timeout_seconds)
+    .bind(params.next_fire_at)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_schedule(pool: &PgPool, id: &str) -> Result<Option<ScheduleRow>, sqlx::Error> {
+    sqlx::query_as::<_, ScheduleRow>("SELECT * FROM schedules WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn list_schedules(
+    pool: &PgPool,
+    queue_name: Option<&str>,
+) -> Result<Vec<ScheduleRow>, sqlx::Error> {
+    sqlx::query_as::<_, ScheduleRow>(
+        r#"
+        SELECT * FROM schedules
+        WHERE ($1::text IS NULL OR queue_name = $1)
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(queue_name)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn delete_schedule(pool: &PgPool, id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM schedules WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Schedules whose next firing is at or before `now`, earliest first.
+pub async fn list_due_schedules(
+    pool: &PgPool,
+    now: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<ScheduleRow>, sqlx::Error> {
+    sqlx::query_as::<_, ScheduleRow>(
+        r#"
+        SELECT * FROM schedules
+        WHERE next_fire_at <= $1
+        ORDER BY next_fire_at
+        LIMIT $2
+        "#,
+    )
+    .bind(now)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Turn the firing at `schedule.next_fire_at` into `task` and move the
+/// schedule on to `next_fire_at`, in one transaction. Returns `None` without
+/// creating the task when that firing was already taken, e.g. by a previous
+/// leader, or the schedule was deleted.
+pub async fn fire_schedule(
+    pool: &PgPool,
+    schedule: &ScheduleRow,
+    task: &CreateTaskParams,
+    next_fire_at: DateTime<Utc>,
+) -> Result<Option<TaskRow>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let advanced = sqlx::query(
+        r#"
+        UPDATE schedules
+        SET last_fired_at = next_fire_at, next_fire_at = $3
+        WHERE id = $1 AND next_fire_at = $2
+        "#,
+    )
+    .bind(&schedule.id)
+    .bind(schedule.next_fire_at)
+    .bind(next_fire_at)
+    .execute(&mut *tx)
+    .await?;
+    if advanced.rows_affected() == 0 {
+        return Ok(None);
+    }
+    let row = insert_task(&mut *tx, task).await?;
+    tx.commit().await?;
+    Ok(Some(row))
+}
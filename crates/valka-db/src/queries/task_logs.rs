@@ -53,6 +53,47 @@ pub async fn batch_insert_logs(
     Ok(result.rows_affected())
 }
 
+/// Like [`batch_insert_logs`], returning the inserted rows with their ids
+pub async fn batch_insert_logs_returning(
+    pool: &PgPool,
+    entries: &[InsertLogEntry],
+) -> Result<Vec<TaskLogRow>, sqlx::Error> {
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let task_run_ids: Vec<&str> = entries.iter().map(|e| e.task_run_id.as_str()).collect();
+    let timestamps: Vec<i64> = entries.iter().map(|e| e.timestamp_ms).collect();
+    let levels: Vec<&str> = entries.iter().map(|e| e.level.as_str()).collect();
+    let messages: Vec<&str> = entries.iter().map(|e| e.message.as_str()).collect();
+    let metadata: Vec<Option<serde_json::Value>> =
+        entries.iter().map(|e| e.metadata.clone()).collect();
+
+    let rows = sqlx::query_as::<_, TaskLogRow>(
+        r#"
+        INSERT INTO task_logs (task_run_id, timestamp_ms, level, message, metadata)
+        SELECT * FROM UNNEST($1::text[], $2::bigint[], $3::text[], $4::text[], $5::jsonb[])
+        RETURNING *
+        "#,
+    )
+    .bind(&task_run_ids)
+    .bind(&timestamps)
+    .bind(&levels)
+    .bind(&messages)
+    .bind(&metadata)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Id of the newest log of a task run
+pub async fn latest_log_id(pool: &PgPool, task_run_id: &str) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT MAX(id) FROM task_logs WHERE task_run_id = $1")
+        .bind(task_run_id)
+        .fetch_one(pool)
+        .await
+}
+
 /// Get logs for a task run
 pub async fn get_logs_for_run(
     pool: &PgPool,
@@ -15,6 +15,10 @@ pub struct TaskRunRow {
     pub started_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
     pub last_heartbeat: DateTime<Utc>,
+    pub progress: Option<f32>,
+    pub progress_message: Option<String>,
+    /// The worker's output was over `limits.max_output_bytes` and not stored
+    pub output_truncated: bool,
 }
 
 pub struct CreateTaskRunParams {
@@ -104,6 +108,29 @@ pub async fn update_heartbeat(
     Ok(result.rows_affected() > 0)
 }
 
+/// Store a RUNNING run's latest progress. Returns the task's queue, or None
+/// if the run has already finished.
+pub async fn update_progress(
+    pool: &PgPool,
+    run_id: &str,
+    progress: f32,
+    message: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        UPDATE task_runs SET progress = $2, progress_message = $3
+        FROM tasks
+        WHERE task_runs.id = $1 AND task_runs.status = 'RUNNING' AND tasks.id = task_runs.task_id
+        RETURNING tasks.queue_name
+        "#,
+    )
+    .bind(run_id)
+    .bind(progress)
+    .bind(message)
+    .fetch_optional(pool)
+    .await
+}
+
 /// Find expired leases for the reaper
 pub async fn find_expired_leases(pool: &PgPool) -> Result<Vec<TaskRunRow>, sqlx::Error> {
     let rows = sqlx::query_as::<_, TaskRunRow>(
@@ -144,6 +171,23 @@ pub async fn get_task_run(pool: &PgPool, run_id: &str) -> Result<Option<TaskRunR
     Ok(row)
 }
 
+/// The task's run that is still RUNNING, if any.
+pub async fn get_running_run(
+    pool: &PgPool,
+    task_id: &str,
+) -> Result<Option<TaskRunRow>, sqlx::Error> {
+    let row = sqlx::query_as::<_, TaskRunRow>(
+        r#"
+        SELECT * FROM task_runs WHERE task_id = $1 AND status = 'RUNNING'
+        ORDER BY attempt_number DESC LIMIT 1
+        "#,
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
 pub async fn get_runs_for_task(
     pool: &PgPool,
     task_id: &str,
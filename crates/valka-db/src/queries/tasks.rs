@@ -1,5 +1,10 @@
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
+use valka_core::Durability;
+use valka_core::labels::Labels;
+use valka_core::retry::{RetryBackoff, RetryPlan, RetryPolicy};
+
+use crate::queries::task_runs::{CreateTaskRunParams, TaskRunRow};
 
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct TaskRow {
@@ -20,8 +25,108 @@ pub struct TaskRow {
     pub updated_at: DateTime<Utc>,
     pub output: Option<serde_json::Value>,
     pub error_message: Option<String>,
+    pub deadline_at: Option<DateTime<Utc>>,
+    /// W3C traceparent of the span that created the task
+    pub traceparent: Option<String>,
+    /// "durable" or "ephemeral"
+    pub durability: String,
+    /// Ordering group on FIFO queues
+    pub fifo_key: Option<String>,
+    /// Webhook POSTed with the task when it reaches a terminal state
+    pub callback_url: Option<String>,
+    /// Delay its handler asked for before the next attempt, until the retry is scheduled
+    pub retry_after_ms: Option<i64>,
+    /// Backoff set at creation; see [`TaskRow::retry_backoff`]
+    pub retry_base_seconds: Option<i64>,
+    pub retry_max_seconds: Option<i64>,
+    pub retry_multiplier: Option<f64>,
+    pub retry_jitter: bool,
+    /// Labels a worker must carry to run it; see [`TaskRow::required_labels`]
+    pub required_labels: serde_json::Value,
+}
+
+impl TaskRow {
+    /// True once the task's deadline (if any) has passed
+    pub fn deadline_expired(&self, now: DateTime<Utc>) -> bool {
+        self.deadline_at.is_some_and(|d| d <= now)
+    }
+
+    /// When the task became dispatchable: its scheduled time if it had one,
+    /// otherwise its creation.
+    pub fn ready_at(&self) -> DateTime<Utc> {
+        self.scheduled_at
+            .map_or(self.created_at, |at| at.max(self.created_at))
+    }
+
+    /// The backoff this task was created with; unset fields defer to the
+    /// scheduler's policy.
+    pub fn retry_backoff(&self) -> RetryBackoff {
+        RetryBackoff {
+            base_seconds: self.retry_base_seconds.and_then(|s| u64::try_from(s).ok()),
+            max_seconds: self.retry_max_seconds.and_then(|s| u64::try_from(s).ok()),
+            multiplier: self.retry_multiplier,
+            jitter: self.retry_jitter,
+        }
+    }
+
+    /// Labels a worker must carry to be handed this task.
+    pub fn required_labels(&self) -> Labels {
+        serde_json::from_value(self.required_labels.clone()).unwrap_or_default()
+    }
+
+    /// The task as the REST API and webhooks present it.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "queue_name": self.queue_name,
+            "task_name": self.task_name,
+            "status": self.status,
+            "priority": self.priority,
+            "max_retries": self.max_retries,
+            "attempt_count": self.attempt_count,
+            "timeout_seconds": self.timeout_seconds,
+            "idempotency_key": self.idempotency_key,
+            "input": self.input,
+            "metadata": self.metadata,
+            "output": self.output,
+            "error_message": self.error_message,
+            "scheduled_at": self.scheduled_at.map(|t| t.to_rfc3339()),
+            "deadline_ms": self.deadline_at.map(|t| t.timestamp_millis()),
+            "deadline_expired": self.deadline_expired(Utc::now()),
+            "durability": self.durability,
+            "fifo_key": self.fifo_key,
+            "callback_url": self.callback_url,
+            "retry_backoff": self.retry_backoff(),
+            "required_labels": self.required_labels,
+            "created_at": self.created_at.to_rfc3339(),
+            "updated_at": self.updated_at.to_rfc3339(),
+        })
+    }
+
+    /// The remaining attempts of a RETRY task under `policy`; `None` for any
+    /// other status or once its attempts are used up.
+    pub fn retry_plan(&self, policy: &RetryPolicy, now: DateTime<Utc>) -> Option<RetryPlan> {
+        if self.status != "RETRY" {
+            return None;
+        }
+        // Not scheduled yet, but the handler's delay is what the scheduler will use
+        let next_attempt_at = self.scheduled_at.or_else(|| {
+            let delay = self
+                .retry_after_ms
+                .map(|ms| chrono::Duration::milliseconds(ms.clamp(0, policy.max_delay_ms())))?;
+            self.updated_at.checked_add_signed(delay)
+        });
+        policy.with_backoff(&self.retry_backoff()).plan(
+            self.attempt_count,
+            self.max_retries,
+            next_attempt_at,
+            self.deadline_at,
+            now,
+        )
+    }
 }
 
+#[derive(Debug, Clone)]
 pub struct CreateTaskParams {
     pub id: String,
     pub queue_name: String,
@@ -34,14 +139,77 @@ pub struct CreateTaskParams {
     pub idempotency_key: Option<String>,
     pub metadata: serde_json::Value,
     pub scheduled_at: Option<DateTime<Utc>>,
+    pub deadline_at: Option<DateTime<Utc>>,
+    pub traceparent: Option<String>,
+    pub durability: Durability,
+    pub fifo_key: Option<String>,
+    pub callback_url: Option<String>,
+    pub retry_backoff: RetryBackoff,
+    pub required_labels: Labels,
+}
+
+impl CreateTaskParams {
+    /// The row these params will produce, for answering before an ephemeral
+    /// task has been written.
+    pub fn unwritten_row(&self, status: &str, now: DateTime<Utc>) -> TaskRow {
+        TaskRow {
+            id: self.id.clone(),
+            queue_name: self.queue_name.clone(),
+            task_name: self.task_name.clone(),
+            partition_id: self.partition_id,
+            status: status.to_string(),
+            input: self.input.clone(),
+            priority: self.priority,
+            max_retries: self.max_retries,
+            attempt_count: 0,
+            timeout_seconds: self.timeout_seconds,
+            idempotency_key: self.idempotency_key.clone(),
+            metadata: self.metadata.clone(),
+            scheduled_at: self.scheduled_at,
+            created_at: now,
+            updated_at: now,
+            output: None,
+            error_message: None,
+            deadline_at: self.deadline_at,
+            traceparent: self.traceparent.clone(),
+            durability: self.durability.as_str().to_string(),
+            fifo_key: self.fifo_key.clone(),
+            callback_url: self.callback_url.clone(),
+            retry_after_ms: None,
+            retry_base_seconds: self.retry_backoff.base_seconds.map(backoff_column),
+            retry_max_seconds: self.retry_backoff.max_seconds.map(backoff_column),
+            retry_multiplier: self.retry_backoff.multiplier,
+            retry_jitter: self.retry_backoff.jitter,
+            required_labels: serde_json::json!(self.required_labels),
+        }
+    }
 }
 
+/// A backoff field for its BIGINT column, saturating rather than wrapping.
+pub fn backoff_column(seconds: u64) -> i64 {
+    i64::try_from(seconds).unwrap_or(i64::MAX)
+}
+
+/// Insert a task, registering its queue if this is the queue's first task.
 pub async fn create_task(pool: &PgPool, params: CreateTaskParams) -> Result<TaskRow, sqlx::Error> {
-    let row = sqlx::query_as::<_, TaskRow>(
+    insert_task(pool, &params).await
+}
+
+/// The insert behind [`create_task`], for callers writing the task as part of
+/// a larger transaction.
+pub(crate) async fn insert_task<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    params: &CreateTaskParams,
+) -> Result<TaskRow, sqlx::Error> {
+    sqlx::query_as::<_, TaskRow>(
         r#"
+        WITH registered AS (INSERT INTO queues (name) VALUES ($2) ON CONFLICT (name) DO NOTHING)
         INSERT INTO tasks (id, queue_name, task_name, partition_id, input, priority, max_retries,
-                          timeout_seconds, idempotency_key, metadata, scheduled_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                          timeout_seconds, idempotency_key, metadata, scheduled_at, deadline_at,
+                          traceparent, durability, fifo_key, callback_url, retry_base_seconds,
+                          retry_max_seconds, retry_multiplier, retry_jitter, required_labels)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+                $19, $20, $21)
         RETURNING *
         "#,
     )
@@ -56,10 +224,72 @@ pub async fn create_task(pool: &PgPool, params: CreateTaskParams) -> Result<Task
     .bind(&params.idempotency_key)
     .bind(&params.metadata)
     .bind(params.scheduled_at)
-    .fetch_one(pool)
-    .await?;
+    .bind(params.deadline_at)
+    .bind(&params.traceparent)
+    .bind(params.durability.as_str())
+    .bind(&params.fifo_key)
+    .bind(&params.callback_url)
+    .bind(params.retry_backoff.base_seconds.map(backoff_column))
+    .bind(params.retry_backoff.max_seconds.map(backoff_column))
+    .bind(params.retry_backoff.multiplier)
+    .bind(params.retry_backoff.jitter)
+    .bind(serde_json::json!(params.required_labels))
+    .fetch_one(executor)
+    .await
+}
 
-    Ok(row)
+/// Write an ephemeral task that was already assigned: the task as RUNNING on
+/// its first attempt, plus its run, in one transaction.
+pub async fn record_ephemeral_dispatch(
+    pool: &PgPool,
+    params: &CreateTaskParams,
+    run: &CreateTaskRunParams,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"
+        WITH registered AS (INSERT INTO queues (name) VALUES ($2) ON CONFLICT (name) DO NOTHING)
+        INSERT INTO tasks (id, queue_name, task_name, partition_id, input, priority, max_retries,
+                          timeout_seconds, metadata, deadline_at, traceparent, durability,
+                          callback_url, retry_base_seconds, retry_max_seconds, retry_multiplier,
+                          retry_jitter, required_labels, status, attempt_count)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+                'RUNNING', 1)
+        "#,
+    )
+    .bind(&params.id)
+    .bind(&params.queue_name)
+    .bind(&params.task_name)
+    .bind(params.partition_id)
+    .bind(&params.input)
+    .bind(params.priority)
+    .bind(params.max_retries)
+    .bind(params.timeout_seconds)
+    .bind(&params.metadata)
+    .bind(params.deadline_at)
+    .bind(&params.traceparent)
+    .bind(params.durability.as_str())
+    .bind(&params.callback_url)
+    .bind(params.retry_backoff.base_seconds.map(backoff_column))
+    .bind(params.retry_backoff.max_seconds.map(backoff_column))
+    .bind(params.retry_backoff.multiplier)
+    .bind(params.retry_backoff.jitter)
+    .bind(serde_json::json!(params.required_labels))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        r#"INSERT INTO task_runs (id, task_id, attempt_number, worker_id, assigned_node_id, lease_expires_at)
+           VALUES ($1, $2, $3, $4, $5, $6)"#,
+    )
+    .bind(&run.id)
+    .bind(&run.task_id)
+    .bind(run.attempt_number)
+    .bind(&run.worker_id)
+    .bind(&run.assigned_node_id)
+    .bind(run.lease_expires_at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
 }
 
 pub async fn get_task(pool: &PgPool, task_id: &str) -> Result<Option<TaskRow>, sqlx::Error> {
@@ -70,6 +300,25 @@ pub async fn get_task(pool: &PgPool, task_id: &str) -> Result<Option<TaskRow>, s
     Ok(row)
 }
 
+pub async fn get_task_by_idempotency_key(
+    pool: &PgPool,
+    idempotency_key: &str,
+) -> Result<Option<TaskRow>, sqlx::Error> {
+    let row = sqlx::query_as::<_, TaskRow>("SELECT * FROM tasks WHERE idempotency_key = $1")
+        .bind(idempotency_key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+/// True if `e` is an insert rejected because its idempotency key is taken.
+pub fn is_idempotency_conflict(e: &sqlx::Error) -> bool {
+    matches!(
+        e,
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some("idx_tasks_idempotency")
+    )
+}
+
 pub async fn list_tasks(
     pool: &PgPool,
     queue_name: Option<&str>,
@@ -95,6 +344,144 @@ pub async fn list_tasks(
     Ok(rows)
 }
 
+/// How [`search_tasks`] orders its results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaskOrder {
+    #[default]
+    CreatedDesc,
+    CreatedAsc,
+    /// Highest priority first, oldest first within a priority
+    Priority,
+}
+
+impl TaskOrder {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "created_desc" => Some(Self::CreatedDesc),
+            "created_asc" => Some(Self::CreatedAsc),
+            "priority" => Some(Self::Priority),
+            _ => None,
+        }
+    }
+
+    fn sql(&self) -> &'static str {
+        match self {
+            Self::CreatedDesc => " ORDER BY created_at DESC, id DESC",
+            Self::CreatedAsc => " ORDER BY created_at ASC, id ASC",
+            Self::Priority => " ORDER BY priority DESC, created_at ASC, id ASC",
+        }
+    }
+}
+
+/// Filters for [`search_tasks`]. Unset fields match every task.
+#[derive(Debug, Clone, Default)]
+pub struct TaskSearch {
+    pub queue_name: Option<String>,
+    /// Matches any of these; empty matches every status
+    pub statuses: Vec<String>,
+    pub task_name_prefix: Option<String>,
+    /// Inclusive
+    pub created_after: Option<DateTime<Utc>>,
+    /// Exclusive
+    pub created_before: Option<DateTime<Utc>>,
+    pub idempotency_key: Option<String>,
+    /// Case-insensitive substring of the error message
+    pub error_contains: Option<String>,
+    pub order: TaskOrder,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Escape `%`, `_` and `\` so user text matches literally in a LIKE pattern.
+fn escape_like(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+pub async fn search_tasks(pool: &PgPool, search: &TaskSearch) -> Result<Vec<TaskRow>, sqlx::Error> {
+    let mut query = sqlx::QueryBuilder::<sqlx::Postgres>::new("SELECT * FROM tasks WHERE TRUE");
+    if let Some(queue_name) = &search.queue_name {
+        query.push(" AND queue_name = ").push_bind(queue_name);
+    }
+    if !search.statuses.is_empty() {
+        query
+            .push(" AND status = ANY(")
+            .push_bind(&search.statuses)
+            .push(")");
+    }
+    if let Some(prefix) = &search.task_name_prefix {
+        query
+            .push(" AND task_name LIKE ")
+            .push_bind(format!("{}%", escape_like(prefix)));
+    }
+    if let Some(after) = search.created_after {
+        query.push(" AND created_at >= ").push_bind(after);
+    }
+    if let Some(before) = search.created_before {
+        query.push(" AND created_at < ").push_bind(before);
+    }
+    if let Some(key) = &search.idempotency_key {
+        query.push(" AND idempotency_key = ").push_bind(key);
+    }
+    if let Some(text) = &search.error_contains {
+        query
+            .push(" AND error_message ILIKE ")
+            .push_bind(format!("%{}%", escape_like(text)));
+    }
+    query.push(search.order.sql());
+    query
+        .push(" LIMIT ")
+        .push_bind(search.limit)
+        .push(" OFFSET ")
+        .push_bind(search.offset);
+    query.build_query_as::<TaskRow>().fetch_all(pool).await
+}
+
+/// Number of tasks in one queue with one status.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TaskCountRow {
+    pub queue_name: String,
+    pub status: String,
+    pub count: i64,
+}
+
+/// Task counts for every (queue, status) pair that has tasks.
+pub async fn count_tasks_grouped(pool: &PgPool) -> Result<Vec<TaskCountRow>, sqlx::Error> {
+    sqlx::query_as::<_, TaskCountRow>(
+        r#"
+        SELECT queue_name, status, COUNT(*) AS count
+        FROM tasks
+        GROUP BY queue_name, status
+        ORDER BY queue_name, status
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Tasks created, and tasks completed, since `since`.
+pub async fn count_recent_tasks(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<(i64, i64), sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM tasks WHERE created_at >= $1),
+            (SELECT COUNT(*) FROM tasks WHERE status = 'COMPLETED' AND updated_at >= $1)
+        "#,
+    )
+    .bind(since)
+    .fetch_one(pool)
+    .await
+}
+
 pub async fn update_task_status(
     pool: &PgPool,
     task_id: &str,
@@ -144,7 +531,14 @@ pub async fn dequeue_tasks(
         WHERE id IN (
             SELECT id FROM tasks
             WHERE queue_name = $1 AND partition_id = $2 AND status = 'PENDING'
-              AND (scheduled_at IS NULL OR scheduled_at <= NOW())
+              AND (scheduled_at IS NULL OR (scheduled_at <= NOW() AND NOT EXISTS (
+                  SELECT 1 FROM queue_configs qc
+                  WHERE qc.queue_name = $1
+                    AND (qc.scheduled_hold OR qc.release_max_per_tick IS NOT NULL)
+              )))
+              AND NOT EXISTS (
+                  SELECT 1 FROM queue_configs qc WHERE qc.queue_name = $1 AND qc.paused
+              )
             ORDER BY priority DESC, created_at ASC
             LIMIT $3
             FOR UPDATE SKIP LOCKED
@@ -160,12 +554,74 @@ pub async fn dequeue_tasks(
     Ok(rows)
 }
 
-/// Cancel a task (only if PENDING or RETRY)
+/// Holds for a task `t` that has a FIFO key while another task of its key is in
+/// flight, waiting on a retry, or still ahead of it in PENDING.
+const FIFO_KEY_BLOCKED: &str = r#"
+    EXISTS (
+        SELECT 1 FROM tasks o
+        WHERE o.queue_name = t.queue_name AND o.fifo_key = t.fifo_key AND o.id <> t.id
+          AND (o.status IN ('DISPATCHING', 'RUNNING', 'RETRY')
+               OR (o.status = 'PENDING' AND (o.created_at, o.id) < (t.created_at, t.id)))
+    )"#;
+
+/// [`dequeue_tasks`] for FIFO queues: a task with a fifo_key is only taken
+/// when it is the next one of its key and nothing else of that key is in flight.
+pub async fn dequeue_fifo_tasks(
+    pool: &PgPool,
+    queue_name: &str,
+    partition_id: i32,
+    batch_size: i64,
+) -> Result<Vec<TaskRow>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        UPDATE tasks SET status = 'DISPATCHING', updated_at = NOW()
+        WHERE id IN (
+            SELECT t.id FROM tasks t
+            WHERE t.queue_name = $1 AND t.partition_id = $2 AND t.status = 'PENDING'
+              AND (t.scheduled_at IS NULL OR (t.scheduled_at <= NOW() AND NOT EXISTS (
+                  SELECT 1 FROM queue_configs qc
+                  WHERE qc.queue_name = $1
+                    AND (qc.scheduled_hold OR qc.release_max_per_tick IS NOT NULL)
+              )))
+              AND NOT EXISTS (
+                  SELECT 1 FROM queue_configs qc WHERE qc.queue_name = $1 AND qc.paused
+              )
+              AND (t.fifo_key IS NULL OR NOT {FIFO_KEY_BLOCKED})
+            ORDER BY t.priority DESC, t.created_at ASC
+            LIMIT $3
+            FOR UPDATE OF t SKIP LOCKED
+        )
+        RETURNING *
+        "#
+    );
+    let rows = sqlx::query_as::<_, TaskRow>(&sql)
+        .bind(queue_name)
+        .bind(partition_id)
+        .bind(batch_size)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+/// Move one PENDING FIFO task to DISPATCHING if it is next in line for its
+/// key. Returns false if it has to wait.
+pub async fn claim_fifo_task(pool: &PgPool, task_id: &str) -> Result<bool, sqlx::Error> {
+    let sql = format!(
+        r#"
+        UPDATE tasks t SET status = 'DISPATCHING', updated_at = NOW()
+        WHERE t.id = $1 AND t.status = 'PENDING' AND NOT {FIFO_KEY_BLOCKED}
+        "#
+    );
+    let result = sqlx::query(&sql).bind(task_id).execute(pool).await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Cancel a task (only if PENDING, RETRY or WAITING)
 pub async fn cancel_task(pool: &PgPool, task_id: &str) -> Result<Option<TaskRow>, sqlx::Error> {
     let row = sqlx::query_as::<_, TaskRow>(
         r#"
         UPDATE tasks SET status = 'CANCELLED', updated_at = NOW()
-        WHERE id = $1 AND status IN ('PENDING', 'RETRY')
+        WHERE id = $1 AND status IN ('PENDING', 'RETRY', 'WAITING')
         RETURNING *
         "#,
     )
@@ -183,7 +639,8 @@ pub async fn schedule_retry(
 ) -> Result<Option<TaskRow>, sqlx::Error> {
     let row = sqlx::query_as::<_, TaskRow>(
         r#"
-        UPDATE tasks SET status = 'RETRY', scheduled_at = $2, updated_at = NOW()
+        UPDATE tasks SET status = 'RETRY', scheduled_at = $2, retry_after_ms = NULL,
+            updated_at = NOW()
         WHERE id = $1
         RETURNING *
         "#,
@@ -213,12 +670,17 @@ pub async fn move_to_dead_letter(
     Ok(row)
 }
 
-/// Promote RETRY tasks whose scheduled_at has passed back to PENDING
+/// Promote RETRY tasks whose scheduled_at has passed back to PENDING.
+/// Queues that are held or ramping a release are skipped.
 pub async fn promote_delayed_tasks(pool: &PgPool) -> Result<Vec<TaskRow>, sqlx::Error> {
     let rows = sqlx::query_as::<_, TaskRow>(
         r#"
         UPDATE tasks SET status = 'PENDING', scheduled_at = NULL, updated_at = NOW()
         WHERE status = 'RETRY' AND scheduled_at <= NOW()
+          AND queue_name NOT IN (
+              SELECT queue_name FROM queue_configs
+              WHERE scheduled_hold OR release_max_per_tick IS NOT NULL
+          )
         RETURNING *
         "#,
     )
@@ -227,6 +689,48 @@ pub async fn promote_delayed_tasks(pool: &PgPool) -> Result<Vec<TaskRow>, sqlx::
     Ok(rows)
 }
 
+/// Release up to `limit` due scheduled or RETRY tasks of one queue, oldest
+/// scheduled_at first, by making them immediately dispatchable.
+pub async fn promote_scheduled_batch(
+    pool: &PgPool,
+    queue_name: &str,
+    limit: i64,
+) -> Result<Vec<TaskRow>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks SET status = 'PENDING', scheduled_at = NULL, updated_at = NOW()
+        WHERE id IN (
+            SELECT id FROM tasks
+            WHERE queue_name = $1 AND status IN ('PENDING', 'RETRY')
+              AND scheduled_at <= NOW()
+            ORDER BY scheduled_at ASC, id ASC
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(queue_name)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Count due scheduled or RETRY tasks of a queue that have not been released yet
+pub async fn count_past_due_scheduled(pool: &PgPool, queue_name: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM tasks
+        WHERE queue_name = $1 AND status IN ('PENDING', 'RETRY')
+          AND scheduled_at <= NOW()
+        "#,
+    )
+    .bind(queue_name)
+    .fetch_one(pool)
+    .await
+}
+
 /// Update task with completed output
 pub async fn complete_task(
     pool: &PgPool,
@@ -267,12 +771,50 @@ pub async fn fail_task(
     Ok(row)
 }
 
+/// Fail a not-yet-running task whose deadline has passed. No-op once it has started or finished.
+pub async fn expire_task(
+    pool: &PgPool,
+    task_id: &str,
+    error_message: &str,
+) -> Result<Option<TaskRow>, sqlx::Error> {
+    let row = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks SET status = 'FAILED', error_message = $2, updated_at = NOW()
+        WHERE id = $1 AND status IN ('PENDING', 'DISPATCHING', 'RETRY')
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(error_message)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+/// Fail every PENDING/RETRY/WAITING task whose deadline has passed
+pub async fn expire_past_deadline_tasks(pool: &PgPool) -> Result<Vec<TaskRow>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks SET status = 'FAILED',
+            error_message = CASE WHEN status = 'RETRY' THEN $2 ELSE $1 END,
+            updated_at = NOW()
+        WHERE status IN ('PENDING', 'RETRY', 'WAITING') AND deadline_at <= NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(valka_core::DEADLINE_EXCEEDED_BEFORE_DISPATCH)
+    .bind(valka_core::DEADLINE_EXCEEDED_BEFORE_RETRY)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
 /// Cancel a task including RUNNING tasks (for forwarding cancellation to workers)
 pub async fn cancel_task_any(pool: &PgPool, task_id: &str) -> Result<Option<TaskRow>, sqlx::Error> {
     let row = sqlx::query_as::<_, TaskRow>(
         r#"
         UPDATE tasks SET status = 'CANCELLED', updated_at = NOW()
-        WHERE id = $1 AND status IN ('PENDING', 'RETRY', 'RUNNING', 'DISPATCHING')
+        WHERE id = $1 AND status IN ('PENDING', 'RETRY', 'WAITING', 'RUNNING', 'DISPATCHING')
         RETURNING *
         "#,
     )
@@ -282,14 +824,86 @@ pub async fn cancel_task_any(pool: &PgPool, task_id: &str) -> Result<Option<Task
     Ok(row)
 }
 
-/// Count pending tasks per queue (for metrics)
-pub async fn count_pending_by_queue(pool: &PgPool) -> Result<Vec<(String, i64)>, sqlx::Error> {
-    let rows: Vec<(String, i64)> = sqlx::query_as(
-        "SELECT queue_name, COUNT(*) as count FROM tasks WHERE status = 'PENDING' GROUP BY queue_name",
+/// Take a RUNNING task back from its worker: its open run is closed as
+/// ABANDONED with `reason` and the task goes to PENDING (`immediate`) or
+/// RETRY, in one transaction. None if the task has no RUNNING run.
+pub async fn reclaim_running_task(
+    pool: &PgPool,
+    task_id: &str,
+    reason: &str,
+    immediate: bool,
+) -> Result<Option<(TaskRow, TaskRunRow)>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let run = sqlx::query_as::<_, TaskRunRow>(
+        r#"
+        UPDATE task_runs SET status = 'ABANDONED', error_message = $2, completed_at = NOW()
+        WHERE task_id = $1 AND status = 'RUNNING'
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(reason)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(run) = run else {
+        return Ok(None);
+    };
+
+    let task = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks SET status = $2, scheduled_at = NULL, updated_at = NOW()
+        WHERE id = $1 AND status = 'RUNNING'
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(if immediate { "PENDING" } else { "RETRY" })
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(task) = task else {
+        return Ok(None);
+    };
+
+    tx.commit().await?;
+    Ok(Some((task, run)))
+}
+
+/// One queue's tasks waiting to be dispatched, split by why they wait.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct QueueBacklog {
+    pub queue_name: String,
+    /// PENDING and ready to run
+    pub pending: i64,
+    /// RETRY, waiting for their next attempt
+    pub retry: i64,
+    /// PENDING but scheduled for later
+    pub scheduled: i64,
+    /// When the longest-waiting task ready to run became ready: its
+    /// scheduled_at, or created_at if it had none. PENDING and due RETRY
+    /// tasks count; None when no task is ready.
+    pub oldest_ready_at: Option<DateTime<Utc>>,
+}
+
+/// Backlog of every queue with PENDING or RETRY tasks (for metrics)
+pub async fn queue_backlog(pool: &PgPool) -> Result<Vec<QueueBacklog>, sqlx::Error> {
+    sqlx::query_as::<_, QueueBacklog>(
+        r#"
+        SELECT queue_name,
+               COUNT(*) FILTER (
+                   WHERE status = 'PENDING' AND (scheduled_at IS NULL OR scheduled_at <= NOW())
+               ) AS pending,
+               COUNT(*) FILTER (WHERE status = 'RETRY') AS retry,
+               COUNT(*) FILTER (WHERE status = 'PENDING' AND scheduled_at > NOW()) AS scheduled,
+               MIN(COALESCE(scheduled_at, created_at)) FILTER (
+                   WHERE scheduled_at IS NULL OR scheduled_at <= NOW()
+               ) AS oldest_ready_at
+        FROM tasks
+        WHERE status IN ('PENDING', 'RETRY')
+        GROUP BY queue_name
+        "#,
     )
     .fetch_all(pool)
-    .await?;
-    Ok(rows)
+    .await
 }
 
 /// List workers from the workers table
@@ -0,0 +1,231 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// Usage of one queue on one (UTC) day.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct UsageDailyRow {
+    pub date: NaiveDate,
+    pub queue_name: String,
+    /// Queue name up to the first `.`
+    pub namespace: String,
+    pub owner_team: Option<String>,
+    pub tasks_created: i64,
+    /// Runs that completed successfully
+    pub tasks_completed: i64,
+    /// Runs that failed (including expired leases)
+    pub tasks_failed: i64,
+    /// Wall-clock time of finished runs
+    pub total_execution_ms: i64,
+    /// Bytes of task log messages
+    pub total_log_bytes: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Usage totals per day for one group key.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct UsageSummaryRow {
+    pub date: NaiveDate,
+    /// Queue name, namespace or owner team, depending on the grouping
+    pub key: Option<String>,
+    pub tasks_created: i64,
+    pub tasks_completed: i64,
+    pub tasks_failed: i64,
+    pub total_execution_ms: i64,
+    pub total_log_bytes: i64,
+}
+
+/// How usage is grouped within each day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UsageGroupBy {
+    #[default]
+    Queue,
+    Namespace,
+    OwnerTeam,
+}
+
+impl UsageGroupBy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "queue" => Some(Self::Queue),
+            "namespace" => Some(Self::Namespace),
+            "owner_team" => Some(Self::OwnerTeam),
+            _ => None,
+        }
+    }
+
+    /// The usage_daily column holding the group key.
+    pub fn column(self) -> &'static str {
+        match self {
+            Self::Queue => "queue_name",
+            Self::Namespace => "namespace",
+            Self::OwnerTeam => "owner_team",
+        }
+    }
+}
+
+/// Result of one incremental rollup.
+#[derive(Debug, Clone)]
+pub struct UsageRollup {
+    /// The window `[from, to)` folded in; empty if `to` was not past the mark
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub rows_upserted: u64,
+}
+
+/// Activity in `[$1, $2)` per day and queue, added onto usage_daily.
+const FOLD_WINDOW: &str = r#"
+    WITH events AS (
+        SELECT queue_name, (created_at AT TIME ZONE 'UTC')::date AS day,
+               1 AS created, 0 AS completed, 0 AS failed,
+               0::BIGINT AS execution_ms, 0::BIGINT AS log_bytes
+        FROM tasks
+        WHERE created_at >= $1 AND created_at < $2
+        UNION ALL
+        SELECT t.queue_name, (r.completed_at AT TIME ZONE 'UTC')::date,
+               0, (r.status = 'COMPLETED')::INT, (r.status = 'FAILED')::INT,
+               (EXTRACT(EPOCH FROM (r.completed_at - r.started_at)) * 1000)::BIGINT, 0
+        FROM task_runs r JOIN tasks t ON t.id = r.task_id
+        WHERE r.completed_at >= $1 AND r.completed_at < $2
+        UNION ALL
+        SELECT t.queue_name, (l.created_at AT TIME ZONE 'UTC')::date,
+               0, 0, 0, 0, octet_length(l.message)::BIGINT
+        FROM task_logs l
+        JOIN task_runs r ON r.id = l.task_run_id
+        JOIN tasks t ON t.id = r.task_id
+        WHERE l.created_at >= $1 AND l.created_at < $2
+    )
+    INSERT INTO usage_daily (
+        date, queue_name, namespace, owner_team, tasks_created, tasks_completed,
+        tasks_failed, total_execution_ms, total_log_bytes
+    )
+    SELECT e.day, e.queue_name, split_part(e.queue_name, '.', 1), qc.owner_team,
+           SUM(e.created), SUM(e.completed), SUM(e.failed),
+           SUM(e.execution_ms)::BIGINT, SUM(e.log_bytes)::BIGINT
+    FROM events e
+    LEFT JOIN queue_configs qc ON qc.queue_name = e.queue_name
+    GROUP BY e.day, e.queue_name, qc.owner_team
+    ON CONFLICT (date, queue_name) DO UPDATE SET
+        owner_team = EXCLUDED.owner_team,
+        tasks_created = usage_daily.tasks_created + EXCLUDED.tasks_created,
+        tasks_completed = usage_daily.tasks_completed + EXCLUDED.tasks_completed,
+        tasks_failed = usage_daily.tasks_failed + EXCLUDED.tasks_failed,
+        total_execution_ms = usage_daily.total_execution_ms + EXCLUDED.total_execution_ms,
+        total_log_bytes = usage_daily.total_log_bytes + EXCLUDED.total_log_bytes,
+        updated_at = NOW()
+"#;
+
+/// Lock the rollup state and return the high-water mark.
+async fn lock_high_water_mark(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<DateTime<Utc>, sqlx::Error> {
+    let (mark,): (DateTime<Utc>,) =
+        sqlx::query_as("SELECT high_water_mark FROM usage_rollup_state FOR UPDATE")
+            .fetch_one(&mut **tx)
+            .await?;
+    Ok(mark)
+}
+
+pub async fn get_high_water_mark(pool: &PgPool) -> Result<DateTime<Utc>, sqlx::Error> {
+    let (mark,): (DateTime<Utc>,) =
+        sqlx::query_as("SELECT high_water_mark FROM usage_rollup_state")
+            .fetch_one(pool)
+            .await?;
+    Ok(mark)
+}
+
+/// Fold activity between the high-water mark and `until` into usage_daily and
+/// advance the mark, in one transaction. Re-running with the same `until` is a
+/// no-op.
+pub async fn rollup_usage(pool: &PgPool, until: DateTime<Utc>) -> Result<UsageRollup, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let from = lock_high_water_mark(&mut tx).await?;
+    if until <= from {
+        return Ok(UsageRollup {
+            from,
+            to: from,
+            rows_upserted: 0,
+        });
+    }
+
+    let result = sqlx::query(FOLD_WINDOW)
+        .bind(from)
+        .bind(until)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE usage_rollup_state SET high_water_mark = $1")
+        .bind(until)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(UsageRollup {
+        from,
+        to: until,
+        rows_upserted: result.rows_affected(),
+    })
+}
+
+/// Recompute the days `from..=to` from tasks, runs and logs, up to the
+/// high-water mark (later activity is left to the rollup). Returns the number
+/// of usage rows written.
+pub async fn backfill_usage(
+    pool: &PgPool,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mark = lock_high_water_mark(&mut tx).await?;
+
+    sqlx::query("DELETE FROM usage_daily WHERE date >= $1 AND date <= $2")
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?;
+
+    let start = from.and_time(chrono::NaiveTime::MIN).and_utc();
+    let end = to
+        .succ_opt()
+        .map_or(mark, |d| d.and_time(chrono::NaiveTime::MIN).and_utc())
+        .min(mark);
+    let mut written = 0;
+    if start < end {
+        written = sqlx::query(FOLD_WINDOW)
+            .bind(start)
+            .bind(end)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+
+    tx.commit().await?;
+    Ok(written)
+}
+
+/// Usage totals for the days `from..=to`, grouped per day by `group_by`.
+pub async fn list_usage(
+    pool: &PgPool,
+    from: NaiveDate,
+    to: NaiveDate,
+    group_by: UsageGroupBy,
+) -> Result<Vec<UsageSummaryRow>, sqlx::Error> {
+    let column = group_by.column();
+    let query = format!(
+        r#"
+        SELECT date, {column} AS key,
+               SUM(tasks_created)::BIGINT AS tasks_created,
+               SUM(tasks_completed)::BIGINT AS tasks_completed,
+               SUM(tasks_failed)::BIGINT AS tasks_failed,
+               SUM(total_execution_ms)::BIGINT AS total_execution_ms,
+               SUM(total_log_bytes)::BIGINT AS total_log_bytes
+        FROM usage_daily
+        WHERE date >= $1 AND date <= $2
+        GROUP BY date, {column}
+        ORDER BY date, {column} NULLS LAST
+        "#
+    );
+    sqlx::query_as::<_, UsageSummaryRow>(&query)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+}
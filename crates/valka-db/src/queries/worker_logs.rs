@@ -0,0 +1,90 @@
+use sqlx::PgPool;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WorkerLogRow {
+    pub id: i64,
+    pub worker_id: String,
+    pub timestamp_ms: i64,
+    pub level: String,
+    pub message: String,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub struct InsertWorkerLogEntry {
+    pub worker_id: String,
+    pub timestamp_ms: i64,
+    pub level: String,
+    pub message: String,
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Filters for querying worker logs
+#[derive(Debug, Default)]
+pub struct WorkerLogFilter {
+    /// Levels to include; empty means all
+    pub levels: Vec<String>,
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    pub limit: i64,
+}
+
+/// Batch insert worker log entries
+pub async fn batch_insert_worker_logs(
+    pool: &PgPool,
+    entries: &[InsertWorkerLogEntry],
+) -> Result<u64, sqlx::Error> {
+    if entries.is_empty() {
+        return Ok(0);
+    }
+
+    let worker_ids: Vec<&str> = entries.iter().map(|e| e.worker_id.as_str()).collect();
+    let timestamps: Vec<i64> = entries.iter().map(|e| e.timestamp_ms).collect();
+    let levels: Vec<&str> = entries.iter().map(|e| e.level.as_str()).collect();
+    let messages: Vec<&str> = entries.iter().map(|e| e.message.as_str()).collect();
+    let metadata: Vec<Option<serde_json::Value>> =
+        entries.iter().map(|e| e.metadata.clone()).collect();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO worker_logs (worker_id, timestamp_ms, level, message, metadata)
+        SELECT * FROM UNNEST($1::text[], $2::bigint[], $3::text[], $4::text[], $5::jsonb[])
+        "#,
+    )
+    .bind(&worker_ids)
+    .bind(&timestamps)
+    .bind(&levels)
+    .bind(&messages)
+    .bind(&metadata)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Get logs for a worker, newest first
+pub async fn get_worker_logs(
+    pool: &PgPool,
+    worker_id: &str,
+    filter: &WorkerLogFilter,
+) -> Result<Vec<WorkerLogRow>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, WorkerLogRow>(
+        r#"
+        SELECT * FROM worker_logs
+        WHERE worker_id = $1
+          AND (cardinality($2::text[]) = 0 OR level = ANY($2))
+          AND ($3::bigint IS NULL OR timestamp_ms >= $3)
+          AND ($4::bigint IS NULL OR timestamp_ms < $4)
+        ORDER BY timestamp_ms DESC, id DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(worker_id)
+    .bind(&filter.levels)
+    .bind(filter.from_ms)
+    .bind(filter.to_ms)
+    .bind(filter.limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
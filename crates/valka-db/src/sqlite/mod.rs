@@ -0,0 +1,50 @@
+//! SQLite storage for local development (`sqlite` feature).
+//!
+//! Not for production: there is no row-level locking, so claims serialize on
+//! SQLite's database lock, and clustering is unsupported. The schema in
+//! `migrations_sqlite/` mirrors the Postgres migrations that share its
+//! version numbers (JSONB and TIMESTAMPTZ columns are stored as TEXT); tables
+//! outside the single-node task lifecycle are not ported.
+
+pub mod queries;
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use tracing::info;
+
+/// Connect to a SQLite database, e.g. `sqlite://valka-dev.db` or `sqlite::memory:`.
+///
+/// On-disk files are created if missing. An in-memory database lives only as
+/// long as its single connection, so the pool is pinned to one connection.
+pub async fn create_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+    let in_memory = database_url.contains(":memory:");
+    let mut options = SqliteConnectOptions::from_str(database_url)?
+        .create_if_missing(true)
+        .foreign_keys(true)
+        .busy_timeout(Duration::from_secs(5));
+    if !in_memory {
+        options = options.journal_mode(SqliteJournalMode::Wal);
+    }
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(if in_memory { 1 } else { 4 })
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(options)
+        .await?;
+
+    info!(database_url, "SQLite pool created (development mode)");
+    Ok(pool)
+}
+
+/// Apply the SQLite schema.
+pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::migrate!("./migrations_sqlite")
+        .run(pool)
+        .await
+        .map_err(|e| -> sqlx::Error { e.into() })?;
+    info!("SQLite migrations completed");
+    Ok(())
+}
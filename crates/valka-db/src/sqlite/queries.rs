@@ -0,0 +1,454 @@
+//! SQLite variants of the queries on the single-node task lifecycle.
+//!
+//! Timestamps are compared through `julianday()` because bound chrono values
+//! and `CURRENT_TIMESTAMP` defaults use different text layouts.
+
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+use crate::queries::dead_letter::DeadLetterRow;
+use crate::queries::task_logs::{InsertLogEntry, TaskLogRow};
+use crate::queries::task_runs::TaskRunRow;
+use crate::queries::tasks::{CreateTaskParams, TaskCountRow, TaskRow, backoff_column};
+
+pub async fn create_task(
+    pool: &SqlitePool,
+    params: CreateTaskParams,
+) -> Result<TaskRow, sqlx::Error> {
+    sqlx::query_as::<_, TaskRow>(
+        r#"
+        INSERT INTO tasks (id, queue_name, task_name, partition_id, input, priority, max_retries,
+                          timeout_seconds, idempotency_key, metadata, scheduled_at, deadline_at,
+                          traceparent, durability, fifo_key, callback_url, retry_base_seconds,
+                          retry_max_seconds, retry_multiplier, retry_jitter, required_labels)
+        VALUES (?1, ?2, ?3, ?4, json(?5), ?6, ?7, ?8, ?9, json(?10), ?11, ?12, ?13, ?14, ?15, ?16,
+                ?17, ?18, ?19, ?20, json(?21))
+        RETURNING *
+        "#,
+    )
+    .bind(&params.id)
+    .bind(&params.queue_name)
+    .bind(&params.task_name)
+    .bind(params.partition_id)
+    .bind(&params.input)
+    .bind(params.priority)
+    .bind(params.max_retries)
+    .bind(params.timeout_seconds)
+    .bind(&params.idempotency_key)
+    .bind(&params.metadata)
+    .bind(params.scheduled_at)
+    .bind(params.deadline_at)
+    .bind(&params.traceparent)
+    .bind(params.durability.as_str())
+    .bind(&params.fifo_key)
+    .bind(&params.callback_url)
+    .bind(params.retry_backoff.base_seconds.map(backoff_column))
+    .bind(params.retry_backoff.max_seconds.map(backoff_column))
+    .bind(params.retry_backoff.multiplier)
+    .bind(params.retry_backoff.jitter)
+    .bind(serde_json::json!(params.required_labels))
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_task(pool: &SqlitePool, task_id: &str) -> Result<Option<TaskRow>, sqlx::Error> {
+    sqlx::query_as::<_, TaskRow>("SELECT * FROM tasks WHERE id = ?1")
+        .bind(task_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn list_tasks(
+    pool: &SqlitePool,
+    queue_name: Option<&str>,
+    status: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TaskRow>, sqlx::Error> {
+    sqlx::query_as::<_, TaskRow>(
+        r#"
+        SELECT * FROM tasks
+        WHERE (?1 IS NULL OR queue_name = ?1)
+          AND (?2 IS NULL OR status = ?2)
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?3 OFFSET ?4
+        "#,
+    )
+    .bind(queue_name)
+    .bind(status)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_tasks_grouped(pool: &SqlitePool) -> Result<Vec<TaskCountRow>, sqlx::Error> {
+    sqlx::query_as::<_, TaskCountRow>(
+        r#"
+        SELECT queue_name, status, COUNT(*) AS count
+        FROM tasks
+        GROUP BY queue_name, status
+        ORDER BY queue_name, status
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Tasks created, and tasks completed, since `since`.
+pub async fn count_recent_tasks(
+    pool: &SqlitePool,
+    since: DateTime<Utc>,
+) -> Result<(i64, i64), sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM tasks WHERE julianday(created_at) >= julianday(?1)),
+            (SELECT COUNT(*) FROM tasks
+             WHERE status = 'COMPLETED' AND julianday(updated_at) >= julianday(?1))
+        "#,
+    )
+    .bind(since)
+    .fetch_one(pool)
+    .await
+}
+
+/// Put tasks left DISPATCHING or RUNNING by a previous process back to
+/// PENDING and close their runs. With a single node, nothing else can be
+/// working on them.
+pub async fn recover_orphaned_tasks(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE task_runs SET status = 'FAILED', error_message = 'Server restarted', \
+         completed_at = CURRENT_TIMESTAMP WHERE status = 'RUNNING'",
+    )
+    .execute(&mut *tx)
+    .await?;
+    let result = sqlx::query(
+        "UPDATE tasks SET status = 'PENDING', updated_at = CURRENT_TIMESTAMP \
+         WHERE status IN ('DISPATCHING', 'RUNNING')",
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+/// Claim a batch of PENDING tasks for dispatch.
+///
+/// Stands in for `FOR UPDATE SKIP LOCKED`: the select and the status flip run
+/// as one UPDATE inside a transaction, so concurrent claimers serialize on
+/// SQLite's write lock and never see the same row as PENDING.
+pub async fn claim_tasks(
+    pool: &SqlitePool,
+    queue_name: &str,
+    partition_id: i32,
+    batch_size: i64,
+) -> Result<Vec<TaskRow>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut rows = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks SET status = 'DISPATCHING', updated_at = CURRENT_TIMESTAMP
+        WHERE id IN (
+            SELECT id FROM tasks
+            WHERE queue_name = ?1 AND partition_id = ?2 AND status = 'PENDING'
+              AND (scheduled_at IS NULL OR julianday(scheduled_at) <= julianday('now'))
+            ORDER BY priority DESC, created_at ASC, id ASC
+            LIMIT ?3
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(queue_name)
+    .bind(partition_id)
+    .bind(batch_size)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    // RETURNING order is unspecified; hand tasks out in dequeue order
+    rows.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then(a.created_at.cmp(&b.created_at))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(rows)
+}
+
+/// Start an attempt: bump attempt_count, mark the task RUNNING and create its run.
+pub async fn start_task_run(
+    pool: &SqlitePool,
+    task_id: &str,
+    run_id: &str,
+    worker_id: &str,
+    node_id: &str,
+    lease_expires_at: DateTime<Utc>,
+) -> Result<TaskRunRow, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let attempt_number: i32 = sqlx::query_scalar(
+        r#"
+        UPDATE tasks SET attempt_count = attempt_count + 1, status = 'RUNNING',
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?1
+        RETURNING attempt_count
+        "#,
+    )
+    .bind(task_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let run = sqlx::query_as::<_, TaskRunRow>(
+        r#"
+        INSERT INTO task_runs (id, task_id, attempt_number, worker_id, assigned_node_id, lease_expires_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        RETURNING *
+        "#,
+    )
+    .bind(run_id)
+    .bind(task_id)
+    .bind(attempt_number)
+    .bind(worker_id)
+    .bind(node_id)
+    .bind(lease_expires_at)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(run)
+}
+
+/// Return a claimed task that never reached a worker to PENDING.
+pub async fn release_task(pool: &SqlitePool, task_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE tasks SET status = 'PENDING', updated_at = CURRENT_TIMESTAMP \
+         WHERE id = ?1 AND status = 'DISPATCHING'",
+    )
+    .bind(task_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Close a run its worker handed back or dropped, and return its task to
+/// PENDING. The attempt stays counted.
+pub async fn release_task_run(
+    pool: &SqlitePool,
+    task_id: &str,
+    run_id: &str,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE task_runs SET status = 'FAILED', error_message = ?2, \
+         completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'RUNNING'",
+    )
+    .bind(run_id)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE tasks SET status = 'PENDING', updated_at = CURRENT_TIMESTAMP \
+         WHERE id = ?1 AND status = 'RUNNING'",
+    )
+    .bind(task_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+pub async fn get_runs_for_task(
+    pool: &SqlitePool,
+    task_id: &str,
+) -> Result<Vec<TaskRunRow>, sqlx::Error> {
+    sqlx::query_as::<_, TaskRunRow>(
+        "SELECT * FROM task_runs WHERE task_id = ?1 ORDER BY attempt_number DESC",
+    )
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Complete the run and its task in one transaction.
+pub async fn complete_task_run(
+    pool: &SqlitePool,
+    task_id: &str,
+    run_id: &str,
+    output: Option<&serde_json::Value>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE task_runs SET status = 'COMPLETED', output = json(?2), \
+         completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'RUNNING'",
+    )
+    .bind(run_id)
+    .bind(output)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE tasks SET status = 'COMPLETED', output = json(?2), \
+         updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+    )
+    .bind(task_id)
+    .bind(output)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+/// Fail the run and move its task on: RETRY at `retry_at` while attempts remain,
+/// DEAD_LETTER (with a dead_letter_queue row) once they are exhausted, or FAILED
+/// when the error is not retryable. Returns the updated task, or `None` when
+/// the run had already finished and nothing changed.
+pub async fn fail_task_run(
+    pool: &SqlitePool,
+    task_id: &str,
+    run_id: &str,
+    error_message: &str,
+    retryable: bool,
+    retry_at: DateTime<Utc>,
+) -> Result<Option<TaskRow>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let failed = sqlx::query(
+        "UPDATE task_runs SET status = 'FAILED', error_message = ?2, \
+         completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'RUNNING'",
+    )
+    .bind(run_id)
+    .bind(error_message)
+    .execute(&mut *tx)
+    .await?;
+    if failed.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    let task = sqlx::query_as::<_, TaskRow>(
+        r#"
+        UPDATE tasks SET
+            status = CASE
+                WHEN NOT ?3 THEN 'FAILED'
+                WHEN attempt_count < max_retries THEN 'RETRY'
+                ELSE 'DEAD_LETTER'
+            END,
+            scheduled_at = CASE WHEN ?3 AND attempt_count < max_retries THEN ?4 END,
+            error_message = ?2,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?1
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(error_message)
+    .bind(retryable)
+    .bind(retry_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if task.status == "DEAD_LETTER" {
+        sqlx::query(
+            r#"
+            INSERT INTO dead_letter_queue (id, task_id, queue_name, task_name, input,
+                                           error_message, attempt_count, metadata, worker_id)
+            VALUES (?1, ?2, ?3, ?4, json(?5), ?6, ?7, json(?8),
+                    (SELECT worker_id FROM task_runs WHERE id = ?9))
+            "#,
+        )
+        .bind(uuid::Uuid::now_v7().to_string())
+        .bind(&task.id)
+        .bind(&task.queue_name)
+        .bind(&task.task_name)
+        .bind(&task.input)
+        .bind(error_message)
+        .bind(task.attempt_count)
+        .bind(&task.metadata)
+        .bind(run_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(Some(task))
+}
+
+/// RUNNING runs whose lease has passed: their worker died without its
+/// stream closing, or overran its timeout without reporting.
+pub async fn find_expired_runs(pool: &SqlitePool) -> Result<Vec<TaskRunRow>, sqlx::Error> {
+    sqlx::query_as::<_, TaskRunRow>(
+        "SELECT * FROM task_runs WHERE status = 'RUNNING' \
+         AND julianday(lease_expires_at) < julianday('now')",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Promote RETRY tasks whose scheduled_at has passed back to PENDING.
+pub async fn promote_delayed_tasks(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE tasks SET status = 'PENDING', scheduled_at = NULL, updated_at = CURRENT_TIMESTAMP
+        WHERE status = 'RETRY' AND julianday(scheduled_at) <= julianday('now')
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Insert log entries in a single transaction.
+pub async fn batch_insert_logs(
+    pool: &SqlitePool,
+    entries: &[InsertLogEntry],
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for entry in entries {
+        sqlx::query(
+            "INSERT INTO task_logs (task_run_id, timestamp_ms, level, message, metadata) \
+             VALUES (?1, ?2, ?3, ?4, json(?5))",
+        )
+        .bind(&entry.task_run_id)
+        .bind(entry.timestamp_ms)
+        .bind(&entry.level)
+        .bind(&entry.message)
+        .bind(&entry.metadata)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(entries.len() as u64)
+}
+
+pub async fn get_logs_for_run(
+    pool: &SqlitePool,
+    task_run_id: &str,
+    limit: i64,
+) -> Result<Vec<TaskLogRow>, sqlx::Error> {
+    sqlx::query_as::<_, TaskLogRow>(
+        "SELECT * FROM task_logs WHERE task_run_id = ?1 ORDER BY timestamp_ms ASC, id ASC LIMIT ?2",
+    )
+    .bind(task_run_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn list_dead_letters(
+    pool: &SqlitePool,
+    queue_name: Option<&str>,
+    limit: i64,
+) -> Result<Vec<DeadLetterRow>, sqlx::Error> {
+    sqlx::query_as::<_, DeadLetterRow>(
+        r#"
+        SELECT * FROM dead_letter_queue
+        WHERE (?1 IS NULL OR queue_name = ?1)
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(queue_name)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_dead_letters(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM dead_letter_queue")
+        .fetch_one(pool)
+        .await
+}
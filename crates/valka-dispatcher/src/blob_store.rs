@@ -0,0 +1,237 @@
+//! Offload of task outputs and inputs too large for PG to an object store
+//! (`[blob_storage]`). The row keeps a `{"$blob": "<url>"}` reference in
+//! place of the JSON, resolved on read and before dispatch. `s3://` stores
+//! need the `s3` feature; `file://` and `memory://` are always available.
+//!
+//! A reference is only followed to the task's own blobs (see [`input_key`] and
+//! [`output_key`]), so a payload that merely looks like one can't read
+//! another task's data.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutPayload};
+use url::Url;
+use valka_core::BlobStorageConfig;
+
+/// Field of the object left in place of an offloaded payload.
+pub const BLOB_REFERENCE_KEY: &str = "$blob";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlobError {
+    #[error("{0}")]
+    Config(String),
+    #[error("Payload is {bytes} bytes, over the {limit} byte blob_storage.max_blob_bytes limit")]
+    TooLarge { bytes: usize, limit: usize },
+    #[error("Blob {0} is not in the configured blob store")]
+    ForeignReference(String),
+    #[error("Blob {0} not found")]
+    NotFound(String),
+    #[error(
+        "{0} must not be a {{\"$blob\": ...}} object; that shape is reserved for offloaded payloads"
+    )]
+    ReservedShape(&'static str),
+    #[error("Blob store error: {0}")]
+    Store(#[from] object_store::Error),
+}
+
+/// A configured object store and where in it blobs go.
+#[derive(Clone)]
+pub struct BlobStore {
+    store: Arc<dyn ObjectStore>,
+    /// `blob_storage.url` less one trailing `/`; every reference starts with it
+    base_url: String,
+    /// Path within the store that `base_url` names
+    prefix: Path,
+    offload_threshold_bytes: usize,
+    max_blob_bytes: usize,
+}
+
+impl std::fmt::Debug for BlobStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobStore")
+            .field("base_url", &self.base_url)
+            .field("offload_threshold_bytes", &self.offload_threshold_bytes)
+            .field("max_blob_bytes", &self.max_blob_bytes)
+            .finish()
+    }
+}
+
+impl BlobStore {
+    /// Open the store `config` names, or `None` when no URL is set.
+    pub fn from_config(config: &BlobStorageConfig) -> Result<Option<Self>, BlobError> {
+        let Some(url) = config.url.as_deref().filter(|url| !url.is_empty()) else {
+            return Ok(None);
+        };
+        let parsed = Url::parse(url)
+            .map_err(|e| BlobError::Config(format!("blob_storage.url {url:?}: {e}")))?;
+        #[cfg(not(feature = "s3"))]
+        if parsed.scheme() == "s3" {
+            return Err(BlobError::Config(
+                "blob_storage.url is s3:// but valka was built without the s3 feature".to_string(),
+            ));
+        }
+        // The usual AWS_* variables, overridden by [blob_storage.options]
+        let options = std::env::vars()
+            .filter(|(key, _)| key.starts_with("AWS_"))
+            .map(|(key, value)| (key.to_ascii_lowercase(), value))
+            .chain(config.options.clone());
+        let (store, prefix) = object_store::parse_url_opts(&parsed, options)
+            .map_err(|e| BlobError::Config(format!("blob_storage.url {url:?}: {e}")))?;
+        Ok(Some(Self {
+            store: Arc::from(store),
+            base_url: url.strip_suffix('/').unwrap_or(url).to_string(),
+            prefix,
+            offload_threshold_bytes: config.offload_threshold_bytes,
+            max_blob_bytes: config.max_blob_bytes,
+        }))
+    }
+
+    pub fn max_blob_bytes(&self) -> usize {
+        self.max_blob_bytes
+    }
+
+    /// Whether a payload of `bytes` belongs in the store rather than the row.
+    pub fn should_offload(&self, bytes: usize) -> bool {
+        bytes > self.offload_threshold_bytes
+    }
+
+    /// Refuse a payload of `bytes` over `max_blob_bytes`.
+    pub fn check_size(&self, bytes: usize) -> Result<(), BlobError> {
+        if bytes > self.max_blob_bytes {
+            return Err(BlobError::TooLarge {
+                bytes,
+                limit: self.max_blob_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// The reference a blob written at `key` leaves in the row.
+    pub fn reference(&self, key: &str) -> serde_json::Value {
+        serde_json::json!({ BLOB_REFERENCE_KEY: self.url(key) })
+    }
+
+    /// Write `json` at `key` (relative to the store's URL, e.g.
+    /// `outputs/<task_id>/<run_id>.json`) and return the reference to keep
+    /// in its place.
+    pub async fn put(&self, key: &str, json: &str) -> Result<serde_json::Value, BlobError> {
+        self.check_size(json.len())?;
+        self.store
+            .put(&self.key_location(key)?, PutPayload::from(json.to_string()))
+            .await?;
+        Ok(self.reference(key))
+    }
+
+    /// Remove the blob at `key`; one already gone is not an error.
+    pub async fn delete(&self, key: &str) -> Result<(), BlobError> {
+        match self.store.delete(&self.key_location(key)?).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The offloaded input of `task_id`. A reference to any other blob is a
+    /// [`BlobError::ForeignReference`].
+    pub async fn get_input(&self, task_id: &str, reference: &str) -> Result<Bytes, BlobError> {
+        if self.url(&input_key(task_id)) != reference {
+            return Err(BlobError::ForeignReference(reference.to_string()));
+        }
+        self.get(reference).await
+    }
+
+    /// Whether `reference` names an output of `task_id` in this store.
+    pub fn is_output_of(&self, task_id: &str, reference: &str) -> bool {
+        reference
+            .strip_prefix(self.base_url.as_str())
+            .and_then(|key| key.strip_prefix('/'))
+            .is_some_and(|key| key.starts_with(&format!("outputs/{task_id}/")))
+    }
+
+    /// The whole blob a reference names.
+    pub async fn get(&self, reference: &str) -> Result<Bytes, BlobError> {
+        let location = self.resolve(reference)?;
+        let result = self
+            .store
+            .get(&location)
+            .await
+            .map_err(|e| not_found(reference, e))?;
+        Ok(result.bytes().await?)
+    }
+
+    /// The blob a reference names, as it arrives from the store.
+    pub async fn get_stream(
+        &self,
+        reference: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes, BlobError>>, BlobError> {
+        let location = self.resolve(reference)?;
+        let result = self
+            .store
+            .get(&location)
+            .await
+            .map_err(|e| not_found(reference, e))?;
+        Ok(result
+            .into_stream()
+            .map(|chunk| chunk.map_err(BlobError::from))
+            .boxed())
+    }
+
+    fn location(&self, key: &Path) -> Path {
+        self.prefix.parts().chain(key.parts()).collect()
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{key}", self.base_url)
+    }
+
+    fn key_location(&self, key: &str) -> Result<Path, BlobError> {
+        let key =
+            Path::parse(key).map_err(|e| BlobError::Config(format!("blob key {key:?}: {e}")))?;
+        Ok(self.location(&key))
+    }
+
+    fn resolve(&self, reference: &str) -> Result<Path, BlobError> {
+        let key = reference
+            .strip_prefix(self.base_url.as_str())
+            .and_then(|key| key.strip_prefix('/'))
+            .and_then(|key| Path::parse(key).ok())
+            .ok_or_else(|| BlobError::ForeignReference(reference.to_string()))?;
+        Ok(self.location(&key))
+    }
+}
+
+fn not_found(reference: &str, error: object_store::Error) -> BlobError {
+    match error {
+        object_store::Error::NotFound { .. } => BlobError::NotFound(reference.to_string()),
+        other => BlobError::Store(other),
+    }
+}
+
+/// Where a create's offloaded input is written.
+pub fn input_key(task_id: &str) -> String {
+    format!("inputs/{task_id}.json")
+}
+
+/// Where an offloaded output of one run is written.
+pub fn output_key(task_id: &str, task_run_id: &str) -> String {
+    format!("outputs/{task_id}/{task_run_id}.json")
+}
+
+/// The URL in a `{"$blob": "<url>"}` reference, if `value` is one.
+pub fn blob_reference(value: &serde_json::Value) -> Option<&str> {
+    let object = value.as_object().filter(|object| object.len() == 1)?;
+    object.get(BLOB_REFERENCE_KEY)?.as_str()
+}
+
+/// [`blob_reference`] for serialized JSON, skipping the parse for anything
+/// that can't be one.
+pub fn blob_reference_in(json: &str) -> Option<String> {
+    if !json.trim_start().starts_with("{\"$blob\"") {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    blob_reference(&value).map(str::to_string)
+}
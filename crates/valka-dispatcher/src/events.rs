@@ -0,0 +1,191 @@
+use std::collections::{HashSet, VecDeque};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, Once};
+use std::time::{Duration, Instant};
+
+use lru::LruCache;
+use tokio::sync::broadcast;
+use valka_core::EventsConfig;
+use valka_proto::TaskEvent;
+
+/// Statuses after which a task emits nothing further.
+const TERMINAL_STATUSES: [i32; 4] = [4, 5, 7, 8]; // COMPLETED, FAILED, DEAD_LETTER, CANCELLED
+
+#[derive(Default)]
+struct TaskEmitState {
+    /// Status of the last event emitted for the task, and when
+    last_emitted: Option<(i32, Instant)>,
+    suppressed: u32,
+}
+
+/// Shared TaskEvent emitter that coalesces repeated statuses for high-churn tasks.
+///
+/// A non-terminal status repeating the task's last emitted one within the
+/// dedup window is dropped and counted; the next event that does get through carries
+/// the count in `suppressed_count`. Terminal statuses and progress updates
+/// (throttled by the SDK) are always emitted.
+#[derive(Clone)]
+pub struct EventEmitter {
+    tx: broadcast::Sender<TaskEvent>,
+    window: Duration,
+    state: Arc<Mutex<LruCache<String, TaskEmitState>>>,
+    history: EventHistory,
+    sse_keep_alive: Option<Duration>,
+}
+
+impl EventEmitter {
+    pub fn new(tx: broadcast::Sender<TaskEvent>, config: &EventsConfig) -> Self {
+        let capacity = NonZeroUsize::new(config.dedup_capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            tx,
+            window: Duration::from_millis(config.dedup_window_ms),
+            state: Arc::new(Mutex::new(LruCache::new(capacity))),
+            history: EventHistory {
+                capacity: config.replay_buffer_size,
+                events: Arc::default(),
+                recording: Arc::new(Once::new()),
+            },
+            sse_keep_alive: (config.sse_keep_alive_secs > 0)
+                .then(|| Duration::from_secs(config.sse_keep_alive_secs)),
+        }
+    }
+
+    /// Emit an event unless it is a duplicate within the window. Returns true if sent.
+    pub fn emit(&self, mut event: TaskEvent) -> bool {
+        if !self.window.is_zero() && event.progress.is_none() {
+            let now = Instant::now();
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+            if TERMINAL_STATUSES.contains(&event.new_status) {
+                if let Some(prev) = state.pop(&event.task_id) {
+                    event.suppressed_count = prev.suppressed;
+                }
+            } else {
+                let entry = state.get_or_insert_mut(event.task_id.clone(), TaskEmitState::default);
+                // Only a repeat of the last status is a duplicate: RUNNING after
+                // RETRY is a new attempt and goes out
+                if let Some((status, at)) = entry.last_emitted
+                    && status == event.new_status
+                    && now.saturating_duration_since(at) < self.window
+                {
+                    entry.suppressed += 1;
+                    valka_core::metrics::record_event_suppressed(&event.queue_name);
+                    return false;
+                }
+                entry.last_emitted = Some((event.new_status, now));
+                event.suppressed_count = std::mem::take(&mut entry.suppressed);
+            }
+        }
+
+        let _ = self.tx.send(event);
+        true
+    }
+
+    pub fn sender(&self) -> &broadcast::Sender<TaskEvent> {
+        &self.tx
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Subscribe to live events, first replaying the buffered ones that came
+    /// after `last_event_id`.
+    pub fn subscribe_since(&self, last_event_id: Option<&str>) -> EventSubscription {
+        self.history.ensure_recording(&self.tx);
+        let rx = self.tx.subscribe();
+        let replay = last_event_id
+            .map(|id| self.history.since(id))
+            .unwrap_or_default();
+        EventSubscription {
+            replayed: replay.iter().map(|e| e.event_id.clone()).collect(),
+            replay: replay.into_iter(),
+            rx,
+        }
+    }
+
+    /// How often idle SSE streams get a keep-alive comment, if at all.
+    pub fn sse_keep_alive(&self) -> Option<Duration> {
+        self.sse_keep_alive
+    }
+}
+
+/// The newest events seen on the broadcast channel, including those relayed
+/// from other nodes. Recording starts with the first subscription.
+#[derive(Clone)]
+struct EventHistory {
+    capacity: usize,
+    events: Arc<Mutex<VecDeque<TaskEvent>>>,
+    recording: Arc<Once>,
+}
+
+impl EventHistory {
+    fn ensure_recording(&self, tx: &broadcast::Sender<TaskEvent>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.recording.call_once(|| {
+            let mut rx = tx.subscribe();
+            let events = self.events.clone();
+            let capacity = self.capacity;
+            tokio::spawn(async move {
+                loop {
+                    match rx.recv().await {
+                        Ok(event) => {
+                            let mut events = events.lock().unwrap_or_else(|e| e.into_inner());
+                            if events.len() == capacity {
+                                events.pop_front();
+                            }
+                            events.push_back(event);
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+        });
+    }
+
+    /// Buffered events after `last_event_id`. If that event is no longer
+    /// buffered, those with a greater id: ids are UUIDv7, so ordered by time.
+    fn since(&self, last_event_id: &str) -> Vec<TaskEvent> {
+        let events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(pos) = events.iter().position(|e| e.event_id == last_event_id) {
+            return events.iter().skip(pos + 1).cloned().collect();
+        }
+        if uuid::Uuid::parse_str(last_event_id).is_err() {
+            return Vec::new();
+        }
+        events
+            .iter()
+            .filter(|e| e.event_id.as_str() > last_event_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Replayed events followed by live ones, for one subscriber.
+pub struct EventSubscription {
+    replay: std::vec::IntoIter<TaskEvent>,
+    /// Replayed ids, so a live copy of one is not sent twice
+    replayed: HashSet<String>,
+    rx: broadcast::Receiver<TaskEvent>,
+}
+
+impl EventSubscription {
+    /// The next event, or `None` once the channel has closed. Events missed
+    /// because the subscriber lagged are skipped.
+    pub async fn next(&mut self) -> Option<TaskEvent> {
+        if let Some(event) = self.replay.next() {
+            return Some(event);
+        }
+        loop {
+            match self.rx.recv().await {
+                Ok(event) if self.replayed.remove(&event.event_id) => continue,
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
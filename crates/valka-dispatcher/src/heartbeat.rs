@@ -3,12 +3,22 @@ use dashmap::DashMap;
 use std::sync::Arc;
 use tokio::time;
 use tracing::{info, warn};
-use valka_core::WorkerId;
+use valka_core::{DispatcherConfig, WorkerId};
 
 use crate::worker_handle::WorkerHandle;
 
-const HEARTBEAT_TIMEOUT_SECS: i64 = 30;
-const SUSPECT_AFTER_SECS: i64 = 10;
+const MIN_INTERVAL_MS: i64 = 1_000;
+
+/// The heartbeat interval granted to a worker asking for `requested_ms`:
+/// the configured one, or a faster one it asks for, down to 1s. A slower
+/// one would have it suspected between heartbeats.
+pub fn negotiate_interval_ms(requested_ms: i64, config: &DispatcherConfig) -> i64 {
+    let configured = (config.heartbeat_interval_secs as i64).saturating_mul(1000);
+    if requested_ms <= 0 {
+        return configured;
+    }
+    requested_ms.clamp(MIN_INTERVAL_MS.min(configured), configured)
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WorkerStatus {
@@ -17,14 +27,15 @@ pub enum WorkerStatus {
     Dead,
 }
 
-/// Check worker heartbeat status
-pub fn check_heartbeat(handle: &WorkerHandle) -> WorkerStatus {
+/// Check worker heartbeat status: suspect once a heartbeat interval has
+/// passed without one, dead after `dead_after_secs`.
+pub fn check_heartbeat(handle: &WorkerHandle, config: &DispatcherConfig) -> WorkerStatus {
     let now = Utc::now();
     let elapsed = now - handle.last_heartbeat;
 
-    if elapsed > Duration::seconds(HEARTBEAT_TIMEOUT_SECS) {
+    if elapsed > Duration::seconds(config.dead_after_secs as i64) {
         WorkerStatus::Dead
-    } else if elapsed > Duration::seconds(SUSPECT_AFTER_SECS) {
+    } else if elapsed > Duration::seconds(config.heartbeat_interval_secs as i64) {
         WorkerStatus::Suspect
     } else {
         WorkerStatus::Alive
@@ -34,6 +45,7 @@ pub fn check_heartbeat(handle: &WorkerHandle) -> WorkerStatus {
 /// Background task that periodically checks heartbeats and removes dead workers
 pub async fn heartbeat_checker(
     workers: Arc<DashMap<String, WorkerHandle>>,
+    config: DispatcherConfig,
     mut shutdown: tokio::sync::watch::Receiver<bool>,
     on_worker_dead: tokio::sync::mpsc::Sender<WorkerId>,
 ) {
@@ -51,14 +63,24 @@ pub async fn heartbeat_checker(
                 let mut dead_workers = Vec::new();
 
                 for entry in workers.iter() {
-                    let status = check_heartbeat(entry.value());
+                    let status = check_heartbeat(entry.value(), &config);
                     match status {
                         WorkerStatus::Dead => {
+                            let handle = entry.value();
+                            let silent_secs =
+                                (Utc::now() - handle.last_heartbeat).num_milliseconds() as f64
+                                    / 1000.0;
                             warn!(
-                                worker_id = %entry.value().worker_id,
-                                worker_name = %entry.value().worker_name,
+                                worker_id = %handle.worker_id,
+                                worker_name = %handle.worker_name,
+                                silent_secs,
+                                dead_after_secs = config.dead_after_secs,
                                 "Worker heartbeat timeout - marking as dead"
                             );
+                            valka_core::metrics::record_worker_declared_dead(
+                                &handle.worker_name,
+                                silent_secs,
+                            );
                             dead_workers.push(entry.key().clone());
                         }
                         WorkerStatus::Suspect => {
@@ -1,6 +1,19 @@
+pub mod blob_store;
+pub mod events;
 pub mod heartbeat;
+pub mod queue_policy;
+pub mod quotas;
+pub mod result_writer;
 pub mod service;
+pub mod shutdown;
 pub mod stream;
+pub mod templating;
+pub mod webhooks;
 pub mod worker_handle;
 
-pub use service::DispatcherService;
+pub use blob_store::BlobStore;
+pub use events::{EventEmitter, EventSubscription};
+pub use queue_policy::QueuePolicies;
+pub use quotas::Quotas;
+pub use service::{DispatcherService, Registration};
+pub use shutdown::{ShutdownPhase, ShutdownTracker};
@@ -0,0 +1,324 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use semver::Version;
+use tokio::sync::watch;
+use tracing::{error, warn};
+use valka_db::DbPool;
+use valka_db::queries::queue_configs::QueueConfigRow;
+use valka_matching::fifo::FifoGroups;
+use valka_matching::paused::PausedQueues;
+use valka_matching::rate_limit::DispatchRateLimits;
+
+/// Parse a worker's declared version. Missing or invalid versions are treated
+/// as 0.0.0 so they never satisfy a minimum.
+pub fn parse_worker_version(worker_id: &str, raw: &str) -> Version {
+    if raw.trim().is_empty() {
+        return Version::new(0, 0, 0);
+    }
+    Version::parse(raw.trim()).unwrap_or_else(|e| {
+        warn!(worker_id, version = raw, error = %e, "Invalid worker version, treating as 0.0.0");
+        Version::new(0, 0, 0)
+    })
+}
+
+/// Used when neither the create request nor its queue sets max_retries.
+pub const DEFAULT_MAX_RETRIES: i32 = 3;
+/// Used when neither the create request nor its queue sets timeout_seconds.
+pub const DEFAULT_TIMEOUT_SECONDS: i32 = 300;
+
+/// A queue's defaults for creates that leave max_retries or timeout_seconds at 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskDefaults {
+    pub max_retries: Option<i32>,
+    pub timeout_seconds: Option<i32>,
+}
+
+impl TaskDefaults {
+    pub fn from_config(config: &QueueConfigRow) -> Self {
+        Self {
+            max_retries: config.default_max_retries,
+            timeout_seconds: config.default_timeout_seconds,
+        }
+    }
+
+    /// `requested` unless it is 0, then the queue default, then [`DEFAULT_MAX_RETRIES`].
+    pub fn max_retries(&self, requested: i32) -> i32 {
+        match requested {
+            0 => self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            n => n,
+        }
+    }
+
+    /// `requested` unless it is 0, then the queue default, then [`DEFAULT_TIMEOUT_SECONDS`].
+    pub fn timeout_seconds(&self, requested: i32) -> i32 {
+        match requested {
+            0 => self.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS),
+            n => n,
+        }
+    }
+}
+
+/// Per-queue rules mirrored from queue_configs: the minimum worker version
+/// every match loop on this node checks, which queues accept ephemeral tasks,
+/// which have their input templated at dispatch, which are FIFO, which are
+/// paused or rate limited, and the defaults new tasks take.
+#[derive(Clone)]
+pub struct QueuePolicies {
+    min_versions: Arc<RwLock<HashMap<String, Version>>>,
+    task_defaults: Arc<RwLock<HashMap<String, TaskDefaults>>>,
+    ephemeral_queues: Arc<RwLock<HashSet<String>>>,
+    templated_queues: Arc<RwLock<HashSet<String>>>,
+    /// Shared with the matching service, which enforces the ordering
+    fifo: FifoGroups,
+    /// Shared with the matching service and its TaskReaders
+    paused: PausedQueues,
+    /// Shared with the matching service's TaskReaders, which meter dispatch
+    rate_limits: DispatchRateLimits,
+    changed: watch::Sender<u64>,
+}
+
+impl Default for QueuePolicies {
+    fn default() -> Self {
+        Self {
+            min_versions: Arc::default(),
+            task_defaults: Arc::default(),
+            ephemeral_queues: Arc::default(),
+            templated_queues: Arc::default(),
+            fifo: FifoGroups::default(),
+            paused: PausedQueues::default(),
+            rate_limits: DispatchRateLimits::default(),
+            changed: watch::Sender::new(0),
+        }
+    }
+}
+
+impl QueuePolicies {
+    /// Keep the FIFO queue set in `fifo` (the matching service's).
+    pub fn with_fifo_groups(mut self, fifo: FifoGroups) -> Self {
+        self.fifo = fifo;
+        self
+    }
+
+    /// Keep the paused queue set in `paused` (the matching service's).
+    pub fn with_paused_queues(mut self, paused: PausedQueues) -> Self {
+        self.paused = paused;
+        self
+    }
+
+    /// Keep the dispatch rate limits in `limits` (the matching service's).
+    pub fn with_rate_limits(mut self, limits: DispatchRateLimits) -> Self {
+        self.rate_limits = limits;
+        self
+    }
+
+    /// Set or clear the minimum worker version for a queue.
+    pub fn set_min_worker_version(&self, queue_name: &str, min: Option<Version>) {
+        let mut map = self.min_versions.write().unwrap_or_else(|e| e.into_inner());
+        let changed = match min {
+            Some(min) => map.insert(queue_name.to_string(), min.clone()) != Some(min),
+            None => map.remove(queue_name).is_some(),
+        };
+        drop(map);
+        if changed {
+            self.changed.send_modify(|v| *v += 1);
+        }
+    }
+
+    /// Apply a queue's freshly written config without waiting for the next refresh.
+    pub fn apply_config(&self, config: &QueueConfigRow) {
+        let min = config
+            .min_worker_version
+            .as_deref()
+            .and_then(|v| Version::parse(v).ok());
+        self.set_min_worker_version(&config.queue_name, min);
+        self.set_allow_ephemeral(&config.queue_name, config.allow_ephemeral);
+        self.set_template_input(&config.queue_name, config.template_input);
+        self.set_fifo(&config.queue_name, config.fifo);
+        self.set_paused(&config.queue_name, config.paused);
+        self.set_max_dispatch_per_second(&config.queue_name, config.max_dispatch_per_second);
+        self.set_task_defaults(&config.queue_name, TaskDefaults::from_config(config));
+    }
+
+    /// Set the defaults for tasks created on a queue.
+    pub fn set_task_defaults(&self, queue_name: &str, defaults: TaskDefaults) {
+        let mut map = self
+            .task_defaults
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if defaults == TaskDefaults::default() {
+            map.remove(queue_name);
+        } else {
+            map.insert(queue_name.to_string(), defaults);
+        }
+    }
+
+    pub fn task_defaults(&self, queue_name: &str) -> TaskDefaults {
+        let map = self.task_defaults.read().unwrap_or_else(|e| e.into_inner());
+        map.get(queue_name).copied().unwrap_or_default()
+    }
+
+    /// Allow or forbid ephemeral task creation on a queue.
+    pub fn set_allow_ephemeral(&self, queue_name: &str, allowed: bool) {
+        let mut set = self
+            .ephemeral_queues
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if allowed {
+            set.insert(queue_name.to_string());
+        } else {
+            set.remove(queue_name);
+        }
+    }
+
+    pub fn allows_ephemeral(&self, queue_name: &str) -> bool {
+        let set = self
+            .ephemeral_queues
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        set.contains(queue_name)
+    }
+
+    /// Turn dispatch-time input templating on or off for a queue.
+    pub fn set_template_input(&self, queue_name: &str, enabled: bool) {
+        let mut set = self
+            .templated_queues
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if enabled {
+            set.insert(queue_name.to_string());
+        } else {
+            set.remove(queue_name);
+        }
+    }
+
+    pub fn templates_input(&self, queue_name: &str) -> bool {
+        let set = self
+            .templated_queues
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        set.contains(queue_name)
+    }
+
+    /// Pause or resume dispatch of new tasks on a queue.
+    pub fn set_paused(&self, queue_name: &str, paused: bool) {
+        self.paused.set_paused(queue_name, paused);
+    }
+
+    pub fn is_paused(&self, queue_name: &str) -> bool {
+        self.paused.is_paused(queue_name)
+    }
+
+    /// Cap or uncap how many tasks per second a queue dispatches on this node.
+    pub fn set_max_dispatch_per_second(&self, queue_name: &str, per_second: Option<i32>) {
+        self.rate_limits
+            .set_limit(queue_name, per_second.map(|r| r.max(0) as u32));
+    }
+
+    pub fn max_dispatch_per_second(&self, queue_name: &str) -> Option<u32> {
+        self.rate_limits.limit(queue_name)
+    }
+
+    /// Turn per-key FIFO ordering on or off for a queue.
+    pub fn set_fifo(&self, queue_name: &str, enabled: bool) {
+        self.fifo.set_fifo(queue_name, enabled);
+    }
+
+    pub fn is_fifo(&self, queue_name: &str) -> bool {
+        self.fifo.is_fifo(queue_name)
+    }
+
+    /// Replace all minimums at once (used by the periodic refresh).
+    pub fn replace_min_worker_versions(&self, mins: HashMap<String, Version>) {
+        let mut map = self.min_versions.write().unwrap_or_else(|e| e.into_inner());
+        if *map != mins {
+            *map = mins;
+            drop(map);
+            self.changed.send_modify(|v| *v += 1);
+        }
+    }
+
+    pub fn min_worker_version(&self, queue_name: &str) -> Option<Version> {
+        let map = self.min_versions.read().unwrap_or_else(|e| e.into_inner());
+        map.get(queue_name).cloned()
+    }
+
+    /// Why a worker at `version` may not take tasks from `queue_name`, if it may not.
+    pub fn exclusion_reason(&self, queue_name: &str, version: &Version) -> Option<String> {
+        self.min_worker_version(queue_name)
+            .filter(|min| version < min)
+            .map(|min| format!("version < {min}"))
+    }
+
+    /// Notified whenever any rule changes.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.changed.subscribe()
+    }
+
+    /// Reload every rule from queue_configs.
+    pub async fn refresh(&self, pool: &DbPool) -> Result<(), sqlx::Error> {
+        let configs = valka_db::queries::queue_configs::list_queue_configs(pool, None).await?;
+        *self
+            .ephemeral_queues
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = configs
+            .iter()
+            .filter(|c| c.allow_ephemeral)
+            .map(|c| c.queue_name.clone())
+            .collect();
+        *self
+            .templated_queues
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = configs
+            .iter()
+            .filter(|c| c.template_input)
+            .map(|c| c.queue_name.clone())
+            .collect();
+        *self
+            .task_defaults
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = configs
+            .iter()
+            .map(|c| (c.queue_name.clone(), TaskDefaults::from_config(c)))
+            .filter(|(_, defaults)| *defaults != TaskDefaults::default())
+            .collect();
+        self.fifo.replace_queues(
+            configs
+                .iter()
+                .filter(|c| c.fifo)
+                .map(|c| c.queue_name.clone())
+                .collect(),
+        );
+        self.paused.replace_queues(
+            configs
+                .iter()
+                .filter(|c| c.paused)
+                .map(|c| c.queue_name.clone())
+                .collect(),
+        );
+        self.rate_limits.replace_limits(
+            configs
+                .iter()
+                .filter_map(|c| {
+                    let rate = c.max_dispatch_per_second.filter(|r| *r > 0)?;
+                    Some((c.queue_name.clone(), rate as u32))
+                })
+                .collect(),
+        );
+        let mins = configs
+            .into_iter()
+            .filter_map(|c| {
+                let raw = c.min_worker_version?;
+                match Version::parse(&raw) {
+                    Ok(v) => Some((c.queue_name, v)),
+                    Err(e) => {
+                        error!(queue = %c.queue_name, version = %raw, error = %e, "Invalid min_worker_version");
+                        None
+                    }
+                }
+            })
+            .collect();
+        self.replace_min_worker_versions(mins);
+        Ok(())
+    }
+}
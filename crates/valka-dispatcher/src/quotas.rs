@@ -0,0 +1,196 @@
+//! Per-namespace task creation quotas.
+//!
+//! Limits live in the `quotas` table, keyed by namespace (a queue name up to its
+//! first `.`, so `billing.invoices` counts against `billing`). Create paths
+//! check and bump in-memory counters; [`Quotas::reconcile`] periodically resets
+//! them from PG counts, which bounds drift from other nodes and from tasks
+//! finishing. Namespaces without a quota row are unlimited.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::Serialize;
+use valka_db::DbPool;
+use valka_db::queries::quotas::{self, QuotaRow};
+
+/// Namespace a queue's tasks are counted against.
+pub fn namespace_of(queue_name: &str) -> &str {
+    queue_name.split('.').next().unwrap_or(queue_name)
+}
+
+/// Start of the clock hour containing `now`; creation windows are fixed hours.
+pub fn window_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.duration_trunc(TimeDelta::hours(1)).unwrap_or(now)
+}
+
+/// Which limit rejected a creation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaLimit {
+    ActiveTasks,
+    CreationsPerHour,
+}
+
+impl QuotaLimit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaLimit::ActiveTasks => "max_active_tasks",
+            QuotaLimit::CreationsPerHour => "max_creations_per_hour",
+        }
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("quota exceeded for {key}: {} usage {usage} of {limit}", .limit_kind.as_str())]
+pub struct QuotaExceeded {
+    pub key: String,
+    pub limit_kind: QuotaLimit,
+    pub usage: i64,
+    pub limit: i64,
+}
+
+/// Limits and counted usage for one namespace.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaUsage {
+    pub key: String,
+    pub max_active_tasks: Option<i64>,
+    pub max_creations_per_hour: Option<i64>,
+    pub active_tasks: i64,
+    pub created_this_hour: i64,
+    pub window_start: DateTime<Utc>,
+}
+
+impl QuotaUsage {
+    /// Usage of `row`'s namespace counted in PG.
+    pub async fn load(
+        pool: &DbPool,
+        row: Option<&QuotaRow>,
+        key: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Self, sqlx::Error> {
+        let window_start = window_start(now);
+        let (active_tasks, created_this_hour) =
+            quotas::namespace_usage(pool, key, window_start).await?;
+        Ok(Self {
+            key: key.to_string(),
+            max_active_tasks: row.and_then(|r| r.max_active_tasks),
+            max_creations_per_hour: row.and_then(|r| r.max_creations_per_hour),
+            active_tasks,
+            created_this_hour,
+            window_start,
+        })
+    }
+
+    fn roll_window(&mut self, now: DateTime<Utc>) {
+        let start = window_start(now);
+        if start > self.window_start {
+            self.window_start = start;
+            self.created_this_hour = 0;
+        }
+    }
+}
+
+/// Cached quota counters shared by every create path on this node.
+#[derive(Clone, Default)]
+pub struct Quotas {
+    usage: Arc<Mutex<HashMap<String, QuotaUsage>>>,
+}
+
+impl Quotas {
+    /// Count one creation on `queue_name`'s namespace, or reject it if a limit is reached.
+    pub fn try_acquire(&self, queue_name: &str, now: DateTime<Utc>) -> Result<(), QuotaExceeded> {
+        let key = namespace_of(queue_name);
+        let mut map = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(usage) = map.get_mut(key) else {
+            return Ok(());
+        };
+        usage.roll_window(now);
+
+        let exceeded = [
+            (
+                QuotaLimit::ActiveTasks,
+                usage.max_active_tasks,
+                usage.active_tasks,
+            ),
+            (
+                QuotaLimit::CreationsPerHour,
+                usage.max_creations_per_hour,
+                usage.created_this_hour,
+            ),
+        ]
+        .into_iter()
+        .find_map(|(kind, limit, used)| {
+            limit
+                .filter(|limit| used >= *limit)
+                .map(|limit| (kind, used, limit))
+        });
+        if let Some((limit_kind, usage, limit)) = exceeded {
+            valka_core::metrics::record_quota_rejected(key, limit_kind.as_str());
+            return Err(QuotaExceeded {
+                key: key.to_string(),
+                limit_kind,
+                usage,
+                limit,
+            });
+        }
+
+        usage.active_tasks += 1;
+        usage.created_this_hour += 1;
+        Ok(())
+    }
+
+    /// Undo a [`Quotas::try_acquire`] whose task was never created.
+    pub fn release(&self, queue_name: &str) {
+        let mut map = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(usage) = map.get_mut(namespace_of(queue_name)) {
+            usage.active_tasks = (usage.active_tasks - 1).max(0);
+            usage.created_this_hour = (usage.created_this_hour - 1).max(0);
+        }
+    }
+
+    /// Cached usage for a namespace with a quota.
+    pub fn usage(&self, key: &str, now: DateTime<Utc>) -> Option<QuotaUsage> {
+        let mut map = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = map.get_mut(key)?;
+        usage.roll_window(now);
+        Some(usage.clone())
+    }
+
+    /// Replace the cached entry for a namespace (`None` removes its quota).
+    pub fn set(&self, key: &str, usage: Option<QuotaUsage>) {
+        let mut map = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        match usage {
+            Some(usage) => map.insert(key.to_string(), usage),
+            None => map.remove(key),
+        };
+    }
+
+    /// Reload a single namespace's limits and counts, e.g. right after its quota changed.
+    pub async fn reconcile_key(
+        &self,
+        pool: &DbPool,
+        key: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let usage = match quotas::get_quota(pool, key).await? {
+            Some(row) => Some(QuotaUsage::load(pool, Some(&row), key, now).await?),
+            None => None,
+        };
+        self.set(key, usage);
+        Ok(())
+    }
+
+    /// Reload every quota and reset its counters from PG.
+    pub async fn reconcile(&self, pool: &DbPool, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let rows = quotas::list_quotas(pool).await?;
+        let mut fresh = HashMap::with_capacity(rows.len());
+        for row in &rows {
+            fresh.insert(
+                row.key.clone(),
+                QuotaUsage::load(pool, Some(row), &row.key, now).await?,
+            );
+        }
+        *self.usage.lock().unwrap_or_else(|e| e.into_inner()) = fresh;
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+//! Batched task result writes.
+//!
+//! Results from every worker stream on this node go through one writer,
+//! which closes their runs and tasks with multi-row UPDATEs in a single
+//! transaction per batch. A batch is written once it holds `batch_size`
+//! results or its first result has waited `flush_interval_ms`. A batch whose
+//! transaction fails is tried once more, then written row by row so one bad
+//! result fails alone.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+use valka_core::ResultWriterConfig;
+use valka_db::DbPool;
+
+/// `(queue_name, callback_url)` of a task whose result was just written.
+pub(crate) type RecordedTask = (String, Option<String>);
+/// A closed run's duration in ms and attempt number, plus its task if the row still exists.
+pub(crate) type RecordedResult = (f64, i32, Option<RecordedTask>);
+/// What a write came to: None when the run was no longer RUNNING.
+pub(crate) type WriteOutcome = Result<Option<RecordedResult>, String>;
+
+/// Where a result leaves its task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResultStatus {
+    Completed,
+    Retry,
+    Failed,
+}
+
+impl ResultStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Completed => "COMPLETED",
+            Self::Retry => "RETRY",
+            Self::Failed => "FAILED",
+        }
+    }
+}
+
+/// One run's result, waiting for its batch.
+#[derive(Debug)]
+pub(crate) struct ResultWrite {
+    pub task_id: String,
+    pub task_run_id: String,
+    pub status: ResultStatus,
+    /// JSON output of a completed run
+    pub output: Option<String>,
+    /// The output was over `limits.max_output_bytes` and left out
+    pub output_truncated: bool,
+    pub error_message: String,
+    pub retry_after_ms: Option<i64>,
+}
+
+type Pending = Vec<(ResultWrite, oneshot::Sender<WriteOutcome>)>;
+
+/// Handle to the node's result writer. The writing task starts with the
+/// first result and stops once every handle is dropped.
+#[derive(Clone)]
+pub(crate) struct ResultWriter {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    pool: DbPool,
+    config: ResultWriterConfig,
+    tx: OnceLock<mpsc::Sender<(ResultWrite, oneshot::Sender<WriteOutcome>)>>,
+    /// Results accepted but not yet written
+    in_flight: watch::Sender<usize>,
+}
+
+impl ResultWriter {
+    pub fn new(pool: DbPool, config: ResultWriterConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                pool,
+                config,
+                tx: OnceLock::new(),
+                in_flight: watch::channel(0).0,
+            }),
+        }
+    }
+
+    /// Queue `write` for the next batch and wait for it to be committed.
+    pub async fn write(&self, write: ResultWrite) -> WriteOutcome {
+        let tx = self.inner.tx.get_or_init(|| {
+            let batch_size = self.inner.config.batch_size.max(1);
+            let (tx, rx) = mpsc::channel(batch_size * 4);
+            tokio::spawn(run(
+                rx,
+                self.inner.pool.clone(),
+                batch_size,
+                Duration::from_millis(self.inner.config.flush_interval_ms),
+            ));
+            tx
+        });
+        let (done_tx, done_rx) = oneshot::channel();
+        if tx.send((write, done_tx)).await.is_err() {
+            return Err("Result writer stopped".to_string());
+        }
+        done_rx
+            .await
+            .unwrap_or_else(|_| Err("Result writer stopped".to_string()))
+    }
+
+    /// Count a result as in flight until the returned guard drops.
+    pub fn track(&self) -> InFlight {
+        self.inner.in_flight.send_modify(|n| *n += 1);
+        InFlight(self.inner.clone())
+    }
+
+    /// Returns once no tracked result is left in flight.
+    pub async fn idle(&self) {
+        let mut in_flight = self.inner.in_flight.subscribe();
+        let _ = in_flight.wait_for(|n| *n == 0).await;
+    }
+}
+
+/// See [`ResultWriter::track`].
+pub(crate) struct InFlight(Arc<Inner>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.send_modify(|n| *n -= 1);
+    }
+}
+
+async fn run(
+    mut rx: mpsc::Receiver<(ResultWrite, oneshot::Sender<WriteOutcome>)>,
+    pool: DbPool,
+    batch_size: usize,
+    flush_interval: Duration,
+) {
+    let mut pending = Vec::new();
+    let mut deadline: Option<Instant> = None;
+    loop {
+        let received = match deadline {
+            Some(at) => tokio::select! {
+                received = rx.recv() => received,
+                () = tokio::time::sleep_until(at) => {
+                    deadline = None;
+                    write_batch(&pool, &mut pending).await;
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+        match received {
+            Some(write) => {
+                pending.push(write);
+                if pending.len() >= batch_size {
+                    deadline = None;
+                    write_batch(&pool, &mut pending).await;
+                } else if deadline.is_none() {
+                    deadline = Some(Instant::now() + flush_interval);
+                }
+            }
+            None => {
+                write_batch(&pool, &mut pending).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Write `pending` in one transaction and hand each result its outcome.
+async fn write_batch(pool: &DbPool, pending: &mut Pending) {
+    if pending.is_empty() {
+        return;
+    }
+    let mut batch = std::mem::take(pending);
+    // A consistent row order keeps concurrent writers from deadlocking
+    batch.sort_by(|a, b| a.0.task_run_id.cmp(&b.0.task_run_id));
+    let size = batch.len();
+    let (writes, done): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+
+    let mut recorded = record_results(pool, &writes).await;
+    if let Err(e) = &recorded {
+        warn!(size, error = %e, "Failed to write task result batch; retrying");
+        recorded = record_results(pool, &writes).await;
+    }
+    match recorded {
+        Ok(mut outcomes) => {
+            for (write, done) in writes.iter().zip(done) {
+                // Only the first of several results for a run closes it
+                let _ = done.send(Ok(outcomes.remove(&write.task_run_id)));
+            }
+        }
+        // Nothing to split a single result into
+        Err(e) if size == 1 => {
+            let message = e.to_string();
+            for done in done {
+                let _ = done.send(Err(message.clone()));
+            }
+        }
+        Err(e) => {
+            warn!(size, error = %e, "Task result batch failed twice; writing it row by row");
+            for (write, done) in writes.iter().zip(done) {
+                let outcome = record_results(pool, std::slice::from_ref(write))
+                    .await
+                    .map(|mut outcomes| outcomes.remove(&write.task_run_id))
+                    .map_err(|e| e.to_string());
+                let _ = done.send(outcome);
+            }
+        }
+    }
+    debug!(size, "Wrote task result batch");
+}
+
+/// Close the RUNNING runs in `batch` and move their tasks on, returning what
+/// was recorded by run id.
+async fn record_results(
+    pool: &DbPool,
+    batch: &[ResultWrite],
+) -> Result<HashMap<String, RecordedResult>, sqlx::Error> {
+    let mut run_ids = Vec::with_capacity(batch.len());
+    let mut run_statuses = Vec::with_capacity(batch.len());
+    let mut outputs = Vec::with_capacity(batch.len());
+    let mut truncated = Vec::with_capacity(batch.len());
+    let mut errors = Vec::with_capacity(batch.len());
+    for write in batch {
+        if run_ids.contains(&write.task_run_id) {
+            continue;
+        }
+        run_ids.push(write.task_run_id.clone());
+        // A retryable failure still fails its run
+        run_statuses.push(match write.status {
+            ResultStatus::Completed => "COMPLETED",
+            ResultStatus::Retry | ResultStatus::Failed => "FAILED",
+        });
+        outputs.push(write.output.clone());
+        truncated.push(write.output_truncated);
+        errors.push(write.error_message.clone());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let closed: Vec<(String, f64, i32)> = sqlx::query_as(
+        "UPDATE task_runs AS r SET status = v.status, \
+         output = CASE WHEN v.status = 'COMPLETED' THEN v.output::jsonb ELSE r.output END, \
+         error_message = CASE WHEN v.status = 'FAILED' THEN v.error_message \
+         ELSE r.error_message END, \
+         output_truncated = v.output_truncated, \
+         completed_at = NOW() \
+         FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::bool[]) \
+         AS v(id, status, output, error_message, output_truncated) \
+         WHERE r.id = v.id AND r.status = 'RUNNING' \
+         RETURNING r.id, \
+         (EXTRACT(EPOCH FROM r.completed_at - r.started_at) * 1000)::float8, \
+         r.attempt_number",
+    )
+    .bind(&run_ids)
+    .bind(&run_statuses)
+    .bind(&outputs)
+    .bind(&errors)
+    .bind(&truncated)
+    .fetch_all(&mut *tx)
+    .await?;
+    if closed.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let runs: HashMap<String, (f64, i32)> = closed
+        .into_iter()
+        .map(|(id, duration_ms, attempt)| (id, (duration_ms, attempt)))
+        .collect();
+
+    // Tasks whose run was still open, in the batch's order
+    let mut task_ids = Vec::with_capacity(runs.len());
+    let mut statuses = Vec::with_capacity(runs.len());
+    let mut outputs = Vec::with_capacity(runs.len());
+    let mut errors = Vec::with_capacity(runs.len());
+    let mut retry_after = Vec::with_capacity(runs.len());
+    for write in batch {
+        if runs.contains_key(&write.task_run_id) && !task_ids.contains(&write.task_id) {
+            task_ids.push(write.task_id.clone());
+            statuses.push(write.status.as_str());
+            outputs.push(write.output.clone());
+            errors.push(write.error_message.clone());
+            retry_after.push(write.retry_after_ms);
+        }
+    }
+
+    // Only a terminal outcome is announced to the callback
+    let tasks: Vec<(String, String, Option<String>)> = sqlx::query_as(
+        "UPDATE tasks AS t SET status = v.status, \
+         output = CASE WHEN v.status = 'COMPLETED' THEN v.output::jsonb ELSE t.output END, \
+         error_message = CASE WHEN v.status = 'FAILED' THEN v.error_message \
+         ELSE t.error_message END, \
+         retry_after_ms = CASE WHEN v.status = 'RETRY' THEN v.retry_after_ms \
+         ELSE t.retry_after_ms END, \
+         updated_at = NOW() \
+         FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::bigint[]) \
+         AS v(id, status, output, error_message, retry_after_ms) \
+         WHERE t.id = v.id \
+         RETURNING t.id, t.queue_name, \
+         CASE WHEN v.status = 'RETRY' THEN NULL ELSE t.callback_url END",
+    )
+    .bind(&task_ids)
+    .bind(&statuses)
+    .bind(&outputs)
+    .bind(&errors)
+    .bind(&retry_after)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let mut tasks: HashMap<String, RecordedTask> = tasks
+        .into_iter()
+        .map(|(id, queue_name, callback_url)| (id, (queue_name, callback_url)))
+        .collect();
+    let mut recorded = HashMap::with_capacity(runs.len());
+    for write in batch {
+        if let Some(&(duration_ms, attempt)) = runs.get(&write.task_run_id) {
+            recorded
+                .entry(write.task_run_id.clone())
+                .or_insert_with(|| (duration_ms, attempt, tasks.remove(&write.task_id)));
+        }
+    }
+    Ok(recorded)
+}
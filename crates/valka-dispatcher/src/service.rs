@@ -1,19 +1,129 @@
+use crate::blob_store::{
+    BlobError, BlobStore, blob_reference, blob_reference_in, input_key, output_key,
+};
+use crate::events::EventEmitter;
 use crate::heartbeat;
-use crate::worker_handle::WorkerHandle;
+use crate::queue_policy::QueuePolicies;
+use crate::quotas::Quotas;
+use crate::result_writer::{ResultStatus, ResultWrite, ResultWriter};
+use crate::shutdown::ShutdownTracker;
+use crate::templating::{self, TemplateValues};
+use crate::webhooks::WebhookSender;
+use crate::worker_handle::{WorkerHandle, WorkerState};
 use chrono::{Duration, Utc};
 use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use serde::Serialize;
+use std::collections::HashSet;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, watch};
-use tracing::{debug, error, info, warn};
-use valka_core::{NodeId, PartitionId, TaskRunId, WorkerId};
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tracing::{Instrument, debug, error, info, info_span, warn};
+use valka_cluster::{ClusterEvent, PartitionOwnership};
+use valka_core::limits::truncate_utf8;
+use valka_core::retry::RetryPolicy;
+use valka_core::{
+    DEADLINE_EXCEEDED_BEFORE_DISPATCH, DispatcherConfig, Durability, EventsConfig, LimitsConfig,
+    LogIngesterConfig, NodeId, PartitionId, ResultWriterConfig, TaskRunId, WorkerId, fault_point,
+    trace_context,
+};
 use valka_db::DbPool;
+use valka_db::queries::dependencies;
+use valka_db::queries::task_logs::TaskLogRow;
+use valka_db::queries::task_runs::{CreateTaskRunParams, TaskRunRow};
+use valka_db::queries::tasks::{CreateTaskParams, TaskRow};
 use valka_matching::MatchingService;
+use valka_matching::decisions::DecisionOutcome;
 use valka_matching::partition::TaskEnvelope;
 use valka_proto::{
-    Heartbeat, LogBatch, SignalAck, TaskAssignment, TaskCancellation, TaskEvent, TaskResult,
-    TaskSignal, WorkerResponse, worker_response,
+    CAPABILITY_SIGNALS, Heartbeat, LogBatch, LogEntry, ServerShutdown, SignalAck, TaskAssignment,
+    TaskCancellation, TaskEvent, TaskProgress, TaskResult, TaskSignal, WORKER_LOG_RUN_PREFIX,
+    WorkerInfo, WorkerResponse, worker_response,
 };
 
+/// What a match loop registration yields: its queue's index, partition and task.
+type SlotDelivery = (
+    usize,
+    PartitionId,
+    Result<TaskEnvelope, oneshot::error::RecvError>,
+);
+
+/// A worker's live registrations in the matching service, kept across
+/// passes of its match loop.
+#[derive(Default)]
+struct MatchSlots {
+    waiting: FuturesUnordered<BoxFuture<'static, SlotDelivery>>,
+    /// `(queue index, partition)` pairs with a registration in `waiting`
+    registered: HashSet<(usize, i32)>,
+}
+
+impl MatchSlots {
+    fn is_empty(&self) -> bool {
+        self.registered.is_empty()
+    }
+}
+
+/// Resolves when partition ownership may have changed; never without a cluster.
+async fn ownership_changed(events: &mut Option<broadcast::Receiver<ClusterEvent>>) {
+    let Some(events) = events else {
+        return std::future::pending().await;
+    };
+    loop {
+        match events.recv().await {
+            Ok(ClusterEvent::PartitionsRebalanced)
+            | Err(broadcast::error::RecvError::Lagged(_)) => {
+                return;
+            }
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Closed) => return std::future::pending().await,
+        }
+    }
+}
+
+/// How often a drain checks whether its worker has gone idle.
+const DRAIN_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Point-in-time sizes of the dispatcher's worker state.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DispatcherStats {
+    pub workers: usize,
+    pub active_tasks: usize,
+    /// Responses queued across all per-worker stream channels
+    pub queued_responses: usize,
+}
+
+/// A create's input bound for the blob store, from
+/// [`DispatcherService::prepare_input`].
+#[derive(Debug)]
+pub struct InputOffload {
+    key: String,
+    json: String,
+}
+
+/// How [`DispatcherService::register_worker`] handled a worker's session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Registration {
+    New,
+    /// Replaced an earlier session of the same worker_id, whose tasks it took over
+    Adopted {
+        previous_session_id: String,
+    },
+    /// The worker_id already has a session and `reject_duplicate_workers` is set
+    Rejected,
+}
+
+/// A task taken back from its worker by [`DispatcherService::reclaim_task`].
+#[derive(Debug)]
+pub struct ReclaimedTask {
+    pub task: TaskRow,
+    /// The run closed as ABANDONED
+    pub run: TaskRunRow,
+    /// Whether the worker streams to this node and was sent a TaskCancellation
+    pub worker_notified: bool,
+}
+
 /// The dispatcher manages all connected workers and their gRPC streams.
 #[derive(Clone)]
 pub struct DispatcherService {
@@ -21,8 +131,27 @@ pub struct DispatcherService {
     matching: MatchingService,
     pool: DbPool,
     node_id: NodeId,
-    event_tx: broadcast::Sender<TaskEvent>,
+    events: EventEmitter,
     log_tx: mpsc::Sender<valka_proto::LogEntry>,
+    /// Task logs as the ingester flushes them, for live tailing
+    log_stream: broadcast::Sender<TaskLogRow>,
+    policies: QueuePolicies,
+    quotas: Quotas,
+    shutdown: ShutdownTracker,
+    /// Ephemeral tasks whose row is still being written, by task id
+    ephemeral_writes: Arc<DashMap<String, watch::Receiver<bool>>>,
+    /// The scheduler's backoff, for previewing retries
+    retry_policy: RetryPolicy,
+    webhooks: WebhookSender,
+    results: ResultWriter,
+    /// Which partitions' tasks local workers wait for; all of them without one
+    ownership: Option<PartitionOwnership>,
+    /// Bounds the create paths check new tasks against
+    limits: LimitsConfig,
+    /// Heartbeat interval handed to workers and when silent ones are dead
+    dispatcher_config: DispatcherConfig,
+    /// Where outputs and inputs over the offload threshold go; kept inline without one
+    blob_store: Option<BlobStore>,
 }
 
 impl DispatcherService {
@@ -35,27 +164,168 @@ impl DispatcherService {
     ) -> Self {
         Self {
             workers: Arc::new(DashMap::new()),
+            policies: QueuePolicies::default()
+                .with_fifo_groups(matching.fifo().clone())
+                .with_paused_queues(matching.paused().clone())
+                .with_rate_limits(matching.rate_limits().clone()),
             matching,
+            results: ResultWriter::new(pool.clone(), ResultWriterConfig::default()),
             pool,
             node_id,
-            event_tx,
+            events: EventEmitter::new(event_tx, &EventsConfig::default()),
             log_tx,
+            log_stream: broadcast::channel(LogIngesterConfig::default().stream_buffer_size).0,
+            quotas: Quotas::default(),
+            shutdown: ShutdownTracker::default(),
+            ephemeral_writes: Arc::default(),
+            retry_policy: RetryPolicy::default(),
+            webhooks: WebhookSender::default(),
+            ownership: None,
+            limits: LimitsConfig::default(),
+            dispatcher_config: DispatcherConfig::default(),
+            blob_store: None,
         }
     }
 
-    pub async fn register_worker(&self, handle: WorkerHandle) {
+    /// Replace the default event coalescing settings.
+    pub fn with_events_config(mut self, config: &EventsConfig) -> Self {
+        self.events = EventEmitter::new(self.events.sender().clone(), config);
+        self
+    }
+
+    /// Size the live log stream buffer from `config`.
+    pub fn with_log_ingester_config(mut self, config: &LogIngesterConfig) -> Self {
+        self.log_stream = broadcast::channel(config.stream_buffer_size.max(1)).0;
+        self
+    }
+
+    /// Batch result writes per `config`.
+    pub fn with_result_writer_config(mut self, config: &ResultWriterConfig) -> Self {
+        self.results = ResultWriter::new(self.pool.clone(), config.clone());
+        self
+    }
+
+    /// Only register local workers on the partitions this node owns.
+    pub fn with_partition_ownership(mut self, ownership: PartitionOwnership) -> Self {
+        self.ownership = Some(ownership);
+        self
+    }
+
+    /// Replace the default retry backoff used for retry previews.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Send `callback_url` webhooks through `webhooks`; without one they are dropped.
+    pub fn with_webhooks(mut self, webhooks: WebhookSender) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    /// Replace the default task create limits.
+    pub fn with_limits(mut self, limits: LimitsConfig) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Replace the default heartbeat interval and dead-worker threshold.
+    pub fn with_dispatcher_config(mut self, config: DispatcherConfig) -> Self {
+        self.dispatcher_config = config;
+        self
+    }
+
+    /// Offload large outputs and inputs to `store`.
+    pub fn with_blob_store(mut self, store: BlobStore) -> Self {
+        self.blob_store = Some(store);
+        self
+    }
+
+    /// Register a worker's session. A worker_id that already has one either
+    /// takes it over (the old stream is sent ServerShutdown and closed, its
+    /// active tasks move to the new handle) or, with
+    /// `reject_duplicate_workers`, is turned away.
+    pub async fn register_worker(&self, mut handle: WorkerHandle) -> Registration {
         let worker_id = handle.worker_id.clone();
-        self.workers.insert(worker_id.0.clone(), handle);
+        let labels = handle.labels.clone();
+        let previous = match self.workers.entry(worker_id.0.clone()) {
+            Entry::Occupied(_) if self.dispatcher_config.reject_duplicate_workers => {
+                warn!(
+                    worker_id = %worker_id,
+                    session_id = %handle.session_id,
+                    "Rejected session for a worker_id that is already connected"
+                );
+                return Registration::Rejected;
+            }
+            Entry::Occupied(mut entry) => {
+                let adopted = entry.get().active_tasks.len();
+                handle.adopt_tasks(entry.get_mut());
+                Some((entry.insert(handle), adopted))
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(handle);
+                None
+            }
+        };
+        self.matching.set_worker_labels(&worker_id, labels);
         valka_core::metrics::set_active_workers(self.workers.len() as f64);
+
+        let Some((previous, adopted)) = previous else {
+            return Registration::New;
+        };
+        info!(
+            worker_id = %worker_id,
+            previous_session_id = %previous.session_id,
+            adopted_tasks = adopted,
+            "Worker reconnected; taking over its previous session"
+        );
+        let notice = WorkerResponse {
+            response: Some(worker_response::Response::ServerShutdown(ServerShutdown {
+                reason: "Superseded by a newer session of this worker".to_string(),
+                drain_seconds: 0,
+            })),
+        };
+        // The old stream may be dead already; never wait on it
+        let _ = previous.response_tx.try_send(notice);
+        previous.set_state(WorkerState::Closed);
+        Registration::Adopted {
+            previous_session_id: previous.session_id,
+        }
+    }
+
+    /// Whether `session_id` is the worker's registered session.
+    pub fn is_current_session(&self, worker_id: &WorkerId, session_id: &str) -> bool {
+        self.workers
+            .get(worker_id.as_ref())
+            .is_some_and(|h| h.session_id == session_id)
     }
 
     pub async fn deregister_worker(&self, worker_id: &WorkerId) {
-        if let Some((_, handle)) = self.workers.remove(worker_id.as_ref()) {
+        let removed = self.workers.remove(worker_id.as_ref());
+        self.finish_deregister(worker_id, removed).await;
+    }
+
+    /// Deregister the worker only if `session_id` is still its session, so a
+    /// stream that was taken over doesn't remove its successor.
+    pub async fn deregister_session(&self, worker_id: &WorkerId, session_id: &str) {
+        let removed = self
+            .workers
+            .remove_if(worker_id.as_ref(), |_, h| h.session_id == session_id);
+        self.finish_deregister(worker_id, removed).await;
+    }
+
+    async fn finish_deregister(
+        &self,
+        worker_id: &WorkerId,
+        removed: Option<(String, WorkerHandle)>,
+    ) {
+        if let Some((_, handle)) = removed {
             // Deregister from matching service
             self.matching.deregister_worker(worker_id);
 
             // Reset delivered (unacknowledged) signals for all active tasks
             for task_id in &handle.active_tasks {
+                self.matching.fifo().release(task_id);
                 if let Err(e) =
                     valka_db::queries::signals::reset_delivered_signals(&self.pool, task_id).await
                 {
@@ -63,6 +333,12 @@ impl DispatcherService {
                 }
             }
 
+            valka_core::metrics::set_worker_active_tasks(
+                worker_id.as_ref(),
+                &handle.worker_name,
+                0,
+                0,
+            );
             info!(
                 worker_id = %worker_id,
                 active_tasks = handle.active_tasks.len(),
@@ -73,84 +349,484 @@ impl DispatcherService {
         valka_core::metrics::set_active_workers(self.workers.len() as f64);
     }
 
-    /// Background loop: register as waiting in matching service, receive tasks, push to worker
+    /// Background loop: register as waiting in matching service, receive tasks, push to worker.
+    /// A registration stays in place until a task arrives on it, so only that
+    /// one is renewed on the next pass.
     pub async fn run_worker_match_loop(&self, worker_id: WorkerId, queues: Vec<String>) {
-        use futures::FutureExt;
-
         let num_partitions = self.matching.config().num_partitions;
+        let mut policy_rx = self.policies.subscribe();
+        let Some((mut state_rx, labels, session_id)) = self
+            .workers
+            .get(worker_id.as_ref())
+            .map(|h| (h.state.subscribe(), h.labels.clone(), h.session_id.clone()))
+        else {
+            return;
+        };
+        let mut slots = MatchSlots::default();
+        let mut cluster_events = self.ownership.as_ref().map(|o| o.subscribe_events());
+        let mut owned = self.owned_partitions(&queues, num_partitions).await;
 
         loop {
-            let available = {
+            let (available, version, queue_full) = {
                 match self.workers.get(worker_id.as_ref()) {
-                    Some(handle) => handle.available_slots(),
-                    None => return, // Worker disconnected
+                    // A newer session took over and runs its own loop. Any of
+                    // its slots this withdraws get registered again.
+                    Some(handle) if handle.session_id != session_id => {
+                        self.withdraw_slots(&worker_id, &queues, &mut slots).await;
+                        return;
+                    }
+                    // A draining worker takes nothing new
+                    Some(handle) if handle.is_draining() => break,
+                    Some(handle) => (
+                        handle.available_slots(),
+                        handle.parsed_version.clone(),
+                        queues
+                            .iter()
+                            .map(|queue| handle.available_queue_slots(queue) <= 0)
+                            .collect::<Vec<_>>(),
+                    ),
+                    None => {
+                        // Worker disconnected
+                        self.withdraw_slots(&worker_id, &queues, &mut slots).await;
+                        return;
+                    }
                 }
             };
 
             if available <= 0 {
+                // Full: don't hold on to tasks other workers could take
+                if !slots.is_empty() {
+                    self.withdraw_slots(&worker_id, &queues, &mut slots).await;
+                }
                 tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
                 continue;
             }
+            // A queue at its own cap keeps no registrations
+            if slots.registered.iter().any(|(index, _)| queue_full[*index]) {
+                self.withdraw_slots(&worker_id, &queues, &mut slots).await;
+            }
 
-            let mut receivers = Vec::new();
-            for queue in &queues {
+            policy_rx.borrow_and_update();
+            for (index, queue) in queues.iter().enumerate() {
+                if queue_full[index] || self.policies.exclusion_reason(queue, &version).is_some() {
+                    continue;
+                }
                 for pid in 0..num_partitions {
                     let partition_id = PartitionId(pid);
+                    // Other nodes match tasks for the partitions they own
+                    if owned.as_ref().is_some_and(|o| !o.contains(&(index, pid)))
+                        || !slots.registered.insert((index, pid))
+                    {
+                        continue;
+                    }
                     let rx = self.matching.register_worker(
                         queue,
                         partition_id,
                         worker_id.clone(),
                     );
-                    receivers.push((queue.clone(), partition_id, rx));
+                    slots
+                        .waiting
+                        .push(Box::pin(async move { (index, partition_id, rx.await) }));
                 }
             }
 
-            if receivers.is_empty() {
-                tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-                continue;
+            let delivered = tokio::select! {
+                Some(delivered) = slots.waiting.next() => Some(delivered),
+                Ok(()) = policy_rx.changed() => None,
+                Ok(()) = state_rx.changed() => None,
+                () = ownership_changed(&mut cluster_events) => {
+                    owned = self.owned_partitions(&queues, num_partitions).await;
+                    None
+                }
+                // Also poll for a capped queue's slots freeing up
+                () = tokio::time::sleep(tokio::time::Duration::from_millis(50)),
+                    if slots.is_empty() || queue_full.contains(&true) => continue,
+            };
+
+            match delivered {
+                Some((index, pid, Ok(envelope))) => {
+                    slots.registered.remove(&(index, pid.0));
+                    let queue = &queues[index];
+                    // The queue's minimum may have been raised, or a drain
+                    // started, while we were waiting
+                    let skipped = if self.policies.exclusion_reason(queue, &version).is_some()
+                        || self.is_draining(&worker_id)
+                    {
+                        Some(DecisionOutcome::SkippedExcluded(worker_id.clone()))
+                    } else if !valka_core::labels::satisfies(&labels, &envelope.required_labels) {
+                        // Matching already checks; never hand over mismatched work
+                        Some(DecisionOutcome::SkippedLabelMismatch(worker_id.clone()))
+                    } else {
+                        None
+                    };
+                    if let Some(outcome) = skipped {
+                        self.matching
+                            .decisions()
+                            .record(queue, pid, &envelope.task_id, outcome);
+                        self.buffer_or_release(queue, pid, envelope).await;
+                        continue;
+                    }
+                    self.dispatch_to_worker(&worker_id, pid, envelope).await;
+                }
+                Some((index, pid, Err(_))) => {
+                    slots.registered.remove(&(index, pid.0));
+                    debug!(worker_id = %worker_id, "Match channel closed");
+                }
+                None => {
+                    // Re-register against the new policies or state
+                    debug!(
+                        worker_id = %worker_id,
+                        "Queue policies, worker state or partition ownership changed"
+                    );
+                    self.withdraw_slots(&worker_id, &queues, &mut slots).await;
+                }
             }
+        }
 
-            let futs: Vec<_> = receivers
-                .into_iter()
-                .map(|(queue, pid, rx)| {
-                    Box::pin(async move { (queue, pid, rx.await) })
-                })
-                .collect();
+        // Draining: take its waiting slots out of the partitions
+        self.withdraw_slots(&worker_id, &queues, &mut slots).await;
+        self.matching.deregister_worker(&worker_id);
+    }
 
-            let (first_result, _index, remaining) =
-                futures::future::select_all(futs).await;
+    /// `(queue index, partition)` pairs this node owns, or None when it owns
+    /// every partition.
+    async fn owned_partitions(
+        &self,
+        queues: &[String],
+        num_partitions: i32,
+    ) -> Option<HashSet<(usize, i32)>> {
+        let ownership = self.ownership.as_ref()?;
+        let mut owned = HashSet::new();
+        for (index, queue) in queues.iter().enumerate() {
+            for pid in 0..num_partitions {
+                if ownership.owns_partition(queue, pid).await {
+                    owned.insert((index, pid));
+                }
+            }
+        }
+        Some(owned)
+    }
+
+    /// Take the worker's waiting slots out of the partitions and put back any
+    /// task that reached one of them but was never picked up.
+    async fn withdraw_slots(
+        &self,
+        worker_id: &WorkerId,
+        queues: &[String],
+        slots: &mut MatchSlots,
+    ) {
+        use futures::FutureExt;
 
-            for fut in remaining {
-                if let Some((q, p, Ok(envelope))) = fut.now_or_never() {
-                    self.matching.buffer_task(&q, p, envelope);
+        self.matching.withdraw_worker(worker_id);
+        // With the senders gone every registration resolves right away
+        while let Some(Some((index, pid, result))) = slots.waiting.next().now_or_never() {
+            if let Ok(envelope) = result {
+                self.buffer_or_release(&queues[index], pid, envelope).await;
+            }
+        }
+        slots.waiting.clear();
+        slots.registered.clear();
+    }
+
+    async fn dispatch_to_worker(
+        &self,
+        worker_id: &WorkerId,
+        partition_id: PartitionId,
+        envelope: TaskEnvelope,
+    ) {
+        // Never hand a worker work from a queue it didn't ask for
+        let subscribed = self
+            .workers
+            .get(worker_id.as_ref())
+            .is_none_or(|h| h.queues.contains(&envelope.queue_name));
+        if !subscribed {
+            warn!(
+                worker_id = %worker_id,
+                task_id = %envelope.task_id,
+                queue = %envelope.queue_name,
+                "Task reached a worker not subscribed to its queue; buffering it again"
+            );
+            self.matching.decisions().record(
+                &envelope.queue_name,
+                partition_id,
+                &envelope.task_id,
+                DecisionOutcome::SkippedQueueMismatch(worker_id.clone()),
+            );
+            let queue = envelope.queue_name.clone();
+            self.buffer_or_release(&queue, partition_id, envelope).await;
+            return;
+        }
+        let span = info_span!(
+            "dispatch_task",
+            task_id = %envelope.task_id,
+            queue = %envelope.queue_name,
+            worker_id = %worker_id,
+        );
+        if let Some(traceparent) = &envelope.traceparent {
+            trace_context::set_parent(&span, traceparent);
+        }
+        self.dispatch_traced(worker_id, partition_id, envelope)
+            .instrument(span)
+            .await
+    }
+
+    async fn dispatch_traced(
+        &self,
+        worker_id: &WorkerId,
+        partition_id: PartitionId,
+        mut envelope: TaskEnvelope,
+    ) {
+        let started = std::time::Instant::now();
+        let ephemeral = envelope.ephemeral.take();
+        // Don't spend a worker slot on a task that can no longer meet its deadline
+        let now_ms = Utc::now().timestamp_millis();
+        let remaining_budget_ms = match envelope.deadline_ms {
+            Some(deadline) if deadline <= now_ms => {
+                self.matching.decisions().record(
+                    &envelope.queue_name,
+                    partition_id,
+                    &envelope.task_id,
+                    DecisionOutcome::Expired,
+                );
+                // An ephemeral task needs its row before the expiry can be recorded
+                if let Some(params) = ephemeral
+                    && !self.persist_unassigned(*params).await
+                {
+                    return;
+                }
+                if let Err(e) = self
+                    .expire_before_dispatch(&envelope.task_id, &envelope.queue_name)
+                    .await
+                {
+                    error!(task_id = %envelope.task_id, error = %e, "Failed to expire task");
                 }
+                self.matching.fifo().release(&envelope.task_id);
+                return;
             }
+            Some(deadline) => deadline - now_ms,
+            None => 0,
+        };
 
-            match first_result.2 {
-                Ok(envelope) => {
-                    self.dispatch_to_worker(&worker_id, envelope).await;
+        if let Err(e) = self.resolve_input(&mut envelope).await {
+            error!(task_id = %envelope.task_id, error = %e, "Failed to fetch offloaded task input");
+            self.matching.decisions().record(
+                &envelope.queue_name,
+                partition_id,
+                &envelope.task_id,
+                DecisionOutcome::DispatchFailed(worker_id.clone()),
+            );
+            // No later attempt would find the input either
+            if matches!(e, BlobError::ForeignReference(_) | BlobError::NotFound(_)) {
+                if let Some(params) = ephemeral
+                    && !self.persist_unassigned(*params).await
+                {
+                    return;
                 }
-                Err(_) => {
-                    debug!(worker_id = %worker_id, "Match channel closed");
+                let message = format!("Task input could not be fetched: {e}");
+                if let Err(e) = self
+                    .fail_before_dispatch(&envelope.task_id, &envelope.queue_name, &message)
+                    .await
+                {
+                    error!(task_id = %envelope.task_id, error = %e, "Failed to fail task");
                 }
+                self.matching.fifo().release(&envelope.task_id);
+                return;
             }
+            match ephemeral {
+                Some(params) => {
+                    self.persist_unassigned(*params).await;
+                }
+                None => self.release_undispatched(&envelope.task_id).await,
+            }
+            return;
         }
-    }
 
-    async fn dispatch_to_worker(&self, worker_id: &WorkerId, mut envelope: TaskEnvelope) {
         // Create a task run
         let run_id = TaskRunId::new();
         envelope.task_run_id = run_id.0.clone();
 
-        let lease_duration = Duration::seconds(envelope.timeout_seconds as i64 + 30);
+        let mut run_timeout_secs = envelope.timeout_seconds as i64;
+        if remaining_budget_ms > 0 {
+            run_timeout_secs = run_timeout_secs.min((remaining_budget_ms + 999) / 1000);
+        }
+        let lease_duration = Duration::seconds(run_timeout_secs + 30);
         let lease_expires = Utc::now() + lease_duration;
 
-        // Use a transaction to atomically: increment attempt, set RUNNING, create run
+        // Ephemeral tasks are written after the assignment is sent
+        if ephemeral.is_none()
+            && !self
+                .record_dispatch(worker_id, &envelope, &run_id, lease_expires)
+                .await
+        {
+            self.matching.decisions().record(
+                &envelope.queue_name,
+                partition_id,
+                &envelope.task_id,
+                DecisionOutcome::DispatchFailed(worker_id.clone()),
+            );
+            self.release_undispatched(&envelope.task_id).await;
+            return;
+        }
+
+        valka_core::metrics::record_dispatch_latency(
+            &envelope.queue_name,
+            started.elapsed().as_secs_f64() * 1000.0,
+            &envelope.task_id,
+        );
+        if let Some(ready_at_ms) = envelope.ready_at_ms {
+            valka_core::metrics::record_queue_wait(
+                &envelope.queue_name,
+                (Utc::now().timestamp_millis() - ready_at_ms).max(0) as f64,
+                &envelope.task_id,
+            );
+        }
+
+        // Emit TaskEvent for RUNNING
+        self.emit_event(&envelope.task_id, &envelope.queue_name, 3); // 3 = RUNNING
+
+        let mut input = envelope.input.unwrap_or_default();
+        if self.policies.templates_input(&envelope.queue_name) {
+            let values = TemplateValues {
+                queue_name: &envelope.queue_name,
+                task_id: &envelope.task_id,
+                run_id: &run_id.0,
+                attempt: envelope.attempt_number,
+                dispatched_at: Utc::now(),
+                metadata: &envelope.metadata,
+            };
+            if let Some(rendered) = templating::render_input(&input, &values) {
+                input = rendered;
+            }
+        }
+
+        // Build assignment message
+        let assignment = TaskAssignment {
+            task_id: envelope.task_id.clone(),
+            task_run_id: run_id.0.clone(),
+            queue_name: envelope.queue_name.clone(),
+            task_name: envelope.task_name.clone(),
+            input,
+            attempt_number: envelope.attempt_number,
+            timeout_seconds: envelope.timeout_seconds,
+            metadata: envelope.metadata,
+            remaining_budget_ms,
+            traceparent: trace_context::current_traceparent().unwrap_or_default(),
+            priority: envelope.priority,
+            created_at_ms: envelope.created_at_ms.unwrap_or_default(),
+            scheduled_at_ms: envelope.scheduled_at_ms.unwrap_or_default(),
+        };
+
+        // Results wait on this until the write-behind lands
+        let written = ephemeral.as_ref().map(|_| {
+            let (tx, rx) = watch::channel(false);
+            self.ephemeral_writes.insert(envelope.task_id.clone(), rx);
+            tx
+        });
+
+        // Send to worker via their response channel
+        let send_fault = fault_point!("dispatcher.before_send", &envelope.queue_name);
+        let Some(mut handle) = self.workers.get_mut(worker_id.as_ref()) else {
+            self.matching.decisions().record(
+                &envelope.queue_name,
+                partition_id,
+                &envelope.task_id,
+                DecisionOutcome::DispatchFailed(worker_id.clone()),
+            );
+            if let Some(params) = ephemeral {
+                self.ephemeral_writes.remove(&envelope.task_id);
+                self.persist_unassigned(*params).await;
+            }
+            return;
+        };
+        handle.assign_task(envelope.task_id.clone(), &envelope.queue_name);
+        let response = WorkerResponse {
+            response: Some(worker_response::Response::TaskAssignment(assignment)),
+        };
+        if send_fault.is_err() || handle.response_tx.send(response).await.is_err() {
+            warn!(worker_id = %worker_id, "Failed to send task assignment - worker disconnected");
+            drop(handle);
+            valka_core::metrics::record_worker_send_failure("assignment");
+            self.matching.decisions().record(
+                &envelope.queue_name,
+                partition_id,
+                &envelope.task_id,
+                DecisionOutcome::DispatchFailed(worker_id.clone()),
+            );
+            if let Some(params) = ephemeral {
+                self.ephemeral_writes.remove(&envelope.task_id);
+                self.persist_unassigned(*params).await;
+            }
+            return;
+        }
+        self.matching.decisions().record(
+            &envelope.queue_name,
+            partition_id,
+            &envelope.task_id,
+            DecisionOutcome::Dispatched(worker_id.clone()),
+        );
+        valka_core::metrics::record_task_assignment_sent(&envelope.queue_name);
+
+        if let (Some(params), Some(written)) = (ephemeral, written) {
+            // Nobody can have signalled a task with no row yet
+            drop(handle);
+            let run = CreateTaskRunParams {
+                id: run_id.0,
+                task_id: envelope.task_id,
+                attempt_number: envelope.attempt_number,
+                worker_id: worker_id.0.clone(),
+                assigned_node_id: self.node_id.0.clone(),
+                lease_expires_at: lease_expires,
+            };
+            self.write_behind(*params, run, written);
+            return;
+        }
+
+        // Deliver any pending signals for this task; a worker that can't take
+        // them leaves them pending
+        let tx = handle.response_tx.clone();
+        let takes_signals = handle.supports(CAPABILITY_SIGNALS);
+        drop(handle); // Release DashMap guard before DB call
+        if !takes_signals {
+            return;
+        }
+        match valka_db::queries::signals::get_pending_signals(&self.pool, &envelope.task_id).await {
+            Ok(signals) => {
+                for sig in signals {
+                    let signal_response = WorkerResponse {
+                        response: Some(worker_response::Response::TaskSignal(TaskSignal {
+                            signal_id: sig.id.clone(),
+                            task_id: sig.task_id,
+                            signal_name: sig.signal_name,
+                            payload: sig.payload.map(|v| v.to_string()).unwrap_or_default(),
+                            timestamp_ms: sig.created_at.timestamp_millis(),
+                        })),
+                    };
+                    if tx.send(signal_response).await.is_ok() {
+                        let _ =
+                            valka_db::queries::signals::mark_delivered(&self.pool, &sig.id).await;
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(task_id = %envelope.task_id, error = %e, "Failed to load pending signals");
+            }
+        }
+    }
+
+    /// Atomically increment the attempt, set RUNNING and create the run.
+    /// Returns false (after logging) if any step failed.
+    async fn record_dispatch(
+        &self,
+        worker_id: &WorkerId,
+        envelope: &TaskEnvelope,
+        run_id: &TaskRunId,
+        lease_expires: chrono::DateTime<Utc>,
+    ) -> bool {
         let mut tx = match self.pool.begin().await {
             Ok(tx) => tx,
             Err(e) => {
                 error!(task_id = %envelope.task_id, error = %e, "Failed to begin transaction");
-                return;
+                return false;
             }
         };
 
@@ -164,7 +840,7 @@ impl DispatcherService {
         {
             error!(task_id = %envelope.task_id, error = %e, "Failed to increment attempt count");
             let _ = tx.rollback().await;
-            return;
+            return false;
         }
 
         // Update task status to RUNNING
@@ -176,7 +852,7 @@ impl DispatcherService {
         {
             error!(task_id = %envelope.task_id, error = %e, "Failed to update task status");
             let _ = tx.rollback().await;
-            return;
+            return false;
         }
 
         // Create task run
@@ -195,233 +871,730 @@ impl DispatcherService {
         {
             error!(task_id = %envelope.task_id, error = %e, "Failed to create task run");
             let _ = tx.rollback().await;
-            return;
+            return false;
+        }
+
+        if let Err(e) = fault_point!(
+            "dispatcher.record_dispatch.before_commit",
+            &envelope.queue_name
+        ) {
+            error!(task_id = %envelope.task_id, error = %e, "Failed to commit dispatch transaction");
+            let _ = tx.rollback().await;
+            return false;
         }
 
         if let Err(e) = tx.commit().await {
             error!(task_id = %envelope.task_id, error = %e, "Failed to commit dispatch transaction");
+            return false;
+        }
+        true
+    }
+
+    /// Replace an offloaded input with the blob it references, so the worker
+    /// gets the input it was created with.
+    async fn resolve_input(&self, envelope: &mut TaskEnvelope) -> Result<(), BlobError> {
+        let Some(blobs) = &self.blob_store else {
+            return Ok(());
+        };
+        let Some(reference) = envelope.input.as_deref().and_then(blob_reference_in) else {
+            return Ok(());
+        };
+        let input = blobs.get_input(&envelope.task_id, &reference).await?;
+        envelope.input = Some(String::from_utf8_lossy(&input).into_owned());
+        Ok(())
+    }
+
+    /// Check a create's `input` and, when it is over the offload threshold,
+    /// put the reference it will leave in its place. The blob is written by
+    /// [`Self::write_input`] once the create has passed its other checks.
+    pub fn prepare_input(
+        &self,
+        task_id: &str,
+        input: &mut Option<serde_json::Value>,
+    ) -> Result<Option<InputOffload>, BlobError> {
+        let Some(value) = input.as_ref() else {
+            return Ok(None);
+        };
+        if blob_reference(value).is_some() {
+            return Err(BlobError::ReservedShape("input"));
+        }
+        let Some(blobs) = &self.blob_store else {
+            return Ok(None);
+        };
+        let json = value.to_string();
+        if !blobs.should_offload(json.len()) {
+            return Ok(None);
+        }
+        blobs.check_size(json.len())?;
+        let key = input_key(task_id);
+        *input = Some(blobs.reference(&key));
+        Ok(Some(InputOffload { key, json }))
+    }
+
+    /// Write an input [`Self::prepare_input`] set aside.
+    pub async fn write_input(&self, offload: &InputOffload) -> Result<(), BlobError> {
+        match &self.blob_store {
+            Some(blobs) => blobs.put(&offload.key, &offload.json).await.map(drop),
+            None => Ok(()),
+        }
+    }
+
+    /// Delete a written input whose task was not created after all.
+    pub async fn discard_input(&self, offload: Option<InputOffload>) {
+        let (Some(blobs), Some(offload)) = (&self.blob_store, offload) else {
             return;
+        };
+        if let Err(e) = blobs.delete(&offload.key).await {
+            warn!(key = %offload.key, error = %e, "Failed to delete offloaded input");
         }
+    }
 
-        // Record dispatch latency metric
-        valka_core::metrics::record_dispatch_latency(&envelope.queue_name, 0.0);
+    /// Put a task whose dispatch couldn't be recorded back to PENDING. The
+    /// TaskReader left it DISPATCHING, where nothing would pick it up again.
+    async fn release_undispatched(&self, task_id: &str) {
+        self.matching.fifo().release(task_id);
+        if let Err(e) = sqlx::query(
+            "UPDATE tasks SET status = 'PENDING', updated_at = NOW() \
+             WHERE id = $1 AND status = 'DISPATCHING'",
+        )
+        .bind(task_id)
+        .execute(&self.pool)
+        .await
+        {
+            error!(task_id, error = %e, "Failed to reset DISPATCHING task to PENDING");
+        }
+    }
 
-        // Emit TaskEvent for RUNNING
-        self.emit_event(&envelope.task_id, &envelope.queue_name, 3); // 3 = RUNNING
+    /// Why `params` cannot be created with ephemeral durability, if it cannot.
+    pub fn ephemeral_rejection(&self, params: &CreateTaskParams) -> Option<String> {
+        if params.durability != Durability::Ephemeral {
+            return None;
+        }
+        if !self.policies.allows_ephemeral(&params.queue_name) {
+            return Some(format!(
+                "Queue {} does not allow ephemeral tasks",
+                params.queue_name
+            ));
+        }
+        if params.scheduled_at.is_some() {
+            return Some("Ephemeral tasks cannot be scheduled".to_string());
+        }
+        if params.idempotency_key.is_some() {
+            return Some("Ephemeral tasks cannot use an idempotency key".to_string());
+        }
+        if params.fifo_key.is_some() {
+            return Some("Ephemeral tasks cannot use a fifo_key".to_string());
+        }
+        None
+    }
 
-        // Build assignment message
-        let assignment = TaskAssignment {
-            task_id: envelope.task_id.clone(),
-            task_run_id: run_id.0.clone(),
-            queue_name: envelope.queue_name.clone(),
-            task_name: envelope.task_name.clone(),
-            input: envelope.input.unwrap_or_default(),
-            attempt_number: envelope.attempt_number,
-            timeout_seconds: envelope.timeout_seconds,
-            metadata: envelope.metadata,
+    /// Hot-path offer of a task just written as PENDING. Returns true if a
+    /// waiting worker took it.
+    ///
+    /// On a FIFO queue a keyed task is first claimed in PG, which only
+    /// succeeds when it is next in line for its key; otherwise it is left for
+    /// the TaskReader. A claimed task that finds no worker is buffered. Tasks
+    /// on a paused queue stay PENDING until it is resumed, and those on a rate
+    /// limited one are left for the TaskReaders, which meter its dispatch.
+    pub async fn offer_new_task(&self, partition: PartitionId, envelope: TaskEnvelope) -> bool {
+        let queue_name = envelope.queue_name.clone();
+        if self.policies.is_paused(&queue_name)
+            || self.matching.rate_limits().is_limited(&queue_name)
+        {
+            return false;
+        }
+        let fifo = self.matching.fifo();
+        let Some(key) = fifo.key_for(&queue_name, envelope.fifo_key.as_deref()) else {
+            return self
+                .matching
+                .offer_task(&queue_name, partition, envelope)
+                .is_ok();
         };
+        if fifo.is_held(&queue_name, key) {
+            return false;
+        }
+        match valka_db::queries::tasks::claim_fifo_task(&self.pool, &envelope.task_id).await {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(e) => {
+                warn!(task_id = %envelope.task_id, error = %e, "Failed to claim FIFO task");
+                return false;
+            }
+        }
 
-        // Send to worker via their response channel
-        if let Some(mut handle) = self.workers.get_mut(worker_id.as_ref()) {
-            handle.assign_task(envelope.task_id.clone());
-            let response = WorkerResponse {
-                response: Some(worker_response::Response::TaskAssignment(assignment)),
-            };
-            if handle.response_tx.send(response).await.is_err() {
-                warn!(worker_id = %worker_id, "Failed to send task assignment - worker disconnected");
-                return;
+        match self.matching.offer_task(&queue_name, partition, envelope) {
+            Ok(()) => true,
+            Err(envelope) => {
+                self.buffer_or_release(&queue_name, partition, envelope)
+                    .await;
+                false
             }
+        }
+    }
 
-            // Deliver any pending signals for this task
-            let tx = handle.response_tx.clone();
-            drop(handle); // Release DashMap guard before DB call
-            match valka_db::queries::signals::get_pending_signals(&self.pool, &envelope.task_id)
-                .await
-            {
-                Ok(signals) => {
-                    for sig in signals {
-                        let signal_response = WorkerResponse {
-                            response: Some(worker_response::Response::TaskSignal(TaskSignal {
-                                signal_id: sig.id.clone(),
-                                task_id: sig.task_id,
-                                signal_name: sig.signal_name,
-                                payload: sig.payload.map(|v| v.to_string()).unwrap_or_default(),
-                                timestamp_ms: sig.created_at.timestamp_millis(),
-                            })),
-                        };
-                        if tx.send(signal_response).await.is_ok() {
-                            let _ = valka_db::queries::signals::mark_delivered(&self.pool, &sig.id)
-                                .await;
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!(task_id = %envelope.task_id, error = %e, "Failed to load pending signals");
-                }
+    /// Buffer a task no local worker took. Whatever a full buffer turns away,
+    /// this task or a lower-priority one it evicted, goes back to PENDING.
+    async fn buffer_or_release(&self, queue: &str, partition: PartitionId, envelope: TaskEnvelope) {
+        let outcome = self.matching.buffer_task(queue, partition, envelope);
+        if let Some(task) = outcome.into_unbuffered() {
+            self.release_unbuffered(task).await;
+        }
+    }
+
+    /// Put a task taken out of (or turned away by) the matching buffers back
+    /// to PENDING. An ephemeral one has no row yet, so it is written instead.
+    pub async fn release_unbuffered(&self, task: TaskEnvelope) {
+        match task.ephemeral {
+            Some(params) => {
+                self.persist_unassigned(*params).await;
+            }
+            None => self.release_undispatched(&task.task_id).await,
+        }
+    }
+
+    /// Put every task still buffered on this node back to PENDING, so
+    /// another node's TaskReaders can pick them up. Returns how many.
+    pub async fn spill_buffered(&self) -> usize {
+        let tasks = self.matching.drain_buffered();
+        let count = tasks.len();
+        for task in tasks {
+            self.release_unbuffered(task).await;
+        }
+        count
+    }
+
+    /// Offer an ephemeral task straight to a waiting worker without writing it.
+    /// Hands the params back when no worker took it, or when the queue is rate
+    /// limited, so the caller can persist them as PENDING instead.
+    pub fn offer_ephemeral(&self, params: CreateTaskParams) -> Result<(), Box<CreateTaskParams>> {
+        if self.matching.rate_limits().is_limited(&params.queue_name) {
+            return Err(Box::new(params));
+        }
+        let partition = PartitionId(params.partition_id);
+        let queue_name = params.queue_name.clone();
+        let now_ms = Utc::now().timestamp_millis();
+        let envelope = TaskEnvelope {
+            task_id: params.id.clone(),
+            task_run_id: String::new(),
+            queue_name: params.queue_name.clone(),
+            task_name: params.task_name.clone(),
+            input: params.input.as_ref().map(|v| v.to_string()),
+            attempt_number: 1,
+            timeout_seconds: params.timeout_seconds,
+            metadata: params.metadata.to_string(),
+            priority: params.priority,
+            deadline_ms: params.deadline_at.map(|d| d.timestamp_millis()),
+            ready_at_ms: Some(now_ms),
+            created_at_ms: Some(now_ms),
+            scheduled_at_ms: params.scheduled_at.map(|at| at.timestamp_millis()),
+            traceparent: params.traceparent.clone(),
+            fifo_key: params.fifo_key.clone(),
+            required_labels: params.required_labels.clone(),
+            ephemeral: Some(Box::new(params)),
+        };
+        self.matching
+            .offer_task(&queue_name, partition, envelope)
+            .map_err(|mut envelope| {
+                valka_core::metrics::record_ephemeral_task(&queue_name, "unmatched");
+                envelope
+                    .ephemeral
+                    .take()
+                    .expect("ephemeral envelope keeps its params")
+            })
+    }
+
+    /// Write an ephemeral task that never reached a worker as PENDING, so the
+    /// TaskReader picks it up like any other task.
+    async fn persist_unassigned(&self, params: CreateTaskParams) -> bool {
+        let task_id = params.id.clone();
+        let created = match fault_point!("dispatcher.persist_unassigned", &params.queue_name) {
+            Ok(()) => valka_db::queries::tasks::create_task(&self.pool, params).await,
+            Err(e) => Err(e.into()),
+        };
+        match created {
+            Ok(_) => true,
+            Err(e) => {
+                error!(task_id = %task_id, error = %e, "Failed to persist ephemeral task");
+                false
             }
         }
     }
 
+    /// Record an assigned ephemeral task and its run in the background.
+    fn write_behind(
+        &self,
+        params: CreateTaskParams,
+        run: CreateTaskRunParams,
+        written: watch::Sender<bool>,
+    ) {
+        let pool = self.pool.clone();
+        let pending = self.ephemeral_writes.clone();
+        tokio::spawn(async move {
+            let outcome =
+                match valka_db::queries::tasks::record_ephemeral_dispatch(&pool, &params, &run)
+                    .await
+                {
+                    Ok(()) => "dispatched",
+                    Err(e) => {
+                        error!(task_id = %params.id, error = %e, "Failed to record ephemeral task");
+                        "write_failed"
+                    }
+                };
+            valka_core::metrics::record_ephemeral_task(&params.queue_name, outcome);
+            pending.remove(&params.id);
+            let _ = written.send(true);
+        });
+    }
+
+    /// Fail a task whose deadline passed before it could be dispatched.
+    /// Returns None if the task had already moved past the dispatchable states.
+    pub async fn expire_before_dispatch(
+        &self,
+        task_id: &str,
+        queue_name: &str,
+    ) -> Result<Option<valka_db::queries::tasks::TaskRow>, sqlx::Error> {
+        let expired = self
+            .fail_before_dispatch(task_id, queue_name, DEADLINE_EXCEEDED_BEFORE_DISPATCH)
+            .await?;
+        if expired.is_some() {
+            info!(task_id, "Task deadline passed before dispatch");
+        }
+        Ok(expired)
+    }
+
+    /// Fail a task that hasn't started with `error_message`, without a retry.
+    /// No-op once it has started or finished.
+    async fn fail_before_dispatch(
+        &self,
+        task_id: &str,
+        queue_name: &str,
+        error_message: &str,
+    ) -> Result<Option<valka_db::queries::tasks::TaskRow>, sqlx::Error> {
+        let failed =
+            valka_db::queries::tasks::expire_task(&self.pool, task_id, error_message).await?;
+        if failed.is_some() {
+            valka_core::metrics::record_task_failed(queue_name);
+            self.emit_event_with_error(task_id, queue_name, 5, error_message);
+        }
+        Ok(failed)
+    }
+
+    /// Free the worker's slot for `result` and wait until it is written.
     pub async fn handle_task_result(&self, worker_id: &WorkerId, result: TaskResult) {
-        // Update worker state
+        self.settle_result_slot(worker_id, &result);
+        self.record_task_result(worker_id, result).await;
+    }
+
+    /// Free the worker's slot for `result` now and write it in the
+    /// background, so the stream doesn't wait on the result's batch.
+    pub fn submit_task_result(&self, worker_id: &WorkerId, result: TaskResult) {
+        self.settle_result_slot(worker_id, &result);
+        let in_flight = self.results.track();
+        let dispatcher = self.clone();
+        let worker_id = worker_id.clone();
+        tokio::spawn(async move {
+            dispatcher.record_task_result(&worker_id, result).await;
+            drop(in_flight);
+        });
+    }
+
+    /// Wait until every result submitted so far is written.
+    pub async fn flush_results(&self) {
+        self.results.idle().await;
+    }
+
+    fn settle_result_slot(&self, worker_id: &WorkerId, result: &TaskResult) {
         if let Some(mut handle) = self.workers.get_mut(worker_id.as_ref()) {
             handle.complete_task(&result.task_id);
         }
+        self.matching.fifo().release(&result.task_id);
+    }
 
-        if result.success {
-            let output: Option<serde_json::Value> = if result.output.is_empty() {
-                None
-            } else {
-                serde_json::from_str(&result.output).ok()
-            };
+    async fn record_task_result(&self, worker_id: &WorkerId, result: TaskResult) {
+        let span = info_span!(
+            "task_result",
+            task_id = %result.task_id,
+            worker_id = %worker_id,
+            success = result.success,
+        );
+        trace_context::set_parent(&span, &result.traceparent);
+        self.record_task_result_traced(worker_id, result)
+            .instrument(span)
+            .await
+    }
 
-            // Atomically complete both run and task in a single transaction
-            let tx_result: Result<(), sqlx::Error> = async {
-                let mut tx = self.pool.begin().await?;
+    async fn record_task_result_traced(&self, worker_id: &WorkerId, mut result: TaskResult) {
+        // An ephemeral task's row may still be on its way to PG
+        let pending_write = self
+            .ephemeral_writes
+            .get(&result.task_id)
+            .map(|w| w.clone());
+        if let Some(mut written) = pending_write {
+            let _ = written.wait_for(|done| *done).await;
+        }
 
-                sqlx::query(
-                    "UPDATE task_runs SET status = 'COMPLETED', output = $2, completed_at = NOW() \
-                     WHERE id = $1 AND status = 'RUNNING'",
-                )
-                .bind(&result.task_run_id)
-                .bind(&output)
-                .execute(&mut *tx)
-                .await?;
-
-                sqlx::query(
-                    "UPDATE tasks SET status = 'COMPLETED', output = $2, updated_at = NOW() \
-                     WHERE id = $1",
-                )
-                .bind(&result.task_id)
-                .bind(&output)
-                .execute(&mut *tx)
-                .await?;
+        if result.cancelled {
+            // The cancel or reclaim already closed the run and freed the slot
+            info!(task_id = %result.task_id, "Worker stopped cancelled task");
+            return;
+        }
+        if result.rejected {
+            self.requeue_rejected(&result).await;
+            return;
+        }
 
-                tx.commit().await?;
-                Ok(())
+        let status = if result.success {
+            ResultStatus::Completed
+        } else if result.retryable {
+            ResultStatus::Retry
+        } else {
+            ResultStatus::Failed
+        };
+        let mut output = (status == ResultStatus::Completed && !result.output.is_empty())
+            .then(|| serde_json::from_str::<serde_json::Value>(&result.output).ok())
+            .flatten()
+            .map(|output| output.to_string());
+        // A large output goes to the blob store, leaving a reference in the row.
+        // So does one shaped like a reference, which would otherwise pass for one
+        if let (Some(blobs), Some(json)) = (&self.blob_store, &output)
+            && (blobs.should_offload(json.len()) || blob_reference_in(json).is_some())
+        {
+            let key = output_key(&result.task_id, &result.task_run_id);
+            match blobs.put(&key, json).await {
+                Ok(reference) => output = Some(reference.to_string()),
+                Err(e) => {
+                    warn!(task_id = %result.task_id, error = %e, "Failed to offload task output");
+                }
+            }
+        }
+        // Bound what lands in PG, whatever the worker's own limits were
+        let output_truncated = output
+            .as_ref()
+            .is_some_and(|output| output.len() > self.limits.max_output_bytes);
+        if output_truncated {
+            warn!(
+                task_id = %result.task_id,
+                bytes = output.as_ref().map_or(0, String::len),
+                limit = self.limits.max_output_bytes,
+                "Task output over limits.max_output_bytes; not storing it"
+            );
+            output = None;
+        }
+        truncate_utf8(
+            &mut result.error_message,
+            self.limits.max_error_message_bytes,
+        );
+        let outcome = match fault_point!("dispatcher.task_result.before_commit", &result.task_id) {
+            Ok(()) => {
+                self.results
+                    .write(ResultWrite {
+                        task_id: result.task_id.clone(),
+                        task_run_id: result.task_run_id.clone(),
+                        status,
+                        output,
+                        output_truncated,
+                        error_message: result.error_message.clone(),
+                        retry_after_ms: (result.retry_after_ms > 0)
+                            .then(|| result.retry_after_ms.min(self.retry_policy.max_delay_ms())),
+                    })
+                    .await
             }
-            .await;
+            Err(e) => Err(e.to_string()),
+        };
 
-            if let Err(e) = tx_result {
+        let (queue_name, attempt_number) = match outcome {
+            Ok(Some((duration_ms, attempt_number, Some((queue_name, callback_url))))) => {
+                valka_core::metrics::record_task_duration(
+                    &queue_name,
+                    duration_ms,
+                    &result.task_id,
+                );
+                if let Some(url) = callback_url {
+                    self.webhooks.notify(&result.task_id, &url);
+                }
+                if status != ResultStatus::Retry {
+                    self.resolve_dependents(&result.task_id);
+                }
+                (queue_name, attempt_number)
+            }
+            Ok(Some((_, attempt_number, None))) => (String::new(), attempt_number),
+            // A run that is no longer RUNNING was reaped or reclaimed
+            Ok(None) => {
+                self.ignore_stale_result(&result);
+                return;
+            }
+            // Nothing was recorded; the run's lease expiring retries the task
+            Err(e) => {
                 error!(
                     task_id = %result.task_id,
                     task_run_id = %result.task_run_id,
                     error = %e,
-                    "Failed to complete task/run transaction"
+                    "Failed to write task result"
                 );
+                return;
             }
+        };
 
-            valka_core::metrics::record_task_completed("");
-            self.emit_event(&result.task_id, "", 4); // 4 = COMPLETED
-        } else {
-            // Atomically fail run and update task status in a single transaction
-            let tx_result: Result<(), sqlx::Error> = async {
-                let mut tx = self.pool.begin().await?;
-
-                sqlx::query(
-                    "UPDATE task_runs SET status = 'FAILED', error_message = $2, \
-                     completed_at = NOW() WHERE id = $1 AND status = 'RUNNING'",
-                )
-                .bind(&result.task_run_id)
-                .bind(&result.error_message)
-                .execute(&mut *tx)
-                .await?;
-
-                if result.retryable {
-                    sqlx::query(
-                        "UPDATE tasks SET status = 'RETRY', updated_at = NOW() WHERE id = $1",
-                    )
-                    .bind(&result.task_id)
-                    .execute(&mut *tx)
-                    .await?;
-                } else {
-                    sqlx::query(
-                        "UPDATE tasks SET status = 'FAILED', error_message = $2, \
-                         updated_at = NOW() WHERE id = $1",
-                    )
-                    .bind(&result.task_id)
-                    .bind(&result.error_message)
-                    .execute(&mut *tx)
-                    .await?;
+        match status {
+            ResultStatus::Completed => {
+                valka_core::metrics::record_task_completed(&queue_name);
+                // 4 = COMPLETED
+                self.emit_result_event(&result, worker_id, &queue_name, attempt_number, 4);
+            }
+            ResultStatus::Retry => {
+                valka_core::metrics::record_task_retried(&queue_name);
+                // 6 = RETRY
+                self.emit_result_event(&result, worker_id, &queue_name, attempt_number, 6);
+            }
+            ResultStatus::Failed => {
+                valka_core::metrics::record_task_failed(&queue_name);
+                // 5 = FAILED
+                self.emit_result_event(&result, worker_id, &queue_name, attempt_number, 5);
+            }
+        }
+    }
+
+    /// Release or cancel the tasks waiting on one that just ended, off the
+    /// result path. The scheduler's pass catches anything this misses.
+    fn resolve_dependents(&self, task_id: &str) {
+        let pool = self.pool.clone();
+        let task_id = task_id.to_string();
+        tokio::spawn(async move {
+            match dependencies::resolve_waiting_tasks(&pool, Some(&task_id)).await {
+                Ok(resolved) if !resolved.released.is_empty() || !resolved.cancelled.is_empty() => {
+                    debug!(
+                        task_id,
+                        released = resolved.released.len(),
+                        cancelled = resolved.cancelled.len(),
+                        "Resolved dependent tasks"
+                    );
                 }
+                Ok(_) => {}
+                Err(e) => warn!(task_id, error = %e, "Failed to resolve dependent tasks"),
+            }
+        });
+    }
+
+    fn ignore_stale_result(&self, result: &TaskResult) {
+        warn!(
+            task_id = %result.task_id,
+            task_run_id = %result.task_run_id,
+            "Ignoring result for a run that is no longer RUNNING"
+        );
+        valka_core::metrics::record_stale_result();
+    }
 
-                tx.commit().await?;
-                Ok(())
+    /// Close a run the worker handed back unprocessed and return its task to
+    /// PENDING right away. Unlike a failure it never leads to RETRY or the
+    /// DLQ, though the run still takes up an attempt number.
+    async fn requeue_rejected(&self, result: &TaskResult) {
+        let tx_result: Result<bool, sqlx::Error> = async {
+            let mut tx = self.pool.begin().await?;
+
+            let closed = sqlx::query(
+                "UPDATE task_runs SET status = 'FAILED', error_message = 'Rejected by worker', \
+                 completed_at = NOW() WHERE id = $1 AND status = 'RUNNING'",
+            )
+            .bind(&result.task_run_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if closed == 0 {
+                return Ok(false);
             }
-            .await;
 
-            if let Err(e) = tx_result {
+            sqlx::query(
+                "UPDATE tasks SET status = 'PENDING', updated_at = NOW() \
+                 WHERE id = $1 AND status = 'RUNNING'",
+            )
+            .bind(&result.task_id)
+            .execute(&mut *tx)
+            .await?;
+
+            fault_point!("dispatcher.requeue_rejected.before_commit", &result.task_id)?;
+
+            tx.commit().await?;
+            Ok(true)
+        }
+        .await;
+
+        match tx_result {
+            Ok(true) => {
+                info!(task_id = %result.task_id, "Task rejected by worker, requeued");
+                self.emit_event(&result.task_id, "", 1); // 1 = PENDING
+            }
+            Ok(false) => {}
+            Err(e) => {
                 error!(
                     task_id = %result.task_id,
                     task_run_id = %result.task_run_id,
                     error = %e,
-                    "Failed to process task result transaction"
+                    "Failed to requeue rejected task"
                 );
             }
-
-            if result.retryable {
-                valka_core::metrics::record_task_retried("");
-                self.emit_event(&result.task_id, "", 6); // 6 = RETRY
-            } else {
-                valka_core::metrics::record_task_failed("");
-                self.emit_event(&result.task_id, "", 5); // 5 = FAILED
-            }
         }
     }
 
     pub async fn handle_heartbeat(&self, worker_id: &WorkerId, heartbeat: Heartbeat) {
-        if let Some(mut handle) = self.workers.get_mut(worker_id.as_ref()) {
+        let (dropped, tracked, worker_name) = {
+            let Some(mut handle) = self.workers.get_mut(worker_id.as_ref()) else {
+                return;
+            };
             handle.update_heartbeat();
+            let dropped = handle.reconcile_reported(&heartbeat.active_task_ids);
+            (
+                dropped,
+                handle.active_tasks.len(),
+                handle.worker_name.clone(),
+            )
+        };
+        valka_core::metrics::set_worker_active_tasks(
+            worker_id.as_ref(),
+            &worker_name,
+            tracked,
+            heartbeat.active_task_ids.len(),
+        );
+        if !dropped.is_empty() {
+            warn!(
+                worker_id = %worker_id,
+                task_ids = ?dropped,
+                "Worker no longer reports tasks; reclaiming their slots"
+            );
+            valka_core::metrics::record_worker_slots_reclaimed("heartbeat", dropped.len());
+            for task_id in &dropped {
+                self.matching.fifo().release(task_id);
+            }
+        }
 
-            // Extend leases for active tasks
-            for task_id in &heartbeat.active_task_ids {
-                // Look up the task run ID from active tasks
-                // We use the task_id to update the lease on any RUNNING run
-                let lease_extension = Duration::seconds(60); // Extend by 60 seconds
-                let new_lease = Utc::now() + lease_extension;
-                // Update heartbeat for all running runs of this task
-                if let Err(e) = valka_db::queries::task_runs::update_heartbeat_by_task(
-                    &self.pool, task_id, new_lease,
-                )
-                .await
-                {
-                    error!(task_id = %task_id, error = %e, "Failed to extend task run lease");
-                }
+        // Extend leases for active tasks
+        for task_id in &heartbeat.active_task_ids {
+            // Look up the task run ID from active tasks
+            // We use the task_id to update the lease on any RUNNING run
+            let lease_extension = Duration::seconds(60); // Extend by 60 seconds
+            let new_lease = Utc::now() + lease_extension;
+            // Update heartbeat for all running runs of this task
+            if let Err(e) = valka_db::queries::task_runs::update_heartbeat_by_task(
+                &self.pool, task_id, new_lease,
+            )
+            .await
+            {
+                error!(task_id = %task_id, error = %e, "Failed to extend task run lease");
             }
         }
     }
 
-    pub async fn handle_log_batch(&self, _worker_id: &WorkerId, batch: LogBatch) {
-        for entry in batch.entries {
+    pub async fn handle_log_batch(&self, worker_id: &WorkerId, batch: LogBatch) {
+        for mut entry in batch.entries {
+            if entry.task_run_id.starts_with(WORKER_LOG_RUN_PREFIX) {
+                // Pin worker-level logs to the sending session so a worker
+                // can't write into another worker's log stream.
+                entry.task_run_id = format!("{WORKER_LOG_RUN_PREFIX}{worker_id}");
+                emit_worker_log(worker_id, &entry);
+            }
             let _ = self.log_tx.send(entry).await;
         }
     }
 
-    /// Cancel a task on the worker that's running it
+    /// Cancel a task on the worker that's running it, freeing its slot
     pub async fn cancel_task_on_worker(&self, task_id: &str) -> bool {
-        for entry in self.workers.iter() {
-            let handle = entry.value();
-            if handle.active_tasks.contains(task_id) {
-                let cancel = WorkerResponse {
-                    response: Some(worker_response::Response::TaskCancellation(
-                        TaskCancellation {
-                            task_id: task_id.to_string(),
-                            reason: "Cancelled by user".to_string(),
-                        },
-                    )),
-                };
-                let _ = handle.response_tx.send(cancel).await;
-                return true;
-            }
+        self.revoke_from_worker(task_id, "Cancelled by user", "cancelled")
+            .await
+    }
+
+    /// Take a RUNNING task back from its worker, e.g. one that is wedged but
+    /// still heartbeating. The run is closed as ABANDONED and the task moved
+    /// to RETRY, or PENDING if `immediate`; a worker connected here is told
+    /// to cancel it and its slot freed. A late result from the worker is
+    /// ignored as stale. None if the task isn't RUNNING.
+    pub async fn reclaim_task(
+        &self,
+        task_id: &str,
+        reason: &str,
+        immediate: bool,
+    ) -> Result<Option<ReclaimedTask>, sqlx::Error> {
+        let Some((task, run)) =
+            valka_db::queries::tasks::reclaim_running_task(&self.pool, task_id, reason, immediate)
+                .await?
+        else {
+            return Ok(None);
+        };
+        let worker_notified = self.revoke_from_worker(task_id, reason, "reclaimed").await;
+
+        info!(
+            target: "valka::audit",
+            action = "reclaim",
+            task_id,
+            run_id = %run.id,
+            worker_id = %run.worker_id,
+            reason,
+            immediate,
+            worker_notified,
+            "Task reclaimed from worker"
+        );
+        // 1 = PENDING, 6 = RETRY
+        let status = if immediate { 1 } else { 6 };
+        self.emit_event_with_error(task_id, &task.queue_name, status, reason);
+        Ok(Some(ReclaimedTask {
+            task,
+            run,
+            worker_notified,
+        }))
+    }
+
+    async fn revoke_from_worker(
+        &self,
+        task_id: &str,
+        reason: &str,
+        slot_reason: &'static str,
+    ) -> bool {
+        let owner = self.workers.iter().find_map(|entry| {
+            entry
+                .active_tasks
+                .contains(task_id)
+                .then(|| (entry.key().clone(), entry.response_tx.clone()))
+        });
+        let Some((worker_id, response_tx)) = owner else {
+            return false;
+        };
+
+        let cancel = WorkerResponse {
+            response: Some(worker_response::Response::TaskCancellation(
+                TaskCancellation {
+                    task_id: task_id.to_string(),
+                    reason: reason.to_string(),
+                },
+            )),
+        };
+        let _ = response_tx.send(cancel).await;
+        self.release_task(&worker_id, task_id, slot_reason);
+        true
+    }
+
+    /// Drop a task that will report no result (cancelled, or its lease was
+    /// reaped) from its worker's active set. Returns false if no local worker
+    /// holds it.
+    pub fn release_task(&self, worker_id: &str, task_id: &str, reason: &'static str) -> bool {
+        self.matching.fifo().release(task_id);
+        let Some(mut handle) = self.workers.get_mut(worker_id) else {
+            return false;
+        };
+        if !handle.active_tasks.contains(task_id) {
+            return false;
         }
-        false
+        handle.complete_task(task_id);
+        valka_core::metrics::record_worker_slots_reclaimed(reason, 1);
+        true
     }
 
-    /// Send a signal to the worker currently running a task. Returns true if delivered.
+    /// Send a signal to the worker currently running a task. Returns true if
+    /// delivered; false too when that worker doesn't handle signals.
     pub async fn send_signal_to_worker(&self, task_id: &str, signal: TaskSignal) -> bool {
         for entry in self.workers.iter() {
             let handle = entry.value();
             if handle.active_tasks.contains(task_id) {
+                if !handle.supports(CAPABILITY_SIGNALS) {
+                    return false;
+                }
                 let response = WorkerResponse {
                     response: Some(worker_response::Response::TaskSignal(signal)),
                 };
@@ -434,24 +1607,303 @@ impl DispatcherService {
 
     /// Handle a signal acknowledgement from a worker
     pub async fn handle_signal_ack(&self, ack: &SignalAck) {
-        if let Err(e) =
-            valka_db::queries::signals::mark_acknowledged(&self.pool, &ack.signal_id).await
-        {
+        use valka_db::queries::signals::{mark_acknowledged, mark_delivered};
+
+        let acked = async {
+            if mark_acknowledged(&self.pool, &ack.signal_id).await? {
+                return Ok(());
+            }
+            // The ack can beat the sender marking the signal delivered
+            if mark_delivered(&self.pool, &ack.signal_id).await? {
+                mark_acknowledged(&self.pool, &ack.signal_id).await?;
+            }
+            Ok::<_, sqlx::Error>(())
+        }
+        .await;
+        if let Err(e) = acked {
             warn!(signal_id = %ack.signal_id, error = %e, "Failed to acknowledge signal");
         }
     }
 
+    /// Store progress reported for a task the worker holds and announce it as
+    /// a RUNNING event carrying the percent.
+    pub async fn handle_task_progress(&self, worker_id: &WorkerId, progress: TaskProgress) {
+        let holds_task = self
+            .workers
+            .get(worker_id.as_ref())
+            .is_some_and(|h| h.active_tasks.contains(&progress.task_id));
+        if !holds_task || !progress.percent.is_finite() {
+            debug!(worker_id = %worker_id, task_id = %progress.task_id, "Ignoring task progress");
+            return;
+        }
+        let percent = progress.percent.clamp(0.0, 100.0);
+        let queue_name = match valka_db::queries::task_runs::update_progress(
+            &self.pool,
+            &progress.task_run_id,
+            percent,
+            &progress.message,
+        )
+        .await
+        {
+            Ok(Some(queue_name)) => queue_name,
+            Ok(None) => return,
+            Err(e) => {
+                warn!(task_id = %progress.task_id, error = %e, "Failed to store task progress");
+                return;
+            }
+        };
+        self.events.emit(TaskEvent {
+            event_id: uuid::Uuid::now_v7().to_string(),
+            task_id: progress.task_id,
+            queue_name,
+            previous_status: 3,
+            new_status: 3, // 3 = RUNNING
+            worker_id: worker_id.0.clone(),
+            node_id: self.node_id.0.clone(),
+            attempt_number: 0,
+            error_message: String::new(),
+            timestamp_ms: Utc::now().timestamp_millis(),
+            suppressed_count: 0,
+            progress: Some(percent),
+            progress_message: progress.message,
+        });
+    }
+
     pub fn workers(&self) -> &Arc<DashMap<String, WorkerHandle>> {
         &self.workers
     }
 
+    /// Workers connected to this node, labelled with its node id.
+    pub fn list_workers(&self) -> Vec<WorkerInfo> {
+        self.workers
+            .iter()
+            .map(|h| WorkerInfo {
+                worker_id: h.worker_id.0.clone(),
+                worker_name: h.worker_name.clone(),
+                queues: h.queues.clone(),
+                concurrency: h.concurrency,
+                active_tasks: h.active_tasks.len() as i32,
+                last_heartbeat: h.last_heartbeat.to_rfc3339(),
+                connected_at: h.connected_at.to_rfc3339(),
+                node_id: self.node_id.0.clone(),
+                version: h.version.clone(),
+                status: h.state().as_str().to_string(),
+            })
+            .collect()
+    }
+
+    /// Stop giving a local worker new tasks. Once its active tasks finish it
+    /// is sent ServerShutdown and its stream closed; after `timeout` the
+    /// stream is closed regardless and lease expiry reclaims what's left.
+    /// Draining again only moves the deadline. Returns the worker's active
+    /// task count, or None if it isn't connected here.
+    pub fn drain_worker(
+        &self,
+        worker_id: &str,
+        timeout: Option<std::time::Duration>,
+    ) -> Option<usize> {
+        let mut handle = self.workers.get_mut(worker_id)?;
+        handle.drain_deadline = timeout
+            .and_then(|t| Duration::from_std(t).ok())
+            .and_then(|t| Utc::now().checked_add_signed(t));
+        let active = handle.active_tasks.len();
+        if handle.is_draining() {
+            return Some(active);
+        }
+        handle.set_state(WorkerState::Draining);
+        drop(handle);
+
+        info!(worker_id, active_tasks = active, timeout = ?timeout, "Draining worker");
+        let dispatcher = self.clone();
+        let worker_id = WorkerId(worker_id.to_string());
+        tokio::spawn(async move { dispatcher.supervise_drain(worker_id).await });
+        Some(active)
+    }
+
+    /// Drain every local worker with `timeout` and wait until all of their
+    /// streams are closed.
+    pub async fn drain_all_workers(&self, timeout: std::time::Duration) {
+        let worker_ids: Vec<String> = self.workers.iter().map(|h| h.key().clone()).collect();
+        let mut closed = Vec::with_capacity(worker_ids.len());
+        for worker_id in worker_ids {
+            let Some(state_rx) = self.workers.get(&worker_id).map(|h| h.state.subscribe()) else {
+                continue;
+            };
+            self.drain_worker(&worker_id, Some(timeout));
+            closed.push(state_rx);
+        }
+        for mut state_rx in closed {
+            // An error means the handle is gone, which also counts as closed
+            let _ = state_rx.wait_for(|s| *s == WorkerState::Closed).await;
+        }
+    }
+
+    /// Send every local worker ServerShutdown with `reason` and the `grace`
+    /// it has left, so it can finish its tasks and then reconnect elsewhere.
+    /// Never waits on a full stream. Returns how many workers were told.
+    pub fn announce_shutdown(&self, reason: &str, grace: std::time::Duration) -> usize {
+        let senders: Vec<(String, mpsc::Sender<WorkerResponse>)> = self
+            .workers
+            .iter()
+            .map(|h| (h.key().clone(), h.response_tx.clone()))
+            .collect();
+        let drain_seconds = i32::try_from(grace.as_secs()).unwrap_or(i32::MAX).max(1);
+        let mut told = 0;
+        for (worker_id, response_tx) in senders {
+            let notice = WorkerResponse {
+                response: Some(worker_response::Response::ServerShutdown(ServerShutdown {
+                    reason: reason.to_string(),
+                    drain_seconds,
+                })),
+            };
+            match response_tx.try_send(notice) {
+                Ok(()) => told += 1,
+                Err(e) => warn!(worker_id, error = %e, "Could not announce shutdown to worker"),
+            }
+        }
+        told
+    }
+
+    /// Wait until no local worker has a task in flight or `deadline` passes.
+    /// Returns whether they all finished.
+    pub async fn wait_for_idle(&self, deadline: tokio::time::Instant) -> bool {
+        let mut tick = tokio::time::interval(DRAIN_POLL_INTERVAL);
+        loop {
+            tick.tick().await;
+            if self.stats().active_tasks == 0 {
+                return true;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return false;
+            }
+        }
+    }
+
+    fn is_draining(&self, worker_id: &WorkerId) -> bool {
+        self.workers
+            .get(worker_id.as_ref())
+            .is_some_and(|h| h.is_draining())
+    }
+
+    /// Wait for a draining worker to go idle or hit its deadline, then close
+    /// its stream. Gives up if the worker disconnects first.
+    async fn supervise_drain(&self, worker_id: WorkerId) {
+        let mut tick = tokio::time::interval(DRAIN_POLL_INTERVAL);
+        loop {
+            tick.tick().await;
+            let (idle, response_tx) = {
+                let Some(handle) = self.workers.get(worker_id.as_ref()) else {
+                    return;
+                };
+                let expired = handle.drain_deadline.is_some_and(|d| d <= Utc::now());
+                if !handle.is_idle() && !expired {
+                    continue;
+                }
+                if !handle.is_idle() {
+                    warn!(
+                        worker_id = %worker_id,
+                        active_tasks = handle.active_tasks.len(),
+                        "Drain timed out; closing worker stream"
+                    );
+                }
+                (handle.is_idle(), handle.response_tx.clone())
+            };
+
+            if idle {
+                // The worker stops once told; its last results must be written by then
+                self.flush_results().await;
+                info!(worker_id = %worker_id, "Worker drained");
+                let shutdown = WorkerResponse {
+                    response: Some(worker_response::Response::ServerShutdown(ServerShutdown {
+                        reason: "Worker drained".to_string(),
+                        drain_seconds: 0,
+                    })),
+                };
+                let _ = response_tx.send(shutdown).await;
+            }
+            if let Some(handle) = self.workers.get(worker_id.as_ref()) {
+                handle.set_state(WorkerState::Closed);
+            }
+            return;
+        }
+    }
+
+    pub fn stats(&self) -> DispatcherStats {
+        self.workers
+            .iter()
+            .fold(DispatcherStats::default(), |mut stats, entry| {
+                let tx = &entry.response_tx;
+                stats.workers += 1;
+                stats.active_tasks += entry.active_tasks.len();
+                stats.queued_responses += tx.max_capacity() - tx.capacity();
+                stats
+            })
+    }
+
+    pub fn log_tx(&self) -> &mpsc::Sender<valka_proto::LogEntry> {
+        &self.log_tx
+    }
+
     pub fn event_tx(&self) -> &broadcast::Sender<TaskEvent> {
-        &self.event_tx
+        self.events.sender()
+    }
+
+    /// Shared emitter; all task status events should go through it.
+    pub fn events(&self) -> &EventEmitter {
+        &self.events
+    }
+
+    /// Flushed task logs; the ingester only publishes while someone is subscribed.
+    pub fn log_stream(&self) -> &broadcast::Sender<TaskLogRow> {
+        &self.log_stream
     }
 
     /// Emit a task event
     fn emit_event(&self, task_id: &str, queue_name: &str, new_status: i32) {
+        self.emit_event_with_error(task_id, queue_name, new_status, "");
+    }
+
+    fn emit_event_with_error(
+        &self,
+        task_id: &str,
+        queue_name: &str,
+        new_status: i32,
+        error_message: &str,
+    ) {
+        self.events
+            .emit(self.task_event(task_id, queue_name, new_status, error_message));
+    }
+
+    /// Announce the outcome of the attempt `worker_id` reported in `result`.
+    fn emit_result_event(
+        &self,
+        result: &TaskResult,
+        worker_id: &WorkerId,
+        queue_name: &str,
+        attempt_number: i32,
+        new_status: i32,
+    ) {
         let event = TaskEvent {
+            worker_id: worker_id.0.clone(),
+            attempt_number,
+            ..self.task_event(
+                &result.task_id,
+                queue_name,
+                new_status,
+                &result.error_message,
+            )
+        };
+        self.events.emit(event);
+    }
+
+    fn task_event(
+        &self,
+        task_id: &str,
+        queue_name: &str,
+        new_status: i32,
+        error_message: &str,
+    ) -> TaskEvent {
+        TaskEvent {
             event_id: uuid::Uuid::now_v7().to_string(),
             task_id: task_id.to_string(),
             queue_name: queue_name.to_string(),
@@ -460,10 +1912,116 @@ impl DispatcherService {
             worker_id: String::new(),
             node_id: self.node_id.0.clone(),
             attempt_number: 0,
-            error_message: String::new(),
+            error_message: error_message.to_string(),
             timestamp_ms: Utc::now().timestamp_millis(),
-        };
-        let _ = self.event_tx.send(event);
+            suppressed_count: 0,
+            progress: None,
+            progress_message: String::new(),
+        }
+    }
+
+    /// Per-queue rules mirrored from queue_configs, checked by the create paths and match loops.
+    pub fn queue_policies(&self) -> &QueuePolicies {
+        &self.policies
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn webhooks(&self) -> &WebhookSender {
+        &self.webhooks
+    }
+
+    /// Queues this worker subscribes to but is currently barred from, with the reason.
+    pub fn excluded_queues(&self, handle: &WorkerHandle) -> Vec<(String, String)> {
+        handle
+            .queues
+            .iter()
+            .filter_map(|queue| {
+                self.policies
+                    .exclusion_reason(queue, &handle.parsed_version)
+                    .map(|reason| (queue.clone(), reason))
+            })
+            .collect()
+    }
+
+    /// Periodically reload queue policies so changes made on other nodes apply here.
+    pub fn start_queue_policy_refresher(
+        &self,
+        interval_ms: u64,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> tokio::task::JoinHandle<()> {
+        let policies = self.policies.clone();
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = policies.refresh(&pool).await {
+                            warn!(error = %e, "Failed to refresh queue policies");
+                        }
+                    }
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Task create limits checked by the create paths.
+    pub fn limits(&self) -> &LimitsConfig {
+        &self.limits
+    }
+
+    /// The store offloaded outputs and inputs are in, if one is configured.
+    pub fn blob_store(&self) -> Option<&BlobStore> {
+        self.blob_store.as_ref()
+    }
+
+    /// Worker heartbeat settings, handed to workers on connect.
+    pub fn dispatcher_config(&self) -> &DispatcherConfig {
+        &self.dispatcher_config
+    }
+
+    /// Task creation quotas checked by the create paths.
+    pub fn quotas(&self) -> &Quotas {
+        &self.quotas
+    }
+
+    pub fn shutdown(&self) -> &ShutdownTracker {
+        &self.shutdown
+    }
+
+    /// Periodically reset quota counters from PG so they track other nodes and finished tasks.
+    pub fn start_quota_reconciler(
+        &self,
+        interval_ms: u64,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> tokio::task::JoinHandle<()> {
+        let quotas = self.quotas.clone();
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = quotas.reconcile(&pool, Utc::now()).await {
+                            warn!(error = %e, "Failed to reconcile quotas");
+                        }
+                    }
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        })
     }
 
     /// Start the heartbeat checker background task
@@ -473,7 +2031,23 @@ impl DispatcherService {
     ) -> (tokio::task::JoinHandle<()>, mpsc::Receiver<WorkerId>) {
         let (dead_tx, dead_rx) = mpsc::channel(64);
         let workers = self.workers.clone();
-        let handle = tokio::spawn(heartbeat::heartbeat_checker(workers, shutdown, dead_tx));
+        let handle = tokio::spawn(heartbeat::heartbeat_checker(
+            workers,
+            self.dispatcher_config.clone(),
+            shutdown,
+            dead_tx,
+        ));
         (handle, dead_rx)
     }
 }
+
+/// Re-emit a worker-level log through the server's own tracing pipeline.
+fn emit_worker_log(worker_id: &WorkerId, entry: &LogEntry) {
+    let (message, metadata) = (&entry.message, &entry.metadata);
+    match entry.level {
+        4 => error!(target: "valka::worker", %worker_id, %metadata, "{message}"),
+        3 => warn!(target: "valka::worker", %worker_id, %metadata, "{message}"),
+        2 => info!(target: "valka::worker", %worker_id, %metadata, "{message}"),
+        _ => debug!(target: "valka::worker", %worker_id, %metadata, "{message}"),
+    }
+}
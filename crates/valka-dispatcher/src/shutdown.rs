@@ -0,0 +1,82 @@
+use std::sync::{Arc, OnceLock};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// Where a node is in its shutdown sequence, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownPhase {
+    Running,
+    /// New work is refused and readiness fails; reads and worker streams carry on
+    Quiescing,
+    /// Readers are stopped and workers are finishing their tasks
+    Draining,
+    /// Listeners are closing
+    Stopped,
+}
+
+impl ShutdownPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShutdownPhase::Running => "RUNNING",
+            ShutdownPhase::Quiescing => "QUIESCING",
+            ShutdownPhase::Draining => "DRAINING",
+            ShutdownPhase::Stopped => "STOPPED",
+        }
+    }
+}
+
+/// Shared shutdown phase of this node; every clone sees the same phase.
+#[derive(Clone)]
+pub struct ShutdownTracker {
+    phase: Arc<watch::Sender<ShutdownPhase>>,
+    deadline: Arc<OnceLock<Instant>>,
+}
+
+impl Default for ShutdownTracker {
+    fn default() -> Self {
+        Self {
+            phase: Arc::new(watch::Sender::new(ShutdownPhase::Running)),
+            deadline: Arc::default(),
+        }
+    }
+}
+
+impl ShutdownTracker {
+    pub fn phase(&self) -> ShutdownPhase {
+        *self.phase.borrow()
+    }
+
+    /// False once shutdown has started: creates and new sessions are refused.
+    pub fn accepting_work(&self) -> bool {
+        self.phase() == ShutdownPhase::Running
+    }
+
+    /// Move forward to `phase`. Never moves backwards.
+    pub fn advance(&self, phase: ShutdownPhase) {
+        let advanced = self.phase.send_if_modified(|current| {
+            if phase > *current {
+                *current = phase;
+                true
+            } else {
+                false
+            }
+        });
+        if advanced {
+            valka_core::metrics::set_shutdown_phase(phase as u8);
+        }
+    }
+
+    /// Record when the shutdown must be done by. Only the first call counts.
+    pub fn set_deadline(&self, deadline: Instant) {
+        let _ = self.deadline.set(deadline);
+    }
+
+    /// When the shutdown must be done by, once one has started.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline.get().copied()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<ShutdownPhase> {
+        self.phase.subscribe()
+    }
+}
@@ -1,11 +1,18 @@
-use crate::service::DispatcherService;
-use crate::worker_handle::WorkerHandle;
+use crate::heartbeat;
+use crate::service::{DispatcherService, Registration};
+use crate::worker_handle::{WorkerHandle, WorkerState};
 use futures::StreamExt;
 use tokio::sync::mpsc;
 use tonic::Streaming;
 use tracing::{error, info, warn};
 use valka_core::WorkerId;
-use valka_proto::{WorkerRequest, WorkerResponse, worker_request, worker_response};
+use valka_proto::{
+    HelloAck, PROTOCOL_VERSION, ServerShutdown, WorkerRequest, WorkerResponse, worker_request,
+    worker_response,
+};
+
+/// How long a closed stream's match loop gets to return its tasks before it is aborted.
+const MATCH_LOOP_EXIT_GRACE: std::time::Duration = std::time::Duration::from_secs(1);
 
 /// Process the bidirectional worker stream
 pub async fn handle_worker_stream(
@@ -33,43 +40,118 @@ pub async fn handle_worker_stream(
     } else {
         WorkerId(hello.worker_id.clone())
     };
+    let session_id = uuid::Uuid::now_v7().to_string();
 
     info!(
         worker_id = %worker_id,
         worker_name = %hello.worker_name,
         queues = ?hello.queues,
         concurrency = hello.concurrency,
+        version = %hello.version,
+        protocol_version = hello.protocol_version,
+        capabilities = ?hello.capabilities,
+        session_id = %session_id,
         "Worker connected"
     );
+    if hello.protocol_version > PROTOCOL_VERSION {
+        warn!(
+            worker_id = %worker_id,
+            worker = hello.protocol_version,
+            server = PROTOCOL_VERSION,
+            "Worker speaks a newer protocol than this server"
+        );
+    }
+
+    // Acknowledge before registering, so the ack is the first message a
+    // versioned worker sees. Older workers wouldn't understand it.
+    if hello.protocol_version > 0 {
+        let ack = WorkerResponse {
+            response: Some(worker_response::Response::HelloAck(HelloAck {
+                protocol_version: PROTOCOL_VERSION,
+                session_id: session_id.clone(),
+                heartbeat_interval_ms: heartbeat::negotiate_interval_ms(
+                    hello.heartbeat_interval_ms,
+                    dispatcher.dispatcher_config(),
+                ),
+            })),
+        };
+        if response_tx.send(ack).await.is_err() {
+            valka_core::metrics::record_worker_send_failure("hello_ack");
+            return;
+        }
+    }
+
+    valka_core::metrics::record_worker_connected(&hello.worker_name);
+    let worker_name = hello.worker_name.clone();
 
     // Register worker
     let handle = WorkerHandle::new(
         worker_id.clone(),
         hello.worker_name,
-        hello.queues.clone(),
+        hello.queues,
         hello.concurrency,
         response_tx.clone(),
         hello.metadata,
-    );
+    )
+    .with_version(hello.version)
+    .with_protocol(hello.protocol_version, hello.capabilities)
+    .with_session_id(session_id.clone())
+    .with_labels(hello.labels.into_iter().collect())
+    .with_queue_concurrency(hello.queue_concurrency);
+
+    // Resolves when a drain closes the stream; never if the handle goes away
+    // some other way
+    let mut state_rx = handle.state.subscribe();
+    let closed = async move {
+        if state_rx
+            .wait_for(|s| *s == WorkerState::Closed)
+            .await
+            .is_err()
+        {
+            std::future::pending::<()>().await;
+        }
+    };
+    tokio::pin!(closed);
 
-    dispatcher.register_worker(handle).await;
+    let queues = handle.queues.clone();
+    if dispatcher.register_worker(handle).await == Registration::Rejected {
+        let notice = WorkerResponse {
+            response: Some(worker_response::Response::ServerShutdown(ServerShutdown {
+                reason: format!("Worker {worker_id} already has a session on this node"),
+                drain_seconds: 0,
+            })),
+        };
+        let _ = response_tx.send(notice).await;
+        valka_core::metrics::record_worker_disconnected(&worker_name, "rejected");
+        return;
+    }
 
     // Start background task matching loop for this worker
     let dispatcher_clone = dispatcher.clone();
     let worker_id_clone = worker_id.clone();
-    let queues = hello.queues.clone();
-    let match_handle = tokio::spawn(async move {
+    let mut match_handle = tokio::spawn(async move {
         dispatcher_clone
             .run_worker_match_loop(worker_id_clone, queues)
             .await;
     });
 
     // Process incoming messages
-    loop {
-        match inbound.next().await {
+    let reason = loop {
+        let next = tokio::select! {
+            next = inbound.next() => next,
+            () = &mut closed => {
+                if !dispatcher.is_current_session(&worker_id, &session_id) {
+                    info!(worker_id = %worker_id, "Closing superseded worker stream");
+                    break "superseded";
+                }
+                info!(worker_id = %worker_id, "Closing drained worker stream");
+                break "drained";
+            }
+        };
+        match next {
             Some(Ok(msg)) => match msg.request {
                 Some(worker_request::Request::TaskResult(result)) => {
-                    dispatcher.handle_task_result(&worker_id, result).await;
+                    dispatcher.submit_task_result(&worker_id, result);
                 }
                 Some(worker_request::Request::Heartbeat(hb)) => {
                     dispatcher.handle_heartbeat(&worker_id, hb).await;
@@ -81,7 +163,8 @@ pub async fn handle_worker_stream(
                         )),
                     };
                     if response_tx.send(ack).await.is_err() {
-                        break;
+                        valka_core::metrics::record_worker_send_failure("heartbeat_ack");
+                        break "send_failed";
                     }
                 }
                 Some(worker_request::Request::LogBatch(batch)) => {
@@ -90,13 +173,17 @@ pub async fn handle_worker_stream(
                 Some(worker_request::Request::SignalAck(ack)) => {
                     dispatcher.handle_signal_ack(&ack).await;
                 }
+                Some(worker_request::Request::Progress(progress)) => {
+                    dispatcher.handle_task_progress(&worker_id, progress).await;
+                }
                 Some(worker_request::Request::Shutdown(shutdown)) => {
                     info!(
                         worker_id = %worker_id,
                         reason = %shutdown.reason,
                         "Worker graceful shutdown"
                     );
-                    break;
+                    // Keep reading so results of its remaining tasks still land
+                    dispatcher.drain_worker(worker_id.as_ref(), None);
                 }
                 None => {
                     warn!(worker_id = %worker_id, "Empty worker request");
@@ -105,16 +192,24 @@ pub async fn handle_worker_stream(
             },
             Some(Err(e)) => {
                 warn!(worker_id = %worker_id, error = %e, "Worker stream error");
-                break;
+                break "error";
             }
             None => {
                 info!(worker_id = %worker_id, "Worker stream closed");
-                break;
+                break "closed";
             }
         }
-    }
+    };
+    valka_core::metrics::record_worker_disconnected(&worker_name, reason);
 
-    // Cleanup
-    match_handle.abort();
-    dispatcher.deregister_worker(&worker_id).await;
+    // Cleanup. Deregistering first lets the match loop hand back tasks that
+    // reached its registrations before it exits. A superseded session leaves
+    // the worker to its successor.
+    dispatcher.deregister_session(&worker_id, &session_id).await;
+    if tokio::time::timeout(MATCH_LOOP_EXIT_GRACE, &mut match_handle)
+        .await
+        .is_err()
+    {
+        match_handle.abort();
+    }
 }
@@ -0,0 +1,153 @@
+//! Dispatch-time placeholders in task input.
+//!
+//! On queues with `template_input` enabled, string values in the input may
+//! reference `{{valka.attempt}}`, `{{valka.task_id}}`, `{{valka.run_id}}`,
+//! `{{valka.dispatched_at}}` or `{{meta.<key>}}` (a dotted path into the task
+//! metadata). They are substituted in the copy sent to the worker; the stored
+//! input is untouched. References that can't be resolved are left as written.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+use tracing::warn;
+
+/// Nesting below this depth is sent as stored.
+pub const MAX_TEMPLATE_DEPTH: usize = 32;
+
+const VALKA_PREFIX: &str = "{{valka.";
+const META_PREFIX: &str = "{{meta.";
+
+/// Values available to placeholders for one dispatch.
+pub struct TemplateValues<'a> {
+    pub queue_name: &'a str,
+    pub task_id: &'a str,
+    pub run_id: &'a str,
+    pub attempt: i32,
+    pub dispatched_at: DateTime<Utc>,
+    /// Task metadata as stored (a JSON object)
+    pub metadata: &'a str,
+}
+
+/// Render the placeholders in `input`. Returns None when the input is sent
+/// as is: no placeholders, invalid JSON, or nothing could be resolved.
+pub fn render_input(input: &str, values: &TemplateValues<'_>) -> Option<String> {
+    if !input.contains(VALKA_PREFIX) && !input.contains(META_PREFIX) {
+        valka_core::metrics::record_input_template(values.queue_name, "fast_path");
+        return None;
+    }
+
+    let mut doc: Value = serde_json::from_str(input).ok()?;
+    let mut renderer = Renderer {
+        values,
+        metadata: None,
+        substituted: 0,
+    };
+    renderer.walk(&mut doc, 0);
+    if renderer.substituted == 0 {
+        valka_core::metrics::record_input_template(values.queue_name, "unresolved");
+        return None;
+    }
+    valka_core::metrics::record_input_template(values.queue_name, "rendered");
+    Some(doc.to_string())
+}
+
+struct Renderer<'a, 'v> {
+    values: &'a TemplateValues<'v>,
+    /// Parsed on first `meta.` reference
+    metadata: Option<Value>,
+    substituted: usize,
+}
+
+impl Renderer<'_, '_> {
+    fn walk(&mut self, value: &mut Value, depth: usize) {
+        if depth > MAX_TEMPLATE_DEPTH {
+            warn!(
+                task_id = self.values.task_id,
+                max_depth = MAX_TEMPLATE_DEPTH,
+                "Task input nested too deeply for templating; rest left as is"
+            );
+            return;
+        }
+        match value {
+            Value::String(s) if s.contains("{{") => {
+                if let Some(rendered) = self.render_str(s) {
+                    *s = rendered;
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.walk(item, depth + 1);
+                }
+            }
+            Value::Object(map) => {
+                for (_, item) in map.iter_mut() {
+                    self.walk(item, depth + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Substitute every resolvable placeholder in `s`, or None if there were none.
+    fn render_str(&mut self, s: &str) -> Option<String> {
+        let mut out = String::with_capacity(s.len());
+        let mut rest = s;
+        let mut changed = false;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start..].find("}}") else {
+                break;
+            };
+            let placeholder = &rest[start..start + len + 2];
+            out.push_str(&rest[..start]);
+            match self.resolve(&placeholder[2..placeholder.len() - 2]) {
+                Some(value) => {
+                    out.push_str(&value);
+                    changed = true;
+                }
+                None => out.push_str(placeholder),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        out.push_str(rest);
+        if changed {
+            self.substituted += 1;
+        }
+        changed.then_some(out)
+    }
+
+    fn resolve(&mut self, name: &str) -> Option<String> {
+        let v = self.values;
+        let resolved = if let Some(field) = name.strip_prefix("valka.") {
+            match field {
+                "attempt" => Some(v.attempt.to_string()),
+                "task_id" => Some(v.task_id.to_string()),
+                "run_id" => Some(v.run_id.to_string()),
+                "dispatched_at" => {
+                    Some(v.dispatched_at.to_rfc3339_opts(SecondsFormat::Millis, true))
+                }
+                _ => None,
+            }
+        } else if let Some(path) = name.strip_prefix("meta.") {
+            let metadata = self
+                .metadata
+                .get_or_insert_with(|| serde_json::from_str(v.metadata).unwrap_or(Value::Null));
+            path.split('.')
+                .try_fold(&*metadata, |node, key| node.get(key))
+                .map(|found| match found {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+        } else {
+            // Not ours, e.g. a template meant for the worker
+            return None;
+        };
+
+        if resolved.is_none() {
+            warn!(
+                task_id = v.task_id,
+                placeholder = name,
+                "Unresolved input placeholder left as is"
+            );
+        }
+        resolved
+    }
+}
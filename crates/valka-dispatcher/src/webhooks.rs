@@ -0,0 +1,339 @@
+//! Delivery of task `callback_url` webhooks.
+//!
+//! Terminal results enqueue a delivery on a bounded channel; a background loop
+//! POSTs the task JSON with its own retry and backoff, so a slow or failing
+//! endpoint never holds up result handling. When the channel is full the
+//! delivery is dropped with a warning.
+//!
+//! Callbacks may only reach public addresses: loopback, private and link-local
+//! hosts are refused at creation and again once resolved at delivery, unless
+//! listed in `webhooks.allowed_hosts`.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::sync::{Semaphore, mpsc};
+use tracing::{debug, error, warn};
+use url::Host;
+use valka_core::WebhooksConfig;
+use valka_db::DbPool;
+use valka_db::queries::tasks::TaskRow;
+
+/// Header holding the Unix timestamp (seconds) a delivery was signed at.
+pub const TIMESTAMP_HEADER: &str = "valka-timestamp";
+/// Header holding the `v1=<hex>` HMAC-SHA256 signature of `"{timestamp}.{body}"`.
+pub const SIGNATURE_HEADER: &str = "valka-signature";
+
+/// Longest accepted `callback_url`.
+const MAX_CALLBACK_URL_LEN: usize = 2048;
+/// Upper bound on the wait between delivery attempts.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+struct Delivery {
+    task_id: String,
+    url: String,
+}
+
+/// Queues webhook deliveries for the background sender. The default sender
+/// is disabled and drops everything.
+#[derive(Clone, Default)]
+pub struct WebhookSender {
+    tx: Option<mpsc::Sender<Delivery>>,
+    allowed_hosts: Arc<Vec<String>>,
+}
+
+impl WebhookSender {
+    /// Spawn the delivery loop. Must be called inside a tokio runtime.
+    pub fn start(pool: DbPool, config: &WebhooksConfig) -> Self {
+        let allowed_hosts = Arc::new(config.allowed_hosts.clone());
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .dns_resolver(Arc::new(PublicResolver {
+                allowed_hosts: allowed_hosts.clone(),
+            }))
+            // A redirect could point anywhere, including at a literal internal address
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("webhook HTTP client");
+        let (tx, rx) = mpsc::channel(config.queue_capacity.max(1));
+        let deliverer = Arc::new(Deliverer {
+            client,
+            pool,
+            config: config.clone(),
+        });
+        tokio::spawn(run(rx, deliverer));
+        Self {
+            tx: Some(tx),
+            allowed_hosts,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    /// [`validate_callback_url`] against this sender's `allowed_hosts`.
+    pub fn validate_callback_url(&self, url: &str) -> Result<(), String> {
+        validate_callback_url(url, &self.allowed_hosts)
+    }
+
+    /// Enqueue a POST of the task's current state to `url`.
+    pub fn notify(&self, task_id: &str, url: &str) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let delivery = Delivery {
+            task_id: task_id.to_string(),
+            url: url.to_string(),
+        };
+        if let Err(e) = tx.try_send(delivery) {
+            valka_core::metrics::record_webhook_delivery("dropped");
+            warn!(task_id, error = %e, "Dropped task webhook: delivery queue unavailable");
+        }
+    }
+}
+
+async fn run(mut rx: mpsc::Receiver<Delivery>, deliverer: Arc<Deliverer>) {
+    let permits = Arc::new(Semaphore::new(deliverer.config.concurrency.max(1)));
+    while let Some(delivery) = rx.recv().await {
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+        let deliverer = deliverer.clone();
+        tokio::spawn(async move {
+            deliverer.deliver(delivery).await;
+            drop(permit);
+        });
+    }
+}
+
+struct Deliverer {
+    client: reqwest::Client,
+    pool: DbPool,
+    config: WebhooksConfig,
+}
+
+impl Deliverer {
+    async fn deliver(&self, delivery: Delivery) {
+        let task_id = delivery.task_id.as_str();
+        let task = match valka_db::queries::tasks::get_task(&self.pool, task_id).await {
+            Ok(Some(task)) => task,
+            Ok(None) => {
+                warn!(task_id, "Task gone before its webhook was sent");
+                valka_core::metrics::record_webhook_delivery("failed");
+                return;
+            }
+            Err(e) => {
+                error!(task_id, error = %e, "Failed to load task for webhook");
+                valka_core::metrics::record_webhook_delivery("failed");
+                return;
+            }
+        };
+        // Rows written before the host checks existed, or by hand, are checked here
+        if let Err(e) = validate_callback_url(&delivery.url, &self.config.allowed_hosts) {
+            warn!(task_id, url = %delivery.url, error = %e, "Refused task webhook");
+            valka_core::metrics::record_webhook_delivery("failed");
+            return;
+        }
+        let body = payload(&task).to_string();
+
+        let mut attempt = 0;
+        loop {
+            match self.post(&delivery.url, &body).await {
+                Ok(()) => {
+                    debug!(task_id, attempt, "Delivered task webhook");
+                    valka_core::metrics::record_webhook_delivery("delivered");
+                    return;
+                }
+                Err(e) if attempt < self.config.max_retries => {
+                    let delay = self.retry_delay(attempt);
+                    warn!(
+                        task_id,
+                        url = %delivery.url,
+                        attempt,
+                        error = %e,
+                        "Task webhook failed, retrying in {delay:?}"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    error!(
+                        task_id,
+                        url = %delivery.url,
+                        attempts = attempt + 1,
+                        error = %e,
+                        "Giving up on task webhook"
+                    );
+                    valka_core::metrics::record_webhook_delivery("failed");
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn post(&self, url: &str, body: &str) -> Result<(), String> {
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = self.config.signing_secret.as_deref() {
+            let timestamp = Utc::now().timestamp().to_string();
+            let signature = sign(secret, &timestamp, body.as_bytes());
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, signature);
+        }
+        let response = request
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("endpoint answered {}", response.status()))
+        }
+    }
+
+    /// `retry_base_delay_ms * 2^attempt`, capped at [`MAX_RETRY_DELAY`].
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let ms = self
+            .config
+            .retry_base_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempt));
+        Duration::from_millis(ms).min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Body POSTed for a task: its REST JSON plus the event fields read by
+/// `valka_sdk::webhooks::TaskWebhookEvent`.
+pub fn payload(task: &TaskRow) -> serde_json::Value {
+    let mut json = task.to_json();
+    json["event_type"] = event_type(&task.status).into();
+    json["task_id"] = task.id.clone().into();
+    json["attempt"] = task.attempt_count.into();
+    json
+}
+
+fn event_type(status: &str) -> String {
+    match status {
+        "DEAD_LETTER" => "task.dead_lettered".to_string(),
+        other => format!("task.{}", other.to_ascii_lowercase()),
+    }
+}
+
+/// `v1=<hex>` signature of `"{timestamp}.{body}"`.
+fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key size");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("v1={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Check a `callback_url` given at task creation: an absolute http(s) URL
+/// whose host is not a loopback, private or link-local address (or
+/// `localhost`) unless it is in `allowed_hosts`. Names are checked again once
+/// resolved, at delivery.
+pub fn validate_callback_url(url: &str, allowed_hosts: &[String]) -> Result<(), String> {
+    if url.len() > MAX_CALLBACK_URL_LEN {
+        return Err(format!(
+            "callback_url is longer than {MAX_CALLBACK_URL_LEN} bytes"
+        ));
+    }
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid callback_url: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("callback_url must be an http or https URL".to_string());
+    }
+    let Some(host) = parsed.host() else {
+        return Err("callback_url has no host".to_string());
+    };
+    let internal = match &host {
+        Host::Ipv4(ip) => !is_public((*ip).into()),
+        Host::Ipv6(ip) => !is_public((*ip).into()),
+        Host::Domain(name) => {
+            let name = name.trim_end_matches('.');
+            name.eq_ignore_ascii_case("localhost")
+                || name.to_ascii_lowercase().ends_with(".localhost")
+        }
+    };
+    if internal && !is_allowed_host(&host.to_string(), allowed_hosts) {
+        return Err(format!(
+            "callback_url host {host} is not a public address; \
+             add it to webhooks.allowed_hosts to allow it"
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `host` is listed in `allowed_hosts`. IPv6 hosts match with or
+/// without brackets.
+fn is_allowed_host(host: &str, allowed_hosts: &[String]) -> bool {
+    let bare = |host: &str| {
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string()
+    };
+    let host = bare(host);
+    allowed_hosts
+        .iter()
+        .any(|allowed| bare(allowed).eq_ignore_ascii_case(&host))
+}
+
+/// False for loopback, private, link-local, shared (CGNAT), multicast,
+/// broadcast, documentation and reserved addresses.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || (a == 100 && b & 0xc0 == 64)
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(ip.into()),
+            None => {
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// DNS for deliveries: a name resolving to any non-public address is refused
+/// unless it is in `allowed_hosts`. Checking here rather than before the
+/// request keeps a name from resolving one way when checked and another when
+/// connected to.
+struct PublicResolver {
+    allowed_hosts: Arc<Vec<String>>,
+}
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let allowed = is_allowed_host(name.as_str(), &self.allowed_hosts);
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if !allowed && let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+                return Err(format!("{host} resolves to non-public address {}", addr.ip()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
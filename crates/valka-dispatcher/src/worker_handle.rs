@@ -1,20 +1,74 @@
 use chrono::{DateTime, Utc};
-use std::collections::HashSet;
-use tokio::sync::mpsc;
+use semver::Version;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::{mpsc, watch};
 use valka_core::WorkerId;
-use valka_proto::WorkerResponse;
+use valka_core::labels::Labels;
+use valka_proto::{CAPABILITY_PROGRESS, CAPABILITY_SIGNALS, WorkerResponse};
+
+/// What a worker that predates protocol versioning handles: everything the
+/// server sent before capabilities were negotiated.
+const LEGACY_CAPABILITIES: &[&str] = &[CAPABILITY_SIGNALS, CAPABILITY_PROGRESS];
+
+/// Where a worker is in a server-initiated drain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerState {
+    Connected,
+    /// Gets no new tasks; finishing the ones it has
+    Draining,
+    /// Drain finished or timed out; its stream is being closed
+    Closed,
+}
+
+impl WorkerState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkerState::Connected => "CONNECTED",
+            WorkerState::Draining => "DRAINING",
+            WorkerState::Closed => "CLOSED",
+        }
+    }
+}
 
 /// Represents a connected worker and its communication channel.
 pub struct WorkerHandle {
     pub worker_id: WorkerId,
     pub worker_name: String,
+    /// Queues the worker subscribed to, each once; it is assigned tasks from no others
     pub queues: Vec<String>,
     pub concurrency: i32,
+    /// Caps on how many tasks of a queue run at once, within `concurrency`
+    pub queue_concurrency: HashMap<String, i32>,
     pub active_tasks: HashSet<String>,
+    /// Queue of each active task
+    task_queues: HashMap<String, String>,
+    /// Active tasks per queue
+    queue_active: HashMap<String, i32>,
+    /// Active tasks missing from the last heartbeat; dropped if the next one omits them too
+    pub unclaimed_tasks: HashSet<String>,
+    /// Number of tasks the last heartbeat reported running
+    pub reported_tasks: usize,
     pub response_tx: mpsc::Sender<WorkerResponse>,
     pub last_heartbeat: DateTime<Utc>,
     pub connected_at: DateTime<Utc>,
     pub metadata: String,
+    /// Version string as declared in WorkerHello
+    pub version: String,
+    /// Parsed `version`; 0.0.0 when missing or invalid
+    pub parsed_version: Version,
+    /// Capabilities declared in WorkerHello; only tasks whose required
+    /// labels these satisfy are assigned to it
+    pub labels: Labels,
+    /// Worker stream protocol from WorkerHello; 0 predates versioning
+    pub protocol_version: i32,
+    /// Features the worker handles; the server sends it nothing else
+    pub capabilities: HashSet<String>,
+    /// Identifies this stream, sent back in the HelloAck
+    pub session_id: String,
+    /// Drain progress, watched by the worker's stream and match loop
+    pub state: watch::Sender<WorkerState>,
+    /// When a drain gives up and closes the stream; None waits for active tasks
+    pub drain_deadline: Option<DateTime<Utc>>,
 }
 
 impl WorkerHandle {
@@ -27,33 +81,172 @@ impl WorkerHandle {
         metadata: String,
     ) -> Self {
         let now = Utc::now();
+        let mut seen = HashSet::new();
+        let queues = queues
+            .into_iter()
+            .filter(|queue| seen.insert(queue.clone()))
+            .collect();
         Self {
             worker_id,
             worker_name,
             queues,
             concurrency,
+            queue_concurrency: HashMap::new(),
             active_tasks: HashSet::new(),
+            task_queues: HashMap::new(),
+            queue_active: HashMap::new(),
+            unclaimed_tasks: HashSet::new(),
+            reported_tasks: 0,
             response_tx,
             last_heartbeat: now,
             connected_at: now,
             metadata,
+            version: String::new(),
+            parsed_version: Version::new(0, 0, 0),
+            labels: Labels::new(),
+            protocol_version: 0,
+            capabilities: LEGACY_CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+            session_id: String::new(),
+            state: watch::Sender::new(WorkerState::Connected),
+            drain_deadline: None,
+        }
+    }
+
+    pub fn with_version(mut self, version: String) -> Self {
+        self.parsed_version =
+            crate::queue_policy::parse_worker_version(&self.worker_id.0, &version);
+        self.version = version;
+        self
+    }
+
+    pub fn with_labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// The protocol and capabilities from WorkerHello. A worker without a
+    /// protocol version keeps the legacy capabilities whatever it lists.
+    pub fn with_protocol(mut self, protocol_version: i32, capabilities: Vec<String>) -> Self {
+        self.protocol_version = protocol_version;
+        if protocol_version > 0 {
+            self.capabilities = capabilities.into_iter().collect();
         }
+        self
+    }
+
+    pub fn with_session_id(mut self, session_id: String) -> Self {
+        self.session_id = session_id;
+        self
+    }
+
+    /// Whether the worker advertised `capability`.
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.contains(capability)
+    }
+
+    /// Per-queue caps; caps below 1 are ignored.
+    pub fn with_queue_concurrency(mut self, caps: HashMap<String, i32>) -> Self {
+        self.queue_concurrency = caps.into_iter().filter(|(_, cap)| *cap > 0).collect();
+        self
     }
 
     pub fn available_slots(&self) -> i32 {
         self.concurrency - self.active_tasks.len() as i32
     }
 
+    /// Free slots for a queue: the global ones, further limited by the
+    /// queue's own cap if it has one.
+    pub fn available_queue_slots(&self, queue: &str) -> i32 {
+        let available = self.available_slots();
+        match self.queue_concurrency.get(queue) {
+            Some(cap) => available.min(cap - self.queue_active_tasks(queue)),
+            None => available,
+        }
+    }
+
+    pub fn queue_active_tasks(&self, queue: &str) -> i32 {
+        self.queue_active.get(queue).copied().unwrap_or(0)
+    }
+
+    pub fn state(&self) -> WorkerState {
+        *self.state.borrow()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.state() != WorkerState::Connected
+    }
+
+    pub fn set_state(&self, state: WorkerState) {
+        self.state.send_replace(state);
+    }
+
     pub fn is_idle(&self) -> bool {
         self.active_tasks.is_empty()
     }
 
-    pub fn assign_task(&mut self, task_id: String) {
-        self.active_tasks.insert(task_id);
+    pub fn assign_task(&mut self, task_id: String, queue: &str) {
+        if self.active_tasks.insert(task_id.clone()) {
+            *self.queue_active.entry(queue.to_string()).or_default() += 1;
+            self.task_queues.insert(task_id, queue.to_string());
+        }
     }
 
     pub fn complete_task(&mut self, task_id: &str) {
-        self.active_tasks.remove(task_id);
+        if self.active_tasks.remove(task_id) {
+            self.release_queue_slot(task_id);
+        }
+        self.unclaimed_tasks.remove(task_id);
+    }
+
+    fn release_queue_slot(&mut self, task_id: &str) {
+        let Some(queue) = self.task_queues.remove(task_id) else {
+            return;
+        };
+        if let Some(active) = self.queue_active.get_mut(&queue) {
+            *active -= 1;
+            if *active <= 0 {
+                self.queue_active.remove(&queue);
+            }
+        }
+    }
+
+    /// Reconcile with the tasks a heartbeat reports running. A task missing
+    /// from two heartbeats in a row (its result was lost) is dropped, freeing
+    /// its slot; one miss is tolerated for assignments still in flight.
+    /// Returns the dropped task ids.
+    pub fn reconcile_reported(&mut self, reported: &[String]) -> Vec<String> {
+        self.reported_tasks = reported.len();
+        let reported: HashSet<&str> = reported.iter().map(String::as_str).collect();
+        let missing: HashSet<String> = self
+            .active_tasks
+            .iter()
+            .filter(|id| !reported.contains(id.as_str()))
+            .cloned()
+            .collect();
+
+        let (dropped, unclaimed): (Vec<String>, Vec<String>) = missing
+            .into_iter()
+            .partition(|id| self.unclaimed_tasks.contains(id));
+        for task_id in &dropped {
+            self.active_tasks.remove(task_id);
+            self.release_queue_slot(task_id);
+        }
+        self.unclaimed_tasks = unclaimed.into_iter().collect();
+        dropped
+    }
+
+    /// Take over the task bookkeeping of an earlier session of this worker,
+    /// leaving `previous` with none.
+    pub fn adopt_tasks(&mut self, previous: &mut WorkerHandle) {
+        self.active_tasks
+            .extend(std::mem::take(&mut previous.active_tasks));
+        self.task_queues
+            .extend(std::mem::take(&mut previous.task_queues));
+        for (queue, active) in std::mem::take(&mut previous.queue_active) {
+            *self.queue_active.entry(queue).or_default() += active;
+        }
+        self.unclaimed_tasks
+            .extend(std::mem::take(&mut previous.unclaimed_tasks));
     }
 
     pub fn update_heartbeat(&mut self) {
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use valka_core::{PartitionId, WorkerId};
+
+/// What matching or dispatch did with a task at one step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecisionOutcome {
+    /// Read from PG by a TaskReader
+    Dequeued,
+    /// Handed to a waiting worker slot
+    Matched(WorkerId),
+    /// Offered, but no worker was waiting
+    Unmatched,
+    Buffered,
+    /// The partition buffer was full
+    RejectedFull,
+    /// Pushed out of a full partition buffer by a higher-priority task
+    Evicted,
+    /// Put back to PENDING after sitting in the buffer past its TTL, or when
+    /// the node shut down
+    Spilled,
+    /// A waiting slot whose worker had gone away was passed over
+    SkippedStaleSlot(WorkerId),
+    SkippedPaused,
+    /// Another task of its FIFO key is in flight on this node
+    SkippedFifoHeld,
+    /// The matched worker no longer qualifies (version below the queue's
+    /// minimum, or draining) and gave the task back
+    SkippedExcluded(WorkerId),
+    /// The waiting worker lacks one of the task's required labels, so the
+    /// task went on to the next one
+    SkippedLabelMismatch(WorkerId),
+    /// The task's queue isn't one the worker subscribed to, so it went back
+    /// to the buffer
+    SkippedQueueMismatch(WorkerId),
+    /// Assigned to the worker and sent
+    Dispatched(WorkerId),
+    /// Past its deadline when it reached the worker, so failed instead
+    Expired,
+    /// The run could not be recorded or the worker's stream was gone
+    DispatchFailed(WorkerId),
+}
+
+impl DecisionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dequeued => "dequeued",
+            Self::Matched(_) => "matched",
+            Self::Unmatched => "unmatched",
+            Self::Buffered => "buffered",
+            Self::RejectedFull => "rejected-full",
+            Self::Evicted => "evicted",
+            Self::Spilled => "spilled",
+            Self::SkippedStaleSlot(_) => "skipped-stale-slot",
+            Self::SkippedPaused => "skipped-paused",
+            Self::SkippedFifoHeld => "skipped-fifo-held",
+            Self::SkippedExcluded(_) => "skipped-excluded",
+            Self::SkippedLabelMismatch(_) => "skipped-label-mismatch",
+            Self::SkippedQueueMismatch(_) => "skipped-queue-mismatch",
+            Self::Dispatched(_) => "dispatched",
+            Self::Expired => "expired",
+            Self::DispatchFailed(_) => "dispatch-failed",
+        }
+    }
+
+    /// The worker the decision concerned, if any.
+    pub fn worker_id(&self) -> Option<&WorkerId> {
+        match self {
+            Self::Matched(w)
+            | Self::SkippedStaleSlot(w)
+            | Self::SkippedExcluded(w)
+            | Self::SkippedLabelMismatch(w)
+            | Self::SkippedQueueMismatch(w)
+            | Self::Dispatched(w)
+            | Self::DispatchFailed(w) => Some(w),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DispatchDecision {
+    pub at: DateTime<Utc>,
+    pub queue_name: String,
+    pub partition_id: i32,
+    pub task_id: String,
+    pub outcome: DecisionOutcome,
+}
+
+/// Sampled log of matching and dispatch decisions on this node, for working
+/// out why pending tasks are not reaching idle workers.
+///
+/// Sampling is by task, so a sampled task has every step recorded. One in
+/// `sample_rate` tasks is kept (0 keeps none), except on queues forced to
+/// full sampling until their expiry. The newest `capacity` decisions are
+/// kept; a capacity of 0 disables the log.
+#[derive(Clone)]
+pub struct DecisionLog {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    capacity: usize,
+    sample_rate: u64,
+    entries: Mutex<VecDeque<DispatchDecision>>,
+    /// Queues sampled in full, until the given time
+    forced: DashMap<String, DateTime<Utc>>,
+}
+
+impl Default for DecisionLog {
+    fn default() -> Self {
+        Self::new(0, 0)
+    }
+}
+
+impl DecisionLog {
+    pub fn new(capacity: usize, sample_rate: u64) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                capacity,
+                sample_rate,
+                entries: Mutex::new(VecDeque::with_capacity(capacity)),
+                forced: DashMap::new(),
+            }),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u64 {
+        self.inner.sample_rate
+    }
+
+    /// Whether decisions about this task are recorded.
+    pub fn is_sampled(&self, queue_name: &str, task_id: &str) -> bool {
+        if self.inner.capacity == 0 {
+            return false;
+        }
+        if self.is_forced(queue_name) {
+            return true;
+        }
+        match self.inner.sample_rate {
+            0 => false,
+            1 => true,
+            rate => {
+                let mut hasher = DefaultHasher::new();
+                task_id.hash(&mut hasher);
+                hasher.finish().is_multiple_of(rate)
+            }
+        }
+    }
+
+    pub fn record(
+        &self,
+        queue_name: &str,
+        partition_id: PartitionId,
+        task_id: &str,
+        outcome: DecisionOutcome,
+    ) {
+        if !self.is_sampled(queue_name, task_id) {
+            return;
+        }
+        let decision = DispatchDecision {
+            at: Utc::now(),
+            queue_name: queue_name.to_string(),
+            partition_id: partition_id.0,
+            task_id: task_id.to_string(),
+            outcome,
+        };
+        let mut entries = self.inner.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.inner.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(decision);
+    }
+
+    /// Sample every task on `queue_name` for `ttl`. Returns when that ends.
+    pub fn force_sampling(&self, queue_name: &str, ttl: std::time::Duration) -> DateTime<Utc> {
+        let now = Utc::now();
+        let until = chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| now.checked_add_signed(ttl))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        self.inner.forced.insert(queue_name.to_string(), until);
+        until
+    }
+
+    /// Return a queue to the normal sampling rate.
+    pub fn clear_forced(&self, queue_name: &str) -> bool {
+        self.inner.forced.remove(queue_name).is_some()
+    }
+
+    /// Queues currently forced to full sampling, with when that ends.
+    pub fn forced_queues(&self) -> Vec<(String, DateTime<Utc>)> {
+        let now = Utc::now();
+        self.inner.forced.retain(|_, until| *until > now);
+        let mut forced: Vec<_> = self
+            .inner
+            .forced
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect();
+        forced.sort();
+        forced
+    }
+
+    /// Up to `limit` recorded decisions, newest first.
+    pub fn recent(&self, queue_name: Option<&str>, limit: usize) -> Vec<DispatchDecision> {
+        let entries = self.inner.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .rev()
+            .filter(|d| queue_name.is_none_or(|q| d.queue_name == q))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn is_forced(&self, queue_name: &str) -> bool {
+        let Some(until) = self.inner.forced.get(queue_name).map(|e| *e.value()) else {
+            return false;
+        };
+        if until > Utc::now() {
+            return true;
+        }
+        self.inner
+            .forced
+            .remove_if(queue_name, |_, until| *until <= Utc::now());
+        false
+    }
+}
@@ -0,0 +1,87 @@
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+/// (queue_name, fifo_key)
+type GroupKey = (String, String);
+
+/// FIFO queues and the keys whose task is in flight on this node.
+///
+/// PG is authoritative for ordering (see `dequeue_fifo_tasks`); this set only
+/// stops a second task of a key being matched while one is already buffered
+/// or running here.
+#[derive(Clone, Default)]
+pub struct FifoGroups {
+    queues: Arc<RwLock<HashSet<String>>>,
+    /// Key -> task holding it
+    held: Arc<DashMap<GroupKey, String>>,
+    /// Task -> key it holds, so results can release by task id
+    holders: Arc<DashMap<String, GroupKey>>,
+}
+
+impl FifoGroups {
+    /// Turn FIFO ordering on or off for a queue.
+    pub fn set_fifo(&self, queue_name: &str, enabled: bool) {
+        let mut set = self.queues.write().unwrap_or_else(|e| e.into_inner());
+        if enabled {
+            set.insert(queue_name.to_string());
+        } else {
+            set.remove(queue_name);
+        }
+    }
+
+    /// Replace the FIFO queue set (used by the periodic policy refresh).
+    pub fn replace_queues(&self, queues: HashSet<String>) {
+        *self.queues.write().unwrap_or_else(|e| e.into_inner()) = queues;
+    }
+
+    pub fn is_fifo(&self, queue_name: &str) -> bool {
+        let set = self.queues.read().unwrap_or_else(|e| e.into_inner());
+        set.contains(queue_name)
+    }
+
+    /// The key a task is ordered by: its fifo_key, if its queue is FIFO.
+    pub fn key_for<'a>(&self, queue_name: &str, fifo_key: Option<&'a str>) -> Option<&'a str> {
+        fifo_key.filter(|_| self.is_fifo(queue_name))
+    }
+
+    /// Take `key` for `task_id`. False if another task holds it.
+    pub fn try_hold(&self, queue_name: &str, key: &str, task_id: &str) -> bool {
+        match self.held.entry((queue_name.to_string(), key.to_string())) {
+            Entry::Occupied(entry) => entry.get() == task_id,
+            Entry::Vacant(entry) => {
+                entry.insert(task_id.to_string());
+                self.holders.insert(
+                    task_id.to_string(),
+                    (queue_name.to_string(), key.to_string()),
+                );
+                true
+            }
+        }
+    }
+
+    /// Take `key` for `task_id` even if another task holds it. For tasks PG
+    /// has already cleared to run, which makes any existing hold stale.
+    pub fn hold(&self, queue_name: &str, key: &str, task_id: &str) {
+        let group = (queue_name.to_string(), key.to_string());
+        if let Some(previous) = self.held.insert(group.clone(), task_id.to_string())
+            && previous != task_id
+        {
+            self.holders.remove(&previous);
+        }
+        self.holders.insert(task_id.to_string(), group);
+    }
+
+    /// Free the key held by `task_id`, if any.
+    pub fn release(&self, task_id: &str) {
+        if let Some((_, group)) = self.holders.remove(task_id) {
+            self.held.remove_if(&group, |_, holder| holder == task_id);
+        }
+    }
+
+    pub fn is_held(&self, queue_name: &str, key: &str) -> bool {
+        self.held
+            .contains_key(&(queue_name.to_string(), key.to_string()))
+    }
+}
@@ -1,4 +1,8 @@
+pub mod decisions;
+pub mod fifo;
 pub mod partition;
+pub mod paused;
+pub mod rate_limit;
 pub mod service;
 pub mod sync_match;
 pub mod task_reader;
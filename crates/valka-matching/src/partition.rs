@@ -1,6 +1,12 @@
-use std::collections::VecDeque;
+use crate::decisions::{DecisionLog, DecisionOutcome};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::Arc;
 use tokio::sync::oneshot;
+use tokio::time::{Duration, Instant};
+use valka_core::labels::{self, Labels};
 use valka_core::{PartitionId, WorkerId};
+use valka_db::queries::tasks::CreateTaskParams;
 
 /// A task envelope passed through the matching service
 #[derive(Debug)]
@@ -14,12 +20,37 @@ pub struct TaskEnvelope {
     pub timeout_seconds: i32,
     pub metadata: String,
     pub priority: i32,
+    /// Absolute deadline in epoch ms; expired envelopes are failed instead of dispatched
+    pub deadline_ms: Option<i64>,
+    /// Epoch ms the task became dispatchable, for the queue wait metric
+    pub ready_at_ms: Option<i64>,
+    /// Epoch ms the task was created, passed on to the worker
+    pub created_at_ms: Option<i64>,
+    /// Epoch ms the task was scheduled for, if it was
+    pub scheduled_at_ms: Option<i64>,
+    /// W3C traceparent the dispatch span is parented under
+    pub traceparent: Option<String>,
+    /// Ordering group; only honoured on FIFO queues
+    pub fifo_key: Option<String>,
+    /// Labels the worker handed this task must carry
+    pub required_labels: Labels,
+    /// Set on ephemeral tasks that have no row yet: what the dispatcher writes
+    /// once the task is assigned
+    pub ephemeral: Option<Box<CreateTaskParams>>,
 }
 
 /// A worker slot waiting for a task assignment
 pub struct WorkerSlot {
     pub worker_id: WorkerId,
     pub task_sender: oneshot::Sender<TaskEnvelope>,
+    /// The worker's labels, checked against each task's `required_labels`
+    pub labels: Arc<Labels>,
+}
+
+impl WorkerSlot {
+    fn can_run(&self, task: &TaskEnvelope) -> bool {
+        labels::satisfies(&self.labels, &task.required_labels)
+    }
 }
 
 impl std::fmt::Debug for WorkerSlot {
@@ -30,15 +61,77 @@ impl std::fmt::Debug for WorkerSlot {
     }
 }
 
+/// A buffered task. The highest priority comes out first, and among equal
+/// priorities the one buffered earliest.
+#[derive(Debug)]
+pub struct BufferedTask {
+    pub task: TaskEnvelope,
+    seq: u64,
+    buffered_at: Instant,
+}
+
+impl Ord for BufferedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.task
+            .priority
+            .cmp(&other.task.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for BufferedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for BufferedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for BufferedTask {}
+
+/// What happened to a task handed to `buffer_task`. A task that comes back
+/// is no longer held anywhere in memory and belongs back in PENDING.
+#[derive(Debug)]
+pub enum BufferOutcome {
+    Buffered,
+    /// Buffered by pushing out this lower-priority task from a full buffer
+    Evicted(TaskEnvelope),
+    /// Not buffered: the buffer is full of tasks at least as urgent, or
+    /// another task of its FIFO key is in flight
+    Rejected(TaskEnvelope),
+}
+
+impl BufferOutcome {
+    pub fn is_buffered(&self) -> bool {
+        !matches!(self, Self::Rejected(_))
+    }
+
+    /// The task that did not end up buffered, if any.
+    pub fn into_unbuffered(self) -> Option<TaskEnvelope> {
+        match self {
+            Self::Buffered => None,
+            Self::Evicted(task) | Self::Rejected(task) => Some(task),
+        }
+    }
+}
+
 /// A partition queue holding waiting workers and pending tasks
 pub struct PartitionQueue {
     pub partition_id: PartitionId,
     pub queue_name: String,
     pub waiting_workers: VecDeque<WorkerSlot>,
-    pub pending_tasks: VecDeque<TaskEnvelope>,
+    pub pending_tasks: BinaryHeap<BufferedTask>,
+    /// Arrival order of buffered tasks, to keep equal priorities FIFO
+    next_seq: u64,
     pub parent: Option<PartitionId>,
     pub children: Vec<PartitionId>,
     pub max_buffer_size: usize,
+    /// Where matches and skipped slots are sampled
+    pub decisions: DecisionLog,
 }
 
 impl PartitionQueue {
@@ -52,38 +145,78 @@ impl PartitionQueue {
             partition_id,
             queue_name,
             waiting_workers: VecDeque::new(),
-            pending_tasks: VecDeque::new(),
+            pending_tasks: BinaryHeap::new(),
+            next_seq: 0,
             parent,
             children: Vec::new(),
             max_buffer_size,
+            decisions: DecisionLog::default(),
         }
     }
 
-    /// Try to match a task with a waiting worker. Returns None if matched.
+    /// Try to match a task with a waiting worker whose labels satisfy it.
+    /// Returns None if matched.
     pub fn try_match_task(&mut self, mut task: TaskEnvelope) -> Option<TaskEnvelope> {
-        while let Some(slot) = self.waiting_workers.pop_front() {
+        let sampled = self.sampled_id(&task);
+        // Workers lacking a required label keep their place in line
+        let mut passed_over = Vec::new();
+        let unmatched = loop {
+            let Some(slot) = self.waiting_workers.pop_front() else {
+                break Some(task); // No workers available, buffer the task
+            };
+            if !slot.can_run(&task) {
+                self.record(
+                    sampled.clone(),
+                    DecisionOutcome::SkippedLabelMismatch(slot.worker_id.clone()),
+                );
+                passed_over.push(slot);
+                continue;
+            }
             // Try to send; if receiver dropped, skip this worker
             match slot.task_sender.send(task) {
-                Ok(()) => return None, // Matched!
+                Ok(()) => {
+                    self.record(sampled, DecisionOutcome::Matched(slot.worker_id));
+                    break None; // Matched!
+                }
                 Err(returned_task) => {
                     // Worker disconnected, reclaim the task and try next
+                    self.record(
+                        sampled.clone(),
+                        DecisionOutcome::SkippedStaleSlot(slot.worker_id),
+                    );
                     task = returned_task;
-                    continue;
                 }
             }
+        };
+        for slot in passed_over.into_iter().rev() {
+            self.waiting_workers.push_front(slot);
         }
-        // No workers available, buffer the task
-        Some(task)
+        unmatched
     }
 
-    /// Register a waiting worker. If there's a pending task, match immediately.
+    /// Register a waiting worker. If there's a pending task it can run, match
+    /// the most urgent one immediately.
     pub fn register_worker(&mut self, slot: WorkerSlot) -> bool {
-        if let Some(task) = self.pending_tasks.pop_front() {
+        if let Some(BufferedTask {
+            task,
+            seq,
+            buffered_at,
+        }) = self.pop_runnable(&slot)
+        {
+            let sampled = self.sampled_id(&task);
             match slot.task_sender.send(task) {
-                Ok(()) => return true, // Matched immediately
+                Ok(()) => {
+                    self.record(sampled, DecisionOutcome::Matched(slot.worker_id));
+                    return true; // Matched immediately
+                }
                 Err(task) => {
-                    // Worker already gone, put task back
-                    self.pending_tasks.push_front(task);
+                    // Worker already gone, put task back in its place
+                    self.record(sampled, DecisionOutcome::SkippedStaleSlot(slot.worker_id));
+                    self.pending_tasks.push(BufferedTask {
+                        task,
+                        seq,
+                        buffered_at,
+                    });
                     return false;
                 }
             }
@@ -92,12 +225,130 @@ impl PartitionQueue {
         false
     }
 
-    /// Buffer a task (when no workers available)
-    pub fn buffer_task(&mut self, task: TaskEnvelope) -> bool {
+    /// Pair buffered tasks with waiting workers, most urgent first. A task no
+    /// waiting worker has the labels for stays buffered without holding up
+    /// the ones behind it. Returns how many were matched.
+    pub fn match_buffered(&mut self) -> usize {
+        let mut matched = 0;
+        let mut unmatched = Vec::new();
+        while !self.waiting_workers.is_empty() {
+            let Some(BufferedTask {
+                task,
+                seq,
+                buffered_at,
+            }) = self.pending_tasks.pop()
+            else {
+                break;
+            };
+            match self.try_match_task(task) {
+                None => matched += 1,
+                Some(task) => unmatched.push(BufferedTask {
+                    task,
+                    seq,
+                    buffered_at,
+                }),
+            }
+        }
+        self.pending_tasks.extend(unmatched);
+        matched
+    }
+
+    /// Take out the most urgent buffered task the slot's worker can run.
+    fn pop_runnable(&mut self, slot: &WorkerSlot) -> Option<BufferedTask> {
+        if slot.can_run(&self.pending_tasks.peek()?.task) {
+            return self.pending_tasks.pop();
+        }
+        let seq = self
+            .pending_tasks
+            .iter()
+            .filter(|buffered| slot.can_run(&buffered.task))
+            .max()?
+            .seq;
+        Some(self.remove_buffered(seq))
+    }
+
+    /// Take a buffered task out from anywhere in the heap. BinaryHeap can
+    /// only pop the top, so this rebuilds it; O(n).
+    fn remove_buffered(&mut self, seq: u64) -> BufferedTask {
+        let mut tasks = std::mem::take(&mut self.pending_tasks).into_vec();
+        let index = tasks
+            .iter()
+            .position(|buffered| buffered.seq == seq)
+            .expect("task is buffered");
+        let removed = tasks.swap_remove(index);
+        self.pending_tasks = tasks.into();
+        removed
+    }
+
+    /// Buffer a task (when no workers available). A full buffer makes room
+    /// for a task only by evicting one of strictly lower priority, the most
+    /// recently buffered of the lowest; otherwise the new task is rejected.
+    pub fn buffer_task(&mut self, task: TaskEnvelope) -> BufferOutcome {
+        let mut evicted = None;
         if self.pending_tasks.len() >= self.max_buffer_size {
-            return false; // Buffer full
+            let Some(lowest) = self
+                .pending_tasks
+                .iter()
+                .min()
+                .filter(|lowest| lowest.task.priority < task.priority)
+                .map(|lowest| lowest.seq)
+            else {
+                return BufferOutcome::Rejected(task); // Buffer full
+            };
+            evicted = Some(self.remove_buffered(lowest).task);
+        }
+        self.pending_tasks.push(BufferedTask {
+            task,
+            seq: self.next_seq,
+            buffered_at: Instant::now(),
+        });
+        self.next_seq += 1;
+        match evicted {
+            Some(task) => BufferOutcome::Evicted(task),
+            None => BufferOutcome::Buffered,
+        }
+    }
+
+    /// How many more tasks fit in the buffer.
+    pub fn free_capacity(&self) -> usize {
+        self.max_buffer_size
+            .saturating_sub(self.pending_tasks.len())
+    }
+
+    /// Take out the tasks buffered for at least `ttl`.
+    pub fn take_expired(&mut self, ttl: Duration) -> Vec<TaskEnvelope> {
+        let now = Instant::now();
+        let expired = |buffered: &BufferedTask| now.duration_since(buffered.buffered_at) >= ttl;
+        if !self.pending_tasks.iter().any(expired) {
+            return Vec::new();
+        }
+        let (expired, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_tasks)
+            .into_iter()
+            .partition(expired);
+        self.pending_tasks = kept.into();
+        expired.into_iter().map(|buffered| buffered.task).collect()
+    }
+
+    /// Take out every buffered task.
+    pub fn take_all(&mut self) -> Vec<TaskEnvelope> {
+        self.pending_tasks
+            .drain()
+            .map(|buffered| buffered.task)
+            .collect()
+    }
+
+    /// The task's id if its decisions are sampled; the envelope itself is
+    /// gone by the time the outcome is known.
+    fn sampled_id(&self, task: &TaskEnvelope) -> Option<String> {
+        self.decisions
+            .is_sampled(&self.queue_name, &task.task_id)
+            .then(|| task.task_id.clone())
+    }
+
+    fn record(&self, task_id: Option<String>, outcome: DecisionOutcome) {
+        if let Some(task_id) = task_id {
+            self.decisions
+                .record(&self.queue_name, self.partition_id, &task_id, outcome);
         }
-        self.pending_tasks.push_back(task);
-        true
     }
 }
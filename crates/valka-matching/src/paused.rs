@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::broadcast;
+
+/// Queues whose dispatch is paused on this node.
+///
+/// PG is authoritative (`queue_configs.paused`); this set mirrors it so the
+/// matching service and TaskReaders can check it without a query. Resumed
+/// queues are announced so their TaskReaders start again right away.
+#[derive(Clone)]
+pub struct PausedQueues {
+    queues: Arc<RwLock<HashSet<String>>>,
+    resumed: broadcast::Sender<String>,
+}
+
+impl Default for PausedQueues {
+    fn default() -> Self {
+        Self {
+            queues: Arc::default(),
+            resumed: broadcast::Sender::new(64),
+        }
+    }
+}
+
+impl PausedQueues {
+    /// Pause or resume a queue.
+    pub fn set_paused(&self, queue_name: &str, paused: bool) {
+        let mut set = self.queues.write().unwrap_or_else(|e| e.into_inner());
+        let resumed = if paused {
+            set.insert(queue_name.to_string());
+            false
+        } else {
+            set.remove(queue_name)
+        };
+        drop(set);
+        if resumed {
+            let _ = self.resumed.send(queue_name.to_string());
+        }
+    }
+
+    /// Replace the paused set (used by the periodic policy refresh), announcing
+    /// every queue that is no longer in it.
+    pub fn replace_queues(&self, queues: HashSet<String>) {
+        let mut set = self.queues.write().unwrap_or_else(|e| e.into_inner());
+        let previous = std::mem::replace(&mut *set, queues);
+        let resumed: Vec<_> = previous.difference(&set).cloned().collect();
+        drop(set);
+        for queue_name in resumed {
+            let _ = self.resumed.send(queue_name);
+        }
+    }
+
+    pub fn is_paused(&self, queue_name: &str) -> bool {
+        let set = self.queues.read().unwrap_or_else(|e| e.into_inner());
+        set.contains(queue_name)
+    }
+
+    /// Names of queues as they are resumed.
+    pub fn subscribe_resumed(&self) -> broadcast::Receiver<String> {
+        self.resumed.subscribe()
+    }
+}
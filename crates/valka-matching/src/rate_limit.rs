@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::time::{Duration, Instant};
+
+/// Per-queue dispatch rate limits on this node, in tasks per second.
+///
+/// PG is authoritative (`queue_configs.max_dispatch_per_second`); this mirrors
+/// it. The TaskReaders of a limited queue share one token bucket holding up to
+/// a second's worth of dispatches, so each node dispatches at most that many of
+/// the queue's tasks per second.
+#[derive(Clone, Default)]
+pub struct DispatchRateLimits {
+    buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+}
+
+struct TokenBucket {
+    per_second: u32,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_second: u32, now: Instant) -> Self {
+        Self {
+            per_second,
+            tokens: per_second as f64,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.per_second as f64).min(self.capacity());
+        self.refilled_at = now;
+    }
+
+    fn capacity(&self) -> f64 {
+        self.per_second as f64
+    }
+}
+
+/// Set, change or clear one queue's bucket. An unchanged limit keeps its
+/// tokens, so the periodic refresh doesn't hand out a fresh burst.
+fn apply_limit(
+    buckets: &mut HashMap<String, TokenBucket>,
+    queue_name: &str,
+    per_second: Option<u32>,
+    now: Instant,
+) {
+    match per_second.filter(|r| *r > 0) {
+        Some(rate) => match buckets.get_mut(queue_name) {
+            Some(bucket) if bucket.per_second == rate => {}
+            Some(bucket) => {
+                bucket.refill(now);
+                bucket.per_second = rate;
+                bucket.tokens = bucket.tokens.min(bucket.capacity());
+            }
+            None => {
+                buckets.insert(queue_name.to_string(), TokenBucket::new(rate, now));
+            }
+        },
+        None => {
+            buckets.remove(queue_name);
+        }
+    }
+}
+
+impl DispatchRateLimits {
+    /// Set a queue's limit; `None` or 0 lifts it.
+    pub fn set_limit(&self, queue_name: &str, per_second: Option<u32>) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        apply_limit(&mut buckets, queue_name, per_second, Instant::now());
+    }
+
+    /// Replace all limits at once (used by the periodic policy refresh).
+    pub fn replace_limits(&self, limits: HashMap<String, u32>) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets.retain(|queue_name, _| limits.contains_key(queue_name));
+        for (queue_name, per_second) in limits {
+            apply_limit(&mut buckets, &queue_name, Some(per_second), now);
+        }
+    }
+
+    /// The queue's limit in tasks per second, if it has one.
+    pub fn limit(&self, queue_name: &str) -> Option<u32> {
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets.get(queue_name).map(|b| b.per_second)
+    }
+
+    pub fn is_limited(&self, queue_name: &str) -> bool {
+        self.limit(queue_name).is_some()
+    }
+
+    /// Take up to `wanted` dispatches from the queue's budget and return how
+    /// many were granted. Unlimited queues get all of them.
+    pub fn acquire(&self, queue_name: &str, wanted: i64) -> i64 {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(bucket) = buckets.get_mut(queue_name) else {
+            return wanted;
+        };
+        bucket.refill(Instant::now());
+        let granted = (bucket.tokens.floor() as i64).min(wanted).max(0);
+        bucket.tokens -= granted as f64;
+        granted
+    }
+
+    /// Give back dispatches granted by [`acquire`](Self::acquire) that were
+    /// not used.
+    pub fn release(&self, queue_name: &str, unused: i64) {
+        if unused <= 0 {
+            return;
+        }
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(bucket) = buckets.get_mut(queue_name) {
+            bucket.tokens = (bucket.tokens + unused as f64).min(bucket.capacity());
+        }
+    }
+
+    /// How long until the queue's budget has a dispatch to grant again.
+    pub fn until_available(&self, queue_name: &str) -> Duration {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(bucket) = buckets.get_mut(queue_name) else {
+            return Duration::ZERO;
+        };
+        bucket.refill(Instant::now());
+        let missing = 1.0 - bucket.tokens;
+        if missing <= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(missing / bucket.per_second as f64)
+    }
+}
@@ -1,27 +1,68 @@
-use crate::partition::{PartitionQueue, TaskEnvelope, WorkerSlot};
+use crate::decisions::{DecisionLog, DecisionOutcome};
+use crate::fifo::FifoGroups;
+use crate::partition::{BufferOutcome, PartitionQueue, TaskEnvelope, WorkerSlot};
+use crate::paused::PausedQueues;
+use crate::rate_limit::DispatchRateLimits;
 use crate::sync_match;
 use dashmap::DashMap;
 use dashmap::mapref::one::{Ref, RefMut};
+use serde::Serialize;
 use std::sync::Arc;
-use tokio::sync::oneshot;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::Duration;
 use tracing::{debug, info};
+use valka_core::labels::Labels;
 use valka_core::{MatchingConfig, PartitionId, WorkerId};
 
 /// Composite key for partition lookup: (queue_name, partition_id)
 type PartitionKey = (String, i32);
 
+/// Point-in-time sizes of the matching service's in-memory state.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MatchingStats {
+    pub partitions: usize,
+    pub buffered_tasks: usize,
+    pub waiting_workers: usize,
+    /// Per partition, ordered by queue then partition
+    pub by_partition: Vec<PartitionStats>,
+}
+
+/// Buffered envelopes and waiting worker slots of one partition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PartitionStats {
+    pub queue_name: String,
+    pub partition_id: i32,
+    pub buffered_tasks: usize,
+    pub waiting_workers: usize,
+}
+
 /// The core matching service that routes tasks to workers.
 #[derive(Clone)]
 pub struct MatchingService {
     partitions: Arc<DashMap<PartitionKey, PartitionQueue>>,
     config: MatchingConfig,
+    /// Buffer size of each partition, changeable at runtime
+    max_buffer_per_partition: Arc<AtomicUsize>,
+    fifo: FifoGroups,
+    paused: PausedQueues,
+    rate_limits: DispatchRateLimits,
+    decisions: DecisionLog,
+    /// Labels of the connected workers that declared any
+    worker_labels: Arc<DashMap<WorkerId, Arc<Labels>>>,
 }
 
 impl MatchingService {
     pub fn new(config: MatchingConfig) -> Self {
         Self {
             partitions: Arc::new(DashMap::new()),
+            decisions: DecisionLog::new(config.decision_log_size, config.decision_sample_rate),
+            max_buffer_per_partition: Arc::new(AtomicUsize::new(config.max_buffer_per_partition)),
             config,
+            fifo: FifoGroups::default(),
+            paused: PausedQueues::default(),
+            rate_limits: DispatchRateLimits::default(),
+            worker_labels: Arc::new(DashMap::new()),
         }
     }
 
@@ -44,8 +85,9 @@ impl MatchingService {
                 PartitionId(i),
                 queue_name.to_string(),
                 parent,
-                self.config.max_buffer_per_partition,
+                self.max_buffer_per_partition.load(Ordering::Relaxed),
             );
+            pq.decisions = self.decisions.clone();
 
             // Set children
             for c in 1..=bf {
@@ -59,6 +101,20 @@ impl MatchingService {
         }
     }
 
+    /// Resize every partition's buffer, and those created from now on. A
+    /// buffer already over the new size keeps its tasks but takes no more
+    /// until it drains below it.
+    pub fn set_max_buffer_per_partition(&self, size: usize) {
+        let previous = self.max_buffer_per_partition.swap(size, Ordering::Relaxed);
+        if previous == size {
+            return;
+        }
+        for mut partition in self.partitions.iter_mut() {
+            partition.max_buffer_size = size;
+        }
+        info!(previous, size, "Resized matching partition buffers");
+    }
+
     pub fn get_partition(
         &self,
         queue_name: &str,
@@ -77,7 +133,9 @@ impl MatchingService {
             .get_mut(&(queue_name.to_string(), partition_id.0))
     }
 
-    /// Offer a task for sync matching. Returns the task back if no match.
+    /// Offer a task for sync matching. Returns the task back if no match, if
+    /// its queue is paused, or if another task of its FIFO key is in flight on
+    /// this node.
     pub fn offer_task(
         &self,
         queue_name: &str,
@@ -85,11 +143,46 @@ impl MatchingService {
         task: TaskEnvelope,
     ) -> Result<(), TaskEnvelope> {
         self.ensure_queue(queue_name);
-        sync_match::try_sync_match(self, queue_name, partition_id, task)
+        if self.paused.is_paused(queue_name) {
+            self.decisions.record(
+                queue_name,
+                partition_id,
+                &task.task_id,
+                DecisionOutcome::SkippedPaused,
+            );
+            return Err(task);
+        }
+        let Some(key) = self.fifo.key_for(queue_name, task.fifo_key.as_deref()) else {
+            return sync_match::try_sync_match(self, queue_name, partition_id, task)
+                .inspect_err(|task| self.record_unmatched(queue_name, partition_id, task));
+        };
+        if !self.fifo.try_hold(queue_name, key, &task.task_id) {
+            self.decisions.record(
+                queue_name,
+                partition_id,
+                &task.task_id,
+                DecisionOutcome::SkippedFifoHeld,
+            );
+            return Err(task);
+        }
+        sync_match::try_sync_match(self, queue_name, partition_id, task).inspect_err(|task| {
+            self.fifo.release(&task.task_id);
+            self.record_unmatched(queue_name, partition_id, task);
+        })
+    }
+
+    fn record_unmatched(&self, queue_name: &str, partition_id: PartitionId, task: &TaskEnvelope) {
+        self.decisions.record(
+            queue_name,
+            partition_id,
+            &task.task_id,
+            DecisionOutcome::Unmatched,
+        );
     }
 
     /// Register a worker as waiting for a task on a given queue/partition.
-    /// Returns a oneshot receiver that will receive the task assignment.
+    /// Returns a oneshot receiver that will receive the task assignment. On a
+    /// paused queue the worker only waits; buffered tasks stay put.
     pub fn register_worker(
         &self,
         queue_name: &str,
@@ -102,9 +195,19 @@ impl MatchingService {
         let slot = WorkerSlot {
             worker_id: worker_id.clone(),
             task_sender: tx,
+            labels: self
+                .worker_labels
+                .get(&worker_id)
+                .map(|labels| labels.clone())
+                .unwrap_or_default(),
         };
 
+        let paused = self.paused.is_paused(queue_name);
         if let Some(mut partition) = self.get_partition_mut(queue_name, partition_id) {
+            if paused {
+                partition.waiting_workers.push_back(slot);
+                return rx;
+            }
             let matched = partition.register_worker(slot);
             if matched {
                 debug!(
@@ -119,33 +222,243 @@ impl MatchingService {
         rx
     }
 
+    /// Record the labels a worker declared; its slots are then only offered
+    /// tasks whose `required_labels` they satisfy. Applies to slots
+    /// registered from now on.
+    pub fn set_worker_labels(&self, worker_id: &WorkerId, labels: Labels) {
+        if labels.is_empty() {
+            self.worker_labels.remove(worker_id);
+        } else {
+            self.worker_labels
+                .insert(worker_id.clone(), Arc::new(labels));
+        }
+    }
+
     /// Deregister a worker from all partitions (e.g., on disconnect)
     pub fn deregister_worker(&self, worker_id: &WorkerId) {
+        self.withdraw_worker(worker_id);
+        self.worker_labels.remove(worker_id);
+        info!(worker = %worker_id, "Worker deregistered from matching service");
+    }
+
+    /// Drop a worker's waiting slots from all partitions, e.g. while it is at
+    /// capacity. Their receivers see the channel closed.
+    pub fn withdraw_worker(&self, worker_id: &WorkerId) {
         for mut entry in self.partitions.iter_mut() {
             entry
                 .value_mut()
                 .waiting_workers
                 .retain(|slot| slot.worker_id != *worker_id);
         }
-        info!(worker = %worker_id, "Worker deregistered from matching service");
     }
 
-    /// Buffer a task that wasn't matched (for TaskReader path)
+    /// Buffer a task that wasn't matched (for TaskReader path). Refused like
+    /// a full buffer while another task of its FIFO key is in flight. The
+    /// caller puts any task handed back (refused, or evicted for a more
+    /// urgent one) back to PENDING.
     pub fn buffer_task(
         &self,
         queue_name: &str,
         partition_id: PartitionId,
         task: TaskEnvelope,
-    ) -> bool {
+    ) -> BufferOutcome {
         self.ensure_queue(queue_name);
-        if let Some(mut partition) = self.get_partition_mut(queue_name, partition_id) {
-            partition.buffer_task(task)
+        let key = self.fifo.key_for(queue_name, task.fifo_key.as_deref());
+        if let Some(key) = key
+            && !self.fifo.try_hold(queue_name, key, &task.task_id)
+        {
+            self.decisions.record(
+                queue_name,
+                partition_id,
+                &task.task_id,
+                DecisionOutcome::SkippedFifoHeld,
+            );
+            return BufferOutcome::Rejected(task);
+        }
+        let keyed = key.is_some();
+        let task_id = task.task_id.clone();
+        let outcome = match self.get_partition_mut(queue_name, partition_id) {
+            Some(mut partition) => partition.buffer_task(task),
+            None => BufferOutcome::Rejected(task),
+        };
+        if !outcome.is_buffered() && keyed {
+            self.fifo.release(&task_id);
+        }
+        if let BufferOutcome::Evicted(evicted) = &outcome {
+            self.fifo.release(&evicted.task_id);
+            self.decisions.record(
+                queue_name,
+                partition_id,
+                &evicted.task_id,
+                DecisionOutcome::Evicted,
+            );
+        }
+        let decision = if outcome.is_buffered() {
+            DecisionOutcome::Buffered
         } else {
-            false
+            DecisionOutcome::RejectedFull
+        };
+        self.decisions
+            .record(queue_name, partition_id, &task_id, decision);
+        outcome
+    }
+
+    /// Hand buffered tasks to workers that are already waiting, e.g. ones that
+    /// registered while the queue was paused. Returns how many were matched.
+    pub fn match_buffered(&self, queue_name: &str, partition_id: PartitionId) -> usize {
+        if self.paused.is_paused(queue_name) {
+            return 0;
         }
+        match self.get_partition_mut(queue_name, partition_id) {
+            Some(mut partition) => partition.match_buffered(),
+            None => 0,
+        }
+    }
+
+    /// How many more tasks the partition's buffer can hold before it is full.
+    pub fn free_buffer_capacity(&self, queue_name: &str, partition_id: PartitionId) -> usize {
+        self.ensure_queue(queue_name);
+        self.get_partition(queue_name, partition_id)
+            .map_or(0, |partition| partition.free_capacity())
     }
 
+    /// Take out the tasks buffered for longer than
+    /// `matching.buffered_task_ttl_ms`; none while the TTL is 0.
+    pub fn expire_buffered(&self) -> Vec<TaskEnvelope> {
+        if self.config.buffered_task_ttl_ms == 0 {
+            return Vec::new();
+        }
+        let ttl = Duration::from_millis(self.config.buffered_task_ttl_ms);
+        self.spill(|partition| partition.take_expired(ttl))
+    }
+
+    /// Take every buffered task out, e.g. when the node shuts down.
+    pub fn drain_buffered(&self) -> Vec<TaskEnvelope> {
+        self.spill(PartitionQueue::take_all)
+    }
+
+    /// Take every task buffered for one partition, e.g. when this node stops
+    /// owning it. The caller puts them back to PENDING.
+    pub fn drain_partition(
+        &self,
+        queue_name: &str,
+        partition_id: PartitionId,
+    ) -> Vec<TaskEnvelope> {
+        let tasks = match self.get_partition_mut(queue_name, partition_id) {
+            Some(mut partition) => partition.take_all(),
+            None => return Vec::new(),
+        };
+        for task in &tasks {
+            self.release_spilled(partition_id, task);
+        }
+        tasks
+    }
+
+    /// Take tasks out of every partition's buffer, releasing their FIFO keys.
+    /// The caller puts them back to PENDING.
+    fn spill(
+        &self,
+        mut take: impl FnMut(&mut PartitionQueue) -> Vec<TaskEnvelope>,
+    ) -> Vec<TaskEnvelope> {
+        let mut spilled = Vec::new();
+        for mut entry in self.partitions.iter_mut() {
+            let partition_id = entry.partition_id;
+            for task in take(entry.value_mut()) {
+                self.release_spilled(partition_id, &task);
+                spilled.push(task);
+            }
+        }
+        spilled
+    }
+
+    /// Release a spilled task's FIFO key and record the decision.
+    fn release_spilled(&self, partition_id: PartitionId, task: &TaskEnvelope) {
+        self.fifo.release(&task.task_id);
+        self.decisions.record(
+            &task.queue_name,
+            partition_id,
+            &task.task_id,
+            DecisionOutcome::Spilled,
+        );
+    }
+
+    /// Periodically take out buffered tasks past their TTL. They arrive on
+    /// the returned channel to be put back to PENDING.
+    pub fn start_buffer_sweeper(
+        &self,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> (tokio::task::JoinHandle<()>, mpsc::Receiver<TaskEnvelope>) {
+        let (spilled_tx, spilled_rx) = mpsc::channel(256);
+        let matching = self.clone();
+        // Sweep often enough that a task overstays its TTL by at most a quarter
+        let period = Duration::from_millis((self.config.buffered_task_ttl_ms / 4).max(10));
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        for task in matching.expire_buffered() {
+                            if spilled_tx.send(task).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        (handle, spilled_rx)
+    }
+
+    /// Count partitions, buffered envelopes and waiting worker slots, in
+    /// total and per partition.
+    pub fn stats(&self) -> MatchingStats {
+        let mut stats = MatchingStats::default();
+        for entry in self.partitions.iter() {
+            let (queue_name, partition_id) = entry.key();
+            let partition = PartitionStats {
+                queue_name: queue_name.clone(),
+                partition_id: *partition_id,
+                buffered_tasks: entry.pending_tasks.len(),
+                waiting_workers: entry.waiting_workers.len(),
+            };
+            stats.partitions += 1;
+            stats.buffered_tasks += partition.buffered_tasks;
+            stats.waiting_workers += partition.waiting_workers;
+            stats.by_partition.push(partition);
+        }
+        stats
+            .by_partition
+            .sort_by(|a, b| (&a.queue_name, a.partition_id).cmp(&(&b.queue_name, b.partition_id)));
+        stats
+    }
+
+    /// The configuration the service was started with.
     pub fn config(&self) -> &MatchingConfig {
         &self.config
     }
+
+    /// FIFO queues and the keys in flight on this node.
+    pub fn fifo(&self) -> &FifoGroups {
+        &self.fifo
+    }
+
+    /// Queues whose dispatch is paused.
+    pub fn paused(&self) -> &PausedQueues {
+        &self.paused
+    }
+
+    /// Queues whose TaskReaders dispatch at a capped rate.
+    pub fn rate_limits(&self) -> &DispatchRateLimits {
+        &self.rate_limits
+    }
+
+    /// Sampled matching and dispatch decisions on this node.
+    pub fn decisions(&self) -> &DecisionLog {
+        &self.decisions
+    }
 }
@@ -1,19 +1,69 @@
+use crate::decisions::DecisionOutcome;
 use crate::partition::TaskEnvelope;
 use crate::service::MatchingService;
 use sqlx::PgPool;
+use tokio::sync::watch;
 use tokio::time::{Duration, sleep};
 use tracing::{debug, error, info, warn};
-use valka_core::{MatchingConfig, PartitionId};
+use valka_core::{MatchingConfig, PartitionId, fault_point};
+
+/// Double the poll interval, staying between the busy and idle intervals.
+fn back_off(current: Duration, busy: Duration, idle: Duration) -> Duration {
+    (current * 2).max(busy).min(idle)
+}
 
 /// Background loop that reads PENDING tasks from PG (SKIP LOCKED) and feeds them
-/// into the matching service for async dispatch.
+/// into the matching service for async dispatch. It stops polling while its
+/// queue is paused and polls again as soon as the queue is resumed.
+///
+/// A full batch is followed by an immediate poll with a doubled batch, up to
+/// `task_reader_max_batch_size`; empty polls back off exponentially from the
+/// busy to the idle interval. A poll takes no more tasks than the partition's
+/// buffer has room for, and none while it is full; a queue's dispatch rate
+/// limit caps it further.
+///
+/// A standby reader, for a partition another node owns, doesn't poll until
+/// its standby flag is cleared. Poll intervals and batch sizes follow config
+/// updates, when given them.
 pub struct TaskReader {
     pool: PgPool,
     matching: MatchingService,
     queue_name: String,
     partition_id: PartitionId,
     config: MatchingConfig,
-    shutdown: tokio::sync::watch::Receiver<bool>,
+    shutdown: watch::Receiver<bool>,
+    standby: Option<watch::Receiver<bool>>,
+    config_updates: Option<watch::Receiver<MatchingConfig>>,
+}
+
+/// Resolves when the watched value changes; never for a reader without one.
+async fn watched_changed<T>(
+    rx: &mut Option<watch::Receiver<T>>,
+) -> Result<(), watch::error::RecvError> {
+    match rx {
+        Some(rx) => rx.changed().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Poll intervals and batch bounds, as set by the matching config.
+struct PollSettings {
+    busy_interval: Duration,
+    idle_interval: Duration,
+    base_batch: i64,
+    max_batch: i64,
+}
+
+impl PollSettings {
+    fn from_config(config: &MatchingConfig) -> Self {
+        let base_batch = config.task_reader_batch_size.max(1);
+        Self {
+            busy_interval: Duration::from_millis(config.task_reader_poll_busy_ms),
+            idle_interval: Duration::from_millis(config.task_reader_poll_idle_ms),
+            base_batch,
+            max_batch: config.task_reader_max_batch_size.max(base_batch),
+        }
+    }
 }
 
 impl TaskReader {
@@ -23,7 +73,7 @@ impl TaskReader {
         queue_name: String,
         partition_id: PartitionId,
         config: MatchingConfig,
-        shutdown: tokio::sync::watch::Receiver<bool>,
+        shutdown: watch::Receiver<bool>,
     ) -> Self {
         Self {
             pool,
@@ -32,9 +82,23 @@ impl TaskReader {
             partition_id,
             config,
             shutdown,
+            standby: None,
+            config_updates: None,
         }
     }
 
+    /// Hold polls while `standby` is true, and poll at once when it clears.
+    pub fn with_standby(mut self, standby: watch::Receiver<bool>) -> Self {
+        self.standby = Some(standby);
+        self
+    }
+
+    /// Take poll intervals and batch sizes from `config` whenever it changes.
+    pub fn with_config_updates(mut self, config: watch::Receiver<MatchingConfig>) -> Self {
+        self.config_updates = Some(config);
+        self
+    }
+
     pub async fn run(mut self) {
         info!(
             queue = %self.queue_name,
@@ -42,9 +106,17 @@ impl TaskReader {
             "TaskReader started"
         );
 
-        let busy_interval = Duration::from_millis(self.config.task_reader_poll_busy_ms);
-        let idle_interval = Duration::from_millis(self.config.task_reader_poll_idle_ms);
+        let PollSettings {
+            mut busy_interval,
+            mut idle_interval,
+            mut base_batch,
+            mut max_batch,
+        } = PollSettings::from_config(&self.config);
+        let mut batch = base_batch;
         let mut current_interval = idle_interval;
+        let mut resumed = self.matching.paused().subscribe_resumed();
+        let mut standby = self.standby.take();
+        let mut config_updates = self.config_updates.take();
 
         loop {
             tokio::select! {
@@ -58,21 +130,104 @@ impl TaskReader {
                         break;
                     }
                 }
+                queue = resumed.recv() => {
+                    // Lagged means some queue was resumed, possibly this one
+                    if matches!(&queue, Ok(q) if *q != self.queue_name) {
+                        continue;
+                    }
+                    let matched = self
+                        .matching
+                        .match_buffered(&self.queue_name, self.partition_id);
+                    debug!(
+                        queue = %self.queue_name,
+                        partition = self.partition_id.0,
+                        matched,
+                        "Queue resumed"
+                    );
+                    // Poll right away
+                    current_interval = Duration::ZERO;
+                }
+                Ok(()) = watched_changed(&mut config_updates) => {
+                    let Some(rx) = config_updates.as_mut() else {
+                        continue;
+                    };
+                    PollSettings {
+                        busy_interval,
+                        idle_interval,
+                        base_batch,
+                        max_batch,
+                    } = PollSettings::from_config(&rx.borrow_and_update());
+                    batch = batch.clamp(base_batch, max_batch);
+                    current_interval = current_interval.min(idle_interval);
+                    debug!(
+                        queue = %self.queue_name,
+                        partition = self.partition_id.0,
+                        "TaskReader poll settings updated"
+                    );
+                }
+                Ok(()) = watched_changed(&mut standby) => {
+                    if standby.as_ref().is_some_and(|rx| !*rx.borrow()) {
+                        info!(
+                            queue = %self.queue_name,
+                            partition = self.partition_id.0,
+                            "TaskReader activated"
+                        );
+                        current_interval = Duration::ZERO;
+                    }
+                }
                 _ = sleep(current_interval) => {
-                    match self.poll_and_dispatch().await {
-                        Ok(count) if count > 0 => {
-                            debug!(
-                                queue = %self.queue_name,
-                                partition = self.partition_id.0,
-                                count,
-                                "TaskReader dispatched tasks"
-                            );
-                            current_interval = busy_interval; // Tasks found, poll fast
-                        }
-                        Ok(_) => {
-                            current_interval = idle_interval; // No tasks, slow down
+                    if self.matching.paused().is_paused(&self.queue_name)
+                        || standby.as_ref().is_some_and(|rx| *rx.borrow())
+                    {
+                        current_interval = idle_interval;
+                        continue;
+                    }
+                    // Only dequeue what the buffer can hold if no worker is waiting
+                    let room = self
+                        .matching
+                        .free_buffer_capacity(&self.queue_name, self.partition_id);
+                    if room == 0 {
+                        current_interval = back_off(current_interval, busy_interval, idle_interval);
+                        continue;
+                    }
+                    let limits = self.matching.rate_limits();
+                    let granted = limits.acquire(&self.queue_name, batch.min(room as i64));
+                    if granted == 0 {
+                        // Over the queue's dispatch rate until a token refills
+                        current_interval = limits.until_available(&self.queue_name);
+                        continue;
+                    }
+                    match self.poll_and_dispatch(granted).await {
+                        Ok(dispatched) => {
+                            limits.release(&self.queue_name, granted - dispatched as i64);
+                            if dispatched > 0 {
+                                debug!(
+                                    queue = %self.queue_name,
+                                    partition = self.partition_id.0,
+                                    count = dispatched,
+                                    "TaskReader dispatched tasks"
+                                );
+                            }
+                            if dispatched as i64 == granted {
+                                // A full batch: more are likely waiting, so go
+                                // again right away, with a bigger batch unless
+                                // the rate limit cut this one short
+                                if granted == batch {
+                                    batch = (batch * 2).min(max_batch);
+                                }
+                                current_interval = Duration::ZERO;
+                            } else if dispatched > 0 {
+                                batch = (batch / 2).max(base_batch);
+                                current_interval = busy_interval;
+                            } else {
+                                // Nothing to do: back off towards the idle interval
+                                batch = base_batch;
+                                current_interval =
+                                    back_off(current_interval, busy_interval, idle_interval);
+                            }
                         }
                         Err(e) => {
+                            limits.release(&self.queue_name, granted);
                             error!(
                                 queue = %self.queue_name,
                                 partition = self.partition_id.0,
@@ -87,30 +242,69 @@ impl TaskReader {
         }
     }
 
-    async fn poll_and_dispatch(&self) -> Result<usize, sqlx::Error> {
-        let tasks = valka_db::queries::tasks::dequeue_tasks(
-            &self.pool,
-            &self.queue_name,
-            self.partition_id.0,
-            self.config.task_reader_batch_size,
-        )
-        .await?;
+    /// Dequeue up to `batch` tasks and hand them to matching. Returns how
+    /// many were matched or buffered; expired tasks and any the full buffer
+    /// turned away don't count.
+    async fn poll_and_dispatch(&self, batch: i64) -> Result<usize, sqlx::Error> {
+        fault_point!("task_reader.poll", &self.queue_name)?;
+        let fifo = self.matching.fifo();
+        let (queue, partition) = (self.queue_name.as_str(), self.partition_id.0);
+        let tasks = if fifo.is_fifo(queue) {
+            valka_db::queries::tasks::dequeue_fifo_tasks(&self.pool, queue, partition, batch)
+                .await?
+        } else {
+            valka_db::queries::tasks::dequeue_tasks(&self.pool, queue, partition, batch).await?
+        };
 
-        let count = tasks.len();
+        let mut dispatched = tasks.len();
+        let now = chrono::Utc::now();
 
+        let decisions = self.matching.decisions();
         for task_row in tasks {
+            decisions.record(
+                queue,
+                self.partition_id,
+                &task_row.id,
+                DecisionOutcome::Dequeued,
+            );
+            if task_row.deadline_expired(now) {
+                decisions.record(
+                    queue,
+                    self.partition_id,
+                    &task_row.id,
+                    DecisionOutcome::Expired,
+                );
+                self.expire(&task_row.id).await;
+                dispatched -= 1;
+                continue;
+            }
+
             let envelope = TaskEnvelope {
                 task_id: task_row.id.clone(),
                 task_run_id: String::new(), // Will be assigned by dispatcher
                 queue_name: task_row.queue_name.clone(),
                 task_name: task_row.task_name.clone(),
-                input: task_row.input.map(|v| v.to_string()),
+                input: task_row.input.as_ref().map(|v| v.to_string()),
                 attempt_number: task_row.attempt_count + 1,
                 timeout_seconds: task_row.timeout_seconds,
                 metadata: task_row.metadata.to_string(),
                 priority: task_row.priority,
+                deadline_ms: task_row.deadline_at.map(|d| d.timestamp_millis()),
+                ready_at_ms: Some(task_row.ready_at().timestamp_millis()),
+                created_at_ms: Some(task_row.created_at.timestamp_millis()),
+                scheduled_at_ms: task_row.scheduled_at.map(|at| at.timestamp_millis()),
+                traceparent: task_row.traceparent.clone(),
+                fifo_key: task_row.fifo_key.clone(),
+                required_labels: task_row.required_labels(),
+                ephemeral: None,
             };
 
+            // PG only hands out a task whose key is free, so any hold left
+            // here for that key is stale
+            if let Some(key) = fifo.key_for(&self.queue_name, envelope.fifo_key.as_deref()) {
+                fifo.hold(&self.queue_name, key, &envelope.task_id);
+            }
+
             // Try sync match first
             match self
                 .matching
@@ -121,37 +315,68 @@ impl TaskReader {
                 }
                 Err(envelope) => {
                     // No worker available, buffer it
-                    if !self
-                        .matching
-                        .buffer_task(&self.queue_name, self.partition_id, envelope)
-                    {
-                        // Buffer full — reset task back to PENDING so it can be
-                        // picked up on the next poll instead of being stuck in
-                        // DISPATCHING indefinitely.
+                    let outcome =
+                        self.matching
+                            .buffer_task(&self.queue_name, self.partition_id, envelope);
+                    if let Some(unbuffered) = outcome.into_unbuffered() {
+                        // Buffer full — reset the task it turned away back to
+                        // PENDING so it can be picked up on a later poll
+                        // instead of being stuck in DISPATCHING indefinitely.
                         warn!(
                             queue = %self.queue_name,
                             partition = self.partition_id.0,
-                            task_id = %task_row.id,
+                            task_id = %unbuffered.task_id,
                             "Buffer full, resetting task to PENDING"
                         );
-                        if let Err(e) = valka_db::queries::tasks::update_task_status(
-                            &self.pool,
-                            &task_row.id,
-                            "PENDING",
-                        )
-                        .await
-                        {
-                            error!(
-                                task_id = %task_row.id,
-                                error = %e,
-                                "Failed to reset DISPATCHING task to PENDING"
-                            );
-                        }
+                        self.reset_to_pending(unbuffered).await;
+                        dispatched -= 1;
                     }
                 }
             }
         }
 
-        Ok(count)
+        Ok(dispatched)
+    }
+
+    /// Put a task the buffer turned away back to PENDING. An ephemeral one
+    /// has no row yet, so it is written as PENDING instead.
+    async fn reset_to_pending(&self, task: TaskEnvelope) {
+        self.matching.fifo().release(&task.task_id);
+        let result = match task.ephemeral {
+            Some(params) => valka_db::queries::tasks::create_task(&self.pool, *params)
+                .await
+                .map(|_| ()),
+            None => {
+                valka_db::queries::tasks::update_task_status(&self.pool, &task.task_id, "PENDING")
+                    .await
+                    .map(|_| ())
+            }
+        };
+        if let Err(e) = result {
+            error!(
+                task_id = %task.task_id,
+                error = %e,
+                "Failed to reset DISPATCHING task to PENDING"
+            );
+        }
+    }
+
+    async fn expire(&self, task_id: &str) {
+        match valka_db::queries::tasks::expire_task(
+            &self.pool,
+            task_id,
+            valka_core::DEADLINE_EXCEEDED_BEFORE_DISPATCH,
+        )
+        .await
+        {
+            Ok(Some(_)) => {
+                debug!(task_id, "Task deadline passed before dispatch");
+                valka_core::metrics::record_task_failed(&self.queue_name);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!(task_id, error = %e, "Failed to expire task");
+            }
+        }
     }
 }
@@ -8,3 +8,16 @@ pub mod valka {
 }
 
 pub use valka::v1::*;
+
+/// `task_run_id` prefix marking a log entry as worker-level rather than task-scoped.
+/// Full form is `worker:<worker_id>`.
+pub const WORKER_LOG_RUN_PREFIX: &str = "worker:";
+
+/// Worker stream protocol version this build speaks. A `WorkerHello` without
+/// one (0) comes from a worker that predates versioning and gets no `HelloAck`.
+pub const PROTOCOL_VERSION: i32 = 1;
+
+/// Capability: the worker handles `TaskSignal` pushes.
+pub const CAPABILITY_SIGNALS: &str = "signals";
+/// Capability: the worker reports `TaskProgress`.
+pub const CAPABILITY_PROGRESS: &str = "progress";
@@ -0,0 +1,109 @@
+//! Recurring tasks: create a task each time a schedule's cron expression fires.
+//!
+//! Expressions are evaluated in UTC. A firing is turned into a task and the
+//! schedule advanced past it in one transaction, so a new leader never repeats
+//! it. Firings missed while no leader was running collapse into one task on
+//! recovery; the schedule then resumes from the next firing after now.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use tracing::{error, info};
+use valka_core::{Durability, TaskId, partition_for_task};
+use valka_db::queries::schedules::{self, ScheduleRow};
+use valka_db::queries::tasks::{CreateTaskParams, TaskRow};
+
+/// Most schedules fired per tick; the rest wait for the next one.
+const FIRE_BATCH_SIZE: i64 = 100;
+
+/// Parse a cron expression: standard five fields (`min hour dom month dow`),
+/// or six/seven with leading seconds and trailing years.
+pub fn parse(expression: &str) -> Result<::cron::Schedule, String> {
+    let expression = expression.trim();
+    let full = match expression.split_whitespace().count() {
+        5 => format!("0 {expression}"),
+        6 | 7 => expression.to_string(),
+        _ => {
+            return Err(format!(
+                "Invalid cron expression {expression:?}: expected 5 to 7 fields"
+            ));
+        }
+    };
+    ::cron::Schedule::from_str(&full)
+        .map_err(|e| format!("Invalid cron expression {expression:?}: {e}"))
+}
+
+/// First firing strictly after `after`, or `None` once the expression is exhausted.
+pub fn next_fire_after(schedule: &::cron::Schedule, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    schedule.after(&after).next()
+}
+
+/// Check a new schedule's expression and return its first firing after `now`.
+pub fn first_fire(expression: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let schedule = parse(expression)?;
+    next_fire_after(&schedule, now)
+        .ok_or_else(|| format!("Cron expression {expression:?} never fires again"))
+}
+
+/// Create a task for every schedule due at `now`. Returns the created tasks.
+pub async fn fire_due_schedules(
+    pool: &sqlx::PgPool,
+    num_partitions: i32,
+    now: DateTime<Utc>,
+) -> Result<Vec<TaskRow>, sqlx::Error> {
+    let due = schedules::list_due_schedules(pool, now, FIRE_BATCH_SIZE).await?;
+    let mut created = Vec::with_capacity(due.len());
+    for schedule in &due {
+        let parsed = match parse(&schedule.cron_expression) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                error!(schedule_id = %schedule.id, error = %e, "Skipping schedule");
+                continue;
+            }
+        };
+        // Anything missed up to now is covered by this one firing. An exhausted
+        // expression parks the schedule where it never comes due again.
+        let next = next_fire_after(&parsed, now.max(schedule.next_fire_at))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let task = task_params(schedule, num_partitions);
+        if let Some(row) = schedules::fire_schedule(pool, schedule, &task, next).await? {
+            valka_core::metrics::record_task_created(&row.queue_name);
+            info!(
+                schedule_id = %schedule.id,
+                task_id = %row.id,
+                fire_time = %schedule.next_fire_at,
+                "Schedule fired"
+            );
+            created.push(row);
+        }
+    }
+    Ok(created)
+}
+
+fn task_params(schedule: &ScheduleRow, num_partitions: i32) -> CreateTaskParams {
+    let id = TaskId::new().0;
+    let partition = partition_for_task(&schedule.queue_name, &id, num_partitions);
+    CreateTaskParams {
+        id,
+        queue_name: schedule.queue_name.clone(),
+        task_name: schedule.task_name.clone(),
+        partition_id: partition.0,
+        input: schedule.input.clone(),
+        priority: schedule.priority,
+        max_retries: schedule.max_retries,
+        timeout_seconds: schedule.timeout_seconds,
+        idempotency_key: None,
+        metadata: serde_json::json!({
+            "schedule_id": schedule.id,
+            "fire_time": schedule.next_fire_at.to_rfc3339(),
+        }),
+        scheduled_at: None,
+        deadline_at: None,
+        traceparent: None,
+        durability: Durability::Durable,
+        fifo_key: None,
+        callback_url: None,
+        retry_backoff: Default::default(),
+        required_labels: Default::default(),
+    }
+}
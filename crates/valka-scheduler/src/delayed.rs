@@ -1,15 +1,66 @@
 use sqlx::PgPool;
 use tracing::info;
-use valka_db::queries::tasks;
+use valka_db::queries::{dependencies, queue_configs, tasks};
 
-/// Promote delayed/retry tasks whose scheduled_at has passed back to PENDING
+/// Promote delayed/retry tasks whose scheduled_at has passed back to PENDING.
+/// Waiting tasks whose deadline has already passed are failed instead.
+/// Queues with a scheduled hold are skipped; a release ramp caps each tick.
 pub async fn promote_delayed_tasks(pool: &PgPool) -> Result<usize, sqlx::Error> {
-    let promoted = tasks::promote_delayed_tasks(pool).await?;
-    let count = promoted.len();
+    Ok(promote_due_tasks(pool).await?.len())
+}
+
+/// Like [`promote_delayed_tasks`], returning the promoted tasks so they can
+/// be offered to matching without waiting for a TaskReader poll.
+pub async fn promote_due_tasks(pool: &PgPool) -> Result<Vec<tasks::TaskRow>, sqlx::Error> {
+    let expired = tasks::expire_past_deadline_tasks(pool).await?;
+    for task in &expired {
+        valka_core::metrics::record_task_failed(&task.queue_name);
+    }
+    if !expired.is_empty() {
+        info!(count = expired.len(), "Expired tasks past their deadline");
+    }
+
+    // Held queues are skipped entirely; ramping queues release a capped batch
+    let mut promoted = tasks::promote_delayed_tasks(pool).await?;
+    for (queue_name, max_per_tick) in queue_configs::list_release_ramps(pool).await? {
+        let released =
+            tasks::promote_scheduled_batch(pool, &queue_name, max_per_tick as i64).await?;
+        let count = released.len();
+        promoted.extend(released);
+        if count < max_per_tick as usize
+            && queue_configs::end_release_ramp(pool, &queue_name, max_per_tick).await?
+        {
+            info!(queue = %queue_name, "Scheduled release ramp finished");
+        }
+    }
+
+    if !promoted.is_empty() {
+        info!(count = promoted.len(), "Promoted delayed tasks to PENDING");
+    }
+
+    Ok(promoted)
+}
+
+/// Release WAITING tasks whose dependencies have all completed and cancel
+/// those with a dependency that ended otherwise. Cancellations cascade down
+/// chains of dependents, a bounded number of levels per tick.
+pub async fn resolve_waiting_tasks(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    const MAX_CASCADE_DEPTH: usize = 10;
+
+    let mut released = 0;
+    let mut cancelled = 0;
+    for _ in 0..MAX_CASCADE_DEPTH {
+        let resolved = dependencies::resolve_waiting_tasks(pool, None).await?;
+        released += resolved.released.len();
+        cancelled += resolved.cancelled.len();
+        if resolved.cancelled.is_empty() {
+            break;
+        }
+    }
 
-    if count > 0 {
-        info!(count, "Promoted delayed tasks to PENDING");
+    if released > 0 || cancelled > 0 {
+        info!(released, cancelled, "Resolved waiting tasks");
     }
 
-    Ok(count)
+    Ok(released + cancelled)
 }
@@ -1,28 +1,52 @@
 use sqlx::PgPool;
+use tokio::sync::broadcast;
 use tracing::{error, info};
 use uuid::Uuid;
-use valka_db::queries::{dead_letter, tasks};
+use valka_db::queries::{dead_letter, task_runs, tasks};
+use valka_proto::{TaskEvent, TaskStatus};
 
 /// Find tasks that have exceeded max_retries and move them to dead letter queue
-pub async fn process_dead_letters(pool: &PgPool) -> Result<usize, sqlx::Error> {
+pub async fn process_dead_letters(
+    pool: &PgPool,
+    events: &broadcast::Sender<TaskEvent>,
+) -> Result<usize, sqlx::Error> {
+    Ok(dead_letter_tasks(pool, events).await?.len())
+}
+
+/// Like [`process_dead_letters`], returning the tasks that were moved so
+/// their callbacks can be notified.
+///
+/// Tasks of queues with `dead_letter_enabled` unset stay FAILED. The dead
+/// letter row takes the error and worker of the task's last run, and each move
+/// is announced on `events`.
+pub async fn dead_letter_tasks(
+    pool: &PgPool,
+    events: &broadcast::Sender<TaskEvent>,
+) -> Result<Vec<tasks::TaskRow>, sqlx::Error> {
     let rows = sqlx::query_as::<_, tasks::TaskRow>(
         r#"
-        SELECT * FROM tasks
-        WHERE status = 'FAILED' AND attempt_count >= max_retries
+        SELECT t.* FROM tasks t
+        LEFT JOIN queue_configs qc ON qc.queue_name = t.queue_name
+        WHERE t.status = 'FAILED' AND t.attempt_count >= t.max_retries
+          AND COALESCE(qc.dead_letter_enabled, TRUE)
         LIMIT 100
         "#,
     )
     .fetch_all(pool)
     .await?;
 
-    let count = rows.len();
+    let mut moved = Vec::with_capacity(rows.len());
 
     for task in rows {
         let dlq_id = Uuid::now_v7().to_string();
 
-        // Get the last error message from task_runs
-        let runs = valka_db::queries::task_runs::get_runs_for_task(pool, &task.id).await?;
-        let error_message = runs.first().and_then(|r| r.error_message.as_deref());
+        // The last run says why and where the final attempt failed
+        let runs = task_runs::get_runs_for_task(pool, &task.id).await?;
+        let last_run = runs.first();
+        let error_message = last_run
+            .and_then(|r| r.error_message.as_deref())
+            .or(task.error_message.as_deref());
+        let worker_id = last_run.map(|r| r.worker_id.as_str());
 
         match dead_letter::insert_dead_letter(
             pool,
@@ -32,17 +56,34 @@ pub async fn process_dead_letters(pool: &PgPool) -> Result<usize, sqlx::Error> {
             &task.task_name,
             task.input.as_ref(),
             error_message,
+            worker_id,
             task.attempt_count,
             &task.metadata,
         )
         .await
         {
             Ok(_) => {
-                if let Err(e) = tasks::move_to_dead_letter(pool, &task.id).await {
-                    error!(task_id = %task.id, error = %e, "Failed to update task status to DEAD_LETTER");
+                match tasks::move_to_dead_letter(pool, &task.id).await {
+                    Ok(row) => moved.extend(row),
+                    Err(e) => {
+                        error!(task_id = %task.id, error = %e, "Failed to update task status to DEAD_LETTER");
+                    }
                 }
                 info!(task_id = %task.id, "Moved to dead letter queue");
                 valka_core::metrics::record_task_dead_lettered(&task.queue_name);
+                // No subscribers is fine
+                let _ = events.send(TaskEvent {
+                    event_id: Uuid::now_v7().to_string(),
+                    task_id: task.id.clone(),
+                    queue_name: task.queue_name.clone(),
+                    previous_status: TaskStatus::Failed as i32,
+                    new_status: TaskStatus::DeadLetter as i32,
+                    worker_id: worker_id.unwrap_or_default().to_string(),
+                    attempt_number: task.attempt_count,
+                    error_message: error_message.unwrap_or_default().to_string(),
+                    timestamp_ms: chrono::Utc::now().timestamp_millis(),
+                    ..Default::default()
+                });
             }
             Err(e) => {
                 error!(task_id = %task.id, error = %e, "Failed to insert into DLQ");
@@ -50,5 +91,5 @@ pub async fn process_dead_letters(pool: &PgPool) -> Result<usize, sqlx::Error> {
         }
     }
 
-    Ok(count)
+    Ok(moved)
 }
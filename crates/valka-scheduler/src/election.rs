@@ -1,5 +1,4 @@
 use sqlx::PgPool;
-use tracing::info;
 
 const ADVISORY_LOCK_ID: i64 = 0x56414C4B41; // "VALKA" in hex
 
@@ -25,9 +24,6 @@ impl SchedulerElection {
             .await?;
 
         self.is_leader = row.0;
-        if self.is_leader {
-            info!("Acquired scheduler leadership");
-        }
         Ok(self.is_leader)
     }
 
@@ -39,7 +35,6 @@ impl SchedulerElection {
                 .execute(&self.pool)
                 .await?;
             self.is_leader = false;
-            info!("Released scheduler leadership");
         }
         Ok(())
     }
@@ -1,7 +1,10 @@
+pub mod cron;
 pub mod delayed;
 pub mod dlq;
 pub mod election;
 pub mod reaper;
+pub mod retention;
 pub mod retry;
+pub mod usage;
 
 pub use election::SchedulerElection;
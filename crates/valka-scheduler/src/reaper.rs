@@ -1,17 +1,36 @@
 use sqlx::PgPool;
+use tokio::sync::broadcast;
 use tracing::{error, info, warn};
-use valka_db::queries::{dead_letter, task_runs, tasks};
+use valka_db::queries::task_runs::TaskRunRow;
+use valka_db::queries::{dead_letter, queue_configs, task_runs, tasks};
+use valka_proto::{TaskEvent, TaskStatus};
 
 /// Scan for expired leases and handle them:
 /// - If task can retry: set status to RETRY
-/// - If max retries exceeded: move to DLQ
-pub async fn reap_expired_leases(pool: &PgPool) -> Result<usize, sqlx::Error> {
+/// - If max retries exceeded: move to DLQ, or set FAILED where the queue disabled it
+///
+/// Each reclaimed task is announced on `events`.
+pub async fn reap_expired_leases(
+    pool: &PgPool,
+    events: &broadcast::Sender<TaskEvent>,
+) -> Result<usize, sqlx::Error> {
+    Ok(reap_expired_runs(pool, events).await?.len())
+}
+
+/// Like [`reap_expired_leases`], returning the runs that were failed so their
+/// workers' slots can be released.
+pub async fn reap_expired_runs(
+    pool: &PgPool,
+    events: &broadcast::Sender<TaskEvent>,
+) -> Result<Vec<TaskRunRow>, sqlx::Error> {
     let expired = task_runs::find_expired_leases(pool).await?;
     let count = expired.len();
+    let mut reaped = Vec::with_capacity(count);
 
     for run in expired {
-        // Fail the run
-        if let Err(e) = task_runs::fail_task_run(pool, &run.id, "Lease expired").await {
+        // Fail the run, saying why for the task's run history
+        let error_message = format!("Lease expired on worker {}", run.worker_id);
+        if let Err(e) = task_runs::fail_task_run(pool, &run.id, &error_message).await {
             error!(run_id = %run.id, error = %e, "Failed to fail expired run");
             continue;
         }
@@ -19,20 +38,31 @@ pub async fn reap_expired_leases(pool: &PgPool) -> Result<usize, sqlx::Error> {
         // Check if the task can retry
         let task = tasks::get_task(pool, &run.task_id).await?;
         if let Some(task) = task {
-            if task.attempt_count < task.max_retries {
+            let new_status = if task.attempt_count < task.max_retries {
                 // Schedule retry
                 if let Err(e) = tasks::update_task_status(pool, &task.id, "RETRY").await {
                     error!(task_id = %task.id, error = %e, "Failed to set task to RETRY");
                 }
-                info!(task_id = %task.id, "Expired lease - scheduling retry");
+                info!(
+                    task_id = %task.id,
+                    worker_id = %run.worker_id,
+                    "Expired lease - scheduling retry"
+                );
+                TaskStatus::Retry
+            } else if !queue_configs::dead_letter_enabled(pool, &task.queue_name).await? {
+                // The queue opted out of the DLQ
+                if let Err(e) = tasks::update_task_status(pool, &task.id, "FAILED").await {
+                    error!(task_id = %task.id, error = %e, "Failed to set task to FAILED");
+                }
+                warn!(
+                    task_id = %task.id,
+                    worker_id = %run.worker_id,
+                    "Expired lease - failed (max retries exceeded, DLQ disabled)"
+                );
+                TaskStatus::Failed
             } else {
                 // Move to dead letter — insert DLQ entry first, then update status
                 let dlq_id = uuid::Uuid::now_v7().to_string();
-                let runs = task_runs::get_runs_for_task(pool, &task.id)
-                    .await
-                    .unwrap_or_default();
-                let error_message = runs.first().and_then(|r| r.error_message.as_deref());
-
                 if let Err(e) = dead_letter::insert_dead_letter(
                     pool,
                     &dlq_id,
@@ -40,7 +70,8 @@ pub async fn reap_expired_leases(pool: &PgPool) -> Result<usize, sqlx::Error> {
                     &task.queue_name,
                     &task.task_name,
                     task.input.as_ref(),
-                    error_message,
+                    Some(&error_message),
+                    Some(&run.worker_id),
                     task.attempt_count,
                     &task.metadata,
                 )
@@ -53,14 +84,34 @@ pub async fn reap_expired_leases(pool: &PgPool) -> Result<usize, sqlx::Error> {
                     error!(task_id = %task.id, error = %e, "Failed to move task to DLQ");
                 }
                 valka_core::metrics::record_task_dead_lettered(&task.queue_name);
-                warn!(task_id = %task.id, "Expired lease - moved to DLQ (max retries exceeded)");
-            }
+                warn!(
+                    task_id = %task.id,
+                    worker_id = %run.worker_id,
+                    "Expired lease - moved to DLQ (max retries exceeded)"
+                );
+                TaskStatus::DeadLetter
+            };
+            valka_core::metrics::record_lease_expired(&task.queue_name);
+            // No subscribers is fine
+            let _ = events.send(TaskEvent {
+                event_id: uuid::Uuid::now_v7().to_string(),
+                task_id: task.id.clone(),
+                queue_name: task.queue_name.clone(),
+                previous_status: TaskStatus::Running as i32,
+                new_status: new_status as i32,
+                worker_id: run.worker_id.clone(),
+                attempt_number: run.attempt_number,
+                error_message,
+                timestamp_ms: chrono::Utc::now().timestamp_millis(),
+                ..Default::default()
+            });
         }
+        reaped.push(run);
     }
 
     if count > 0 {
         info!(count, "Reaped expired leases");
     }
 
-    Ok(count)
+    Ok(reaped)
 }
@@ -0,0 +1,107 @@
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use tracing::info;
+use valka_core::RetentionConfig;
+use valka_core::metrics::record_retention_deleted;
+use valka_db::queries::retention::{self, DeletedTasks};
+
+const COMPLETED_STATUSES: &[&str] = &["COMPLETED"];
+const FAILED_STATUSES: &[&str] = &["FAILED", "DEAD_LETTER", "CANCELLED"];
+
+/// Rows removed by one [`cleanup`] pass.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionStats {
+    pub tasks: u64,
+    pub task_runs: u64,
+    pub task_logs: u64,
+    pub worker_logs: u64,
+    pub dead_letters: u64,
+}
+
+impl RetentionStats {
+    fn add_tasks(&mut self, batch: DeletedTasks) {
+        record_retention_deleted("tasks", batch.tasks);
+        record_retention_deleted("task_runs", batch.task_runs);
+        record_retention_deleted("task_logs", batch.task_logs);
+        record_retention_deleted("dead_letter_queue", batch.dead_letters);
+        self.tasks += batch.tasks;
+        self.task_runs += batch.task_runs;
+        self.task_logs += batch.task_logs;
+        self.dead_letters += batch.dead_letters;
+    }
+}
+
+/// Batches [`cleanup`] deletes per kind of row in one run. A backlog larger
+/// than this is worked off over the following runs rather than holding up
+/// the scheduler's other jobs.
+pub const MAX_BATCHES_PER_RUN: usize = 10;
+
+/// Delete tasks and logs that are past their TTL, `config.batch_size` rows at
+/// a time and at most [`MAX_BATCHES_PER_RUN`] batches of each kind. Tasks on
+/// queues with `queue_configs.retention_days` follow that instead of the
+/// configured TTLs. TTLs of 0 are skipped.
+pub async fn cleanup(
+    pool: &PgPool,
+    config: &RetentionConfig,
+) -> Result<RetentionStats, sqlx::Error> {
+    let mut stats = RetentionStats::default();
+    let limit = config.batch_size.max(1) as i64;
+
+    for (ttl_days, statuses) in [
+        (config.completed_task_ttl_days, COMPLETED_STATUSES),
+        (config.failed_task_ttl_days, FAILED_STATUSES),
+    ] {
+        if ttl_days == 0 {
+            continue;
+        }
+        let before = Utc::now() - Duration::days(ttl_days as i64);
+        for _ in 0..MAX_BATCHES_PER_RUN {
+            let batch = retention::delete_expired_tasks(pool, statuses, before, limit).await?;
+            stats.add_tasks(batch);
+            if (batch.tasks as i64) < limit {
+                break;
+            }
+        }
+    }
+
+    let finished = [COMPLETED_STATUSES, FAILED_STATUSES].concat();
+    for _ in 0..MAX_BATCHES_PER_RUN {
+        let batch = retention::delete_expired_queue_tasks(pool, &finished, limit).await?;
+        stats.add_tasks(batch);
+        if (batch.tasks as i64) < limit {
+            break;
+        }
+    }
+
+    if config.log_ttl_days > 0 {
+        let before = Utc::now() - Duration::days(config.log_ttl_days as i64);
+        for _ in 0..MAX_BATCHES_PER_RUN {
+            let deleted = retention::delete_expired_task_logs(pool, before, limit).await?;
+            record_retention_deleted("task_logs", deleted);
+            stats.task_logs += deleted;
+            if (deleted as i64) < limit {
+                break;
+            }
+        }
+        for _ in 0..MAX_BATCHES_PER_RUN {
+            let deleted = retention::delete_expired_worker_logs(pool, before, limit).await?;
+            record_retention_deleted("worker_logs", deleted);
+            stats.worker_logs += deleted;
+            if (deleted as i64) < limit {
+                break;
+            }
+        }
+    }
+
+    if stats != RetentionStats::default() {
+        info!(
+            tasks = stats.tasks,
+            task_runs = stats.task_runs,
+            task_logs = stats.task_logs,
+            worker_logs = stats.worker_logs,
+            dead_letters = stats.dead_letters,
+            "Retention cleanup deleted expired rows"
+        );
+    }
+    Ok(stats)
+}
@@ -1,6 +1,7 @@
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use sqlx::PgPool;
 use tracing::{error, info};
+use valka_core::retry::RetryPolicy;
 use valka_db::queries::tasks;
 
 /// Compute exponential backoff delay for a retry attempt
@@ -9,12 +10,16 @@ pub fn compute_retry_delay(
     base_delay_secs: u64,
     max_delay_secs: u64,
 ) -> Duration {
-    let delay_secs = base_delay_secs.saturating_mul(2u64.saturating_pow(attempt_count as u32));
-    let capped = delay_secs.min(max_delay_secs);
-    Duration::seconds(capped as i64)
+    RetryPolicy {
+        base_delay_secs,
+        max_delay_secs,
+        ..Default::default()
+    }
+    .delay(attempt_count)
 }
 
-/// Process tasks in RETRY status: compute next attempt time and set scheduled_at
+/// Process tasks in RETRY status: compute next attempt time and set scheduled_at.
+/// Tasks created with their own backoff use it over the given defaults.
 pub async fn process_retries(
     pool: &PgPool,
     base_delay_secs: u64,
@@ -32,10 +37,39 @@ pub async fn process_retries(
     .await?;
 
     let count = rows.len();
+    let policy = RetryPolicy {
+        base_delay_secs,
+        max_delay_secs,
+        ..Default::default()
+    };
 
     for task in rows {
-        let delay = compute_retry_delay(task.attempt_count, base_delay_secs, max_delay_secs);
-        let scheduled_at = Utc::now() + delay;
+        // A delay the handler asked for replaces backoff for this attempt,
+        // up to the longest backoff
+        let delay = match task.retry_after_ms {
+            Some(ms) => Duration::milliseconds(ms.clamp(0, policy.max_delay_ms())),
+            None => policy
+                .with_backoff(&task.retry_backoff())
+                .jittered_delay(task.attempt_count),
+        };
+        let scheduled_at = Utc::now()
+            .checked_add_signed(delay)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+
+        // The next attempt would start after the deadline, so give up now
+        if task.deadline_at.is_some_and(|d| scheduled_at >= d) {
+            match tasks::expire_task(pool, &task.id, valka_core::DEADLINE_EXCEEDED_BEFORE_RETRY)
+                .await
+            {
+                Ok(Some(_)) => {
+                    info!(task_id = %task.id, "Deadline exceeded before retry");
+                    valka_core::metrics::record_task_failed(&task.queue_name);
+                }
+                Ok(None) => {}
+                Err(e) => error!(task_id = %task.id, error = %e, "Failed to expire task"),
+            }
+            continue;
+        }
 
         if let Err(e) = tasks::schedule_retry(pool, &task.id, scheduled_at).await {
             error!(task_id = %task.id, error = %e, "Failed to schedule retry");
@@ -0,0 +1,19 @@
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use tracing::debug;
+use valka_db::queries::usage;
+
+/// Fold task activity older than `lag` into the daily usage table. Only the
+/// window since the stored high-water mark is scanned.
+pub async fn rollup_usage(pool: &PgPool, lag: Duration) -> Result<u64, sqlx::Error> {
+    let rollup = usage::rollup_usage(pool, Utc::now() - lag).await?;
+    if rollup.rows_upserted > 0 {
+        debug!(
+            rows = rollup.rows_upserted,
+            from = %rollup.from,
+            to = %rollup.to,
+            "Rolled up usage"
+        );
+    }
+    Ok(rollup.rows_upserted)
+}
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// Sends `authorization: Bearer <token>` with every request when set, for
+/// servers with API tokens configured. A request that already carries an
+/// `authorization` header (such as an admin token) keeps it.
+#[derive(Clone, Default)]
+pub struct BearerToken(Option<Arc<str>>);
+
+impl BearerToken {
+    /// An empty token sends nothing.
+    pub fn new(token: &str) -> Self {
+        Self((!token.is_empty()).then(|| Arc::from(format!("Bearer {token}"))))
+    }
+}
+
+impl Interceptor for BearerToken {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(value) = &self.0
+            && !request.metadata().contains_key("authorization")
+        {
+            let value = value
+                .parse()
+                .map_err(|_| Status::invalid_argument("API token is not valid header text"))?;
+            request.metadata_mut().insert("authorization", value);
+        }
+        Ok(request)
+    }
+}
@@ -0,0 +1,189 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, mpsc};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, info_span};
+
+use valka_proto::{TaskAssignment, TaskSignal, WorkerRequest};
+
+use crate::context::TaskContext;
+use crate::handlers::TaskError;
+use crate::limits::ResultLimits;
+use crate::telemetry;
+use crate::worker::{ActiveTasks, SignalSenders, cancelled_result, execution_timeout, task_result};
+
+/// Handler for [`batch_handler`](crate::worker::ValkaWorkerBuilder::batch_handler):
+/// gets a batch of tasks and returns one result per task, in the same order.
+pub type BatchHandler = Arc<
+    dyn Fn(
+            Vec<TaskContext>,
+        )
+            -> Pin<Box<dyn Future<Output = Vec<Result<serde_json::Value, TaskError>>> + Send>>
+        + Send
+        + Sync,
+>;
+
+#[derive(Clone)]
+pub(crate) struct BatchConfig {
+    pub handler: BatchHandler,
+    pub max_batch: usize,
+    pub max_wait: Duration,
+}
+
+/// An assignment waiting in a batch; holds its concurrency permit.
+pub(crate) struct Batched {
+    assignment: TaskAssignment,
+    signal_rx: mpsc::Receiver<TaskSignal>,
+    cancel: CancellationToken,
+    /// Counted from when the assignment arrived, so time spent waiting for
+    /// the batch to fill comes out of the task's timeout.
+    deadline: Option<Instant>,
+    permit: OwnedSemaphorePermit,
+}
+
+/// Assignments collected until the batch is full or its window closes.
+#[derive(Default)]
+pub(crate) struct PendingBatch {
+    tasks: Vec<Batched>,
+    flush_at: Option<Instant>,
+}
+
+impl PendingBatch {
+    pub fn push(
+        &mut self,
+        assignment: TaskAssignment,
+        signal_rx: mpsc::Receiver<TaskSignal>,
+        cancel: CancellationToken,
+        permit: OwnedSemaphorePermit,
+        max_wait: Duration,
+    ) {
+        let now = Instant::now();
+        let deadline =
+            execution_timeout(assignment.timeout_seconds, assignment.remaining_budget_ms)
+                .map(|limit| now + limit);
+        self.flush_at.get_or_insert(now + max_wait);
+        self.tasks.push(Batched {
+            assignment,
+            signal_rx,
+            cancel,
+            deadline,
+            permit,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// When the batch window closes; `None` while nothing is waiting.
+    pub fn flush_at(&self) -> Option<Instant> {
+        self.flush_at
+    }
+
+    /// Drop a cancelled task before its batch starts, freeing its permit.
+    pub fn remove(&mut self, task_id: &str) {
+        self.tasks.retain(|t| t.assignment.task_id != task_id);
+        if self.tasks.is_empty() {
+            self.flush_at = None;
+        }
+    }
+
+    pub fn take(&mut self) -> Vec<Batched> {
+        self.flush_at = None;
+        std::mem::take(&mut self.tasks)
+    }
+}
+
+/// Run one batch through the handler and report a TaskResult per task. The
+/// batch is bounded by the earliest task deadline; a timeout or a result count
+/// that does not match the batch fails every task in it, retryably.
+pub(crate) async fn run_batch(
+    handler: BatchHandler,
+    batch: Vec<Batched>,
+    tx: mpsc::Sender<WorkerRequest>,
+    active: ActiveTasks,
+    sigs: SignalSenders,
+    limits: ResultLimits,
+) {
+    let size = batch.len();
+    let deadline = batch.iter().filter_map(|t| t.deadline).min();
+    let mut tasks = Vec::with_capacity(size);
+    let mut permits = Vec::with_capacity(size);
+    let mut contexts = Vec::with_capacity(size);
+    for t in batch {
+        let span = telemetry::task_span(&t.assignment);
+        let a = t.assignment;
+        tasks.push((
+            a.task_id.clone(),
+            a.task_run_id.clone(),
+            t.cancel.clone(),
+            span,
+        ));
+        permits.push(t.permit);
+        contexts.push(
+            TaskContext::new(
+                a.task_id,
+                a.task_run_id,
+                a.queue_name,
+                a.task_name,
+                a.attempt_number,
+                a.input,
+                a.metadata,
+                tx.clone(),
+                t.signal_rx,
+            )
+            .with_schedule(a.priority, a.created_at_ms, a.scheduled_at_ms)
+            .with_cancellation(t.cancel),
+        );
+    }
+
+    let logs: Vec<_> = contexts.iter().map(|ctx| ctx.logs().clone()).collect();
+    let started = Instant::now();
+    let run = handler(contexts).instrument(info_span!("handle_batch", size));
+    let results = match deadline {
+        Some(deadline) => match tokio::time::timeout_at(deadline, run).await {
+            Ok(results) => Ok(results),
+            Err(_) => Err(TaskError::retryable(format!(
+                "Batch timed out after {}ms",
+                started.elapsed().as_millis()
+            ))),
+        },
+        None => Ok(run.await),
+    };
+    for logs in &logs {
+        logs.flush().await;
+    }
+    let results = match results {
+        Ok(results) if results.len() == size => results,
+        Ok(results) => vec![
+            Err(TaskError::retryable(format!(
+                "Batch handler returned {} results for {size} tasks",
+                results.len()
+            )));
+            size
+        ],
+        Err(err) => vec![Err(err); size],
+    };
+
+    for ((task_id, task_run_id, cancel, span), result) in tasks.into_iter().zip(results) {
+        let traceparent = telemetry::traceparent(&span);
+        drop(span);
+        let request = if cancel.is_cancelled() {
+            cancelled_result(task_id.clone(), task_run_id)
+        } else {
+            task_result(task_id.clone(), task_run_id, result, traceparent, limits)
+        };
+        let _ = tx.send(request).await;
+        active.lock().await.remove(&task_id);
+        sigs.lock().await.remove(&task_id);
+    }
+    drop(permits);
+}
@@ -1,13 +1,25 @@
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use tonic::service::interceptor::InterceptedService;
 use tonic::transport::Channel;
 use valka_proto::api_service_client::ApiServiceClient;
 use valka_proto::*;
 
-use crate::error::SdkError;
+use crate::auth::BearerToken;
+use crate::error::{SdkError, WaitError};
+use crate::task::{CreateTaskOptions, EventFilter, Signal, Task, TaskFilter, TaskPage};
+
+/// How often a waiting client re-reads the task while following its events.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How often it re-reads the task when it has no event stream.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Client for interacting with the Valka API (task CRUD operations).
 #[derive(Clone)]
 pub struct ValkaClient {
-    inner: ApiServiceClient<Channel>,
+    channel: Channel,
+    inner: ApiServiceClient<InterceptedService<Channel, BearerToken>>,
 }
 
 impl ValkaClient {
@@ -18,84 +30,169 @@ impl ValkaClient {
             .await?;
 
         Ok(Self {
-            inner: ApiServiceClient::new(channel),
+            inner: ApiServiceClient::with_interceptor(channel.clone(), BearerToken::default()),
+            channel,
         })
     }
 
-    pub async fn create_task(
-        &mut self,
-        queue_name: &str,
-        task_name: &str,
-        input: Option<serde_json::Value>,
-    ) -> Result<TaskMeta, SdkError> {
-        let response = self
-            .inner
-            .create_task(CreateTaskRequest {
-                queue_name: queue_name.to_string(),
-                task_name: task_name.to_string(),
-                input: input.map(|v| v.to_string()).unwrap_or_default(),
-                priority: 0,
-                max_retries: 3,
-                timeout_seconds: 300,
-                idempotency_key: String::new(),
-                metadata: String::new(),
-                scheduled_at: String::new(),
-            })
-            .await?;
+    /// Send `token` as a bearer token with every request.
+    pub fn token(mut self, token: &str) -> Self {
+        self.inner =
+            ApiServiceClient::with_interceptor(self.channel.clone(), BearerToken::new(token));
+        self
+    }
 
-        response
-            .into_inner()
-            .task
-            .ok_or_else(|| SdkError::Handler("No task in response".to_string()))
+    pub async fn create_task(&mut self, options: CreateTaskOptions) -> Result<Task, SdkError> {
+        let response = self.inner.create_task(options.into_request()).await?;
+        task_from(response.into_inner().task)
     }
 
-    pub async fn get_task(&mut self, task_id: &str) -> Result<TaskMeta, SdkError> {
+    pub async fn get_task(&mut self, task_id: &str) -> Result<Task, SdkError> {
         let response = self
             .inner
             .get_task(GetTaskRequest {
                 task_id: task_id.to_string(),
             })
             .await?;
+        task_from(response.into_inner().task)
+    }
 
-        response
-            .into_inner()
-            .task
-            .ok_or_else(|| SdkError::Handler("No task in response".to_string()))
+    pub async fn list_tasks(&mut self, filter: TaskFilter) -> Result<TaskPage, SdkError> {
+        let response = self.inner.list_tasks(filter.into_request()).await?;
+        let response = response.into_inner();
+        Ok(TaskPage {
+            tasks: response
+                .tasks
+                .into_iter()
+                .map(Task::try_from)
+                .collect::<Result<_, _>>()?,
+            next_page_token: (!response.next_page_token.is_empty())
+                .then_some(response.next_page_token),
+        })
     }
 
-    pub async fn list_tasks(
-        &mut self,
-        queue_name: Option<&str>,
-        status: Option<i32>,
-        page_size: i32,
-    ) -> Result<Vec<TaskMeta>, SdkError> {
+    pub async fn cancel_task(&mut self, task_id: &str) -> Result<Task, SdkError> {
         let response = self
             .inner
-            .list_tasks(ListTasksRequest {
-                queue_name: queue_name.unwrap_or_default().to_string(),
-                status: status.unwrap_or(0),
-                pagination: Some(Pagination {
-                    page_size,
-                    page_token: String::new(),
-                }),
+            .cancel_task(CancelTaskRequest {
+                task_id: task_id.to_string(),
             })
             .await?;
-
-        Ok(response.into_inner().tasks)
+        task_from(response.into_inner().task)
     }
 
-    pub async fn cancel_task(&mut self, task_id: &str) -> Result<TaskMeta, SdkError> {
+    /// Live task events from the node this client is connected to. The stream
+    /// ends after the first error.
+    ///
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use valka_sdk::ValkaClient;
+    /// use valka_sdk::task::{EventFilter, TaskStatus};
+    ///
+    /// # async fn run(mut client: ValkaClient) -> Result<(), valka_sdk::SdkError> {
+    /// let filter = EventFilter::new()
+    ///     .queue("emails")
+    ///     .status(TaskStatus::Failed);
+    /// let mut events = std::pin::pin!(client.subscribe_events(filter).await?);
+    /// while let Some(event) = events.next().await {
+    ///     let event = event?;
+    ///     println!("{} failed: {}", event.task_id, event.error_message);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn subscribe_events(
+        &mut self,
+        filter: EventFilter,
+    ) -> Result<impl Stream<Item = Result<TaskEvent, SdkError>> + Send + use<>, SdkError> {
         let response = self
             .inner
-            .cancel_task(CancelTaskRequest {
-                task_id: task_id.to_string(),
+            .subscribe_events(SubscribeEventsRequest {
+                queue_name: filter.queue_name.clone(),
             })
             .await?;
+        let mut failed = false;
+        Ok(response.into_inner().filter_map(move |item| {
+            let item = match item {
+                _ if failed => None,
+                Ok(event) => filter.matches(&event).then_some(Ok(event)),
+                Err(status) => {
+                    failed = true;
+                    Some(Err(SdkError::from(status)))
+                }
+            };
+            std::future::ready(item)
+        }))
+    }
 
-        response
-            .into_inner()
-            .task
-            .ok_or_else(|| SdkError::Handler("No task in response".to_string()))
+    /// Wait up to `timeout` for the task to finish, returning it once
+    /// COMPLETED. Follows the task's events, re-reading it every few seconds in
+    /// case one is missed, and polls instead if the event stream fails.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use valka_sdk::ValkaClient;
+    /// use valka_sdk::task::CreateTaskOptions;
+    ///
+    /// # async fn run(mut client: ValkaClient) -> Result<(), Box<dyn std::error::Error>> {
+    /// let task = client
+    ///     .create_task(CreateTaskOptions::new("reports", "render").input(serde_json::json!({ "id": 7 })))
+    ///     .await?;
+    /// let done = client
+    ///     .wait_for_completion(&task.id, Duration::from_secs(60))
+    ///     .await?;
+    /// println!("{:?}", done.output);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_for_completion(
+        &mut self,
+        task_id: &str,
+        timeout: Duration,
+    ) -> Result<Task, WaitError> {
+        let task = tokio::time::timeout(timeout, self.wait_until_terminal(task_id))
+            .await
+            .map_err(|_| WaitError::Timeout(task_id.to_string()))??;
+        match task.status {
+            TaskStatus::Completed => Ok(task),
+            _ => Err(WaitError::Unsuccessful(Box::new(task))),
+        }
+    }
+
+    async fn wait_until_terminal(&mut self, task_id: &str) -> Result<Task, SdkError> {
+        let filter = [
+            TaskStatus::Completed,
+            TaskStatus::Failed,
+            TaskStatus::DeadLetter,
+            TaskStatus::Cancelled,
+        ]
+        .into_iter()
+        .fold(EventFilter::new().task(task_id), EventFilter::status);
+        // Subscribed before the first read so a transition in between is seen
+        let mut events = match self.subscribe_events(filter).await {
+            Ok(events) => Some(Box::pin(events)),
+            Err(e) => {
+                tracing::debug!(error = %e, "Event stream unavailable, polling task");
+                None
+            }
+        };
+        loop {
+            let task = self.get_task(task_id).await?;
+            if task.is_terminal() {
+                return Ok(task);
+            }
+            match events.as_mut() {
+                Some(stream) => tokio::select! {
+                    event = stream.next() => {
+                        if !matches!(event, Some(Ok(_))) {
+                            events = None;
+                        }
+                    }
+                    () = tokio::time::sleep(RECHECK_INTERVAL) => {}
+                },
+                None => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        }
     }
 
     pub async fn send_signal(
@@ -116,4 +213,31 @@ impl ValkaClient {
         let resp = response.into_inner();
         Ok((resp.signal_id, resp.delivered))
     }
+
+    /// Signals sent to a task, oldest first. `status` keeps only those
+    /// PENDING, DELIVERED or ACKNOWLEDGED.
+    pub async fn list_signals(
+        &mut self,
+        task_id: &str,
+        status: Option<&str>,
+    ) -> Result<Vec<Signal>, SdkError> {
+        let response = self
+            .inner
+            .list_signals(ListSignalsRequest {
+                task_id: task_id.to_string(),
+                status: status.unwrap_or_default().to_string(),
+            })
+            .await?;
+        response
+            .into_inner()
+            .signals
+            .into_iter()
+            .map(Signal::try_from)
+            .collect()
+    }
+}
+
+fn task_from(meta: Option<TaskMeta>) -> Result<Task, SdkError> {
+    meta.ok_or_else(|| SdkError::InvalidResponse("No task in response".to_string()))?
+        .try_into()
 }
@@ -1,7 +1,52 @@
 use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use tokio::sync::mpsc;
-use valka_proto::{LogEntry, SignalAck, TaskSignal, WorkerRequest, worker_request};
+use tokio::time::Instant;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+use valka_proto::{SignalAck, TaskProgress, TaskSignal, WorkerRequest, worker_request};
+
+use crate::task_logs::TaskLogs;
+
+pub use valka_proto::LogLevel;
+
+/// Progress updates of one task are coalesced to at most one per interval.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct ProgressState {
+    last_sent: Option<Instant>,
+    /// Latest update held back by the throttle, sent when the interval ends
+    pending: Option<TaskProgress>,
+}
+
+enum Throttled {
+    SendNow(TaskProgress),
+    /// Held back; the first held-back update schedules a flush for this time
+    FlushAt(Instant),
+    /// Replaced an update whose flush is already scheduled
+    Replaced,
+}
+
+impl ProgressState {
+    fn throttle(&mut self, update: TaskProgress, now: Instant) -> Throttled {
+        match self.last_sent {
+            Some(at) if now < at + PROGRESS_INTERVAL => {
+                if self.pending.replace(update).is_some() {
+                    Throttled::Replaced
+                } else {
+                    Throttled::FlushAt(at + PROGRESS_INTERVAL)
+                }
+            }
+            _ => {
+                self.last_sent = Some(now);
+                Throttled::SendNow(update)
+            }
+        }
+    }
+}
 
 /// Data from a received signal.
 pub struct SignalData {
@@ -26,9 +71,15 @@ pub struct TaskContext {
     pub attempt_number: i32,
     pub input: String,
     pub metadata: String,
+    priority: i32,
+    created_at: Option<DateTime<Utc>>,
+    scheduled_at: Option<DateTime<Utc>>,
     request_tx: mpsc::Sender<WorkerRequest>,
     signal_rx: mpsc::Receiver<TaskSignal>,
     signal_buffer: VecDeque<TaskSignal>,
+    cancellation: CancellationToken,
+    progress: Arc<Mutex<ProgressState>>,
+    logs: TaskLogs,
 }
 
 impl TaskContext {
@@ -43,6 +94,7 @@ impl TaskContext {
         request_tx: mpsc::Sender<WorkerRequest>,
         signal_rx: mpsc::Receiver<TaskSignal>,
     ) -> Self {
+        let logs = TaskLogs::new(task_run_id.clone(), request_tx.clone());
         Self {
             task_id,
             task_run_id,
@@ -51,12 +103,71 @@ impl TaskContext {
             attempt_number,
             input,
             metadata,
+            priority: 0,
+            created_at: None,
+            scheduled_at: None,
             request_tx,
             signal_rx,
             signal_buffer: VecDeque::new(),
+            cancellation: CancellationToken::new(),
+            progress: Arc::default(),
+            logs,
         }
     }
 
+    /// Use `token` to tell the handler its task was cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Set the priority and the creation and scheduled times, in epoch ms
+    /// with 0 for unset, as sent on the task's assignment.
+    pub fn with_schedule(
+        mut self,
+        priority: i32,
+        created_at_ms: i64,
+        scheduled_at_ms: i64,
+    ) -> Self {
+        self.priority = priority;
+        self.created_at = epoch_ms(created_at_ms);
+        self.scheduled_at = epoch_ms(scheduled_at_ms);
+        self
+    }
+
+    /// The priority the task was created with; higher runs first.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// When the task was created. `None` if the server didn't say.
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    /// When the task was scheduled to run, for tasks created with a delay or
+    /// a run time.
+    pub fn scheduled_at(&self) -> Option<DateTime<Utc>> {
+        self.scheduled_at
+    }
+
+    /// Resolves once the server cancels this task (cancelled by a user or
+    /// reclaimed). Race it against the work, e.g. in `tokio::select!`, and
+    /// return early: the result is then reported as cancelled.
+    pub fn cancelled(&self) -> WaitForCancellationFuture<'_> {
+        self.cancellation.cancelled()
+    }
+
+    /// Whether the server has cancelled this task, for polling in loops.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// The task's cancellation token, to hand to work spawned by the handler.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
     /// Parse the input JSON
     pub fn input<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
         serde_json::from_str(&self.input)
@@ -130,43 +241,93 @@ impl TaskContext {
         let _ = self.request_tx.send(request).await;
     }
 
+    /// Report how far along the task is (percent 0-100) with a short status
+    /// message, shown on the run in the dashboard. Cheap to call in a tight
+    /// loop: updates are sent at most once a second, the latest one winning.
+    pub async fn report_progress(&self, percent: f32, message: &str) {
+        let update = TaskProgress {
+            task_id: self.task_id.clone(),
+            task_run_id: self.task_run_id.clone(),
+            percent,
+            message: message.to_string(),
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+        };
+        let outcome = self
+            .progress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .throttle(update, Instant::now());
+        let flush_at = match outcome {
+            Throttled::SendNow(update) => {
+                let _ = self.request_tx.send(progress_request(update)).await;
+                return;
+            }
+            Throttled::FlushAt(at) => at,
+            Throttled::Replaced => return,
+        };
+
+        let progress = self.progress.clone();
+        let request_tx = self.request_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep_until(flush_at).await;
+            let update = {
+                let mut state = progress.lock().unwrap_or_else(|e| e.into_inner());
+                state.last_sent = Some(Instant::now());
+                state.pending.take()
+            };
+            if let Some(update) = update {
+                let _ = request_tx.send(progress_request(update)).await;
+            }
+        });
+    }
+
     /// Log a message at INFO level
     pub async fn log(&self, message: &str) {
-        self.log_at_level(2, message).await;
+        self.log_at(LogLevel::Info, message).await;
     }
 
     /// Log a message at DEBUG level
     pub async fn debug(&self, message: &str) {
-        self.log_at_level(1, message).await;
+        self.log_at(LogLevel::Debug, message).await;
     }
 
     /// Log a message at WARN level
     pub async fn warn(&self, message: &str) {
-        self.log_at_level(3, message).await;
+        self.log_at(LogLevel::Warn, message).await;
     }
 
     /// Log a message at ERROR level
     pub async fn error(&self, message: &str) {
-        self.log_at_level(4, message).await;
+        self.log_at(LogLevel::Error, message).await;
     }
 
-    async fn log_at_level(&self, level: i32, message: &str) {
-        let entry = LogEntry {
-            task_run_id: self.task_run_id.clone(),
-            timestamp_ms: chrono::Utc::now().timestamp_millis(),
-            level,
-            message: message.to_string(),
-            metadata: String::new(),
-        };
+    /// Log a message to this run's logs. Entries are batched and all reach
+    /// the server before the task's result.
+    pub async fn log_at(&self, level: LogLevel, message: &str) {
+        self.logs.push(level, message.to_string(), String::new());
+    }
 
-        let batch = valka_proto::LogBatch {
-            entries: vec![entry],
-        };
+    /// Like [`Self::log_at`], with structured metadata stored alongside.
+    pub async fn log_with(&self, level: LogLevel, message: &str, metadata: serde_json::Value) {
+        self.logs
+            .push(level, message.to_string(), metadata.to_string());
+    }
 
-        let request = WorkerRequest {
-            request: Some(worker_request::Request::LogBatch(batch)),
-        };
+    /// This run's log buffer, flushed by the worker before reporting a result.
+    pub(crate) fn logs(&self) -> &TaskLogs {
+        &self.logs
+    }
+}
 
-        let _ = self.request_tx.send(request).await;
+fn progress_request(update: TaskProgress) -> WorkerRequest {
+    WorkerRequest {
+        request: Some(worker_request::Request::Progress(update)),
     }
 }
+
+/// An epoch-ms timestamp, where 0 means unset.
+fn epoch_ms(ms: i64) -> Option<DateTime<Utc>> {
+    (ms > 0)
+        .then_some(ms)
+        .and_then(DateTime::from_timestamp_millis)
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::task::Task;
+
 #[derive(Debug, Error)]
 pub enum SdkError {
     #[error("Connection error: {0}")]
@@ -14,6 +16,9 @@ pub enum SdkError {
     #[error("Task handler error: {0}")]
     Handler(String),
 
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
@@ -23,3 +28,18 @@ pub enum SdkError {
     #[error("Shutdown in progress")]
     ShuttingDown,
 }
+
+/// Why [`ValkaClient::wait_for_completion`](crate::ValkaClient::wait_for_completion)
+/// returned without a completed task.
+#[derive(Debug, Error)]
+pub enum WaitError {
+    #[error("Timed out waiting for task {0}")]
+    Timeout(String),
+
+    /// The task ended FAILED, DEAD_LETTER or CANCELLED.
+    #[error("Task {} ended {}", .0.id, .0.status.as_str_name())]
+    Unsuccessful(Box<Task>),
+
+    #[error(transparent)]
+    Sdk(#[from] SdkError),
+}
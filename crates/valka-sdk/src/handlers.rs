@@ -0,0 +1,147 @@
+//! Task handlers routed by task name, with typed input and output.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::context::TaskContext;
+use crate::worker::TaskHandler;
+
+/// A failed handler outcome. Plain string errors are retryable; use
+/// [`TaskError::fatal`] for failures a retry cannot fix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskError {
+    pub message: String,
+    pub retryable: bool,
+    /// Delay before the next attempt, in place of the server's backoff.
+    pub retry_after: Option<Duration>,
+}
+
+impl TaskError {
+    /// Retry after the server's backoff, if attempts remain.
+    pub fn retryable(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: true,
+            retry_after: None,
+        }
+    }
+
+    /// Retry once `delay` has passed, e.g. when a rate limit says when to
+    /// come back. Attempts and deadlines still apply.
+    pub fn retry_after(message: impl Into<String>, delay: Duration) -> Self {
+        Self {
+            retry_after: Some(delay),
+            ..Self::retryable(message)
+        }
+    }
+
+    /// Fail the task without further attempts.
+    pub fn fatal(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: false,
+            retry_after: None,
+        }
+    }
+}
+
+impl From<String> for TaskError {
+    fn from(message: String) -> Self {
+        Self::retryable(message)
+    }
+}
+
+impl From<&str> for TaskError {
+    fn from(message: &str) -> Self {
+        Self::retryable(message)
+    }
+}
+
+impl std::fmt::Display for TaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+pub(crate) type HandlerFuture =
+    Pin<Box<dyn Future<Output = Result<serde_json::Value, TaskError>> + Send>>;
+
+type NamedHandler = Arc<dyn Fn(TaskContext) -> HandlerFuture + Send + Sync>;
+
+/// Handlers by task name, plus the untyped handler for every other name.
+#[derive(Clone, Default)]
+pub(crate) struct HandlerRegistry {
+    named: HashMap<String, NamedHandler>,
+    pub fallback: Option<TaskHandler>,
+}
+
+impl HandlerRegistry {
+    pub fn is_empty(&self) -> bool {
+        self.named.is_empty() && self.fallback.is_none()
+    }
+
+    pub fn has_named(&self) -> bool {
+        !self.named.is_empty()
+    }
+
+    /// Route `task_name` to `f`, which gets the input deserialized as `I` and
+    /// returns an output serialized back to JSON. Input that doesn't parse
+    /// fails the task without retry.
+    pub fn register<I, O, F, Fut>(&mut self, task_name: &str, f: F)
+    where
+        I: DeserializeOwned + Send + 'static,
+        O: Serialize + Send + 'static,
+        F: Fn(TaskContext, I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<O, TaskError>> + Send + 'static,
+    {
+        let name = task_name.to_string();
+        let handler: NamedHandler = Arc::new(move |ctx: TaskContext| {
+            // Tasks created without input carry an empty string
+            let raw = if ctx.input.is_empty() {
+                "null"
+            } else {
+                ctx.input.as_str()
+            };
+            let input = match serde_json::from_str::<I>(raw) {
+                Ok(input) => input,
+                Err(e) => {
+                    let err = TaskError::fatal(format!("Invalid input for task '{name}': {e}"));
+                    return Box::pin(std::future::ready(Err(err)));
+                }
+            };
+            let run = f(ctx, input);
+            Box::pin(async move {
+                let output = run.await?;
+                serde_json::to_value(output)
+                    .map_err(|e| TaskError::fatal(format!("Failed to serialize output: {e}")))
+            })
+        });
+        self.named.insert(task_name.to_string(), handler);
+    }
+
+    /// Run the handler for `ctx.task_name`. Names with neither a handler nor
+    /// a fallback fail without retry.
+    pub fn call(&self, ctx: TaskContext) -> HandlerFuture {
+        if let Some(handler) = self.named.get(&ctx.task_name) {
+            return handler(ctx);
+        }
+        match &self.fallback {
+            Some(fallback) => fallback(ctx),
+            None => {
+                let err = TaskError::fatal(format!(
+                    "No handler registered for task '{}'",
+                    ctx.task_name
+                ));
+                Box::pin(std::future::ready(Err(err)))
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Opening of a worker session: after `WorkerHello` the SDK waits for the
+//! server's `HelloAck` before it heartbeats or takes tasks.
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use tracing::{info, warn};
+use valka_proto::{
+    CAPABILITY_PROGRESS, CAPABILITY_SIGNALS, HelloAck, PROTOCOL_VERSION, WorkerResponse,
+    worker_response,
+};
+
+use crate::error::SdkError;
+
+/// How long a server gets to acknowledge the hello before it is taken to
+/// predate `HelloAck`.
+const HELLO_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Heartbeat interval when neither the server nor the builder sets one.
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What this SDK handles, advertised in `WorkerHello`.
+pub(crate) fn capabilities() -> Vec<String> {
+    [CAPABILITY_SIGNALS, CAPABILITY_PROGRESS]
+        .map(String::from)
+        .to_vec()
+}
+
+/// The `heartbeat_interval_ms` to ask for in `WorkerHello`; 0 leaves it to
+/// the server.
+pub(crate) fn requested_interval_ms(requested: Option<Duration>) -> i64 {
+    requested.map_or(0, |interval| interval.as_millis() as i64)
+}
+
+/// The session the server agreed to.
+pub(crate) struct Established {
+    pub heartbeat_interval: Duration,
+    /// First message from a server that sent no `HelloAck`, to be handled as usual
+    pub replay: Option<WorkerResponse>,
+}
+
+/// Wait for the `HelloAck` answering our hello. A server that sends anything
+/// else first, or nothing in time, predates versioning: the session goes on
+/// with the heartbeat interval we asked for, or the default.
+pub(crate) async fn await_hello_ack(
+    inbound: &mut tonic::Streaming<WorkerResponse>,
+    requested: Option<Duration>,
+) -> Result<Established, SdkError> {
+    let first = match tokio::time::timeout(HELLO_ACK_TIMEOUT, inbound.next()).await {
+        Ok(Some(Ok(response))) => Some(response),
+        Ok(Some(Err(e))) => {
+            return Err(SdkError::Connection(format!(
+                "Stream error before HelloAck: {e}"
+            )));
+        }
+        Ok(None) => {
+            return Err(SdkError::Connection(
+                "Server closed stream before HelloAck".to_string(),
+            ));
+        }
+        Err(_) => None,
+    };
+    match first {
+        Some(WorkerResponse {
+            response: Some(worker_response::Response::HelloAck(ack)),
+        }) => Ok(established(ack, requested)),
+        replay => {
+            warn!("Server sent no HelloAck; it predates protocol versioning");
+            Ok(Established {
+                heartbeat_interval: requested.unwrap_or(DEFAULT_HEARTBEAT_INTERVAL),
+                replay,
+            })
+        }
+    }
+}
+
+fn established(ack: HelloAck, requested: Option<Duration>) -> Established {
+    if ack.protocol_version != PROTOCOL_VERSION {
+        warn!(
+            server = ack.protocol_version,
+            worker = PROTOCOL_VERSION,
+            "Server speaks a different worker protocol version"
+        );
+    }
+    let heartbeat_interval = u64::try_from(ack.heartbeat_interval_ms)
+        .ok()
+        .filter(|ms| *ms > 0)
+        .map(Duration::from_millis)
+        .or(requested)
+        .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL);
+    info!(
+        session_id = %ack.session_id,
+        heartbeat_interval_ms = heartbeat_interval.as_millis() as u64,
+        "Worker session established"
+    );
+    Established {
+        heartbeat_interval,
+        replay: None,
+    }
+}
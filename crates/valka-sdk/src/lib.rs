@@ -1,10 +1,24 @@
+pub mod auth;
+pub mod batch;
 pub mod client;
 pub mod context;
 pub mod error;
+pub mod handlers;
+mod handshake;
+pub mod limits;
+pub mod logging;
 pub mod retry;
+pub mod stream;
+pub mod task;
+pub mod task_logs;
+pub mod telemetry;
+pub mod webhooks;
 pub mod worker;
 
 pub use client::ValkaClient;
 pub use context::TaskContext;
-pub use error::SdkError;
-pub use worker::{ShutdownHandle, ValkaWorker};
+pub use error::{SdkError, WaitError};
+pub use handlers::TaskError;
+pub use stream::{IncomingTask, ValkaTaskStream};
+pub use task::{CreateTaskOptions, Task};
+pub use worker::{ShutdownHandle, ValkaWorker, WorkerQueue};
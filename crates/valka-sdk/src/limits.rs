@@ -0,0 +1,67 @@
+//! Bounds on what a worker reports back for a task, checked before the
+//! TaskResult is sent so an oversize output can't break the message.
+
+use tracing::warn;
+use valka_proto::TaskResult;
+
+/// Default for [`ValkaWorkerBuilder::max_output_bytes`](crate::worker::ValkaWorkerBuilder::max_output_bytes),
+/// matching the server's `limits.max_output_bytes`.
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Default for [`ValkaWorkerBuilder::max_error_message_bytes`](crate::worker::ValkaWorkerBuilder::max_error_message_bytes),
+/// matching the server's `limits.max_error_message_bytes`.
+pub const DEFAULT_MAX_ERROR_MESSAGE_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ResultLimits {
+    pub max_output_bytes: usize,
+    pub max_error_message_bytes: usize,
+}
+
+impl Default for ResultLimits {
+    fn default() -> Self {
+        Self {
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            max_error_message_bytes: DEFAULT_MAX_ERROR_MESSAGE_BYTES,
+        }
+    }
+}
+
+impl ResultLimits {
+    /// Fit `result` within the limits. An output over the limit turns the
+    /// result into a non-retryable failure, since running the task again
+    /// would produce the same output; a long error message is cut short.
+    pub fn apply(&self, result: &mut TaskResult) {
+        if result.success && result.output.len() > self.max_output_bytes {
+            warn!(
+                task_id = %result.task_id,
+                bytes = result.output.len(),
+                limit = self.max_output_bytes,
+                "Task output over the size limit; failing the task"
+            );
+            result.error_message = format!(
+                "Task output is {} bytes, over the {} byte limit",
+                result.output.len(),
+                self.max_output_bytes
+            );
+            result.output = String::new();
+            result.success = false;
+            result.retryable = false;
+        }
+        truncate_utf8(&mut result.error_message, self.max_error_message_bytes);
+    }
+}
+
+/// Cut `s` to at most `max_bytes`, on a character boundary. Returns whether
+/// anything was cut.
+pub fn truncate_utf8(s: &mut String, max_bytes: usize) -> bool {
+    if s.len() <= max_bytes {
+        return false;
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s.truncate(end);
+    true
+}
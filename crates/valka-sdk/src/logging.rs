@@ -0,0 +1,257 @@
+//! Forwarding of worker-level `tracing` events to the Valka server.
+//!
+//! Install the [`WorkerLogLayer`] in your subscriber and hand the paired
+//! [`WorkerLogSink`] to [`ValkaWorkerBuilder::forward_logs`](crate::worker::ValkaWorkerBuilder::forward_logs).
+//! Events at or above the configured level are shipped as `LogBatch` messages
+//! with a `worker:<worker_id>` run id so they land in the server's worker log store.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use tokio::sync::{Mutex, mpsc};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+
+use valka_proto::{LogBatch, LogEntry, WORKER_LOG_RUN_PREFIX, WorkerRequest, worker_request};
+
+/// Max entries shipped in a single LogBatch.
+const MAX_BATCH: usize = 50;
+
+/// Configuration for worker log forwarding.
+#[derive(Debug, Clone)]
+pub struct WorkerLogConfig {
+    /// Least severe level that is forwarded.
+    pub level: Level,
+    /// Sustained forwarding rate; bursts up to this many are allowed.
+    pub max_per_second: u32,
+    /// Entries buffered while disconnected before new ones are dropped.
+    pub buffer_size: usize,
+}
+
+impl Default for WorkerLogConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::WARN,
+            max_per_second: 20,
+            buffer_size: 1024,
+        }
+    }
+}
+
+/// A log event captured by the layer, not yet addressed to a worker.
+#[derive(Debug)]
+struct CapturedLog {
+    timestamp_ms: i64,
+    level: i32,
+    message: String,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Create a paired layer and sink for forwarding worker logs.
+pub fn worker_log_layer(config: WorkerLogConfig) -> (WorkerLogLayer, WorkerLogSink) {
+    let (tx, rx) = mpsc::channel(config.buffer_size.max(1));
+    let dropped = Arc::new(AtomicU64::new(0));
+    let layer = WorkerLogLayer {
+        tx,
+        level: config.level,
+        limiter: std::sync::Mutex::new(RateLimiter::new(config.max_per_second)),
+        dropped: dropped.clone(),
+    };
+    let sink = WorkerLogSink {
+        rx: Arc::new(Mutex::new(rx)),
+        dropped,
+    };
+    (layer, sink)
+}
+
+/// `tracing` layer capturing events for forwarding to the server.
+pub struct WorkerLogLayer {
+    tx: mpsc::Sender<CapturedLog>,
+    level: Level,
+    limiter: std::sync::Mutex<RateLimiter>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<S: Subscriber> Layer<S> for WorkerLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        if *meta.level() > self.level {
+            return;
+        }
+        // Never capture our own shipping path, to avoid feedback loops.
+        if meta.target().starts_with(module_path!()) {
+            return;
+        }
+
+        let allowed = self
+            .limiter
+            .lock()
+            .map(|mut l| l.try_acquire(Instant::now()))
+            .unwrap_or(false);
+        if !allowed {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        visitor.fields.insert(
+            "target".to_string(),
+            serde_json::Value::String(meta.target().to_string()),
+        );
+
+        let captured = CapturedLog {
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            level: level_to_proto(meta.level()),
+            message: visitor.message,
+            fields: visitor.fields,
+        };
+        if self.tx.try_send(captured).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Receiving side of the layer, consumed by the worker connection.
+#[derive(Clone)]
+pub struct WorkerLogSink {
+    rx: Arc<Mutex<mpsc::Receiver<CapturedLog>>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl WorkerLogSink {
+    /// Number of events dropped by the rate limit or a full buffer.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Ship captured logs on the request stream until it closes.
+    pub async fn ship(
+        self,
+        worker_id: String,
+        worker_name: String,
+        request_tx: mpsc::Sender<WorkerRequest>,
+    ) {
+        let run_id = format!("{WORKER_LOG_RUN_PREFIX}{worker_id}");
+        let mut rx = self.rx.lock().await;
+
+        while let Some(first) = rx.recv().await {
+            let mut captured = vec![first];
+            while captured.len() < MAX_BATCH {
+                match rx.try_recv() {
+                    Ok(log) => captured.push(log),
+                    Err(_) => break,
+                }
+            }
+
+            let entries = captured
+                .into_iter()
+                .map(|mut log| {
+                    log.fields.insert(
+                        "worker_name".to_string(),
+                        serde_json::Value::String(worker_name.clone()),
+                    );
+                    LogEntry {
+                        task_run_id: run_id.clone(),
+                        timestamp_ms: log.timestamp_ms,
+                        level: log.level,
+                        message: log.message,
+                        metadata: serde_json::Value::Object(log.fields).to_string(),
+                    }
+                })
+                .collect();
+
+            let request = WorkerRequest {
+                request: Some(worker_request::Request::LogBatch(LogBatch { entries })),
+            };
+            if request_tx.send(request).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+fn level_to_proto(level: &Level) -> i32 {
+    match *level {
+        Level::ERROR => 4,
+        Level::WARN => 3,
+        Level::INFO => 2,
+        _ => 1,
+    }
+}
+
+/// Collects an event's `message` and its other fields as JSON.
+#[derive(Default)]
+pub(crate) struct FieldVisitor {
+    pub message: String,
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields
+                .insert(field.name().to_string(), serde_json::Value::from(value));
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields
+            .insert(field.name().to_string(), serde_json::Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields
+            .insert(field.name().to_string(), serde_json::Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields
+            .insert(field.name().to_string(), serde_json::Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.fields.insert(
+                field.name().to_string(),
+                serde_json::Value::String(format!("{value:?}")),
+            );
+        }
+    }
+}
+
+/// Token bucket refilled continuously at `rate` tokens per second.
+struct RateLimiter {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    fn new(rate: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last: Instant::now(),
+        }
+    }
+
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,640 @@
+//! Pull-based consumption of task assignments.
+//!
+//! [`ValkaTaskStream`] hands each assignment to the caller as an
+//! [`IncomingTask`] instead of running a handler, for consumers that schedule
+//! execution themselves (custom thread pools, batching across tasks). The SDK
+//! still owns the connection: hello, heartbeats with the unresolved task ids,
+//! signal routing and reconnection.
+//!
+//! At most `concurrency` tasks are outstanding at once; the stream stops
+//! yielding until one is resolved. Dropping an unresolved task rejects it.
+//!
+//! ```no_run
+//! use futures::StreamExt;
+//! use valka_sdk::ValkaWorker;
+//!
+//! # async fn run() -> Result<(), valka_sdk::SdkError> {
+//! let mut tasks = ValkaWorker::builder()
+//!     .queues(&["embeddings"])
+//!     .concurrency(32)
+//!     .build_stream()
+//!     .await?;
+//!
+//! while let Some(task) = tasks.next().await {
+//!     let input: serde_json::Value = task.context().input()?;
+//!     task.complete(serde_json::json!({ "echo": input })).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::{Stream, StreamExt};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tonic::transport::Channel;
+use tracing::{error, info, warn};
+
+use valka_proto::worker_service_client::WorkerServiceClient;
+use valka_proto::*;
+use valka_proto::{worker_request, worker_response};
+
+use crate::auth::BearerToken;
+use crate::context::TaskContext;
+use crate::error::SdkError;
+use crate::handshake;
+use crate::limits::ResultLimits;
+use crate::logging::WorkerLogSink;
+use crate::retry::RetryPolicy;
+use crate::task_logs::TaskLogs;
+use crate::worker::ShutdownHandle;
+
+/// Connection settings taken from [`crate::worker::ValkaWorkerBuilder`].
+pub(crate) struct StreamConfig {
+    pub worker_id: String,
+    pub name: String,
+    pub server_addr: String,
+    pub queues: Vec<String>,
+    pub concurrency: i32,
+    pub queue_concurrency: HashMap<String, i32>,
+    pub metadata: String,
+    pub version: String,
+    pub labels: HashMap<String, String>,
+    pub log_sink: Option<WorkerLogSink>,
+    pub token: BearerToken,
+    /// Asked of the server in the hello; None takes its configured interval
+    pub heartbeat_interval: Option<std::time::Duration>,
+    pub result_limits: ResultLimits,
+}
+
+/// Task ids reported in heartbeats, where to route their signals, and how
+/// to cancel them.
+#[derive(Default)]
+struct Tracking {
+    active: Mutex<HashSet<String>>,
+    signals: Mutex<HashMap<String, mpsc::Sender<TaskSignal>>>,
+    cancels: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl Tracking {
+    fn track(&self, task_id: &str, signal_tx: mpsc::Sender<TaskSignal>, cancel: CancellationToken) {
+        self.active.lock().unwrap().insert(task_id.to_string());
+        self.signals
+            .lock()
+            .unwrap()
+            .insert(task_id.to_string(), signal_tx);
+        self.cancels
+            .lock()
+            .unwrap()
+            .insert(task_id.to_string(), cancel);
+    }
+
+    fn untrack(&self, task_id: &str) {
+        self.active.lock().unwrap().remove(task_id);
+        self.signals.lock().unwrap().remove(task_id);
+        self.cancels.lock().unwrap().remove(task_id);
+    }
+
+    /// Fire the task's cancellation token and stop tracking it.
+    fn cancel(&self, task_id: &str) {
+        if let Some(cancel) = self.cancels.lock().unwrap().remove(task_id) {
+            cancel.cancel();
+        }
+        self.untrack(task_id);
+    }
+
+    fn active_ids(&self) -> Vec<String> {
+        self.active.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn signal_sender(&self, task_id: &str) -> Option<mpsc::Sender<TaskSignal>> {
+        self.signals.lock().unwrap().get(task_id).cloned()
+    }
+}
+
+/// A stream of [`IncomingTask`]s from the server.
+///
+/// Ends after [`ShutdownHandle::shutdown`]; the connection is closed once
+/// every outstanding task has been resolved. Dropping the stream shuts it down
+/// the same way.
+pub struct ValkaTaskStream {
+    worker_id: String,
+    tasks: mpsc::Receiver<IncomingTask>,
+    shutdown: Arc<Notify>,
+}
+
+impl ValkaTaskStream {
+    pub(crate) fn spawn(config: StreamConfig) -> Self {
+        let (task_tx, tasks) = mpsc::channel(config.concurrency.max(1) as usize);
+        let (outbound_tx, outbound_rx) = mpsc::channel(256);
+        let shutdown = Arc::new(Notify::new());
+        let worker_id = config.worker_id.clone();
+
+        let session = Session {
+            permits: Arc::new(Semaphore::new(config.concurrency.max(1) as usize)),
+            config,
+            tracking: Arc::new(Tracking::default()),
+            outbound_tx,
+            outbound_rx,
+            task_tx: Some(task_tx),
+            backlog: VecDeque::new(),
+            shutdown: shutdown.clone(),
+        };
+        tokio::spawn(session.run());
+
+        Self {
+            worker_id,
+            tasks,
+            shutdown,
+        }
+    }
+
+    pub fn worker_id(&self) -> &str {
+        &self.worker_id
+    }
+
+    /// Returns a handle that stops the stream from another task.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle(self.shutdown.clone())
+    }
+}
+
+impl Stream for ValkaTaskStream {
+    type Item = IncomingTask;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<IncomingTask>> {
+        self.tasks.poll_recv(cx)
+    }
+}
+
+impl Drop for ValkaTaskStream {
+    fn drop(&mut self) {
+        self.shutdown.notify_one();
+    }
+}
+
+/// An assignment handed to the consumer. Resolve it with [`complete`],
+/// [`fail`] or [`reject`]; dropping it unresolved rejects it.
+///
+/// [`complete`]: IncomingTask::complete
+/// [`fail`]: IncomingTask::fail
+/// [`reject`]: IncomingTask::reject
+pub struct IncomingTask {
+    context: TaskContext,
+    assignment: TaskAssignment,
+    resolver: TaskResolver,
+}
+
+impl IncomingTask {
+    /// Logging, input and signal access for this task.
+    pub fn context(&self) -> &TaskContext {
+        &self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut TaskContext {
+        &mut self.context
+    }
+
+    /// The assignment as received from the server.
+    pub fn assignment(&self) -> &TaskAssignment {
+        &self.assignment
+    }
+
+    /// Split off the resolver, e.g. to resolve from a different thread than
+    /// the one holding the context.
+    pub fn into_parts(self) -> (TaskContext, TaskAssignment, TaskResolver) {
+        (self.context, self.assignment, self.resolver)
+    }
+
+    pub async fn complete(self, output: serde_json::Value) -> Result<(), SdkError> {
+        self.resolver.complete(output).await
+    }
+
+    pub async fn fail(self, error: impl Into<String>, retryable: bool) -> Result<(), SdkError> {
+        self.resolver.fail(error, retryable).await
+    }
+
+    pub async fn reject(self) -> Result<(), SdkError> {
+        self.resolver.reject().await
+    }
+}
+
+/// Reports the outcome of one [`IncomingTask`] and frees its slot.
+pub struct TaskResolver {
+    task_id: String,
+    task_run_id: String,
+    outbound: mpsc::Sender<WorkerRequest>,
+    tracking: Arc<Tracking>,
+    cancel: CancellationToken,
+    logs: TaskLogs,
+    limits: ResultLimits,
+    resolved: bool,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl TaskResolver {
+    /// Report success with `output` as the task result.
+    pub async fn complete(self, output: serde_json::Value) -> Result<(), SdkError> {
+        let result = TaskResult {
+            success: true,
+            output: output.to_string(),
+            ..self.result()
+        };
+        self.send(result).await
+    }
+
+    /// Report failure. Retryable failures go through the queue's retry policy;
+    /// others fail the task outright.
+    pub async fn fail(self, error: impl Into<String>, retryable: bool) -> Result<(), SdkError> {
+        let result = TaskResult {
+            retryable,
+            error_message: error.into(),
+            ..self.result()
+        };
+        self.send(result).await
+    }
+
+    /// Hand the task back unprocessed. The server returns it to PENDING
+    /// without counting it as a failure.
+    pub async fn reject(self) -> Result<(), SdkError> {
+        let result = rejection(&self.task_id, &self.task_run_id);
+        self.send(result).await
+    }
+
+    fn result(&self) -> TaskResult {
+        TaskResult {
+            task_id: self.task_id.clone(),
+            task_run_id: self.task_run_id.clone(),
+            success: false,
+            retryable: false,
+            output: String::new(),
+            error_message: String::new(),
+            traceparent: String::new(),
+            rejected: false,
+            cancelled: false,
+            retry_after_ms: 0,
+        }
+    }
+
+    fn cancelled(&self) -> TaskResult {
+        TaskResult {
+            cancelled: true,
+            ..self.result()
+        }
+    }
+
+    /// Once the task is cancelled, any outcome is reported as cancelled.
+    async fn send(mut self, mut result: TaskResult) -> Result<(), SdkError> {
+        self.resolved = true;
+        if self.cancel.is_cancelled() {
+            result = self.cancelled();
+        }
+        self.limits.apply(&mut result);
+        self.logs.flush().await;
+        let request = WorkerRequest {
+            request: Some(worker_request::Request::TaskResult(result)),
+        };
+        let sent = self.outbound.send(request).await;
+        self.tracking.untrack(&self.task_id);
+        sent.map_err(|_| SdkError::NotConnected)
+    }
+}
+
+impl Drop for TaskResolver {
+    fn drop(&mut self) {
+        if self.resolved {
+            return;
+        }
+        let result = if self.cancel.is_cancelled() {
+            self.cancelled()
+        } else {
+            warn!(task_id = %self.task_id, "Task dropped unresolved, rejecting");
+            rejection(&self.task_id, &self.task_run_id)
+        };
+        let request = WorkerRequest {
+            request: Some(worker_request::Request::TaskResult(result)),
+        };
+        let _ = self.outbound.try_send(request);
+        self.tracking.untrack(&self.task_id);
+    }
+}
+
+pub(crate) fn rejection(task_id: &str, task_run_id: &str) -> TaskResult {
+    TaskResult {
+        task_id: task_id.to_string(),
+        task_run_id: task_run_id.to_string(),
+        success: false,
+        retryable: false,
+        output: String::new(),
+        error_message: String::new(),
+        traceparent: String::new(),
+        rejected: true,
+        cancelled: false,
+        retry_after_ms: 0,
+    }
+}
+
+/// An assignment waiting for a free slot before it is yielded.
+struct Pending {
+    assignment: TaskAssignment,
+    signal_rx: mpsc::Receiver<TaskSignal>,
+    cancel: CancellationToken,
+}
+
+enum Event {
+    Inbound(Option<Result<WorkerResponse, tonic::Status>>),
+    Outbound(WorkerRequest),
+    SlotFreed(OwnedSemaphorePermit),
+    Shutdown,
+    Drained,
+}
+
+/// Background task owning the server connection.
+struct Session {
+    config: StreamConfig,
+    tracking: Arc<Tracking>,
+    permits: Arc<Semaphore>,
+    /// Results, logs and signal acks from tasks; survives reconnects
+    outbound_tx: mpsc::Sender<WorkerRequest>,
+    outbound_rx: mpsc::Receiver<WorkerRequest>,
+    /// None once shutting down
+    task_tx: Option<mpsc::Sender<IncomingTask>>,
+    backlog: VecDeque<Pending>,
+    shutdown: Arc<Notify>,
+}
+
+impl Session {
+    async fn run(mut self) {
+        let mut retry_policy = RetryPolicy::new();
+
+        loop {
+            match self.connect_and_run(&mut retry_policy).await {
+                Ok(()) => {
+                    info!("Task stream closed gracefully");
+                    return;
+                }
+                Err(e) => {
+                    let delay = retry_policy.next_delay();
+                    warn!(
+                        error = %e,
+                        retry_in_ms = delay.as_millis(),
+                        "Task stream connection lost, reconnecting..."
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    async fn connect_and_run(&mut self, retry_policy: &mut RetryPolicy) -> Result<(), SdkError> {
+        let channel = Channel::from_shared(self.config.server_addr.clone())
+            .map_err(|e| SdkError::Connection(e.to_string()))?
+            .http2_keep_alive_interval(std::time::Duration::from_secs(10))
+            .keep_alive_timeout(std::time::Duration::from_secs(5))
+            .keep_alive_while_idle(true)
+            .connect()
+            .await?;
+
+        let mut client = WorkerServiceClient::with_interceptor(channel, self.config.token.clone());
+        let (request_tx, request_rx) = mpsc::channel::<WorkerRequest>(256);
+        let response = client.session(ReceiverStream::new(request_rx)).await?;
+        let mut inbound = response.into_inner();
+
+        retry_policy.reset();
+        info!(worker_id = %self.config.worker_id, name = %self.config.name, "Task stream connected");
+
+        let hello = WorkerRequest {
+            request: Some(worker_request::Request::Hello(WorkerHello {
+                worker_id: self.config.worker_id.clone(),
+                worker_name: self.config.name.clone(),
+                queues: self.config.queues.clone(),
+                concurrency: self.config.concurrency,
+                queue_concurrency: self.config.queue_concurrency.clone(),
+                metadata: self.config.metadata.clone(),
+                version: self.config.version.clone(),
+                labels: self.config.labels.clone(),
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: handshake::capabilities(),
+                heartbeat_interval_ms: handshake::requested_interval_ms(
+                    self.config.heartbeat_interval,
+                ),
+            })),
+        };
+        request_tx
+            .send(hello)
+            .await
+            .map_err(|_| SdkError::NotConnected)?;
+        let session =
+            handshake::await_hello_ack(&mut inbound, self.config.heartbeat_interval).await?;
+        if let Some(response) = session.replay {
+            self.on_response(response).await?;
+        }
+
+        let hb_tx = request_tx.clone();
+        let hb_tracking = self.tracking.clone();
+        let hb_every = session.heartbeat_interval;
+        let hb_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(hb_every);
+            loop {
+                interval.tick().await;
+                let hb = WorkerRequest {
+                    request: Some(worker_request::Request::Heartbeat(Heartbeat {
+                        active_task_ids: hb_tracking.active_ids(),
+                        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+                    })),
+                };
+                if hb_tx.send(hb).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let log_handle = self.config.log_sink.clone().map(|sink| {
+            tokio::spawn(sink.ship(
+                self.config.worker_id.clone(),
+                self.config.name.clone(),
+                request_tx.clone(),
+            ))
+        });
+
+        let result = self.process(&mut inbound, &request_tx).await;
+        hb_handle.abort();
+        if let Some(handle) = log_handle {
+            handle.abort();
+        }
+        result
+    }
+
+    async fn process(
+        &mut self,
+        inbound: &mut tonic::Streaming<WorkerResponse>,
+        request_tx: &mpsc::Sender<WorkerRequest>,
+    ) -> Result<(), SdkError> {
+        let concurrency = self.config.concurrency.max(1) as u32;
+
+        loop {
+            let stopping = self.task_tx.is_none();
+            let has_backlog = !self.backlog.is_empty();
+            let event = tokio::select! {
+                msg = inbound.next() => Event::Inbound(msg),
+                Some(request) = self.outbound_rx.recv() => Event::Outbound(request),
+                permit = self.permits.clone().acquire_owned(), if !stopping && has_backlog => {
+                    Event::SlotFreed(permit.map_err(|_| SdkError::ShuttingDown)?)
+                }
+                _ = self.shutdown.notified(), if !stopping => Event::Shutdown,
+                drained = self.permits.acquire_many(concurrency), if stopping => {
+                    drop(drained);
+                    Event::Drained
+                }
+            };
+
+            match event {
+                Event::Inbound(Some(Ok(response))) => self.on_response(response).await?,
+                Event::Inbound(Some(Err(e))) => {
+                    error!(error = %e, "Stream error");
+                    return Err(SdkError::Connection("Stream closed".to_string()));
+                }
+                Event::Inbound(None) => {
+                    info!("Server closed stream");
+                    return Err(SdkError::Connection("Stream closed".to_string()));
+                }
+                Event::Outbound(request) => {
+                    request_tx
+                        .send(request)
+                        .await
+                        .map_err(|_| SdkError::NotConnected)?;
+                }
+                Event::SlotFreed(permit) => {
+                    let pending = self.backlog.pop_front().expect("backlog is not empty");
+                    let task = self.incoming(pending, permit);
+                    if let Some(task_tx) = &self.task_tx {
+                        // A closed stream drops the task, which rejects it
+                        let _ = task_tx.send(task).await;
+                    }
+                }
+                Event::Shutdown => {
+                    info!("Task stream shutting down, waiting for outstanding tasks");
+                    self.task_tx = None;
+                    for pending in std::mem::take(&mut self.backlog) {
+                        let assignment = pending.assignment;
+                        self.tracking.untrack(&assignment.task_id);
+                        let request = WorkerRequest {
+                            request: Some(worker_request::Request::TaskResult(rejection(
+                                &assignment.task_id,
+                                &assignment.task_run_id,
+                            ))),
+                        };
+                        request_tx
+                            .send(request)
+                            .await
+                            .map_err(|_| SdkError::NotConnected)?;
+                    }
+                }
+                Event::Drained => {
+                    // Every outstanding task has queued its result by now
+                    while let Ok(request) = self.outbound_rx.try_recv() {
+                        request_tx
+                            .send(request)
+                            .await
+                            .map_err(|_| SdkError::NotConnected)?;
+                    }
+                    let shutdown = WorkerRequest {
+                        request: Some(worker_request::Request::Shutdown(GracefulShutdown {
+                            reason: "task_stream_closed".to_string(),
+                        })),
+                    };
+                    let _ = request_tx.send(shutdown).await;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    async fn on_response(&mut self, response: WorkerResponse) -> Result<(), SdkError> {
+        match response.response {
+            Some(worker_response::Response::TaskAssignment(assignment)) => {
+                let (signal_tx, signal_rx) = mpsc::channel::<TaskSignal>(64);
+                let cancel = CancellationToken::new();
+                self.tracking
+                    .track(&assignment.task_id, signal_tx, cancel.clone());
+                self.backlog.push_back(Pending {
+                    assignment,
+                    signal_rx,
+                    cancel,
+                });
+            }
+            Some(worker_response::Response::TaskCancellation(cancel)) => {
+                info!(task_id = %cancel.task_id, "Task cancelled by server");
+                self.tracking.cancel(&cancel.task_id);
+                self.backlog
+                    .retain(|pending| pending.assignment.task_id != cancel.task_id);
+            }
+            Some(worker_response::Response::TaskSignal(signal)) => {
+                if let Some(tx) = self.tracking.signal_sender(&signal.task_id)
+                    && tx.send(signal).await.is_err()
+                {
+                    warn!("Signal channel closed for task");
+                }
+            }
+            Some(worker_response::Response::HeartbeatAck(_))
+            | Some(worker_response::Response::HelloAck(_)) => {}
+            Some(worker_response::Response::ServerShutdown(shutdown)) => {
+                // A drain window means results are still accepted; the server
+                // closes the stream once our tasks are done
+                if shutdown.drain_seconds > 0 {
+                    info!(
+                        reason = %shutdown.reason,
+                        drain_seconds = shutdown.drain_seconds,
+                        "Server shutting down; finishing in-flight tasks"
+                    );
+                    return Ok(());
+                }
+                info!(reason = %shutdown.reason, "Server shutting down");
+                return Err(SdkError::Connection("Server shutting down".to_string()));
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn incoming(&self, pending: Pending, permit: OwnedSemaphorePermit) -> IncomingTask {
+        let assignment = pending.assignment;
+        let context = TaskContext::new(
+            assignment.task_id.clone(),
+            assignment.task_run_id.clone(),
+            assignment.queue_name.clone(),
+            assignment.task_name.clone(),
+            assignment.attempt_number,
+            assignment.input.clone(),
+            assignment.metadata.clone(),
+            self.outbound_tx.clone(),
+            pending.signal_rx,
+        )
+        .with_schedule(
+            assignment.priority,
+            assignment.created_at_ms,
+            assignment.scheduled_at_ms,
+        )
+        .with_cancellation(pending.cancel.clone());
+        let resolver = TaskResolver {
+            task_id: assignment.task_id.clone(),
+            task_run_id: assignment.task_run_id.clone(),
+            outbound: self.outbound_tx.clone(),
+            tracking: self.tracking.clone(),
+            cancel: pending.cancel,
+            logs: context.logs().clone(),
+            limits: self.config.result_limits,
+            resolved: false,
+            _permit: permit,
+        };
+        IncomingTask {
+            context,
+            assignment,
+            resolver,
+        }
+    }
+}
@@ -0,0 +1,452 @@
+//! Typed tasks, and the options and filters [`ValkaClient`](crate::ValkaClient)
+//! takes for them.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use valka_proto::{CreateTaskRequest, ListTasksRequest, Pagination, SignalInfo, TaskMeta};
+
+pub use valka_proto::{RetryBackoff, TaskEvent, TaskOrder, TaskStatus};
+
+use crate::error::SdkError;
+
+/// A task as the API returns it, with its JSON fields parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub id: String,
+    pub queue_name: String,
+    pub task_name: String,
+    pub status: TaskStatus,
+    pub priority: i32,
+    pub max_retries: i32,
+    pub attempt_count: i32,
+    pub timeout_seconds: i32,
+    pub idempotency_key: Option<String>,
+    pub input: Option<Value>,
+    pub metadata: Option<Value>,
+    /// Set once the task has completed.
+    pub output: Option<Value>,
+    pub error_message: Option<String>,
+    pub scheduled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deadline: Option<DateTime<Utc>>,
+    pub deadline_expired: bool,
+    /// "durable" or "ephemeral".
+    pub durability: String,
+    pub fifo_key: Option<String>,
+    pub callback_url: Option<String>,
+    /// Set when the task was created with its own backoff.
+    pub retry_backoff: Option<RetryBackoff>,
+    /// Labels a worker must carry to run the task; empty for any worker.
+    pub required_labels: HashMap<String, String>,
+}
+
+impl Task {
+    /// Whether the task will not run again: COMPLETED, FAILED, DEAD_LETTER or
+    /// CANCELLED.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self.status,
+            TaskStatus::Completed
+                | TaskStatus::Failed
+                | TaskStatus::DeadLetter
+                | TaskStatus::Cancelled
+        )
+    }
+}
+
+impl TryFrom<TaskMeta> for Task {
+    type Error = SdkError;
+
+    fn try_from(meta: TaskMeta) -> Result<Self, SdkError> {
+        Ok(Self {
+            status: TaskStatus::try_from(meta.status).unwrap_or(TaskStatus::Unspecified),
+            input: json(&meta.input)?,
+            metadata: json(&meta.metadata)?,
+            output: json(&meta.output)?,
+            scheduled_at: non_empty(meta.scheduled_at)
+                .map(|s| timestamp(&s))
+                .transpose()?,
+            created_at: timestamp(&meta.created_at)?,
+            updated_at: timestamp(&meta.updated_at)?,
+            deadline: match meta.deadline_ms {
+                0 => None,
+                ms => DateTime::from_timestamp_millis(ms),
+            },
+            id: meta.id,
+            queue_name: meta.queue_name,
+            task_name: meta.task_name,
+            priority: meta.priority,
+            max_retries: meta.max_retries,
+            attempt_count: meta.attempt_count,
+            timeout_seconds: meta.timeout_seconds,
+            idempotency_key: non_empty(meta.idempotency_key),
+            error_message: non_empty(meta.error_message),
+            deadline_expired: meta.deadline_expired,
+            durability: meta.durability,
+            fifo_key: non_empty(meta.fifo_key),
+            callback_url: non_empty(meta.callback_url),
+            retry_backoff: meta.retry_backoff,
+            required_labels: meta.required_labels,
+        })
+    }
+}
+
+/// A signal sent to a task, as [`ValkaClient::list_signals`](crate::ValkaClient::list_signals)
+/// returns it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    pub id: String,
+    pub task_id: String,
+    pub signal_name: String,
+    pub payload: Option<Value>,
+    /// PENDING, DELIVERED or ACKNOWLEDGED.
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<SignalInfo> for Signal {
+    type Error = SdkError;
+
+    fn try_from(info: SignalInfo) -> Result<Self, SdkError> {
+        Ok(Self {
+            payload: json(&info.payload)?,
+            created_at: timestamp(&info.created_at)?,
+            delivered_at: non_empty(info.delivered_at)
+                .map(|s| timestamp(&s))
+                .transpose()?,
+            acknowledged_at: non_empty(info.acknowledged_at)
+                .map(|s| timestamp(&s))
+                .transpose()?,
+            id: info.id,
+            task_id: info.task_id,
+            signal_name: info.signal_name,
+            status: info.status,
+        })
+    }
+}
+
+fn non_empty(s: String) -> Option<String> {
+    (!s.is_empty()).then_some(s)
+}
+
+fn json(s: &str) -> Result<Option<Value>, SdkError> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(s)?))
+}
+
+fn timestamp(s: &str) -> Result<DateTime<Utc>, SdkError> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| SdkError::InvalidResponse(format!("Invalid timestamp '{s}': {e}")))
+}
+
+/// What [`ValkaClient::create_task`](crate::ValkaClient::create_task) creates.
+/// Retries and timeout left unset take the queue's defaults.
+///
+/// ```
+/// use std::time::Duration;
+/// use valka_sdk::task::CreateTaskOptions;
+///
+/// let options = CreateTaskOptions::new("emails", "send-welcome")
+///     .input(serde_json::json!({ "to": "user@example.com" }))
+///     .priority(5)
+///     .idempotency_key("welcome:user@example.com")
+///     .deadline_in(Duration::from_secs(600));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CreateTaskOptions {
+    queue_name: String,
+    task_name: String,
+    input: Option<Value>,
+    priority: i32,
+    max_retries: i32,
+    timeout_seconds: i32,
+    idempotency_key: Option<String>,
+    metadata: Option<Value>,
+    scheduled_at: Option<DateTime<Utc>>,
+    deadline: Option<Deadline>,
+    ephemeral: bool,
+    fifo_key: Option<String>,
+    callback_url: Option<String>,
+    retry_backoff: Option<RetryBackoff>,
+    required_labels: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Deadline {
+    At(DateTime<Utc>),
+    In(Duration),
+}
+
+impl CreateTaskOptions {
+    pub fn new(queue_name: &str, task_name: &str) -> Self {
+        Self {
+            queue_name: queue_name.to_string(),
+            task_name: task_name.to_string(),
+            input: None,
+            priority: 0,
+            max_retries: 0,
+            timeout_seconds: 0,
+            idempotency_key: None,
+            metadata: None,
+            scheduled_at: None,
+            deadline: None,
+            ephemeral: false,
+            fifo_key: None,
+            callback_url: None,
+            retry_backoff: None,
+            required_labels: HashMap::new(),
+        }
+    }
+
+    pub fn input(mut self, input: Value) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: i32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Per-attempt timeout, rounded up to whole seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+        self.timeout_seconds = i32::try_from(secs).unwrap_or(i32::MAX).max(1);
+        self
+    }
+
+    /// A create repeating the key returns the existing task instead.
+    pub fn idempotency_key(mut self, key: &str) -> Self {
+        self.idempotency_key = Some(key.to_string());
+        self
+    }
+
+    pub fn metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Hold the task until `at`.
+    pub fn scheduled_at(mut self, at: DateTime<Utc>) -> Self {
+        self.scheduled_at = Some(at);
+        self
+    }
+
+    /// Fail the task instead of dispatching it after `at`; workers get the
+    /// remaining budget as their timeout cap.
+    pub fn deadline_at(mut self, at: DateTime<Utc>) -> Self {
+        self.deadline = Some(Deadline::At(at));
+        self
+    }
+
+    /// Like [`deadline_at`](Self::deadline_at), relative to the server's clock.
+    pub fn deadline_in(mut self, within: Duration) -> Self {
+        self.deadline = Some(Deadline::In(within));
+        self
+    }
+
+    /// Keep the task in memory only. The queue must allow ephemeral tasks.
+    pub fn ephemeral(mut self) -> Self {
+        self.ephemeral = true;
+        self
+    }
+
+    /// Ordering group on FIFO queues.
+    pub fn fifo_key(mut self, key: &str) -> Self {
+        self.fifo_key = Some(key.to_string());
+        self
+    }
+
+    /// POSTed the task once it completes, fails or is dead-lettered.
+    pub fn callback_url(mut self, url: &str) -> Self {
+        self.callback_url = Some(url.to_string());
+        self
+    }
+
+    /// Backoff between this task's attempts in place of the scheduler's;
+    /// zero fields keep the scheduler's setting.
+    pub fn retry_backoff(mut self, backoff: RetryBackoff) -> Self {
+        self.retry_backoff = Some(backoff);
+        self
+    }
+
+    /// Only hand the task to workers declaring this label (see
+    /// [`ValkaWorkerBuilder::label`](crate::worker::ValkaWorkerBuilder::label)).
+    pub fn required_label(mut self, key: &str, value: &str) -> Self {
+        self.required_labels
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub(crate) fn into_request(self) -> CreateTaskRequest {
+        let (deadline_ms, deadline_in_ms) = match self.deadline {
+            Some(Deadline::At(at)) => (at.timestamp_millis(), 0),
+            Some(Deadline::In(within)) => (
+                0,
+                i64::try_from(within.as_millis()).unwrap_or(i64::MAX).max(1),
+            ),
+            None => (0, 0),
+        };
+        CreateTaskRequest {
+            queue_name: self.queue_name,
+            task_name: self.task_name,
+            input: self.input.map(|v| v.to_string()).unwrap_or_default(),
+            priority: self.priority,
+            max_retries: self.max_retries,
+            timeout_seconds: self.timeout_seconds,
+            idempotency_key: self.idempotency_key.unwrap_or_default(),
+            metadata: self.metadata.map(|v| v.to_string()).unwrap_or_default(),
+            scheduled_at: self
+                .scheduled_at
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+            deadline_ms,
+            deadline_in_ms,
+            durability: if self.ephemeral { "ephemeral" } else { "" }.to_string(),
+            fifo_key: self.fifo_key.unwrap_or_default(),
+            callback_url: self.callback_url.unwrap_or_default(),
+            retry_backoff: self.retry_backoff,
+            required_labels: self.required_labels,
+        }
+    }
+}
+
+/// Which tasks [`ValkaClient::list_tasks`](crate::ValkaClient::list_tasks)
+/// returns. The default lists the 50 newest tasks.
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    request: ListTasksRequest,
+}
+
+impl TaskFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn queue(mut self, queue_name: &str) -> Self {
+        self.request.queue_name = queue_name.to_string();
+        self
+    }
+
+    /// Match tasks in `status`; repeat to match any of several.
+    pub fn status(mut self, status: TaskStatus) -> Self {
+        self.request.statuses.push(status as i32);
+        self
+    }
+
+    pub fn task_name_prefix(mut self, prefix: &str) -> Self {
+        self.request.task_name_prefix = prefix.to_string();
+        self
+    }
+
+    /// Created at or after `at`.
+    pub fn created_after(mut self, at: DateTime<Utc>) -> Self {
+        self.request.created_after_ms = at.timestamp_millis();
+        self
+    }
+
+    /// Created before `at`.
+    pub fn created_before(mut self, at: DateTime<Utc>) -> Self {
+        self.request.created_before_ms = at.timestamp_millis();
+        self
+    }
+
+    pub fn idempotency_key(mut self, key: &str) -> Self {
+        self.request.idempotency_key = key.to_string();
+        self
+    }
+
+    /// Case-insensitive substring of the error message.
+    pub fn error_contains(mut self, text: &str) -> Self {
+        self.request.error_contains = text.to_string();
+        self
+    }
+
+    pub fn order(mut self, order: TaskOrder) -> Self {
+        self.request.order = order as i32;
+        self
+    }
+
+    pub fn page_size(mut self, page_size: i32) -> Self {
+        self.pagination().page_size = page_size;
+        self
+    }
+
+    /// Continue from a previous page's [`TaskPage::next_page_token`].
+    pub fn page_token(mut self, token: &str) -> Self {
+        self.pagination().page_token = token.to_string();
+        self
+    }
+
+    fn pagination(&mut self) -> &mut Pagination {
+        self.request.pagination.get_or_insert(Pagination {
+            page_size: 50,
+            page_token: String::new(),
+        })
+    }
+
+    pub(crate) fn into_request(self) -> ListTasksRequest {
+        self.request
+    }
+}
+
+/// One page of [`ValkaClient::list_tasks`](crate::ValkaClient::list_tasks).
+#[derive(Debug, Clone)]
+pub struct TaskPage {
+    pub tasks: Vec<Task>,
+    /// Pass to [`TaskFilter::page_token`] for the next page; `None` on the last.
+    pub next_page_token: Option<String>,
+}
+
+/// Which events
+/// [`ValkaClient::subscribe_events`](crate::ValkaClient::subscribe_events)
+/// yields. The default yields every event.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub(crate) queue_name: String,
+    task_id: Option<String>,
+    statuses: Vec<TaskStatus>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn queue(mut self, queue_name: &str) -> Self {
+        self.queue_name = queue_name.to_string();
+        self
+    }
+
+    pub fn task(mut self, task_id: &str) -> Self {
+        self.task_id = Some(task_id.to_string());
+        self
+    }
+
+    /// Only transitions into `status`; repeat to match any of several.
+    pub fn status(mut self, status: TaskStatus) -> Self {
+        self.statuses.push(status);
+        self
+    }
+
+    pub fn matches(&self, event: &TaskEvent) -> bool {
+        (self.queue_name.is_empty() || event.queue_name == self.queue_name)
+            && self.task_id.as_ref().is_none_or(|id| &event.task_id == id)
+            && (self.statuses.is_empty()
+                || self.statuses.iter().any(|s| *s as i32 == event.new_status))
+    }
+}
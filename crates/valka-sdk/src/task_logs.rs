@@ -0,0 +1,235 @@
+//! Handler logs, batched onto the worker's request stream.
+//!
+//! [`TaskContext`](crate::TaskContext) logging methods buffer entries per run
+//! and ship them as `LogBatch` messages every 50 entries or 500ms, and once
+//! more before the task's result. Install [`task_log_layer`] to also forward
+//! `tracing` events emitted while a handler runs.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+use valka_proto::{LogBatch, LogEntry, LogLevel, WorkerRequest, worker_request};
+
+use crate::logging::FieldVisitor;
+
+/// Max entries shipped in a single LogBatch.
+const MAX_BATCH: usize = 50;
+/// How long an entry may wait for others to share its batch.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Runs currently executing, for [`TaskLogLayer`]. Only filled once a layer exists.
+static RUNNING: LazyLock<Mutex<HashMap<String, TaskLogs>>> = LazyLock::new(Mutex::default);
+static LAYER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+enum Command {
+    Entry(LogEntry),
+    Flush(oneshot::Sender<()>),
+}
+
+/// One run's log buffer. The forwarding task starts with the first entry and
+/// ships what is left once every handle is dropped.
+#[derive(Clone)]
+pub(crate) struct TaskLogs {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    task_run_id: String,
+    request_tx: mpsc::Sender<WorkerRequest>,
+    tx: OnceLock<mpsc::UnboundedSender<Command>>,
+}
+
+impl TaskLogs {
+    pub fn new(task_run_id: String, request_tx: mpsc::Sender<WorkerRequest>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                task_run_id,
+                request_tx,
+                tx: OnceLock::new(),
+            }),
+        }
+    }
+
+    pub fn push(&self, level: LogLevel, message: String, metadata: String) {
+        let entry = LogEntry {
+            task_run_id: self.inner.task_run_id.clone(),
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            level: level as i32,
+            message,
+            metadata,
+        };
+        let tx = self.inner.tx.get_or_init(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(forward(rx, self.inner.request_tx.clone()));
+            tx
+        });
+        let _ = tx.send(Command::Entry(entry));
+    }
+
+    /// Returns once everything logged so far is on the request stream.
+    pub async fn flush(&self) {
+        let Some(tx) = self.inner.tx.get() else {
+            return;
+        };
+        let (done_tx, done_rx) = oneshot::channel();
+        if tx.send(Command::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+
+    /// Make this run's buffer reachable from [`TaskLogLayer`] until the
+    /// returned guard drops.
+    pub fn register(&self) -> Option<Registration> {
+        if !LAYER_INSTALLED.load(Ordering::Relaxed) {
+            return None;
+        }
+        let run_id = self.inner.task_run_id.clone();
+        lock_running().insert(run_id.clone(), self.clone());
+        Some(Registration(run_id))
+    }
+}
+
+pub(crate) struct Registration(String);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        lock_running().remove(&self.0);
+    }
+}
+
+fn lock_running() -> std::sync::MutexGuard<'static, HashMap<String, TaskLogs>> {
+    RUNNING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+async fn forward(
+    mut rx: mpsc::UnboundedReceiver<Command>,
+    request_tx: mpsc::Sender<WorkerRequest>,
+) {
+    let mut pending = Vec::new();
+    let mut deadline: Option<Instant> = None;
+    loop {
+        let command = match deadline {
+            Some(at) => tokio::select! {
+                command = rx.recv() => command,
+                () = tokio::time::sleep_until(at) => {
+                    deadline = None;
+                    ship(&mut pending, &request_tx).await;
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+        match command {
+            Some(Command::Entry(entry)) => {
+                pending.push(entry);
+                if pending.len() >= MAX_BATCH {
+                    deadline = None;
+                    ship(&mut pending, &request_tx).await;
+                } else if deadline.is_none() {
+                    deadline = Some(Instant::now() + FLUSH_INTERVAL);
+                }
+            }
+            Some(Command::Flush(done)) => {
+                deadline = None;
+                ship(&mut pending, &request_tx).await;
+                let _ = done.send(());
+            }
+            None => {
+                ship(&mut pending, &request_tx).await;
+                return;
+            }
+        }
+    }
+}
+
+async fn ship(pending: &mut Vec<LogEntry>, request_tx: &mpsc::Sender<WorkerRequest>) {
+    if pending.is_empty() {
+        return;
+    }
+    let request = WorkerRequest {
+        request: Some(worker_request::Request::LogBatch(LogBatch {
+            entries: std::mem::take(pending),
+        })),
+    };
+    let _ = request_tx.send(request).await;
+}
+
+/// Create a `tracing` layer that forwards events at or above `level` emitted
+/// inside a running handler to that task run's logs, with the event's fields
+/// and target as metadata.
+pub fn task_log_layer(level: Level) -> TaskLogLayer {
+    LAYER_INSTALLED.store(true, Ordering::Relaxed);
+    TaskLogLayer { level }
+}
+
+/// See [`task_log_layer`].
+pub struct TaskLogLayer {
+    level: Level,
+}
+
+/// Run id of a worker's `handle_task` span, kept in the span's extensions.
+struct TaskRunId(String);
+
+impl<S> Layer<S> for TaskLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "handle_task" {
+            return;
+        }
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        if let (Some(run_id), Some(span)) = (visitor.fields.remove("task_run_id"), ctx.span(id))
+            && let Some(run_id) = run_id.as_str()
+        {
+            span.extensions_mut().insert(TaskRunId(run_id.to_string()));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        // The SDK's own events are about the worker, not the task
+        if *meta.level() > self.level || meta.target().starts_with("valka_sdk") {
+            return;
+        }
+        let Some(run_id) = ctx.event_scope(event).and_then(|scope| {
+            scope
+                .from_root()
+                .find_map(|span| span.extensions().get::<TaskRunId>().map(|r| r.0.clone()))
+        }) else {
+            return;
+        };
+        let Some(logs) = lock_running().get(&run_id).cloned() else {
+            return;
+        };
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        visitor.fields.insert(
+            "target".to_string(),
+            serde_json::Value::String(meta.target().to_string()),
+        );
+        let level = match *meta.level() {
+            Level::ERROR => LogLevel::Error,
+            Level::WARN => LogLevel::Warn,
+            Level::INFO => LogLevel::Info,
+            _ => LogLevel::Debug,
+        };
+        logs.push(
+            level,
+            visitor.message,
+            serde_json::Value::Object(visitor.fields).to_string(),
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Optional OpenTelemetry export for workers.
+//!
+//! The server sends the W3C traceparent of its dispatch span with every
+//! `TaskAssignment`; the worker runs the handler inside a [`task_span`] parented
+//! under it and echoes the handler span's traceparent back on the `TaskResult`,
+//! so one trace covers create → dispatch → handler → result. Without an
+//! OpenTelemetry layer in the subscriber the spans are plain `tracing` spans.
+//! [`otlp_layer`] needs the `otel` feature (on by default); any other
+//! tracing-opentelemetry layer works as well.
+//!
+//! ```ignore
+//! let (layer, provider) = valka_sdk::telemetry::otlp_layer(&OtlpConfig::default())?;
+//! tracing_subscriber::registry().with(layer).init();
+//! let worker = ValkaWorker::builder().trace_export(provider) /* ... */;
+//! ```
+
+use std::collections::HashMap;
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::{Span, info_span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use valka_proto::TaskAssignment;
+
+#[cfg(feature = "otel")]
+use {
+    crate::error::SdkError,
+    opentelemetry::trace::TracerProvider as _,
+    opentelemetry_otlp::WithExportConfig,
+    opentelemetry_sdk::Resource,
+    opentelemetry_sdk::runtime,
+    opentelemetry_sdk::trace::{
+        Sampler, SdkTracer, SdkTracerProvider, span_processor_with_async_runtime,
+    },
+    tracing::Subscriber,
+    tracing_opentelemetry::OpenTelemetryLayer,
+    tracing_subscriber::registry::LookupSpan,
+};
+
+const TRACEPARENT: &str = "traceparent";
+
+/// OTLP/gRPC export settings.
+#[cfg(feature = "otel")]
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    /// Collector endpoint, e.g. `http://tempo:4317`.
+    pub endpoint: String,
+    /// Fraction of new traces that are sampled; tasks dispatched under a
+    /// sampled server trace are always recorded.
+    pub sampling_ratio: f64,
+    pub service_name: String,
+}
+
+#[cfg(feature = "otel")]
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://127.0.0.1:4317".to_string(),
+            sampling_ratio: 1.0,
+            service_name: "valka-worker".to_string(),
+        }
+    }
+}
+
+/// Create a `tracing` layer exporting spans over OTLP, plus the provider
+/// backing it. Hand the provider to
+/// [`ValkaWorkerBuilder::trace_export`](crate::worker::ValkaWorkerBuilder::trace_export)
+/// so buffered spans are flushed when the worker stops. Must be called from
+/// within the tokio runtime.
+#[cfg(feature = "otel")]
+pub fn otlp_layer<S>(
+    config: &OtlpConfig,
+) -> Result<(OpenTelemetryLayer<S, SdkTracer>, SdkTracerProvider), SdkError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(config.endpoint.clone())
+        .build()
+        .map_err(|e| SdkError::Connection(e.to_string()))?;
+    let processor =
+        span_processor_with_async_runtime::BatchSpanProcessor::builder(exporter, runtime::Tokio)
+            .build();
+    let provider = SdkTracerProvider::builder()
+        .with_span_processor(processor)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sampling_ratio.clamp(0.0, 1.0),
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("valka-sdk"));
+    Ok((layer, provider))
+}
+
+/// The span a task's handler runs in, parented under the assignment's traceparent.
+pub fn task_span(assignment: &TaskAssignment) -> Span {
+    let span = info_span!(
+        "handle_task",
+        task_id = %assignment.task_id,
+        task_run_id = %assignment.task_run_id,
+        queue = %assignment.queue_name,
+        task_name = %assignment.task_name,
+        attempt = assignment.attempt_number,
+    );
+    if !assignment.traceparent.is_empty() {
+        let carrier = HashMap::from([(TRACEPARENT.to_string(), assignment.traceparent.clone())]);
+        let cx = TraceContextPropagator::new().extract(&carrier);
+        if cx.span().span_context().is_valid() {
+            let _ = span.set_parent(cx);
+        }
+    }
+    span
+}
+
+/// W3C traceparent of `span`, or empty when it is not part of an exported trace.
+pub fn traceparent(span: &Span) -> String {
+    let cx = span.context();
+    if !cx.span().span_context().is_valid() {
+        return String::new();
+    }
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&cx, &mut carrier);
+    carrier.remove(TRACEPARENT).unwrap_or_default()
+}
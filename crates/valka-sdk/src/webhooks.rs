@@ -0,0 +1,238 @@
+//! Verification of signed task lifecycle webhooks.
+//!
+//! Each delivery carries the Unix timestamp it was signed at in
+//! `valka-timestamp` and `v1=<hex>` HMAC-SHA256 signatures of
+//! `"{timestamp}.{body}"` in `valka-signature` (several, comma separated,
+//! while a secret is being rotated). [`verify_and_parse`] checks the signature
+//! in constant time, rejects deliveries outside the replay tolerance and
+//! deserializes the [`TaskWebhookEvent`].
+//!
+//! Dropping it into an axum service as an extractor:
+//!
+//! ```
+//! use axum::body::Bytes;
+//! use axum::extract::{FromRef, FromRequest, Request};
+//! use axum::http::StatusCode;
+//! use axum::{Router, routing::post};
+//! use valka_sdk::webhooks::{TaskWebhookEvent, WebhookVerifier};
+//!
+//! struct ValkaWebhook(TaskWebhookEvent);
+//!
+//! impl<S> FromRequest<S> for ValkaWebhook
+//! where
+//!     S: Send + Sync,
+//!     WebhookVerifier: FromRef<S>,
+//! {
+//!     type Rejection = (StatusCode, String);
+//!
+//!     async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+//!         let verifier = WebhookVerifier::from_ref(state);
+//!         let headers = req.headers().clone();
+//!         let body = Bytes::from_request(req, state)
+//!             .await
+//!             .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+//!         verifier
+//!             .verify_and_parse(&headers, &body)
+//!             .map(ValkaWebhook)
+//!             .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))
+//!     }
+//! }
+//!
+//! async fn on_task_event(ValkaWebhook(event): ValkaWebhook) -> StatusCode {
+//!     println!("{} is now {}", event.task_id, event.status);
+//!     StatusCode::NO_CONTENT
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/hooks/valka", post(on_task_event))
+//!     .with_state(WebhookVerifier::new("whsec_example"));
+//! ```
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+
+/// Header holding the Unix timestamp (seconds) the delivery was signed at.
+pub const TIMESTAMP_HEADER: &str = "valka-timestamp";
+/// Header holding one or more `v1=<hex>` signatures.
+pub const SIGNATURE_HEADER: &str = "valka-signature";
+/// Deliveries signed longer ago than this are rejected as possible replays.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+const SIGNATURE_SCHEME: &str = "v1";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Payload of a task lifecycle webhook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskWebhookEvent {
+    /// Lifecycle event, e.g. `task.completed` or `task.dead_lettered`.
+    pub event_type: String,
+    pub task_id: String,
+    pub queue_name: String,
+    pub task_name: String,
+    pub status: String,
+    pub attempt: i32,
+    #[serde(default)]
+    pub output: Option<serde_json::Value>,
+    #[serde(default)]
+    pub error_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Missing webhook header: {0}")]
+    MissingHeader(&'static str),
+
+    #[error("Malformed webhook header: {0}")]
+    MalformedHeader(&'static str),
+
+    #[error("Webhook signature does not match")]
+    InvalidSignature,
+
+    #[error("Webhook timestamp is {age_secs}s old, outside the {tolerance_secs}s tolerance")]
+    Stale { age_secs: i64, tolerance_secs: u64 },
+
+    #[error("Invalid webhook payload: {0}")]
+    Payload(#[from] serde_json::Error),
+}
+
+/// Verifies deliveries signed with a shared secret.
+#[derive(Clone)]
+pub struct WebhookVerifier {
+    secret: Vec<u8>,
+    tolerance: Duration,
+}
+
+impl WebhookVerifier {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Maximum distance between the signing timestamp and now, either way.
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Verify a delivery and deserialize its event.
+    pub fn verify_and_parse(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<TaskWebhookEvent, WebhookError> {
+        self.verify_and_parse_at(headers, body, Utc::now())
+    }
+
+    /// [`verify_and_parse`](Self::verify_and_parse) against an explicit clock.
+    pub fn verify_and_parse_at(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: DateTime<Utc>,
+    ) -> Result<TaskWebhookEvent, WebhookError> {
+        self.verify_at(headers, body, now)?;
+        Ok(serde_json::from_slice(body)?)
+    }
+
+    /// Check the signature and timestamp without parsing the body.
+    pub fn verify_at(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: DateTime<Utc>,
+    ) -> Result<(), WebhookError> {
+        let timestamp = header_str(headers, TIMESTAMP_HEADER)?;
+        let signed_at: i64 = timestamp
+            .parse()
+            .map_err(|_| WebhookError::MalformedHeader(TIMESTAMP_HEADER))?;
+
+        let mut signatures = header_str(headers, SIGNATURE_HEADER)?
+            .split(',')
+            .filter_map(|part| part.trim().split_once('='))
+            .filter(|(scheme, _)| *scheme == SIGNATURE_SCHEME)
+            .map(|(_, sig)| hex::decode(sig))
+            .peekable();
+        if signatures.peek().is_none() {
+            return Err(WebhookError::MalformedHeader(SIGNATURE_HEADER));
+        }
+        let mac = self.mac(timestamp, body);
+        let matched = signatures
+            .flatten()
+            .any(|sig| mac.clone().verify_slice(&sig).is_ok());
+        if !matched {
+            return Err(WebhookError::InvalidSignature);
+        }
+
+        let age_secs = now.timestamp() - signed_at;
+        let tolerance_secs = self.tolerance.as_secs();
+        if age_secs.unsigned_abs() > tolerance_secs {
+            return Err(WebhookError::Stale {
+                age_secs,
+                tolerance_secs,
+            });
+        }
+        Ok(())
+    }
+
+    fn mac(&self, timestamp: &str, body: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key size");
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac
+    }
+}
+
+/// Verify a delivery with the default tolerance and deserialize its event.
+pub fn verify_and_parse(
+    headers: &HeaderMap,
+    body: &[u8],
+    secret: impl AsRef<[u8]>,
+) -> Result<TaskWebhookEvent, WebhookError> {
+    WebhookVerifier::new(secret).verify_and_parse(headers, body)
+}
+
+/// `valka-signature` value for `body` signed at `timestamp`.
+pub fn sign(secret: impl AsRef<[u8]>, timestamp: DateTime<Utc>, body: &[u8]) -> String {
+    let mac = WebhookVerifier::new(secret).mac(&timestamp.timestamp().to_string(), body);
+    format!(
+        "{SIGNATURE_SCHEME}={}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// Headers of a delivery of `body` signed at `timestamp`, for unit testing
+/// webhook handlers.
+pub fn signed_headers(
+    secret: impl AsRef<[u8]>,
+    timestamp: DateTime<Utc>,
+    body: &[u8],
+) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(TIMESTAMP_HEADER, HeaderValue::from(timestamp.timestamp()));
+    let signature = sign(secret, timestamp, body);
+    headers.insert(
+        SIGNATURE_HEADER,
+        HeaderValue::from_str(&signature).expect("hex signature is a valid header"),
+    );
+    headers
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<&'a str, WebhookError> {
+    headers
+        .get(name)
+        .ok_or(WebhookError::MissingHeader(name))?
+        .to_str()
+        .map_err(|_| WebhookError::MalformedHeader(name))
+}
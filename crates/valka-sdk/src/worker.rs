@@ -1,37 +1,109 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures::StreamExt;
+use opentelemetry_sdk::trace::SdkTracerProvider;
 use tokio::sync::{Mutex, Notify, mpsc};
 use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
 use tonic::transport::Channel;
-use tracing::{error, info, warn};
+use tracing::{Instrument, error, info, warn};
 use uuid::Uuid;
 
 use valka_proto::worker_service_client::WorkerServiceClient;
 use valka_proto::*;
 use valka_proto::{worker_request, worker_response};
 
+use crate::auth::BearerToken;
+use crate::batch::{self, BatchConfig, BatchHandler, PendingBatch};
 use crate::context::TaskContext;
 use crate::error::SdkError;
+use crate::handlers::{HandlerRegistry, TaskError};
+use crate::handshake;
+use crate::limits::ResultLimits;
+use crate::logging::WorkerLogSink;
 use crate::retry::RetryPolicy;
+use crate::stream::{StreamConfig, ValkaTaskStream, rejection};
+use crate::telemetry;
 
 pub type TaskHandler = Arc<
-    dyn Fn(TaskContext) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, String>> + Send>>
+    dyn Fn(
+            TaskContext,
+        ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value, TaskError>> + Send>>
         + Send
         + Sync,
 >;
 
+/// Every task this worker holds, by task id.
+pub(crate) type ActiveTasks = Arc<Mutex<HashMap<String, ActiveTask>>>;
+pub(crate) type SignalSenders = Arc<Mutex<HashMap<String, mpsc::Sender<TaskSignal>>>>;
+
+/// How long a shutting-down worker waits for its tasks by default, inside
+/// the 30s Kubernetes gives a pod between SIGTERM and SIGKILL.
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(25);
+/// How long after the drain to wait for the server to confirm it has every
+/// result by closing the stream.
+const DRAIN_CLOSE_GRACE: Duration = Duration::from_secs(3);
+
+pub(crate) struct ActiveTask {
+    pub task_run_id: String,
+    /// Fired when the server cancels the task
+    pub cancel: CancellationToken,
+}
+
+#[derive(Clone)]
+enum WorkerHandler {
+    Single(Arc<HandlerRegistry>),
+    Batch(BatchConfig),
+}
+
+/// A queue given to [`ValkaWorkerBuilder::queues`]: a name, or a name and the
+/// most tasks of that queue the worker runs at once.
+pub trait WorkerQueue {
+    fn name(&self) -> &str;
+
+    fn concurrency(&self) -> Option<i32> {
+        None
+    }
+}
+
+impl WorkerQueue for &str {
+    fn name(&self) -> &str {
+        self
+    }
+}
+
+impl WorkerQueue for (&str, i32) {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn concurrency(&self) -> Option<i32> {
+        Some(self.1)
+    }
+}
+
 /// Builder for creating a ValkaWorker.
 pub struct ValkaWorkerBuilder {
     name: String,
     server_addr: String,
     queues: Vec<String>,
     concurrency: i32,
-    handler: Option<TaskHandler>,
+    queue_concurrency: HashMap<String, i32>,
+    handlers: HandlerRegistry,
+    batch: Option<BatchConfig>,
     metadata: String,
+    version: String,
+    labels: HashMap<String, String>,
+    log_sink: Option<WorkerLogSink>,
+    tracer_provider: Option<SdkTracerProvider>,
+    token: BearerToken,
+    drain_timeout: Duration,
+    heartbeat_interval: Option<Duration>,
+    result_limits: ResultLimits,
 }
 
 impl ValkaWorkerBuilder {
@@ -41,8 +113,18 @@ impl ValkaWorkerBuilder {
             server_addr: "http://127.0.0.1:50051".to_string(),
             queues: vec![],
             concurrency: 1,
-            handler: None,
+            queue_concurrency: HashMap::new(),
+            handlers: HandlerRegistry::default(),
+            batch: None,
             metadata: String::new(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            labels: HashMap::new(),
+            log_sink: None,
+            tracer_provider: None,
+            token: BearerToken::default(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            heartbeat_interval: None,
+            result_limits: ResultLimits::default(),
         }
     }
 
@@ -56,8 +138,29 @@ impl ValkaWorkerBuilder {
         self
     }
 
-    pub fn queues(mut self, queues: &[&str]) -> Self {
-        self.queues = queues.iter().map(|s| s.to_string()).collect();
+    /// Bearer token presented when opening the session: a worker token or an API token.
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = BearerToken::new(token);
+        self
+    }
+
+    /// Queues to take tasks from, as names or `(name, concurrency)` pairs. A
+    /// pair caps how many of that queue's tasks run at once, within the
+    /// worker's overall [`concurrency`](Self::concurrency).
+    ///
+    /// ```
+    /// use valka_sdk::ValkaWorker;
+    ///
+    /// let builder = ValkaWorker::builder()
+    ///     .queues(&[("video.encode", 2), ("email.send", 10)])
+    ///     .concurrency(12);
+    /// ```
+    pub fn queues<Q: WorkerQueue>(mut self, queues: &[Q]) -> Self {
+        self.queues = queues.iter().map(|q| q.name().to_string()).collect();
+        self.queue_concurrency = queues
+            .iter()
+            .filter_map(|q| Some((q.name().to_string(), q.concurrency()?)))
+            .collect();
         self
     }
 
@@ -66,12 +169,52 @@ impl ValkaWorkerBuilder {
         self
     }
 
+    /// Handle every task not routed by [`Self::register`]. String errors
+    /// convert into retryable [`TaskError`]s, so `?` works on them.
     pub fn handler<F, Fut>(mut self, f: F) -> Self
     where
         F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Result<serde_json::Value, String>> + Send + 'static,
+        Fut: Future<Output = Result<serde_json::Value, TaskError>> + Send + 'static,
+    {
+        let handler: TaskHandler = Arc::new(move |ctx| Box::pin(f(ctx)));
+        self.handlers.fallback = Some(handler);
+        self.batch = None;
+        self
+    }
+
+    /// Handle tasks named `task_name` with `f`, which gets the input
+    /// deserialized as `I` and returns an output serialized back to JSON.
+    /// Input that doesn't deserialize fails the task without retry. Register
+    /// several names on one worker; other names go to [`Self::handler`], or
+    /// fail without retry if there is none.
+    pub fn register<I, O, F, Fut>(mut self, task_name: &str, f: F) -> Self
+    where
+        I: serde::de::DeserializeOwned + Send + 'static,
+        O: serde::Serialize + Send + 'static,
+        F: Fn(TaskContext, I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<O, TaskError>> + Send + 'static,
+    {
+        self.handlers.register(task_name, f);
+        self
+    }
+
+    /// Handle tasks in batches instead of one at a time. Assignments are
+    /// collected until `max_batch` arrive or `max_wait` passes since the first,
+    /// then `f` gets them all and returns one result per task, positionally.
+    /// Each batched task holds one concurrency slot. Replaces [`Self::handler`]
+    /// and can't be combined with [`Self::register`].
+    pub fn batch_handler<F, Fut>(mut self, max_batch: usize, max_wait: Duration, f: F) -> Self
+    where
+        F: Fn(Vec<TaskContext>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Vec<Result<serde_json::Value, TaskError>>> + Send + 'static,
     {
-        self.handler = Some(Arc::new(move |ctx| Box::pin(f(ctx))));
+        let handler: BatchHandler = Arc::new(move |batch| Box::pin(f(batch)));
+        self.batch = Some(BatchConfig {
+            handler,
+            max_batch,
+            max_wait,
+        });
+        self.handlers.fallback = None;
         self
     }
 
@@ -80,10 +223,81 @@ impl ValkaWorkerBuilder {
         self
     }
 
+    /// Semver of this worker build, checked against a queue's `min_worker_version`.
+    /// Defaults to the SDK version; pass your own crate's `env!("CARGO_PKG_VERSION")`.
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    /// Declare a capability (`gpu=true`, `region=eu`). Tasks created with
+    /// required labels only go to workers carrying all of them.
+    pub fn label(mut self, key: &str, value: &str) -> Self {
+        self.labels.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Forward worker-level logs captured by a [`crate::logging::WorkerLogLayer`] to the server.
+    pub fn forward_logs(mut self, sink: WorkerLogSink) -> Self {
+        self.log_sink = Some(sink);
+        self
+    }
+
+    /// Flush spans exported through this provider (see [`crate::telemetry::otlp_layer`])
+    /// when the worker stops.
+    pub fn trace_export(mut self, provider: SdkTracerProvider) -> Self {
+        self.tracer_provider = Some(provider);
+        self
+    }
+
+    /// How long to let in-flight tasks finish on shutdown (SIGTERM, SIGINT or
+    /// [`ShutdownHandle`]). Tasks still running after it are reported as
+    /// retryable failures so the server requeues them right away. Default 25s.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Ask the server to heartbeat this often instead of at its configured
+    /// interval. It grants faster intervals (down to 1s), not slower ones.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = Some(interval);
+        self
+    }
+
+    /// Largest task output to report, as serialized JSON. A handler returning
+    /// more fails its task without a retry. Default 1 MiB; keep it within the
+    /// server's `limits.max_output_bytes`, which drops larger outputs.
+    pub fn max_output_bytes(mut self, bytes: usize) -> Self {
+        self.result_limits.max_output_bytes = bytes;
+        self
+    }
+
+    /// Longest error message to report; longer ones are cut short. Default 16 KiB.
+    pub fn max_error_message_bytes(mut self, bytes: usize) -> Self {
+        self.result_limits.max_error_message_bytes = bytes;
+        self
+    }
+
     pub async fn build(self) -> Result<ValkaWorker, SdkError> {
-        let handler = self
-            .handler
-            .ok_or_else(|| SdkError::Handler("No handler provided".to_string()))?;
+        validate_queue_concurrency(&self.queue_concurrency)?;
+        let handler = match self.batch {
+            Some(batch) if batch.max_batch < 1 => {
+                return Err(SdkError::Handler(
+                    "Batch size must be at least 1".to_string(),
+                ));
+            }
+            Some(_) if self.handlers.has_named() => {
+                return Err(SdkError::Handler(
+                    "A batch handler can't be combined with registered task handlers".to_string(),
+                ));
+            }
+            Some(batch) => WorkerHandler::Batch(batch),
+            None if self.handlers.is_empty() => {
+                return Err(SdkError::Handler("No handler provided".to_string()));
+            }
+            None => WorkerHandler::Single(Arc::new(self.handlers)),
+        };
 
         Ok(ValkaWorker {
             worker_id: Uuid::now_v7().to_string(),
@@ -91,11 +305,56 @@ impl ValkaWorkerBuilder {
             server_addr: self.server_addr,
             queues: self.queues,
             concurrency: self.concurrency,
+            queue_concurrency: self.queue_concurrency,
             handler,
             metadata: self.metadata,
+            version: self.version,
+            labels: self.labels,
+            log_sink: self.log_sink,
+            tracer_provider: self.tracer_provider,
+            token: self.token,
+            drain_timeout: self.drain_timeout,
+            heartbeat_interval: self.heartbeat_interval,
+            result_limits: self.result_limits,
             shutdown: Arc::new(Notify::new()),
         })
     }
+
+    /// Connect without a handler and yield assignments as a [`ValkaTaskStream`].
+    /// Any handler set on the builder is ignored.
+    pub async fn build_stream(self) -> Result<ValkaTaskStream, SdkError> {
+        if self.concurrency < 1 {
+            return Err(SdkError::Handler(
+                "Concurrency must be at least 1".to_string(),
+            ));
+        }
+        validate_queue_concurrency(&self.queue_concurrency)?;
+
+        Ok(ValkaTaskStream::spawn(StreamConfig {
+            worker_id: Uuid::now_v7().to_string(),
+            name: self.name,
+            server_addr: self.server_addr,
+            queues: self.queues,
+            concurrency: self.concurrency,
+            queue_concurrency: self.queue_concurrency,
+            metadata: self.metadata,
+            version: self.version,
+            labels: self.labels,
+            log_sink: self.log_sink,
+            token: self.token,
+            heartbeat_interval: self.heartbeat_interval,
+            result_limits: self.result_limits,
+        }))
+    }
+}
+
+fn validate_queue_concurrency(caps: &HashMap<String, i32>) -> Result<(), SdkError> {
+    match caps.iter().find(|(_, cap)| **cap < 1) {
+        Some((queue, _)) => Err(SdkError::Handler(format!(
+            "Concurrency for queue {queue} must be at least 1"
+        ))),
+        None => Ok(()),
+    }
 }
 
 impl Default for ValkaWorkerBuilder {
@@ -106,7 +365,7 @@ impl Default for ValkaWorkerBuilder {
 
 /// Handle to request graceful shutdown of a running worker.
 #[derive(Clone)]
-pub struct ShutdownHandle(Arc<Notify>);
+pub struct ShutdownHandle(pub(crate) Arc<Notify>);
 
 impl ShutdownHandle {
     /// Signal the worker to shut down gracefully, draining in-flight tasks.
@@ -122,8 +381,17 @@ pub struct ValkaWorker {
     server_addr: String,
     queues: Vec<String>,
     concurrency: i32,
-    handler: TaskHandler,
+    queue_concurrency: HashMap<String, i32>,
+    handler: WorkerHandler,
     metadata: String,
+    version: String,
+    labels: HashMap<String, String>,
+    log_sink: Option<WorkerLogSink>,
+    tracer_provider: Option<SdkTracerProvider>,
+    token: BearerToken,
+    drain_timeout: Duration,
+    heartbeat_interval: Option<Duration>,
+    result_limits: ResultLimits,
     shutdown: Arc<Notify>,
 }
 
@@ -145,6 +413,11 @@ impl ValkaWorker {
             match self.connect_and_run(&mut retry_policy).await {
                 Ok(()) => {
                     info!("Worker disconnected gracefully");
+                    if let Some(provider) = &self.tracer_provider
+                        && let Err(e) = provider.force_flush()
+                    {
+                        warn!(error = %e, "Failed to flush trace exporter");
+                    }
                     return Ok(());
                 }
                 Err(e) => {
@@ -169,7 +442,7 @@ impl ValkaWorker {
             .connect()
             .await?;
 
-        let mut client = WorkerServiceClient::new(channel);
+        let mut client = WorkerServiceClient::with_interceptor(channel, self.token.clone());
 
         // Set up bidirectional stream
         let (request_tx, request_rx) = mpsc::channel::<WorkerRequest>(256);
@@ -188,30 +461,38 @@ impl ValkaWorker {
                 worker_name: self.name.clone(),
                 queues: self.queues.clone(),
                 concurrency: self.concurrency,
+                queue_concurrency: self.queue_concurrency.clone(),
                 metadata: self.metadata.clone(),
+                version: self.version.clone(),
+                labels: self.labels.clone(),
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: handshake::capabilities(),
+                heartbeat_interval_ms: handshake::requested_interval_ms(self.heartbeat_interval),
             })),
         };
         request_tx
             .send(hello)
             .await
             .map_err(|_| SdkError::NotConnected)?;
+        let session = handshake::await_hello_ack(&mut inbound, self.heartbeat_interval).await?;
+        let mut inbound = futures::stream::iter(session.replay.map(Ok)).chain(inbound);
 
         // Shared active task tracking
-        let active_tasks: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+        let active_tasks: ActiveTasks = Arc::new(Mutex::new(HashMap::new()));
         // Signal senders for routing signals to task contexts
-        let signal_senders: Arc<Mutex<HashMap<String, mpsc::Sender<TaskSignal>>>> =
-            Arc::new(Mutex::new(HashMap::new()));
+        let signal_senders: SignalSenders = Arc::new(Mutex::new(HashMap::new()));
 
         // Start heartbeat loop
         let hb_tx = request_tx.clone();
         let hb_active = active_tasks.clone();
+        let hb_every = session.heartbeat_interval;
         let hb_handle = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(10));
+            let mut interval = tokio::time::interval(hb_every);
             loop {
                 interval.tick().await;
                 let task_ids: Vec<String> = {
                     let guard = hb_active.lock().await;
-                    guard.iter().cloned().collect()
+                    guard.keys().cloned().collect()
                 };
                 let hb = WorkerRequest {
                     request: Some(worker_request::Request::Heartbeat(Heartbeat {
@@ -225,20 +506,39 @@ impl ValkaWorker {
             }
         });
 
+        // Ship forwarded worker logs on this connection
+        let log_handle = self.log_sink.clone().map(|sink| {
+            tokio::spawn(sink.ship(
+                self.worker_id.clone(),
+                self.name.clone(),
+                request_tx.clone(),
+            ))
+        });
+
         // Process incoming messages
         let semaphore = Arc::new(tokio::sync::Semaphore::new(self.concurrency as usize));
+        let mut pending_batch = PendingBatch::default();
+        let mut sigterm = Sigterm::new();
 
-        loop {
+        // Set to the reason once a graceful shutdown is asked for
+        let stop = loop {
             tokio::select! {
                 msg = inbound.next() => {
                     match msg {
                         Some(Ok(response)) => {
                             match response.response {
                                 Some(worker_response::Response::TaskAssignment(assignment)) => {
-                                    // Track active task
+                                    // Track active task; heartbeats cover tasks waiting in a batch too
+                                    let cancel = CancellationToken::new();
                                     {
                                         let mut guard = active_tasks.lock().await;
-                                        guard.insert(assignment.task_id.clone());
+                                        guard.insert(
+                                            assignment.task_id.clone(),
+                                            ActiveTask {
+                                                task_run_id: assignment.task_run_id.clone(),
+                                                cancel: cancel.clone(),
+                                            },
+                                        );
                                     }
 
                                     // Create signal channel for this task
@@ -248,16 +548,35 @@ impl ValkaWorker {
                                         sigs.insert(assignment.task_id.clone(), sig_tx);
                                     }
 
+                                    // A batch holding every slot cannot grow any further
+                                    if !pending_batch.is_empty() && semaphore.available_permits() == 0 {
+                                        self.flush_batch(&mut pending_batch, &request_tx, &active_tasks, &signal_senders);
+                                    }
                                     let permit = semaphore.clone().acquire_owned().await
                                         .map_err(|_| SdkError::ShuttingDown)?;
-                                    let handler = self.handler.clone();
+                                    let handlers = match &self.handler {
+                                        WorkerHandler::Single(handlers) => handlers.clone(),
+                                        WorkerHandler::Batch(config) => {
+                                            pending_batch.push(assignment, sig_rx, cancel, permit, config.max_wait);
+                                            if pending_batch.len() >= config.max_batch {
+                                                self.flush_batch(&mut pending_batch, &request_tx, &active_tasks, &signal_senders);
+                                            }
+                                            continue;
+                                        }
+                                    };
                                     let tx = request_tx.clone();
                                     let active = active_tasks.clone();
                                     let sigs = signal_senders.clone();
+                                    let limits = self.result_limits;
                                     tokio::spawn(async move {
                                         let task_id = assignment.task_id.clone();
                                         let task_run_id = assignment.task_run_id.clone();
+                                        let limit = execution_timeout(
+                                            assignment.timeout_seconds,
+                                            assignment.remaining_budget_ms,
+                                        );
 
+                                        let span = telemetry::task_span(&assignment);
                                         let ctx = TaskContext::new(
                                             assignment.task_id.clone(),
                                             assignment.task_run_id.clone(),
@@ -268,31 +587,38 @@ impl ValkaWorker {
                                             assignment.metadata,
                                             tx.clone(),
                                             sig_rx,
-                                        );
-
-                                        let result = handler(ctx).await;
-
-                                        let task_result = match result {
-                                            Ok(output) => TaskResult {
-                                                task_id: task_id.clone(),
-                                                task_run_id,
-                                                success: true,
-                                                retryable: false,
-                                                output: output.to_string(),
-                                                error_message: String::new(),
-                                            },
-                                            Err(err) => TaskResult {
-                                                task_id: task_id.clone(),
-                                                task_run_id,
-                                                success: false,
-                                                retryable: true,
-                                                output: String::new(),
-                                                error_message: err,
-                                            },
+                                        )
+                                        .with_schedule(
+                                            assignment.priority,
+                                            assignment.created_at_ms,
+                                            assignment.scheduled_at_ms,
+                                        )
+                                        .with_cancellation(cancel.clone());
+
+                                        let logs = ctx.logs().clone();
+                                        let registration = logs.register();
+                                        let run = handlers.call(ctx).instrument(span.clone());
+                                        let result = match limit {
+                                            Some(limit) => {
+                                                match tokio::time::timeout(limit, run).await {
+                                                    Ok(result) => result,
+                                                    Err(_) => Err(TaskError::retryable(format!(
+                                                        "Task timed out after {}ms",
+                                                        limit.as_millis()
+                                                    ))),
+                                                }
+                                            }
+                                            None => run.await,
                                         };
-
-                                        let request = WorkerRequest {
-                                            request: Some(worker_request::Request::TaskResult(task_result)),
+                                        let traceparent = telemetry::traceparent(&span);
+                                        drop(span);
+                                        logs.flush().await;
+                                        drop(registration);
+
+                                        let request = if cancel.is_cancelled() {
+                                            cancelled_result(task_id.clone(), task_run_id)
+                                        } else {
+                                            task_result(task_id.clone(), task_run_id, result, traceparent, limits)
                                         };
                                         let _ = tx.send(request).await;
 
@@ -311,10 +637,13 @@ impl ValkaWorker {
                                 }
                                 Some(worker_response::Response::TaskCancellation(cancel)) => {
                                     info!(task_id = %cancel.task_id, "Task cancelled by server");
-                                    // Remove from active tasks and signal senders
+                                    pending_batch.remove(&cancel.task_id);
+                                    // Stop the handler and forget the task
                                     {
                                         let mut guard = active_tasks.lock().await;
-                                        guard.remove(&cancel.task_id);
+                                        if let Some(task) = guard.remove(&cancel.task_id) {
+                                            task.cancel.cancel();
+                                        }
                                     }
                                     {
                                         let mut guard = signal_senders.lock().await;
@@ -329,53 +658,304 @@ impl ValkaWorker {
                                         warn!("Signal channel closed for task");
                                     }
                                 }
-                                Some(worker_response::Response::HeartbeatAck(_)) => {}
+                                Some(worker_response::Response::HeartbeatAck(_))
+                                | Some(worker_response::Response::HelloAck(_)) => {}
                                 Some(worker_response::Response::ServerShutdown(shutdown)) => {
+                                    // A drain window means results are still accepted; the
+                                    // server closes the stream once our tasks are done
+                                    if shutdown.drain_seconds > 0 {
+                                        info!(
+                                            reason = %shutdown.reason,
+                                            drain_seconds = shutdown.drain_seconds,
+                                            "Server shutting down; finishing in-flight tasks"
+                                        );
+                                        continue;
+                                    }
                                     info!(reason = %shutdown.reason, "Server shutting down");
-                                    break;
+                                    break None;
                                 }
                                 None => {}
                             }
                         }
                         Some(Err(e)) => {
                             error!(error = %e, "Stream error");
-                            break;
+                            break None;
                         }
                         None => {
                             info!("Server closed stream");
-                            break;
+                            break None;
                         }
                     }
                 }
+                _ = tokio::time::sleep_until(pending_batch.flush_at().unwrap_or_else(tokio::time::Instant::now)),
+                    if pending_batch.flush_at().is_some() => {
+                    self.flush_batch(&mut pending_batch, &request_tx, &active_tasks, &signal_senders);
+                }
                 _ = tokio::signal::ctrl_c() => {
                     info!("SIGINT received, shutting down gracefully");
-                    let shutdown = WorkerRequest {
-                        request: Some(worker_request::Request::Shutdown(GracefulShutdown {
-                            reason: "SIGINT".to_string(),
-                        })),
-                    };
-                    let _ = request_tx.send(shutdown).await;
-                    // Wait for in-flight tasks
-                    let _ = semaphore.acquire_many(self.concurrency as u32).await;
-                    hb_handle.abort();
-                    return Ok(());
+                    break Some("SIGINT");
+                }
+                () = sigterm.recv() => {
+                    info!("SIGTERM received, shutting down gracefully");
+                    break Some("SIGTERM");
                 }
                 _ = self.shutdown.notified() => {
                     info!("Shutdown requested via handle, draining gracefully");
-                    let shutdown = WorkerRequest {
-                        request: Some(worker_request::Request::Shutdown(GracefulShutdown {
-                            reason: "shutdown_handle".to_string(),
-                        })),
+                    break Some("shutdown_handle");
+                }
+            }
+        };
+
+        let Some(reason) = stop else {
+            hb_handle.abort();
+            abort_log_shipper(&log_handle);
+            return Err(SdkError::Connection("Stream closed".to_string()));
+        };
+        let shutdown = WorkerRequest {
+            request: Some(worker_request::Request::Shutdown(GracefulShutdown {
+                reason: reason.to_string(),
+            })),
+        };
+        let _ = request_tx.send(shutdown).await;
+        self.flush_batch(
+            &mut pending_batch,
+            &request_tx,
+            &active_tasks,
+            &signal_senders,
+        );
+        self.drain(
+            &mut inbound,
+            &request_tx,
+            &semaphore,
+            &active_tasks,
+            &signal_senders,
+        )
+        .await;
+        hb_handle.abort();
+        abort_log_shipper(&log_handle);
+        Ok(())
+    }
+
+    /// Wait for in-flight tasks after a graceful shutdown was sent. Tasks
+    /// assigned meanwhile are handed back; any still running after
+    /// `drain_timeout` are failed retryably so they needn't wait out their
+    /// lease. Returns once the server, holding every result, closes the stream.
+    async fn drain(
+        &self,
+        inbound: &mut (impl futures::Stream<Item = Result<WorkerResponse, tonic::Status>> + Unpin),
+        request_tx: &mpsc::Sender<WorkerRequest>,
+        semaphore: &tokio::sync::Semaphore,
+        active_tasks: &ActiveTasks,
+        signal_senders: &SignalSenders,
+    ) {
+        let idle = semaphore.acquire_many(self.concurrency as u32);
+        let timeout = tokio::time::sleep(self.drain_timeout);
+        let give_up = tokio::time::sleep(self.drain_timeout + DRAIN_CLOSE_GRACE);
+        tokio::pin!(idle, timeout, give_up);
+        let mut settled = false;
+        loop {
+            tokio::select! {
+                _ = &mut idle, if !settled => settled = true,
+                () = &mut timeout, if !settled => {
+                    settled = true;
+                    self.hand_back_unfinished(request_tx, active_tasks, signal_senders).await;
+                }
+                () = &mut give_up => {
+                    warn!("Server did not close the stream after the drain");
+                    return;
+                }
+                msg = inbound.next() => {
+                    let Some(Ok(response)) = msg else {
+                        return;
                     };
-                    let _ = request_tx.send(shutdown).await;
-                    let _ = semaphore.acquire_many(self.concurrency as u32).await;
-                    hb_handle.abort();
-                    return Ok(());
+                    match response.response {
+                        Some(worker_response::Response::TaskAssignment(assignment)) => {
+                            info!(task_id = %assignment.task_id, "Handing back task assigned while draining");
+                            let request = WorkerRequest {
+                                request: Some(worker_request::Request::TaskResult(rejection(
+                                    &assignment.task_id,
+                                    &assignment.task_run_id,
+                                ))),
+                            };
+                            let _ = request_tx.send(request).await;
+                        }
+                        Some(worker_response::Response::TaskCancellation(cancel)) => {
+                            if let Some(task) = active_tasks.lock().await.remove(&cancel.task_id) {
+                                task.cancel.cancel();
+                            }
+                            signal_senders.lock().await.remove(&cancel.task_id);
+                        }
+                        Some(worker_response::Response::TaskSignal(signal)) => {
+                            let sigs = signal_senders.lock().await;
+                            if let Some(tx) = sigs.get(&signal.task_id)
+                                && tx.send(signal).await.is_err()
+                            {
+                                warn!("Signal channel closed for task");
+                            }
+                        }
+                        _ => {}
+                    }
                 }
             }
         }
+    }
 
-        hb_handle.abort();
-        Err(SdkError::Connection("Stream closed".to_string()))
+    /// Report every task still held as a retryable failure.
+    async fn hand_back_unfinished(
+        &self,
+        request_tx: &mpsc::Sender<WorkerRequest>,
+        active_tasks: &ActiveTasks,
+        signal_senders: &SignalSenders,
+    ) {
+        let unfinished: Vec<(String, String)> = active_tasks
+            .lock()
+            .await
+            .drain()
+            .map(|(task_id, task)| (task_id, task.task_run_id))
+            .collect();
+        warn!(
+            tasks = unfinished.len(),
+            drain_timeout_ms = self.drain_timeout.as_millis() as u64,
+            "Drain timed out, handing back unfinished tasks"
+        );
+        for (task_id, task_run_id) in unfinished {
+            signal_senders.lock().await.remove(&task_id);
+            let request = task_result(
+                task_id,
+                task_run_id,
+                Err(TaskError::retryable(
+                    "Worker shut down before the task finished",
+                )),
+                String::new(),
+                self.result_limits,
+            );
+            let _ = request_tx.send(request).await;
+        }
+    }
+
+    /// Hand the pending batch, if any, to the batch handler.
+    fn flush_batch(
+        &self,
+        pending: &mut PendingBatch,
+        tx: &mpsc::Sender<WorkerRequest>,
+        active: &ActiveTasks,
+        sigs: &SignalSenders,
+    ) {
+        let WorkerHandler::Batch(config) = &self.handler else {
+            return;
+        };
+        if pending.is_empty() {
+            return;
+        }
+        tokio::spawn(batch::run_batch(
+            config.handler.clone(),
+            pending.take(),
+            tx.clone(),
+            active.clone(),
+            sigs.clone(),
+            self.result_limits,
+        ));
+    }
+}
+
+/// The TaskResult reported for a handler outcome.
+pub(crate) fn task_result(
+    task_id: String,
+    task_run_id: String,
+    result: Result<serde_json::Value, TaskError>,
+    traceparent: String,
+    limits: ResultLimits,
+) -> WorkerRequest {
+    let mut task_result = match result {
+        Ok(output) => TaskResult {
+            task_id,
+            task_run_id,
+            success: true,
+            retryable: false,
+            output: output.to_string(),
+            error_message: String::new(),
+            traceparent,
+            rejected: false,
+            cancelled: false,
+            retry_after_ms: 0,
+        },
+        Err(err) => TaskResult {
+            task_id,
+            task_run_id,
+            success: false,
+            retryable: err.retryable,
+            output: String::new(),
+            error_message: err.message,
+            traceparent,
+            rejected: false,
+            cancelled: false,
+            retry_after_ms: err
+                .retry_after
+                .map(|d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX).max(1))
+                .unwrap_or(0),
+        },
+    };
+    limits.apply(&mut task_result);
+    WorkerRequest {
+        request: Some(worker_request::Request::TaskResult(task_result)),
+    }
+}
+
+/// The TaskResult reported when a handler returns after its task was cancelled.
+pub(crate) fn cancelled_result(task_id: String, task_run_id: String) -> WorkerRequest {
+    WorkerRequest {
+        request: Some(worker_request::Request::TaskResult(TaskResult {
+            task_id,
+            task_run_id,
+            cancelled: true,
+            ..Default::default()
+        })),
+    }
+}
+
+/// How long a handler may run: the task timeout, capped by the remaining
+/// deadline budget. Non-positive values mean "no limit" for either input.
+pub fn execution_timeout(timeout_seconds: i32, remaining_budget_ms: i64) -> Option<Duration> {
+    let timeout = (timeout_seconds > 0).then(|| Duration::from_secs(timeout_seconds as u64));
+    let budget =
+        (remaining_budget_ms > 0).then(|| Duration::from_millis(remaining_budget_ms as u64));
+    match (timeout, budget) {
+        (Some(t), Some(b)) => Some(t.min(b)),
+        (t, b) => t.or(b),
+    }
+}
+
+/// SIGTERM listener, created once per session so no signal slips between
+/// polls. Never fires where SIGTERM doesn't exist or can't be listened for.
+struct Sigterm(#[cfg(unix)] Option<tokio::signal::unix::Signal>);
+
+impl Sigterm {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            let listener = signal(SignalKind::terminate())
+                .inspect_err(|e| warn!(error = %e, "Cannot listen for SIGTERM"))
+                .ok();
+            Self(listener)
+        }
+        #[cfg(not(unix))]
+        Self()
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(listener) = &mut self.0
+            && listener.recv().await.is_some()
+        {
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
+
+fn abort_log_shipper(handle: &Option<tokio::task::JoinHandle<()>>) {
+    if let Some(handle) = handle {
+        handle.abort();
     }
 }
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use tonic::{Request, Status};
+use valka_core::AuthConfig;
+
+use crate::admin::constant_time_eq;
+
+/// Bearer token gate for the API (REST `/api/v1/*` and gRPC ApiService) and
+/// worker sessions. API tokens open both; worker tokens only open worker
+/// sessions. Each side is open while it has no token that could open it.
+#[derive(Clone, Default)]
+pub struct ApiAuth {
+    inner: Arc<Tokens>,
+}
+
+#[derive(Clone, Default)]
+struct Tokens {
+    api: Vec<Box<str>>,
+    worker: Vec<Box<str>>,
+    protect_health: bool,
+    protect_metrics: bool,
+}
+
+impl ApiAuth {
+    /// Empty tokens are ignored.
+    pub fn new(api_tokens: Vec<String>, worker_tokens: Vec<String>) -> Self {
+        let keep = |tokens: Vec<String>| -> Vec<Box<str>> {
+            tokens
+                .into_iter()
+                .filter(|t| !t.is_empty())
+                .map(String::into_boxed_str)
+                .collect()
+        };
+        Self {
+            inner: Arc::new(Tokens {
+                api: keep(api_tokens),
+                worker: keep(worker_tokens),
+                protect_health: false,
+                protect_metrics: false,
+            }),
+        }
+    }
+
+    /// Tokens from `config` (reading its `token_file`), plus any `extra`
+    /// API tokens such as the admin token.
+    pub fn from_config(config: &AuthConfig, extra: Option<&str>) -> std::io::Result<Self> {
+        let mut api = config.tokens.clone();
+        if let Some(path) = &config.token_file {
+            api.extend(
+                std::fs::read_to_string(path)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        // Only alongside other tokens: on its own it must not close the API
+        if !api.iter().all(|t| t.is_empty()) {
+            api.extend(extra.map(str::to_string));
+        }
+        Ok(Self::new(api, config.worker_tokens.clone())
+            .with_protected(config.protect_health, config.protect_metrics))
+    }
+
+    /// Also gate the health and/or metrics endpoints.
+    pub fn with_protected(self, health: bool, metrics: bool) -> Self {
+        let tokens = Arc::unwrap_or_clone(self.inner);
+        Self {
+            inner: Arc::new(Tokens {
+                protect_health: health,
+                protect_metrics: metrics,
+                ..tokens
+            }),
+        }
+    }
+
+    pub fn api_enabled(&self) -> bool {
+        !self.inner.api.is_empty()
+    }
+
+    pub fn protects_health(&self) -> bool {
+        self.api_enabled() && self.inner.protect_health
+    }
+
+    pub fn protects_metrics(&self) -> bool {
+        self.api_enabled() && self.inner.protect_metrics
+    }
+
+    /// Whether an `authorization` header value carries an API token.
+    pub fn allows_api(&self, authorization: Option<&str>) -> bool {
+        !self.api_enabled() || matches_any(&self.inner.api, authorization)
+    }
+
+    /// Whether an `authorization` header value may open a worker session.
+    pub fn allows_worker(&self, authorization: Option<&str>) -> bool {
+        (self.inner.api.is_empty() && self.inner.worker.is_empty())
+            || matches_any(&self.inner.api, authorization)
+            || matches_any(&self.inner.worker, authorization)
+    }
+
+    /// Interceptor for the gRPC ApiService.
+    pub fn check_api(&self, request: Request<()>) -> Result<Request<()>, Status> {
+        if self.allows_api(authorization(&request)) {
+            Ok(request)
+        } else {
+            Err(Status::unauthenticated("Missing or invalid API token"))
+        }
+    }
+
+    /// Interceptor for the gRPC WorkerService.
+    pub fn check_worker(&self, request: Request<()>) -> Result<Request<()>, Status> {
+        if self.allows_worker(authorization(&request)) {
+            Ok(request)
+        } else {
+            Err(Status::unauthenticated("Missing or invalid worker token"))
+        }
+    }
+}
+
+fn authorization<T>(request: &Request<T>) -> Option<&str> {
+    request
+        .metadata()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+}
+
+fn matches_any(tokens: &[Box<str>], authorization: Option<&str>) -> bool {
+    let Some(given) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    // Checks every token so timing doesn't say which one matched
+    tokens.iter().fold(false, |found, token| {
+        constant_time_eq(given.as_bytes(), token.as_bytes()) | found
+    })
+}
@@ -0,0 +1,1102 @@
+//! `valka-server --dev`: one node on SQLite (`sqlite` feature), for trying
+//! Valka without Postgres. It speaks the worker protocol and serves the
+//! task, run, log, worker, dead letter, stats and event endpoints the
+//! dashboard reads, plus the dashboard itself. Queue configs, schedules,
+//! quotas, signals, dependencies and clustering are not available.
+//!
+//! Not for production: see [`valka_db::sqlite`].
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive};
+use axum::response::{IntoResponse, Sse};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::services::{ServeDir, ServeFile};
+use tracing::{error, info, warn};
+use valka_core::limits::NewTask;
+use valka_core::retry::{RetryBackoff, RetryPolicy};
+use valka_core::{DispatcherConfig, ServerConfig, TaskId, WorkerId, partition_for_task};
+use valka_db::queries::task_logs::InsertLogEntry;
+use valka_db::queries::tasks::{CreateTaskParams, TaskRow};
+use valka_db::sqlite::{self, queries};
+use valka_dispatcher::Registration;
+use valka_dispatcher::queue_policy::{DEFAULT_MAX_RETRIES, DEFAULT_TIMEOUT_SECONDS};
+use valka_proto::{
+    HeartbeatAck, HelloAck, LogBatch, PROTOCOL_VERSION, ServerShutdown, TaskAssignment, TaskEvent,
+    TaskResult, TaskStatus, WORKER_LOG_RUN_PREFIX, WorkerRequest, WorkerResponse, worker_request,
+    worker_response, worker_service_server,
+};
+
+use crate::auth::ApiAuth;
+use crate::rest::{
+    ApiError, ApiJson, ApiPath, ApiQuery, require_api_auth, task_log_to_json, task_run_to_json,
+};
+use crate::server::log_level_to_string;
+
+/// Database `--dev` uses when not given one.
+pub const DEFAULT_DEV_DATABASE_URL: &str = "sqlite://valka-dev.db";
+
+/// Every task lands on partition 0; one node owns everything.
+const DEV_PARTITIONS: i32 = 1;
+
+/// How often a worker's session looks for PENDING tasks on its queues.
+const CLAIM_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often RETRY tasks whose time has come go back to PENDING.
+const PROMOTE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often expired leases and silent workers are looked for.
+const REAP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Lease a run gets beyond its timeout, as in the dispatcher.
+const LEASE_GRACE_SECS: i64 = 30;
+
+const BANNER: &str = "\
+==================================================================
+  valka-server --dev: SQLite development mode. NOT FOR PRODUCTION.
+  Single node, no clustering; queue configs, schedules, quotas,
+  signals and dependencies are unavailable.
+==================================================================";
+
+/// Shared by the dev worker sessions and REST handlers.
+#[derive(Clone)]
+pub struct DevState {
+    pool: SqlitePool,
+    event_tx: broadcast::Sender<TaskEvent>,
+    workers: Arc<Mutex<HashMap<String, DevWorker>>>,
+    node_id: String,
+    retry_policy: RetryPolicy,
+    dispatcher_config: DispatcherConfig,
+    limits: valka_core::LimitsConfig,
+}
+
+/// A connected worker and the tasks it holds.
+struct DevWorker {
+    /// The stream this entry belongs to; a reconnect under the same worker_id
+    /// replaces it
+    session_id: String,
+    tx: mpsc::Sender<WorkerResponse>,
+    name: String,
+    queues: Vec<String>,
+    concurrency: i32,
+    /// task_id -> run_id
+    active: HashMap<String, String>,
+    draining: bool,
+    connected_at: DateTime<Utc>,
+    last_heartbeat: DateTime<Utc>,
+}
+
+impl DevState {
+    pub fn new(pool: SqlitePool, config: &ServerConfig) -> Self {
+        let (event_tx, _) = broadcast::channel(1024);
+        Self {
+            pool,
+            event_tx,
+            workers: Arc::new(Mutex::new(HashMap::new())),
+            node_id: if config.node_id.is_empty() {
+                "dev".to_string()
+            } else {
+                config.node_id.clone()
+            },
+            retry_policy: RetryPolicy::from_config(&config.scheduler),
+            dispatcher_config: config.dispatcher.clone(),
+            limits: config.limits.clone(),
+        }
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    fn workers(&self) -> std::sync::MutexGuard<'_, HashMap<String, DevWorker>> {
+        self.workers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn emit(&self, task: &TaskRow, status: TaskStatus, worker_id: &str, error_message: &str) {
+        let _ = self.event_tx.send(TaskEvent {
+            event_id: uuid::Uuid::now_v7().to_string(),
+            task_id: task.id.clone(),
+            queue_name: task.queue_name.clone(),
+            new_status: status as i32,
+            worker_id: worker_id.to_string(),
+            node_id: self.node_id.clone(),
+            attempt_number: task.attempt_count,
+            error_message: error_message.to_string(),
+            timestamp_ms: Utc::now().timestamp_millis(),
+            ..Default::default()
+        });
+    }
+
+    /// Add a worker's session. A second session for a connected worker_id
+    /// takes over the first one's tasks, unless `reject_duplicate_workers`
+    /// turns it away.
+    fn register(&self, worker_id: &str, mut worker: DevWorker) -> Registration {
+        let previous = match self.workers().entry(worker_id.to_string()) {
+            Entry::Occupied(_) if self.dispatcher_config.reject_duplicate_workers => {
+                warn!(
+                    worker_id,
+                    session_id = %worker.session_id,
+                    "Rejected session for a worker_id that is already connected"
+                );
+                return Registration::Rejected;
+            }
+            Entry::Occupied(mut entry) => {
+                worker.active = std::mem::take(&mut entry.get_mut().active);
+                entry.insert(worker)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(worker);
+                return Registration::New;
+            }
+        };
+        info!(
+            worker_id,
+            previous_session_id = %previous.session_id,
+            "Worker reconnected; taking over its previous session"
+        );
+        let notice = WorkerResponse {
+            response: Some(worker_response::Response::ServerShutdown(ServerShutdown {
+                reason: "Superseded by a newer session of this worker".to_string(),
+                drain_seconds: 0,
+            })),
+        };
+        // The old stream may be dead already; never wait on it
+        let _ = previous.tx.try_send(notice);
+        Registration::Adopted {
+            previous_session_id: previous.session_id,
+        }
+    }
+
+    /// Queues a worker's session takes tasks from and how many more it can
+    /// hold; `None` once the session is gone or replaced.
+    fn capacity(&self, worker_id: &str, session_id: &str) -> Option<(Vec<String>, i64)> {
+        let workers = self.workers();
+        let worker = workers
+            .get(worker_id)
+            .filter(|worker| worker.session_id == session_id)?;
+        let free = if worker.draining {
+            0
+        } else {
+            (worker.concurrency.max(1) as i64 - worker.active.len() as i64).max(0)
+        };
+        Some((worker.queues.clone(), free))
+    }
+
+    /// Start a run of a claimed task and send it to the worker's session.
+    /// False once the stream is closed or the session replaced.
+    async fn assign(
+        &self,
+        worker_id: &str,
+        session_id: &str,
+        task: TaskRow,
+        tx: &mpsc::Sender<WorkerResponse>,
+    ) -> bool {
+        let run_id = uuid::Uuid::now_v7().to_string();
+        let lease_expires_at =
+            Utc::now() + chrono::Duration::seconds(task.timeout_seconds as i64 + LEASE_GRACE_SECS);
+        let run = match queries::start_task_run(
+            &self.pool,
+            &task.id,
+            &run_id,
+            worker_id,
+            &self.node_id,
+            lease_expires_at,
+        )
+        .await
+        {
+            Ok(run) => run,
+            Err(e) => {
+                error!(task_id = %task.id, error = %e, "Failed to start task run");
+                if let Err(e) = queries::release_task(&self.pool, &task.id).await {
+                    error!(task_id = %task.id, error = %e, "Failed to release task");
+                }
+                return true;
+            }
+        };
+        let held = match self.workers().get_mut(worker_id) {
+            Some(worker) if worker.session_id == session_id => {
+                worker.active.insert(task.id.clone(), run.id.clone());
+                true
+            }
+            _ => false,
+        };
+        if !held {
+            if let Err(e) =
+                queries::release_task_run(&self.pool, &task.id, &run.id, "Worker session replaced")
+                    .await
+            {
+                error!(task_id = %task.id, error = %e, "Failed to release task run");
+            }
+            return false;
+        }
+
+        let now = Utc::now();
+        let assignment = TaskAssignment {
+            task_id: task.id.clone(),
+            task_run_id: run.id.clone(),
+            queue_name: task.queue_name.clone(),
+            task_name: task.task_name.clone(),
+            input: task
+                .input
+                .as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            attempt_number: run.attempt_number,
+            timeout_seconds: task.timeout_seconds,
+            metadata: task.metadata.to_string(),
+            remaining_budget_ms: task
+                .deadline_at
+                .map_or(0, |d| (d - now).num_milliseconds().max(1)),
+            traceparent: task.traceparent.clone().unwrap_or_default(),
+            priority: task.priority,
+            created_at_ms: task.created_at.timestamp_millis(),
+            scheduled_at_ms: task.scheduled_at.map_or(0, |t| t.timestamp_millis()),
+        };
+        let sent = tx
+            .send(WorkerResponse {
+                response: Some(worker_response::Response::TaskAssignment(assignment)),
+            })
+            .await
+            .is_ok();
+        if sent {
+            self.emit(&task, TaskStatus::Running, worker_id, "");
+        }
+        sent
+    }
+
+    async fn handle_result(&self, worker_id: &str, result: TaskResult) {
+        let held = self
+            .workers()
+            .get_mut(worker_id)
+            .and_then(|w| w.active.remove(&result.task_id))
+            .is_some_and(|run_id| run_id == result.task_run_id);
+        if !held {
+            warn!(task_id = %result.task_id, worker_id, "Result for a run this worker doesn't hold");
+            return;
+        }
+        if let Err(e) = self.write_result(worker_id, &result).await {
+            error!(task_id = %result.task_id, error = %e, "Failed to record task result");
+        }
+    }
+
+    async fn write_result(&self, worker_id: &str, result: &TaskResult) -> Result<(), sqlx::Error> {
+        let Some(task) = queries::get_task(&self.pool, &result.task_id).await? else {
+            return Ok(());
+        };
+        if result.rejected || result.cancelled {
+            queries::release_task_run(
+                &self.pool,
+                &task.id,
+                &result.task_run_id,
+                "Handed back by the worker",
+            )
+            .await?;
+            self.emit(&task, TaskStatus::Pending, worker_id, "");
+            return Ok(());
+        }
+        if result.success {
+            let output = serde_json::from_str::<serde_json::Value>(&result.output).ok();
+            queries::complete_task_run(&self.pool, &task.id, &result.task_run_id, output.as_ref())
+                .await?;
+            self.emit(&task, TaskStatus::Completed, worker_id, "");
+            return Ok(());
+        }
+
+        // A delay the handler asked for replaces backoff, up to the longest backoff
+        let delay = if result.retry_after_ms > 0 {
+            chrono::Duration::milliseconds(
+                result.retry_after_ms.min(self.retry_policy.max_delay_ms()),
+            )
+        } else {
+            self.retry_policy
+                .with_backoff(&task.retry_backoff())
+                .jittered_delay(task.attempt_count)
+        };
+        let retry_at = Utc::now()
+            .checked_add_signed(delay)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let Some(task) = queries::fail_task_run(
+            &self.pool,
+            &task.id,
+            &result.task_run_id,
+            &result.error_message,
+            result.retryable,
+            retry_at,
+        )
+        .await?
+        else {
+            return Ok(());
+        };
+        self.emit(
+            &task,
+            failed_status(&task),
+            worker_id,
+            &result.error_message,
+        );
+        Ok(())
+    }
+
+    async fn insert_logs(&self, batch: LogBatch) {
+        // Worker-level logs have no table in the dev schema
+        let entries: Vec<InsertLogEntry> = batch
+            .entries
+            .into_iter()
+            .filter(|entry| !entry.task_run_id.starts_with(WORKER_LOG_RUN_PREFIX))
+            .map(|entry| InsertLogEntry {
+                task_run_id: entry.task_run_id,
+                timestamp_ms: entry.timestamp_ms,
+                level: log_level_to_string(entry.level),
+                message: entry.message,
+                metadata: serde_json::from_str(&entry.metadata).ok(),
+            })
+            .collect();
+        if let Err(e) = queries::batch_insert_logs(&self.pool, &entries).await {
+            error!(error = %e, "Failed to insert task logs");
+        }
+    }
+
+    /// Forget a worker session whose stream ended and put its tasks back to
+    /// PENDING. A session that was replaced leaves its successor alone.
+    async fn remove_worker(&self, worker_id: &str, session_id: &str) {
+        let worker = {
+            let mut workers = self.workers();
+            match workers.get(worker_id) {
+                Some(worker) if worker.session_id == session_id => workers.remove(worker_id),
+                _ => None,
+            }
+        };
+        if let Some(worker) = worker {
+            self.release_runs(worker.active, "Worker disconnected")
+                .await;
+        }
+    }
+
+    async fn release_runs(&self, runs: HashMap<String, String>, reason: &str) {
+        for (task_id, run_id) in runs {
+            if let Err(e) = queries::release_task_run(&self.pool, &task_id, &run_id, reason).await {
+                error!(task_id, error = %e, "Failed to release task of a departed worker");
+            }
+        }
+    }
+
+    /// Fail RUNNING runs whose lease has passed and drop workers that stopped
+    /// heartbeating without their stream closing, releasing their tasks.
+    /// Returns how many runs were failed.
+    pub async fn reap_expired_runs(&self) -> Result<usize, sqlx::Error> {
+        let dead_after = chrono::Duration::seconds(self.dispatcher_config.dead_after_secs as i64);
+        let silent: Vec<(String, DevWorker)> = {
+            let mut workers = self.workers();
+            let cutoff = Utc::now() - dead_after;
+            let ids: Vec<String> = workers
+                .iter()
+                .filter(|(_, worker)| worker.last_heartbeat < cutoff)
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.into_iter()
+                .filter_map(|id| workers.remove(&id).map(|worker| (id, worker)))
+                .collect()
+        };
+        for (worker_id, worker) in silent {
+            warn!(
+                worker_id,
+                "Worker stopped heartbeating; releasing its tasks"
+            );
+            let notice = WorkerResponse {
+                response: Some(worker_response::Response::ServerShutdown(ServerShutdown {
+                    reason: "Worker stopped heartbeating".to_string(),
+                    drain_seconds: 0,
+                })),
+            };
+            let _ = worker.tx.try_send(notice);
+            self.release_runs(worker.active, "Worker stopped heartbeating")
+                .await;
+        }
+
+        let mut reaped = 0;
+        for run in queries::find_expired_runs(&self.pool).await? {
+            let message = format!("Lease expired on worker {}", run.worker_id);
+            let Some(task) = queries::fail_task_run(
+                &self.pool,
+                &run.task_id,
+                &run.id,
+                &message,
+                true,
+                Utc::now(),
+            )
+            .await?
+            else {
+                continue;
+            };
+            if let Some(worker) = self.workers().get_mut(&run.worker_id)
+                && worker.active.get(&run.task_id) == Some(&run.id)
+            {
+                worker.active.remove(&run.task_id);
+            }
+            warn!(task_id = %task.id, run_id = %run.id, worker_id = %run.worker_id, "Task run lease expired");
+            self.emit(&task, failed_status(&task), &run.worker_id, &message);
+            reaped += 1;
+        }
+        Ok(reaped)
+    }
+}
+
+/// The event status of a task that `fail_task_run` just moved on.
+fn failed_status(task: &TaskRow) -> TaskStatus {
+    match task.status.as_str() {
+        "RETRY" => TaskStatus::Retry,
+        "DEAD_LETTER" => TaskStatus::DeadLetter,
+        _ => TaskStatus::Failed,
+    }
+}
+
+/// Run the dev server until `shutdown` resolves.
+pub async fn run(
+    mut config: ServerConfig,
+    database_url: &str,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> anyhow::Result<()> {
+    eprintln!("{BANNER}");
+    warn!("Running in SQLite development mode; not for production");
+    if !config.gossip.seed_nodes.is_empty() {
+        warn!("gossip.seed_nodes is ignored in development mode");
+    }
+    config.matching.num_partitions = DEV_PARTITIONS;
+
+    let pool = sqlite::create_pool(database_url).await?;
+    sqlite::run_migrations(&pool).await?;
+    let recovered = queries::recover_orphaned_tasks(&pool).await?;
+    if recovered > 0 {
+        info!(count = recovered, "Recovered unfinished tasks to PENDING");
+    }
+
+    let state = DevState::new(pool, &config);
+    let auth = ApiAuth::from_config(&config.auth, config.admin_token.as_deref())
+        .map_err(|e| anyhow::anyhow!("Failed to read auth.token_file: {e}"))?;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    tokio::spawn(run_retry_promoter(state.pool.clone(), shutdown_rx.clone()));
+    tokio::spawn(run_lease_reaper(state.clone(), shutdown_rx.clone()));
+
+    let grpc_addr: SocketAddr = config.grpc_addr.parse()?;
+    let grpc_state = state.clone();
+    let grpc_auth = auth.clone();
+    let grpc_shutdown = shutdown_rx.clone();
+    let grpc = tokio::spawn(async move {
+        if let Err(e) = serve_grpc(grpc_addr, grpc_state, grpc_auth, grpc_shutdown).await {
+            error!(error = %e, "gRPC server failed");
+        }
+    });
+
+    let http_addr: SocketAddr = config.http_addr.parse()?;
+    let index_path = format!("{}/index.html", &config.web_dir);
+    let app = require_api_auth(build_dev_router(state), auth).fallback_service(
+        ServeDir::new(&config.web_dir).not_found_service(ServeFile::new(index_path)),
+    );
+    let listener = tokio::net::TcpListener::bind(http_addr).await?;
+    let mut http_shutdown = shutdown_rx;
+    let http = tokio::spawn(async move {
+        let served = axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                let _ = http_shutdown.changed().await;
+            })
+            .await;
+        if let Err(e) = served {
+            error!(error = %e, "REST server failed");
+        }
+    });
+
+    info!(
+        grpc_addr = %config.grpc_addr,
+        http_addr = %config.http_addr,
+        database_url,
+        "Valka dev server started"
+    );
+
+    shutdown.await;
+    info!("Shutdown signal received");
+    let _ = shutdown_tx.send(true);
+    let _ = grpc.await;
+    let _ = http.await;
+    info!("Valka dev server stopped");
+    Ok(())
+}
+
+async fn run_lease_reaper(state: DevState, mut shutdown: watch::Receiver<bool>) {
+    let mut interval = tokio::time::interval(REAP_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = state.reap_expired_runs().await {
+                    error!(error = %e, "Failed to reap expired leases");
+                }
+            }
+            _ = shutdown.changed() => return,
+        }
+    }
+}
+
+async fn run_retry_promoter(pool: SqlitePool, mut shutdown: watch::Receiver<bool>) {
+    let mut interval = tokio::time::interval(PROMOTE_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = queries::promote_delayed_tasks(&pool).await {
+                    error!(error = %e, "Failed to promote retries");
+                }
+            }
+            _ = shutdown.changed() => return,
+        }
+    }
+}
+
+// ─── Worker Sessions ────────────────────────────────────────────────
+
+struct DevWorkerService {
+    state: DevState,
+}
+
+#[tonic::async_trait]
+impl worker_service_server::WorkerService for DevWorkerService {
+    type SessionStream =
+        Pin<Box<dyn Stream<Item = Result<WorkerResponse, Status>> + Send + 'static>>;
+
+    async fn session(
+        &self,
+        request: Request<Streaming<WorkerRequest>>,
+    ) -> Result<Response<Self::SessionStream>, Status> {
+        let inbound = request.into_inner();
+        let (response_tx, response_rx) = mpsc::channel(256);
+        tokio::spawn(handle_worker_stream(
+            self.state.clone(),
+            inbound,
+            response_tx,
+        ));
+        let stream = ReceiverStream::new(response_rx).map(Ok);
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Serve the worker protocol on `addr` until `shutdown` flips.
+pub async fn serve_grpc(
+    addr: SocketAddr,
+    state: DevState,
+    auth: ApiAuth,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), tonic::transport::Error> {
+    info!("gRPC server listening on {addr}");
+    tonic::transport::Server::builder()
+        .add_service(
+            worker_service_server::WorkerServiceServer::with_interceptor(
+                DevWorkerService { state },
+                move |request| auth.check_worker(request),
+            ),
+        )
+        .serve_with_shutdown(addr, async move {
+            let _ = shutdown.changed().await;
+        })
+        .await
+}
+
+async fn handle_worker_stream(
+    state: DevState,
+    mut inbound: Streaming<WorkerRequest>,
+    response_tx: mpsc::Sender<WorkerResponse>,
+) {
+    let hello = match inbound.next().await {
+        Some(Ok(WorkerRequest {
+            request: Some(worker_request::Request::Hello(hello)),
+        })) => hello,
+        _ => {
+            error!("First message must be WorkerHello");
+            return;
+        }
+    };
+    let worker_id = if hello.worker_id.is_empty() {
+        WorkerId::new().0
+    } else {
+        hello.worker_id.clone()
+    };
+    info!(
+        worker_id,
+        worker_name = %hello.worker_name,
+        queues = ?hello.queues,
+        concurrency = hello.concurrency,
+        "Worker connected"
+    );
+
+    let session_id = uuid::Uuid::now_v7().to_string();
+    let now = Utc::now();
+    let registration = state.register(
+        &worker_id,
+        DevWorker {
+            session_id: session_id.clone(),
+            tx: response_tx.clone(),
+            name: hello.worker_name,
+            queues: hello.queues,
+            concurrency: hello.concurrency,
+            active: HashMap::new(),
+            draining: false,
+            connected_at: now,
+            last_heartbeat: now,
+        },
+    );
+    if registration == Registration::Rejected {
+        let notice = WorkerResponse {
+            response: Some(worker_response::Response::ServerShutdown(ServerShutdown {
+                reason: format!("Worker {worker_id} already has a session on this node"),
+                drain_seconds: 0,
+            })),
+        };
+        let _ = response_tx.send(notice).await;
+        return;
+    }
+
+    if hello.protocol_version > 0 {
+        let ack = WorkerResponse {
+            response: Some(worker_response::Response::HelloAck(HelloAck {
+                protocol_version: PROTOCOL_VERSION,
+                session_id: session_id.clone(),
+                heartbeat_interval_ms: valka_dispatcher::heartbeat::negotiate_interval_ms(
+                    hello.heartbeat_interval_ms,
+                    &state.dispatcher_config,
+                ),
+            })),
+        };
+        if response_tx.send(ack).await.is_err() {
+            state.remove_worker(&worker_id, &session_id).await;
+            return;
+        }
+    }
+
+    let claimer = tokio::spawn(run_claim_loop(
+        state.clone(),
+        worker_id.clone(),
+        session_id.clone(),
+        response_tx.clone(),
+    ));
+
+    while let Some(next) = inbound.next().await {
+        let request = match next {
+            Ok(WorkerRequest {
+                request: Some(request),
+            }) => request,
+            Ok(_) => continue,
+            Err(e) => {
+                warn!(worker_id, error = %e, "Worker stream error");
+                break;
+            }
+        };
+        match request {
+            worker_request::Request::TaskResult(result) => {
+                state.handle_result(&worker_id, result).await;
+            }
+            worker_request::Request::Heartbeat(_) => {
+                if let Some(worker) = state.workers().get_mut(&worker_id)
+                    && worker.session_id == session_id
+                {
+                    worker.last_heartbeat = Utc::now();
+                }
+                let ack = WorkerResponse {
+                    response: Some(worker_response::Response::HeartbeatAck(HeartbeatAck {
+                        server_timestamp_ms: Utc::now().timestamp_millis(),
+                    })),
+                };
+                if response_tx.send(ack).await.is_err() {
+                    break;
+                }
+            }
+            worker_request::Request::LogBatch(batch) => state.insert_logs(batch).await,
+            worker_request::Request::Shutdown(_) => {
+                // Keep reading so results of its remaining tasks still land
+                if let Some(worker) = state.workers().get_mut(&worker_id)
+                    && worker.session_id == session_id
+                {
+                    worker.draining = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    claimer.abort();
+    state.remove_worker(&worker_id, &session_id).await;
+    info!(worker_id, "Worker disconnected");
+}
+
+/// Claim PENDING tasks on the worker's queues while its session has room.
+async fn run_claim_loop(
+    state: DevState,
+    worker_id: String,
+    session_id: String,
+    tx: mpsc::Sender<WorkerResponse>,
+) {
+    let mut interval = tokio::time::interval(CLAIM_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some((queues, mut free)) = state.capacity(&worker_id, &session_id) else {
+            return;
+        };
+        for queue in queues {
+            if free == 0 {
+                break;
+            }
+            let claimed = match queries::claim_tasks(&state.pool, &queue, 0, free).await {
+                Ok(claimed) => claimed,
+                Err(e) => {
+                    error!(queue, error = %e, "Failed to claim tasks");
+                    continue;
+                }
+            };
+            free -= claimed.len() as i64;
+            let mut claimed = claimed.into_iter();
+            while let Some(task) = claimed.next() {
+                if !state.assign(&worker_id, &session_id, task, &tx).await {
+                    for task in claimed {
+                        if let Err(e) = queries::release_task(&state.pool, &task.id).await {
+                            error!(task_id = %task.id, error = %e, "Failed to release task");
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// ─── REST ───────────────────────────────────────────────────────────
+
+/// The REST routes `--dev` serves, without the dashboard's static files.
+pub fn build_dev_router(state: DevState) -> Router {
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    Router::new()
+        .route("/api/v1/tasks", post(create_task).get(list_tasks))
+        .route("/api/v1/tasks/{task_id}", get(get_task))
+        .route("/api/v1/tasks/{task_id}/runs", get(get_task_runs))
+        .route(
+            "/api/v1/tasks/{task_id}/runs/{run_id}/logs",
+            get(get_run_logs),
+        )
+        .route("/api/v1/workers", get(list_workers))
+        .route("/api/v1/queues", get(list_queues))
+        .route("/api/v1/stats", get(get_stats))
+        .route("/api/v1/dead-letters", get(list_dead_letters))
+        .route("/api/v1/events", get(subscribe_events_sse))
+        .route("/healthz", get(healthz))
+        .with_state(state)
+        .layer(cors)
+}
+
+#[derive(Deserialize)]
+struct CreateTaskBody {
+    queue_name: String,
+    task_name: String,
+    #[serde(default)]
+    input: Option<serde_json::Value>,
+    #[serde(default)]
+    priority: i32,
+    /// 0 takes the default
+    #[serde(default)]
+    max_retries: i32,
+    /// 0 takes the default
+    #[serde(default)]
+    timeout_seconds: i32,
+    #[serde(default)]
+    idempotency_key: Option<String>,
+    #[serde(default)]
+    metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    scheduled_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    retry_backoff: Option<RetryBackoff>,
+}
+
+async fn create_task(
+    State(state): State<DevState>,
+    ApiJson(body): ApiJson<CreateTaskBody>,
+) -> Result<impl IntoResponse, ApiError> {
+    let max_retries = match body.max_retries {
+        0 => DEFAULT_MAX_RETRIES,
+        n => n,
+    };
+    let timeout_seconds = match body.timeout_seconds {
+        0 => DEFAULT_TIMEOUT_SECONDS,
+        n => n,
+    };
+    state
+        .limits
+        .check_task(&NewTask {
+            queue_name: &body.queue_name,
+            task_name: &body.task_name,
+            input: body.input.as_ref(),
+            metadata: body.metadata.as_ref(),
+            priority: body.priority,
+            max_retries,
+            timeout_seconds,
+        })
+        .map_err(ApiError::Validation)?;
+    let retry_backoff = body.retry_backoff.unwrap_or_default();
+    retry_backoff.validate().map_err(ApiError::Validation)?;
+
+    let task_id = TaskId::new();
+    let task = queries::create_task(
+        &state.pool,
+        CreateTaskParams {
+            partition_id: partition_for_task(&body.queue_name, &task_id.0, DEV_PARTITIONS).0,
+            id: task_id.0,
+            queue_name: body.queue_name,
+            task_name: body.task_name,
+            input: body.input,
+            priority: body.priority,
+            max_retries,
+            timeout_seconds,
+            idempotency_key: body.idempotency_key,
+            metadata: body.metadata.unwrap_or(serde_json::json!({})),
+            scheduled_at: body.scheduled_at,
+            deadline_at: None,
+            traceparent: None,
+            durability: Default::default(),
+            fifo_key: None,
+            callback_url: None,
+            retry_backoff,
+            required_labels: Default::default(),
+        },
+    )
+    .await?;
+    state.emit(&task, TaskStatus::Pending, "", "");
+    Ok((StatusCode::CREATED, Json(task.to_json())))
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    #[serde(default)]
+    queue_name: Option<String>,
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+async fn list_tasks(
+    State(state): State<DevState>,
+    ApiQuery(query): ApiQuery<ListQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let tasks = queries::list_tasks(
+        &state.pool,
+        query.queue_name.as_deref(),
+        query.status.as_deref(),
+        query.limit,
+        query.offset,
+    )
+    .await?;
+    Ok(Json(tasks.iter().map(TaskRow::to_json).collect::<Vec<_>>()))
+}
+
+async fn get_task(
+    State(state): State<DevState>,
+    ApiPath(task_id): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let task = queries::get_task(&state.pool, &task_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Task not found".to_string()))?;
+    Ok(Json(task.to_json()))
+}
+
+async fn get_task_runs(
+    State(state): State<DevState>,
+    ApiPath(task_id): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let runs = queries::get_runs_for_task(&state.pool, &task_id).await?;
+    Ok(Json(
+        runs.into_iter().map(task_run_to_json).collect::<Vec<_>>(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct LogsQuery {
+    #[serde(default = "default_log_limit")]
+    limit: i64,
+}
+
+fn default_log_limit() -> i64 {
+    1000
+}
+
+async fn get_run_logs(
+    State(state): State<DevState>,
+    ApiPath((_task_id, run_id)): ApiPath<(String, String)>,
+    ApiQuery(query): ApiQuery<LogsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let logs = queries::get_logs_for_run(&state.pool, &run_id, query.limit).await?;
+    Ok(Json(
+        logs.into_iter().map(task_log_to_json).collect::<Vec<_>>(),
+    ))
+}
+
+async fn list_workers(State(state): State<DevState>) -> impl IntoResponse {
+    let workers: Vec<serde_json::Value> = state
+        .workers()
+        .iter()
+        .map(|(id, w)| {
+            serde_json::json!({
+                "id": id,
+                "name": w.name,
+                "queues": w.queues,
+                "concurrency": w.concurrency,
+                "active_tasks": w.active.len(),
+                "status": if w.draining { "DRAINING" } else { "ACTIVE" },
+                "last_heartbeat": w.last_heartbeat.to_rfc3339(),
+                "connected_at": w.connected_at.to_rfc3339(),
+            })
+        })
+        .collect();
+    Json(workers)
+}
+
+/// Queue configs don't exist in development mode.
+async fn list_queues() -> impl IntoResponse {
+    Json(Vec::<serde_json::Value>::new())
+}
+
+async fn get_stats(State(state): State<DevState>) -> Result<impl IntoResponse, ApiError> {
+    let now = Utc::now();
+    let grouped = queries::count_tasks_grouped(&state.pool).await?;
+    let dead_letters = queries::count_dead_letters(&state.pool).await?;
+    let (created_last_hour, completed_last_hour) =
+        queries::count_recent_tasks(&state.pool, now - chrono::Duration::hours(1)).await?;
+
+    let mut total = 0;
+    let mut by_status = serde_json::Map::new();
+    let mut by_queue: Vec<(String, i64, serde_json::Map<String, serde_json::Value>)> = Vec::new();
+    for row in grouped {
+        total += row.count;
+        let status_total = by_status
+            .get(&row.status)
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0);
+        by_status.insert(row.status.clone(), (status_total + row.count).into());
+        // Rows arrive ordered by queue
+        match by_queue.last_mut() {
+            Some((queue, queue_total, statuses)) if *queue == row.queue_name => {
+                *queue_total += row.count;
+                statuses.insert(row.status, row.count.into());
+            }
+            _ => {
+                let mut statuses = serde_json::Map::new();
+                statuses.insert(row.status, row.count.into());
+                by_queue.push((row.queue_name, row.count, statuses));
+            }
+        }
+    }
+    by_queue.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let active_workers = state.workers().values().filter(|w| !w.draining).count();
+
+    Ok(Json(serde_json::json!({
+        "total_tasks": total,
+        "by_status": by_status,
+        "by_queue": by_queue
+            .into_iter()
+            .map(|(queue_name, total, by_status)| serde_json::json!({
+                "queue_name": queue_name,
+                "total": total,
+                "by_status": by_status,
+            }))
+            .collect::<Vec<_>>(),
+        "active_workers": active_workers,
+        "dead_letters": dead_letters,
+        "created_last_hour": created_last_hour,
+        "completed_last_hour": completed_last_hour,
+        "generated_at": now.to_rfc3339(),
+    })))
+}
+
+#[derive(Deserialize)]
+struct DeadLetterQuery {
+    #[serde(default)]
+    queue_name: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+async fn list_dead_letters(
+    State(state): State<DevState>,
+    ApiQuery(query): ApiQuery<DeadLetterQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let dls =
+        queries::list_dead_letters(&state.pool, query.queue_name.as_deref(), query.limit).await?;
+    let result: Vec<serde_json::Value> = dls
+        .into_iter()
+        .map(|dl| {
+            serde_json::json!({
+                "id": dl.id,
+                "task_id": dl.task_id,
+                "queue_name": dl.queue_name,
+                "task_name": dl.task_name,
+                "input": dl.input,
+                "error_message": dl.error_message,
+                "worker_id": dl.worker_id,
+                "attempt_count": dl.attempt_count,
+                "metadata": dl.metadata,
+                "owner": null,
+                "created_at": dl.created_at.to_rfc3339(),
+            })
+        })
+        .collect();
+    Ok(Json(result))
+}
+
+/// Task events as SSE, from the moment of subscribing.
+async fn subscribe_events_sse(State(state): State<DevState>) -> impl IntoResponse {
+    let mut rx = state.event_tx.subscribe();
+    let stream = async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let data = serde_json::json!({
+                        "event_id": event.event_id,
+                        "task_id": event.task_id,
+                        "queue_name": event.queue_name,
+                        "new_status": event.new_status,
+                        "timestamp_ms": event.timestamp_ms,
+                    });
+                    yield Ok::<_, Infallible>(
+                        Event::default().id(event.event_id).data(data.to_string()),
+                    );
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn healthz() -> &'static str {
+    "ok"
+}
@@ -5,17 +5,26 @@ use std::sync::Arc;
 use futures::{Stream, StreamExt};
 use tokio::sync::{broadcast, mpsc, watch};
 use tokio_stream::wrappers::ReceiverStream;
+use tonic::metadata::MetadataValue;
 use tonic::{Request, Response, Status, Streaming};
-use tracing::info;
+use tracing::{Instrument, info, info_span, warn};
 
+use crate::admin::AdminAuth;
+use crate::auth::ApiAuth;
+use crate::health;
+use crate::internal_grpc::InternalServiceImpl;
+use crate::log_tail::{self, LogTailItem};
+use crate::run_relay;
 use valka_cluster::{ClusterManager, NodeForwarder};
-use valka_core::{NodeId, TaskId, partition_for_task};
+use valka_core::limits::NewTask;
+use valka_core::{Durability, NodeId, TaskId, fault_point, partition_for_task, trace_context};
 use valka_db::DbPool;
 use valka_dispatcher::DispatcherService;
+use valka_dispatcher::blob_store::BlobError;
+use valka_dispatcher::quotas::QuotaExceeded;
 use valka_matching::MatchingService;
 use valka_matching::partition::TaskEnvelope;
 use valka_proto::*;
-use crate::internal_grpc::InternalServiceImpl;
 
 pub struct ApiServiceImpl {
     pool: DbPool,
@@ -25,27 +34,85 @@ pub struct ApiServiceImpl {
     node_id: NodeId,
     cluster: Arc<ClusterManager>,
     forwarder: NodeForwarder,
+    admin: AdminAuth,
 }
 
 pub struct WorkerServiceImpl {
     dispatcher: DispatcherService,
 }
 
-#[tonic::async_trait]
-impl api_service_server::ApiService for ApiServiceImpl {
-    async fn create_task(
+/// A drain's timeout; 0 waits for the worker's active tasks however long.
+pub(crate) fn drain_timeout(seconds: i32) -> Result<Option<std::time::Duration>, Status> {
+    match u64::try_from(seconds) {
+        Ok(0) => Ok(None),
+        Ok(secs) => Ok(Some(std::time::Duration::from_secs(secs))),
+        Err(_) => Err(Status::invalid_argument(
+            "timeout_seconds must not be negative",
+        )),
+    }
+}
+
+/// RESOURCE_EXHAUSTED with the quota key, usage and limit in the trailers.
+fn quota_exceeded_status(e: QuotaExceeded) -> Status {
+    let mut status = Status::resource_exhausted(e.to_string());
+    let metadata = status.metadata_mut();
+    if let Ok(key) = e.key.parse() {
+        metadata.insert("quota-key", key);
+    }
+    metadata.insert(
+        "quota-limit-kind",
+        MetadataValue::from_static(e.limit_kind.as_str()),
+    );
+    metadata.insert("quota-usage", e.usage.into());
+    metadata.insert("quota-limit", e.limit.into());
+    status
+}
+
+/// INVALID_ARGUMENT for an input the blob store refuses, INTERNAL otherwise.
+fn blob_status(e: BlobError) -> Status {
+    match e {
+        BlobError::TooLarge { .. } | BlobError::ReservedShape(_) => {
+            Status::invalid_argument(e.to_string())
+        }
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+impl ApiServiceImpl {
+    fn emit_task_created(&self, task_id: &str, queue_name: &str) {
+        self.dispatcher.events().emit(TaskEvent {
+            event_id: uuid::Uuid::now_v7().to_string(),
+            task_id: task_id.to_string(),
+            queue_name: queue_name.to_string(),
+            previous_status: 0,
+            new_status: 1, // PENDING
+            worker_id: String::new(),
+            node_id: self.node_id.0.clone(),
+            attempt_number: 0,
+            error_message: String::new(),
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            suppressed_count: 0,
+            progress: None,
+            progress_message: String::new(),
+        });
+    }
+
+    async fn create_task_traced(
         &self,
-        request: Request<CreateTaskRequest>,
+        req: CreateTaskRequest,
     ) -> Result<Response<CreateTaskResponse>, Status> {
-        let req = request.into_inner();
         let task_id = TaskId::new();
+        tracing::Span::current().record("task_id", task_id.0.as_str());
+        let traceparent = trace_context::current_traceparent();
+        let fifo_key = (!req.fifo_key.is_empty()).then(|| req.fifo_key.clone());
+        // A FIFO key keeps all of its tasks on one partition
         let partition = partition_for_task(
             &req.queue_name,
-            &task_id.0,
+            fifo_key.as_deref().unwrap_or(&task_id.0),
             self.matching.config().num_partitions,
         );
 
-        let input: Option<serde_json::Value> = if req.input.is_empty() {
+        let mut input: Option<serde_json::Value> = if req.input.is_empty() {
             None
         } else {
             Some(
@@ -53,6 +120,11 @@ impl api_service_server::ApiService for ApiServiceImpl {
                     .map_err(|e| Status::invalid_argument(format!("Invalid input JSON: {e}")))?,
             )
         };
+        // Checked against the limits as the reference it leaves
+        let offload = self
+            .dispatcher
+            .prepare_input(&task_id.0, &mut input)
+            .map_err(blob_status)?;
 
         let metadata: serde_json::Value = if req.metadata.is_empty() {
             serde_json::json!({})
@@ -61,6 +133,19 @@ impl api_service_server::ApiService for ApiServiceImpl {
                 .map_err(|e| Status::invalid_argument(format!("Invalid metadata JSON: {e}")))?
         };
 
+        self.dispatcher
+            .limits()
+            .check_task(&NewTask {
+                queue_name: &req.queue_name,
+                task_name: &req.task_name,
+                input: input.as_ref(),
+                metadata: Some(&metadata),
+                priority: req.priority,
+                max_retries: req.max_retries,
+                timeout_seconds: req.timeout_seconds,
+            })
+            .map_err(Status::invalid_argument)?;
+
         let scheduled_at = if req.scheduled_at.is_empty() {
             None
         } else {
@@ -71,64 +156,169 @@ impl api_service_server::ApiService for ApiServiceImpl {
             )
         };
 
-        let max_retries = if req.max_retries == 0 {
-            3
-        } else {
-            req.max_retries
+        let defaults = self
+            .dispatcher
+            .queue_policies()
+            .task_defaults(&req.queue_name);
+        let max_retries = defaults.max_retries(req.max_retries);
+        let timeout_seconds = defaults.timeout_seconds(req.timeout_seconds);
+
+        let now = chrono::Utc::now();
+        let deadline_ms = valka_core::resolve_deadline_ms(
+            (req.deadline_ms != 0).then_some(req.deadline_ms),
+            (req.deadline_in_ms != 0).then_some(req.deadline_in_ms),
+            now.timestamp_millis(),
+        )
+        .map_err(Status::invalid_argument)?;
+        let callback_url = (!req.callback_url.is_empty()).then(|| req.callback_url.clone());
+        if let Some(url) = &callback_url {
+            self.dispatcher
+                .webhooks()
+                .validate_callback_url(url)
+                .map_err(Status::invalid_argument)?;
+        }
+
+        let retry_backoff = req
+            .retry_backoff
+            .map(retry_backoff_from_proto)
+            .unwrap_or_default();
+        retry_backoff.validate().map_err(Status::invalid_argument)?;
+        let required_labels: valka_core::labels::Labels =
+            req.required_labels.clone().into_iter().collect();
+        valka_core::labels::validate_required(&required_labels)
+            .map_err(Status::invalid_argument)?;
+
+        let params = valka_db::queries::tasks::CreateTaskParams {
+            id: task_id.0.clone(),
+            queue_name: req.queue_name.clone(),
+            task_name: req.task_name.clone(),
+            partition_id: partition.0,
+            input: input.clone(),
+            priority: req.priority,
+            max_retries,
+            timeout_seconds,
+            idempotency_key: if req.idempotency_key.is_empty() {
+                None
+            } else {
+                Some(req.idempotency_key.clone())
+            },
+            metadata: metadata.clone(),
+            scheduled_at,
+            deadline_at: deadline_ms.and_then(chrono::DateTime::from_timestamp_millis),
+            traceparent: traceparent.clone(),
+            durability: Durability::parse(&req.durability).map_err(Status::invalid_argument)?,
+            fifo_key: fifo_key.clone(),
+            callback_url,
+            retry_backoff,
+            required_labels,
         };
-        let timeout_seconds = if req.timeout_seconds == 0 {
-            300
+        if let Some(reason) = self.dispatcher.ephemeral_rejection(&params) {
+            return Err(Status::invalid_argument(reason));
+        }
+
+        // A retried create is answered before its input is written again
+        if offload.is_some()
+            && let Some(key) = params.idempotency_key.as_deref()
+            && let Some(existing) =
+                valka_db::queries::tasks::get_task_by_idempotency_key(&self.pool, key)
+                    .await
+                    .map_err(|e| Status::internal(format!("Database error: {e}")))?
+        {
+            return Ok(Response::new(CreateTaskResponse {
+                task: Some(task_row_to_proto(existing)),
+                deduplicated: true,
+            }));
+        }
+
+        let quotas = self.dispatcher.quotas();
+        quotas
+            .try_acquire(&req.queue_name, now)
+            .map_err(quota_exceeded_status)?;
+        if let Some(offload) = &offload
+            && let Err(e) = self.dispatcher.write_input(offload).await
+        {
+            quotas.release(&req.queue_name);
+            return Err(blob_status(e));
+        }
+
+        // Ephemeral tasks skip the insert when a worker on this node takes them
+        // right away; otherwise they are persisted like any other task
+        let mut announced = false;
+        let params = if params.durability == Durability::Ephemeral
+            && deadline_ms.is_none_or(|d| d > now.timestamp_millis())
+            && self
+                .cluster
+                .owns_partition(&req.queue_name, partition.0)
+                .await
+        {
+            let response = params.unwritten_row("DISPATCHING", now);
+            self.emit_task_created(&task_id.0, &req.queue_name);
+            announced = true;
+            match self.dispatcher.offer_ephemeral(params) {
+                Ok(()) => {
+                    valka_core::metrics::record_task_created(&req.queue_name);
+                    return Ok(Response::new(CreateTaskResponse {
+                        task: Some(task_row_to_proto(response)),
+                        deduplicated: false,
+                    }));
+                }
+                Err(params) => *params,
+            }
         } else {
-            req.timeout_seconds
+            params
         };
 
         // Always persist to PG first
-        let task_row = valka_db::queries::tasks::create_task(
-            &self.pool,
-            valka_db::queries::tasks::CreateTaskParams {
-                id: task_id.0.clone(),
-                queue_name: req.queue_name.clone(),
-                task_name: req.task_name.clone(),
-                partition_id: partition.0,
-                input: input.clone(),
-                priority: req.priority,
-                max_retries,
-                timeout_seconds,
-                idempotency_key: if req.idempotency_key.is_empty() {
-                    None
-                } else {
-                    Some(req.idempotency_key.clone())
-                },
-                metadata: metadata.clone(),
-                scheduled_at,
-            },
-        )
-        .await
-        .map_err(|e| {
-            if let sqlx::Error::Database(ref db_err) = e
-                && db_err.constraint() == Some("idx_tasks_idempotency")
-            {
-                return Status::already_exists("Task with this idempotency key already exists");
+        let created = match fault_point!("grpc.create_task.before_insert", &req.queue_name) {
+            Ok(()) => valka_db::queries::tasks::create_task(&self.pool, params).await,
+            Err(e) => Err(e.into()),
+        };
+        let mut task_row = match created {
+            Ok(task_row) => task_row,
+            Err(e) => {
+                quotas.release(&req.queue_name);
+                self.dispatcher.discard_input(offload).await;
+                if !valka_db::queries::tasks::is_idempotency_conflict(&e) {
+                    return Err(Status::internal(format!("Database error: {e}")));
+                }
+                // A retried create gets back the task its first attempt made
+                let existing = valka_db::queries::tasks::get_task_by_idempotency_key(
+                    &self.pool,
+                    &req.idempotency_key,
+                )
+                .await
+                .map_err(|e| Status::internal(format!("Database error: {e}")))?
+                .ok_or_else(|| {
+                    Status::already_exists("Task with this idempotency key already exists")
+                })?;
+                return Ok(Response::new(CreateTaskResponse {
+                    task: Some(task_row_to_proto(existing)),
+                    deduplicated: true,
+                }));
             }
-            Status::internal(format!("Database error: {e}"))
-        })?;
+        };
 
         valka_core::metrics::record_task_created(&req.queue_name);
 
-        // Emit task created event
-        let event = TaskEvent {
-            event_id: uuid::Uuid::now_v7().to_string(),
-            task_id: task_id.0.clone(),
-            queue_name: req.queue_name.clone(),
-            previous_status: 0,
-            new_status: 1, // PENDING
-            worker_id: String::new(),
-            node_id: self.node_id.0.clone(),
-            attempt_number: 0,
-            error_message: String::new(),
-            timestamp_ms: chrono::Utc::now().timestamp_millis(),
-        };
-        let _ = self.event_tx.send(event);
+        // A deadline that has already passed fails the task without ever dispatching it
+        if task_row.deadline_expired(now) {
+            if let Some(expired) = self
+                .dispatcher
+                .expire_before_dispatch(&task_row.id, &task_row.queue_name)
+                .await
+                .map_err(|e| Status::internal(format!("Database error: {e}")))?
+            {
+                task_row = expired;
+            }
+            return Ok(Response::new(CreateTaskResponse {
+                task: Some(task_row_to_proto(task_row)),
+                deduplicated: false,
+            }));
+        }
+
+        if !announced {
+            self.emit_task_created(&task_id.0, &req.queue_name);
+        }
 
         // Check if we own this partition; if not, forward to owner
         if !self
@@ -140,13 +330,15 @@ impl api_service_server::ApiService for ApiServiceImpl {
                 .get_partition_owner_addr(&req.queue_name, partition.0)
                 .await
         {
+            // Coalesced with other creates bound for the same node
             let _ = self
                 .forwarder
-                .forward_task(&owner_addr, &task_id.0, &req.queue_name, partition.0)
+                .forward_task_batched(&owner_addr, &task_id.0, &req.queue_name, partition.0)
                 .await;
             valka_core::metrics::record_task_forwarded(&req.queue_name);
             return Ok(Response::new(CreateTaskResponse {
                 task: Some(task_row_to_proto(task_row)),
+                deduplicated: false,
             }));
         }
         // If owner unknown, fall through to local sync match (safety)
@@ -163,19 +355,71 @@ impl api_service_server::ApiService for ApiServiceImpl {
                 timeout_seconds,
                 metadata: metadata.to_string(),
                 priority: req.priority,
+                deadline_ms,
+                ready_at_ms: Some(task_row.created_at.timestamp_millis()),
+                created_at_ms: Some(task_row.created_at.timestamp_millis()),
+                scheduled_at_ms: task_row.scheduled_at.map(|at| at.timestamp_millis()),
+                traceparent,
+                fifo_key,
+                required_labels: task_row.required_labels(),
+                ephemeral: None,
             };
 
             // Fire and forget the sync match - if it fails, TaskReader will pick it up
-            let _ = self
-                .matching
-                .offer_task(&req.queue_name, partition, envelope);
+            self.dispatcher.offer_new_task(partition, envelope).await;
         }
 
         Ok(Response::new(CreateTaskResponse {
             task: Some(task_row_to_proto(task_row)),
+            deduplicated: false,
         }))
     }
 
+    /// Persist a queue's paused flag, apply it here straight away and publish
+    /// it so other nodes apply it without waiting for their next refresh.
+    async fn set_queue_paused(
+        &self,
+        queue_name: &str,
+        paused: bool,
+    ) -> Result<QueueConfig, Status> {
+        if queue_name.is_empty() {
+            return Err(Status::invalid_argument("queue_name is required"));
+        }
+        let config = valka_db::queries::queue_configs::set_paused(&self.pool, queue_name, paused)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?;
+        self.dispatcher.queue_policies().apply_config(&config);
+        self.cluster
+            .set_key(
+                &crate::server::queue_paused_key(queue_name),
+                &paused.to_string(),
+            )
+            .await;
+        Ok(queue_config_row_to_proto(config))
+    }
+}
+
+#[tonic::async_trait]
+impl api_service_server::ApiService for ApiServiceImpl {
+    async fn create_task(
+        &self,
+        request: Request<CreateTaskRequest>,
+    ) -> Result<Response<CreateTaskResponse>, Status> {
+        // Clients retry UNAVAILABLE against another node
+        if !self.dispatcher.shutdown().accepting_work() {
+            return Err(Status::unavailable("Node is shutting down"));
+        }
+        let span = info_span!(
+            "create_task",
+            queue = %request.get_ref().queue_name,
+            task_id = tracing::field::Empty,
+        );
+        trace_context::set_parent(&span, &trace_context::extract_metadata(request.metadata()));
+        self.create_task_traced(request.into_inner())
+            .instrument(span)
+            .await
+    }
+
     async fn get_task(
         &self,
         request: Request<GetTaskRequest>,
@@ -185,9 +429,21 @@ impl api_service_server::ApiService for ApiServiceImpl {
             .await
             .map_err(|e| Status::internal(format!("Database error: {e}")))?
             .ok_or_else(|| Status::not_found(format!("Task not found: {}", req.task_id)))?;
+        let retry_plan = task
+            .retry_plan(self.dispatcher.retry_policy(), chrono::Utc::now())
+            .map(|plan| RetryPlan {
+                next_attempt_at: plan
+                    .next_attempt_at
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default(),
+                attempts_remaining: plan.attempts_remaining,
+                schedule: plan.schedule.iter().map(|t| t.to_rfc3339()).collect(),
+                dead_letter: plan.dead_letter,
+            });
 
         Ok(Response::new(GetTaskResponse {
             task: Some(task_row_to_proto(task)),
+            retry_plan,
         }))
     }
 
@@ -196,16 +452,28 @@ impl api_service_server::ApiService for ApiServiceImpl {
         request: Request<ListTasksRequest>,
     ) -> Result<Response<ListTasksResponse>, Status> {
         let req = request.into_inner();
-        let queue_name = if req.queue_name.is_empty() {
-            None
-        } else {
-            Some(req.queue_name.as_str())
+        let mut statuses = Vec::new();
+        for status in std::iter::once(req.status)
+            .chain(req.statuses.iter().copied())
+            .filter(|s| *s != 0)
+        {
+            let status = proto_status_to_str(status)
+                .ok_or_else(|| Status::invalid_argument(format!("Unknown status: {status}")))?;
+            statuses.push(status.to_string());
+        }
+        let order = match TaskOrder::try_from(req.order) {
+            Ok(TaskOrder::CreatedDesc) => valka_db::queries::tasks::TaskOrder::CreatedDesc,
+            Ok(TaskOrder::CreatedAsc) => valka_db::queries::tasks::TaskOrder::CreatedAsc,
+            Ok(TaskOrder::Priority) => valka_db::queries::tasks::TaskOrder::Priority,
+            Err(_) => return Err(Status::invalid_argument("Unknown order")),
         };
-
-        let status_filter = if req.status == 0 {
-            None
-        } else {
-            proto_status_to_str(req.status)
+        let non_empty = |s: String| if s.is_empty() { None } else { Some(s) };
+        let timestamp = |ms: i64| {
+            if ms == 0 {
+                None
+            } else {
+                chrono::DateTime::from_timestamp_millis(ms)
+            }
         };
 
         let (limit, offset) = if let Some(ref p) = req.pagination {
@@ -215,15 +483,21 @@ impl api_service_server::ApiService for ApiServiceImpl {
             (50, 0)
         };
 
-        let tasks = valka_db::queries::tasks::list_tasks(
-            &self.pool,
-            queue_name,
-            status_filter,
+        let search = valka_db::queries::tasks::TaskSearch {
+            queue_name: non_empty(req.queue_name),
+            statuses,
+            task_name_prefix: non_empty(req.task_name_prefix),
+            created_after: timestamp(req.created_after_ms),
+            created_before: timestamp(req.created_before_ms),
+            idempotency_key: non_empty(req.idempotency_key),
+            error_contains: non_empty(req.error_contains),
+            order,
             limit,
             offset,
-        )
-        .await
-        .map_err(|e| Status::internal(format!("Database error: {e}")))?;
+        };
+        let tasks = valka_db::queries::tasks::search_tasks(&self.pool, &search)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?;
 
         let next_token = if tasks.len() as i64 == limit {
             (offset + limit).to_string()
@@ -252,8 +526,15 @@ impl api_service_server::ApiService for ApiServiceImpl {
                 ))
             })?;
 
-        // Forward cancellation to worker if running
-        self.dispatcher.cancel_task_on_worker(&req.task_id).await;
+        // Forward cancellation to worker if running, on whichever node it streams to
+        run_relay::cancel_on_worker(
+            &self.pool,
+            &self.dispatcher,
+            &self.cluster,
+            &self.forwarder,
+            &req.task_id,
+        )
+        .await;
 
         // Emit cancel event
         let event = TaskEvent {
@@ -267,14 +548,57 @@ impl api_service_server::ApiService for ApiServiceImpl {
             attempt_number: 0,
             error_message: String::new(),
             timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            suppressed_count: 0,
+            progress: None,
+            progress_message: String::new(),
         };
-        let _ = self.event_tx.send(event);
+        self.dispatcher.events().emit(event);
 
         Ok(Response::new(CancelTaskResponse {
             task: Some(task_row_to_proto(task)),
         }))
     }
 
+    async fn reclaim_task(
+        &self,
+        request: Request<ReclaimTaskRequest>,
+    ) -> Result<Response<ReclaimTaskResponse>, Status> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok());
+        if !self.admin.allows(authorization) {
+            return Err(Status::unauthenticated("Admin token required"));
+        }
+        let req = request.into_inner();
+        if req.reason.trim().is_empty() {
+            return Err(Status::invalid_argument("reason is required"));
+        }
+
+        let reclaimed = self
+            .dispatcher
+            .reclaim_task(&req.task_id, &req.reason, req.immediate)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?;
+        let Some(reclaimed) = reclaimed else {
+            let task = valka_db::queries::tasks::get_task(&self.pool, &req.task_id)
+                .await
+                .map_err(|e| Status::internal(format!("Database error: {e}")))?
+                .ok_or_else(|| Status::not_found(format!("Task not found: {}", req.task_id)))?;
+            return Err(Status::failed_precondition(format!(
+                "Task {} is {}, not RUNNING",
+                req.task_id, task.status
+            )));
+        };
+
+        Ok(Response::new(ReclaimTaskResponse {
+            task: Some(task_row_to_proto(reclaimed.task)),
+            abandoned_run_id: reclaimed.run.id,
+            worker_id: reclaimed.run.worker_id,
+            worker_notified: reclaimed.worker_notified,
+        }))
+    }
+
     async fn send_signal(
         &self,
         request: Request<SendSignalRequest>,
@@ -329,10 +653,14 @@ impl api_service_server::ApiService for ApiServiceImpl {
             timestamp_ms: signal.created_at.timestamp_millis(),
         };
 
-        let delivered = self
-            .dispatcher
-            .send_signal_to_worker(&req.task_id, task_signal)
-            .await;
+        let delivered = run_relay::signal_worker(
+            &self.pool,
+            &self.dispatcher,
+            &self.cluster,
+            &self.forwarder,
+            task_signal,
+        )
+        .await;
 
         if delivered {
             let _ = valka_db::queries::signals::mark_delivered(&self.pool, &signal.id).await;
@@ -344,19 +672,64 @@ impl api_service_server::ApiService for ApiServiceImpl {
         }))
     }
 
+    async fn list_signals(
+        &self,
+        request: Request<ListSignalsRequest>,
+    ) -> Result<Response<ListSignalsResponse>, Status> {
+        let req = request.into_inner();
+        if req.task_id.is_empty() {
+            return Err(Status::invalid_argument("task_id is required"));
+        }
+        let status = match req.status.as_str() {
+            "" => None,
+            "PENDING" | "DELIVERED" | "ACKNOWLEDGED" => Some(req.status.as_str()),
+            other => {
+                return Err(Status::invalid_argument(format!(
+                    "Unknown signal status: {other}"
+                )));
+            }
+        };
+
+        let signals = valka_db::queries::signals::list_signals(&self.pool, &req.task_id, status)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?;
+
+        Ok(Response::new(ListSignalsResponse {
+            signals: signals
+                .into_iter()
+                .map(|s| SignalInfo {
+                    id: s.id,
+                    task_id: s.task_id,
+                    signal_name: s.signal_name,
+                    payload: s.payload.map(|v| v.to_string()).unwrap_or_default(),
+                    status: s.status,
+                    created_at: s.created_at.to_rfc3339(),
+                    delivered_at: s.delivered_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    acknowledged_at: s
+                        .acknowledged_at
+                        .map(|t| t.to_rfc3339())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        }))
+    }
+
     type SubscribeEventsStream =
         Pin<Box<dyn Stream<Item = Result<TaskEvent, Status>> + Send + 'static>>;
 
     async fn subscribe_events(
         &self,
-        _request: Request<SubscribeEventsRequest>,
+        request: Request<SubscribeEventsRequest>,
     ) -> Result<Response<Self::SubscribeEventsStream>, Status> {
+        let queue_name = request.into_inner().queue_name;
         let mut rx = self.event_tx.subscribe();
         let (tx, rx_stream) = mpsc::channel(256);
 
         tokio::spawn(async move {
+            let _subscriber = valka_core::metrics::track_event_subscriber("grpc");
             loop {
                 match rx.recv().await {
+                    Ok(event) if !queue_name.is_empty() && event.queue_name != queue_name => {}
                     Ok(event) => {
                         if tx.send(Ok(event)).await.is_err() {
                             break;
@@ -381,35 +754,351 @@ impl api_service_server::ApiService for ApiServiceImpl {
         request: Request<SubscribeLogsRequest>,
     ) -> Result<Response<Self::SubscribeLogsStream>, Status> {
         let req = request.into_inner();
-        let (tx, rx) = mpsc::channel(256);
-
-        // If include_history, fetch from PG first
-        if req.include_history {
-            let pool = self.pool.clone();
-            let run_id = req.task_run_id.clone();
-            let tx_clone = tx.clone();
-            tokio::spawn(async move {
-                if let Ok(logs) =
-                    valka_db::queries::task_logs::get_logs_for_run(&pool, &run_id, 10000, None)
-                        .await
-                {
-                    for log in logs {
-                        let entry = LogEntry {
-                            task_run_id: log.task_run_id,
-                            timestamp_ms: log.timestamp_ms,
-                            level: str_to_log_level(&log.level),
-                            message: log.message,
-                            metadata: log.metadata.map(|m| m.to_string()).unwrap_or_default(),
-                        };
-                        if tx_clone.send(Ok(entry)).await.is_err() {
-                            break;
-                        }
-                    }
+        if req.task_run_id.is_empty() {
+            return Err(Status::invalid_argument("task_run_id is required"));
+        }
+        valka_db::queries::task_runs::get_task_run(&self.pool, &req.task_run_id)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?
+            .ok_or_else(|| Status::not_found("Task run not found"))?;
+
+        // Subscribed before the cursor is read so nothing flushed in between is missed
+        let live = self.dispatcher.log_stream().subscribe();
+        let after_id = if req.include_history {
+            None
+        } else {
+            valka_db::queries::task_logs::latest_log_id(&self.pool, &req.task_run_id)
+                .await
+                .map_err(|e| Status::internal(format!("Database error: {e}")))?
+        };
+
+        // Ends when the run does
+        let stream = log_tail::tail_run_logs(self.pool.clone(), live, req.task_run_id, after_id)
+            .filter_map(|item| async move {
+                match item {
+                    LogTailItem::Log(log) => Some(Ok(LogEntry {
+                        task_run_id: log.task_run_id,
+                        timestamp_ms: log.timestamp_ms,
+                        level: str_to_log_level(&log.level),
+                        message: log.message,
+                        metadata: log.metadata.map(|m| m.to_string()).unwrap_or_default(),
+                    })),
+                    LogTailItem::End(_) => None,
                 }
             });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_queue_config(
+        &self,
+        request: Request<GetQueueConfigRequest>,
+    ) -> Result<Response<GetQueueConfigResponse>, Status> {
+        let req = request.into_inner();
+        let config =
+            valka_db::queries::queue_configs::get_queue_config(&self.pool, &req.queue_name)
+                .await
+                .map_err(|e| Status::internal(format!("Database error: {e}")))?
+                .ok_or_else(|| {
+                    Status::not_found(format!("Queue config not found: {}", req.queue_name))
+                })?;
+
+        Ok(Response::new(GetQueueConfigResponse {
+            config: Some(queue_config_row_to_proto(config)),
+        }))
+    }
+
+    async fn update_queue_config(
+        &self,
+        request: Request<UpdateQueueConfigRequest>,
+    ) -> Result<Response<UpdateQueueConfigResponse>, Status> {
+        let req = request.into_inner();
+        if req.queue_name.is_empty() {
+            return Err(Status::invalid_argument("queue_name is required"));
+        }
+
+        let update = valka_db::queries::queue_configs::QueueOwnershipUpdate {
+            owner_team: req.owner_team,
+            contact: req.contact,
+            runbook_url: req.runbook_url,
+            labels: req.replace_labels.then(|| req.labels.into_iter().collect()),
+            min_worker_version: req.min_worker_version,
+            allow_ephemeral: req.allow_ephemeral,
+            template_input: req.template_input,
+            fifo: req.fifo,
+            default_max_retries: req.default_max_retries,
+            default_timeout_seconds: req.default_timeout_seconds,
+            max_concurrency: req.max_concurrency,
+            paused: req.paused,
+            retention_days: req.retention_days,
+            dead_letter_enabled: req.dead_letter_enabled,
+            max_dispatch_per_second: req.max_dispatch_per_second,
+        };
+        update.validate().map_err(Status::invalid_argument)?;
+
+        let config = valka_db::queries::queue_configs::upsert_queue_ownership(
+            &self.pool,
+            &req.queue_name,
+            &update,
+        )
+        .await
+        .map_err(|e| Status::internal(format!("Database error: {e}")))?;
+        self.dispatcher.queue_policies().apply_config(&config);
+
+        Ok(Response::new(UpdateQueueConfigResponse {
+            config: Some(queue_config_row_to_proto(config)),
+        }))
+    }
+
+    async fn list_queue_configs(
+        &self,
+        request: Request<ListQueueConfigsRequest>,
+    ) -> Result<Response<ListQueueConfigsResponse>, Status> {
+        let req = request.into_inner();
+        let owner_team = (!req.owner_team.is_empty()).then_some(req.owner_team.as_str());
+        let configs = valka_db::queries::queue_configs::list_queue_configs(&self.pool, owner_team)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?;
+
+        Ok(Response::new(ListQueueConfigsResponse {
+            configs: configs.into_iter().map(queue_config_row_to_proto).collect(),
+        }))
+    }
+
+    async fn pause_queue(
+        &self,
+        request: Request<PauseQueueRequest>,
+    ) -> Result<Response<PauseQueueResponse>, Status> {
+        let req = request.into_inner();
+        let config = self.set_queue_paused(&req.queue_name, true).await?;
+        Ok(Response::new(PauseQueueResponse {
+            config: Some(config),
+        }))
+    }
+
+    async fn resume_queue(
+        &self,
+        request: Request<ResumeQueueRequest>,
+    ) -> Result<Response<ResumeQueueResponse>, Status> {
+        let req = request.into_inner();
+        let config = self.set_queue_paused(&req.queue_name, false).await?;
+        Ok(Response::new(ResumeQueueResponse {
+            config: Some(config),
+        }))
+    }
+
+    async fn create_schedule(
+        &self,
+        request: Request<CreateScheduleRequest>,
+    ) -> Result<Response<CreateScheduleResponse>, Status> {
+        let req = request.into_inner();
+        if req.queue_name.is_empty() || req.task_name.is_empty() {
+            return Err(Status::invalid_argument(
+                "queue_name and task_name are required",
+            ));
         }
+        let input: Option<serde_json::Value> = if req.input.is_empty() {
+            None
+        } else {
+            Some(
+                serde_json::from_str(&req.input)
+                    .map_err(|e| Status::invalid_argument(format!("Invalid input JSON: {e}")))?,
+            )
+        };
+        let next_fire_at =
+            valka_scheduler::cron::first_fire(&req.cron_expression, chrono::Utc::now())
+                .map_err(Status::invalid_argument)?;
+        let defaults = self
+            .dispatcher
+            .queue_policies()
+            .task_defaults(&req.queue_name);
+        let params = valka_db::queries::schedules::CreateScheduleParams {
+            id: TaskId::new().0,
+            cron_expression: req.cron_expression.trim().to_string(),
+            max_retries: defaults.max_retries(req.max_retries),
+            timeout_seconds: defaults.timeout_seconds(req.timeout_seconds),
+            queue_name: req.queue_name,
+            task_name: req.task_name,
+            input,
+            priority: req.priority,
+            next_fire_at,
+        };
+        let row = valka_db::queries::schedules::create_schedule(&self.pool, params)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?;
 
-        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+        Ok(Response::new(CreateScheduleResponse {
+            schedule: Some(schedule_row_to_proto(row)),
+        }))
+    }
+
+    async fn list_schedules(
+        &self,
+        request: Request<ListSchedulesRequest>,
+    ) -> Result<Response<ListSchedulesResponse>, Status> {
+        let req = request.into_inner();
+        let queue_name = (!req.queue_name.is_empty()).then_some(req.queue_name.as_str());
+        let rows = valka_db::queries::schedules::list_schedules(&self.pool, queue_name)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?;
+
+        Ok(Response::new(ListSchedulesResponse {
+            schedules: rows.into_iter().map(schedule_row_to_proto).collect(),
+        }))
+    }
+
+    async fn delete_schedule(
+        &self,
+        request: Request<DeleteScheduleRequest>,
+    ) -> Result<Response<DeleteScheduleResponse>, Status> {
+        let req = request.into_inner();
+        let deleted = valka_db::queries::schedules::delete_schedule(&self.pool, &req.schedule_id)
+            .await
+            .map_err(|e| Status::internal(format!("Database error: {e}")))?;
+        if !deleted {
+            return Err(Status::not_found(format!(
+                "Schedule not found: {}",
+                req.schedule_id
+            )));
+        }
+
+        Ok(Response::new(DeleteScheduleResponse {}))
+    }
+
+    async fn list_workers(
+        &self,
+        request: Request<ListWorkersRequest>,
+    ) -> Result<Response<ListWorkersResponse>, Status> {
+        let req = request.into_inner();
+        let mut workers = self.dispatcher.list_workers();
+        let mut unreachable_nodes = Vec::new();
+
+        // Each node only tracks the workers streaming to it
+        if !req.local_only && self.cluster.is_clustered() {
+            let peers = self.cluster.members().await;
+            let mut lookups = Vec::new();
+            for peer in peers.into_iter().filter(|p| *p != self.node_id.0) {
+                let addr = self.cluster.get_node_grpc_addr(&peer).await;
+                lookups.push(async move {
+                    let result = match &addr {
+                        Some(addr) => self.forwarder.list_workers(addr).await,
+                        None => Err(anyhow::anyhow!("no gRPC address known")),
+                    };
+                    (peer, result)
+                });
+            }
+            for (peer, result) in futures::future::join_all(lookups).await {
+                match result {
+                    Ok(remote) => workers.extend(remote),
+                    Err(e) => {
+                        warn!(node_id = %peer, error = %e, "Failed to list workers on peer");
+                        unreachable_nodes.push(peer);
+                    }
+                }
+            }
+        }
+
+        workers.sort_by(|a, b| {
+            a.node_id
+                .cmp(&b.node_id)
+                .then_with(|| a.worker_name.cmp(&b.worker_name))
+                .then_with(|| a.worker_id.cmp(&b.worker_id))
+        });
+        unreachable_nodes.sort();
+        Ok(Response::new(ListWorkersResponse {
+            workers,
+            node_id: self.node_id.0.clone(),
+            unreachable_nodes,
+        }))
+    }
+
+    async fn drain_worker(
+        &self,
+        request: Request<DrainWorkerRequest>,
+    ) -> Result<Response<DrainWorkerResponse>, Status> {
+        let req = request.into_inner();
+        if req.worker_id.is_empty() {
+            return Err(Status::invalid_argument("worker_id is required"));
+        }
+        let timeout = drain_timeout(req.timeout_seconds)?;
+
+        if let Some(active) = self.dispatcher.drain_worker(&req.worker_id, timeout) {
+            return Ok(Response::new(DrainWorkerResponse {
+                node_id: self.node_id.0.clone(),
+                active_tasks: active as i32,
+            }));
+        }
+
+        // Only the node holding its stream can drain it
+        if self.cluster.is_clustered() {
+            let peers = self.cluster.members().await;
+            for peer in peers.into_iter().filter(|p| *p != self.node_id.0) {
+                let Some(addr) = self.cluster.get_node_grpc_addr(&peer).await else {
+                    continue;
+                };
+                match self.forwarder.drain_worker(&addr, req.clone()).await {
+                    Ok(Some(resp)) => return Ok(Response::new(resp)),
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!(node_id = %peer, error = %e, "Failed to drain worker on peer");
+                    }
+                }
+            }
+        }
+        Err(Status::not_found(format!(
+            "Worker not found: {}",
+            req.worker_id
+        )))
+    }
+
+    async fn get_cluster_status(
+        &self,
+        request: Request<GetClusterStatusRequest>,
+    ) -> Result<Response<GetClusterStatusResponse>, Status> {
+        let req = request.into_inner();
+
+        let members = self
+            .cluster
+            .member_details()
+            .await
+            .into_iter()
+            .map(|m| ClusterMember {
+                is_self: m.node_id == self.node_id.0,
+                node_id: m.node_id,
+                grpc_addr: m.grpc_addr.unwrap_or_default(),
+                live: m.live,
+                joined_at: m.joined_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            })
+            .collect();
+
+        let mut partitions = Vec::new();
+        if !req.queue_name.is_empty() {
+            let num_partitions = self.matching.config().num_partitions;
+            for assignment in self
+                .cluster
+                .partition_map(&req.queue_name, num_partitions)
+                .await
+            {
+                let grpc_addr = self
+                    .cluster
+                    .get_node_grpc_addr(&assignment.owner_node_id)
+                    .await;
+                partitions.push(PartitionOwner {
+                    partition_id: assignment.partition_id.0,
+                    node_id: assignment.owner_node_id,
+                    grpc_addr: grpc_addr.unwrap_or_default(),
+                });
+            }
+        }
+
+        let leader_since = self.cluster.leader_since().await;
+        Ok(Response::new(GetClusterStatusResponse {
+            node_id: self.node_id.0.clone(),
+            clustered: self.cluster.is_clustered(),
+            members,
+            is_leader: leader_since.is_some(),
+            leader_since: leader_since.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            partitions,
+        }))
     }
 }
 
@@ -422,6 +1111,10 @@ impl worker_service_server::WorkerService for WorkerServiceImpl {
         &self,
         request: Request<Streaming<WorkerRequest>>,
     ) -> Result<Response<Self::SessionStream>, Status> {
+        // Streams already open carry on; new workers belong on another node
+        if !self.dispatcher.shutdown().accepting_work() {
+            return Err(Status::unavailable("Node is shutting down"));
+        }
         let inbound = request.into_inner();
         let (response_tx, response_rx) = mpsc::channel(256);
 
@@ -445,8 +1138,16 @@ pub async fn serve_grpc(
     cluster: Arc<ClusterManager>,
     forwarder: NodeForwarder,
     _log_tx: mpsc::Sender<LogEntry>,
+    admin: AdminAuth,
+    auth: ApiAuth,
     mut shutdown: watch::Receiver<bool>,
 ) -> Result<(), anyhow::Error> {
+    let cluster_auth = cluster.auth().clone();
+    let health_pool = pool.clone();
+    let health_dispatcher = dispatcher.clone();
+    let health_cluster = cluster.clone();
+    let api_auth = auth.clone();
+    let drain = dispatcher.clone();
     let api_service = ApiServiceImpl {
         pool: pool.clone(),
         matching: matching.clone(),
@@ -455,13 +1156,17 @@ pub async fn serve_grpc(
         node_id: node_id.clone(),
         cluster,
         forwarder,
+        admin,
     };
 
-    let worker_service = WorkerServiceImpl { dispatcher };
+    let worker_service = WorkerServiceImpl {
+        dispatcher: dispatcher.clone(),
+    };
 
     let internal_service = InternalServiceImpl {
         pool,
         matching,
+        dispatcher,
         node_id,
         event_tx,
     };
@@ -470,6 +1175,13 @@ pub async fn serve_grpc(
     health_reporter
         .set_serving::<api_service_server::ApiServiceServer<ApiServiceImpl>>()
         .await;
+    tokio::spawn(health::run_grpc_health(
+        health_reporter,
+        health_pool,
+        health_dispatcher,
+        health_cluster,
+        shutdown.clone(),
+    ));
 
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(valka_proto::valka::v1::FILE_DESCRIPTOR_SET)
@@ -482,15 +1194,33 @@ pub async fn serve_grpc(
         .http2_keepalive_timeout(Some(std::time::Duration::from_secs(5)))
         .add_service(health_service)
         .add_service(reflection_service)
-        .add_service(api_service_server::ApiServiceServer::new(api_service))
-        .add_service(worker_service_server::WorkerServiceServer::new(
-            worker_service,
-        ))
-        .add_service(internal_service_server::InternalServiceServer::new(
-            internal_service,
+        .add_service(api_service_server::ApiServiceServer::with_interceptor(
+            api_service,
+            move |request| api_auth.check_api(request),
         ))
+        .add_service(
+            worker_service_server::WorkerServiceServer::with_interceptor(
+                worker_service,
+                move |request| auth.check_worker(request),
+            ),
+        )
+        .add_service(
+            internal_service_server::InternalServiceServer::with_interceptor(
+                internal_service,
+                move |request| cluster_auth.verify(request),
+            ),
+        )
         .serve_with_shutdown(addr, async move {
             let _ = shutdown.changed().await;
+            // Keep result reporting open for tasks still in flight
+            if let Some(deadline) = drain.shutdown().deadline()
+                && !drain.wait_for_idle(deadline).await
+            {
+                warn!(
+                    active_tasks = drain.stats().active_tasks,
+                    "Closing gRPC with tasks still in flight"
+                );
+            }
         })
         .await?;
 
@@ -500,6 +1230,19 @@ pub async fn serve_grpc(
 // --- Helper functions ---
 
 fn task_row_to_proto(row: valka_db::queries::tasks::TaskRow) -> TaskMeta {
+    let deadline_expired = row.deadline_expired(chrono::Utc::now());
+    let backoff = row.retry_backoff();
+    let retry_backoff = (!backoff.is_default()).then(|| valka_proto::RetryBackoff {
+        base_seconds: backoff
+            .base_seconds
+            .map_or(0, |s| u32::try_from(s).unwrap_or(u32::MAX)),
+        max_seconds: backoff
+            .max_seconds
+            .map_or(0, |s| u32::try_from(s).unwrap_or(u32::MAX)),
+        multiplier: backoff.multiplier.unwrap_or(0.0),
+        jitter: backoff.jitter,
+    });
+    let required_labels = row.required_labels().into_iter().collect();
     TaskMeta {
         id: row.id,
         queue_name: row.queue_name,
@@ -517,6 +1260,68 @@ fn task_row_to_proto(row: valka_db::queries::tasks::TaskRow) -> TaskMeta {
         scheduled_at: row.scheduled_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
         created_at: row.created_at.to_rfc3339(),
         updated_at: row.updated_at.to_rfc3339(),
+        deadline_expired,
+        deadline_ms: row.deadline_at.map(|t| t.timestamp_millis()).unwrap_or(0),
+        durability: row.durability,
+        fifo_key: row.fifo_key.unwrap_or_default(),
+        callback_url: row.callback_url.unwrap_or_default(),
+        retry_backoff,
+        required_labels,
+    }
+}
+
+/// Zero fields of the proto message mean "use the scheduler's setting".
+fn retry_backoff_from_proto(backoff: valka_proto::RetryBackoff) -> valka_core::retry::RetryBackoff {
+    valka_core::retry::RetryBackoff {
+        base_seconds: (backoff.base_seconds != 0).then_some(u64::from(backoff.base_seconds)),
+        max_seconds: (backoff.max_seconds != 0).then_some(u64::from(backoff.max_seconds)),
+        multiplier: (backoff.multiplier != 0.0).then_some(backoff.multiplier),
+        jitter: backoff.jitter,
+    }
+}
+
+fn queue_config_row_to_proto(row: valka_db::queries::queue_configs::QueueConfigRow) -> QueueConfig {
+    let labels = serde_json::from_value(row.labels).unwrap_or_default();
+    QueueConfig {
+        queue_name: row.queue_name,
+        owner_team: row.owner_team.unwrap_or_default(),
+        contact: row.contact.unwrap_or_default(),
+        runbook_url: row.runbook_url.unwrap_or_default(),
+        labels,
+        created_at: row.created_at.to_rfc3339(),
+        updated_at: row.updated_at.to_rfc3339(),
+        min_worker_version: row.min_worker_version.unwrap_or_default(),
+        scheduled_hold: row.scheduled_hold,
+        release_max_per_tick: row.release_max_per_tick.unwrap_or(0),
+        allow_ephemeral: row.allow_ephemeral,
+        template_input: row.template_input,
+        fifo: row.fifo,
+        default_max_retries: row.default_max_retries.unwrap_or(0),
+        default_timeout_seconds: row.default_timeout_seconds.unwrap_or(0),
+        max_concurrency: row.max_concurrency.unwrap_or(0),
+        paused: row.paused,
+        retention_days: row.retention_days.unwrap_or(0),
+        dead_letter_enabled: row.dead_letter_enabled,
+        max_dispatch_per_second: row.max_dispatch_per_second.unwrap_or(0),
+    }
+}
+
+fn schedule_row_to_proto(row: valka_db::queries::schedules::ScheduleRow) -> TaskSchedule {
+    TaskSchedule {
+        id: row.id,
+        cron_expression: row.cron_expression,
+        queue_name: row.queue_name,
+        task_name: row.task_name,
+        input: row.input.map(|v| v.to_string()).unwrap_or_default(),
+        priority: row.priority,
+        max_retries: row.max_retries,
+        timeout_seconds: row.timeout_seconds,
+        next_fire_at: row.next_fire_at.to_rfc3339(),
+        last_fired_at: row
+            .last_fired_at
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default(),
+        created_at: row.created_at.to_rfc3339(),
     }
 }
 
@@ -530,6 +1335,7 @@ fn str_to_task_status(s: &str) -> i32 {
         "RETRY" => 6,
         "DEAD_LETTER" => 7,
         "CANCELLED" => 8,
+        "WAITING" => 9,
         _ => 0,
     }
 }
@@ -544,6 +1350,7 @@ fn proto_status_to_str(status: i32) -> Option<&'static str> {
         6 => Some("RETRY"),
         7 => Some("DEAD_LETTER"),
         8 => Some("CANCELLED"),
+        9 => Some("WAITING"),
         _ => None,
     }
 }
@@ -0,0 +1,112 @@
+//! Readiness: whether this node should be sent traffic. Checked on demand by
+//! REST `/readyz`, and periodically to keep the gRPC health service current.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::watch;
+use tonic_health::ServingStatus;
+use tonic_health::server::HealthReporter;
+use tracing::{info, warn};
+use valka_cluster::ClusterManager;
+use valka_db::DbPool;
+use valka_dispatcher::DispatcherService;
+use valka_proto::api_service_server;
+
+/// How often the gRPC health status is re-evaluated.
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// A database ping slower than this counts as a failure.
+const DB_PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The outcome of the readiness checks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    /// Why each failing check failed, by check name
+    pub failed: BTreeMap<&'static str, String>,
+}
+
+/// Run the readiness checks: the database answers, the node isn't shutting
+/// down, and gossip sees at least `gossip.min_ready_members` members.
+pub async fn check_readiness(
+    pool: &DbPool,
+    dispatcher: &DispatcherService,
+    cluster: &ClusterManager,
+) -> Readiness {
+    let mut failed = BTreeMap::new();
+
+    match tokio::time::timeout(DB_PING_TIMEOUT, sqlx::query("SELECT 1").execute(pool)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => {
+            failed.insert("database", e.to_string());
+        }
+        Err(_) => {
+            failed.insert("database", format!("no answer within {DB_PING_TIMEOUT:?}"));
+        }
+    }
+
+    let shutdown = dispatcher.shutdown();
+    if !shutdown.accepting_work() {
+        failed.insert("shutdown", shutdown.phase().as_str().to_string());
+    }
+
+    let min_members = cluster.min_ready_members();
+    let members = cluster.members().await.len();
+    if members < min_members {
+        failed.insert(
+            "cluster",
+            format!("{members} members in view, {min_members} required"),
+        );
+    }
+
+    Readiness {
+        ready: failed.is_empty(),
+        failed,
+    }
+}
+
+/// Keep the gRPC health status in step with [`check_readiness`]: the overall
+/// status and the API service go NOT_SERVING while a check fails and back to
+/// SERVING once all pass. Re-checks every [`HEALTH_CHECK_INTERVAL`], and at
+/// once when the shutdown phase moves.
+pub async fn run_grpc_health(
+    reporter: HealthReporter,
+    pool: DbPool,
+    dispatcher: DispatcherService,
+    cluster: Arc<ClusterManager>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut phase = dispatcher.shutdown().subscribe();
+    let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    let mut serving = true;
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            Ok(()) = phase.changed() => {}
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() {
+                    return;
+                }
+            }
+        }
+
+        let readiness = check_readiness(&pool, &dispatcher, &cluster).await;
+        if readiness.ready == serving {
+            continue;
+        }
+        serving = readiness.ready;
+        let status = if serving {
+            info!("Readiness checks pass again, gRPC health SERVING");
+            ServingStatus::Serving
+        } else {
+            warn!(failed = ?readiness.failed, "Readiness checks failing, gRPC health NOT_SERVING");
+            ServingStatus::NotServing
+        };
+        reporter.set_service_status("", status).await;
+        reporter
+            .set_service_status(api_service_server::SERVICE_NAME, status)
+            .await;
+    }
+}
@@ -4,10 +4,11 @@ use futures::Stream;
 use tokio::sync::{broadcast, mpsc};
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
-use tracing::debug;
+use tracing::{Instrument, debug, info_span, warn};
 
-use valka_core::{NodeId, PartitionId};
+use valka_core::{NodeId, PartitionId, trace_context};
 use valka_db::DbPool;
+use valka_dispatcher::DispatcherService;
 use valka_matching::MatchingService;
 use valka_matching::partition::TaskEnvelope;
 use valka_proto::*;
@@ -15,17 +16,16 @@ use valka_proto::*;
 pub struct InternalServiceImpl {
     pub pool: DbPool,
     pub matching: MatchingService,
+    pub dispatcher: DispatcherService,
     pub node_id: NodeId,
     pub event_tx: broadcast::Sender<TaskEvent>,
 }
 
-#[tonic::async_trait]
-impl internal_service_server::InternalService for InternalServiceImpl {
-    async fn forward_task(
+impl InternalServiceImpl {
+    async fn accept_forwarded_task(
         &self,
-        request: Request<ForwardTaskRequest>,
+        req: ForwardTaskRequest,
     ) -> Result<Response<ForwardTaskResponse>, Status> {
-        let req = request.into_inner();
         debug!(
             task_id = %req.task_id,
             queue = %req.queue_name,
@@ -47,19 +47,25 @@ impl internal_service_server::InternalService for InternalServiceImpl {
             task_run_id: String::new(),
             queue_name: task_row.queue_name.clone(),
             task_name: task_row.task_name.clone(),
-            input: task_row.input.map(|v| v.to_string()),
+            input: task_row.input.as_ref().map(|v| v.to_string()),
             attempt_number: task_row.attempt_count + 1,
             timeout_seconds: task_row.timeout_seconds,
             metadata: task_row.metadata.to_string(),
             priority: task_row.priority,
+            deadline_ms: task_row.deadline_at.map(|d| d.timestamp_millis()),
+            ready_at_ms: Some(task_row.ready_at().timestamp_millis()),
+            created_at_ms: Some(task_row.created_at.timestamp_millis()),
+            scheduled_at_ms: task_row.scheduled_at.map(|at| at.timestamp_millis()),
+            required_labels: task_row.required_labels(),
+            // Continue under this node's span; the stored context covers untraced forwards
+            traceparent: trace_context::current_traceparent().or(task_row.traceparent),
+            fifo_key: task_row.fifo_key,
+            ephemeral: None,
         };
 
         // Try sync match locally (on the owning node)
         let partition = PartitionId(req.partition_id);
-        let accepted = self
-            .matching
-            .offer_task(&req.queue_name, partition, envelope)
-            .is_ok();
+        let accepted = self.dispatcher.offer_new_task(partition, envelope).await;
 
         if accepted {
             debug!(task_id = %req.task_id, "Forwarded task accepted via sync match");
@@ -67,6 +73,52 @@ impl internal_service_server::InternalService for InternalServiceImpl {
 
         Ok(Response::new(ForwardTaskResponse { accepted }))
     }
+}
+
+#[tonic::async_trait]
+impl internal_service_server::InternalService for InternalServiceImpl {
+    async fn forward_task(
+        &self,
+        request: Request<ForwardTaskRequest>,
+    ) -> Result<Response<ForwardTaskResponse>, Status> {
+        let span = info_span!(
+            "accept_forwarded_task",
+            task_id = %request.get_ref().task_id,
+            queue = %request.get_ref().queue_name,
+        );
+        trace_context::set_parent(&span, &trace_context::extract_metadata(request.metadata()));
+        self.accept_forwarded_task(request.into_inner())
+            .instrument(span)
+            .await
+    }
+
+    async fn forward_tasks(
+        &self,
+        request: Request<ForwardTasksRequest>,
+    ) -> Result<Response<ForwardTasksResponse>, Status> {
+        let span = info_span!(
+            "accept_forwarded_tasks",
+            count = request.get_ref().tasks.len()
+        );
+        trace_context::set_parent(&span, &trace_context::extract_metadata(request.metadata()));
+        async {
+            let mut accepted = Vec::new();
+            for task in request.into_inner().tasks {
+                let task_id = task.task_id.clone();
+                // One bad task doesn't fail the rest; the readers pick it up from PG
+                match self.accept_forwarded_task(task).await {
+                    Ok(resp) => accepted.push(resp.into_inner().accepted),
+                    Err(status) => {
+                        warn!(task_id = %task_id, error = %status, "Forwarded task not offered");
+                        accepted.push(false);
+                    }
+                }
+            }
+            Ok(Response::new(ForwardTasksResponse { accepted }))
+        }
+        .instrument(span)
+        .await
+    }
 
     async fn forward_event(
         &self,
@@ -130,6 +182,60 @@ impl internal_service_server::InternalService for InternalServiceImpl {
             timestamp_ms: chrono::Utc::now().timestamp_millis(),
         }))
     }
+
+    async fn list_local_workers(
+        &self,
+        _request: Request<ListLocalWorkersRequest>,
+    ) -> Result<Response<ListWorkersResponse>, Status> {
+        Ok(Response::new(ListWorkersResponse {
+            workers: self.dispatcher.list_workers(),
+            node_id: self.node_id.0.clone(),
+            unreachable_nodes: Vec::new(),
+        }))
+    }
+
+    async fn drain_local_worker(
+        &self,
+        request: Request<DrainWorkerRequest>,
+    ) -> Result<Response<DrainWorkerResponse>, Status> {
+        let req = request.into_inner();
+        let timeout = crate::grpc::drain_timeout(req.timeout_seconds)?;
+        let active = self
+            .dispatcher
+            .drain_worker(&req.worker_id, timeout)
+            .ok_or_else(|| Status::not_found(format!("Worker not found: {}", req.worker_id)))?;
+        Ok(Response::new(DrainWorkerResponse {
+            node_id: self.node_id.0.clone(),
+            active_tasks: active as i32,
+        }))
+    }
+
+    async fn forward_cancel(
+        &self,
+        request: Request<ForwardCancelRequest>,
+    ) -> Result<Response<ForwardCancelResponse>, Status> {
+        let req = request.into_inner();
+        let notified = self.dispatcher.cancel_task_on_worker(&req.task_id).await;
+        debug!(task_id = %req.task_id, notified, "Received forwarded cancellation");
+        Ok(Response::new(ForwardCancelResponse { notified }))
+    }
+
+    async fn forward_signal(
+        &self,
+        request: Request<ForwardSignalRequest>,
+    ) -> Result<Response<ForwardSignalResponse>, Status> {
+        let signal = request
+            .into_inner()
+            .signal
+            .ok_or_else(|| Status::invalid_argument("signal is required"))?;
+        let task_id = signal.task_id.clone();
+        let delivered = self
+            .dispatcher
+            .send_signal_to_worker(&task_id, signal)
+            .await;
+        debug!(task_id = %task_id, delivered, "Received forwarded signal");
+        Ok(Response::new(ForwardSignalResponse { delivered }))
+    }
 }
 
 fn str_to_log_level(s: &str) -> i32 {
@@ -1,4 +1,14 @@
+pub mod admin;
+pub mod auth;
+#[cfg(feature = "sqlite")]
+pub mod dev;
 pub mod grpc;
+pub mod health;
 pub mod internal_grpc;
+pub mod log_tail;
+pub mod otel;
+pub mod reload;
 pub mod rest;
+pub mod run_relay;
 pub mod server;
+pub mod telemetry;
@@ -0,0 +1,127 @@
+//! Tailing a task run's logs: what PG has, then rows as the log ingester
+//! flushes them (`DispatcherService::log_stream`), until the run is over.
+//! Shared by the REST SSE stream and gRPC `SubscribeLogs`.
+
+use futures::Stream;
+use tokio::sync::broadcast;
+use tracing::warn;
+use valka_db::DbPool;
+use valka_db::queries::task_logs::{self, TaskLogRow};
+use valka_db::queries::task_runs;
+
+/// How often a tail checks the run and re-reads PG, which also picks up
+/// logs flushed on other nodes.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// Time after a run ends for the ingester to flush its last logs.
+const DRAIN_GRACE: chrono::TimeDelta = chrono::TimeDelta::seconds(2);
+const PAGE_SIZE: i64 = 1000;
+
+#[derive(Debug)]
+pub enum LogTailItem {
+    Log(TaskLogRow),
+    /// The run ended with this status; nothing follows.
+    End(String),
+}
+
+enum Step {
+    Live(TaskLogRow),
+    CatchUp,
+    Poll,
+}
+
+/// Logs of `run_id` with ids after `after_id` (all of them if `None`), in
+/// order and without duplicates. `live` must be subscribed before `after_id`
+/// is read so nothing flushed in between is missed. A tail that falls behind
+/// the live feed drops off it and catches up from PG, so it never holds up
+/// the ingester.
+pub fn tail_run_logs(
+    pool: DbPool,
+    mut live: broadcast::Receiver<TaskLogRow>,
+    run_id: String,
+    after_id: Option<i64>,
+) -> impl Stream<Item = LogTailItem> + Send + 'static {
+    async_stream::stream! {
+        let mut last_id = after_id;
+        let mut live_open = true;
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut step = Step::CatchUp;
+        loop {
+            match step {
+                Step::Live(row) => {
+                    if row.task_run_id == run_id && last_id < Some(row.id) {
+                        last_id = Some(row.id);
+                        yield LogTailItem::Log(row);
+                    }
+                }
+                Step::CatchUp | Step::Poll => {
+                    // Status first, so the catch-up after a finished run is complete
+                    let mut ended = None;
+                    if matches!(step, Step::Poll) {
+                        match task_runs::get_task_run(&pool, &run_id).await {
+                            Ok(Some(run)) => {
+                                let drained = run
+                                    .completed_at
+                                    .is_none_or(|at| at + DRAIN_GRACE <= chrono::Utc::now());
+                                if run.status != "RUNNING" && drained {
+                                    ended = Some(run.status);
+                                }
+                            }
+                            Ok(None) => break,
+                            Err(e) => {
+                                warn!(run_id = %run_id, error = %e, "Log tail status check failed");
+                                break;
+                            }
+                        }
+                    }
+                    match logs_after(&pool, &run_id, last_id).await {
+                        Ok(rows) => {
+                            for row in rows {
+                                last_id = last_id.max(Some(row.id));
+                                yield LogTailItem::Log(row);
+                            }
+                        }
+                        Err(e) => {
+                            warn!(run_id = %run_id, error = %e, "Log tail catch-up failed");
+                            break;
+                        }
+                    }
+                    if let Some(status) = ended {
+                        yield LogTailItem::End(status);
+                        break;
+                    }
+                }
+            }
+            step = tokio::select! {
+                row = live.recv(), if live_open => match row {
+                    Ok(row) => Step::Live(row),
+                    Err(broadcast::error::RecvError::Lagged(_)) => Step::CatchUp,
+                    Err(broadcast::error::RecvError::Closed) => {
+                        live_open = false;
+                        Step::CatchUp
+                    }
+                },
+                _ = poll.tick() => Step::Poll,
+            };
+        }
+    }
+}
+
+/// Every persisted log of `run_id` after `after_id`.
+async fn logs_after(
+    pool: &DbPool,
+    run_id: &str,
+    after_id: Option<i64>,
+) -> Result<Vec<TaskLogRow>, sqlx::Error> {
+    let mut rows = Vec::new();
+    let mut cursor = after_id;
+    loop {
+        let page = task_logs::get_logs_for_run(pool, run_id, PAGE_SIZE, cursor).await?;
+        let full = page.len() as i64 == PAGE_SIZE;
+        cursor = page.iter().map(|row| row.id).max().or(cursor);
+        rows.extend(page);
+        if !full {
+            return Ok(rows);
+        }
+    }
+}
@@ -6,32 +6,75 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use tokio::sync::{broadcast, mpsc, watch};
-use tracing::info;
+use tracing::{error, info};
 
 mod shutdown;
 
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
 use valka_server::grpc;
+use valka_server::otel;
+use valka_server::reload::ConfigReloader;
 use valka_server::rest;
 use valka_server::server;
+use valka_server::telemetry;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Load .env file (if present) before anything reads env vars
     dotenvy::dotenv().ok();
 
+    // Load configuration (before tracing, which depends on it)
+    let args = parse_args()?;
+    let (config_path, log_format) = (args.config_path, args.log_format);
+    let mut config = valka_core::ServerConfig::load(config_path.as_deref())?;
+    if let Some(log_format) = log_format {
+        config.log_format = log_format;
+    }
+    if let Err(errors) = config.validate() {
+        eprintln!("Invalid configuration:");
+        for error in &errors {
+            eprintln!("  - {error}");
+        }
+        std::process::exit(1);
+    }
+
     // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "valka=info,tower_http=info".into()),
-        )
-        .init();
+    let tracing_guard = otel::init_tracing(&config)?;
 
     info!("Starting Valka server");
 
-    // Load configuration
-    let config_path = std::env::args().nth(1);
-    let mut config = valka_core::ServerConfig::load(config_path.as_deref())?;
+    #[cfg(feature = "sqlite")]
+    if let Some(database_url) = &args.dev_database_url {
+        valka_server::dev::run(config, database_url, shutdown::wait_for_shutdown()).await?;
+        if let Some(provider) = &tracing_guard.tracer_provider
+            && let Err(e) = provider.shutdown()
+        {
+            error!(error = %e, "Failed to flush trace exporter");
+        }
+        return Ok(());
+    }
+
+    // Refuse to start if this build routes tasks differently from the fixture.
+    if let Some(path) = &config.partition_fixture {
+        match valka_core::partitioning::verify_fixture(path) {
+            Ok(count) => info!(count, "Partition routing fixture verified"),
+            Err(e) => {
+                if let valka_core::partitioning::FixtureError::Drift { mismatches, .. } = &e {
+                    for m in mismatches.iter().take(5) {
+                        error!(
+                            queue = %m.entry.queue_name,
+                            task_id = %m.entry.task_id,
+                            expected = m.entry.partition_id,
+                            actual = m.actual,
+                            "Partition routing mismatch"
+                        );
+                    }
+                }
+                return Err(e.into());
+            }
+        }
+    }
 
     // Resolve migration URL: use direct PG connection if configured (bypasses PgBouncer).
     let migration_url = config
@@ -73,8 +116,19 @@ async fn main() -> Result<()> {
         );
     }
 
-    // Shutdown signal
+    // Settings that can change on SIGHUP or the admin endpoint are published
+    // to the loops using them
+    let reloader = ConfigReloader::new(config.clone(), move || {
+        let mut config = valka_core::ServerConfig::load(config_path.as_deref())?;
+        if let Some(log_format) = log_format {
+            config.log_format = log_format;
+        }
+        Ok(config)
+    });
+
+    // Shutdown signal; TaskReaders get their own so they can stop before the listeners
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (readers_shutdown_tx, readers_shutdown_rx) = watch::channel(false);
 
     // Event broadcast channel
     let (event_tx, _) = broadcast::channel::<valka_proto::TaskEvent>(4096);
@@ -106,15 +160,56 @@ async fn main() -> Result<()> {
         .await?
     });
 
-    let forwarder = valka_cluster::NodeForwarder::new();
+    let forwarder = valka_cluster::NodeForwarder::new()
+        .with_auth(cluster.auth().clone())
+        .with_recovery_timeout(std::time::Duration::from_secs(
+            config.gossip.circuit_recovery_secs,
+        ));
+
+    let blob_store = valka_dispatcher::BlobStore::from_config(&config.blob_storage)
+        .map_err(|e| anyhow::anyhow!("Failed to open blob storage: {e}"))?;
 
-    let dispatcher = valka_dispatcher::DispatcherService::new(
+    let mut dispatcher = valka_dispatcher::DispatcherService::new(
         matching.clone(),
         pool.clone(),
         node_id.clone(),
         event_tx.clone(),
         log_tx.clone(),
-    );
+    )
+    .with_events_config(&config.events)
+    .with_log_ingester_config(&config.log_ingester)
+    .with_result_writer_config(&config.result_writer)
+    .with_partition_ownership(cluster.partition_ownership())
+    .with_retry_policy(valka_core::retry::RetryPolicy::from_config(
+        &config.scheduler,
+    ))
+    .with_limits(config.limits.clone())
+    .with_dispatcher_config(config.dispatcher.clone())
+    .with_webhooks(valka_dispatcher::webhooks::WebhookSender::start(
+        pool.clone(),
+        &config.webhooks,
+    ));
+    if let Some(store) = blob_store {
+        info!(url = ?config.blob_storage.url, "Offloading large task payloads to blob storage");
+        dispatcher = dispatcher.with_blob_store(store);
+    }
+
+    // Load task creation quotas; the reconciler keeps their counters in line with PG
+    dispatcher
+        .quotas()
+        .reconcile(&pool, chrono::Utc::now())
+        .await?;
+    if config.quotas.reconcile_interval_ms > 0 {
+        dispatcher.start_quota_reconciler(config.quotas.reconcile_interval_ms, shutdown_rx.clone());
+    }
+
+    // Keep per-queue worker rules in sync with queue_configs
+    if config.matching.queue_policy_refresh_ms > 0 {
+        dispatcher.start_queue_policy_refresher(
+            config.matching.queue_policy_refresh_ms,
+            shutdown_rx.clone(),
+        );
+    }
 
     // Start heartbeat checker
     let (_hb_handle, mut dead_rx) = dispatcher.start_heartbeat_checker(shutdown_rx.clone());
@@ -127,33 +222,75 @@ async fn main() -> Result<()> {
         }
     });
 
+    // Put tasks left in the matching buffers past their TTL back to PENDING
+    if config.matching.buffered_task_ttl_ms > 0 {
+        let (_sweep_handle, mut spilled_rx) = matching.start_buffer_sweeper(shutdown_rx.clone());
+        let dispatcher_clone = dispatcher.clone();
+        tokio::spawn(async move {
+            while let Some(task) = spilled_rx.recv().await {
+                dispatcher_clone.release_unbuffered(task).await;
+            }
+        });
+    }
+
     // Start scheduler
     let scheduler_pool = pool.clone();
-    let scheduler_config = config.scheduler.clone();
+    let scheduler_config = reloader.scheduler();
+    let retention_config = reloader.retention();
+    let num_partitions = config.matching.num_partitions;
+    let scheduler_dispatcher = dispatcher.clone();
+    let scheduler_cluster = cluster.clone();
+    let scheduler_forwarder = forwarder.clone();
     let scheduler_shutdown = shutdown_rx.clone();
     tokio::spawn(async move {
-        server::run_scheduler(scheduler_pool, scheduler_config, scheduler_shutdown).await;
+        server::run_scheduler(
+            scheduler_pool,
+            scheduler_config,
+            retention_config,
+            num_partitions,
+            scheduler_dispatcher,
+            scheduler_cluster,
+            scheduler_forwarder,
+            scheduler_shutdown,
+        )
+        .await;
     });
 
     // Start log ingester
     let log_pool = pool.clone();
-    let log_config = config.log_ingester.clone();
+    let log_config = reloader.log_ingester();
+    let log_stream = dispatcher.log_stream().clone();
     let log_shutdown = shutdown_rx.clone();
     tokio::spawn(async move {
-        server::run_log_ingester(log_pool, log_config, log_rx, log_shutdown).await;
+        server::run_log_ingester(log_pool, log_config, log_rx, log_stream, log_shutdown).await;
     });
 
     // Start TaskReaders for owned partitions
     let tr_pool = pool.clone();
     let tr_matching = matching.clone();
-    let tr_config = config.matching.clone();
+    let tr_dispatcher = dispatcher.clone();
+    let tr_config = reloader.matching();
     let tr_cluster = cluster.clone();
-    let tr_shutdown = shutdown_rx.clone();
+    let tr_shutdown = readers_shutdown_rx;
     tokio::spawn(async move {
-        server::run_task_reader_manager(tr_pool, tr_matching, tr_config, tr_cluster, tr_shutdown)
-            .await;
+        server::run_task_reader_manager(
+            tr_pool,
+            tr_matching,
+            tr_dispatcher,
+            tr_config,
+            tr_cluster,
+            tr_shutdown,
+        )
+        .await;
     });
 
+    // Reload the config on SIGHUP
+    #[cfg(unix)]
+    tokio::spawn(valka_server::reload::run_sighup_reloader(
+        reloader.clone(),
+        shutdown_rx.clone(),
+    ));
+
     // Start event relay (only in clustered mode)
     if cluster.is_clustered() {
         let relay_cluster = cluster.clone();
@@ -171,11 +308,47 @@ async fn main() -> Result<()> {
         });
     }
 
+    // Apply queue pauses published by other nodes
+    if cluster.is_clustered() {
+        let kw_cluster = cluster.clone();
+        let kw_dispatcher = dispatcher.clone();
+        let kw_shutdown = shutdown_rx.clone();
+        tokio::spawn(async move {
+            server::run_cluster_key_watcher(kw_cluster, kw_dispatcher, kw_shutdown).await;
+        });
+    }
+
     // Install metrics exporter
-    let metrics_handle = metrics_exporter_prometheus::PrometheusBuilder::new()
+    let mut metrics_builder = metrics_exporter_prometheus::PrometheusBuilder::new();
+    for (metric, buckets) in valka_core::metrics::histogram_buckets(&config.metrics) {
+        if !buckets.is_empty() {
+            metrics_builder = metrics_builder.set_buckets_for_metric(
+                metrics_exporter_prometheus::Matcher::Full(metric.to_string()),
+                buckets,
+            )?;
+        }
+    }
+    valka_core::metrics::configure_exemplars(&config.metrics);
+    let metrics_handle = metrics_builder
         .install_recorder()
         .expect("Failed to install Prometheus recorder");
 
+    // Start self-telemetry sampler
+    if config.telemetry.interval_ms > 0 {
+        let sources = telemetry::TelemetrySources {
+            event_tx: Some(event_tx.clone()),
+            log_tx: Some(log_tx.clone()),
+            dispatcher: Some(dispatcher.clone()),
+            matching: Some(matching.clone()),
+            forwarder: Some(forwarder.clone()),
+        };
+        let interval_ms = config.telemetry.interval_ms;
+        let telemetry_shutdown = shutdown_rx.clone();
+        tokio::spawn(async move {
+            telemetry::run_self_telemetry(sources, interval_ms, telemetry_shutdown).await;
+        });
+    }
+
     // Start gRPC server
     let grpc_addr = config.grpc_addr.parse()?;
     let grpc_dispatcher = dispatcher.clone();
@@ -187,6 +360,14 @@ async fn main() -> Result<()> {
     let grpc_forwarder = forwarder.clone();
     let grpc_log_tx = log_tx.clone();
     let grpc_shutdown = shutdown_rx.clone();
+    let admin = AdminAuth::new(config.admin_token.clone());
+    let grpc_admin = admin.clone();
+    let auth = ApiAuth::from_config(&config.auth, config.admin_token.as_deref())
+        .map_err(|e| anyhow::anyhow!("Failed to read auth.token_file: {e}"))?;
+    if auth.api_enabled() {
+        info!("API token auth enabled");
+    }
+    let grpc_auth = auth.clone();
 
     let shutdown_tx_grpc = shutdown_tx.clone();
     let grpc_handle = tokio::spawn(async move {
@@ -200,6 +381,8 @@ async fn main() -> Result<()> {
             grpc_cluster,
             grpc_forwarder,
             grpc_log_tx,
+            grpc_admin,
+            grpc_auth,
             grpc_shutdown,
         )
         .await
@@ -231,6 +414,9 @@ async fn main() -> Result<()> {
             rest_cluster,
             rest_forwarder,
             config.web_dir.clone(),
+            admin,
+            reloader,
+            auth,
             rest_shutdown,
         )
         .await
@@ -249,15 +435,20 @@ async fn main() -> Result<()> {
     // Wait for shutdown signal
     shutdown::wait_for_shutdown().await;
     info!("Shutdown signal received, draining...");
-    let _ = shutdown_tx.send(true);
+    let deadline =
+        tokio::time::Instant::now() + tokio::time::Duration::from_secs(config.shutdown_grace_secs);
+    server::run_shutdown_sequence(&dispatcher, &readers_shutdown_tx, &shutdown_tx, deadline).await;
 
-    // Wait for tasks to complete
-    let _ = tokio::time::timeout(tokio::time::Duration::from_secs(30), async {
+    // Wait for the listeners to close
+    let _ = tokio::time::timeout_at(deadline, async {
         let _ = grpc_handle.await;
         let _ = http_handle.await;
     })
     .await;
 
+    // Tell peers we are gone so they take our partitions over right away
+    cluster.leave().await;
+
     // Shutdown cluster gossip
     // Note: We need to unwrap Arc to call shutdown which consumes self.
     // If other references still exist, we just skip graceful shutdown.
@@ -265,6 +456,57 @@ async fn main() -> Result<()> {
         cluster.shutdown().await;
     }
 
+    // Flush buffered spans
+    if let Some(provider) = &tracing_guard.tracer_provider
+        && let Err(e) = provider.shutdown()
+    {
+        error!(error = %e, "Failed to flush trace exporter");
+    }
+
     info!("Valka server stopped");
     Ok(())
 }
+
+struct Args {
+    config_path: Option<String>,
+    log_format: Option<valka_core::LogFormat>,
+    /// `--dev`: the SQLite database to run on instead of the configured PG
+    #[cfg(feature = "sqlite")]
+    dev_database_url: Option<String>,
+}
+
+/// `valka-server [CONFIG] [--log-format json|pretty] [--dev[=SQLITE_URL]]`
+fn parse_args() -> Result<Args> {
+    let mut parsed = Args {
+        config_path: None,
+        log_format: None,
+        #[cfg(feature = "sqlite")]
+        dev_database_url: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--dev" || arg.starts_with("--dev=") {
+            #[cfg(not(feature = "sqlite"))]
+            anyhow::bail!("--dev needs valka-server built with the sqlite feature");
+            #[cfg(feature = "sqlite")]
+            {
+                let url = arg
+                    .strip_prefix("--dev=")
+                    .unwrap_or(valka_server::dev::DEFAULT_DEV_DATABASE_URL);
+                parsed.dev_database_url = Some(url.to_string());
+                continue;
+            }
+        }
+        let value = if arg == "--log-format" {
+            args.next()
+        } else if let Some(value) = arg.strip_prefix("--log-format=") {
+            Some(value.to_string())
+        } else {
+            parsed.config_path = Some(arg);
+            continue;
+        };
+        let value = value.ok_or_else(|| anyhow::anyhow!("--log-format needs a value"))?;
+        parsed.log_format = Some(value.parse().map_err(anyhow::Error::msg)?);
+    }
+    Ok(parsed)
+}
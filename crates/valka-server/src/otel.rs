@@ -0,0 +1,229 @@
+//! Tracing subscriber setup: `pretty` or `json` log output (`log_format`), an
+//! optional daily-rotated `log_file`, and optional OTLP trace export configured
+//! by the `[telemetry]` section. The exporter is behind the `otel` feature (on
+//! by default); without it `telemetry.otlp_endpoint` is ignored with a warning.
+
+use std::path::Path;
+
+use anyhow::Result;
+use opentelemetry::trace::TracerProvider as _;
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::WithExportConfig;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::Resource;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::runtime;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::{Sampler, span_processor_with_async_runtime};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::Layer;
+use tracing_subscriber::fmt::format::{JsonFields, Writer};
+use tracing_subscriber::fmt::{
+    self, FmtContext, FormatEvent, FormatFields, FormattedFields, MakeWriter,
+};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use valka_core::{LogFormat, ServerConfig, TelemetryConfig};
+
+/// Builds a tracer provider exporting over OTLP/gRPC, or `None` when no endpoint is configured.
+/// Must be called from within the tokio runtime.
+#[cfg(feature = "otel")]
+pub fn tracer_provider(config: &TelemetryConfig) -> Result<Option<SdkTracerProvider>> {
+    let Some(endpoint) = config.otlp_endpoint.as_deref().filter(|e| !e.is_empty()) else {
+        return Ok(None);
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let processor =
+        span_processor_with_async_runtime::BatchSpanProcessor::builder(exporter, runtime::Tokio)
+            .build();
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+        config.sampling_ratio.clamp(0.0, 1.0),
+    )));
+    let provider = SdkTracerProvider::builder()
+        .with_span_processor(processor)
+        .with_sampler(sampler)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+    Ok(Some(provider))
+}
+
+/// Built without the `otel` feature: there is no exporter to build.
+#[cfg(not(feature = "otel"))]
+pub fn tracer_provider(_config: &TelemetryConfig) -> Result<Option<SdkTracerProvider>> {
+    Ok(None)
+}
+
+/// Keeps trace export and the log file writer running; hold it until exit.
+pub struct TracingGuard {
+    /// Shut down on exit to flush buffered spans
+    pub tracer_provider: Option<SdkTracerProvider>,
+    /// Flushes buffered log lines to `log_file` when dropped
+    pub log_file: Option<WorkerGuard>,
+}
+
+/// Installs the global subscriber: logs in `config.log_format` to stdout (and
+/// `config.log_file`, if set) filtered by `RUST_LOG`, plus an OpenTelemetry
+/// layer when trace export is enabled.
+pub fn init_tracing(config: &ServerConfig) -> Result<TracingGuard> {
+    let provider = tracer_provider(&config.telemetry)?;
+    let otel_layer = provider
+        .as_ref()
+        .map(|p| tracing_opentelemetry::layer().with_tracer(p.tracer("valka-server")));
+    let (file_layer, log_file) = match config.log_file.as_deref().filter(|p| !p.is_empty()) {
+        Some(path) => {
+            let (writer, guard) = tracing_appender::non_blocking(log_file_appender(path)?);
+            (
+                Some(fmt_layer(config.log_format, writer, false)),
+                Some(guard),
+            )
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "valka=info,tower_http=info".into()),
+        )
+        .with(fmt_layer(config.log_format, std::io::stdout, true))
+        .with(file_layer)
+        .with(otel_layer)
+        .init();
+
+    if cfg!(not(feature = "otel"))
+        && config
+            .telemetry
+            .otlp_endpoint
+            .as_deref()
+            .is_some_and(|e| !e.is_empty())
+    {
+        tracing::warn!(
+            "telemetry.otlp_endpoint is set but valka-server was built without the otel feature"
+        );
+    }
+
+    Ok(TracingGuard {
+        tracer_provider: provider,
+        log_file,
+    })
+}
+
+/// The fmt layer for `format`, writing to `writer`. `ansi` only affects `pretty`.
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Pretty => fmt::layer().with_ansi(ansi).with_writer(writer).boxed(),
+        LogFormat::Json => fmt::layer()
+            .fmt_fields(JsonFields::new())
+            .event_format(FlatJson)
+            .with_writer(writer)
+            .boxed(),
+    }
+}
+
+/// Appender for `log_file`: writes `<path>.YYYY-MM-DD`, starting a new file
+/// each day. The directory is created if missing.
+pub fn log_file_appender(path: &str) -> Result<RollingFileAppender> {
+    let path = Path::new(path);
+    let Some(prefix) = path.file_name().and_then(|n| n.to_str()) else {
+        anyhow::bail!("log_file {} does not name a file", path.display());
+    };
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    Ok(RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(prefix)
+        .build(dir)?)
+}
+
+/// One JSON object per event: timestamp, level, target, then the fields of
+/// every enclosing span from the root down, then the event's own fields
+/// (including `message`). Inner values win on a name clash.
+struct FlatJson;
+
+impl<S, N> FormatEvent<S, N> for FlatJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let meta = event.metadata();
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".into(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+                .into(),
+        );
+        line.insert("level".into(), meta.level().as_str().into());
+        line.insert("target".into(), meta.target().into());
+
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                // Span fields were already rendered as a JSON object by JsonFields
+                if let Some(fields) = extensions.get::<FormattedFields<N>>()
+                    && let Ok(Value::Object(fields)) = serde_json::from_str(&fields.fields)
+                {
+                    line.extend(fields);
+                }
+            }
+        }
+        event.record(&mut JsonVisitor(&mut line));
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{value:?}").into());
+    }
+}
@@ -0,0 +1,245 @@
+//! Config hot-reload: re-read the config on SIGHUP or
+//! `POST /api/v1/admin/reload-config` and apply the settings that can change
+//! while the node runs. Each of those reaches the loop using it through a
+//! watch channel; a change to anything else needs a restart and is rejected.
+
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::watch;
+use tracing::{info, warn};
+use valka_core::{
+    LogIngesterConfig, MatchingConfig, RetentionConfig, SchedulerConfig, ServerConfig,
+};
+
+/// Settings applied on reload, as dotted paths into the config. Every
+/// `retention` setting is, too.
+const RELOADABLE: &[&str] = &[
+    "scheduler.reaper_interval_secs",
+    "scheduler.retry_check_interval_secs",
+    "scheduler.dlq_check_interval_secs",
+    "scheduler.delayed_check_interval_secs",
+    "scheduler.usage_rollup_interval_secs",
+    "scheduler.usage_rollup_lag_secs",
+    "scheduler.schedule_check_interval_secs",
+    "matching.max_buffer_per_partition",
+    "matching.task_reader_batch_size",
+    "matching.task_reader_max_batch_size",
+    "matching.task_reader_poll_busy_ms",
+    "matching.task_reader_poll_idle_ms",
+    "log_ingester.batch_size",
+    "log_ingester.flush_interval_ms",
+];
+
+/// Whether a change to the setting at `path` is applied on reload.
+pub fn is_reloadable(path: &str) -> bool {
+    path.starts_with("retention.") || RELOADABLE.contains(&path)
+}
+
+/// Dotted paths of the settings that differ between two configs.
+pub fn changed_settings(running: &ServerConfig, loaded: &ServerConfig) -> Vec<String> {
+    let mut changed = Vec::new();
+    diff_values(
+        "",
+        &serde_json::to_value(running).unwrap_or_default(),
+        &serde_json::to_value(loaded).unwrap_or_default(),
+        &mut changed,
+    );
+    changed
+}
+
+fn diff_values(path: &str, running: &Value, loaded: &Value, changed: &mut Vec<String>) {
+    match (running, loaded) {
+        (Value::Object(running), Value::Object(loaded)) => {
+            let mut keys: Vec<&String> = running.keys().chain(loaded.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                diff_values(
+                    &child,
+                    running.get(key).unwrap_or(&Value::Null),
+                    loaded.get(key).unwrap_or(&Value::Null),
+                    changed,
+                );
+            }
+        }
+        (running, loaded) if running != loaded => changed.push(path.to_string()),
+        _ => {}
+    }
+}
+
+/// What a reload changed. Only setting names are reported, never values,
+/// since some are secrets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReloadOutcome {
+    /// Settings now in effect
+    pub applied: Vec<String>,
+    /// Changed settings kept at their running value until a restart
+    pub rejected: Vec<String>,
+}
+
+type Loader = dyn Fn() -> anyhow::Result<ServerConfig> + Send + Sync;
+
+/// Holds the running config and the channels its reloadable sections are
+/// published on. Cheap to clone.
+#[derive(Clone)]
+pub struct ConfigReloader {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    load: Box<Loader>,
+    running: Mutex<ServerConfig>,
+    scheduler: watch::Sender<SchedulerConfig>,
+    retention: watch::Sender<RetentionConfig>,
+    log_ingester: watch::Sender<LogIngesterConfig>,
+    matching: watch::Sender<MatchingConfig>,
+}
+
+impl ConfigReloader {
+    /// `load` reads the config afresh, as at startup.
+    pub fn new(
+        running: ServerConfig,
+        load: impl Fn() -> anyhow::Result<ServerConfig> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                load: Box::new(load),
+                scheduler: watch::Sender::new(running.scheduler.clone()),
+                retention: watch::Sender::new(running.retention.clone()),
+                log_ingester: watch::Sender::new(running.log_ingester.clone()),
+                matching: watch::Sender::new(running.matching.clone()),
+                running: Mutex::new(running),
+            }),
+        }
+    }
+
+    pub fn scheduler(&self) -> watch::Receiver<SchedulerConfig> {
+        self.inner.scheduler.subscribe()
+    }
+
+    pub fn retention(&self) -> watch::Receiver<RetentionConfig> {
+        self.inner.retention.subscribe()
+    }
+
+    pub fn log_ingester(&self) -> watch::Receiver<LogIngesterConfig> {
+        self.inner.log_ingester.subscribe()
+    }
+
+    pub fn matching(&self) -> watch::Receiver<MatchingConfig> {
+        self.inner.matching.subscribe()
+    }
+
+    /// A copy of the config in effect.
+    pub fn running(&self) -> ServerConfig {
+        self.inner.running.lock().unwrap().clone()
+    }
+
+    /// Re-read the config and apply what changed. Fails, changing nothing,
+    /// when the config can't be read or doesn't validate.
+    pub fn reload(&self) -> anyhow::Result<ReloadOutcome> {
+        let loaded = (self.inner.load)()?;
+        if let Err(errors) = loaded.validate() {
+            anyhow::bail!("invalid configuration: {}", errors.join("; "));
+        }
+        Ok(self.apply(loaded))
+    }
+
+    /// Apply the reloadable settings of `loaded`, logging a warning for each
+    /// other setting it changes.
+    pub fn apply(&self, mut loaded: ServerConfig) -> ReloadOutcome {
+        let mut running = self.inner.running.lock().unwrap();
+        // Generated at startup when not configured
+        if loaded.node_id.is_empty() {
+            loaded.node_id = running.node_id.clone();
+        }
+
+        let mut outcome = ReloadOutcome::default();
+        let mut next = serde_json::to_value(&*running).unwrap_or_default();
+        let loaded_value = serde_json::to_value(&loaded).unwrap_or_default();
+        for path in changed_settings(&running, &loaded) {
+            if !is_reloadable(&path) {
+                warn!(
+                    setting = %path,
+                    "Config change needs a restart to take effect, keeping the running value"
+                );
+                outcome.rejected.push(path);
+                continue;
+            }
+            let pointer = format!("/{}", path.replace('.', "/"));
+            if let (Some(slot), Some(value)) =
+                (next.pointer_mut(&pointer), loaded_value.pointer(&pointer))
+            {
+                *slot = value.clone();
+                outcome.applied.push(path);
+            }
+        }
+        if outcome.applied.is_empty() {
+            info!(
+                rejected = outcome.rejected.len(),
+                "Config reloaded, nothing to apply"
+            );
+            return outcome;
+        }
+
+        *running = serde_json::from_value(next).expect("running config round-trips");
+        self.inner
+            .scheduler
+            .send_if_modified(|c| replace_if_changed(c, &running.scheduler));
+        self.inner
+            .retention
+            .send_if_modified(|c| replace_if_changed(c, &running.retention));
+        self.inner
+            .log_ingester
+            .send_if_modified(|c| replace_if_changed(c, &running.log_ingester));
+        self.inner
+            .matching
+            .send_if_modified(|c| replace_if_changed(c, &running.matching));
+        info!(applied = ?outcome.applied, "Config reloaded");
+        outcome
+    }
+}
+
+/// Overwrite `current` with `next` when they serialize differently.
+fn replace_if_changed<T: Serialize + Clone>(current: &mut T, next: &T) -> bool {
+    if serde_json::to_value(&*current).ok() == serde_json::to_value(next).ok() {
+        return false;
+    }
+    *current = next.clone();
+    true
+}
+
+/// Reload the config each time the process gets SIGHUP.
+#[cfg(unix)]
+pub async fn run_sighup_reloader(reloader: ConfigReloader, mut shutdown: watch::Receiver<bool>) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!(error = %e, "Failed to install SIGHUP handler, config reload only via the API");
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            Some(()) = hangup.recv() => {
+                info!("SIGHUP received, reloading config");
+                if let Err(e) = reloader.reload() {
+                    warn!(error = %e, "Config reload failed, keeping the running config");
+                }
+            }
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() {
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -1,55 +1,258 @@
 use axum::{
     Json, Router,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{IntoResponse, Sse, sse::Event},
-    routing::{get, post},
+    body::Body,
+    extract::{
+        DefaultBodyLimit, FromRequest, FromRequestParts, OptionalFromRequest, Path, Query, Request,
+        State,
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+    },
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
+    response::{
+        IntoResponse, Response, Sse,
+        sse::{Event, KeepAlive},
+    },
+    routing::{delete, get, post, put},
 };
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{broadcast, watch};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::{ServeDir, ServeFile};
-use tracing::info;
-
+use tracing::{Instrument, info, info_span};
+
+use crate::admin::AdminAuth;
+use crate::auth::ApiAuth;
+use crate::health::{self, Readiness};
+use crate::log_tail::{self, LogTailItem};
+use crate::reload::ConfigReloader;
+use crate::run_relay;
 use valka_cluster::{ClusterManager, NodeForwarder};
-use valka_core::{TaskId, partition_for_task};
+use valka_core::limits::NewTask;
+use valka_core::{
+    Durability, PartitionId, TaskId, TaskStatus, fault_point, partition_for_task, trace_context,
+};
 use valka_db::DbPool;
+use valka_db::queries::dependencies::{self, DependencyError};
 use valka_dispatcher::DispatcherService;
+use valka_dispatcher::blob_store::{BlobError, blob_reference};
+use valka_dispatcher::quotas::{QuotaExceeded, QuotaUsage};
 use valka_matching::MatchingService;
 use valka_matching::partition::TaskEnvelope;
 
 // ─── Structured Error Response ──────────────────────────────────────
 
+/// Every error response is `{"error": {"code": ..., "message": ...}}`.
+#[derive(Serialize)]
+struct ErrorEnvelope {
+    error: ErrorBody,
+}
+
 #[derive(Serialize)]
 struct ErrorBody {
-    error: String,
-    code: String,
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quota: Option<serde_json::Value>,
 }
 
-enum ApiError {
+pub(crate) enum ApiError {
     NotFound(String),
     InvalidState(String),
+    Validation(String),
+    /// The request collides with an existing row
+    Conflict(String),
+    QuotaExceeded(QuotaExceeded),
+    PayloadTooLarge(String),
+    UnsupportedMediaType(String),
+    Unauthorized(String),
+    /// The node is shutting down; sent with Retry-After
+    Unavailable(String),
     Internal(String),
 }
 
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                ApiError::Conflict(db.message().to_string())
+            }
+            _ => ApiError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::MissingJsonContentType(_) => {
+                ApiError::UnsupportedMediaType(rejection.body_text())
+            }
+            _ if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                ApiError::PayloadTooLarge(rejection.body_text())
+            }
+            // The body text names the offending field, e.g.
+            // "...: priority: invalid type: string \"high\", expected i32 at line 1 column 42"
+            _ => ApiError::Validation(rejection.body_text()),
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        // e.g. "Failed to deserialize query string: limit: invalid digit found in string"
+        ApiError::Validation(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        match rejection {
+            PathRejection::FailedToDeserializePathParams(_) => {
+                ApiError::Validation(rejection.body_text())
+            }
+            // A route and handler that disagree on their parameters
+            _ => ApiError::Internal(rejection.body_text()),
+        }
+    }
+}
+
+impl From<BlobError> for ApiError {
+    fn from(e: BlobError) -> Self {
+        match e {
+            BlobError::TooLarge { .. } => ApiError::PayloadTooLarge(e.to_string()),
+            BlobError::ReservedShape(_) => ApiError::Validation(e.to_string()),
+            BlobError::NotFound(_) => ApiError::NotFound(e.to_string()),
+            _ => ApiError::Internal(e.to_string()),
+        }
+    }
+}
+
+/// Retry-After sent with 503s while the node shuts down.
+const SHUTDOWN_RETRY_AFTER_SECS: &str = "5";
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
+        let mut quota = None;
         let (status, code, message) = match self {
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg),
             ApiError::InvalidState(msg) => (StatusCode::UNPROCESSABLE_ENTITY, "INVALID_STATE", msg),
+            ApiError::Validation(msg) => (StatusCode::BAD_REQUEST, "VALIDATION_ERROR", msg),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg),
+            ApiError::QuotaExceeded(e) => {
+                quota = Some(serde_json::json!({
+                    "key": e.key,
+                    "limit_kind": e.limit_kind.as_str(),
+                    "usage": e.usage,
+                    "limit": e.limit,
+                }));
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "QUOTA_EXCEEDED",
+                    e.to_string(),
+                )
+            }
+            ApiError::PayloadTooLarge(msg) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "PAYLOAD_TOO_LARGE", msg)
+            }
+            ApiError::UnsupportedMediaType(msg) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "UNSUPPORTED_MEDIA_TYPE",
+                msg,
+            ),
+            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", msg),
+            ApiError::Unavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE", msg),
             ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR", msg),
         };
-        (
+        let mut response = (
             status,
-            Json(ErrorBody {
-                error: message,
-                code: code.to_string(),
+            Json(ErrorEnvelope {
+                error: ErrorBody {
+                    code,
+                    message,
+                    quota,
+                },
             }),
         )
-            .into_response()
+            .into_response();
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                header::HeaderValue::from_static(SHUTDOWN_RETRY_AFTER_SECS),
+            );
+        }
+        response
+    }
+}
+
+/// `Json` whose rejections are rendered as an `ApiError`.
+pub(crate) struct ApiJson<T>(pub(crate) T);
+
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: serde::de::DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = <Json<T> as FromRequest<S>>::from_request(req, state).await?;
+        Ok(ApiJson(value))
+    }
+}
+
+impl<T, S> OptionalFromRequest<S> for ApiJson<T>
+where
+    T: serde::de::DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        let value = <Json<T> as OptionalFromRequest<S>>::from_request(req, state).await?;
+        Ok(value.map(|Json(value)| ApiJson(value)))
+    }
+}
+
+/// `Query` whose rejections are rendered as an `ApiError`.
+pub(crate) struct ApiQuery<T>(pub(crate) T);
+
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: serde::de::DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state).await?;
+        Ok(ApiQuery(value))
+    }
+}
+
+/// `Path` whose rejections are rendered as an `ApiError`.
+pub(crate) struct ApiPath<T>(pub(crate) T);
+
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: serde::de::DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Path(value) = Path::<T>::from_request_parts(parts, state).await?;
+        Ok(ApiPath(value))
     }
 }
 
@@ -63,9 +266,12 @@ pub struct AppState {
     cluster: Arc<ClusterManager>,
     forwarder: NodeForwarder,
     node_id: String,
+    admin: AdminAuth,
+    reloader: ConfigReloader,
 }
 
 /// Build the API router (useful for testing with tower::ServiceExt::oneshot)
+#[allow(clippy::too_many_arguments)]
 pub fn build_api_router(
     pool: DbPool,
     event_tx: broadcast::Sender<valka_proto::TaskEvent>,
@@ -74,6 +280,8 @@ pub fn build_api_router(
     metrics_handle: metrics_exporter_prometheus::PrometheusHandle,
     cluster: Arc<ClusterManager>,
     forwarder: NodeForwarder,
+    admin: AdminAuth,
+    reloader: ConfigReloader,
 ) -> Router {
     let node_id = cluster.node_id().0.clone();
     let state = AppState {
@@ -85,8 +293,18 @@ pub fn build_api_router(
         cluster,
         forwarder,
         node_id,
+        admin,
+        reloader,
     };
 
+    // A create may carry an input up to the blob store's limit, plus the other fields
+    let create_body_limit = state
+        .dispatcher
+        .blob_store()
+        .map_or(DEFAULT_BODY_LIMIT, |blobs| {
+            DEFAULT_BODY_LIMIT.max(blobs.max_blob_bytes() + CREATE_BODY_OVERHEAD)
+        });
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
@@ -95,22 +313,92 @@ pub fn build_api_router(
     Router::new()
         .route(
             "/api/v1/tasks",
-            post(create_task).get(list_tasks).delete(clear_all_tasks),
+            post(create_task)
+                .layer(DefaultBodyLimit::max(create_body_limit))
+                .get(list_tasks)
+                .delete(clear_all_tasks),
         )
+        .route("/api/v1/tasks/search", get(search_tasks))
         .route("/api/v1/tasks/{task_id}", get(get_task).delete(delete_task))
         .route("/api/v1/tasks/{task_id}/cancel", post(cancel_task))
+        .route("/api/v1/tasks/{task_id}/reclaim", post(reclaim_task))
         .route("/api/v1/tasks/{task_id}/signal", post(send_signal))
         .route("/api/v1/tasks/{task_id}/signals", get(list_signals))
+        .route("/api/v1/tasks/{task_id}/output", get(get_task_output))
         .route("/api/v1/tasks/{task_id}/runs", get(get_task_runs))
         .route(
             "/api/v1/tasks/{task_id}/runs/{run_id}/logs",
             get(get_run_logs),
         )
+        .route(
+            "/api/v1/tasks/{task_id}/runs/{run_id}/logs/stream",
+            get(stream_run_logs),
+        )
         .route("/api/v1/workers", get(list_workers))
-        .route("/api/v1/dead-letters", get(list_dead_letters))
+        .route("/api/v1/workers/{worker_id}/logs", get(get_worker_logs))
+        .route("/api/v1/queues", get(list_queue_configs))
+        .route(
+            "/api/v1/queues/{queue_name}",
+            get(get_queue_config).put(update_queue_config),
+        )
+        .route("/api/v1/queues/{queue_name}/pause", post(pause_queue))
+        .route("/api/v1/queues/{queue_name}/resume", post(resume_queue))
+        .route(
+            "/api/v1/queues/{queue_name}/hold-scheduled",
+            post(hold_scheduled),
+        )
+        .route(
+            "/api/v1/queues/{queue_name}/release-scheduled",
+            post(release_scheduled),
+        )
+        .route("/api/v1/quotas", get(list_quotas))
+        .route(
+            "/api/v1/quotas/{key}",
+            put(update_quota).delete(delete_quota),
+        )
+        .route("/api/v1/quotas/{key}/usage", get(get_quota_usage))
+        .route(
+            "/api/v1/schedules",
+            get(list_schedules).post(create_schedule),
+        )
+        .route(
+            "/api/v1/schedules/{schedule_id}",
+            get(get_schedule).delete(delete_schedule),
+        )
+        .route("/api/v1/stats", get(get_stats))
+        .route("/api/v1/usage", get(get_usage))
+        .route("/api/v1/usage/backfill", post(backfill_usage))
+        .route(
+            "/api/v1/dead-letters",
+            get(list_dead_letters).delete(purge_dead_letters),
+        )
+        .route("/api/v1/dead-letters/{dl_id}", delete(delete_dead_letter))
+        .route(
+            "/api/v1/dead-letters/{dl_id}/retry",
+            post(retry_dead_letter),
+        )
         .route("/api/v1/events", get(subscribe_events_sse))
+        .route("/api/v1/cluster/status", get(cluster_status))
+        .route("/api/v1/cluster/members", get(cluster_members))
+        .route("/api/v1/cluster/partitions", get(cluster_partitions))
+        .route("/api/v1/admin/reload-config", post(reload_config))
         .route("/metrics", get(metrics))
+        .route("/debug/internal", get(debug_internal))
+        .route("/api/v1/debug/matching", get(debug_matching))
+        .route(
+            "/api/v1/debug/dispatch-decisions",
+            get(list_dispatch_decisions),
+        )
+        .route(
+            "/api/v1/debug/dispatch-decisions/sampling/{queue_name}",
+            put(force_decision_sampling).delete(clear_decision_sampling),
+        )
         .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            refuse_writes_while_quiescing,
+        ))
         .with_state(state)
         .layer(cors)
 }
@@ -125,6 +413,9 @@ pub async fn serve_rest(
     cluster: Arc<ClusterManager>,
     forwarder: NodeForwarder,
     web_dir: String,
+    admin: AdminAuth,
+    reloader: ConfigReloader,
+    auth: ApiAuth,
     mut shutdown: watch::Receiver<bool>,
 ) -> Result<(), anyhow::Error> {
     let api_routes = build_api_router(
@@ -135,13 +426,15 @@ pub async fn serve_rest(
         metrics_handle,
         cluster,
         forwarder,
+        admin,
+        reloader,
     );
 
     // Serve static files with SPA fallback
     let index_path = format!("{}/index.html", &web_dir);
     let spa_fallback = ServeDir::new(&web_dir).not_found_service(ServeFile::new(index_path));
 
-    let app = api_routes.fallback_service(spa_fallback);
+    let app = require_api_auth(api_routes, auth).fallback_service(spa_fallback);
 
     info!("REST server listening on {addr}");
 
@@ -163,9 +456,11 @@ struct CreateTaskBody {
     input: Option<serde_json::Value>,
     #[serde(default)]
     priority: i32,
-    #[serde(default = "default_max_retries")]
+    /// 0 takes the queue's default
+    #[serde(default)]
     max_retries: i32,
-    #[serde(default = "default_timeout")]
+    /// 0 takes the queue's default
+    #[serde(default)]
     timeout_seconds: i32,
     #[serde(default)]
     idempotency_key: Option<String>,
@@ -173,13 +468,203 @@ struct CreateTaskBody {
     metadata: Option<serde_json::Value>,
     #[serde(default)]
     scheduled_at: Option<String>,
+    /// Absolute deadline in epoch ms
+    #[serde(default)]
+    deadline_ms: Option<i64>,
+    /// Deadline relative to now, in ms
+    #[serde(default)]
+    deadline_in_ms: Option<i64>,
+    #[serde(default)]
+    durability: Durability,
+    /// Ordering group on FIFO queues
+    #[serde(default)]
+    fifo_key: Option<String>,
+    /// Webhook POSTed with the task once it completes, fails or is dead-lettered
+    #[serde(default)]
+    callback_url: Option<String>,
+    /// Tasks that must complete before this one runs
+    #[serde(default)]
+    depends_on: Vec<String>,
+    /// Backoff between this task's attempts, over the scheduler's settings
+    #[serde(default)]
+    retry_backoff: Option<valka_core::retry::RetryBackoff>,
+    /// Labels a worker must carry to be handed the task
+    #[serde(default)]
+    required_labels: valka_core::labels::Labels,
+}
+
+/// Most tasks a single task may depend on.
+const MAX_DEPENDENCIES: usize = 100;
+
+/// Largest YAML or form-encoded create body accepted.
+const MAX_TEXT_BODY_BYTES: usize = 256 * 1024;
+
+/// axum's request body limit, kept for creates without blob storage.
+const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Room for the other fields of a create whose input is up to `max_blob_bytes`.
+const CREATE_BODY_OVERHEAD: usize = 1024 * 1024;
+
+/// A create body sent as JSON, YAML (`application/yaml`) or a form
+/// (`application/x-www-form-urlencoded`). Anything that isn't YAML or a form
+/// is read as JSON, rejections included.
+struct TaskBody(CreateTaskBody);
+
+impl<S: Send + Sync> FromRequest<S> for TaskBody {
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let mime = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        let body = match mime.as_str() {
+            "application/yaml" | "application/x-yaml" | "text/yaml" => {
+                let bytes = read_text_body(req).await?;
+                serde_yaml::from_slice(&bytes)
+                    .map_err(|e| ApiError::Validation(format!("Invalid YAML body: {e}")))
+            }
+            "application/x-www-form-urlencoded" => {
+                let bytes = read_text_body(req).await?;
+                serde_urlencoded::from_bytes::<CreateTaskForm>(&bytes)
+                    .map_err(|e| ApiError::Validation(format!("Invalid form body: {e}")))
+                    .and_then(CreateTaskForm::into_body)
+            }
+            _ => {
+                return <ApiJson<CreateTaskBody> as FromRequest<S>>::from_request(req, state)
+                    .await
+                    .map(|ApiJson(body)| TaskBody(body))
+                    .map_err(IntoResponse::into_response);
+            }
+        };
+        body.map(TaskBody).map_err(IntoResponse::into_response)
+    }
+}
+
+async fn read_text_body(req: Request) -> Result<axum::body::Bytes, Response> {
+    let too_large = || {
+        ApiError::PayloadTooLarge(format!("Body exceeds {MAX_TEXT_BODY_BYTES} bytes"))
+            .into_response()
+    };
+    let declared = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if declared.is_some_and(|len| len > MAX_TEXT_BODY_BYTES) {
+        return Err(too_large());
+    }
+    axum::body::to_bytes(req.into_body(), MAX_TEXT_BODY_BYTES)
+        .await
+        .map_err(|_| too_large())
+}
+
+/// Flat form fields of a create body; `input` and `metadata` are JSON or
+/// YAML documents in a string.
+#[derive(Deserialize)]
+struct CreateTaskForm {
+    queue_name: String,
+    task_name: String,
+    #[serde(default)]
+    input: Option<String>,
+    #[serde(default)]
+    priority: i32,
+    /// 0 takes the queue's default
+    #[serde(default)]
+    max_retries: i32,
+    /// 0 takes the queue's default
+    #[serde(default)]
+    timeout_seconds: i32,
+    #[serde(default)]
+    idempotency_key: Option<String>,
+    #[serde(default)]
+    metadata: Option<String>,
+    #[serde(default)]
+    scheduled_at: Option<String>,
+    #[serde(default)]
+    deadline_ms: Option<i64>,
+    #[serde(default)]
+    deadline_in_ms: Option<i64>,
+    #[serde(default)]
+    durability: Durability,
+    /// Ordering group on FIFO queues
+    #[serde(default)]
+    fifo_key: Option<String>,
+    #[serde(default)]
+    callback_url: Option<String>,
+    /// Comma-separated task IDs
+    #[serde(default)]
+    depends_on: Option<String>,
+}
+
+impl CreateTaskForm {
+    fn into_body(self) -> Result<CreateTaskBody, ApiError> {
+        Ok(CreateTaskBody {
+            queue_name: self.queue_name,
+            task_name: self.task_name,
+            input: self
+                .input
+                .map(|s| parse_document("input", &s))
+                .transpose()?,
+            priority: self.priority,
+            max_retries: self.max_retries,
+            timeout_seconds: self.timeout_seconds,
+            idempotency_key: self.idempotency_key,
+            metadata: self
+                .metadata
+                .map(|s| parse_document("metadata", &s))
+                .transpose()?,
+            scheduled_at: self.scheduled_at,
+            deadline_ms: self.deadline_ms,
+            deadline_in_ms: self.deadline_in_ms,
+            durability: self.durability,
+            fifo_key: self.fifo_key,
+            callback_url: self.callback_url,
+            depends_on: self
+                .depends_on
+                .iter()
+                .flat_map(|ids| ids.split(','))
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string)
+                .collect(),
+            // Nested settings only come in JSON or YAML bodies
+            retry_backoff: None,
+            required_labels: Default::default(),
+        })
+    }
 }
 
-fn default_max_retries() -> i32 {
-    3
+/// Parse a form field holding JSON, or failing that YAML. Text that only
+/// reads as a bare YAML string is rejected: it is far more likely a broken
+/// document than a string value, which JSON can spell unambiguously.
+fn parse_document(field: &str, text: &str) -> Result<serde_json::Value, ApiError> {
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok(value);
+    }
+    match serde_yaml::from_str::<serde_json::Value>(text) {
+        Ok(serde_json::Value::String(_)) => Err(ApiError::Validation(format!(
+            "{field} is neither a JSON nor a YAML document (quote it as JSON to send a string)"
+        ))),
+        Ok(value) => Ok(value),
+        Err(e) => Err(ApiError::Validation(format!(
+            "{field} is neither valid JSON nor valid YAML: {e}"
+        ))),
+    }
 }
-fn default_timeout() -> i32 {
-    300
+
+/// Whether the client asked for YAML via `Accept`.
+fn wants_yaml(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| {
+            accept.contains("application/yaml") || accept.contains("application/x-yaml")
+        })
 }
 
 #[derive(Deserialize)]
@@ -198,14 +683,150 @@ fn default_limit() -> i64 {
     50
 }
 
+/// Filters of `GET /api/v1/tasks/search`; empty values are ignored.
+#[derive(Deserialize)]
+struct SearchTasksQuery {
+    #[serde(default)]
+    queue_name: Option<String>,
+    /// Comma-separated, e.g. `FAILED,RETRY`
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    task_name_prefix: Option<String>,
+    #[serde(default)]
+    created_after: Option<String>,
+    #[serde(default)]
+    created_before: Option<String>,
+    #[serde(default)]
+    idempotency_key: Option<String>,
+    #[serde(default)]
+    error_contains: Option<String>,
+    /// `created_desc` (default), `created_asc` or `priority`
+    #[serde(default)]
+    order: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+}
+
+impl SearchTasksQuery {
+    fn into_search(self) -> Result<valka_db::queries::tasks::TaskSearch, ApiError> {
+        fn non_empty(value: Option<String>) -> Option<String> {
+            value.filter(|v| !v.is_empty())
+        }
+        fn timestamp(
+            name: &str,
+            value: Option<String>,
+        ) -> Result<Option<chrono::DateTime<chrono::Utc>>, ApiError> {
+            non_empty(value)
+                .map(|s| {
+                    s.parse()
+                        .map_err(|_| ApiError::Validation(format!("Invalid {name} timestamp: {s}")))
+                })
+                .transpose()
+        }
+
+        let statuses = non_empty(self.status)
+            .map(|s| {
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| {
+                        TaskStatus::from_str_status(s)
+                            .map(|status| status.as_str().to_string())
+                            .ok_or_else(|| ApiError::Validation(format!("Unknown status: {s}")))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let order = match non_empty(self.order) {
+            Some(order) => valka_db::queries::tasks::TaskOrder::parse(&order).ok_or_else(|| {
+                ApiError::Validation(format!(
+                    "Unknown order: {order} (expected created_desc, created_asc or priority)"
+                ))
+            })?,
+            None => Default::default(),
+        };
+        if !(1..=1000).contains(&self.limit) || self.offset < 0 {
+            return Err(ApiError::Validation(
+                "limit must be 1-1000 and offset non-negative".to_string(),
+            ));
+        }
+        Ok(valka_db::queries::tasks::TaskSearch {
+            queue_name: non_empty(self.queue_name),
+            statuses,
+            task_name_prefix: non_empty(self.task_name_prefix),
+            created_after: timestamp("created_after", self.created_after)?,
+            created_before: timestamp("created_before", self.created_before)?,
+            idempotency_key: non_empty(self.idempotency_key),
+            error_contains: non_empty(self.error_contains),
+            order,
+            limit: self.limit,
+            offset: self.offset,
+        })
+    }
+}
+
 async fn create_task(
     State(state): State<AppState>,
-    Json(body): Json<CreateTaskBody>,
+    headers: HeaderMap,
+    TaskBody(body): TaskBody,
 ) -> Result<impl IntoResponse, ApiError> {
+    let span = info_span!(
+        "create_task",
+        queue = %body.queue_name,
+        task_id = tracing::field::Empty,
+    );
+    // Join the caller's trace when it sent one
+    if let Some(traceparent) = headers
+        .get(trace_context::TRACEPARENT)
+        .and_then(|v| v.to_str().ok())
+    {
+        trace_context::set_parent(&span, traceparent);
+    }
+    create_task_traced(state, body).instrument(span).await
+}
+
+/// The reply to a create whose idempotency key already made `existing`.
+fn deduplicated(
+    existing: valka_db::queries::tasks::TaskRow,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let mut json = existing.to_json();
+    json["deduplicated"] = serde_json::Value::Bool(true);
+    (StatusCode::OK, Json(json))
+}
+
+async fn create_task_traced(
+    state: AppState,
+    mut body: CreateTaskBody,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
     let task_id = TaskId::new();
+    tracing::Span::current().record("task_id", task_id.0.as_str());
+    // Checked against the limits as the reference it leaves
+    let offload = state
+        .dispatcher
+        .prepare_input(&task_id.0, &mut body.input)?;
+    state
+        .dispatcher
+        .limits()
+        .check_task(&NewTask {
+            queue_name: &body.queue_name,
+            task_name: &body.task_name,
+            input: body.input.as_ref(),
+            metadata: body.metadata.as_ref(),
+            priority: body.priority,
+            max_retries: body.max_retries,
+            timeout_seconds: body.timeout_seconds,
+        })
+        .map_err(ApiError::Validation)?;
+    let traceparent = trace_context::current_traceparent();
+    let fifo_key = body.fifo_key.filter(|k| !k.is_empty());
+    // A FIFO key keeps all of its tasks on one partition
     let partition = partition_for_task(
         &body.queue_name,
-        &task_id.0,
+        fifo_key.as_deref().unwrap_or(&task_id.0),
         state.matching.config().num_partitions,
     );
 
@@ -215,42 +836,167 @@ async fn create_task(
         .and_then(|s| s.parse::<chrono::DateTime<chrono::Utc>>().ok());
 
     let metadata = body.metadata.unwrap_or(serde_json::json!({}));
+    let idempotency_key = body.idempotency_key;
 
-    let task = valka_db::queries::tasks::create_task(
-        &state.pool,
-        valka_db::queries::tasks::CreateTaskParams {
-            id: task_id.0.clone(),
-            queue_name: body.queue_name.clone(),
-            task_name: body.task_name.clone(),
-            partition_id: partition.0,
-            input: body.input.clone(),
-            priority: body.priority,
-            max_retries: body.max_retries,
-            timeout_seconds: body.timeout_seconds,
-            idempotency_key: body.idempotency_key,
-            metadata: metadata.clone(),
-            scheduled_at,
-        },
+    let now = chrono::Utc::now();
+    let deadline_ms = valka_core::resolve_deadline_ms(
+        body.deadline_ms,
+        body.deadline_in_ms,
+        now.timestamp_millis(),
     )
-    .await
-    .map_err(|e| ApiError::Internal(e.to_string()))?;
+    .map_err(ApiError::Validation)?;
+    let callback_url = body.callback_url.filter(|u| !u.is_empty());
+    if let Some(url) = &callback_url {
+        state
+            .dispatcher
+            .webhooks()
+            .validate_callback_url(url)
+            .map_err(ApiError::Validation)?;
+    }
+    let retry_backoff = body.retry_backoff.unwrap_or_default();
+    retry_backoff.validate().map_err(ApiError::Validation)?;
+    valka_core::labels::validate_required(&body.required_labels).map_err(ApiError::Validation)?;
+    let defaults = state
+        .dispatcher
+        .queue_policies()
+        .task_defaults(&body.queue_name);
+    let params = valka_db::queries::tasks::CreateTaskParams {
+        id: task_id.0.clone(),
+        queue_name: body.queue_name.clone(),
+        task_name: body.task_name.clone(),
+        partition_id: partition.0,
+        input: body.input.clone(),
+        priority: body.priority,
+        max_retries: defaults.max_retries(body.max_retries),
+        timeout_seconds: defaults.timeout_seconds(body.timeout_seconds),
+        idempotency_key: idempotency_key.clone(),
+        metadata: metadata.clone(),
+        scheduled_at,
+        deadline_at: deadline_ms.and_then(chrono::DateTime::from_timestamp_millis),
+        traceparent: traceparent.clone(),
+        durability: body.durability,
+        fifo_key: fifo_key.clone(),
+        callback_url,
+        retry_backoff,
+        required_labels: body.required_labels,
+    };
+    if let Some(reason) = state.dispatcher.ephemeral_rejection(&params) {
+        return Err(ApiError::Validation(reason));
+    }
+    let mut depends_on = body.depends_on;
+    depends_on.sort();
+    depends_on.dedup();
+    if !depends_on.is_empty() && params.durability == Durability::Ephemeral {
+        return Err(ApiError::Validation(
+            "Ephemeral tasks cannot have dependencies".to_string(),
+        ));
+    }
+    if depends_on.len() > MAX_DEPENDENCIES {
+        return Err(ApiError::Validation(format!(
+            "A task can depend on at most {MAX_DEPENDENCIES} tasks"
+        )));
+    }
 
-    valka_core::metrics::record_task_created(&body.queue_name);
+    // A retried create is answered before its input is written again
+    if offload.is_some()
+        && let Some(key) = idempotency_key.as_deref()
+        && let Some(existing) =
+            valka_db::queries::tasks::get_task_by_idempotency_key(&state.pool, key).await?
+    {
+        return Ok(deduplicated(existing));
+    }
 
-    // Emit task created event
-    let event = valka_proto::TaskEvent {
-        event_id: uuid::Uuid::now_v7().to_string(),
-        task_id: task_id.0.clone(),
-        queue_name: body.queue_name.clone(),
-        previous_status: 0,
-        new_status: 1, // PENDING
-        worker_id: String::new(),
-        node_id: state.node_id.clone(),
-        attempt_number: 0,
-        error_message: String::new(),
-        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+    let quotas = state.dispatcher.quotas();
+    quotas
+        .try_acquire(&body.queue_name, now)
+        .map_err(ApiError::QuotaExceeded)?;
+    if let Some(offload) = &offload
+        && let Err(e) = state.dispatcher.write_input(offload).await
+    {
+        quotas.release(&body.queue_name);
+        return Err(e.into());
+    }
+
+    // Ephemeral tasks skip the insert when a worker on this node takes them
+    // right away; otherwise they are persisted like any other task
+    let mut announced = false;
+    let params = if body.durability == Durability::Ephemeral
+        && deadline_ms.is_none_or(|d| d > now.timestamp_millis())
+        && state
+            .cluster
+            .owns_partition(&body.queue_name, partition.0)
+            .await
+    {
+        let response = params.unwritten_row("DISPATCHING", now);
+        emit_task_created(&state, &task_id.0, &body.queue_name);
+        announced = true;
+        match state.dispatcher.offer_ephemeral(params) {
+            Ok(()) => {
+                valka_core::metrics::record_task_created(&body.queue_name);
+                return Ok((StatusCode::CREATED, Json(response.to_json())));
+            }
+            Err(params) => *params,
+        }
+    } else {
+        params
+    };
+
+    let created = match fault_point!("rest.create_task.before_insert", &body.queue_name) {
+        Ok(()) if depends_on.is_empty() => {
+            valka_db::queries::tasks::create_task(&state.pool, params).await
+        }
+        Ok(()) => {
+            match dependencies::create_dependent_task(&state.pool, params, &depends_on).await {
+                Ok(task) => Ok(task),
+                Err(DependencyError::Database(e)) => Err(e),
+                Err(e) => {
+                    quotas.release(&body.queue_name);
+                    state.dispatcher.discard_input(offload).await;
+                    return Err(ApiError::Validation(e.to_string()));
+                }
+            }
+        }
+        Err(e) => Err(e.into()),
     };
-    let _ = state.event_tx.send(event);
+    let mut task = match created {
+        Ok(task) => task,
+        Err(e) => {
+            quotas.release(&body.queue_name);
+            state.dispatcher.discard_input(offload).await;
+            // A retried create gets back the task its first attempt made
+            if let Some(key) = idempotency_key.as_deref()
+                && valka_db::queries::tasks::is_idempotency_conflict(&e)
+                && let Some(existing) =
+                    valka_db::queries::tasks::get_task_by_idempotency_key(&state.pool, key).await?
+            {
+                return Ok(deduplicated(existing));
+            }
+            return Err(e.into());
+        }
+    };
+
+    valka_core::metrics::record_task_created(&body.queue_name);
+
+    // A deadline that has already passed fails the task without ever dispatching it
+    if task.deadline_expired(now) {
+        if let Some(expired) = state
+            .dispatcher
+            .expire_before_dispatch(&task.id, &task.queue_name)
+            .await
+            .map_err(|e| ApiError::Internal(e.to_string()))?
+        {
+            task = expired;
+        }
+        return Ok((StatusCode::CREATED, Json(task.to_json())));
+    }
+
+    if !announced {
+        emit_task_created(&state, &task_id.0, &body.queue_name);
+    }
+    // Readers pick it up from PG once its dependencies complete
+    if task.status == "WAITING" {
+        return Ok((StatusCode::CREATED, Json(task.to_json())));
+    }
 
     // Check if we own this partition; if not, forward to owner
     if !state
@@ -262,12 +1008,13 @@ async fn create_task(
             .get_partition_owner_addr(&body.queue_name, partition.0)
             .await
     {
+        // Coalesced with other creates bound for the same node
         let _ = state
             .forwarder
-            .forward_task(&owner_addr, &task_id.0, &body.queue_name, partition.0)
+            .forward_task_batched(&owner_addr, &task_id.0, &body.queue_name, partition.0)
             .await;
         valka_core::metrics::record_task_forwarded(&body.queue_name);
-        return Ok((StatusCode::CREATED, Json(task_row_to_json(task))));
+        return Ok((StatusCode::CREATED, Json(task.to_json())));
     }
     // If owner unknown, fall through to local sync match (safety)
 
@@ -280,33 +1027,70 @@ async fn create_task(
             task_name: body.task_name.clone(),
             input: body.input.map(|v| v.to_string()),
             attempt_number: 1,
-            timeout_seconds: body.timeout_seconds,
+            timeout_seconds: task.timeout_seconds,
             metadata: metadata.to_string(),
             priority: body.priority,
+            deadline_ms,
+            ready_at_ms: Some(task.created_at.timestamp_millis()),
+            created_at_ms: Some(task.created_at.timestamp_millis()),
+            scheduled_at_ms: task.scheduled_at.map(|at| at.timestamp_millis()),
+            traceparent,
+            fifo_key,
+            required_labels: task.required_labels(),
+            ephemeral: None,
         };
-        let _ = state
-            .matching
-            .offer_task(&body.queue_name, partition, envelope);
+        state.dispatcher.offer_new_task(partition, envelope).await;
     }
 
-    Ok((StatusCode::CREATED, Json(task_row_to_json(task))))
+    Ok((StatusCode::CREATED, Json(task.to_json())))
+}
+
+#[derive(Deserialize)]
+struct GetTaskQuery {
+    /// Inline an output offloaded to blob storage
+    #[serde(default)]
+    resolve_output: bool,
 }
 
 async fn get_task(
     State(state): State<AppState>,
-    Path(task_id): Path<String>,
-) -> Result<impl IntoResponse, ApiError> {
+    ApiPath(task_id): ApiPath<String>,
+    ApiQuery(query): ApiQuery<GetTaskQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     let task = valka_db::queries::tasks::get_task(&state.pool, &task_id)
-        .await
-        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .await?
         .ok_or_else(|| ApiError::NotFound("Task not found".to_string()))?;
 
-    Ok(Json(task_row_to_json(task)))
+    let dependencies = dependencies::list_dependencies(&state.pool, &task_id).await?;
+
+    let retry_plan = task.retry_plan(state.dispatcher.retry_policy(), chrono::Utc::now());
+    let mut json = task.to_json();
+    if query.resolve_output
+        && let Some((blobs, reference)) = offloaded_output(&state, &task)
+    {
+        let output = blobs.get(reference).await?;
+        json["output"] = serde_json::from_slice(&output)
+            .map_err(|e| ApiError::Internal(format!("Offloaded output is not JSON: {e}")))?;
+    }
+    if let Some(plan) = retry_plan {
+        json["retry_plan"] = retry_plan_to_json(&plan);
+    }
+    json["unmet_dependencies"] = dependencies.iter().filter(|d| !d.is_met()).count().into();
+    json["dependencies"] = dependencies
+        .into_iter()
+        .map(|d| serde_json::json!({ "task_id": d.depends_on, "status": d.status }))
+        .collect();
+    if wants_yaml(&headers) {
+        let yaml = serde_yaml::to_string(&json).map_err(|e| ApiError::Internal(e.to_string()))?;
+        return Ok(([(header::CONTENT_TYPE, "application/yaml")], yaml).into_response());
+    }
+    Ok(Json(json).into_response())
 }
 
 async fn list_tasks(
     State(state): State<AppState>,
-    Query(query): Query<ListTasksQuery>,
+    ApiQuery(query): ApiQuery<ListTasksQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
     let tasks = valka_db::queries::tasks::list_tasks(
         &state.pool,
@@ -315,27 +1099,43 @@ async fn list_tasks(
         query.limit,
         query.offset,
     )
-    .await
-    .map_err(|e| ApiError::Internal(e.to_string()))?;
+    .await?;
+
+    let result: Vec<serde_json::Value> = tasks.into_iter().map(|t| t.to_json()).collect();
+    Ok(Json(result))
+}
+
+async fn search_tasks(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<SearchTasksQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let search = query.into_search()?;
+    let tasks = valka_db::queries::tasks::search_tasks(&state.pool, &search).await?;
 
-    let result: Vec<serde_json::Value> = tasks.into_iter().map(task_row_to_json).collect();
+    let result: Vec<serde_json::Value> = tasks.into_iter().map(|t| t.to_json()).collect();
     Ok(Json(result))
 }
 
 async fn cancel_task(
     State(state): State<AppState>,
-    Path(task_id): Path<String>,
+    ApiPath(task_id): ApiPath<String>,
 ) -> Result<impl IntoResponse, ApiError> {
     // Try cancelling (PENDING, RETRY, RUNNING, DISPATCHING)
     let task = valka_db::queries::tasks::cancel_task_any(&state.pool, &task_id)
-        .await
-        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .await?
         .ok_or_else(|| {
             ApiError::InvalidState("Task not found or not in cancellable state".to_string())
         })?;
 
-    // If task was RUNNING, forward cancellation to the worker
-    state.dispatcher.cancel_task_on_worker(&task_id).await;
+    // If task was RUNNING, tell its worker, on whichever node it streams to
+    run_relay::cancel_on_worker(
+        &state.pool,
+        &state.dispatcher,
+        &state.cluster,
+        &state.forwarder,
+        &task_id,
+    )
+    .await;
 
     // Emit cancel event
     let event = valka_proto::TaskEvent {
@@ -349,28 +1149,101 @@ async fn cancel_task(
         attempt_number: 0,
         error_message: String::new(),
         timestamp_ms: chrono::Utc::now().timestamp_millis(),
+        suppressed_count: 0,
+        progress: None,
+        progress_message: String::new(),
     };
-    let _ = state.event_tx.send(event);
+    state.dispatcher.events().emit(event);
 
-    Ok(Json(task_row_to_json(task)))
+    Ok(Json(task.to_json()))
 }
 
 #[derive(Deserialize)]
-struct SendSignalBody {
-    signal_name: String,
+struct ReclaimQuery {
+    /// Back to PENDING instead of RETRY
     #[serde(default)]
-    payload: Option<serde_json::Value>,
+    immediate: bool,
+}
+
+#[derive(Deserialize)]
+struct ReclaimBody {
+    reason: String,
+}
+
+async fn reclaim_task(
+    State(state): State<AppState>,
+    ApiPath(task_id): ApiPath<String>,
+    ApiQuery(query): ApiQuery<ReclaimQuery>,
+    headers: HeaderMap,
+    ApiJson(body): ApiJson<ReclaimBody>,
+) -> Result<impl IntoResponse, ApiError> {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !state.admin.allows(authorization) {
+        return Err(ApiError::Unauthorized("Admin token required".to_string()));
+    }
+    if body.reason.trim().is_empty() {
+        return Err(ApiError::Validation("reason is required".to_string()));
+    }
+
+    let reclaimed = state
+        .dispatcher
+        .reclaim_task(&task_id, &body.reason, query.immediate)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let Some(reclaimed) = reclaimed else {
+        let task = valka_db::queries::tasks::get_task(&state.pool, &task_id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Task not found".to_string()))?;
+        return Err(ApiError::InvalidState(format!(
+            "Task is {}, not RUNNING",
+            task.status
+        )));
+    };
+
+    Ok(Json(serde_json::json!({
+        "task": reclaimed.task.to_json(),
+        "abandoned_run_id": reclaimed.run.id,
+        "worker_id": reclaimed.run.worker_id,
+        "worker_notified": reclaimed.worker_notified,
+    })))
+}
+
+/// Re-read the config and apply the settings that can change at runtime.
+/// Responds with the settings applied and those that need a restart.
+async fn reload_config(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !state.admin.allows(authorization) {
+        return Err(ApiError::Unauthorized("Admin token required".to_string()));
+    }
+    let outcome = state
+        .reloader
+        .reload()
+        .map_err(|e| ApiError::Validation(format!("Config reload failed: {e:#}")))?;
+    Ok(Json(outcome))
+}
+
+#[derive(Deserialize)]
+struct SendSignalBody {
+    signal_name: String,
+    #[serde(default)]
+    payload: Option<serde_json::Value>,
 }
 
 async fn send_signal(
     State(state): State<AppState>,
-    Path(task_id): Path<String>,
-    Json(body): Json<SendSignalBody>,
+    ApiPath(task_id): ApiPath<String>,
+    ApiJson(body): ApiJson<SendSignalBody>,
 ) -> Result<impl IntoResponse, ApiError> {
     // Validate task exists
     let task = valka_db::queries::tasks::get_task(&state.pool, &task_id)
-        .await
-        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .await?
         .ok_or_else(|| ApiError::NotFound("Task not found".to_string()))?;
 
     // Reject if terminal status
@@ -393,8 +1266,7 @@ async fn send_signal(
         &body.signal_name,
         body.payload,
     )
-    .await
-    .map_err(|e| ApiError::Internal(e.to_string()))?;
+    .await?;
 
     // Try immediate delivery
     let task_signal = valka_proto::TaskSignal {
@@ -405,10 +1277,14 @@ async fn send_signal(
         timestamp_ms: signal.created_at.timestamp_millis(),
     };
 
-    let delivered = state
-        .dispatcher
-        .send_signal_to_worker(&task_id, task_signal)
-        .await;
+    let delivered = run_relay::signal_worker(
+        &state.pool,
+        &state.dispatcher,
+        &state.cluster,
+        &state.forwarder,
+        task_signal,
+    )
+    .await;
 
     if delivered {
         let _ = valka_db::queries::signals::mark_delivered(&state.pool, &signal.id).await;
@@ -431,13 +1307,12 @@ struct ListSignalsQuery {
 
 async fn list_signals(
     State(state): State<AppState>,
-    Path(task_id): Path<String>,
-    Query(query): Query<ListSignalsQuery>,
+    ApiPath(task_id): ApiPath<String>,
+    ApiQuery(query): ApiQuery<ListSignalsQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
     let signals =
         valka_db::queries::signals::list_signals(&state.pool, &task_id, query.status.as_deref())
-            .await
-            .map_err(|e| ApiError::Internal(e.to_string()))?;
+            .await?;
 
     let result: Vec<serde_json::Value> = signals
         .into_iter()
@@ -459,11 +1334,9 @@ async fn list_signals(
 
 async fn delete_task(
     State(state): State<AppState>,
-    Path(task_id): Path<String>,
+    ApiPath(task_id): ApiPath<String>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let deleted = valka_db::queries::tasks::delete_task(&state.pool, &task_id)
-        .await
-        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let deleted = valka_db::queries::tasks::delete_task(&state.pool, &task_id).await?;
 
     if !deleted {
         return Err(ApiError::NotFound("Task not found".to_string()));
@@ -473,20 +1346,53 @@ async fn delete_task(
 }
 
 async fn clear_all_tasks(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
-    let count = valka_db::queries::tasks::clear_all_tasks(&state.pool)
-        .await
-        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let count = valka_db::queries::tasks::clear_all_tasks(&state.pool).await?;
 
     Ok(Json(serde_json::json!({ "deleted_count": count })))
 }
 
+/// A task's output as the response body, streamed from blob storage when
+/// it was offloaded.
+async fn get_task_output(
+    State(state): State<AppState>,
+    ApiPath(task_id): ApiPath<String>,
+) -> Result<Response, ApiError> {
+    let task = valka_db::queries::tasks::get_task(&state.pool, &task_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Task not found".to_string()))?;
+    let Some((blobs, reference)) = offloaded_output(&state, &task) else {
+        let output = task
+            .output
+            .ok_or_else(|| ApiError::NotFound("Task has no output".to_string()))?;
+        return Ok(Json(output).into_response());
+    };
+    let stream = blobs.get_stream(reference).await?;
+    Ok((
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// The blob store holding `task`'s offloaded output and its reference, if it
+/// has one. Only the task's own blobs in the configured store count; anything
+/// else shaped like a reference is the output itself.
+fn offloaded_output<'s, 't>(
+    state: &'s AppState,
+    task: &'t valka_db::queries::tasks::TaskRow,
+) -> Option<(&'s valka_dispatcher::BlobStore, &'t str)> {
+    let blobs = state.dispatcher.blob_store()?;
+    let reference = task.output.as_ref().and_then(blob_reference)?;
+    blobs
+        .is_output_of(&task.id, reference)
+        .then_some((blobs, reference))
+}
+
 async fn get_task_runs(
     State(state): State<AppState>,
-    Path(task_id): Path<String>,
+    ApiPath(task_id): ApiPath<String>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let runs = valka_db::queries::task_runs::get_runs_for_task(&state.pool, &task_id)
-        .await
-        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let runs = valka_db::queries::task_runs::get_runs_for_task(&state.pool, &task_id).await?;
 
     let result: Vec<serde_json::Value> = runs.into_iter().map(task_run_to_json).collect();
     Ok(Json(result))
@@ -506,8 +1412,8 @@ fn default_log_limit() -> i64 {
 
 async fn get_run_logs(
     State(state): State<AppState>,
-    Path((task_id, run_id)): Path<(String, String)>,
-    Query(query): Query<LogsQuery>,
+    ApiPath((task_id, run_id)): ApiPath<(String, String)>,
+    ApiQuery(query): ApiQuery<LogsQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
     // Verify the run belongs to the task
     let _ = task_id; // Used for API consistency; logs are queried by run_id
@@ -518,13 +1424,47 @@ async fn get_run_logs(
         query.limit,
         query.after_id,
     )
-    .await
-    .map_err(|e| ApiError::Internal(e.to_string()))?;
+    .await?;
 
     let result: Vec<serde_json::Value> = logs.into_iter().map(task_log_to_json).collect();
     Ok(Json(result))
 }
 
+/// SSE tail of a run's logs: everything persisted (after `Last-Event-ID`),
+/// then entries as the ingester flushes them, then an `end` event with the
+/// run status once the run is over.
+async fn stream_run_logs(
+    State(state): State<AppState>,
+    ApiPath((task_id, run_id)): ApiPath<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    valka_db::queries::task_runs::get_task_run(&state.pool, &run_id)
+        .await?
+        .filter(|run| run.task_id == task_id)
+        .ok_or_else(|| ApiError::NotFound("Task run not found".to_string()))?;
+    let after_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|id| id.parse::<i64>().ok());
+
+    let live = state.dispatcher.log_stream().subscribe();
+    let stream = log_tail::tail_run_logs(state.pool.clone(), live, run_id, after_id).map(|item| {
+        Ok::<_, Infallible>(match item {
+            LogTailItem::Log(row) => Event::default()
+                .id(row.id.to_string())
+                .data(task_log_to_json(row).to_string()),
+            LogTailItem::End(status) => Event::default()
+                .event("end")
+                .data(serde_json::json!({ "status": status }).to_string()),
+        })
+    });
+
+    Ok(sse_response(
+        stream,
+        state.dispatcher.events().sse_keep_alive(),
+    ))
+}
+
 async fn list_workers(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
     // Return in-memory connected workers from dispatcher
     let workers: Vec<serde_json::Value> = state
@@ -538,8 +1478,14 @@ async fn list_workers(State(state): State<AppState>) -> Result<impl IntoResponse
                 "name": h.worker_name,
                 "queues": h.queues,
                 "concurrency": h.concurrency,
+                "queue_concurrency": h.queue_concurrency,
+                "version": h.version,
+                "labels": h.labels,
+                "excluded_queues": state.dispatcher.excluded_queues(h).into_iter()
+                    .map(|(queue, reason)| serde_json::json!({ "queue": queue, "reason": reason }))
+                    .collect::<Vec<_>>(),
                 "active_tasks": h.active_tasks.len(),
-                "status": "CONNECTED",
+                "status": h.state().as_str(),
                 "last_heartbeat": h.last_heartbeat.to_rfc3339(),
                 "connected_at": h.connected_at.to_rfc3339(),
             })
@@ -548,6 +1494,66 @@ async fn list_workers(State(state): State<AppState>) -> Result<impl IntoResponse
     Ok(Json(workers))
 }
 
+#[derive(Deserialize)]
+struct WorkerLogsQuery {
+    /// Minimum severity (DEBUG, INFO, WARN, ERROR)
+    #[serde(default)]
+    level: Option<String>,
+    #[serde(default)]
+    from_ms: Option<i64>,
+    #[serde(default)]
+    to_ms: Option<i64>,
+    #[serde(default = "default_log_limit")]
+    limit: i64,
+}
+
+const LOG_LEVELS: [&str; 4] = ["DEBUG", "INFO", "WARN", "ERROR"];
+
+async fn get_worker_logs(
+    State(state): State<AppState>,
+    ApiPath(worker_id): ApiPath<String>,
+    ApiQuery(query): ApiQuery<WorkerLogsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let levels = match query.level {
+        Some(level) => {
+            let level = level.to_uppercase();
+            let idx = LOG_LEVELS
+                .iter()
+                .position(|l| *l == level)
+                .ok_or_else(|| ApiError::Validation(format!("Unknown log level: {level}")))?;
+            LOG_LEVELS[idx..].iter().map(|l| l.to_string()).collect()
+        }
+        None => vec![],
+    };
+
+    let logs = valka_db::queries::worker_logs::get_worker_logs(
+        &state.pool,
+        &worker_id,
+        &valka_db::queries::worker_logs::WorkerLogFilter {
+            levels,
+            from_ms: query.from_ms,
+            to_ms: query.to_ms,
+            limit: query.limit,
+        },
+    )
+    .await?;
+
+    let result: Vec<serde_json::Value> = logs
+        .into_iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.id,
+                "worker_id": row.worker_id,
+                "timestamp_ms": row.timestamp_ms,
+                "level": row.level,
+                "message": row.message,
+                "metadata": row.metadata,
+            })
+        })
+        .collect();
+    Ok(Json(result))
+}
+
 #[derive(Deserialize)]
 struct DeadLetterQuery {
     #[serde(default)]
@@ -560,7 +1566,7 @@ struct DeadLetterQuery {
 
 async fn list_dead_letters(
     State(state): State<AppState>,
-    Query(query): Query<DeadLetterQuery>,
+    ApiQuery(query): ApiQuery<DeadLetterQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
     let dls = valka_db::queries::dead_letter::list_dead_letters(
         &state.pool,
@@ -568,12 +1574,26 @@ async fn list_dead_letters(
         query.limit,
         query.offset,
     )
-    .await
-    .map_err(|e| ApiError::Internal(e.to_string()))?;
+    .await?;
+
+    // Attach queue ownership so alerts built from this response can route themselves
+    let mut queue_names: Vec<String> = dls.iter().map(|dl| dl.queue_name.clone()).collect();
+    queue_names.sort();
+    queue_names.dedup();
+    let owners: HashMap<String, serde_json::Value> =
+        valka_db::queries::queue_configs::get_queue_configs(&state.pool, &queue_names)
+            .await?
+            .into_iter()
+            .map(|c| (c.queue_name.clone(), queue_owner_to_json(&c)))
+            .collect();
 
     let result: Vec<serde_json::Value> = dls
         .into_iter()
         .map(|dl| {
+            let owner = owners
+                .get(&dl.queue_name)
+                .cloned()
+                .unwrap_or(serde_json::Value::Null);
             serde_json::json!({
                 "id": dl.id,
                 "task_id": dl.task_id,
@@ -581,8 +1601,10 @@ async fn list_dead_letters(
                 "task_name": dl.task_name,
                 "input": dl.input,
                 "error_message": dl.error_message,
+                "worker_id": dl.worker_id,
                 "attempt_count": dl.attempt_count,
                 "metadata": dl.metadata,
+                "owner": owner,
                 "created_at": dl.created_at.to_rfc3339(),
             })
         })
@@ -590,63 +1612,1111 @@ async fn list_dead_letters(
     Ok(Json(result))
 }
 
-async fn subscribe_events_sse(
+async fn delete_dead_letter(
     State(state): State<AppState>,
-) -> Sse<impl futures::Stream<Item = Result<Event, Infallible>>> {
-    let mut rx = state.event_tx.subscribe();
+    ApiPath(dl_id): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = valka_db::queries::dead_letter::delete_dead_letter(&state.pool, &dl_id).await?;
 
-    let stream = async_stream::stream! {
-        loop {
-            match rx.recv().await {
-                Ok(event) => {
-                    let data = serde_json::json!({
-                        "event_id": event.event_id,
-                        "task_id": event.task_id,
-                        "queue_name": event.queue_name,
-                        "new_status": event.new_status,
-                        "timestamp_ms": event.timestamp_ms,
-                    });
-                    yield Ok(Event::default().data(data.to_string()));
-                }
-                Err(broadcast::error::RecvError::Lagged(_)) => continue,
-                Err(broadcast::error::RecvError::Closed) => break,
+    if !deleted {
+        return Err(ApiError::NotFound("Dead letter not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
+#[derive(Deserialize)]
+struct PurgeDeadLettersQuery {
+    #[serde(default)]
+    queue_name: Option<String>,
+    /// RFC 3339 timestamp; only dead letters created before it are deleted
+    #[serde(default)]
+    older_than: Option<String>,
+}
+
+async fn purge_dead_letters(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<PurgeDeadLettersQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let older_than = query
+        .older_than
+        .as_deref()
+        .map(|s| {
+            s.parse::<chrono::DateTime<chrono::Utc>>()
+                .map_err(|_| ApiError::Validation(format!("Invalid older_than timestamp: {s}")))
+        })
+        .transpose()?;
+    let count = valka_db::queries::dead_letter::purge_dead_letters(
+        &state.pool,
+        query.queue_name.as_deref(),
+        older_than,
+    )
+    .await?;
+
+    Ok(Json(serde_json::json!({ "deleted_count": count })))
+}
+
+/// Re-run a dead-lettered task: back to PENDING with attempt_count 0, then
+/// dispatched the same way as a newly created task.
+async fn retry_dead_letter(
+    State(state): State<AppState>,
+    ApiPath(dl_id): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let dl = valka_db::queries::dead_letter::get_dead_letter(&state.pool, &dl_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Dead letter not found".to_string()))?;
+    // Only used if the task row is gone and has to be recreated
+    let partition = partition_for_task(
+        &dl.queue_name,
+        &dl.task_id,
+        state.matching.config().num_partitions,
+    );
+    let Some(task) =
+        valka_db::queries::dead_letter::requeue_dead_letter(&state.pool, &dl_id, partition.0)
+            .await?
+    else {
+        let current = valka_db::queries::tasks::get_task(&state.pool, &dl.task_id).await?;
+        return Err(match current {
+            Some(task) => {
+                ApiError::InvalidState(format!("Task is {}, not DEAD_LETTER", task.status))
+            }
+            // Retried by someone else in the meantime
+            None => ApiError::NotFound("Dead letter not found".to_string()),
+        });
+    };
+    info!(dead_letter_id = %dl_id, task_id = %task.id, "Dead letter requeued");
+
+    state.dispatcher.events().emit(valka_proto::TaskEvent {
+        event_id: uuid::Uuid::now_v7().to_string(),
+        task_id: task.id.clone(),
+        queue_name: task.queue_name.clone(),
+        previous_status: 7, // DEAD_LETTER
+        new_status: 1,      // PENDING
+        worker_id: String::new(),
+        node_id: state.node_id.clone(),
+        attempt_number: 0,
+        error_message: String::new(),
+        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+        suppressed_count: 0,
+        progress: None,
+        progress_message: String::new(),
+    });
+
+    let partition = PartitionId(task.partition_id);
+    if !state
+        .cluster
+        .owns_partition(&task.queue_name, partition.0)
+        .await
+        && let Some(owner_addr) = state
+            .cluster
+            .get_partition_owner_addr(&task.queue_name, partition.0)
+            .await
+    {
+        let _ = state
+            .forwarder
+            .forward_task(&owner_addr, &task.id, &task.queue_name, partition.0)
+            .await;
+        valka_core::metrics::record_task_forwarded(&task.queue_name);
+        return Ok(Json(task.to_json()));
+    }
+
+    let envelope = TaskEnvelope {
+        task_id: task.id.clone(),
+        task_run_id: String::new(),
+        queue_name: task.queue_name.clone(),
+        task_name: task.task_name.clone(),
+        input: task.input.as_ref().map(|v| v.to_string()),
+        attempt_number: 1,
+        timeout_seconds: task.timeout_seconds,
+        metadata: task.metadata.to_string(),
+        priority: task.priority,
+        deadline_ms: task.deadline_at.map(|d| d.timestamp_millis()),
+        ready_at_ms: Some(task.ready_at().timestamp_millis()),
+        created_at_ms: Some(task.created_at.timestamp_millis()),
+        scheduled_at_ms: task.scheduled_at.map(|at| at.timestamp_millis()),
+        traceparent: task.traceparent.clone(),
+        fifo_key: task.fifo_key.clone(),
+        required_labels: task.required_labels(),
+        ephemeral: None,
+    };
+    state.dispatcher.offer_new_task(partition, envelope).await;
+
+    Ok(Json(task.to_json()))
+}
+
+#[derive(Deserialize)]
+struct QueueConfigListQuery {
+    #[serde(default)]
+    owner_team: Option<String>,
+}
+
+async fn list_queue_configs(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<QueueConfigListQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let configs = valka_db::queries::queue_configs::list_queue_configs(
+        &state.pool,
+        query.owner_team.as_deref(),
+    )
+    .await?;
+
+    let result: Vec<serde_json::Value> = configs.into_iter().map(queue_config_to_json).collect();
+    Ok(Json(result))
+}
+
+async fn get_queue_config(
+    State(state): State<AppState>,
+    ApiPath(queue_name): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let config = valka_db::queries::queue_configs::get_queue_config(&state.pool, &queue_name)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Queue config not found: {queue_name}")))?;
+
+    let held_past_due = if config.scheduled_hold || config.release_max_per_tick.is_some() {
+        valka_db::queries::tasks::count_past_due_scheduled(&state.pool, &queue_name).await?
+    } else {
+        0
+    };
+
+    let mut json = queue_config_to_json(config);
+    json["held_past_due"] = serde_json::json!(held_past_due);
+    Ok(Json(json))
+}
+
+#[derive(Deserialize)]
+struct UpdateQueueConfigBody {
+    #[serde(default)]
+    owner_team: Option<String>,
+    #[serde(default)]
+    contact: Option<String>,
+    #[serde(default)]
+    runbook_url: Option<String>,
+    #[serde(default)]
+    labels: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    min_worker_version: Option<String>,
+    #[serde(default)]
+    allow_ephemeral: Option<bool>,
+    #[serde(default)]
+    template_input: Option<bool>,
+    #[serde(default)]
+    fifo: Option<bool>,
+    #[serde(default)]
+    default_max_retries: Option<i32>,
+    #[serde(default)]
+    default_timeout_seconds: Option<i32>,
+    #[serde(default)]
+    max_concurrency: Option<i32>,
+    #[serde(default)]
+    paused: Option<bool>,
+    #[serde(default)]
+    retention_days: Option<i32>,
+    #[serde(default)]
+    dead_letter_enabled: Option<bool>,
+    #[serde(default)]
+    max_dispatch_per_second: Option<i32>,
+}
+
+async fn update_queue_config(
+    State(state): State<AppState>,
+    ApiPath(queue_name): ApiPath<String>,
+    ApiJson(body): ApiJson<UpdateQueueConfigBody>,
+) -> Result<impl IntoResponse, ApiError> {
+    let update = valka_db::queries::queue_configs::QueueOwnershipUpdate {
+        owner_team: body.owner_team,
+        contact: body.contact,
+        runbook_url: body.runbook_url,
+        labels: body.labels,
+        min_worker_version: body.min_worker_version,
+        allow_ephemeral: body.allow_ephemeral,
+        template_input: body.template_input,
+        fifo: body.fifo,
+        default_max_retries: body.default_max_retries,
+        default_timeout_seconds: body.default_timeout_seconds,
+        max_concurrency: body.max_concurrency,
+        paused: body.paused,
+        retention_days: body.retention_days,
+        dead_letter_enabled: body.dead_letter_enabled,
+        max_dispatch_per_second: body.max_dispatch_per_second,
+    };
+    update.validate().map_err(ApiError::Validation)?;
+
+    let config =
+        valka_db::queries::queue_configs::upsert_queue_ownership(&state.pool, &queue_name, &update)
+            .await?;
+    state.dispatcher.queue_policies().apply_config(&config);
+
+    Ok(Json(queue_config_to_json(config)))
+}
+
+async fn pause_queue(
+    State(state): State<AppState>,
+    ApiPath(queue_name): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    set_queue_paused(&state, &queue_name, true).await
+}
+
+async fn resume_queue(
+    State(state): State<AppState>,
+    ApiPath(queue_name): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    set_queue_paused(&state, &queue_name, false).await
+}
+
+/// Persist the flag so it survives restarts, apply it here straight away and
+/// publish it so other nodes apply it without waiting for their next refresh.
+async fn set_queue_paused(
+    state: &AppState,
+    queue_name: &str,
+    paused: bool,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let config =
+        valka_db::queries::queue_configs::set_paused(&state.pool, queue_name, paused).await?;
+    state.dispatcher.queue_policies().apply_config(&config);
+    state
+        .cluster
+        .set_key(
+            &crate::server::queue_paused_key(queue_name),
+            &paused.to_string(),
+        )
+        .await;
+
+    Ok(Json(queue_config_to_json(config)))
+}
+
+async fn hold_scheduled(
+    State(state): State<AppState>,
+    ApiPath(queue_name): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let config =
+        valka_db::queries::queue_configs::set_scheduled_hold(&state.pool, &queue_name, true, None)
+            .await?;
+
+    Ok(Json(queue_config_to_json(config)))
+}
+
+#[derive(Deserialize)]
+struct ReleaseScheduledBody {
+    #[serde(default)]
+    max_per_tick: Option<i32>,
+}
+
+async fn release_scheduled(
+    State(state): State<AppState>,
+    ApiPath(queue_name): ApiPath<String>,
+    body: Option<ApiJson<ReleaseScheduledBody>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let max_per_tick = body.and_then(|ApiJson(b)| b.max_per_tick);
+    if max_per_tick.is_some_and(|m| m <= 0) {
+        return Err(ApiError::Validation(
+            "max_per_tick must be greater than 0".to_string(),
+        ));
+    }
+
+    let config = valka_db::queries::queue_configs::set_scheduled_hold(
+        &state.pool,
+        &queue_name,
+        false,
+        max_per_tick,
+    )
+    .await?;
+
+    Ok(Json(queue_config_to_json(config)))
+}
+
+async fn list_quotas(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let rows = valka_db::queries::quotas::list_quotas(&state.pool).await?;
+
+    let now = chrono::Utc::now();
+    let result: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| {
+            let cached = state.dispatcher.quotas().usage(&row.key, now);
+            serde_json::json!({
+                "key": row.key,
+                "max_active_tasks": row.max_active_tasks,
+                "max_creations_per_hour": row.max_creations_per_hour,
+                "active_tasks": cached.as_ref().map(|u| u.active_tasks),
+                "created_this_hour": cached.as_ref().map(|u| u.created_this_hour),
+                "created_at": row.created_at.to_rfc3339(),
+                "updated_at": row.updated_at.to_rfc3339(),
+            })
+        })
+        .collect();
+    Ok(Json(result))
+}
+
+#[derive(Deserialize)]
+struct UpdateQuotaBody {
+    #[serde(default)]
+    max_active_tasks: Option<i64>,
+    #[serde(default)]
+    max_creations_per_hour: Option<i64>,
+}
+
+async fn update_quota(
+    State(state): State<AppState>,
+    ApiPath(key): ApiPath<String>,
+    ApiJson(body): ApiJson<UpdateQuotaBody>,
+) -> Result<impl IntoResponse, ApiError> {
+    if key.is_empty() || key.contains('.') {
+        return Err(ApiError::Validation(
+            "Quota key must be a namespace (a queue name prefix without '.')".to_string(),
+        ));
+    }
+    for (field, value) in [
+        ("max_active_tasks", body.max_active_tasks),
+        ("max_creations_per_hour", body.max_creations_per_hour),
+    ] {
+        if value.is_some_and(|v| v <= 0) {
+            return Err(ApiError::Validation(format!(
+                "{field} must be greater than 0"
+            )));
+        }
+    }
+
+    let row = valka_db::queries::quotas::upsert_quota(
+        &state.pool,
+        &key,
+        body.max_active_tasks,
+        body.max_creations_per_hour,
+    )
+    .await?;
+    let usage = QuotaUsage::load(&state.pool, Some(&row), &key, chrono::Utc::now())
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    state.dispatcher.quotas().set(&key, Some(usage.clone()));
+
+    Ok(Json(usage))
+}
+
+async fn delete_quota(
+    State(state): State<AppState>,
+    ApiPath(key): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = valka_db::queries::quotas::delete_quota(&state.pool, &key).await?;
+    if !deleted {
+        return Err(ApiError::NotFound(format!("Quota not found: {key}")));
+    }
+    state.dispatcher.quotas().set(&key, None);
+
+    Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
+/// Usage counted in PG; limits are null when the namespace has no quota.
+async fn get_quota_usage(
+    State(state): State<AppState>,
+    ApiPath(key): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let row = valka_db::queries::quotas::get_quota(&state.pool, &key).await?;
+    let usage = QuotaUsage::load(&state.pool, row.as_ref(), &key, chrono::Utc::now())
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    Ok(Json(usage))
+}
+
+// ─── Recurring schedules ────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct CreateScheduleBody {
+    cron_expression: String,
+    queue_name: String,
+    task_name: String,
+    #[serde(default)]
+    input: Option<serde_json::Value>,
+    #[serde(default)]
+    priority: i32,
+    /// 0 takes the queue's default
+    #[serde(default)]
+    max_retries: i32,
+    /// 0 takes the queue's default
+    #[serde(default)]
+    timeout_seconds: i32,
+}
+
+async fn create_schedule(
+    State(state): State<AppState>,
+    ApiJson(body): ApiJson<CreateScheduleBody>,
+) -> Result<impl IntoResponse, ApiError> {
+    if body.queue_name.is_empty() || body.task_name.is_empty() {
+        return Err(ApiError::Validation(
+            "queue_name and task_name are required".to_string(),
+        ));
+    }
+    let now = chrono::Utc::now();
+    let next_fire_at = valka_scheduler::cron::first_fire(&body.cron_expression, now)
+        .map_err(ApiError::Validation)?;
+    let defaults = state
+        .dispatcher
+        .queue_policies()
+        .task_defaults(&body.queue_name);
+    let params = valka_db::queries::schedules::CreateScheduleParams {
+        id: TaskId::new().0,
+        cron_expression: body.cron_expression.trim().to_string(),
+        queue_name: body.queue_name,
+        task_name: body.task_name,
+        input: body.input,
+        priority: body.priority,
+        max_retries: defaults.max_retries(body.max_retries),
+        timeout_seconds: defaults.timeout_seconds(body.timeout_seconds),
+        next_fire_at,
+    };
+    let row = valka_db::queries::schedules::create_schedule(&state.pool, params).await?;
+
+    Ok((StatusCode::CREATED, Json(row.to_json())))
+}
+
+#[derive(Deserialize)]
+struct ListSchedulesQuery {
+    #[serde(default)]
+    queue: Option<String>,
+}
+
+async fn list_schedules(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<ListSchedulesQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rows =
+        valka_db::queries::schedules::list_schedules(&state.pool, query.queue.as_deref()).await?;
+    let result: Vec<serde_json::Value> = rows.iter().map(|row| row.to_json()).collect();
+    Ok(Json(result))
+}
+
+async fn get_schedule(
+    State(state): State<AppState>,
+    ApiPath(schedule_id): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let row = valka_db::queries::schedules::get_schedule(&state.pool, &schedule_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Schedule not found: {schedule_id}")))?;
+    Ok(Json(row.to_json()))
+}
+
+async fn delete_schedule(
+    State(state): State<AppState>,
+    ApiPath(schedule_id): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = valka_db::queries::schedules::delete_schedule(&state.pool, &schedule_id).await?;
+    if !deleted {
+        return Err(ApiError::NotFound(format!(
+            "Schedule not found: {schedule_id}"
+        )));
+    }
+
+    Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
+// ─── Usage accounting ───────────────────────────────────────────────
+
+/// Longest date range a usage query or backfill may span.
+const MAX_USAGE_DAYS: i64 = 366;
+
+#[derive(Deserialize)]
+struct UsageRange {
+    #[serde(default)]
+    from: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    to: Option<chrono::NaiveDate>,
+}
+
+impl UsageRange {
+    fn validate(&self) -> Result<(chrono::NaiveDate, chrono::NaiveDate), ApiError> {
+        let (Some(from), Some(to)) = (self.from, self.to) else {
+            return Err(ApiError::Validation(
+                "from and to are required (YYYY-MM-DD)".to_string(),
+            ));
+        };
+        if from > to {
+            return Err(ApiError::Validation(
+                "from must not be after to".to_string(),
+            ));
+        }
+        if (to - from).num_days() >= MAX_USAGE_DAYS {
+            return Err(ApiError::Validation(format!(
+                "Range must not exceed {MAX_USAGE_DAYS} days"
+            )));
+        }
+        Ok((from, to))
+    }
+}
+
+#[derive(Deserialize)]
+struct UsageQuery {
+    #[serde(default)]
+    from: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    to: Option<chrono::NaiveDate>,
+    #[serde(default)]
+    group_by: Option<String>,
+}
+
+const USAGE_COUNTERS: [&str; 5] = [
+    "tasks_created",
+    "tasks_completed",
+    "tasks_failed",
+    "total_execution_ms",
+    "total_log_bytes",
+];
+
+/// Dashboard counts: tasks by status and by queue, workers connected to this
+/// node and not draining, dead letters, and task activity over the last hour.
+async fn get_stats(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let now = chrono::Utc::now();
+    let hour_ago = now - chrono::Duration::hours(1);
+    let (grouped, dead_letters, (created_last_hour, completed_last_hour)) = tokio::try_join!(
+        valka_db::queries::tasks::count_tasks_grouped(&state.pool),
+        valka_db::queries::dead_letter::count_dead_letters(&state.pool),
+        valka_db::queries::tasks::count_recent_tasks(&state.pool, hour_ago),
+    )?;
+
+    let mut total = 0;
+    let mut by_status = serde_json::Map::new();
+    let mut by_queue: Vec<(String, i64, serde_json::Map<String, serde_json::Value>)> = Vec::new();
+    for row in grouped {
+        total += row.count;
+        let status_total = by_status
+            .get(&row.status)
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0);
+        by_status.insert(row.status.clone(), (status_total + row.count).into());
+        // Rows arrive ordered by queue
+        match by_queue.last_mut() {
+            Some((queue, queue_total, statuses)) if *queue == row.queue_name => {
+                *queue_total += row.count;
+                statuses.insert(row.status, row.count.into());
+            }
+            _ => {
+                let mut statuses = serde_json::Map::new();
+                statuses.insert(row.status, row.count.into());
+                by_queue.push((row.queue_name, row.count, statuses));
             }
         }
+    }
+    by_queue.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(Json(serde_json::json!({
+        "total_tasks": total,
+        "by_status": by_status,
+        "by_queue": by_queue
+            .into_iter()
+            .map(|(queue_name, total, by_status)| serde_json::json!({
+                "queue_name": queue_name,
+                "total": total,
+                "by_status": by_status,
+            }))
+            .collect::<Vec<_>>(),
+        "active_workers": state
+            .dispatcher
+            .workers()
+            .iter()
+            .filter(|w| !w.is_draining())
+            .count(),
+        "dead_letters": dead_letters,
+        "created_last_hour": created_last_hour,
+        "completed_last_hour": completed_last_hour,
+        "generated_at": now.to_rfc3339(),
+    })))
+}
+
+/// Daily usage rolled up by the scheduler, as JSON or as CSV when the client
+/// sends `Accept: text/csv`. Activity after `complete_through` is not yet counted.
+async fn get_usage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ApiQuery(query): ApiQuery<UsageQuery>,
+) -> Result<Response, ApiError> {
+    use valka_db::queries::usage::{self, UsageGroupBy};
+
+    let (from, to) = UsageRange {
+        from: query.from,
+        to: query.to,
+    }
+    .validate()?;
+    let group_by = match query.group_by.as_deref() {
+        None => UsageGroupBy::default(),
+        Some(s) => UsageGroupBy::parse(s).ok_or_else(|| {
+            ApiError::Validation(format!(
+                "Invalid group_by: {s} (expected queue, namespace or owner_team)"
+            ))
+        })?,
     };
 
-    Sse::new(stream)
+    let rows = usage::list_usage(&state.pool, from, to, group_by).await?;
+    let key_column = group_by.column();
+
+    let wants_csv = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/csv"));
+    if wants_csv {
+        let mut csv = format!("date,{key_column},{}\n", USAGE_COUNTERS.join(","));
+        for row in &rows {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                row.date,
+                csv_field(row.key.as_deref().unwrap_or_default()),
+                row.tasks_created,
+                row.tasks_completed,
+                row.tasks_failed,
+                row.total_execution_ms,
+                row.total_log_bytes,
+            ));
+        }
+        return Ok(([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], csv).into_response());
+    }
+
+    let complete_through = usage::get_high_water_mark(&state.pool).await?;
+    let rows: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| {
+            serde_json::json!({
+                "date": row.date.to_string(),
+                key_column: row.key,
+                "tasks_created": row.tasks_created,
+                "tasks_completed": row.tasks_completed,
+                "tasks_failed": row.tasks_failed,
+                "total_execution_ms": row.total_execution_ms,
+                "total_log_bytes": row.total_log_bytes,
+            })
+        })
+        .collect();
+    Ok(Json(serde_json::json!({
+        "from": from.to_string(),
+        "to": to.to_string(),
+        "group_by": key_column,
+        "complete_through": complete_through.to_rfc3339(),
+        "rows": rows,
+    }))
+    .into_response())
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Recompute usage for a date range from the tasks, runs and logs tables.
+async fn backfill_usage(
+    State(state): State<AppState>,
+    ApiJson(body): ApiJson<UsageRange>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (from, to) = body.validate()?;
+    let rows = valka_db::queries::usage::backfill_usage(&state.pool, from, to).await?;
+    info!(%from, %to, rows, "Backfilled usage");
+
+    Ok(Json(serde_json::json!({
+        "from": from.to_string(),
+        "to": to.to_string(),
+        "rows": rows,
+    })))
 }
 
-async fn metrics(State(state): State<AppState>) -> String {
-    state.metrics_handle.render()
+/// Task events as SSE, each with its event_id as the SSE id. A client
+/// reconnecting with `Last-Event-ID` first gets the buffered events it missed.
+async fn subscribe_events_sse(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| !id.is_empty());
+    let events = state.dispatcher.events();
+    let mut subscription = events.subscribe_since(last_event_id);
+
+    let stream = async_stream::stream! {
+        let _subscriber = valka_core::metrics::track_event_subscriber("sse");
+        while let Some(event) = subscription.next().await {
+            let data = serde_json::json!({
+                "event_id": event.event_id,
+                "task_id": event.task_id,
+                "queue_name": event.queue_name,
+                "new_status": event.new_status,
+                "timestamp_ms": event.timestamp_ms,
+                "suppressed_count": event.suppressed_count,
+                "progress": event.progress,
+                "progress_message": event.progress.map(|_| event.progress_message),
+            });
+            yield Ok::<_, Infallible>(Event::default().id(event.event_id).data(data.to_string()));
+        }
+    };
+
+    sse_response(stream, events.sse_keep_alive())
+}
+
+fn sse_response<S>(stream: S, keep_alive: Option<std::time::Duration>) -> Response
+where
+    S: futures::Stream<Item = Result<Event, Infallible>> + Send + 'static,
+{
+    match keep_alive {
+        Some(interval) => Sse::new(stream)
+            .keep_alive(KeepAlive::new().interval(interval))
+            .into_response(),
+        None => Sse::new(stream).into_response(),
+    }
+}
+
+/// Prometheus text by default; OpenMetrics (with exemplars, if enabled) when the
+/// scraper asks for it via `Accept`.
+async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let body = state.metrics_handle.render();
+    let wants_openmetrics = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/openmetrics-text"));
+
+    if wants_openmetrics {
+        (
+            [(
+                header::CONTENT_TYPE,
+                valka_core::metrics::OPENMETRICS_CONTENT_TYPE,
+            )],
+            valka_core::metrics::to_openmetrics(&body),
+        )
+            .into_response()
+    } else {
+        body.into_response()
+    }
+}
+
+/// Current self-telemetry sample (the stats behind the `valka_internal_*` gauges)
+async fn debug_internal(State(state): State<AppState>) -> impl IntoResponse {
+    let sources = crate::telemetry::TelemetrySources {
+        event_tx: Some(state.event_tx.clone()),
+        log_tx: Some(state.dispatcher.log_tx().clone()),
+        dispatcher: Some(state.dispatcher.clone()),
+        matching: Some(state.matching.clone()),
+        forwarder: Some(state.forwarder.clone()),
+    };
+    Json(sources.sample().await)
+}
+
+/// This node's in-memory matching state: buffered envelopes and waiting
+/// worker slots per queue partition.
+async fn debug_matching(State(state): State<AppState>) -> impl IntoResponse {
+    let stats = state.matching.stats();
+    let partitions: Vec<_> = stats
+        .by_partition
+        .into_iter()
+        .map(|p| {
+            serde_json::json!({
+                "queue_name": p.queue_name,
+                "partition_id": p.partition_id,
+                "buffered_tasks": p.buffered_tasks,
+                "waiting_workers": p.waiting_workers,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "node_id": state.node_id,
+        "buffered_tasks": stats.buffered_tasks,
+        "waiting_workers": stats.waiting_workers,
+        "partitions": partitions,
+    }))
+}
+
+/// This node's view of the cluster: its id, the members it sees, and whether
+/// it runs the scheduler.
+async fn cluster_status(State(state): State<AppState>) -> impl IntoResponse {
+    let mut members: Vec<String> = state.cluster.members().await.into_iter().collect();
+    members.sort();
+    let leader_since = state.cluster.leader_since().await;
+    Json(serde_json::json!({
+        "node_id": state.node_id,
+        "clustered": state.cluster.is_clustered(),
+        "members": members,
+        "is_leader": leader_since.is_some(),
+        "leader_since": leader_since.map(|t| t.to_rfc3339()),
+    }))
+}
+
+/// Every node this one knows of, live or marked dead by gossip.
+async fn cluster_members(State(state): State<AppState>) -> impl IntoResponse {
+    let members: Vec<_> = state
+        .cluster
+        .member_details()
+        .await
+        .into_iter()
+        .map(|m| {
+            serde_json::json!({
+                "is_self": m.node_id == state.node_id,
+                "node_id": m.node_id,
+                "grpc_addr": m.grpc_addr,
+                "live": m.live,
+                "joined_at": m.joined_at.map(|t| t.to_rfc3339()),
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "members": members }))
+}
+
+#[derive(Deserialize)]
+struct ClusterPartitionsQuery {
+    #[serde(default)]
+    queue_name: String,
+}
+
+/// Which node owns each partition of a queue, by this node's view of the ring.
+async fn cluster_partitions(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<ClusterPartitionsQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if query.queue_name.is_empty() {
+        return Err(ApiError::Validation("queue_name is required".to_string()));
+    }
+    let num_partitions = state.matching.config().num_partitions;
+    let mut partitions = Vec::new();
+    for assignment in state
+        .cluster
+        .partition_map(&query.queue_name, num_partitions)
+        .await
+    {
+        let grpc_addr = state
+            .cluster
+            .get_node_grpc_addr(&assignment.owner_node_id)
+            .await;
+        partitions.push(serde_json::json!({
+            "partition_id": assignment.partition_id.0,
+            "node_id": assignment.owner_node_id,
+            "grpc_addr": grpc_addr,
+        }));
+    }
+    Ok(Json(serde_json::json!({
+        "queue_name": query.queue_name,
+        "num_partitions": num_partitions,
+        "partitions": partitions,
+    })))
+}
+
+#[derive(Deserialize)]
+struct DispatchDecisionsQuery {
+    #[serde(default)]
+    queue: Option<String>,
+    #[serde(default = "default_decision_limit")]
+    limit: usize,
+}
+
+fn default_decision_limit() -> usize {
+    200
+}
+
+/// Sampled matching/dispatch decisions of this node, newest first.
+async fn list_dispatch_decisions(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<DispatchDecisionsQuery>,
+) -> impl IntoResponse {
+    let log = state.matching.decisions();
+    let decisions: Vec<_> = log
+        .recent(query.queue.as_deref(), query.limit)
+        .into_iter()
+        .map(|d| {
+            serde_json::json!({
+                "at": d.at.to_rfc3339(),
+                "queue_name": d.queue_name,
+                "partition_id": d.partition_id,
+                "task_id": d.task_id,
+                "outcome": d.outcome.as_str(),
+                "worker_id": d.outcome.worker_id().map(|w| w.0.clone()),
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "node_id": state.node_id,
+        "sample_rate": log.sample_rate(),
+        "full_sampling": forced_sampling_json(&state),
+        "decisions": decisions,
+    }))
+}
+
+#[derive(Deserialize)]
+struct DecisionSamplingBody {
+    #[serde(default)]
+    ttl_secs: Option<u64>,
+}
+
+const DEFAULT_SAMPLING_TTL_SECS: u64 = 600;
+/// Longest a queue can be forced to full sampling in one go.
+const MAX_SAMPLING_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Record every task of a queue on this node until the TTL runs out.
+async fn force_decision_sampling(
+    State(state): State<AppState>,
+    ApiPath(queue_name): ApiPath<String>,
+    body: Option<ApiJson<DecisionSamplingBody>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let ttl_secs = body
+        .and_then(|ApiJson(b)| b.ttl_secs)
+        .unwrap_or(DEFAULT_SAMPLING_TTL_SECS);
+    if ttl_secs == 0 || ttl_secs > MAX_SAMPLING_TTL_SECS {
+        return Err(ApiError::Validation(format!(
+            "ttl_secs must be between 1 and {MAX_SAMPLING_TTL_SECS}"
+        )));
+    }
+    state
+        .matching
+        .decisions()
+        .force_sampling(&queue_name, std::time::Duration::from_secs(ttl_secs));
+    Ok(Json(serde_json::json!({
+        "full_sampling": forced_sampling_json(&state),
+    })))
+}
+
+async fn clear_decision_sampling(
+    State(state): State<AppState>,
+    ApiPath(queue_name): ApiPath<String>,
+) -> impl IntoResponse {
+    state.matching.decisions().clear_forced(&queue_name);
+    Json(serde_json::json!({
+        "full_sampling": forced_sampling_json(&state),
+    }))
+}
+
+fn forced_sampling_json(state: &AppState) -> Vec<serde_json::Value> {
+    state
+        .matching
+        .decisions()
+        .forced_queues()
+        .into_iter()
+        .map(|(queue_name, until)| {
+            serde_json::json!({"queue_name": queue_name, "until": until.to_rfc3339()})
+        })
+        .collect()
 }
 
 async fn healthz() -> &'static str {
     "ok"
 }
 
-fn task_row_to_json(row: valka_db::queries::tasks::TaskRow) -> serde_json::Value {
+/// Fails as soon as shutdown starts, while the database doesn't answer, or
+/// while gossip sees too few members, so load balancers stop routing here.
+/// Unlike `/healthz`, which only says the process is up.
+async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let readiness = health::check_readiness(&state.pool, &state.dispatcher, &state.cluster).await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+/// Require an API token on `/api/v1/*`, and on the health and metrics
+/// endpoints if `auth` says so. Browsers' `EventSource` can't set headers, so
+/// an `access_token` query parameter is accepted too.
+pub fn require_api_auth(router: Router, auth: ApiAuth) -> Router {
+    router.layer(middleware::from_fn_with_state(auth, check_api_token))
+}
+
+async fn check_api_token(State(auth): State<ApiAuth>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    let protected = match path {
+        "/healthz" | "/readyz" => auth.protects_health(),
+        "/metrics" | "/debug/internal" => auth.protects_metrics(),
+        _ => path.starts_with("/api/v1/"),
+    };
+    // CORS preflights carry no credentials
+    if !protected || request.method() == axum::http::Method::OPTIONS {
+        return next.run(request).await;
+    }
+
+    let query_token = request
+        .uri()
+        .query()
+        .and_then(|q| serde_urlencoded::from_str::<HashMap<String, String>>(q).ok())
+        .and_then(|mut params| params.remove("access_token"))
+        .map(|token| format!("Bearer {token}"));
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .or(query_token.as_deref());
+    if !auth.allows_api(authorization) {
+        return ApiError::Unauthorized("Missing or invalid API token".to_string()).into_response();
+    }
+    next.run(request).await
+}
+
+/// Once shutdown starts, refuse anything that could create or change work so
+/// clients retry on another node. Reads keep working.
+async fn refuse_writes_while_quiescing(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !request.method().is_safe() && !state.dispatcher.shutdown().accepting_work() {
+        return ApiError::Unavailable("Node is shutting down".to_string()).into_response();
+    }
+    next.run(request).await
+}
+
+fn retry_plan_to_json(plan: &valka_core::retry::RetryPlan) -> serde_json::Value {
+    serde_json::json!({
+        "next_attempt_at": plan.next_attempt_at.map(|t| t.to_rfc3339()),
+        "attempts_remaining": plan.attempts_remaining,
+        "schedule": plan.schedule.iter().map(|t| t.to_rfc3339()).collect::<Vec<_>>(),
+        "dead_letter": plan.dead_letter,
+    })
+}
+
+fn emit_task_created(state: &AppState, task_id: &str, queue_name: &str) {
+    state.dispatcher.events().emit(valka_proto::TaskEvent {
+        event_id: uuid::Uuid::now_v7().to_string(),
+        task_id: task_id.to_string(),
+        queue_name: queue_name.to_string(),
+        previous_status: 0,
+        new_status: 1, // PENDING
+        worker_id: String::new(),
+        node_id: state.node_id.clone(),
+        attempt_number: 0,
+        error_message: String::new(),
+        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+        suppressed_count: 0,
+        progress: None,
+        progress_message: String::new(),
+    });
+}
+
+fn queue_config_to_json(
+    row: valka_db::queries::queue_configs::QueueConfigRow,
+) -> serde_json::Value {
     serde_json::json!({
-        "id": row.id,
         "queue_name": row.queue_name,
-        "task_name": row.task_name,
-        "status": row.status,
-        "priority": row.priority,
-        "max_retries": row.max_retries,
-        "attempt_count": row.attempt_count,
-        "timeout_seconds": row.timeout_seconds,
-        "idempotency_key": row.idempotency_key,
-        "input": row.input,
-        "metadata": row.metadata,
-        "output": row.output,
-        "error_message": row.error_message,
-        "scheduled_at": row.scheduled_at.map(|t| t.to_rfc3339()),
+        "owner_team": row.owner_team,
+        "contact": row.contact,
+        "runbook_url": row.runbook_url,
+        "labels": row.labels,
+        "min_worker_version": row.min_worker_version,
+        "scheduled_hold": row.scheduled_hold,
+        "release_max_per_tick": row.release_max_per_tick,
+        "allow_ephemeral": row.allow_ephemeral,
+        "template_input": row.template_input,
+        "fifo": row.fifo,
+        "default_max_retries": row.default_max_retries,
+        "default_timeout_seconds": row.default_timeout_seconds,
+        "max_concurrency": row.max_concurrency,
+        "paused": row.paused,
+        "retention_days": row.retention_days,
+        "dead_letter_enabled": row.dead_letter_enabled,
+        "max_dispatch_per_second": row.max_dispatch_per_second,
         "created_at": row.created_at.to_rfc3339(),
         "updated_at": row.updated_at.to_rfc3339(),
     })
 }
 
-fn task_run_to_json(row: valka_db::queries::task_runs::TaskRunRow) -> serde_json::Value {
+fn queue_owner_to_json(
+    row: &valka_db::queries::queue_configs::QueueConfigRow,
+) -> serde_json::Value {
+    serde_json::json!({
+        "owner_team": row.owner_team,
+        "contact": row.contact,
+        "runbook_url": row.runbook_url,
+        "labels": row.labels,
+    })
+}
+
+pub(crate) fn task_run_to_json(row: valka_db::queries::task_runs::TaskRunRow) -> serde_json::Value {
     serde_json::json!({
         "id": row.id,
         "task_id": row.task_id,
@@ -660,10 +2730,13 @@ fn task_run_to_json(row: valka_db::queries::task_runs::TaskRunRow) -> serde_json
         "started_at": row.started_at.to_rfc3339(),
         "completed_at": row.completed_at.map(|t| t.to_rfc3339()),
         "last_heartbeat": row.last_heartbeat.to_rfc3339(),
+        "progress": row.progress,
+        "progress_message": row.progress_message,
+        "output_truncated": row.output_truncated,
     })
 }
 
-fn task_log_to_json(row: valka_db::queries::task_logs::TaskLogRow) -> serde_json::Value {
+pub(crate) fn task_log_to_json(row: valka_db::queries::task_logs::TaskLogRow) -> serde_json::Value {
     serde_json::json!({
         "id": row.id,
         "task_run_id": row.task_run_id,
@@ -0,0 +1,81 @@
+//! Reaching the worker running a task, wherever it streams. A worker is only
+//! connected to one node; when that isn't this node, the message goes to the
+//! node the task's RUNNING run is assigned to. Shared by the REST and gRPC
+//! cancel and signal handlers.
+
+use tracing::warn;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_db::DbPool;
+use valka_db::queries::task_runs;
+use valka_dispatcher::DispatcherService;
+use valka_proto::TaskSignal;
+
+/// Tell the worker running `task_id` that the task was cancelled. Whether a
+/// worker was told.
+pub async fn cancel_on_worker(
+    pool: &DbPool,
+    dispatcher: &DispatcherService,
+    cluster: &ClusterManager,
+    forwarder: &NodeForwarder,
+    task_id: &str,
+) -> bool {
+    if dispatcher.cancel_task_on_worker(task_id).await {
+        return true;
+    }
+    let Some(addr) = run_node_addr(pool, cluster, task_id).await else {
+        return false;
+    };
+    match forwarder.forward_cancel(&addr, task_id).await {
+        Ok(notified) => notified,
+        Err(e) => {
+            warn!(task_id, addr = %addr, error = %e, "Failed to forward cancellation");
+            false
+        }
+    }
+}
+
+/// Deliver `signal` to the worker running its task. Whether it was delivered.
+pub async fn signal_worker(
+    pool: &DbPool,
+    dispatcher: &DispatcherService,
+    cluster: &ClusterManager,
+    forwarder: &NodeForwarder,
+    signal: TaskSignal,
+) -> bool {
+    let task_id = signal.task_id.clone();
+    if dispatcher
+        .send_signal_to_worker(&task_id, signal.clone())
+        .await
+    {
+        return true;
+    }
+    let Some(addr) = run_node_addr(pool, cluster, &task_id).await else {
+        return false;
+    };
+    match forwarder.forward_signal(&addr, signal).await {
+        Ok(delivered) => delivered,
+        Err(e) => {
+            warn!(task_id = %task_id, addr = %addr, error = %e, "Failed to forward signal");
+            false
+        }
+    }
+}
+
+/// gRPC address of the node the task's RUNNING run is assigned to, when
+/// that is another node.
+async fn run_node_addr(pool: &DbPool, cluster: &ClusterManager, task_id: &str) -> Option<String> {
+    if !cluster.is_clustered() {
+        return None;
+    }
+    let run = match task_runs::get_running_run(pool, task_id).await {
+        Ok(run) => run?,
+        Err(e) => {
+            warn!(task_id, error = %e, "Failed to look up the task's running run");
+            return None;
+        }
+    };
+    if run.assigned_node_id == cluster.node_id().0 {
+        return None;
+    }
+    cluster.get_node_grpc_addr(&run.assigned_node_id).await
+}
@@ -2,32 +2,263 @@ use sqlx::PgPool;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::{broadcast, mpsc, watch};
-use tokio::time::{Duration, interval};
-use tracing::{error, info};
-use valka_cluster::ClusterManager;
-use valka_core::{LogIngesterConfig, MatchingConfig, PartitionId, SchedulerConfig};
-use valka_db::queries::task_logs::{InsertLogEntry, batch_insert_logs};
+use tokio::time::{Duration, Instant, MissedTickBehavior, interval};
+use tracing::{error, info, warn};
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{
+    LogIngesterConfig, MatchingConfig, PartitionId, RetentionConfig, SchedulerConfig,
+};
+use valka_db::queries::task_logs::{
+    InsertLogEntry, TaskLogRow, batch_insert_logs, batch_insert_logs_returning,
+};
+use valka_db::queries::tasks::TaskRow;
+use valka_db::queries::worker_logs::{InsertWorkerLogEntry, batch_insert_worker_logs};
+use valka_dispatcher::{DispatcherService, ShutdownPhase};
 use valka_matching::MatchingService;
+use valka_matching::partition::TaskEnvelope;
 use valka_matching::task_reader::TaskReader;
+use valka_proto::{ForwardTaskRequest, WORKER_LOG_RUN_PREFIX};
 
-/// Run the scheduler loop (leader election + periodic tasks)
+/// How often the pending task and matching partition gauges are refreshed.
+const PENDING_METRICS_INTERVAL: Duration = Duration::from_secs(5);
+/// Pause before re-subscribing to new queue notifications after a failure.
+const QUEUE_LISTENER_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// How long a TaskReader for a lost partition gets to finish its poll before
+/// its buffer is handed back anyway.
+const READER_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Share of the shutdown budget kept back for the listeners to close.
+const LISTENER_CLOSE_GRACE: Duration = Duration::from_secs(5);
+
+/// Cluster key under which a node publishes a queue's paused flag.
+const QUEUE_PAUSED_KEY_PREFIX: &str = "paused:";
+
+/// The cluster key carrying `queue_name`'s paused flag.
+pub fn queue_paused_key(queue_name: &str) -> String {
+    format!("{QUEUE_PAUSED_KEY_PREFIX}{queue_name}")
+}
+
+/// Apply queue pauses published by other nodes as soon as gossip delivers
+/// them, rather than waiting for the next policy refresh. The database stays
+/// the source of truth: the refresh still overwrites whatever arrives here.
+pub async fn run_cluster_key_watcher(
+    cluster: Arc<ClusterManager>,
+    dispatcher: DispatcherService,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut events = cluster.subscribe_events();
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() {
+                    return;
+                }
+            }
+            event = events.recv() => match event {
+                Ok(valka_cluster::ClusterEvent::KeyChanged { node_id, key, value }) => {
+                    let Some(queue_name) = key.strip_prefix(QUEUE_PAUSED_KEY_PREFIX) else {
+                        continue;
+                    };
+                    let paused = value == "true";
+                    info!(node_id = %node_id, queue_name, paused, "Queue pause received from cluster");
+                    dispatcher.queue_policies().set_paused(queue_name, paused);
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!(n, "Cluster key watcher lagged, relying on the policy refresh");
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        }
+    }
+}
+
+/// Shut this node down in order before `deadline`: refuse new work (which
+/// also fails readiness), stop the TaskReaders, warn local workers, let them
+/// finish and tell them to go, write their last results, then close the
+/// listeners and remaining loops.
+pub async fn run_shutdown_sequence(
+    dispatcher: &DispatcherService,
+    readers: &watch::Sender<bool>,
+    listeners: &watch::Sender<bool>,
+    deadline: Instant,
+) {
+    let phase = dispatcher.shutdown();
+    phase.set_deadline(deadline);
+    phase.advance(ShutdownPhase::Quiescing);
+    info!("Quiescing: refusing new work");
+
+    let _ = readers.send(true);
+    phase.advance(ShutdownPhase::Draining);
+    let drain_budget = deadline
+        .saturating_duration_since(Instant::now())
+        .saturating_sub(LISTENER_CLOSE_GRACE);
+    let told = dispatcher.announce_shutdown("Server shutting down", drain_budget);
+    info!(
+        workers = told,
+        budget_ms = drain_budget.as_millis() as u64,
+        "Draining workers"
+    );
+    let drained =
+        tokio::time::timeout_at(deadline, dispatcher.drain_all_workers(drain_budget)).await;
+    if drained.is_err() {
+        warn!("Worker drain did not finish before the shutdown deadline");
+    }
+    match tokio::time::timeout_at(deadline, dispatcher.spill_buffered()).await {
+        Ok(0) => {}
+        Ok(spilled) => info!(tasks = spilled, "Spilled buffered tasks back to PENDING"),
+        Err(_) => warn!("Buffered tasks were still being spilled at the shutdown deadline"),
+    }
+    if tokio::time::timeout_at(deadline, dispatcher.flush_results())
+        .await
+        .is_err()
+    {
+        warn!("Task results were still being written at the shutdown deadline");
+    }
+
+    phase.advance(ShutdownPhase::Stopped);
+    let _ = listeners.send(true);
+}
+
+/// Ticker for a scheduler job. The first tick is immediate, and ticks missed
+/// while a pass ran long are skipped rather than fired in a burst.
+fn job_interval(secs: u64) -> tokio::time::Interval {
+    let mut ticker = interval(Duration::from_secs(secs.max(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    ticker
+}
+
+/// The scheduler's job tickers.
+struct SchedulerJobs {
+    reaper_interval: tokio::time::Interval,
+    retry_interval: tokio::time::Interval,
+    dlq_interval: tokio::time::Interval,
+    delayed_interval: tokio::time::Interval,
+    // 0 disables the rollup; the interval still needs a non-zero period
+    usage_interval: tokio::time::Interval,
+    schedule_interval: tokio::time::Interval,
+    retention_interval: tokio::time::Interval,
+}
+
+impl SchedulerJobs {
+    fn new(config: &SchedulerConfig, retention: &RetentionConfig) -> Self {
+        Self {
+            reaper_interval: job_interval(config.reaper_interval_secs),
+            retry_interval: job_interval(config.retry_check_interval_secs),
+            dlq_interval: job_interval(config.dlq_check_interval_secs),
+            delayed_interval: job_interval(config.delayed_check_interval_secs),
+            usage_interval: job_interval(config.usage_rollup_interval_secs),
+            schedule_interval: job_interval(config.schedule_check_interval_secs),
+            retention_interval: job_interval(retention.interval_secs),
+        }
+    }
+
+    /// Replace the tickers whose period changed. Unlike at election, a
+    /// replaced job waits out a full new period before it next runs.
+    fn reschedule(&mut self, config: &SchedulerConfig, retention: &RetentionConfig) {
+        let next = Self::new(config, retention);
+        for (ticker, mut next) in [
+            (&mut self.reaper_interval, next.reaper_interval),
+            (&mut self.retry_interval, next.retry_interval),
+            (&mut self.dlq_interval, next.dlq_interval),
+            (&mut self.delayed_interval, next.delayed_interval),
+            (&mut self.usage_interval, next.usage_interval),
+            (&mut self.schedule_interval, next.schedule_interval),
+            (&mut self.retention_interval, next.retention_interval),
+        ] {
+            if ticker.period() != next.period() {
+                next.reset();
+                *ticker = next;
+            }
+        }
+    }
+}
+
+/// Offer tasks the scheduler just made PENDING to matching, on this node when
+/// it owns the partition and through the owner otherwise (one `ForwardTasks`
+/// call per owner), so they don't wait for the next TaskReader poll. A task
+/// nobody takes stays PENDING for the readers.
+async fn offer_promoted(
+    dispatcher: &DispatcherService,
+    cluster: &ClusterManager,
+    forwarder: &NodeForwarder,
+    promoted: Vec<TaskRow>,
+) {
+    let mut forwards: HashMap<String, Vec<ForwardTaskRequest>> = HashMap::new();
+    for task in promoted {
+        let partition = PartitionId(task.partition_id);
+        if !cluster.owns_partition(&task.queue_name, partition.0).await
+            && let Some(owner_addr) = cluster
+                .get_partition_owner_addr(&task.queue_name, partition.0)
+                .await
+        {
+            valka_core::metrics::record_task_forwarded(&task.queue_name);
+            forwards
+                .entry(owner_addr)
+                .or_default()
+                .push(ForwardTaskRequest {
+                    task_id: task.id,
+                    queue_name: task.queue_name,
+                    partition_id: partition.0,
+                });
+            continue;
+        }
+
+        let envelope = TaskEnvelope {
+            task_id: task.id.clone(),
+            task_run_id: String::new(),
+            queue_name: task.queue_name.clone(),
+            task_name: task.task_name.clone(),
+            input: task.input.as_ref().map(|v| v.to_string()),
+            attempt_number: task.attempt_count + 1,
+            timeout_seconds: task.timeout_seconds,
+            metadata: task.metadata.to_string(),
+            priority: task.priority,
+            deadline_ms: task.deadline_at.map(|d| d.timestamp_millis()),
+            ready_at_ms: Some(task.ready_at().timestamp_millis()),
+            created_at_ms: Some(task.created_at.timestamp_millis()),
+            scheduled_at_ms: task.scheduled_at.map(|at| at.timestamp_millis()),
+            traceparent: task.traceparent.clone(),
+            required_labels: task.required_labels(),
+            fifo_key: task.fifo_key,
+            ephemeral: None,
+        };
+        dispatcher.offer_new_task(partition, envelope).await;
+    }
+
+    for (owner_addr, tasks) in forwards {
+        if let Err(e) = forwarder.forward_tasks(&owner_addr, tasks).await {
+            warn!(addr = %owner_addr, error = %e, "Failed to forward promoted tasks");
+        }
+    }
+}
+
+/// Run the scheduler loop (leader election + periodic tasks). Job intervals
+/// and retention settings follow the values on `config_rx` and
+/// `retention_rx`.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_scheduler(
     pool: PgPool,
-    config: SchedulerConfig,
+    mut config_rx: watch::Receiver<SchedulerConfig>,
+    mut retention_rx: watch::Receiver<RetentionConfig>,
+    num_partitions: i32,
+    dispatcher: DispatcherService,
+    cluster: Arc<ClusterManager>,
+    forwarder: NodeForwarder,
     mut shutdown: watch::Receiver<bool>,
 ) {
     let mut election = valka_scheduler::SchedulerElection::new(pool.clone());
-    let mut reaper_interval = interval(Duration::from_secs(config.reaper_interval_secs));
-    let mut retry_interval = interval(Duration::from_secs(config.reaper_interval_secs));
-    let mut dlq_interval = interval(Duration::from_secs(config.dlq_check_interval_secs));
-    let mut delayed_interval = interval(Duration::from_secs(config.delayed_check_interval_secs));
 
     info!("Scheduler started");
+    valka_core::metrics::set_scheduler_leader(false);
 
     loop {
         // Try to acquire leadership
         match election.try_acquire().await {
-            Ok(true) => {}
+            Ok(true) => {
+                valka_core::metrics::set_scheduler_leader(true);
+                cluster.set_scheduler_leader(true).await;
+            }
             Ok(false) => {
                 tokio::time::sleep(Duration::from_secs(5)).await;
                 continue;
@@ -39,22 +270,48 @@ pub async fn run_scheduler(
             }
         }
 
+        // Every job runs once right away, so a new leader picks up where the
+        // last one stopped without waiting out a full period
+        let mut config = config_rx.borrow_and_update().clone();
+        let mut retention = retention_rx.borrow_and_update().clone();
+        let mut jobs = SchedulerJobs::new(&config, &retention);
+
         // Leader loop
         loop {
             tokio::select! {
+                Ok(()) = config_rx.changed() => {
+                    config = config_rx.borrow_and_update().clone();
+                    jobs.reschedule(&config, &retention);
+                    info!("Scheduler intervals updated");
+                }
+                Ok(()) = retention_rx.changed() => {
+                    retention = retention_rx.borrow_and_update().clone();
+                    jobs.reschedule(&config, &retention);
+                    info!("Retention settings updated");
+                }
                 _ = shutdown.changed() => {
                     if *shutdown.borrow() {
                         info!("Scheduler shutting down");
                         let _ = election.release().await;
+                        valka_core::metrics::set_scheduler_leader(false);
+                        cluster.set_scheduler_leader(false).await;
                         return;
                     }
                 }
-                _ = reaper_interval.tick() => {
-                    if let Err(e) = valka_scheduler::reaper::reap_expired_leases(&pool).await {
-                        error!(error = %e, "Reaper error");
+                _ = jobs.reaper_interval.tick() => {
+                    match valka_scheduler::reaper::reap_expired_runs(&pool, dispatcher.event_tx())
+                        .await
+                    {
+                        // Workers on other nodes drop the task at their next heartbeats
+                        Ok(reaped) => {
+                            for run in &reaped {
+                                dispatcher.release_task(&run.worker_id, &run.task_id, "reaped");
+                            }
+                        }
+                        Err(e) => error!(error = %e, "Reaper error"),
                     }
                 }
-                _ = retry_interval.tick() => {
+                _ = jobs.retry_interval.tick() => {
                     if let Err(e) = valka_scheduler::retry::process_retries(
                         &pool,
                         config.retry_base_delay_secs,
@@ -63,14 +320,49 @@ pub async fn run_scheduler(
                         error!(error = %e, "Retry processor error");
                     }
                 }
-                _ = dlq_interval.tick() => {
-                    if let Err(e) = valka_scheduler::dlq::process_dead_letters(&pool).await {
-                        error!(error = %e, "DLQ processor error");
+                _ = jobs.dlq_interval.tick() => {
+                    match valka_scheduler::dlq::dead_letter_tasks(&pool, dispatcher.event_tx())
+                        .await
+                    {
+                        Ok(moved) => {
+                            for task in &moved {
+                                if let Some(url) = &task.callback_url {
+                                    dispatcher.webhooks().notify(&task.id, url);
+                                }
+                            }
+                        }
+                        Err(e) => error!(error = %e, "DLQ processor error"),
                     }
                 }
-                _ = delayed_interval.tick() => {
-                    if let Err(e) = valka_scheduler::delayed::promote_delayed_tasks(&pool).await {
-                        error!(error = %e, "Delayed task promoter error");
+                _ = jobs.delayed_interval.tick() => {
+                    match valka_scheduler::delayed::promote_due_tasks(&pool).await {
+                        Ok(promoted) => {
+                            offer_promoted(&dispatcher, &cluster, &forwarder, promoted).await;
+                        }
+                        Err(e) => error!(error = %e, "Delayed task promoter error"),
+                    }
+                    if let Err(e) = valka_scheduler::delayed::resolve_waiting_tasks(&pool).await {
+                        error!(error = %e, "Waiting task resolver error");
+                    }
+                }
+                _ = jobs.usage_interval.tick(), if config.usage_rollup_interval_secs > 0 => {
+                    let usage_lag = chrono::Duration::seconds(config.usage_rollup_lag_secs as i64);
+                    if let Err(e) = valka_scheduler::usage::rollup_usage(&pool, usage_lag).await {
+                        error!(error = %e, "Usage rollup error");
+                    }
+                }
+                _ = jobs.schedule_interval.tick() => {
+                    if let Err(e) = valka_scheduler::cron::fire_due_schedules(
+                        &pool,
+                        num_partitions,
+                        chrono::Utc::now(),
+                    ).await {
+                        error!(error = %e, "Schedule processor error");
+                    }
+                }
+                _ = jobs.retention_interval.tick() => {
+                    if let Err(e) = valka_scheduler::retention::cleanup(&pool, &retention).await {
+                        error!(error = %e, "Retention cleanup error");
                     }
                 }
             }
@@ -78,65 +370,135 @@ pub async fn run_scheduler(
     }
 }
 
-/// Run the log ingester: batch log entries from workers and flush to PG
+/// Run the log ingester: batch log entries from workers and flush to PG,
+/// publishing flushed task logs on `log_stream` while it has subscribers.
+/// Batch size and flush interval follow updates to `config`.
 pub async fn run_log_ingester(
     pool: PgPool,
-    config: LogIngesterConfig,
+    mut config_rx: watch::Receiver<LogIngesterConfig>,
     mut log_rx: mpsc::Receiver<valka_proto::LogEntry>,
+    log_stream: broadcast::Sender<TaskLogRow>,
     mut shutdown: watch::Receiver<bool>,
 ) {
+    let mut config = config_rx.borrow_and_update().clone();
     let mut buffer: Vec<InsertLogEntry> = Vec::with_capacity(config.batch_size);
+    let mut worker_buffer: Vec<InsertWorkerLogEntry> = Vec::new();
     let mut flush_interval = interval(Duration::from_millis(config.flush_interval_ms));
 
     info!("Log ingester started");
 
     loop {
         tokio::select! {
+            Ok(()) = config_rx.changed() => {
+                let previous = std::mem::replace(&mut config, config_rx.borrow_and_update().clone());
+                if config.flush_interval_ms != previous.flush_interval_ms {
+                    flush_interval = interval(Duration::from_millis(config.flush_interval_ms));
+                    flush_interval.reset();
+                }
+                info!(
+                    batch_size = config.batch_size,
+                    flush_interval_ms = config.flush_interval_ms,
+                    "Log ingester settings updated"
+                );
+            }
             _ = shutdown.changed() => {
                 if *shutdown.borrow() {
                     // Flush remaining
                     if !buffer.is_empty() {
-                        let _ = flush_logs(&pool, &mut buffer).await;
+                        let _ = flush_logs(&pool, &mut buffer, &log_stream).await;
+                    }
+                    if !worker_buffer.is_empty() {
+                        let _ = flush_worker_logs(&pool, &mut worker_buffer).await;
                     }
                     info!("Log ingester shutting down");
                     return;
                 }
             }
             Some(entry) = log_rx.recv() => {
+                let metadata = if entry.metadata.is_empty() {
+                    None
+                } else {
+                    serde_json::from_str(&entry.metadata).ok()
+                };
+
+                // Worker-level logs go to their own table
+                if let Some(worker_id) = entry.task_run_id.strip_prefix(WORKER_LOG_RUN_PREFIX) {
+                    worker_buffer.push(InsertWorkerLogEntry {
+                        worker_id: worker_id.to_string(),
+                        timestamp_ms: entry.timestamp_ms,
+                        level: log_level_to_string(entry.level),
+                        message: entry.message,
+                        metadata,
+                    });
+                    if worker_buffer.len() >= config.batch_size {
+                        let _ = flush_worker_logs(&pool, &mut worker_buffer).await;
+                    }
+                    continue;
+                }
+
                 buffer.push(InsertLogEntry {
                     task_run_id: entry.task_run_id,
                     timestamp_ms: entry.timestamp_ms,
                     level: log_level_to_string(entry.level),
                     message: entry.message,
-                    metadata: if entry.metadata.is_empty() {
-                        None
-                    } else {
-                        serde_json::from_str(&entry.metadata).ok()
-                    },
+                    metadata,
                 });
 
                 if buffer.len() >= config.batch_size {
-                    let _ = flush_logs(&pool, &mut buffer).await;
+                    let _ = flush_logs(&pool, &mut buffer, &log_stream).await;
                 }
             }
             _ = flush_interval.tick() => {
                 if !buffer.is_empty() {
-                    let _ = flush_logs(&pool, &mut buffer).await;
+                    let _ = flush_logs(&pool, &mut buffer, &log_stream).await;
+                }
+                if !worker_buffer.is_empty() {
+                    let _ = flush_worker_logs(&pool, &mut worker_buffer).await;
                 }
             }
         }
     }
 }
 
-async fn flush_logs(pool: &PgPool, buffer: &mut Vec<InsertLogEntry>) -> Result<(), sqlx::Error> {
+async fn flush_logs(
+    pool: &PgPool,
+    buffer: &mut Vec<InsertLogEntry>,
+    log_stream: &broadcast::Sender<TaskLogRow>,
+) -> Result<(), sqlx::Error> {
     let entries: Vec<InsertLogEntry> = std::mem::take(buffer);
     let count = entries.len();
-    batch_insert_logs(pool, &entries).await?;
+    let started = Instant::now();
+    if log_stream.receiver_count() > 0 {
+        // Never blocks: a subscriber that falls behind lags and re-reads from PG
+        for row in batch_insert_logs_returning(pool, &entries).await? {
+            let _ = log_stream.send(row);
+        }
+    } else {
+        batch_insert_logs(pool, &entries).await?;
+    }
+    valka_core::metrics::record_log_flush("task", count, started.elapsed().as_secs_f64() * 1000.0);
     tracing::debug!(count, "Flushed log entries to PG");
     Ok(())
 }
 
-fn log_level_to_string(level: i32) -> String {
+async fn flush_worker_logs(
+    pool: &PgPool,
+    buffer: &mut Vec<InsertWorkerLogEntry>,
+) -> Result<(), sqlx::Error> {
+    let entries: Vec<InsertWorkerLogEntry> = std::mem::take(buffer);
+    let count = entries.len();
+    let started = Instant::now();
+    batch_insert_worker_logs(pool, &entries).await?;
+    valka_core::metrics::record_log_flush(
+        "worker",
+        count,
+        started.elapsed().as_secs_f64() * 1000.0,
+    );
+    tracing::debug!(count, "Flushed worker log entries to PG");
+    Ok(())
+}
+
+pub(crate) fn log_level_to_string(level: i32) -> String {
     match level {
         1 => "DEBUG".to_string(),
         2 => "INFO".to_string(),
@@ -146,21 +508,62 @@ fn log_level_to_string(level: i32) -> String {
     }
 }
 
-/// Discover queues from PG and start TaskReaders for owned partitions.
-/// When cluster membership changes (PartitionsRebalanced), reconciles readers:
-/// stops readers for partitions we no longer own, starts readers for newly owned ones.
+/// A running TaskReader, by the manager that started it.
+struct ReaderHandle {
+    shutdown: watch::Sender<bool>,
+    /// True while the reader is a warm standby for another node's partition
+    standby: watch::Sender<bool>,
+}
+
+/// (queue_name, partition_id) -> the reader this node runs for it
+type Readers = HashMap<(String, i32), ReaderHandle>;
+
+/// Why this node runs a reader for a partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReaderRole {
+    Owner,
+    /// Next in line on the ring: the reader is started but held, ready to
+    /// take over as soon as the owner leaves
+    Standby,
+}
+
+/// This node's role for a partition, if it has one.
+async fn reader_role(cluster: &ClusterManager, queue_name: &str, pid: i32) -> Option<ReaderRole> {
+    let replicas = cluster.partition_replicas(queue_name, pid, 2).await;
+    match replicas
+        .iter()
+        .position(|node| *node == cluster.node_id().0)
+    {
+        Some(0) => Some(ReaderRole::Owner),
+        Some(_) => Some(ReaderRole::Standby),
+        None => None,
+    }
+}
+
+/// Start TaskReaders for owned partitions of every known queue, and held
+/// standby readers for the partitions this node would take over next. New
+/// queues are pushed by the `queues` table's NOTIFY trigger, with a periodic
+/// re-read of the registry as a fallback. When cluster membership changes
+/// (PartitionsRebalanced), reconciles readers: stops readers for partitions
+/// we no longer own, activates standbys for partitions we now own, and starts
+/// readers for the rest.
 pub async fn run_task_reader_manager(
     pool: PgPool,
     matching: MatchingService,
-    config: MatchingConfig,
+    dispatcher: DispatcherService,
+    mut config: watch::Receiver<MatchingConfig>,
     cluster: Arc<ClusterManager>,
     mut shutdown: watch::Receiver<bool>,
 ) {
     let mut known_queues: HashSet<String> = HashSet::new();
-    // (queue_name, partition_id) -> shutdown sender for that reader
-    let mut reader_shutdowns: HashMap<(String, i32), watch::Sender<bool>> = HashMap::new();
-    let mut check_interval = interval(Duration::from_secs(5));
+    let mut readers = Readers::new();
+    let mut fallback_interval = interval(Duration::from_millis(
+        config.borrow().queue_discovery_fallback_ms.max(1),
+    ));
+    let mut metrics_interval = interval(PENDING_METRICS_INTERVAL);
     let mut cluster_events = cluster.subscribe_events();
+    let (discovered_tx, mut discovered_rx) = mpsc::channel::<Option<String>>(256);
+    let listener = tokio::spawn(listen_for_new_queues(pool.clone(), discovered_tx));
 
     info!("TaskReader manager started");
 
@@ -168,14 +571,20 @@ pub async fn run_task_reader_manager(
         tokio::select! {
             _ = shutdown.changed() => {
                 if *shutdown.borrow() {
+                    listener.abort();
                     // Shut down all readers
-                    for (_, tx) in reader_shutdowns.drain() {
-                        let _ = tx.send(true);
+                    for (_, reader) in readers.drain() {
+                        let _ = reader.shutdown.send(true);
                     }
                     info!("TaskReader manager shutting down");
                     return;
                 }
             }
+            // Readers watch the config themselves; buffers are shared
+            Ok(()) = config.changed() => {
+                let max_buffer = config.borrow_and_update().max_buffer_per_partition;
+                matching.set_max_buffer_per_partition(max_buffer);
+            }
             event = cluster_events.recv() => {
                 match event {
                     Ok(valka_cluster::ClusterEvent::PartitionsRebalanced) => {
@@ -183,10 +592,11 @@ pub async fn run_task_reader_manager(
                         reconcile_readers(
                             &pool,
                             &matching,
+                            &dispatcher,
                             &config,
                             &cluster,
                             &known_queues,
-                            &mut reader_shutdowns,
+                            &mut readers,
                         ).await;
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
@@ -194,10 +604,11 @@ pub async fn run_task_reader_manager(
                         reconcile_readers(
                             &pool,
                             &matching,
+                            &dispatcher,
                             &config,
                             &cluster,
                             &known_queues,
-                            &mut reader_shutdowns,
+                            &mut readers,
                         ).await;
                     }
                     Err(broadcast::error::RecvError::Closed) => {
@@ -206,105 +617,268 @@ pub async fn run_task_reader_manager(
                     _ => {}
                 }
             }
-            _ = check_interval.tick() => {
-                // Discover queues from the tasks table
-                match discover_queues(&pool).await {
-                    Ok(queues) => {
-                        let mut new_queues = false;
-                        for queue_name in queues {
-                            if known_queues.contains(&queue_name) {
-                                continue;
-                            }
-                            known_queues.insert(queue_name.clone());
-                            new_queues = true;
-
-                            // Ensure queue partitions exist
-                            matching.ensure_queue(&queue_name);
-
-                            // Start readers only for partitions we own
-                            for pid in 0..config.num_partitions {
-                                let key = (queue_name.clone(), pid);
-                                if reader_shutdowns.contains_key(&key) {
-                                    continue;
-                                }
-                                if !cluster.owns_partition(&queue_name, pid).await {
-                                    continue;
-                                }
-                                start_reader(
-                                    &pool,
-                                    &matching,
-                                    &config,
-                                    &queue_name,
-                                    pid,
-                                    &mut reader_shutdowns,
-                                );
-                            }
-
-                            info!(queue = %queue_name, "Started TaskReaders for owned partitions");
-                        }
-
-                        if !new_queues {
-                            // Even if no new queues, periodically reconcile
-                            // to handle ownership changes without explicit event
-                        }
+            Some(discovered) = discovered_rx.recv() => {
+                match discovered {
+                    Some(queue_name) => {
+                        add_queue(
+                            &pool,
+                            &matching,
+                            &config,
+                            &cluster,
+                            &mut known_queues,
+                            &mut readers,
+                            queue_name,
+                        ).await;
                     }
-                    Err(e) => {
-                        error!(error = %e, "Failed to discover queues");
+                    // (Re)subscribed: notifications may have been missed meanwhile
+                    None => {
+                        discover_queues(
+                            &pool,
+                            &matching,
+                            &config,
+                            &cluster,
+                            &mut known_queues,
+                            &mut readers,
+                        ).await;
                     }
                 }
+            }
+            _ = fallback_interval.tick() => {
+                discover_queues(
+                    &pool,
+                    &matching,
+                    &config,
+                    &cluster,
+                    &mut known_queues,
+                    &mut readers,
+                ).await;
+            }
+            _ = metrics_interval.tick() => {
+                publish_queue_backlog(&pool, &known_queues).await;
+                for p in matching.stats().by_partition {
+                    valka_core::metrics::set_matching_partition(
+                        &p.queue_name,
+                        p.partition_id,
+                        p.buffered_tasks,
+                        p.waiting_workers,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Publish each queue's waiting tasks by state, and the age of its oldest
+/// ready task. Known queues without any report zeros, so their gauges don't
+/// keep a stale backlog.
+pub async fn publish_queue_backlog(pool: &PgPool, known_queues: &HashSet<String>) {
+    let backlog = match valka_db::queries::tasks::queue_backlog(pool).await {
+        Ok(backlog) => backlog,
+        Err(e) => {
+            warn!(error = %e, "Failed to read queue backlog");
+            return;
+        }
+    };
+    let now = chrono::Utc::now();
+    let mut idle: HashSet<&String> = known_queues.iter().collect();
+    for queue in &backlog {
+        idle.remove(&queue.queue_name);
+        valka_core::metrics::set_pending_tasks(&queue.queue_name, "pending", queue.pending as f64);
+        valka_core::metrics::set_pending_tasks(&queue.queue_name, "retry", queue.retry as f64);
+        valka_core::metrics::set_pending_tasks(
+            &queue.queue_name,
+            "scheduled",
+            queue.scheduled as f64,
+        );
+        let age = queue.oldest_ready_at.map_or(0.0, |at| {
+            (now - at).num_milliseconds().max(0) as f64 / 1000.0
+        });
+        valka_core::metrics::set_queue_oldest_pending_age(&queue.queue_name, age);
+    }
+    for queue in idle {
+        for state in ["pending", "retry", "scheduled"] {
+            valka_core::metrics::set_pending_tasks(queue, state, 0.0);
+        }
+        valka_core::metrics::set_queue_oldest_pending_age(queue, 0.0);
+    }
+}
 
-                // Update pending tasks metrics
-                if let Ok(counts) = valka_db::queries::tasks::count_pending_by_queue(&pool).await {
-                    for (queue, count) in counts {
-                        valka_core::metrics::set_pending_tasks(&queue, count as f64);
+/// Forward new queue names from the NOTIFY channel. Sends `None` each time
+/// the subscription is (re)established, since anything created while it was
+/// down was not announced. Reconnects until the manager goes away.
+async fn listen_for_new_queues(pool: PgPool, discovered: mpsc::Sender<Option<String>>) {
+    loop {
+        match valka_db::queries::queues::listen_for_new_queues(&pool).await {
+            Ok(mut listener) => {
+                if discovered.send(None).await.is_err() {
+                    return;
+                }
+                loop {
+                    let next = match listener.try_recv().await {
+                        Ok(Some(notification)) => Some(notification.payload().to_string()),
+                        // Connection lost and re-established
+                        Ok(None) => None,
+                        Err(e) => {
+                            warn!(error = %e, "Queue notification listener failed");
+                            break;
+                        }
+                    };
+                    if discovered.send(next).await.is_err() {
+                        return;
                     }
                 }
             }
+            Err(e) => warn!(error = %e, "Failed to listen for new queues"),
+        }
+        tokio::time::sleep(QUEUE_LISTENER_RETRY_DELAY).await;
+    }
+}
+
+/// Read the queue registry and add any queue not yet known.
+async fn discover_queues(
+    pool: &PgPool,
+    matching: &MatchingService,
+    config: &watch::Receiver<MatchingConfig>,
+    cluster: &Arc<ClusterManager>,
+    known_queues: &mut HashSet<String>,
+    readers: &mut Readers,
+) {
+    match valka_db::queries::queues::list_queue_names(pool).await {
+        Ok(queues) => {
+            for queue_name in queues {
+                add_queue(
+                    pool,
+                    matching,
+                    config,
+                    cluster,
+                    known_queues,
+                    readers,
+                    queue_name,
+                )
+                .await;
+            }
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to discover queues");
         }
     }
 }
 
-/// Reconcile readers: stop readers for partitions we no longer own,
-/// start readers for partitions we now own.
+/// Track `queue_name` and start readers for the partitions of it we own or
+/// stand by for.
+async fn add_queue(
+    pool: &PgPool,
+    matching: &MatchingService,
+    config: &watch::Receiver<MatchingConfig>,
+    cluster: &Arc<ClusterManager>,
+    known_queues: &mut HashSet<String>,
+    readers: &mut Readers,
+    queue_name: String,
+) {
+    if known_queues.contains(&queue_name) {
+        return;
+    }
+
+    // Ensure queue partitions exist
+    matching.ensure_queue(&queue_name);
+
+    // Start readers only for partitions we own or stand by for
+    let num_partitions = config.borrow().num_partitions;
+    for pid in 0..num_partitions {
+        let key = (queue_name.clone(), pid);
+        if readers.contains_key(&key) {
+            continue;
+        }
+        let Some(role) = reader_role(cluster, &queue_name, pid).await else {
+            continue;
+        };
+        start_reader(pool, matching, config, &queue_name, pid, role, readers);
+    }
+
+    info!(queue = %queue_name, "Started TaskReaders for owned partitions");
+    known_queues.insert(queue_name);
+}
+
+/// Reconcile readers: stop readers for partitions we neither own nor stand
+/// by for, activate standbys for partitions we now own, and start readers
+/// for the rest.
 async fn reconcile_readers(
     pool: &PgPool,
     matching: &MatchingService,
-    config: &MatchingConfig,
+    dispatcher: &DispatcherService,
+    config: &watch::Receiver<MatchingConfig>,
     cluster: &Arc<ClusterManager>,
     known_queues: &HashSet<String>,
-    reader_shutdowns: &mut HashMap<(String, i32), watch::Sender<bool>>,
+    readers: &mut Readers,
 ) {
-    // Stop readers for partitions we no longer own
-    let keys_to_check: Vec<(String, i32)> = reader_shutdowns.keys().cloned().collect();
+    // Stop readers for partitions we no longer own. A lost partition we now
+    // stand by for gets a fresh, held reader below.
+    let keys_to_check: Vec<(String, i32)> = readers.keys().cloned().collect();
+    let mut stopped = Vec::new();
     for key in keys_to_check {
-        if !cluster.owns_partition(&key.0, key.1).await
-            && let Some(tx) = reader_shutdowns.remove(&key)
+        let role = reader_role(cluster, &key.0, key.1).await;
+        let was_standby = *readers[&key].standby.borrow();
+        match role {
+            Some(ReaderRole::Owner) if was_standby => {
+                let _ = readers[&key].standby.send(false);
+                info!(
+                    queue = %key.0,
+                    partition = key.1,
+                    "Activated standby TaskReader (partition now owned)"
+                );
+            }
+            Some(ReaderRole::Owner) => {}
+            Some(ReaderRole::Standby) if was_standby => {}
+            Some(ReaderRole::Standby) | None => {
+                if let Some(reader) = readers.remove(&key) {
+                    let _ = reader.shutdown.send(true);
+                    stopped.push((key, reader.shutdown));
+                }
+            }
+        }
+    }
+
+    // Hand their buffered tasks back once the readers can no longer add any,
+    // so the new owner's readers pick them up instead of the reaper
+    for ((queue_name, pid), tx) in stopped {
+        if tokio::time::timeout(READER_STOP_TIMEOUT, tx.closed())
+            .await
+            .is_err()
         {
-            let _ = tx.send(true);
-            info!(
-                queue = %key.0,
-                partition = key.1,
-                "Stopped TaskReader (partition no longer owned)"
-            );
+            warn!(queue = %queue_name, partition = pid, "TaskReader slow to stop");
+        }
+        let drained = matching.drain_partition(&queue_name, PartitionId(pid));
+        let handed_back = drained.len();
+        for task in drained {
+            dispatcher.release_unbuffered(task).await;
         }
+        info!(
+            queue = %queue_name,
+            partition = pid,
+            handed_back,
+            "Stopped TaskReader (partition no longer owned)"
+        );
     }
 
-    // Start readers for partitions we now own but don't have a reader for
+    // Start readers for partitions we own or stand by for but don't have a
+    // reader for
+    let num_partitions = config.borrow().num_partitions;
     for queue_name in known_queues {
         matching.ensure_queue(queue_name);
-        for pid in 0..config.num_partitions {
+        for pid in 0..num_partitions {
             let key = (queue_name.clone(), pid);
-            if reader_shutdowns.contains_key(&key) {
+            if readers.contains_key(&key) {
                 continue;
             }
-            if !cluster.owns_partition(queue_name, pid).await {
+            let Some(role) = reader_role(cluster, queue_name, pid).await else {
                 continue;
-            }
-            start_reader(pool, matching, config, queue_name, pid, reader_shutdowns);
+            };
+            start_reader(pool, matching, config, queue_name, pid, role, readers);
             info!(
                 queue = %queue_name,
                 partition = pid,
-                "Started TaskReader (newly owned partition)"
+                standby = role == ReaderRole::Standby,
+                "Started TaskReader"
             );
         }
     }
@@ -313,27 +887,30 @@ async fn reconcile_readers(
 fn start_reader(
     pool: &PgPool,
     matching: &MatchingService,
-    config: &MatchingConfig,
+    config: &watch::Receiver<MatchingConfig>,
     queue_name: &str,
     partition_id: i32,
-    reader_shutdowns: &mut HashMap<(String, i32), watch::Sender<bool>>,
+    role: ReaderRole,
+    readers: &mut Readers,
 ) {
     let (reader_shutdown_tx, reader_shutdown_rx) = watch::channel(false);
+    let (standby_tx, standby_rx) = watch::channel(role == ReaderRole::Standby);
     let reader = TaskReader::new(
         pool.clone(),
         matching.clone(),
         queue_name.to_string(),
         PartitionId(partition_id),
-        config.clone(),
+        config.borrow().clone(),
         reader_shutdown_rx,
-    );
+    )
+    .with_standby(standby_rx)
+    .with_config_updates(config.clone());
     tokio::spawn(reader.run());
-    reader_shutdowns.insert((queue_name.to_string(), partition_id), reader_shutdown_tx);
-}
-
-async fn discover_queues(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
-    let rows: Vec<(String,)> = sqlx::query_as("SELECT DISTINCT queue_name FROM tasks")
-        .fetch_all(pool)
-        .await?;
-    Ok(rows.into_iter().map(|(name,)| name).collect())
+    readers.insert(
+        (queue_name.to_string(), partition_id),
+        ReaderHandle {
+            shutdown: reader_shutdown_tx,
+            standby: standby_tx,
+        },
+    );
 }
@@ -0,0 +1,145 @@
+//! Self-telemetry: periodically samples internal channel and map sizes into
+//! `valka_internal_*` gauges so a silently filling queue shows up on a dashboard
+//! before it shows up as latency.
+
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::{Duration, MissedTickBehavior, interval};
+use tracing::info;
+
+use valka_cluster::NodeForwarder;
+use valka_cluster::forwarder::ForwarderStats;
+use valka_dispatcher::DispatcherService;
+use valka_dispatcher::service::DispatcherStats;
+use valka_matching::MatchingService;
+use valka_matching::service::MatchingStats;
+
+/// Subsystems to sample. Any of them may be absent; their gauges are then skipped.
+#[derive(Clone, Default)]
+pub struct TelemetrySources {
+    pub event_tx: Option<broadcast::Sender<valka_proto::TaskEvent>>,
+    pub log_tx: Option<mpsc::Sender<valka_proto::LogEntry>>,
+    pub dispatcher: Option<DispatcherService>,
+    pub matching: Option<MatchingService>,
+    pub forwarder: Option<NodeForwarder>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventChannelStats {
+    pub receivers: usize,
+    /// Events still queued for the slowest receiver (approximate lag)
+    pub queued: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChannelDepth {
+    pub depth: usize,
+    pub capacity: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RuntimeSnapshot {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+}
+
+/// One sample of every available subsystem.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InternalSnapshot {
+    pub event_channel: Option<EventChannelStats>,
+    pub log_channel: Option<ChannelDepth>,
+    pub dispatcher: Option<DispatcherStats>,
+    pub matching: Option<MatchingStats>,
+    pub forwarder: Option<ForwarderStats>,
+    pub runtime: Option<RuntimeSnapshot>,
+}
+
+impl TelemetrySources {
+    pub async fn sample(&self) -> InternalSnapshot {
+        InternalSnapshot {
+            event_channel: self.event_tx.as_ref().map(|tx| EventChannelStats {
+                receivers: tx.receiver_count(),
+                queued: tx.len(),
+            }),
+            log_channel: self.log_tx.as_ref().map(|tx| ChannelDepth {
+                depth: tx.max_capacity() - tx.capacity(),
+                capacity: tx.max_capacity(),
+            }),
+            dispatcher: self.dispatcher.as_ref().map(DispatcherService::stats),
+            matching: self.matching.as_ref().map(MatchingService::stats),
+            forwarder: match &self.forwarder {
+                Some(f) => Some(f.stats().await),
+                None => None,
+            },
+            runtime: tokio::runtime::Handle::try_current().ok().map(|handle| {
+                let metrics = handle.metrics();
+                RuntimeSnapshot {
+                    workers: metrics.num_workers(),
+                    alive_tasks: metrics.num_alive_tasks(),
+                    global_queue_depth: metrics.global_queue_depth(),
+                }
+            }),
+        }
+    }
+}
+
+impl InternalSnapshot {
+    /// Publish the snapshot as `valka_internal_*` gauges.
+    pub fn export(&self) {
+        use valka_core::metrics::set_internal_gauge as set;
+
+        if let Some(e) = &self.event_channel {
+            set("event_channel_receivers", e.receivers as f64);
+            set("event_channel_queued", e.queued as f64);
+        }
+        if let Some(l) = &self.log_channel {
+            set("log_channel_depth", l.depth as f64);
+            set("log_channel_capacity", l.capacity as f64);
+        }
+        if let Some(d) = &self.dispatcher {
+            set("dispatcher_workers", d.workers as f64);
+            set("dispatcher_active_tasks", d.active_tasks as f64);
+            set("dispatcher_queued_responses", d.queued_responses as f64);
+        }
+        if let Some(m) = &self.matching {
+            set("matching_partitions", m.partitions as f64);
+            set("matching_buffered_tasks", m.buffered_tasks as f64);
+            set("matching_waiting_workers", m.waiting_workers as f64);
+        }
+        if let Some(f) = &self.forwarder {
+            set("forwarder_cached_channels", f.cached_channels as f64);
+            set("forwarder_open_circuits", f.open_circuits as f64);
+        }
+        if let Some(r) = &self.runtime {
+            set("runtime_workers", r.workers as f64);
+            set("runtime_alive_tasks", r.alive_tasks as f64);
+            set("runtime_global_queue_depth", r.global_queue_depth as f64);
+        }
+    }
+}
+
+/// Sample and export internal gauges every `interval_ms` until shutdown.
+pub async fn run_self_telemetry(
+    sources: TelemetrySources,
+    interval_ms: u64,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut ticker = interval(Duration::from_millis(interval_ms.max(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(interval_ms, "Self-telemetry started");
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                if *shutdown.borrow() {
+                    return;
+                }
+            }
+            _ = ticker.tick() => {
+                sources.sample().await.export();
+            }
+        }
+    }
+}
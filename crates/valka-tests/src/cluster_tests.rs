@@ -1,8 +1,10 @@
 use std::time::Duration;
 
+use tonic::service::Interceptor;
+
 use valka_cluster::forwarder::CircuitState;
 use valka_cluster::ring::HashRing;
-use valka_cluster::{ClusterEvent, ClusterManager, NodeForwarder};
+use valka_cluster::{ClusterAuth, ClusterEvent, ClusterManager, NodeForwarder};
 use valka_core::{GossipConfig, NodeId};
 
 #[test]
@@ -48,6 +50,33 @@ fn test_two_nodes_distribution() {
     assert!(count_2 > 0, "Node 2 should have some keys, got {count_2}");
 }
 
+#[test]
+fn test_more_vnodes_spread_keys_more_evenly() {
+    // Variance of the per-node key counts across four nodes
+    let variance = |vnodes: usize| {
+        let mut ring = HashRing::new_with_vnodes(vnodes);
+        let nodes = ["node-1", "node-2", "node-3", "node-4"];
+        for node in nodes {
+            ring.add_node(node);
+        }
+        let mut counts = [0f64; 4];
+        for i in 0..4096 {
+            let owner = ring.get_node(&format!("task-{i}")).unwrap();
+            let idx = nodes.iter().position(|n| *n == owner).unwrap();
+            counts[idx] += 1.0;
+        }
+        let mean = 4096.0 / 4.0;
+        counts.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / 4.0
+    };
+
+    let (one, sixteen, many) = (variance(1), variance(16), variance(256));
+    assert!(sixteen < one, "16 vnodes ({sixteen}) should beat 1 ({one})");
+    assert!(
+        many < sixteen,
+        "256 vnodes ({many}) should beat 16 ({sixteen})"
+    );
+}
+
 #[test]
 fn test_add_remove_node() {
     let mut ring = HashRing::new();
@@ -163,8 +192,55 @@ fn test_default_creates_empty_ring() {
     assert!(ring.get_node("key").is_none());
 }
 
+#[test]
+fn test_ring_replicas_distinct_and_rotate_on_removal() {
+    let mut ring = HashRing::new();
+    for node in ["node-a", "node-b", "node-c"] {
+        ring.add_node(node);
+    }
+    assert!(HashRing::new().get_nodes("work:0", 2).is_empty());
+    assert!(ring.get_nodes("work:0", 0).is_empty());
+    // Asking for more than there are gives every node once
+    assert_eq!(ring.get_nodes("work:0", 5).len(), 3);
+
+    let before: Vec<Vec<String>> = (0..32)
+        .map(|pid| ring.get_nodes(&format!("work:{pid}"), 3))
+        .collect();
+    for (pid, replicas) in before.iter().enumerate() {
+        let distinct: std::collections::HashSet<_> = replicas.iter().collect();
+        assert_eq!(distinct.len(), 3, "partition {pid}: {replicas:?}");
+        assert_eq!(
+            ring.get_node(&format!("work:{pid}")).as_ref(),
+            Some(&replicas[0]),
+            "partition {pid}: the owner comes first"
+        );
+    }
+
+    // Everyone behind the removed node moves up one place
+    ring.remove_node("node-b");
+    for (pid, replicas) in before.iter().enumerate() {
+        let expected: Vec<String> = replicas
+            .iter()
+            .filter(|node| *node != "node-b")
+            .cloned()
+            .collect();
+        assert_eq!(
+            ring.get_nodes(&format!("work:{pid}"), 2),
+            expected,
+            "partition {pid}"
+        );
+    }
+}
+
 // --- ClusterManager tests ---
 
+#[tokio::test]
+async fn test_single_node_is_its_only_replica() {
+    let cluster = ClusterManager::new_single_node(NodeId("solo".to_string()), 4);
+    assert_eq!(cluster.partition_replicas("q", 0, 2).await, ["solo"]);
+    assert!(cluster.partition_replicas("q", 0, 0).await.is_empty());
+}
+
 #[tokio::test]
 async fn test_single_node_owns_all_partitions() {
     let node_id = NodeId("test-node-1".to_string());
@@ -220,6 +296,32 @@ async fn test_single_node_not_clustered() {
     assert!(!cluster.is_clustered());
 }
 
+#[tokio::test]
+async fn test_scheduler_leadership_changes_are_announced() {
+    let node_id = NodeId("test-node-1".to_string());
+    let cluster = ClusterManager::new_single_node(node_id, 4);
+    let mut rx = cluster.subscribe_events();
+    assert!(cluster.leader_since().await.is_none());
+
+    cluster.set_scheduler_leader(true).await;
+    let since = cluster.leader_since().await.expect("leader");
+    // Re-asserting leadership keeps the original timestamp and stays quiet
+    cluster.set_scheduler_leader(true).await;
+    assert_eq!(cluster.leader_since().await, Some(since));
+
+    cluster.set_scheduler_leader(false).await;
+    assert!(cluster.leader_since().await.is_none());
+
+    let mut changes = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        if let ClusterEvent::LeadershipChanged { node_id, is_leader } = event {
+            assert_eq!(node_id.0, "test-node-1");
+            changes.push(is_leader);
+        }
+    }
+    assert_eq!(changes, vec![true, false]);
+}
+
 #[test]
 fn test_two_node_ring_partition_split() {
     // Verify that with 2 nodes in the ring, partitions distribute across both
@@ -283,6 +385,23 @@ fn test_ring_partition_ownership_changes_on_node_add() {
     );
 }
 
+#[tokio::test]
+async fn test_single_node_cluster_keys() {
+    let cluster = ClusterManager::new_single_node(NodeId("solo".to_string()), 4);
+    let mut watch = cluster.watch_key("draining");
+
+    cluster.set_key("draining", "1").await;
+
+    assert_eq!(
+        cluster.get_key("solo", "draining").await.as_deref(),
+        Some("1")
+    );
+    assert_eq!(cluster.get_key("other", "draining").await, None);
+    let (node_id, value) = watch.changed().await.unwrap();
+    assert_eq!(node_id.0, "solo");
+    assert_eq!(value, "1");
+}
+
 // --- Circuit Breaker tests ---
 
 #[tokio::test]
@@ -346,6 +465,39 @@ async fn test_circuit_breaker_resets_on_success() {
     );
 }
 
+#[tokio::test]
+async fn test_circuit_breaker_failed_probe_reopens() {
+    let forwarder = NodeForwarder::new().with_recovery_timeout(Duration::from_millis(200));
+    let addr = "127.0.0.1:99997";
+
+    for _ in 0..2 {
+        let _ = forwarder.forward_task(addr, "task-1", "queue-1", 0).await;
+    }
+    assert_eq!(forwarder.get_circuit_state(addr).await, CircuitState::Open);
+
+    // After the cool-down one probe goes out, and its failure reopens the circuit
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    let probe = forwarder.forward_task(addr, "task-2", "queue-1", 0).await;
+    assert!(
+        !probe
+            .unwrap_err()
+            .to_string()
+            .contains("Circuit breaker open"),
+        "The probe should reach the node"
+    );
+    assert_eq!(forwarder.get_circuit_state(addr).await, CircuitState::Open);
+    assert_eq!(forwarder.stats().await.open_circuits, 1);
+
+    // The cool-down starts over
+    let result = forwarder.forward_task(addr, "task-3", "queue-1", 0).await;
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("Circuit breaker open")
+    );
+}
+
 #[tokio::test]
 async fn test_circuit_breaker_default_state() {
     let forwarder = NodeForwarder::new();
@@ -392,6 +544,8 @@ fn gossip_config(listen_port: u16, seeds: Vec<u16>, cluster_id: &str) -> GossipC
             .collect(),
         cluster_id: cluster_id.to_string(),
         advertise_addr: None,
+        secret: None,
+        ..GossipConfig::default()
     }
 }
 
@@ -622,7 +776,9 @@ async fn test_cluster_events_emitted_on_node_join() {
                     Ok(ClusterEvent::PartitionsRebalanced) => {
                         saw_rebalance = true;
                     }
-                    Ok(ClusterEvent::NodeLeft { .. }) => {}
+                    Ok(ClusterEvent::NodeLeft { .. })
+                    | Ok(ClusterEvent::LeadershipChanged { .. })
+                    | Ok(ClusterEvent::KeyChanged { .. }) => {}
                     Err(_) => break,
                 }
                 if saw_join && saw_rebalance {
@@ -643,11 +799,134 @@ async fn test_cluster_events_emitted_on_node_join() {
     node_b.shutdown().await;
 }
 
-// Note: Testing gossip-level node leave detection (failure detector) is omitted because
-// chitchat's phi-accrual detector takes 30-60+ seconds to declare a node dead with
-// default thresholds, making it too slow for unit tests. The ring-level reclamation
-// is already tested by test_add_remove_node. If needed, this can be tested with
-// chitchat's test utilities or with a custom failure_detector_config.
+#[tokio::test]
+async fn test_cluster_key_propagates_to_other_node() {
+    let config_a = gossip_config(17771, vec![17772], "test-kv");
+    let node_a =
+        ClusterManager::new_clustered(NodeId("kv-a".to_string()), 4, &config_a, "127.0.0.1:50171")
+            .await
+            .unwrap();
+    let config_b = gossip_config(17772, vec![17771], "test-kv");
+    let node_b =
+        ClusterManager::new_clustered(NodeId("kv-b".to_string()), 4, &config_b, "127.0.0.1:50172")
+            .await
+            .unwrap();
+    wait_for_members(&node_b, 2, 10).await;
+
+    let mut watch = node_b.watch_key("paused:emails");
+    node_a.set_key("paused:emails", "true").await;
+    assert_eq!(
+        node_a.get_key("kv-a", "paused:emails").await.as_deref(),
+        Some("true")
+    );
+
+    let (node_id, value) = tokio::time::timeout(Duration::from_secs(5), watch.changed())
+        .await
+        .expect("key change should reach node B")
+        .unwrap();
+    assert_eq!(node_id.0, "kv-a");
+    assert_eq!(value, "true");
+    assert_eq!(
+        node_b.get_key("kv-a", "paused:emails").await.as_deref(),
+        Some("true")
+    );
+    // Keys are per node
+    assert_eq!(node_b.get_key("kv-b", "paused:emails").await, None);
+
+    node_a.shutdown().await;
+    node_b.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_graceful_leave_rebalances_survivor() {
+    let config_a = gossip_config(17781, vec![17782], "test-leave");
+    let node_a = ClusterManager::new_clustered(
+        NodeId("leave-a".to_string()),
+        8,
+        &config_a,
+        "127.0.0.1:50181",
+    )
+    .await
+    .unwrap();
+    let config_b = gossip_config(17782, vec![17781], "test-leave");
+    let node_b = ClusterManager::new_clustered(
+        NodeId("leave-b".to_string()),
+        8,
+        &config_b,
+        "127.0.0.1:50182",
+    )
+    .await
+    .unwrap();
+    wait_for_members(&node_b, 2, 10).await;
+    let mut events = node_b.subscribe_events();
+
+    node_a.leave().await;
+    node_a.shutdown().await;
+
+    // Well inside the failure detector's 30s+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    loop {
+        let mut owns_all = true;
+        for pid in 0..8 {
+            owns_all &= node_b.owns_partition("leave-queue", pid).await;
+        }
+        if owns_all {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "survivor did not take every partition over"
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(node_b.members().await.len(), 1);
+
+    let mut saw_left = false;
+    while let Ok(event) = events.try_recv() {
+        saw_left |= matches!(event, ClusterEvent::NodeLeft { node_id } if node_id.0 == "leave-a");
+    }
+    assert!(saw_left, "NodeLeft should be emitted for the leaving node");
+
+    node_b.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_aggressive_detector_drops_dead_node_quickly() {
+    // With the defaults a silent node takes 30-60+ seconds to be declared dead
+    let fast = |listen_port: u16, seed: u16| GossipConfig {
+        gossip_interval_ms: 100,
+        phi_threshold: 2.0,
+        ..gossip_config(listen_port, vec![seed], "test-detector")
+    };
+    let node_a = ClusterManager::new_clustered(
+        NodeId("detect-a".to_string()),
+        4,
+        &fast(17791, 17792),
+        "127.0.0.1:50191",
+    )
+    .await
+    .unwrap();
+    let node_b = ClusterManager::new_clustered(
+        NodeId("detect-b".to_string()),
+        4,
+        &fast(17792, 17791),
+        "127.0.0.1:50192",
+    )
+    .await
+    .unwrap();
+    wait_for_members(&node_b, 2, 10).await;
+    // Let node B collect a few heartbeat intervals
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    // Gone without leaving: only the failure detector can notice
+    node_a.shutdown().await;
+    wait_for_members(&node_b, 1, 10).await;
+    for pid in 0..4 {
+        assert!(node_b.owns_partition("detect-queue", pid).await);
+    }
+
+    node_b.shutdown().await;
+}
 
 #[test]
 fn test_ring_node_leave_reclaims_all_partitions() {
@@ -862,3 +1141,102 @@ fn test_ring_multiple_queues_distribute_independently() {
          but all {same_mapping}/32 matched"
     );
 }
+
+// ─── Cluster secret ─────────────────────────────────────────────────
+
+#[test]
+fn test_cluster_auth_seals_for_same_secret_and_cluster_only() {
+    let sender = ClusterAuth::new(Some("s3cret"), "prod");
+    let mut datagram = sender.seal(b"membership").unwrap();
+    assert!(!datagram.windows(10).any(|w| w == b"membership"));
+
+    let receiver = ClusterAuth::new(Some("s3cret"), "prod");
+    assert_eq!(
+        receiver.open(&mut datagram.clone()),
+        Some(&b"membership"[..])
+    );
+
+    for other in [
+        ClusterAuth::new(Some("wrong"), "prod"),
+        ClusterAuth::new(Some("s3cret"), "staging"),
+    ] {
+        assert_eq!(other.open(&mut datagram.clone()), None);
+    }
+
+    // Any flipped bit fails authentication
+    let last = datagram.len() - 1;
+    datagram[last] ^= 1;
+    assert_eq!(receiver.open(&mut datagram), None);
+    assert_eq!(receiver.open(&mut [0u8; 4]), None);
+}
+
+#[test]
+fn test_cluster_auth_without_secret_passes_through() {
+    for auth in [ClusterAuth::default(), ClusterAuth::new(Some(""), "prod")] {
+        assert!(!auth.is_enabled());
+        assert_eq!(auth.seal(b"plain").unwrap(), b"plain");
+        assert!(auth.allows(&tonic::metadata::MetadataMap::new()));
+    }
+}
+
+#[test]
+fn test_cluster_auth_token_checks() {
+    let mut auth = ClusterAuth::new(Some("s3cret"), "prod");
+    let request = auth.call(tonic::Request::new(())).unwrap();
+    let token = request.metadata().get("x-valka-cluster-token").unwrap();
+    assert!(!token.to_str().unwrap().contains("s3cret"));
+
+    assert!(auth.verify(request).is_ok());
+    let forged = ClusterAuth::new(Some("wrong"), "prod")
+        .call(tonic::Request::new(()))
+        .unwrap();
+    let status = auth.verify(forged).unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    let status = auth.verify(tonic::Request::new(())).unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    assert!(!format!("{auth:?}").contains("s3cret"));
+}
+
+#[tokio::test]
+async fn test_sealed_cluster_excludes_node_with_wrong_secret() {
+    let sealed = |port, seeds, secret: &str| GossipConfig {
+        secret: Some(secret.to_string()),
+        ..gossip_config(port, seeds, "test-sealed")
+    };
+    let node_a = ClusterManager::new_clustered(
+        NodeId("sealed-a".to_string()),
+        4,
+        &sealed(17751, vec![17752], "s3cret"),
+        "127.0.0.1:50151",
+    )
+    .await
+    .unwrap();
+    let node_b = ClusterManager::new_clustered(
+        NodeId("sealed-b".to_string()),
+        4,
+        &sealed(17752, vec![17751], "s3cret"),
+        "127.0.0.1:50152",
+    )
+    .await
+    .unwrap();
+    let rogue = ClusterManager::new_clustered(
+        NodeId("sealed-rogue".to_string()),
+        4,
+        &sealed(17753, vec![17751, 17752], "guess"),
+        "127.0.0.1:50153",
+    )
+    .await
+    .unwrap();
+
+    wait_for_members(&node_a, 2, 10).await;
+    wait_for_members(&node_b, 2, 10).await;
+    // Give the rogue node plenty of gossip rounds to get in
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert!(!node_a.members().await.contains("sealed-rogue"));
+    assert!(!node_b.members().await.contains("sealed-rogue"));
+    assert_eq!(rogue.members().await.len(), 1);
+
+    node_a.shutdown().await;
+    node_b.shutdown().await;
+    rogue.shutdown().await;
+}
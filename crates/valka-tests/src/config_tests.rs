@@ -1,4 +1,8 @@
-use valka_core::{GossipConfig, LogIngesterConfig, MatchingConfig, SchedulerConfig, ServerConfig};
+use valka_core::{
+    AuthConfig, BlobStorageConfig, EventsConfig, GossipConfig, LimitsConfig, LogFormat,
+    LogIngesterConfig, MatchingConfig, ResultWriterConfig, RetentionConfig, SchedulerConfig,
+    ServerConfig,
+};
 
 #[test]
 fn test_matching_config_defaults() {
@@ -7,6 +11,7 @@ fn test_matching_config_defaults() {
     assert_eq!(config.branching_factor, 3);
     assert_eq!(config.max_buffer_per_partition, 1000);
     assert_eq!(config.task_reader_batch_size, 50);
+    assert_eq!(config.task_reader_max_batch_size, 500);
     assert_eq!(config.task_reader_poll_busy_ms, 10);
     assert_eq!(config.task_reader_poll_idle_ms, 200);
 }
@@ -18,8 +23,12 @@ fn test_scheduler_config_defaults() {
     assert_eq!(config.lease_timeout_secs, 60);
     assert_eq!(config.retry_base_delay_secs, 1);
     assert_eq!(config.retry_max_delay_secs, 3600);
+    assert_eq!(config.retry_check_interval_secs, 10);
     assert_eq!(config.dlq_check_interval_secs, 30);
     assert_eq!(config.delayed_check_interval_secs, 5);
+    assert_eq!(config.usage_rollup_interval_secs, 60);
+    assert_eq!(config.usage_rollup_lag_secs, 30);
+    assert_eq!(config.schedule_check_interval_secs, 1);
 }
 
 #[test]
@@ -27,6 +36,44 @@ fn test_log_ingester_config_defaults() {
     let config = LogIngesterConfig::default();
     assert_eq!(config.batch_size, 100);
     assert_eq!(config.flush_interval_ms, 500);
+    assert_eq!(config.stream_buffer_size, 1024);
+}
+
+#[test]
+fn test_result_writer_config_defaults() {
+    let config = ResultWriterConfig::default();
+    assert_eq!(config.batch_size, 200);
+    assert_eq!(config.flush_interval_ms, 5);
+}
+
+#[test]
+fn test_events_config_defaults() {
+    let config = EventsConfig::default();
+    assert_eq!(config.dedup_window_ms, 1000);
+    assert_eq!(config.sse_keep_alive_secs, 15);
+    assert_eq!(config.replay_buffer_size, 1000);
+}
+
+#[test]
+fn test_retention_config_defaults() {
+    let config = RetentionConfig::default();
+    assert_eq!(config.completed_task_ttl_days, 0);
+    assert_eq!(config.failed_task_ttl_days, 0);
+    assert_eq!(config.log_ttl_days, 0);
+    assert_eq!(config.batch_size, 1000);
+    assert_eq!(config.interval_secs, 3600);
+}
+
+#[test]
+fn test_limits_config_defaults() {
+    let config = LimitsConfig::default();
+    assert_eq!(config.max_name_len, 255);
+    assert_eq!(config.max_payload_bytes, 256 * 1024);
+    assert_eq!((config.min_priority, config.max_priority), (-1000, 1000));
+    assert_eq!(config.max_retries, 100);
+    assert_eq!(config.max_timeout_seconds, 604_800);
+    assert_eq!(config.max_output_bytes, 1024 * 1024);
+    assert_eq!(config.max_error_message_bytes, 16 * 1024);
 }
 
 #[test]
@@ -35,6 +82,46 @@ fn test_gossip_config_defaults() {
     assert_eq!(config.listen_addr, "0.0.0.0:7280");
     assert!(config.seed_nodes.is_empty());
     assert_eq!(config.cluster_id, "valka");
+    assert!(config.secret.is_none());
+    assert_eq!(config.circuit_recovery_secs, 10);
+    assert_eq!(config.gossip_interval_ms, 500);
+    assert_eq!(config.phi_threshold, 8.0);
+    assert_eq!(config.virtual_nodes, 64);
+    assert_eq!(config.min_ready_members, 0);
+}
+
+#[test]
+fn test_gossip_config_debug_redacts_secret() {
+    let config = GossipConfig {
+        secret: Some("hunter2".to_string()),
+        ..GossipConfig::default()
+    };
+    let debug = format!("{config:?}");
+    assert!(!debug.contains("hunter2"));
+    assert!(debug.contains("<redacted>"));
+}
+
+#[test]
+fn test_auth_config_defaults() {
+    let config = AuthConfig::default();
+    assert!(config.tokens.is_empty());
+    assert!(config.token_file.is_none());
+    assert!(config.worker_tokens.is_empty());
+    assert!(!config.protect_health);
+    assert!(!config.protect_metrics);
+}
+
+#[test]
+fn test_auth_config_debug_redacts_tokens() {
+    let config = AuthConfig {
+        tokens: vec!["hunter2".to_string()],
+        worker_tokens: vec!["swordfish".to_string()],
+        ..AuthConfig::default()
+    };
+    let debug = format!("{config:?}");
+    assert!(!debug.contains("hunter2"));
+    assert!(!debug.contains("swordfish"));
+    assert!(debug.contains("<1 redacted>"));
 }
 
 #[test]
@@ -43,11 +130,33 @@ fn test_server_config_all_sub_configs() {
     assert_eq!(config.grpc_addr, "0.0.0.0:50051");
     assert_eq!(config.http_addr, "0.0.0.0:8989");
     assert!(!config.database_url.is_empty());
+    assert_eq!(config.shutdown_grace_secs, 30);
+    assert_eq!(config.log_format, LogFormat::Pretty);
+    assert!(config.log_file.is_none());
     // Verify sub-configs are nested correctly
     assert_eq!(config.matching.num_partitions, 4);
     assert_eq!(config.scheduler.reaper_interval_secs, 10);
     assert_eq!(config.log_ingester.batch_size, 100);
     assert_eq!(config.gossip.cluster_id, "valka");
+    assert!(!config.metrics.dispatch_latency_buckets.is_empty());
+    assert!(!config.metrics.exemplars);
+    assert!(config.telemetry.otlp_endpoint.is_none());
+    assert_eq!(config.telemetry.sampling_ratio, 1.0);
+    assert_eq!(config.quotas.reconcile_interval_ms, 30_000);
+    assert_eq!(config.dispatcher.heartbeat_interval_secs, 10);
+    assert_eq!(config.dispatcher.dead_after_secs, 30);
+}
+
+#[test]
+fn test_log_format_parses_and_deserializes() {
+    assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+    assert_eq!("pretty".parse::<LogFormat>(), Ok(LogFormat::Pretty));
+    assert!("xml".parse::<LogFormat>().unwrap_err().contains("xml"));
+    // Config files and VALKA_LOG_FORMAT use the same lowercase names
+    assert_eq!(
+        serde_json::from_str::<LogFormat>("\"json\"").unwrap(),
+        LogFormat::Json
+    );
 }
 
 #[test]
@@ -59,6 +168,183 @@ fn test_config_load_missing_file() {
     assert_eq!(config.matching.num_partitions, 4);
 }
 
+#[test]
+fn test_config_precedence_defaults_file_env() {
+    let path = std::env::temp_dir().join(format!("valka-precedence-{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        "database_url = \"postgresql://file/valka\"\n\
+         shutdown_grace_secs = 45\n\
+         [matching]\n\
+         decision_log_size = 77\n",
+    )
+    .unwrap();
+    // No other test reads these variables
+    unsafe {
+        std::env::set_var("VALKA_DATABASE_URL", "postgresql://env/valka");
+        std::env::set_var("VALKA_MATCHING__DECISION_LOG_SIZE", "99");
+    }
+    let config = ServerConfig::load(path.to_str());
+    unsafe {
+        std::env::remove_var("VALKA_DATABASE_URL");
+        std::env::remove_var("VALKA_MATCHING__DECISION_LOG_SIZE");
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    let config = config.unwrap();
+    // env beats the file, including nested keys
+    assert_eq!(config.database_url, "postgresql://env/valka");
+    assert_eq!(config.matching.decision_log_size, 99);
+    // the file beats defaults
+    assert_eq!(config.shutdown_grace_secs, 45);
+    // untouched keys keep their defaults
+    assert_eq!(config.grpc_addr, "0.0.0.0:50051");
+}
+
+#[test]
+fn test_default_config_is_valid() {
+    assert_eq!(ServerConfig::default().validate(), Ok(()));
+}
+
+#[test]
+fn test_validate_rejects_each_bad_value() {
+    type Breaker = fn(&mut ServerConfig);
+    let cases: Vec<(Breaker, &str)> = vec![
+        (|c| c.grpc_addr = "localhost".into(), "grpc_addr"),
+        (|c| c.http_addr = "0.0.0.0:http".into(), "http_addr"),
+        (|c| c.database_url = " ".into(), "database_url"),
+        (
+            |c| c.database.max_connections = 0,
+            "database.max_connections",
+        ),
+        (|c| c.matching.num_partitions = 0, "matching.num_partitions"),
+        (
+            |c| c.matching.branching_factor = 0,
+            "matching.branching_factor",
+        ),
+        (
+            |c| c.matching.max_buffer_per_partition = 0,
+            "matching.max_buffer_per_partition",
+        ),
+        (
+            |c| c.matching.task_reader_batch_size = 0,
+            "matching.task_reader_batch_size",
+        ),
+        (
+            |c| c.matching.task_reader_max_batch_size = 1,
+            "matching.task_reader_max_batch_size",
+        ),
+        (|c| c.log_ingester.batch_size = 0, "log_ingester.batch_size"),
+        (
+            |c| c.result_writer.batch_size = 0,
+            "result_writer.batch_size",
+        ),
+        (
+            |c| c.dispatcher.heartbeat_interval_secs = 0,
+            "dispatcher.heartbeat_interval_secs",
+        ),
+        (
+            |c| c.dispatcher.dead_after_secs = 15,
+            "dispatcher.dead_after_secs",
+        ),
+        (|c| c.retention.batch_size = 0, "retention.batch_size"),
+        (|c| c.limits.max_name_len = 0, "limits.max_name_len"),
+        (
+            |c| c.limits.max_payload_bytes = 0,
+            "limits.max_payload_bytes",
+        ),
+        (|c| c.limits.min_priority = 2000, "limits.min_priority"),
+        (|c| c.limits.max_retries = -1, "limits.max_retries"),
+        (
+            |c| c.limits.max_timeout_seconds = 0,
+            "limits.max_timeout_seconds",
+        ),
+        (|c| c.limits.max_output_bytes = 0, "limits.max_output_bytes"),
+        (
+            |c| c.limits.max_error_message_bytes = 0,
+            "limits.max_error_message_bytes",
+        ),
+    ];
+    for (break_config, field) in cases {
+        let mut config = ServerConfig::default();
+        break_config(&mut config);
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1, "{field}: {errors:?}");
+        assert!(errors[0].starts_with(field), "{field}: {errors:?}");
+    }
+}
+
+#[test]
+fn test_blob_storage_config_defaults() {
+    let config = BlobStorageConfig::default();
+    assert!(!config.is_enabled());
+    assert_eq!(config.offload_threshold_bytes, 256 * 1024);
+    assert_eq!(config.max_blob_bytes, 64 * 1024 * 1024);
+}
+
+#[test]
+fn test_validate_checks_blob_storage_only_when_enabled() {
+    let mut config = ServerConfig::default();
+    config.blob_storage.offload_threshold_bytes = 512 * 1024;
+    assert_eq!(config.validate(), Ok(()));
+
+    // Inputs between the limit and the threshold would be rejected, not offloaded
+    config.blob_storage.url = Some("s3://bucket/valka".into());
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(errors[0].starts_with("blob_storage.offload_threshold_bytes must not exceed 262144"));
+
+    config.blob_storage.offload_threshold_bytes = 1024;
+    config.blob_storage.max_blob_bytes = 1024;
+    let errors = config.validate().unwrap_err();
+    assert_eq!(
+        errors,
+        ["blob_storage.max_blob_bytes must be above offload_threshold_bytes"]
+    );
+}
+
+#[test]
+fn test_validate_checks_gossip_only_when_clustered() {
+    let mut config = ServerConfig::default();
+    config.gossip.listen_addr = "nope".into();
+    config.gossip.virtual_nodes = 0;
+    assert_eq!(config.validate(), Ok(()));
+
+    config.gossip.seed_nodes = vec![
+        "valka-0.valka:7946".into(),
+        "10.0.0.2:7946".into(),
+        "valka-1".into(),
+        ":7946".into(),
+    ];
+    config.gossip.advertise_addr = Some("valka-2.valka:99999".into());
+    config.gossip.phi_threshold = 0.0;
+    let errors = config.validate().unwrap_err();
+    assert_eq!(
+        errors,
+        vec![
+            "gossip.listen_addr \"nope\" is not an ip:port address",
+            "gossip.seed_nodes entry \"valka-1\" is not host:port",
+            "gossip.seed_nodes entry \":7946\" is not host:port",
+            "gossip.advertise_addr \"valka-2.valka:99999\" is not host:port",
+            "gossip.virtual_nodes must be at least 1",
+            "gossip.phi_threshold must be positive",
+        ]
+    );
+}
+
+#[test]
+fn test_validate_collects_every_error() {
+    let mut config = ServerConfig {
+        grpc_addr: String::new(),
+        ..Default::default()
+    };
+    config.matching.num_partitions = -1;
+    config.result_writer.batch_size = 0;
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(errors.contains(&"matching.num_partitions must be at least 1, got -1".to_string()));
+}
+
 #[test]
 fn test_matching_config_custom_values() {
     let config = MatchingConfig {
@@ -66,8 +352,14 @@ fn test_matching_config_custom_values() {
         branching_factor: 4,
         max_buffer_per_partition: 500,
         task_reader_batch_size: 100,
+        task_reader_max_batch_size: 400,
         task_reader_poll_busy_ms: 5,
         task_reader_poll_idle_ms: 100,
+        queue_policy_refresh_ms: 1000,
+        queue_discovery_fallback_ms: 60000,
+        decision_log_size: 1000,
+        decision_sample_rate: 100,
+        buffered_task_ttl_ms: 30000,
     };
     assert_eq!(config.num_partitions, 16);
     assert_eq!(config.branching_factor, 4);
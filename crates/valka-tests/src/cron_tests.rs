@@ -0,0 +1,40 @@
+use chrono::{DateTime, TimeZone, Utc};
+use valka_scheduler::cron::{first_fire, next_fire_after, parse};
+
+fn at(s: &str) -> DateTime<Utc> {
+    s.parse().unwrap()
+}
+
+#[test]
+fn test_five_field_expression_fires_on_the_minute() {
+    let schedule = parse("*/5 * * * *").unwrap();
+    assert_eq!(
+        next_fire_after(&schedule, at("2025-01-01T10:02:30Z")),
+        Some(at("2025-01-01T10:05:00Z"))
+    );
+    // Strictly after: a firing time is not its own successor
+    assert_eq!(
+        next_fire_after(&schedule, at("2025-01-01T10:05:00Z")),
+        Some(at("2025-01-01T10:10:00Z"))
+    );
+}
+
+#[test]
+fn test_expression_with_seconds_and_weekdays() {
+    let schedule = parse("30 0 9 * * Mon-Fri").unwrap();
+    // 2025-01-04 is a Saturday
+    assert_eq!(
+        next_fire_after(&schedule, at("2025-01-04T12:00:00Z")),
+        Some(Utc.with_ymd_and_hms(2025, 1, 6, 9, 0, 30).unwrap())
+    );
+}
+
+#[test]
+fn test_invalid_expressions_are_rejected() {
+    assert!(parse("* * *").is_err());
+    assert!(parse("61 * * * *").is_err());
+    assert!(parse("not a cron").is_err());
+    // Parses, but its only year is in the past
+    let err = first_fire("0 0 0 1 1 * 2020", at("2025-01-01T00:00:00Z")).unwrap_err();
+    assert!(err.contains("never fires"), "{err}");
+}
@@ -1,9 +1,9 @@
 use chrono::Utc;
 use tokio::sync::{broadcast, mpsc};
-use valka_core::{MatchingConfig, NodeId, WorkerId};
+use valka_core::{DispatcherConfig, MatchingConfig, NodeId, WorkerId};
 use valka_db::DbPool;
-use valka_dispatcher::DispatcherService;
-use valka_dispatcher::worker_handle::WorkerHandle;
+use valka_dispatcher::worker_handle::{WorkerHandle, WorkerState};
+use valka_dispatcher::{DispatcherService, Registration};
 use valka_matching::MatchingService;
 use valka_proto::WorkerResponse;
 
@@ -30,10 +30,10 @@ fn test_worker_handle_available_slots() {
     let (mut handle, _rx) = make_handle_with_id(WorkerId::new(), 3);
     assert_eq!(handle.available_slots(), 3);
 
-    handle.assign_task("task-1".to_string());
+    handle.assign_task("task-1".to_string(), "default");
     assert_eq!(handle.available_slots(), 2);
 
-    handle.assign_task("task-2".to_string());
+    handle.assign_task("task-2".to_string(), "default");
     assert_eq!(handle.available_slots(), 1);
 }
 
@@ -41,13 +41,45 @@ fn test_worker_handle_available_slots() {
 fn test_worker_handle_assign_and_complete() {
     let (mut handle, _rx) = make_handle_with_id(WorkerId::new(), 2);
 
-    handle.assign_task("task-1".to_string());
+    handle.assign_task("task-1".to_string(), "default");
     assert_eq!(handle.available_slots(), 1);
 
     handle.complete_task("task-1");
     assert_eq!(handle.available_slots(), 2);
 }
 
+#[test]
+fn test_worker_handle_queue_concurrency() {
+    let (tx, _rx) = mpsc::channel::<WorkerResponse>(1);
+    let mut handle = WorkerHandle::new(
+        WorkerId::new(),
+        "test-worker".to_string(),
+        vec!["video".to_string(), "email".to_string()],
+        5,
+        tx,
+        String::new(),
+    )
+    .with_queue_concurrency([("video".to_string(), 2), ("email".to_string(), 0)].into());
+    // A cap below 1 is ignored
+    assert_eq!(handle.queue_concurrency.len(), 1);
+
+    handle.assign_task("v1".to_string(), "video");
+    handle.assign_task("v2".to_string(), "video");
+    assert_eq!(handle.available_queue_slots("video"), 0);
+    assert_eq!(handle.available_queue_slots("email"), 3);
+    assert_eq!(handle.available_slots(), 3);
+
+    handle.complete_task("v1");
+    assert_eq!(handle.available_queue_slots("video"), 1);
+
+    // Uncapped queues are limited only by the global count
+    for i in 0..4 {
+        handle.assign_task(format!("e{i}"), "email");
+    }
+    assert_eq!(handle.available_queue_slots("email"), 0);
+    assert_eq!(handle.available_queue_slots("video"), 0);
+}
+
 #[test]
 fn test_worker_handle_zero_concurrency() {
     let (handle, _rx) = make_handle_with_id(WorkerId::new(), 0);
@@ -59,7 +91,7 @@ fn test_worker_handle_is_idle() {
     let (mut handle, _rx) = make_handle_with_id(WorkerId::new(), 2);
     assert!(handle.is_idle());
 
-    handle.assign_task("task-1".to_string());
+    handle.assign_task("task-1".to_string(), "default");
     assert!(!handle.is_idle());
 
     handle.complete_task("task-1");
@@ -77,9 +109,9 @@ fn test_worker_handle_complete_unknown_task() {
 #[test]
 fn test_worker_handle_duplicate_assign() {
     let (mut handle, _rx) = make_handle_with_id(WorkerId::new(), 5);
-    handle.assign_task("task-1".to_string());
+    handle.assign_task("task-1".to_string(), "default");
     // HashSet: inserting same value is idempotent
-    handle.assign_task("task-1".to_string());
+    handle.assign_task("task-1".to_string(), "default");
     assert_eq!(handle.active_tasks.len(), 1);
     assert_eq!(handle.available_slots(), 4);
 }
@@ -109,6 +141,35 @@ fn test_worker_handle_connected_at_set() {
     assert!(handle.connected_at <= now_after);
 }
 
+#[test]
+fn test_worker_handle_dedups_queues() {
+    let (tx, _rx) = mpsc::channel::<WorkerResponse>(1);
+    let handle = WorkerHandle::new(
+        WorkerId::new(),
+        "test-worker".to_string(),
+        ["emails", "reports", "emails"].map(String::from).to_vec(),
+        1,
+        tx,
+        String::new(),
+    );
+    assert_eq!(handle.queues, ["emails", "reports"]);
+}
+
+#[test]
+fn test_worker_handle_protocol_capabilities() {
+    // Workers that predate versioning keep what the server always sent
+    let (handle, _rx) = make_handle_with_id(WorkerId::new(), 1);
+    let handle = handle.with_protocol(0, vec![]);
+    assert!(handle.supports(valka_proto::CAPABILITY_SIGNALS));
+    assert!(handle.supports(valka_proto::CAPABILITY_PROGRESS));
+
+    let (handle, _rx) = make_handle_with_id(WorkerId::new(), 1);
+    let handle = handle.with_protocol(1, vec!["progress".to_string()]);
+    assert_eq!(handle.protocol_version, 1);
+    assert!(handle.supports(valka_proto::CAPABILITY_PROGRESS));
+    assert!(!handle.supports(valka_proto::CAPABILITY_SIGNALS));
+}
+
 // === DispatcherService tests ===
 
 fn make_pool() -> DbPool {
@@ -141,6 +202,86 @@ async fn test_dispatcher_register_deregister() {
     assert_eq!(dispatcher.workers().len(), 0);
 }
 
+#[tokio::test]
+async fn test_dispatcher_duplicate_worker_id_takes_over_session() {
+    let dispatcher = make_dispatcher();
+    let worker_id = WorkerId::new();
+    let (old, mut old_rx) = make_handle_with_id(worker_id.clone(), 2);
+    let mut old = old.with_session_id("session-1".to_string());
+    old.assign_task("task-running".to_string(), "default");
+    let old_state = old.state.subscribe();
+    assert_eq!(dispatcher.register_worker(old).await, Registration::New);
+
+    let (new, mut new_rx) = make_handle_with_id(worker_id.clone(), 2);
+    let new = new.with_session_id("session-2".to_string());
+    assert_eq!(
+        dispatcher.register_worker(new).await,
+        Registration::Adopted {
+            previous_session_id: "session-1".to_string()
+        }
+    );
+    assert_eq!(dispatcher.workers().len(), 1);
+    assert!(dispatcher.is_current_session(&worker_id, "session-2"));
+    {
+        let handle = dispatcher.workers().get(worker_id.as_ref()).unwrap();
+        assert!(handle.active_tasks.contains("task-running"));
+        assert_eq!(handle.queue_active_tasks("default"), 1);
+        assert_eq!(handle.available_slots(), 1);
+    }
+
+    // The stale stream is told to go and closed
+    match old_rx.try_recv().unwrap().response {
+        Some(valka_proto::worker_response::Response::ServerShutdown(shutdown)) => {
+            assert_eq!(shutdown.drain_seconds, 0);
+        }
+        other => panic!("Expected ServerShutdown, got {other:?}"),
+    }
+    assert_eq!(*old_state.borrow(), WorkerState::Closed);
+
+    // Its cleanup leaves the new session alone
+    dispatcher.deregister_session(&worker_id, "session-1").await;
+    assert_eq!(dispatcher.workers().len(), 1);
+
+    // The adopted task is cancelled on the new stream
+    assert!(dispatcher.cancel_task_on_worker("task-running").await);
+    match new_rx.recv().await.unwrap().response {
+        Some(valka_proto::worker_response::Response::TaskCancellation(cancel)) => {
+            assert_eq!(cancel.task_id, "task-running");
+        }
+        other => panic!("Expected TaskCancellation, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_dispatcher_rejects_duplicate_worker_id_when_configured() {
+    let dispatcher = make_dispatcher().with_dispatcher_config(DispatcherConfig {
+        reject_duplicate_workers: true,
+        ..Default::default()
+    });
+    let worker_id = WorkerId::new();
+    let (old, mut old_rx) = make_handle_with_id(worker_id.clone(), 2);
+    let mut old = old.with_session_id("session-1".to_string());
+    old.assign_task("task-running".to_string(), "default");
+    dispatcher.register_worker(old).await;
+
+    let (new, _new_rx) = make_handle_with_id(worker_id.clone(), 2);
+    let new = new.with_session_id("session-2".to_string());
+    assert_eq!(
+        dispatcher.register_worker(new).await,
+        Registration::Rejected
+    );
+    assert!(dispatcher.is_current_session(&worker_id, "session-1"));
+    assert!(
+        dispatcher
+            .workers()
+            .get(worker_id.as_ref())
+            .unwrap()
+            .active_tasks
+            .contains("task-running")
+    );
+    assert!(old_rx.try_recv().is_err());
+}
+
 #[tokio::test]
 async fn test_dispatcher_multiple_workers() {
     let dispatcher = make_dispatcher();
@@ -177,6 +318,9 @@ async fn test_dispatcher_emit_event() {
         attempt_number: 0,
         error_message: String::new(),
         timestamp_ms: 0,
+        suppressed_count: 0,
+        progress: None,
+        progress_message: String::new(),
     };
     dispatcher.event_tx().send(event.clone()).unwrap();
 
@@ -197,7 +341,7 @@ async fn test_dispatcher_cancel_active_task() {
     let dispatcher = make_dispatcher();
     let worker_id = WorkerId::new();
     let (mut handle, mut rx) = make_handle_with_id(worker_id.clone(), 2);
-    handle.assign_task("task-to-cancel".to_string());
+    handle.assign_task("task-to-cancel".to_string(), "default");
     dispatcher.register_worker(handle).await;
 
     let result = dispatcher.cancel_task_on_worker("task-to-cancel").await;
@@ -221,7 +365,7 @@ async fn test_dispatcher_send_signal_to_active_task() {
     let dispatcher = make_dispatcher();
     let worker_id = WorkerId::new();
     let (mut handle, mut rx) = make_handle_with_id(worker_id.clone(), 2);
-    handle.assign_task("task-signaled".to_string());
+    handle.assign_task("task-signaled".to_string(), "default");
     dispatcher.register_worker(handle).await;
 
     let signal = valka_proto::TaskSignal {
@@ -270,12 +414,33 @@ async fn test_dispatcher_send_signal_no_worker() {
     assert!(!delivered, "Should return false when no worker registered");
 }
 
+#[tokio::test]
+async fn test_dispatcher_send_signal_needs_capability() {
+    let dispatcher = make_dispatcher();
+    let (handle, mut rx) = make_handle_with_id(WorkerId::new(), 2);
+    let mut handle = handle.with_protocol(1, vec!["progress".to_string()]);
+    handle.assign_task("task-nosig".to_string(), "default");
+    dispatcher.register_worker(handle).await;
+
+    let signal = valka_proto::TaskSignal {
+        signal_id: "sig-held".to_string(),
+        task_id: "task-nosig".to_string(),
+        signal_name: "approve".to_string(),
+        payload: String::new(),
+        timestamp_ms: 0,
+    };
+
+    let delivered = dispatcher.send_signal_to_worker("task-nosig", signal).await;
+    assert!(!delivered, "Worker without the signals capability got one");
+    assert!(rx.try_recv().is_err());
+}
+
 #[tokio::test]
 async fn test_dispatcher_send_signal_wrong_task() {
     let dispatcher = make_dispatcher();
     let worker_id = WorkerId::new();
     let (mut handle, _rx) = make_handle_with_id(worker_id.clone(), 2);
-    handle.assign_task("task-A".to_string());
+    handle.assign_task("task-A".to_string(), "default");
     dispatcher.register_worker(handle).await;
 
     let signal = valka_proto::TaskSignal {
@@ -292,3 +457,379 @@ async fn test_dispatcher_send_signal_wrong_task() {
         "Should return false when worker has different task"
     );
 }
+
+// ─── EventEmitter coalescing ────────────────────────────────────────
+
+fn make_event(task_id: &str, new_status: i32) -> valka_proto::TaskEvent {
+    valka_proto::TaskEvent {
+        event_id: uuid::Uuid::now_v7().to_string(),
+        task_id: task_id.to_string(),
+        queue_name: "demo".to_string(),
+        previous_status: 0,
+        new_status,
+        worker_id: String::new(),
+        node_id: String::new(),
+        attempt_number: 0,
+        error_message: String::new(),
+        timestamp_ms: 0,
+        suppressed_count: 0,
+        progress: None,
+        progress_message: String::new(),
+    }
+}
+
+fn make_emitter(
+    window_ms: u64,
+) -> (
+    valka_dispatcher::EventEmitter,
+    broadcast::Receiver<valka_proto::TaskEvent>,
+) {
+    let (event_tx, event_rx) = broadcast::channel(1024);
+    let config = valka_core::EventsConfig {
+        dedup_window_ms: window_ms,
+        ..Default::default()
+    };
+    (
+        valka_dispatcher::EventEmitter::new(event_tx, &config),
+        event_rx,
+    )
+}
+
+fn drain(rx: &mut broadcast::Receiver<valka_proto::TaskEvent>) -> Vec<valka_proto::TaskEvent> {
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+    events
+}
+
+#[tokio::test]
+async fn test_event_emitter_coalesces_retry_flaps() {
+    let (emitter, mut rx) = make_emitter(1000);
+
+    // 50 RETRY events within ~200ms
+    for _ in 0..50 {
+        emitter.emit(make_event("flappy", 6));
+        tokio::time::sleep(std::time::Duration::from_millis(4)).await;
+    }
+    let events = drain(&mut rx);
+    assert_eq!(events.len(), 1, "Only the first RETRY gets through");
+    assert_eq!(events[0].suppressed_count, 0);
+
+    // The next distinct status reports the interim repeats
+    assert!(emitter.emit(make_event("flappy", 3)));
+    let events = drain(&mut rx);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].suppressed_count, 49);
+
+    // Terminal status always gets through
+    assert!(emitter.emit(make_event("flappy", 4)));
+    assert_eq!(drain(&mut rx)[0].new_status, 4);
+}
+
+#[tokio::test]
+async fn test_event_emitter_dedups_only_against_last_status() {
+    let (emitter, mut rx) = make_emitter(1000);
+
+    // RUNNING -> RETRY -> RUNNING is a second attempt, not a repeat
+    assert!(emitter.emit(make_event("t", 3)));
+    assert!(emitter.emit(make_event("t", 6)));
+    assert!(emitter.emit(make_event("t", 3)));
+    assert!(!emitter.emit(make_event("t", 3)));
+    let statuses: Vec<i32> = drain(&mut rx).iter().map(|e| e.new_status).collect();
+    assert_eq!(statuses, [3, 6, 3]);
+}
+
+#[tokio::test]
+async fn test_event_emitter_terminal_never_suppressed() {
+    let (emitter, mut rx) = make_emitter(1000);
+
+    for status in [4, 4, 5, 8, 7] {
+        assert!(emitter.emit(make_event("t", status)));
+    }
+    assert_eq!(drain(&mut rx).len(), 5);
+}
+
+#[tokio::test]
+async fn test_event_emitter_window_expiry_reports_suppressed() {
+    let (emitter, mut rx) = make_emitter(50);
+
+    assert!(emitter.emit(make_event("t", 6)));
+    assert!(!emitter.emit(make_event("t", 6)));
+    assert!(!emitter.emit(make_event("t", 6)));
+    tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    assert!(emitter.emit(make_event("t", 6)));
+
+    let events = drain(&mut rx);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].suppressed_count, 2);
+}
+
+#[tokio::test]
+async fn test_event_emitter_tasks_are_independent() {
+    let (emitter, mut rx) = make_emitter(1000);
+
+    assert!(emitter.emit(make_event("a", 1)));
+    assert!(emitter.emit(make_event("b", 1)));
+    assert!(!emitter.emit(make_event("a", 1)));
+    assert_eq!(drain(&mut rx).len(), 2);
+}
+
+#[tokio::test]
+async fn test_event_emitter_zero_window_disables_dedup() {
+    let (emitter, mut rx) = make_emitter(0);
+
+    for _ in 0..10 {
+        assert!(emitter.emit(make_event("t", 6)));
+    }
+    assert_eq!(drain(&mut rx).len(), 10);
+}
+
+// ─── EventEmitter replay ────────────────────────────────────────────
+
+fn make_replaying_emitter(replay_buffer_size: usize) -> valka_dispatcher::EventEmitter {
+    let (event_tx, _) = broadcast::channel(1024);
+    let config = valka_core::EventsConfig {
+        dedup_window_ms: 0,
+        replay_buffer_size,
+        ..Default::default()
+    };
+    let emitter = valka_dispatcher::EventEmitter::new(event_tx, &config);
+    // Recording starts with the first subscriber
+    drop(emitter.subscribe_since(None));
+    emitter
+}
+
+async fn emit_all(emitter: &valka_dispatcher::EventEmitter, n: usize) -> Vec<String> {
+    let mut ids = Vec::new();
+    for i in 0..n {
+        let event = make_event(&format!("t{i}"), 1);
+        ids.push(event.event_id.clone());
+        emitter.emit(event);
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    ids
+}
+
+async fn next_id(sub: &mut valka_dispatcher::EventSubscription) -> String {
+    tokio::time::timeout(std::time::Duration::from_secs(1), sub.next())
+        .await
+        .expect("no event within 1s")
+        .unwrap()
+        .event_id
+}
+
+#[tokio::test]
+async fn test_event_replay_after_last_event_id() {
+    let emitter = make_replaying_emitter(100);
+    let ids = emit_all(&emitter, 3).await;
+
+    let mut sub = emitter.subscribe_since(Some(&ids[0]));
+    assert_eq!(next_id(&mut sub).await, ids[1]);
+    assert_eq!(next_id(&mut sub).await, ids[2]);
+
+    let live = make_event("live", 1);
+    emitter.emit(live.clone());
+    assert_eq!(next_id(&mut sub).await, live.event_id);
+}
+
+#[tokio::test]
+async fn test_event_replay_evicted_cursor_falls_back_to_id_order() {
+    let emitter = make_replaying_emitter(2);
+    let ids = emit_all(&emitter, 4).await;
+
+    // ids[1] was evicted; everything buffered is newer
+    let mut sub = emitter.subscribe_since(Some(&ids[1]));
+    assert_eq!(next_id(&mut sub).await, ids[2]);
+    assert_eq!(next_id(&mut sub).await, ids[3]);
+
+    // Not an event id: nothing to replay
+    let mut sub = emitter.subscribe_since(Some("bogus"));
+    let live = make_event("live", 1);
+    emitter.emit(live.clone());
+    assert_eq!(next_id(&mut sub).await, live.event_id);
+}
+
+// === QueuePolicies tests ===
+
+#[test]
+fn test_queue_policy_min_worker_version() {
+    use semver::Version;
+    use valka_dispatcher::QueuePolicies;
+
+    let policies = QueuePolicies::default();
+    let mut rx = policies.subscribe();
+    let old = Version::new(1, 4, 0);
+    let new = Version::new(2, 0, 0);
+    assert_eq!(policies.exclusion_reason("default", &old), None);
+
+    policies.set_min_worker_version("default", Some(Version::new(2, 0, 0)));
+    assert!(rx.has_changed().unwrap());
+    rx.mark_unchanged();
+    assert_eq!(
+        policies.exclusion_reason("default", &old).as_deref(),
+        Some("version < 2.0.0")
+    );
+    assert_eq!(policies.exclusion_reason("default", &new), None);
+    assert_eq!(policies.exclusion_reason("other", &old), None);
+
+    // Re-setting the same minimum does not wake match loops
+    policies.set_min_worker_version("default", Some(Version::new(2, 0, 0)));
+    assert!(!rx.has_changed().unwrap());
+
+    policies.set_min_worker_version("default", None);
+    assert!(rx.has_changed().unwrap());
+    assert_eq!(policies.exclusion_reason("default", &old), None);
+}
+
+#[test]
+fn test_worker_handle_version_parsing() {
+    let (handle, _rx) = make_handle_with_id(WorkerId::new(), 1);
+    assert_eq!(handle.parsed_version, semver::Version::new(0, 0, 0));
+
+    let handle = handle.with_version("1.2.3".to_string());
+    assert_eq!(handle.version, "1.2.3");
+    assert_eq!(handle.parsed_version, semver::Version::new(1, 2, 3));
+
+    // Unparseable versions never satisfy a minimum
+    let handle = handle.with_version("dev-build".to_string());
+    assert_eq!(handle.parsed_version, semver::Version::new(0, 0, 0));
+}
+
+// === Quotas tests ===
+
+fn quota(
+    key: &str,
+    max_active_tasks: Option<i64>,
+    max_creations_per_hour: Option<i64>,
+    now: chrono::DateTime<Utc>,
+) -> valka_dispatcher::quotas::QuotaUsage {
+    valka_dispatcher::quotas::QuotaUsage {
+        key: key.to_string(),
+        max_active_tasks,
+        max_creations_per_hour,
+        active_tasks: 0,
+        created_this_hour: 0,
+        window_start: valka_dispatcher::quotas::window_start(now),
+    }
+}
+
+#[test]
+fn test_quota_namespace_of() {
+    use valka_dispatcher::quotas::namespace_of;
+
+    assert_eq!(namespace_of("billing.invoices"), "billing");
+    assert_eq!(namespace_of("billing.invoices.eu"), "billing");
+    assert_eq!(namespace_of("default"), "default");
+}
+
+#[test]
+fn test_quota_unlimited_by_default() {
+    let quotas = valka_dispatcher::Quotas::default();
+    let now = Utc::now();
+    for _ in 0..1000 {
+        quotas.try_acquire("default", now).unwrap();
+    }
+    assert!(quotas.usage("default", now).is_none());
+}
+
+#[test]
+fn test_quota_active_tasks_limit() {
+    use valka_dispatcher::quotas::QuotaLimit;
+
+    let quotas = valka_dispatcher::Quotas::default();
+    let now = Utc::now();
+    quotas.set("billing", Some(quota("billing", Some(10), None, now)));
+
+    let rejections: Vec<_> = (0..12)
+        .filter_map(|_| quotas.try_acquire("billing.invoices", now).err())
+        .collect();
+    assert_eq!(rejections.len(), 2);
+    for e in &rejections {
+        assert_eq!(e.key, "billing");
+        assert_eq!(e.limit_kind, QuotaLimit::ActiveTasks);
+        assert_eq!(e.usage, 10);
+        assert_eq!(e.limit, 10);
+    }
+    assert_eq!(quotas.usage("billing", now).unwrap().active_tasks, 10);
+
+    // Other namespaces are unaffected; a failed insert gives its slot back
+    quotas.try_acquire("shipping", now).unwrap();
+    quotas.release("billing.invoices");
+    quotas.try_acquire("billing.invoices", now).unwrap();
+}
+
+#[test]
+fn test_quota_hourly_window_resets() {
+    use chrono::TimeZone;
+    use valka_dispatcher::quotas::QuotaLimit;
+
+    let quotas = valka_dispatcher::Quotas::default();
+    let t0 = Utc.with_ymd_and_hms(2025, 6, 1, 10, 15, 0).unwrap();
+    quotas.set("billing", Some(quota("billing", None, Some(3), t0)));
+
+    for _ in 0..3 {
+        quotas.try_acquire("billing", t0).unwrap();
+    }
+    let late_in_hour = Utc.with_ymd_and_hms(2025, 6, 1, 10, 59, 59).unwrap();
+    let err = quotas.try_acquire("billing", late_in_hour).unwrap_err();
+    assert_eq!(err.limit_kind, QuotaLimit::CreationsPerHour);
+    assert_eq!(err.usage, 3);
+
+    // The next clock hour starts a fresh window
+    let next_hour = Utc.with_ymd_and_hms(2025, 6, 1, 11, 0, 0).unwrap();
+    quotas.try_acquire("billing", next_hour).unwrap();
+    let usage = quotas.usage("billing", next_hour).unwrap();
+    assert_eq!(usage.created_this_hour, 1);
+    assert_eq!(usage.window_start, next_hour);
+    assert_eq!(usage.active_tasks, 4);
+}
+
+// === Webhook tests ===
+
+#[test]
+fn test_callback_url_refuses_internal_hosts() {
+    use valka_dispatcher::webhooks::validate_callback_url;
+
+    for url in [
+        "https://example.com/hook",
+        "http://93.184.216.34:8080/hook",
+        "http://[2606:4700::1111]/hook",
+    ] {
+        assert_eq!(validate_callback_url(url, &[]), Ok(()), "{url}");
+    }
+    for url in [
+        "http://127.0.0.1/hook",
+        "http://10.1.2.3/hook",
+        "http://192.168.0.10/hook",
+        "http://172.16.0.1/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://100.64.0.1/hook",
+        "http://0.0.0.0/hook",
+        "http://2130706433/hook",
+        "http://[::1]/hook",
+        "http://[fd00::1]/hook",
+        "http://[fe80::1]/hook",
+        "http://[::ffff:127.0.0.1]/hook",
+        "http://localhost:8080/hook",
+        "http://api.LOCALHOST./hook",
+    ] {
+        let err = validate_callback_url(url, &[]).unwrap_err();
+        assert!(err.contains("webhooks.allowed_hosts"), "{url}: {err}");
+    }
+
+    // Allowed hosts pass, matched without case or IPv6 brackets
+    let allowed = [
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+        "LocalHost".to_string(),
+    ];
+    for url in [
+        "http://127.0.0.1:9000/hook",
+        "http://[::1]/hook",
+        "http://localhost/hook",
+    ] {
+        assert_eq!(validate_callback_url(url, &allowed), Ok(()), "{url}");
+    }
+    assert!(validate_callback_url("http://10.0.0.1/hook", &allowed).is_err());
+}
@@ -1,7 +1,7 @@
 use chrono::{Duration, Utc};
 use tokio::sync::mpsc;
-use valka_core::WorkerId;
-use valka_dispatcher::heartbeat::{WorkerStatus, check_heartbeat};
+use valka_core::{DispatcherConfig, WorkerId};
+use valka_dispatcher::heartbeat::{WorkerStatus, check_heartbeat, negotiate_interval_ms};
 use valka_dispatcher::worker_handle::WorkerHandle;
 use valka_proto::WorkerResponse;
 
@@ -21,7 +21,10 @@ fn make_handle(concurrency: i32) -> WorkerHandle {
 fn test_heartbeat_alive() {
     let handle = make_handle(1);
     // Just created, should be alive
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Alive);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Alive
+    );
 }
 
 #[test]
@@ -29,7 +32,10 @@ fn test_heartbeat_suspect() {
     let mut handle = make_handle(1);
     // Set heartbeat to 15 seconds ago
     handle.last_heartbeat = Utc::now() - Duration::seconds(15);
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Suspect);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Suspect
+    );
 }
 
 #[test]
@@ -37,7 +43,10 @@ fn test_heartbeat_dead() {
     let mut handle = make_handle(1);
     // Set heartbeat to 60 seconds ago
     handle.last_heartbeat = Utc::now() - Duration::seconds(60);
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Dead);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Dead
+    );
 }
 
 #[test]
@@ -45,11 +54,17 @@ fn test_heartbeat_boundary_alive_suspect() {
     let mut handle = make_handle(1);
     // Just under the 10s threshold — should be alive
     handle.last_heartbeat = Utc::now() - Duration::seconds(9);
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Alive);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Alive
+    );
 
     // Just over — should be suspect
     handle.last_heartbeat = Utc::now() - Duration::seconds(11);
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Suspect);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Suspect
+    );
 }
 
 #[test]
@@ -57,18 +72,24 @@ fn test_heartbeat_boundary_suspect_dead() {
     let mut handle = make_handle(1);
     // Just under the 30s threshold — should be suspect
     handle.last_heartbeat = Utc::now() - Duration::seconds(29);
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Suspect);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Suspect
+    );
 
     // Just over — should be dead
     handle.last_heartbeat = Utc::now() - Duration::seconds(31);
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Dead);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Dead
+    );
 }
 
 #[test]
 fn test_heartbeat_just_created() {
     let handle = make_handle(5);
     // Fresh worker should always be alive
-    let status = check_heartbeat(&handle);
+    let status = check_heartbeat(&handle, &DispatcherConfig::default());
     assert_eq!(status, WorkerStatus::Alive);
 }
 
@@ -77,11 +98,53 @@ fn test_heartbeat_after_update() {
     let mut handle = make_handle(1);
     // Set to dead timing
     handle.last_heartbeat = Utc::now() - Duration::seconds(60);
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Dead);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Dead
+    );
 
     // Update heartbeat
     handle.update_heartbeat();
-    assert_eq!(check_heartbeat(&handle), WorkerStatus::Alive);
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Alive
+    );
+}
+
+#[test]
+fn test_reconcile_reported_drops_after_one_missed_heartbeat() {
+    let mut handle = make_handle(2);
+    handle.assign_task("lost".to_string(), "default");
+    handle.assign_task("running".to_string(), "default");
+    let reported = vec!["running".to_string()];
+
+    // First miss is tolerated: the assignment may still be in flight
+    assert!(handle.reconcile_reported(&reported).is_empty());
+    assert_eq!(handle.available_slots(), 0);
+    assert_eq!(handle.reported_tasks, 1);
+
+    assert_eq!(
+        handle.reconcile_reported(&reported),
+        vec!["lost".to_string()]
+    );
+    assert_eq!(handle.available_slots(), 1);
+    assert!(handle.unclaimed_tasks.is_empty());
+}
+
+#[test]
+fn test_reconcile_reported_grace_resets_when_task_reappears() {
+    let mut handle = make_handle(1);
+    handle.assign_task("late".to_string(), "default");
+
+    assert!(handle.reconcile_reported(&[]).is_empty());
+    // Reported once the assignment arrived: no longer a candidate
+    assert!(handle.reconcile_reported(&["late".to_string()]).is_empty());
+    assert!(handle.reconcile_reported(&[]).is_empty());
+    assert_eq!(handle.available_slots(), 0);
+
+    // A result clears the pending miss too
+    handle.complete_task("late");
+    assert!(handle.unclaimed_tasks.is_empty());
 }
 
 #[tokio::test]
@@ -105,6 +168,7 @@ async fn test_heartbeat_checker_detects_dead_worker() {
     let workers_clone = workers.clone();
     let checker = tokio::spawn(valka_dispatcher::heartbeat::heartbeat_checker(
         workers_clone,
+        DispatcherConfig::default(),
         shutdown_rx,
         dead_tx,
     ));
@@ -124,3 +188,41 @@ async fn test_heartbeat_checker_detects_dead_worker() {
     let _ = shutdown_tx.send(true);
     let _ = checker.await;
 }
+
+#[test]
+fn test_negotiate_heartbeat_interval() {
+    let config = DispatcherConfig::default();
+    assert_eq!(negotiate_interval_ms(0, &config), 10_000);
+    assert_eq!(negotiate_interval_ms(-5, &config), 10_000);
+    assert_eq!(negotiate_interval_ms(2_500, &config), 2_500);
+    // Clamped so a worker is never suspected between heartbeats
+    assert_eq!(negotiate_interval_ms(100, &config), 1_000);
+    assert_eq!(negotiate_interval_ms(60_000, &config), 10_000);
+
+    let config = DispatcherConfig {
+        heartbeat_interval_secs: 5,
+        dead_after_secs: 15,
+        ..Default::default()
+    };
+    assert_eq!(negotiate_interval_ms(0, &config), 5_000);
+    assert_eq!(negotiate_interval_ms(10_000, &config), 5_000);
+}
+
+#[test]
+fn test_heartbeat_thresholds_follow_config() {
+    let config = DispatcherConfig {
+        heartbeat_interval_secs: 2,
+        dead_after_secs: 5,
+        ..Default::default()
+    };
+    let mut handle = make_handle(1);
+    handle.last_heartbeat = Utc::now() - Duration::seconds(3);
+    assert_eq!(check_heartbeat(&handle, &config), WorkerStatus::Suspect);
+    handle.last_heartbeat = Utc::now() - Duration::seconds(6);
+    assert_eq!(check_heartbeat(&handle, &config), WorkerStatus::Dead);
+    // Within a heartbeat interval under the defaults
+    assert_eq!(
+        check_heartbeat(&handle, &DispatcherConfig::default()),
+        WorkerStatus::Alive
+    );
+}
@@ -0,0 +1,216 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tower::ServiceExt;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{AuthConfig, NodeId};
+use valka_dispatcher::DispatcherService;
+use valka_sdk::{SdkError, ValkaClient, ValkaWorker};
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+fn request(uri: &str, authorization: Option<&str>) -> Request<Body> {
+    let mut req = Request::builder().uri(uri);
+    if let Some(value) = authorization {
+        req = req.header("authorization", value);
+    }
+    req.body(Body::empty()).unwrap()
+}
+
+#[test]
+fn test_api_auth_from_config_reads_token_file() {
+    let path = std::env::temp_dir().join(format!("valka-tokens-{}", uuid::Uuid::now_v7()));
+    std::fs::write(&path, "# ops\nfrom-file\n\n  padded  \n").unwrap();
+    let auth = ApiAuth::from_config(
+        &AuthConfig {
+            tokens: vec!["inline".to_string()],
+            token_file: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        },
+        Some("admin"),
+    )
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    for token in ["inline", "from-file", "padded", "admin"] {
+        assert!(auth.allows_api(Some(&format!("Bearer {token}"))), "{token}");
+    }
+    assert!(!auth.allows_api(Some("Bearer # ops")));
+    assert!(!auth.allows_api(Some("inline")));
+    assert!(!auth.allows_api(None));
+}
+
+#[test]
+fn test_api_auth_admin_token_alone_leaves_api_open() {
+    let auth = ApiAuth::from_config(&AuthConfig::default(), Some("admin")).unwrap();
+    assert!(!auth.api_enabled());
+    assert!(auth.allows_api(None));
+    assert!(auth.allows_worker(None));
+}
+
+#[test]
+fn test_api_auth_worker_tokens_only_open_sessions() {
+    let auth = ApiAuth::new(vec!["api".to_string()], vec!["wrk".to_string()]);
+    assert!(auth.allows_worker(Some("Bearer wrk")));
+    assert!(auth.allows_worker(Some("Bearer api")));
+    assert!(!auth.allows_worker(None));
+    assert!(!auth.allows_api(Some("Bearer wrk")));
+
+    // Worker tokens alone gate sessions but leave the API open
+    let auth = ApiAuth::new(vec![], vec!["wrk".to_string()]);
+    assert!(auth.allows_api(None));
+    assert!(!auth.allows_worker(None));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_requires_api_token(pool: PgPool) {
+    let auth = ApiAuth::new(vec!["t0k3n".to_string()], vec![]).with_protected(false, true);
+    let app = valka_server::rest::require_api_auth(build_test_router(pool), auth);
+
+    let resp = app
+        .clone()
+        .oneshot(request("/api/v1/tasks", None))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::UNAUTHORIZED,
+        "UNAUTHORIZED",
+        "Missing or invalid API token",
+    )
+    .await;
+    let resp = app
+        .clone()
+        .oneshot(request("/api/v1/tasks", Some("Bearer wrong")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = app
+        .clone()
+        .oneshot(request("/api/v1/tasks", Some("Bearer t0k3n")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    // How EventSource clients authenticate
+    let resp = app
+        .clone()
+        .oneshot(request("/api/v1/stats?access_token=t0k3n", None))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app
+        .clone()
+        .oneshot(request("/healthz", None))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = app
+        .clone()
+        .oneshot(request("/metrics", None))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let preflight = Request::builder()
+        .method("OPTIONS")
+        .uri("/api/v1/tasks")
+        .header("origin", "http://localhost:5173")
+        .header("access-control-request-method", "GET")
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.oneshot(preflight).await.unwrap();
+    assert!(resp.status().is_success(), "{}", resp.status());
+}
+
+/// Serve gRPC on `addr` with `auth` until the returned sender fires.
+async fn start_server(
+    pool: &PgPool,
+    dispatcher: &DispatcherService,
+    addr: SocketAddr,
+    auth: ApiAuth,
+) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    let (_, matching) = make_dispatcher(pool.clone());
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching,
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        auth,
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown_tx
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_grpc_requires_api_and_worker_tokens(pool: PgPool) {
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19893".parse().unwrap();
+    let auth = ApiAuth::new(vec!["api".to_string()], vec!["wrk".to_string()]);
+    let _shutdown = start_server(&pool, &dispatcher, addr, auth).await;
+    let url = format!("http://{addr}");
+
+    let client = ValkaClient::connect(&url).await.unwrap();
+    let err = client.clone().get_task("missing").await.unwrap_err();
+    assert!(
+        matches!(&err, SdkError::Grpc(s) if s.code() == tonic::Code::Unauthenticated),
+        "{err:?}"
+    );
+    let err = client
+        .clone()
+        .token("wrk")
+        .get_task("missing")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, SdkError::Grpc(s) if s.code() == tonic::Code::Unauthenticated),
+        "{err:?}"
+    );
+    let err = client.token("api").get_task("missing").await.unwrap_err();
+    assert!(
+        matches!(&err, SdkError::Grpc(s) if s.code() == tonic::Code::NotFound),
+        "{err:?}"
+    );
+
+    // A session without a token is refused; one with the worker token registers
+    let _anonymous = ValkaWorker::builder()
+        .server_addr(&url)
+        .queues(&["auth"])
+        .build_stream()
+        .await
+        .unwrap();
+    let _worker = ValkaWorker::builder()
+        .server_addr(&url)
+        .queues(&["auth"])
+        .token("wrk")
+        .build_stream()
+        .await
+        .unwrap();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while dispatcher.workers().is_empty() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "worker never registered"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(dispatcher.workers().len(), 1);
+}
@@ -0,0 +1,187 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::tasks::TaskRow;
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_sdk::ValkaWorker;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+type Batches = Arc<Mutex<Vec<Vec<String>>>>;
+
+async fn start_server(
+    pool: &PgPool,
+    dispatcher: &DispatcherService,
+    matching: &MatchingService,
+    addr: SocketAddr,
+) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown_tx
+}
+
+/// Run a batching worker that records the task ids of every batch it gets.
+/// With `echo` it doubles each input's `n`; otherwise it returns no results.
+async fn start_batch_worker(
+    addr: SocketAddr,
+    queue: &str,
+    max_batch: usize,
+    max_wait: Duration,
+    echo: bool,
+) -> (Batches, JoinHandle<()>) {
+    let batches: Batches = Arc::default();
+    let seen = batches.clone();
+    let worker = ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&[queue])
+        .concurrency(5)
+        .batch_handler(max_batch, max_wait, move |batch| {
+            seen.lock()
+                .unwrap()
+                .push(batch.iter().map(|ctx| ctx.task_id.clone()).collect());
+            async move {
+                if !echo {
+                    return Vec::new();
+                }
+                batch
+                    .iter()
+                    .map(|ctx| {
+                        let input: serde_json::Value = ctx.input().map_err(|e| e.to_string())?;
+                        Ok(serde_json::json!({ "doubled": input["n"].as_i64().unwrap() * 2 }))
+                    })
+                    .collect()
+            }
+        })
+        .build()
+        .await
+        .unwrap();
+    let handle = tokio::spawn(async move {
+        let _ = worker.run().await;
+    });
+    // Let the server register it and start its match loop
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    (batches, handle)
+}
+
+async fn create_numbered_task(pool: &PgPool, queue: &str, n: i64) -> TaskRow {
+    let mut params = default_task_params(queue, "embed");
+    params.input = Some(serde_json::json!({ "n": n }));
+    create_test_task_full(pool, params).await
+}
+
+fn offer(matching: &MatchingService, task: &TaskRow) {
+    let mut envelope = envelope_for(task);
+    envelope.input = task.input.as_ref().map(|input| input.to_string());
+    let partition = PartitionId(task.partition_id);
+    if let Err(envelope) = matching.offer_task(&task.queue_name, partition, envelope) {
+        matching.buffer_task(&task.queue_name, partition, envelope);
+    }
+}
+
+async fn wait_for_status(pool: &PgPool, task_id: &str, status: &str) -> TaskRow {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let task = valka_db::queries::tasks::get_task(pool, task_id)
+            .await
+            .unwrap()
+            .unwrap();
+        if task.status == status {
+            return task;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "task {task_id} is {}, expected {status}",
+            task.status
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_batch_handler_splits_batches_and_maps_results(pool: PgPool) {
+    let queue = "batch-embed";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19889".parse().unwrap();
+    let shutdown = start_server(&pool, &dispatcher, &matching, addr).await;
+    let (batches, worker) =
+        start_batch_worker(addr, queue, 3, Duration::from_millis(300), true).await;
+
+    let mut tasks = Vec::new();
+    for n in 1..=5 {
+        let task = create_numbered_task(&pool, queue, n).await;
+        offer(&matching, &task);
+        tasks.push(task);
+    }
+
+    for (n, task) in (1..=5).zip(&tasks) {
+        let done = wait_for_status(&pool, &task.id, "COMPLETED").await;
+        assert_eq!(done.output, Some(serde_json::json!({ "doubled": n * 2 })));
+    }
+    // A full batch of three, then the remaining two once the window closed
+    let batches = batches.lock().unwrap().clone();
+    assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), [3, 2]);
+    let mut seen: Vec<_> = batches.concat();
+    let mut expected: Vec<_> = tasks.iter().map(|t| t.id.clone()).collect();
+    seen.sort();
+    expected.sort();
+    assert_eq!(seen, expected);
+
+    worker.abort();
+    let _ = shutdown.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_batch_handler_drops_cancelled_and_fails_mismatched_batch(pool: PgPool) {
+    let queue = "batch-cancel";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19890".parse().unwrap();
+    let shutdown = start_server(&pool, &dispatcher, &matching, addr).await;
+    let (batches, worker) = start_batch_worker(addr, queue, 3, Duration::from_secs(1), false).await;
+
+    let cancelled = create_numbered_task(&pool, queue, 1).await;
+    let kept = create_numbered_task(&pool, queue, 2).await;
+    offer(&matching, &cancelled);
+    offer(&matching, &kept);
+    wait_for_status(&pool, &cancelled.id, "RUNNING").await;
+    wait_for_status(&pool, &kept.id, "RUNNING").await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(dispatcher.cancel_task_on_worker(&cancelled.id).await);
+
+    // Only the remaining task reaches the handler, whose empty answer fails it
+    let failed = wait_for_status(&pool, &kept.id, "RETRY").await;
+    assert_eq!(*batches.lock().unwrap(), [vec![kept.id.clone()]]);
+    let runs = valka_db::queries::task_runs::get_runs_for_task(&pool, &failed.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        runs[0].error_message.as_deref(),
+        Some("Batch handler returned 0 results for 1 tasks")
+    );
+
+    worker.abort();
+    let _ = shutdown.send(true);
+}
@@ -0,0 +1,411 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use valka_core::{BlobStorageConfig, PartitionId, TaskId, WorkerId};
+use valka_db::queries::tasks;
+use valka_dispatcher::blob_store::blob_reference;
+use valka_dispatcher::{BlobStore, DispatcherService};
+use valka_matching::MatchingService;
+use valka_matching::partition::TaskEnvelope;
+
+use super::helpers::*;
+
+/// A dispatcher offloading payloads over 64 bytes to the store at `url`.
+fn make_blob_dispatcher(pool: PgPool, url: &str) -> (DispatcherService, MatchingService) {
+    let store = BlobStore::from_config(&BlobStorageConfig {
+        url: Some(url.to_string()),
+        offload_threshold_bytes: 64,
+        max_blob_bytes: 1024,
+        ..Default::default()
+    })
+    .unwrap()
+    .unwrap();
+    let (dispatcher, matching) = make_dispatcher(pool);
+    (dispatcher.with_blob_store(store), matching)
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+fn create(body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/api/v1/tasks")
+        .header("content-type", "application/json")
+        .body(Body::from(json_body(body)))
+        .unwrap()
+}
+
+/// Record a successful result carrying `output`.
+async fn complete(dispatcher: &DispatcherService, task_id: &str, run_id: &str, output: String) {
+    dispatcher
+        .handle_task_result(
+            &WorkerId::new(),
+            valka_proto::TaskResult {
+                task_id: task_id.to_string(),
+                task_run_id: run_id.to_string(),
+                success: true,
+                output,
+                ..Default::default()
+            },
+        )
+        .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_large_output_is_offloaded_and_resolved(pool: PgPool) {
+    let (dispatcher, matching) = make_blob_dispatcher(pool.clone(), "memory:///");
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+    let output = serde_json::json!({ "rows": "x".repeat(200) });
+
+    let (task, run) = create_running_task(&pool, "blob-output").await;
+    dispatcher
+        .handle_task_result(
+            &WorkerId::new(),
+            valka_proto::TaskResult {
+                task_id: task.id.clone(),
+                task_run_id: run.id.clone(),
+                success: true,
+                output: output.to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+
+    let stored = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(stored.status, "COMPLETED");
+    let reference = blob_reference(stored.output.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        reference,
+        format!("memory:///outputs/{}/{}.json", task.id, run.id)
+    );
+
+    // Left as the reference unless asked to resolve it
+    let uri = format!("/api/v1/tasks/{}", task.id);
+    let json = parse_response_json(app.clone().oneshot(get(&uri)).await.unwrap()).await;
+    assert_eq!(json["output"]["$blob"], reference);
+    let uri = format!("/api/v1/tasks/{}?resolve_output=true", task.id);
+    let json = parse_response_json(app.clone().oneshot(get(&uri)).await.unwrap()).await;
+    assert_eq!(json["output"], output);
+
+    let uri = format!("/api/v1/tasks/{}/output", task.id);
+    let resp = app.clone().oneshot(get(&uri)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await, output);
+
+    // A small output stays in the row
+    let (task, run) = create_running_task(&pool, "blob-output").await;
+    dispatcher
+        .handle_task_result(
+            &WorkerId::new(),
+            valka_proto::TaskResult {
+                task_id: task.id.clone(),
+                task_run_id: run.id.clone(),
+                success: true,
+                output: r#"{"ok":true}"#.to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+    let uri = format!("/api/v1/tasks/{}/output", task.id);
+    let resp = app.oneshot(get(&uri)).await.unwrap();
+    assert_eq!(
+        parse_response_json(resp).await,
+        serde_json::json!({ "ok": true })
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_large_input_is_offloaded_and_sent_to_worker(pool: PgPool) {
+    let queue = "blob-input";
+    let (dispatcher, matching) = make_blob_dispatcher(pool.clone(), "memory:///");
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    let input = serde_json::json!({ "document": "y".repeat(500) });
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(serde_json::json!({
+                    "queue_name": queue,
+                    "task_name": "t",
+                    "input": input,
+                }))))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let task_id = parse_response_json(resp).await["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let task = tasks::get_task(&pool, &task_id).await.unwrap().unwrap();
+    assert_eq!(
+        blob_reference(task.input.as_ref().unwrap()),
+        Some(format!("memory:///inputs/{task_id}.json").as_str())
+    );
+
+    // The worker gets the input back, not the reference
+    let (_worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let envelope = TaskEnvelope {
+        input: task.input.as_ref().map(|input| input.to_string()),
+        ..envelope_for(&task)
+    };
+    let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope);
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&assignment.input).unwrap(),
+        input
+    );
+
+    // Over max_blob_bytes the create is refused
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(serde_json::json!({
+                    "queue_name": queue,
+                    "task_name": "t",
+                    "input": { "document": "z".repeat(2000) },
+                }))))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "PAYLOAD_TOO_LARGE",
+        "over the 1024 byte blob_storage.max_blob_bytes limit",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_reference_shaped_input_is_refused(pool: PgPool) {
+    let (dispatcher, matching) = make_blob_dispatcher(pool.clone(), "memory:///");
+    let app = build_test_router_with(pool, matching, dispatcher);
+
+    let resp = app
+        .oneshot(create(serde_json::json!({
+            "queue_name": "blob-shape",
+            "task_name": "t",
+            "input": { "$blob": "memory:///inputs/someone-else.json" },
+        })))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "input must not be a {\"$blob\": ...} object",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_unresolvable_input_fails_task(pool: PgPool) {
+    let queue = "blob-unresolvable";
+    let (dispatcher, matching) = make_blob_dispatcher(pool.clone(), "memory:///");
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    let (_worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+
+    // Another task's offloaded input, then the task's own key with nothing there
+    let resp = app
+        .oneshot(create(serde_json::json!({
+            "queue_name": "blob-other",
+            "task_name": "t",
+            "input": { "secret": "s".repeat(200) },
+        })))
+        .await
+        .unwrap();
+    let other_id = parse_response_json(resp).await["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let own_id = TaskId::new().0;
+    for (id, reference) in [
+        (TaskId::new().0, format!("memory:///inputs/{other_id}.json")),
+        (own_id.clone(), format!("memory:///inputs/{own_id}.json")),
+    ] {
+        let task = create_test_task_full(
+            &pool,
+            tasks::CreateTaskParams {
+                id,
+                queue_name: queue.to_string(),
+                task_name: "t".to_string(),
+                partition_id: 0,
+                input: Some(serde_json::json!({ "$blob": reference })),
+                priority: 0,
+                max_retries: 3,
+                timeout_seconds: 300,
+                idempotency_key: None,
+                metadata: serde_json::json!({}),
+                scheduled_at: None,
+                deadline_at: None,
+                traceparent: None,
+                durability: Default::default(),
+                fifo_key: None,
+                callback_url: None,
+                retry_backoff: Default::default(),
+                required_labels: Default::default(),
+            },
+        )
+        .await;
+        let envelope = TaskEnvelope {
+            input: task.input.as_ref().map(|input| input.to_string()),
+            ..envelope_for(&task)
+        };
+        let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope);
+
+        // Failed for good rather than put back to PENDING to fail again
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+        let task = loop {
+            let task = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+            if task.status == "FAILED" {
+                break task;
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "task is {}",
+                task.status
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        };
+        let message = task.error_message.unwrap();
+        assert!(
+            message.contains("Task input could not be fetched"),
+            "{message}"
+        );
+    }
+    assert!(
+        rx.try_recv().is_err(),
+        "worker was sent an unresolvable task"
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_reference_shaped_output_reads_back_as_itself(pool: PgPool) {
+    let (dispatcher, matching) = make_blob_dispatcher(pool.clone(), "memory:///");
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+
+    // A real offloaded output, which other tasks must not be able to read
+    let (secret, run) = create_running_task(&pool, "blob-output").await;
+    let secret_output = serde_json::json!({ "secret": "s".repeat(200) });
+    complete(&dispatcher, &secret.id, &run.id, secret_output.to_string()).await;
+    let secret = tasks::get_task(&pool, &secret.id).await.unwrap().unwrap();
+    let secret_ref = blob_reference(secret.output.as_ref().unwrap())
+        .unwrap()
+        .to_string();
+
+    // A worker returning a reference-shaped value gets that value back
+    let (task, run) = create_running_task(&pool, "blob-output").await;
+    let shaped = serde_json::json!({ "$blob": secret_ref });
+    complete(&dispatcher, &task.id, &run.id, shaped.to_string()).await;
+    let uri = format!("/api/v1/tasks/{}?resolve_output=true", task.id);
+    let json = parse_response_json(app.clone().oneshot(get(&uri)).await.unwrap()).await;
+    assert_eq!(json["output"], shaped);
+    let uri = format!("/api/v1/tasks/{}/output", task.id);
+    let resp = app.clone().oneshot(get(&uri)).await.unwrap();
+    assert_eq!(parse_response_json(resp).await, shaped);
+
+    // A row already holding another task's reference is not followed
+    sqlx::query("UPDATE tasks SET output = $2 WHERE id = $1")
+        .bind(&task.id)
+        .bind(&shaped)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let uri = format!("/api/v1/tasks/{}?resolve_output=true", task.id);
+    let json = parse_response_json(app.clone().oneshot(get(&uri)).await.unwrap()).await;
+    assert_eq!(json["output"], shaped);
+    let uri = format!("/api/v1/tasks/{}/output", task.id);
+    let resp = app.oneshot(get(&uri)).await.unwrap();
+    assert_eq!(parse_response_json(resp).await, shaped);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_refused_or_repeated_create_writes_no_blob(pool: PgPool) {
+    let dir = std::env::temp_dir().join(format!("valka-blobs-{}", uuid::Uuid::now_v7()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("file://{}", dir.display());
+    let (dispatcher, matching) = make_blob_dispatcher(pool.clone(), &url);
+    let app = build_test_router_with(pool, matching, dispatcher);
+    let stored_inputs = || {
+        std::fs::read_dir(dir.join("inputs"))
+            .map(|entries| entries.count())
+            .unwrap_or(0)
+    };
+    let body = |extra: serde_json::Value| {
+        let mut body = serde_json::json!({
+            "queue_name": "blob-orphans",
+            "task_name": "t",
+            "input": { "document": "d".repeat(500) },
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        body
+    };
+
+    let resp = app
+        .clone()
+        .oneshot(create(body(
+            serde_json::json!({ "idempotency_key": "once" }),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(stored_inputs(), 1);
+
+    // The retry is answered with the first task and writes nothing
+    let resp = app
+        .clone()
+        .oneshot(create(body(
+            serde_json::json!({ "idempotency_key": "once" }),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await["deduplicated"], true);
+    assert_eq!(stored_inputs(), 1);
+
+    // Neither does a create refused by a later check
+    let resp = app
+        .oneshot(create(body(
+            serde_json::json!({ "callback_url": "ftp://example.com/hook" }),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(stored_inputs(), 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_reference_shaped_output_without_blob_store(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+
+    let (task, run) = create_running_task(&pool, "blob-none").await;
+    let shaped = serde_json::json!({ "$blob": "memory:///outputs/x/y.json" });
+    complete(&dispatcher, &task.id, &run.id, shaped.to_string()).await;
+
+    let uri = format!("/api/v1/tasks/{}?resolve_output=true", task.id);
+    let resp = app.clone().oneshot(get(&uri)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await["output"], shaped);
+    let uri = format!("/api/v1/tasks/{}/output", task.id);
+    let resp = app.oneshot(get(&uri)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await, shaped);
+}
@@ -0,0 +1,351 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::NodeId;
+use valka_dispatcher::DispatcherService;
+use valka_sdk::task::{EventFilter, RetryBackoff, TaskFilter, TaskOrder, TaskStatus};
+use valka_sdk::{CreateTaskOptions, SdkError, TaskError, ValkaClient, ValkaWorker, WaitError};
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+/// Serve gRPC on `addr` until the returned sender drops.
+async fn start_server(pool: &PgPool, addr: &str) -> (DispatcherService, watch::Sender<bool>) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr.parse::<SocketAddr>().unwrap(),
+        pool.clone(),
+        dispatcher.clone(),
+        matching,
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    (dispatcher, shutdown_tx)
+}
+
+async fn connect(addr: &str) -> ValkaClient {
+    ValkaClient::connect(&format!("http://{addr}"))
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_client_task_crud_is_typed(pool: PgPool) {
+    let (_dispatcher, _shutdown) = start_server(&pool, "127.0.0.1:19904").await;
+    let mut client = connect("127.0.0.1:19904").await;
+
+    let created = client
+        .create_task(
+            CreateTaskOptions::new("client-crud", "resize")
+                .input(serde_json::json!({ "width": 640 }))
+                .metadata(serde_json::json!({ "source": "upload" }))
+                .priority(7)
+                .max_retries(1)
+                .timeout(Duration::from_millis(1500))
+                .idempotency_key("resize-1")
+                .deadline_in(Duration::from_secs(600))
+                .scheduled_at(chrono::Utc::now() + chrono::Duration::hours(1)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(created.status, TaskStatus::Pending);
+    assert_eq!(created.input, Some(serde_json::json!({ "width": 640 })));
+    assert_eq!(
+        created.metadata,
+        Some(serde_json::json!({ "source": "upload" }))
+    );
+    assert_eq!(created.priority, 7);
+    assert_eq!(created.max_retries, 1);
+    assert_eq!(created.timeout_seconds, 2);
+    assert_eq!(created.idempotency_key.as_deref(), Some("resize-1"));
+    assert!(created.scheduled_at.is_some());
+    assert!(created.deadline.is_some());
+    assert_eq!(created.output, None);
+    assert!(!created.is_terminal());
+
+    let fetched = client.get_task(&created.id).await.unwrap();
+    assert_eq!(fetched.id, created.id);
+    assert_eq!(fetched.input, created.input);
+    assert_eq!(fetched.retry_backoff, None);
+
+    let backoff = RetryBackoff {
+        base_seconds: 1,
+        max_seconds: 30,
+        multiplier: 1.5,
+        jitter: true,
+    };
+    let custom = client
+        .create_task(CreateTaskOptions::new("client-backoff", "poll").retry_backoff(backoff))
+        .await
+        .unwrap();
+    assert_eq!(custom.retry_backoff, Some(backoff));
+    let err = client
+        .create_task(
+            CreateTaskOptions::new("client-backoff", "poll").retry_backoff(RetryBackoff {
+                multiplier: 0.5,
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, SdkError::Grpc(s) if s.code() == tonic::Code::InvalidArgument),
+        "{err:?}"
+    );
+
+    for n in 0..2 {
+        client
+            .create_task(CreateTaskOptions::new("client-crud", &format!("thumb-{n}")))
+            .await
+            .unwrap();
+    }
+    let first = client
+        .list_tasks(
+            TaskFilter::new()
+                .queue("client-crud")
+                .task_name_prefix("thumb-")
+                .order(TaskOrder::CreatedAsc)
+                .page_size(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(first.tasks.len(), 1);
+    assert_eq!(first.tasks[0].task_name, "thumb-0");
+    let token = first.next_page_token.expect("more tasks to list");
+    let second = client
+        .list_tasks(
+            TaskFilter::new()
+                .queue("client-crud")
+                .task_name_prefix("thumb-")
+                .order(TaskOrder::CreatedAsc)
+                .page_size(1)
+                .page_token(&token),
+        )
+        .await
+        .unwrap();
+    assert_eq!(second.tasks[0].task_name, "thumb-1");
+
+    let cancelled = client.cancel_task(&created.id).await.unwrap();
+    assert_eq!(cancelled.status, TaskStatus::Cancelled);
+    assert!(cancelled.is_terminal());
+    let pending = client
+        .list_tasks(
+            TaskFilter::new()
+                .queue("client-crud")
+                .status(TaskStatus::Pending),
+        )
+        .await
+        .unwrap();
+    assert_eq!(pending.tasks.len(), 2);
+    assert_eq!(pending.next_page_token, None);
+
+    let err = client.get_task("missing").await.unwrap_err();
+    assert!(
+        matches!(&err, SdkError::Grpc(s) if s.code() == tonic::Code::NotFound),
+        "{err:?}"
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_client_send_and_list_signals(pool: PgPool) {
+    let (_dispatcher, _shutdown) = start_server(&pool, "127.0.0.1:19913").await;
+    let mut client = connect("127.0.0.1:19913").await;
+
+    let task = client
+        .create_task(CreateTaskOptions::new("client-signals", "approve"))
+        .await
+        .unwrap();
+    // No worker runs the task, so the signal waits as PENDING
+    let (signal_id, delivered) = client
+        .send_signal(
+            &task.id,
+            "approve",
+            Some(serde_json::json!({ "by": "ops" })),
+        )
+        .await
+        .unwrap();
+    assert!(!delivered);
+
+    let signals = client.list_signals(&task.id, None).await.unwrap();
+    assert_eq!(signals.len(), 1);
+    assert_eq!(signals[0].id, signal_id);
+    assert_eq!(signals[0].task_id, task.id);
+    assert_eq!(signals[0].signal_name, "approve");
+    assert_eq!(signals[0].payload, Some(serde_json::json!({ "by": "ops" })));
+    assert_eq!(signals[0].status, "PENDING");
+    assert_eq!(signals[0].delivered_at, None);
+    assert!(
+        client
+            .list_signals(&task.id, Some("DELIVERED"))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let err = client
+        .list_signals(&task.id, Some("LOST"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, SdkError::Grpc(s) if s.code() == tonic::Code::InvalidArgument),
+        "{err:?}"
+    );
+
+    client.cancel_task(&task.id).await.unwrap();
+    let err = client
+        .send_signal(&task.id, "approve", None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, SdkError::Grpc(s) if s.code() == tonic::Code::FailedPrecondition),
+        "{err:?}"
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_client_subscribe_events_filters(pool: PgPool) {
+    let (_dispatcher, _shutdown) = start_server(&pool, "127.0.0.1:19905").await;
+    let mut client = connect("127.0.0.1:19905").await;
+
+    let events = client
+        .subscribe_events(EventFilter::new().queue("client-events"))
+        .await
+        .unwrap();
+    let mut events = std::pin::pin!(events);
+    client
+        .create_task(CreateTaskOptions::new("client-other", "skipped"))
+        .await
+        .unwrap();
+    let task = client
+        .create_task(CreateTaskOptions::new("client-events", "seen"))
+        .await
+        .unwrap();
+
+    let event = tokio::time::timeout(Duration::from_secs(2), events.next())
+        .await
+        .expect("no event")
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.task_id, task.id);
+    assert_eq!(event.queue_name, "client-events");
+    assert_eq!(event.new_status, TaskStatus::Pending as i32);
+
+    // Task and status filters apply on top of the queue
+    let events = client
+        .subscribe_events(
+            EventFilter::new()
+                .task(&task.id)
+                .status(TaskStatus::Cancelled),
+        )
+        .await
+        .unwrap();
+    let mut events = std::pin::pin!(events);
+    let other = client
+        .create_task(CreateTaskOptions::new("client-events", "other"))
+        .await
+        .unwrap();
+    client.cancel_task(&other.id).await.unwrap();
+    client.cancel_task(&task.id).await.unwrap();
+    let event = tokio::time::timeout(Duration::from_secs(2), events.next())
+        .await
+        .expect("no event")
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.task_id, task.id);
+    assert_eq!(event.new_status, TaskStatus::Cancelled as i32);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_client_wait_for_completion(pool: PgPool) {
+    let (dispatcher, _shutdown) = start_server(&pool, "127.0.0.1:19906").await;
+    let worker = ValkaWorker::builder()
+        .server_addr("http://127.0.0.1:19906")
+        .queues(&["client-wait"])
+        .register("double", |_ctx, n: i64| async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok(n * 2)
+        })
+        .register("reject", |_ctx, _: serde_json::Value| async move {
+            Err::<(), _>(TaskError::fatal("not allowed"))
+        })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(worker.run());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while dispatcher.workers().is_empty() {
+        assert!(Instant::now() < deadline, "worker never registered");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let mut client = connect("127.0.0.1:19906").await;
+
+    // Created while the worker waits, so it is dispatched right away
+    let task = client
+        .create_task(CreateTaskOptions::new("client-wait", "double").input(serde_json::json!(21)))
+        .await
+        .unwrap();
+    let started = Instant::now();
+    let done = client
+        .wait_for_completion(&task.id, Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert_eq!(done.status, TaskStatus::Completed);
+    assert_eq!(done.output, Some(serde_json::json!(42)));
+    // Woken by the COMPLETED event, not the periodic re-read
+    assert!(
+        started.elapsed() < Duration::from_secs(3),
+        "{:?}",
+        started.elapsed()
+    );
+
+    // Already finished: returns straight away
+    let again = client
+        .wait_for_completion(&task.id, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(again.id, task.id);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let rejected = client
+        .create_task(CreateTaskOptions::new("client-wait", "reject").max_retries(1))
+        .await
+        .unwrap();
+    let err = client
+        .wait_for_completion(&rejected.id, Duration::from_secs(10))
+        .await
+        .unwrap_err();
+    let WaitError::Unsuccessful(task) = err else {
+        panic!("expected an unsuccessful task, got {err:?}");
+    };
+    assert_eq!(task.status, TaskStatus::Failed);
+    assert_eq!(task.error_message.as_deref(), Some("not allowed"));
+
+    let idle = client
+        .create_task(CreateTaskOptions::new("client-idle", "never"))
+        .await
+        .unwrap();
+    let err = client
+        .wait_for_completion(&idle.id, Duration::from_millis(300))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, WaitError::Timeout(id) if *id == idle.id),
+        "{err:?}"
+    );
+}
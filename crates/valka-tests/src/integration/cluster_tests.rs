@@ -8,13 +8,19 @@ use tokio::sync::{broadcast, mpsc, watch};
 use tokio::task::JoinHandle;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::Channel;
+use tracing::Instrument;
 
-use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_cluster::forwarder::CircuitState;
+use valka_cluster::{ClusterAuth, ClusterManager, NodeForwarder};
 use valka_core::{GossipConfig, MatchingConfig, NodeId, TaskId, partition_for_task};
 use valka_db::queries::tasks::CreateTaskParams;
 use valka_dispatcher::DispatcherService;
 use valka_matching::MatchingService;
 use valka_proto::*;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::{capture_spans, finished_span};
 
 // ---------------------------------------------------------------------------
 // Test infrastructure
@@ -43,6 +49,17 @@ impl TestNode {
         seed_gossip_ports: Vec<u16>,
         cluster_id: &str,
         num_partitions: i32,
+    ) -> Self {
+        let gossip_cfg = gossip_config(gossip_port, seed_gossip_ports, cluster_id);
+        Self::start_with_gossip(pool, node_name, grpc_port, gossip_cfg, num_partitions).await
+    }
+
+    async fn start_with_gossip(
+        pool: PgPool,
+        node_name: &str,
+        grpc_port: u16,
+        gossip_cfg: GossipConfig,
+        num_partitions: i32,
     ) -> Self {
         let node_id = NodeId(node_name.to_string());
         let matching = MatchingService::new(MatchingConfig {
@@ -52,18 +69,7 @@ impl TestNode {
         let (event_tx, _) = broadcast::channel::<TaskEvent>(128);
         let (log_tx, _log_rx) = mpsc::channel::<LogEntry>(128);
 
-        let dispatcher = DispatcherService::new(
-            matching.clone(),
-            pool.clone(),
-            node_id.clone(),
-            event_tx.clone(),
-            log_tx.clone(),
-        );
-
-        let forwarder = NodeForwarder::new();
-
         let grpc_addr: SocketAddr = format!("127.0.0.1:{grpc_port}").parse().unwrap();
-        let gossip_cfg = gossip_config(gossip_port, seed_gossip_ports, cluster_id);
 
         let cluster = Arc::new(
             ClusterManager::new_clustered(
@@ -75,6 +81,16 @@ impl TestNode {
             .await
             .expect("Failed to create ClusterManager"),
         );
+        let forwarder = NodeForwarder::new().with_auth(cluster.auth().clone());
+
+        let dispatcher = DispatcherService::new(
+            matching.clone(),
+            pool.clone(),
+            node_id.clone(),
+            event_tx.clone(),
+            log_tx.clone(),
+        )
+        .with_partition_ownership(cluster.partition_ownership());
 
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
@@ -98,6 +114,8 @@ impl TestNode {
                 srv_cluster,
                 srv_forwarder,
                 log_tx,
+                AdminAuth::default(),
+                ApiAuth::default(),
                 shutdown_rx,
             )
             .await
@@ -139,6 +157,8 @@ fn gossip_config(listen_port: u16, seeds: Vec<u16>, cluster_id: &str) -> GossipC
             .collect(),
         cluster_id: cluster_id.to_string(),
         advertise_addr: None,
+        secret: None,
+        ..GossipConfig::default()
     }
 }
 
@@ -210,6 +230,13 @@ async fn insert_task(pool: &PgPool, task_id: &str, queue: &str, partition_id: i3
             idempotency_key: None,
             metadata: serde_json::json!({}),
             scheduled_at: None,
+            deadline_at: None,
+            traceparent: None,
+            durability: Default::default(),
+            fifo_key: None,
+            callback_url: None,
+            retry_backoff: Default::default(),
+            required_labels: Default::default(),
         },
     )
     .await
@@ -252,6 +279,12 @@ async fn connect_mock_worker(
             queues: queues.iter().map(|s| s.to_string()).collect(),
             concurrency,
             metadata: String::new(),
+            version: String::new(),
+            labels: Default::default(),
+            queue_concurrency: Default::default(),
+            protocol_version: 0,
+            capabilities: vec![],
+            heartbeat_interval_ms: 0,
         })),
     };
     tx.send(hello).await.expect("Failed to send WorkerHello");
@@ -351,6 +384,109 @@ async fn test_cluster_partition_ownership_split(pool: PgPool) {
     node_b.shutdown().await;
 }
 
+/// With a cluster secret, only peers holding the same secret may call the internal RPCs.
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_forward_task_requires_cluster_token(pool: PgPool) {
+    let queue = "sealed-queue";
+    let gossip_cfg = GossipConfig {
+        secret: Some("s3cret".to_string()),
+        ..gossip_config(18871, vec![], "test-sealed")
+    };
+    let node = TestNode::start_with_gossip(pool.clone(), "sealed-a", 19891, gossip_cfg, 4).await;
+    let addr = node.grpc_addr.to_string();
+    let (task_id, partition_id) = find_task_for_partition(queue, &[0, 1, 2, 3], 4);
+    insert_task(&pool, &task_id, queue, partition_id).await;
+
+    let peer = NodeForwarder::new().with_auth(ClusterAuth::new(Some("s3cret"), "test-sealed"));
+    let accepted = peer
+        .forward_task(&addr, &task_id, queue, partition_id)
+        .await
+        .expect("peer with the secret should be let in");
+    assert!(!accepted);
+
+    for rogue in [
+        NodeForwarder::new().with_auth(ClusterAuth::new(Some("guess"), "test-sealed")),
+        NodeForwarder::new(),
+    ] {
+        let err = rogue
+            .forward_task(&addr, &task_id, queue, partition_id)
+            .await
+            .expect_err("forward without the cluster token should be rejected");
+        let status = err.downcast_ref::<tonic::Status>().expect("gRPC status");
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    node.shutdown().await;
+}
+
+/// A node that comes back is forwarded to again once the circuit's probe succeeds.
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_circuit_breaker_probe_success_closes(pool: PgPool) {
+    let queue = "probe-queue";
+    let addr = "127.0.0.1:19917";
+    let forwarder = NodeForwarder::new().with_recovery_timeout(Duration::from_millis(200));
+
+    // Nothing listens yet
+    for _ in 0..2 {
+        let _ = forwarder.forward_task(addr, "missing", queue, 0).await;
+    }
+    assert_eq!(forwarder.get_circuit_state(addr).await, CircuitState::Open);
+
+    // Starting the node outlasts the cool-down
+    let node = TestNode::start(
+        pool.clone(), "probe-a", 18892, 19917, vec![], "test-probe", 4,
+    )
+    .await;
+    let (task_id, partition_id) = find_task_for_partition(queue, &[0, 1, 2, 3], 4);
+    insert_task(&pool, &task_id, queue, partition_id).await;
+
+    let accepted = forwarder
+        .forward_task(addr, &task_id, queue, partition_id)
+        .await
+        .expect("the probe should reach the restarted node");
+    assert!(!accepted);
+    assert_eq!(
+        forwarder.get_circuit_state(addr).await,
+        CircuitState::Closed
+    );
+    assert_eq!(forwarder.stats().await.open_circuits, 0);
+
+    node.shutdown().await;
+}
+
+/// Concurrent forwards to one node are coalesced into a few `ForwardTasks` calls.
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_batched_forwards_coalesce_rpcs(pool: PgPool) {
+    let queue = "batched-queue";
+    let node = TestNode::start(
+        pool.clone(), "batch-a", 18895, 19920, vec![], "test-batch", 4,
+    )
+    .await;
+    let addr = node.grpc_addr.to_string();
+
+    let mut task_ids = Vec::new();
+    for i in 0..1000 {
+        let task_id = TaskId::new().0;
+        insert_task(&pool, &task_id, queue, i % 4).await;
+        task_ids.push((task_id, i % 4));
+    }
+
+    let forwarder = NodeForwarder::new();
+    let results = futures::future::join_all(task_ids.iter().map(|(task_id, partition_id)| {
+        forwarder.forward_task_batched(&addr, task_id, queue, *partition_id)
+    }))
+    .await;
+
+    for result in results {
+        // No worker is waiting, so each one is left for the readers
+        assert!(!result.expect("batched forward failed"));
+    }
+    let rpcs = forwarder.stats().await.forward_rpcs;
+    assert!((10..=20).contains(&rpcs), "1000 tasks took {rpcs} RPCs");
+
+    node.shutdown().await;
+}
+
 /// Forwarding a task to the owner node when no worker is connected returns accepted=false.
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
 async fn test_forward_task_no_worker_returns_not_accepted(pool: PgPool) {
@@ -445,6 +581,9 @@ async fn test_forward_task_with_waiting_worker(pool: PgPool) {
     assert_eq!(assignment.task_id, task_id);
     assert_eq!(assignment.queue_name, queue);
     assert_eq!(assignment.task_name, "cluster-test-task");
+    // The forwarded envelope is rebuilt from the row, timestamps included
+    assert!(assignment.created_at_ms > 0);
+    assert_eq!(assignment.scheduled_at_ms, 0);
 
     node_a.shutdown().await;
     node_b.shutdown().await;
@@ -497,6 +636,10 @@ async fn test_cross_node_task_completion(pool: PgPool) {
             retryable: false,
             output: r#"{"result":"done"}"#.to_string(),
             error_message: String::new(),
+            traceparent: String::new(),
+            rejected: false,
+            cancelled: false,
+            retry_after_ms: 0,
         })),
     };
     worker_tx
@@ -529,6 +672,90 @@ async fn test_cross_node_task_completion(pool: PgPool) {
     node_b.shutdown().await;
 }
 
+/// Signals and cancellations sent to a node the worker isn't connected to
+/// reach it through the node holding its run.
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_cancel_and_signal_reach_worker_on_other_node(pool: PgPool) {
+    let num_partitions = 8;
+    let queue = "relay-queue";
+
+    let node_a = TestNode::start(
+        pool.clone(), "rl-a", 18893, 19918, vec![18894], "test-rl", num_partitions,
+    )
+    .await;
+    let node_b = TestNode::start(
+        pool.clone(), "rl-b", 18894, 19919, vec![18893], "test-rl", num_partitions,
+    )
+    .await;
+
+    wait_for_members(&node_a.cluster, 2, 10).await;
+    wait_for_members(&node_b.cluster, 2, 10).await;
+
+    let (_worker_tx, mut worker_stream, _worker_id) =
+        connect_mock_worker(&node_b.grpc_addr, &[queue], 1).await;
+
+    let b_owns = owned_partitions(&node_b.cluster, queue, num_partitions).await;
+    let (task_id, partition_id) = find_task_for_partition(queue, &b_owns, num_partitions);
+    insert_task(&pool, &task_id, queue, partition_id).await;
+    let accepted = node_a
+        .forwarder
+        .forward_task(&node_b.grpc_addr.to_string(), &task_id, queue, partition_id)
+        .await
+        .expect("forward_task failed");
+    assert!(accepted);
+    wait_for_task_assignment(&mut worker_stream, 5).await;
+
+    let channel = Channel::from_shared(format!("http://{}", node_a.grpc_addr))
+        .unwrap()
+        .connect()
+        .await
+        .expect("Failed to connect gRPC channel to Node A");
+    let mut api_client = api_service_client::ApiServiceClient::new(channel);
+
+    let signal = api_client
+        .send_signal(SendSignalRequest {
+            task_id: task_id.clone(),
+            signal_name: "approve".to_string(),
+            payload: String::new(),
+        })
+        .await
+        .expect("send_signal failed")
+        .into_inner();
+    assert!(signal.delivered, "Node A should deliver through Node B");
+
+    api_client
+        .cancel_task(CancelTaskRequest {
+            task_id: task_id.clone(),
+        })
+        .await
+        .expect("cancel_task failed");
+
+    let mut signalled = false;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let resp = tokio::time::timeout_at(deadline, worker_stream.next())
+            .await
+            .expect("Timed out waiting for the cancellation")
+            .expect("Stream closed")
+            .expect("Stream error");
+        match resp.response {
+            Some(worker_response::Response::TaskSignal(s)) => {
+                assert_eq!(s.signal_id, signal.signal_id);
+                signalled = true;
+            }
+            Some(worker_response::Response::TaskCancellation(c)) => {
+                assert_eq!(c.task_id, task_id);
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert!(signalled, "The signal should arrive before the cancellation");
+
+    node_a.shutdown().await;
+    node_b.shutdown().await;
+}
+
 /// With 3 nodes, verify tasks get routed to the correct owner.
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
 async fn test_three_node_task_routing(pool: PgPool) {
@@ -697,3 +924,239 @@ async fn test_grpc_create_task_auto_forwards(pool: PgPool) {
     node_a.shutdown().await;
     node_b.shutdown().await;
 }
+
+/// A task forwarded to its owner stays in the originating node's trace through
+/// dispatch, the worker's handler span and the result.
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_cross_node_forward_stays_in_one_trace(pool: PgPool) {
+    let (exporter, provider, _guard) = capture_spans();
+    let num_partitions = 8;
+    let queue = "traced-queue";
+
+    let node_a = TestNode::start(
+        pool.clone(), "tr-a", 18861, 19861, vec![18862], "test-tr", num_partitions,
+    )
+    .await;
+    let node_b = TestNode::start(
+        pool.clone(), "tr-b", 18862, 19862, vec![18861], "test-tr", num_partitions,
+    )
+    .await;
+
+    wait_for_members(&node_a.cluster, 2, 10).await;
+    wait_for_members(&node_b.cluster, 2, 10).await;
+
+    let (worker_tx, mut worker_stream, _worker_id) =
+        connect_mock_worker(&node_b.grpc_addr, &[queue], 1).await;
+
+    let b_owns = owned_partitions(&node_b.cluster, queue, num_partitions).await;
+    let (task_id, partition_id) = find_task_for_partition(queue, &b_owns, num_partitions);
+    insert_task(&pool, &task_id, queue, partition_id).await;
+
+    let accepted = node_a
+        .forwarder
+        .forward_task(&node_b.grpc_addr.to_string(), &task_id, queue, partition_id)
+        .instrument(tracing::info_span!("create_task"))
+        .await
+        .expect("forward_task failed");
+    assert!(accepted, "Task should be accepted by sync match");
+
+    // Mock worker: handler span parented the way the SDK does it
+    let assignment = wait_for_task_assignment(&mut worker_stream, 5).await;
+    assert!(!assignment.traceparent.is_empty());
+    let span = valka_sdk::telemetry::task_span(&assignment);
+    let traceparent = valka_sdk::telemetry::traceparent(&span);
+    drop(span);
+
+    let result_msg = WorkerRequest {
+        request: Some(worker_request::Request::TaskResult(TaskResult {
+            task_id: assignment.task_id.clone(),
+            task_run_id: assignment.task_run_id.clone(),
+            success: true,
+            retryable: false,
+            output: "{}".to_string(),
+            error_message: String::new(),
+            traceparent,
+            rejected: false,
+            cancelled: false,
+            retry_after_ms: 0,
+        })),
+    };
+    worker_tx
+        .send(result_msg)
+        .await
+        .expect("Failed to send TaskResult");
+
+    let names = [
+        "create_task",
+        "forward_task",
+        "accept_forwarded_task",
+        "dispatch_task",
+        "handle_task",
+        "task_result",
+    ];
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    let spans = loop {
+        let _ = provider.force_flush();
+        let spans = exporter.get_finished_spans().unwrap();
+        if names.iter().all(|n| spans.iter().any(|s| s.name == *n)) {
+            break spans;
+        }
+        if tokio::time::Instant::now() > deadline {
+            panic!("Not all spans finished within 5s");
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+
+    let trace_id = finished_span(&spans, "create_task").span_context.trace_id();
+    for name in names {
+        assert_eq!(
+            finished_span(&spans, name).span_context.trace_id(),
+            trace_id,
+            "{name} should share the originating trace"
+        );
+    }
+
+    node_a.shutdown().await;
+    node_b.shutdown().await;
+}
+
+/// A worker only waits on partitions its node owns, and follows a rebalance.
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_match_loop_registers_on_owned_partitions(pool: PgPool) {
+    let num_partitions = 8;
+    let queue = "owned-match-queue";
+
+    let node_a = TestNode::start(
+        pool.clone(), "match-a", 18881, 19907, vec![18882], "test-match", num_partitions,
+    )
+    .await;
+    let (_tx, _inbound, _worker_id) = connect_mock_worker(&node_a.grpc_addr, &[queue], 4).await;
+    let node_b = TestNode::start(
+        pool, "match-b", 18882, 19908, vec![18881], "test-match", num_partitions,
+    )
+    .await;
+
+    wait_for_members(&node_a.cluster, 2, 10).await;
+    let a_owns = owned_partitions(&node_a.cluster, queue, num_partitions).await;
+    assert!(a_owns.len() < num_partitions as usize, "Node A still owns every partition");
+
+    // Registered everywhere while alone, then only on what it kept
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while node_a.matching.stats().waiting_workers != a_owns.len() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "{} waiting slots on node A, expected {}",
+            node_a.matching.stats().waiting_workers,
+            a_owns.len()
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    for pid in 0..num_partitions {
+        let waiting = node_a
+            .matching
+            .get_partition(queue, valka_core::PartitionId(pid))
+            .map_or(0, |p| p.waiting_workers.len());
+        assert_eq!(waiting, usize::from(a_owns.contains(&pid)), "partition {pid}");
+    }
+
+    node_a.shutdown().await;
+    node_b.shutdown().await;
+}
+
+/// Tasks buffered for partitions a node loses go back to PENDING as soon as
+/// its readers stop, not when the reaper finds them.
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rebalance_hands_back_buffered_tasks(pool: PgPool) {
+    let num_partitions = 8;
+    let queue = "handoff-queue";
+
+    let node_a = TestNode::start(
+        pool.clone(), "handoff-a", 18896, 19909, vec![18897], "test-handoff", num_partitions,
+    )
+    .await;
+    let (readers_tx, readers_rx) = watch::channel(false);
+    tokio::spawn(valka_server::server::run_task_reader_manager(
+        pool.clone(),
+        node_a.matching.clone(),
+        node_a.dispatcher.clone(),
+        watch::channel(MatchingConfig {
+            num_partitions,
+            task_reader_poll_busy_ms: 20,
+            task_reader_poll_idle_ms: 20,
+            queue_discovery_fallback_ms: 100,
+            ..MatchingConfig::default()
+        })
+        .1,
+        node_a.cluster.clone(),
+        readers_rx,
+    ));
+
+    // A backlog across every partition, read into node A's buffers with no
+    // worker to take it
+    let all: Vec<i32> = (0..num_partitions).collect();
+    let mut tasks = Vec::new();
+    for _ in 0..40 {
+        let (task_id, pid) = find_task_for_partition(queue, &all, num_partitions);
+        insert_task(&pool, &task_id, queue, pid).await;
+        tasks.push((task_id, pid));
+    }
+    let status_of = |pool: PgPool, status: &'static str| async move {
+        sqlx::query_scalar::<_, String>(
+            "SELECT id FROM tasks WHERE queue_name = $1 AND status = $2 ORDER BY id",
+        )
+        .bind(queue)
+        .bind(status)
+        .fetch_all(&pool)
+        .await
+        .unwrap()
+    };
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while status_of(pool.clone(), "DISPATCHING").await.len() < tasks.len() {
+        assert!(tokio::time::Instant::now() < deadline, "backlog never buffered on node A");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let node_b = TestNode::start(
+        pool.clone(), "handoff-b", 18897, 19910, vec![18896], "test-handoff", num_partitions,
+    )
+    .await;
+    wait_for_members(&node_a.cluster, 2, 10).await;
+    let a_owns = owned_partitions(&node_a.cluster, queue, num_partitions).await;
+    assert!(a_owns.len() < num_partitions as usize, "Node A still owns every partition");
+
+    // Node B runs no readers, so what node A hands back stays PENDING
+    let mut handed_back: Vec<String> = tasks
+        .iter()
+        .filter(|(_, pid)| !a_owns.contains(pid))
+        .map(|(task_id, _)| task_id.clone())
+        .collect();
+    handed_back.sort();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let pending = status_of(pool.clone(), "PENDING").await;
+        if pending == handed_back {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "{} tasks PENDING, expected {}",
+            pending.len(),
+            handed_back.len()
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    for pid in 0..num_partitions {
+        if a_owns.contains(&pid) {
+            continue;
+        }
+        let buffered = node_a
+            .matching
+            .get_partition(queue, valka_core::PartitionId(pid))
+            .map_or(0, |p| p.pending_tasks.len());
+        assert_eq!(buffered, 0, "partition {pid} still buffered on node A");
+    }
+
+    let _ = readers_tx.send(true);
+    node_a.shutdown().await;
+    node_b.shutdown().await;
+}
@@ -0,0 +1,140 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::sync::{broadcast, mpsc, watch};
+use tower::ServiceExt;
+use valka_core::ServerConfig;
+use valka_server::admin::AdminAuth;
+use valka_server::reload::ConfigReloader;
+
+use super::helpers::*;
+
+fn reload_request(token: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method("POST")
+        .uri("/api/v1/admin/reload-config");
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {token}"));
+    }
+    builder.body(Body::empty()).unwrap()
+}
+
+/// A reloader whose config "file" is the returned handle.
+fn reloader_over(running: ServerConfig) -> (ConfigReloader, Arc<Mutex<ServerConfig>>) {
+    let file = Arc::new(Mutex::new(running.clone()));
+    let source = file.clone();
+    let reloader = ConfigReloader::new(running, move || Ok(source.lock().unwrap().clone()));
+    (reloader, file)
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_reload_config_endpoint(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (reloader, file) = reloader_over(ServerConfig::default());
+    let app = build_test_router_reloader(
+        pool,
+        matching,
+        dispatcher,
+        AdminAuth::new(Some("s3cret".to_string())),
+        reloader.clone(),
+    );
+
+    let resp = app.clone().oneshot(reload_request(None)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    {
+        let mut file = file.lock().unwrap();
+        file.scheduler.dlq_check_interval_secs = 2;
+        file.http_addr = "0.0.0.0:9090".to_string();
+    }
+    let resp = app
+        .clone()
+        .oneshot(reload_request(Some("s3cret")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "applied": ["scheduler.dlq_check_interval_secs"],
+            "rejected": ["http_addr"],
+        })
+    );
+    assert_eq!(reloader.running().scheduler.dlq_check_interval_secs, 2);
+
+    // An invalid file is refused whole
+    file.lock().unwrap().log_ingester.batch_size = 0;
+    let resp = app.oneshot(reload_request(Some("s3cret"))).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = parse_response_json(resp).await;
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("log_ingester.batch_size"),
+        "{body}"
+    );
+    assert_eq!(
+        reloader.running().log_ingester.batch_size,
+        ServerConfig::default().log_ingester.batch_size
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_log_ingester_follows_reloaded_flush_interval(pool: PgPool) {
+    // Neither a full batch nor the flush interval comes around during the test
+    let mut running = ServerConfig::default();
+    running.log_ingester.flush_interval_ms = 3_600_000;
+    let (reloader, file) = reloader_over(running);
+
+    let (log_tx, log_rx) = mpsc::channel(16);
+    let (log_stream, _) = broadcast::channel(16);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::server::run_log_ingester(
+        pool.clone(),
+        reloader.log_ingester(),
+        log_rx,
+        log_stream,
+        shutdown_rx,
+    ));
+    // Let the first, immediate tick pass with nothing buffered
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    log_tx
+        .send(valka_proto::LogEntry {
+            task_run_id: "run-reload".to_string(),
+            timestamp_ms: 1,
+            level: 2,
+            message: "hello".to_string(),
+            metadata: String::new(),
+        })
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let logs = valka_db::queries::task_logs::get_logs_for_run(&pool, "run-reload", 10, None)
+        .await
+        .unwrap();
+    assert!(logs.is_empty());
+
+    file.lock().unwrap().log_ingester.flush_interval_ms = 20;
+    reloader.reload().unwrap();
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    loop {
+        let logs = valka_db::queries::task_logs::get_logs_for_run(&pool, "run-reload", 10, None)
+            .await
+            .unwrap();
+        if logs.len() == 1 {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "entry not flushed after the flush interval was shortened"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    shutdown_tx.send(true).unwrap();
+}
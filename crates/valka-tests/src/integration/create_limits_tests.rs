@@ -0,0 +1,186 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::sync::watch;
+use tonic::transport::Channel;
+use tower::ServiceExt;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{LimitsConfig, NodeId};
+use valka_proto::CreateTaskRequest;
+use valka_proto::api_service_client::ApiServiceClient;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+fn create_req(body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/api/v1/tasks")
+        .header("content-type", "application/json")
+        .body(Body::from(json_body(body)))
+        .unwrap()
+}
+
+fn small_limits() -> LimitsConfig {
+    LimitsConfig {
+        max_name_len: 8,
+        max_payload_bytes: 32,
+        ..LimitsConfig::default()
+    }
+}
+
+async fn count_tasks(pool: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM tasks")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_create_task_rejects_out_of_bounds_fields(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+
+    let resp = app
+        .clone()
+        .oneshot(create_req(serde_json::json!({
+            "queue_name": "",
+            "task_name": "send email",
+            "priority": i32::MAX,
+            "timeout_seconds": -1,
+        })))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "queue_name is required; \
+         task_name may only contain ASCII letters, digits, '.', '_', '-' and ':'; \
+         priority must be between -1000 and 1000, got 2147483647; \
+         timeout_seconds must be between 0 and 604800, got -1",
+    )
+    .await;
+
+    let resp = app
+        .oneshot(create_req(serde_json::json!({
+            "queue_name": "q",
+            "task_name": "t",
+            "input": {"blob": "x".repeat(256 * 1024)},
+        })))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "input is 262155 bytes, over the 262144 byte limit",
+    )
+    .await;
+    assert_eq!(count_tasks(&pool).await, 0);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_create_task_uses_configured_limits(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(
+        pool.clone(),
+        matching,
+        dispatcher.with_limits(small_limits()),
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(create_req(serde_json::json!({
+            "queue_name": "reports.eu",
+            "task_name": "t",
+            "metadata": {"owner": "someone.else@example.com"},
+        })))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "queue_name is longer than 8 characters; \
+         metadata is 36 bytes, over the 32 byte limit",
+    )
+    .await;
+
+    let resp = app
+        .oneshot(create_req(serde_json::json!({
+            "queue_name": "reports",
+            "task_name": "t",
+            "metadata": {"owner": "ops"},
+        })))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(count_tasks(&pool).await, 1);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_grpc_create_task_enforces_the_same_limits(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let dispatcher = dispatcher.with_limits(small_limits());
+    let node_id = NodeId::new();
+    let addr = "127.0.0.1:19912".parse().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching,
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id, 4)),
+        NodeForwarder::new(),
+        dispatcher.log_tx().clone(),
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut api = ApiServiceClient::new(channel);
+
+    // Whitespace in the JSON string doesn't count towards the size
+    let err = api
+        .create_task(CreateTaskRequest {
+            queue_name: "reports.eu".to_string(),
+            task_name: "t".to_string(),
+            metadata: r#"{ "owner": "someone.else@example.com" }"#.to_string(),
+            max_retries: 101,
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    assert_eq!(
+        err.message(),
+        "queue_name is longer than 8 characters; \
+         metadata is 36 bytes, over the 32 byte limit; \
+         max_retries must be between 0 and 100, got 101"
+    );
+
+    let created = api
+        .create_task(CreateTaskRequest {
+            queue_name: "reports".to_string(),
+            task_name: "t".to_string(),
+            metadata: r#"{"owner": "ops"}"#.to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(created.task.unwrap().queue_name, "reports");
+    assert_eq!(count_tasks(&pool).await, 1);
+    let _ = shutdown_tx.send(true);
+}
@@ -16,6 +16,7 @@ async fn test_insert_dead_letter(pool: PgPool) {
         "t",
         task.input.as_ref(),
         Some("max retries"),
+        Some("worker-1"),
         3,
         &serde_json::json!({"source": "test"}),
     )
@@ -27,6 +28,7 @@ async fn test_insert_dead_letter(pool: PgPool) {
     assert_eq!(dl.queue_name, "q");
     assert_eq!(dl.task_name, "t");
     assert_eq!(dl.error_message.as_deref(), Some("max retries"));
+    assert_eq!(dl.worker_id.as_deref(), Some("worker-1"));
     assert_eq!(dl.attempt_count, 3);
     assert_eq!(dl.metadata["source"], "test");
 }
@@ -41,6 +43,7 @@ async fn test_insert_dead_letter_nonexistent_task(pool: PgPool) {
         "t",
         None,
         None,
+        None,
         0,
         &serde_json::json!({}),
     )
@@ -60,6 +63,7 @@ async fn test_list_dead_letters_all(pool: PgPool) {
             &format!("t{i}"),
             None,
             Some("error"),
+            None,
             i + 1,
             &serde_json::json!({}),
         )
@@ -84,6 +88,7 @@ async fn test_list_dead_letters_filter_by_queue(pool: PgPool) {
         "t",
         None,
         None,
+        None,
         1,
         &serde_json::json!({}),
     )
@@ -98,6 +103,7 @@ async fn test_list_dead_letters_filter_by_queue(pool: PgPool) {
         "t",
         None,
         None,
+        None,
         1,
         &serde_json::json!({}),
     )
@@ -123,6 +129,7 @@ async fn test_list_dead_letters_pagination(pool: PgPool) {
             &format!("t{i}"),
             None,
             None,
+            None,
             1,
             &serde_json::json!({}),
         )
@@ -142,3 +149,147 @@ async fn test_list_dead_letters_empty(pool: PgPool) {
     let dls = list_dead_letters(&pool, None, 50, 0).await.unwrap();
     assert!(dls.is_empty());
 }
+
+/// A task that has exhausted its retries, with its dead letter.
+async fn dead_lettered(
+    pool: &PgPool,
+    queue: &str,
+) -> (valka_db::queries::tasks::TaskRow, DeadLetterRow) {
+    let task = create_test_task(pool, queue, "t").await;
+    sqlx::query("UPDATE tasks SET attempt_count = 3, error_message = 'boom' WHERE id = $1")
+        .bind(&task.id)
+        .execute(pool)
+        .await
+        .unwrap();
+    valka_db::queries::tasks::move_to_dead_letter(pool, &task.id)
+        .await
+        .unwrap();
+    let dl = insert_dead_letter(
+        pool,
+        &uuid::Uuid::now_v7().to_string(),
+        &task.id,
+        queue,
+        "t",
+        task.input.as_ref(),
+        Some("boom"),
+        None,
+        3,
+        &serde_json::json!({"source": "test"}),
+    )
+    .await
+    .unwrap();
+    (task, dl)
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_requeue_dead_letter_resets_task(pool: PgPool) {
+    let (task, dl) = dead_lettered(&pool, "q").await;
+
+    let requeued = requeue_dead_letter(&pool, &dl.id, 0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(requeued.id, task.id);
+    assert_eq!(requeued.status, "PENDING");
+    assert_eq!(requeued.attempt_count, 0);
+    assert!(requeued.error_message.is_none());
+    assert_eq!(requeued.partition_id, task.partition_id);
+
+    assert!(get_dead_letter(&pool, &dl.id).await.unwrap().is_none());
+    assert!(
+        requeue_dead_letter(&pool, &dl.id, 0)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_requeue_dead_letter_leaves_active_task(pool: PgPool) {
+    let (task, dl) = dead_lettered(&pool, "q").await;
+    valka_db::queries::tasks::update_task_status(&pool, &task.id, "RUNNING")
+        .await
+        .unwrap();
+
+    assert!(
+        requeue_dead_letter(&pool, &dl.id, 0)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let current = valka_db::queries::tasks::get_task(&pool, &task.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(current.status, "RUNNING");
+    assert!(get_dead_letter(&pool, &dl.id).await.unwrap().is_some());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_requeue_dead_letter_recreates_missing_task(pool: PgPool) {
+    let (task, dl) = dead_lettered(&pool, "q").await;
+    // Dead letters normally keep their task alive; simulate one that outlived it
+    sqlx::query("ALTER TABLE dead_letter_queue DROP CONSTRAINT dead_letter_queue_task_id_fkey")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM tasks WHERE id = $1")
+        .bind(&task.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let recreated = requeue_dead_letter(&pool, &dl.id, 5)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(recreated.id, task.id);
+    assert_eq!(recreated.queue_name, "q");
+    assert_eq!(recreated.task_name, "t");
+    assert_eq!(recreated.input, task.input);
+    assert_eq!(recreated.metadata["source"], "test");
+    assert_eq!(recreated.partition_id, 5);
+    assert_eq!(recreated.status, "PENDING");
+    assert!(get_dead_letter(&pool, &dl.id).await.unwrap().is_none());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_delete_dead_letter(pool: PgPool) {
+    let (_, dl) = dead_lettered(&pool, "q").await;
+
+    assert!(delete_dead_letter(&pool, &dl.id).await.unwrap());
+    assert!(get_dead_letter(&pool, &dl.id).await.unwrap().is_none());
+    assert!(!delete_dead_letter(&pool, &dl.id).await.unwrap());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_purge_dead_letters_filters(pool: PgPool) {
+    let (_, old_a) = dead_lettered(&pool, "queue-a").await;
+    let (_, new_a) = dead_lettered(&pool, "queue-a").await;
+    let (_, old_b) = dead_lettered(&pool, "queue-b").await;
+    for dl in [&old_a, &old_b] {
+        sqlx::query(
+            "UPDATE dead_letter_queue SET created_at = NOW() - INTERVAL '2 days' WHERE id = $1",
+        )
+        .bind(&dl.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(1);
+
+    assert_eq!(
+        purge_dead_letters(&pool, Some("queue-a"), Some(cutoff))
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(get_dead_letter(&pool, &old_a.id).await.unwrap().is_none());
+    assert_eq!(
+        purge_dead_letters(&pool, None, Some(cutoff)).await.unwrap(),
+        1
+    );
+    assert!(get_dead_letter(&pool, &old_b.id).await.unwrap().is_none());
+    assert!(get_dead_letter(&pool, &new_a.id).await.unwrap().is_some());
+    assert_eq!(purge_dead_letters(&pool, None, None).await.unwrap(), 1);
+}
@@ -1,6 +1,7 @@
 use chrono::{Duration, Utc};
 use sqlx::PgPool;
 use valka_core::TaskId;
+use valka_db::queries::queue_configs::{QueueOwnershipUpdate, upsert_queue_ownership};
 use valka_db::queries::tasks::*;
 
 use super::helpers::*;
@@ -39,6 +40,13 @@ async fn test_create_task_with_all_fields(pool: PgPool) {
         idempotency_key: Some("idem-123".to_string()),
         metadata: serde_json::json!({"source": "api"}),
         scheduled_at: Some(scheduled),
+        deadline_at: None,
+        traceparent: None,
+        durability: Default::default(),
+        fifo_key: None,
+        callback_url: None,
+        retry_backoff: Default::default(),
+        required_labels: Default::default(),
     };
     let task = create_test_task_full(&pool, params).await;
 
@@ -66,6 +74,30 @@ async fn test_create_task_duplicate_idempotency_key(pool: PgPool) {
     assert!(result.is_err(), "Duplicate idempotency_key should fail");
 }
 
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_get_task_by_idempotency_key(pool: PgPool) {
+    let mut params = default_task_params("q", "t");
+    params.idempotency_key = Some("lookup-key".to_string());
+    let created = create_test_task_full(&pool, params).await;
+
+    let mut retry = default_task_params("q", "t");
+    retry.idempotency_key = Some("lookup-key".to_string());
+    let err = create_task(&pool, retry).await.unwrap_err();
+    assert!(is_idempotency_conflict(&err));
+
+    let found = get_task_by_idempotency_key(&pool, "lookup-key")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id, created.id);
+    assert!(
+        get_task_by_idempotency_key(&pool, "other-key")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
 async fn test_get_task_exists(pool: PgPool) {
     let created = create_test_task(&pool, "q", "t").await;
@@ -347,6 +379,29 @@ async fn test_dequeue_tasks_respects_scheduled_at(pool: PgPool) {
     assert!(!names.contains(&"future"));
 }
 
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dequeue_tasks_skips_paused_queue(pool: PgPool) {
+    let mut params = default_task_params("paused-q", "t");
+    params.partition_id = 0;
+    create_test_task_full(&pool, params).await;
+
+    let pause = |paused| QueueOwnershipUpdate {
+        paused: Some(paused),
+        ..Default::default()
+    };
+    upsert_queue_ownership(&pool, "paused-q", &pause(true))
+        .await
+        .unwrap();
+    let dequeued = dequeue_tasks(&pool, "paused-q", 0, 10).await.unwrap();
+    assert!(dequeued.is_empty(), "paused queue was dequeued");
+
+    upsert_queue_ownership(&pool, "paused-q", &pause(false))
+        .await
+        .unwrap();
+    let dequeued = dequeue_tasks(&pool, "paused-q", 0, 10).await.unwrap();
+    assert_eq!(dequeued.len(), 1);
+}
+
 // ─── Recovery ───────────────────────────────────────────────────────
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -362,3 +417,49 @@ async fn test_recover_orphaned_dispatching(pool: PgPool) {
     assert_eq!(recovered[0].id, task.id);
     assert_eq!(recovered[0].status, "PENDING");
 }
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_queue_backlog_splits_waiting_tasks(pool: PgPool) {
+    let oldest = create_test_task(&pool, "backlog-q", "oldest").await;
+    sqlx::query("UPDATE tasks SET created_at = NOW() - INTERVAL '10 minutes' WHERE id = $1")
+        .bind(&oldest.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    create_test_task(&pool, "backlog-q", "fresh").await;
+    let mut later = default_task_params("backlog-q", "later");
+    later.scheduled_at = Some(Utc::now() + Duration::hours(1));
+    create_test_task_full(&pool, later).await;
+    let backing_off = create_test_task(&pool, "backlog-q", "backing-off").await;
+    schedule_retry(&pool, &backing_off.id, Utc::now() + Duration::hours(1))
+        .await
+        .unwrap();
+    let due = create_test_task(&pool, "backlog-q", "due-retry").await;
+    schedule_retry(&pool, &due.id, Utc::now() - Duration::seconds(1))
+        .await
+        .unwrap();
+    create_running_task(&pool, "backlog-q").await;
+
+    let mut other = default_task_params("later-q", "later");
+    other.scheduled_at = Some(Utc::now() + Duration::hours(1));
+    create_test_task_full(&pool, other).await;
+
+    let mut backlog = queue_backlog(&pool).await.unwrap();
+    backlog.sort_by(|a, b| a.queue_name.cmp(&b.queue_name));
+    assert_eq!(backlog.len(), 2);
+
+    let queue = &backlog[0];
+    assert_eq!(queue.queue_name, "backlog-q");
+    assert_eq!((queue.pending, queue.retry, queue.scheduled), (2, 2, 1));
+    let waited = Utc::now() - queue.oldest_ready_at.unwrap();
+    assert!(
+        (Duration::minutes(10)..Duration::minutes(11)).contains(&waited),
+        "{waited}"
+    );
+
+    // Only intentionally delayed work: nothing is ready
+    let queue = &backlog[1];
+    assert_eq!(queue.queue_name, "later-q");
+    assert_eq!((queue.pending, queue.retry, queue.scheduled), (0, 0, 1));
+    assert_eq!(queue.oldest_ready_at, None);
+}
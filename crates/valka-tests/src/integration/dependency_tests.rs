@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::time::Instant;
+use tower::ServiceExt;
+use valka_core::WorkerId;
+use valka_db::queries::{dependencies, tasks};
+
+use super::helpers::*;
+
+async fn create_dependent(app: &Router, depends_on: &[&str]) -> axum::http::Response<Body> {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(serde_json::json!({
+                    "queue_name": "deps",
+                    "task_name": "child",
+                    "depends_on": depends_on,
+                }))))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn finish(pool: &PgPool, task_id: &str, run_id: &str, success: bool) {
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    dispatcher
+        .handle_task_result(
+            &WorkerId::new(),
+            valka_proto::TaskResult {
+                task_id: task_id.to_string(),
+                task_run_id: run_id.to_string(),
+                success,
+                output: String::new(),
+                error_message: if success {
+                    String::new()
+                } else {
+                    "boom".to_string()
+                },
+                retryable: false,
+                traceparent: String::new(),
+                rejected: false,
+                cancelled: false,
+                retry_after_ms: 0,
+            },
+        )
+        .await;
+}
+
+/// Resolution after a result runs in the background.
+async fn wait_for_status(pool: &PgPool, task_id: &str, status: &str) -> tasks::TaskRow {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let task = tasks::get_task(pool, task_id).await.unwrap().unwrap();
+        if task.status == status {
+            return task;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "{task_id} is {}, never {status}",
+            task.status
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dependent_waits_until_every_parent_completes(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+    let (first, first_run) = create_running_task(&pool, "deps").await;
+    let (second, second_run) = create_running_task(&pool, "deps").await;
+
+    let resp = create_dependent(&app, &[&first.id, &second.id]).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let child = parse_response_json(resp).await;
+    assert_eq!(child["status"], "WAITING");
+    let child_id = child["id"].as_str().unwrap();
+
+    finish(&pool, &first.id, &first_run.id, true).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/v1/tasks/{child_id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["status"], "WAITING");
+    assert_eq!(body["unmet_dependencies"], 1);
+    assert_eq!(body["dependencies"].as_array().unwrap().len(), 2);
+
+    finish(&pool, &second.id, &second_run.id, true).await;
+    wait_for_status(&pool, child_id, "PENDING").await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_failed_parent_cancels_dependent(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+    let (parent, run) = create_running_task(&pool, "deps").await;
+    let resp = create_dependent(&app, &[&parent.id]).await;
+    let child = parse_response_json(resp).await;
+    let child_id = child["id"].as_str().unwrap();
+
+    finish(&pool, &parent.id, &run.id, false).await;
+    let child = wait_for_status(&pool, child_id, "CANCELLED").await;
+    assert_eq!(
+        child.error_message.as_deref(),
+        Some(format!("Dependency {} ended FAILED", parent.id).as_str())
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_scheduler_pass_cascades_cancellation(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+    let parent = create_test_task(&pool, "deps", "parent").await;
+    let child = parse_response_json(create_dependent(&app, &[&parent.id]).await).await;
+    let child_id = child["id"].as_str().unwrap();
+    let grandchild = parse_response_json(create_dependent(&app, &[child_id]).await).await;
+    let grandchild_id = grandchild["id"].as_str().unwrap();
+
+    // Cancelled outside the result path, so only the scheduler sees it
+    tasks::cancel_task(&pool, &parent.id)
+        .await
+        .unwrap()
+        .unwrap();
+    let resolved = valka_scheduler::delayed::resolve_waiting_tasks(&pool)
+        .await
+        .unwrap();
+    assert_eq!(resolved, 2);
+    for id in [child_id, grandchild_id] {
+        let task = tasks::get_task(&pool, id).await.unwrap().unwrap();
+        assert_eq!(task.status, "CANCELLED");
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_completed_parents_create_pending_task(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+    let parent = create_test_task(&pool, "deps", "parent").await;
+    tasks::update_task_status(&pool, &parent.id, "COMPLETED")
+        .await
+        .unwrap();
+
+    let resp = create_dependent(&app, &[&parent.id, &parent.id]).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let child = parse_response_json(resp).await;
+    assert_eq!(child["status"], "PENDING");
+    let deps = dependencies::list_dependencies(&pool, child["id"].as_str().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(deps.len(), 1);
+    assert!(deps[0].is_met());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_unknown_or_ended_parent_is_rejected(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+    let resp = create_dependent(&app, &["no-such-task"]).await;
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "no-such-task",
+    )
+    .await;
+
+    let parent = create_test_task(&pool, "deps", "parent").await;
+    tasks::cancel_task(&pool, &parent.id)
+        .await
+        .unwrap()
+        .unwrap();
+    let resp = create_dependent(&app, &[&parent.id]).await;
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "already ended CANCELLED",
+    )
+    .await;
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tasks WHERE task_name = 'child'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
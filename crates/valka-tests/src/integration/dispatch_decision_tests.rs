@@ -0,0 +1,276 @@
+use std::time::Duration;
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::Instant;
+use tower::ServiceExt;
+use valka_core::{MatchingConfig, NodeId, PartitionId};
+use valka_db::queries::tasks::TaskRow;
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_matching::task_reader::TaskReader;
+
+use super::helpers::*;
+
+const QUEUE: &str = "debugged";
+
+/// Services whose decision log only samples queues forced to full sampling.
+fn make_services(pool: &PgPool) -> (DispatcherService, MatchingService, Router) {
+    let matching = MatchingService::new(MatchingConfig {
+        decision_sample_rate: 0,
+        ..MatchingConfig::default()
+    });
+    let (event_tx, _) = broadcast::channel::<valka_proto::TaskEvent>(128);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    let dispatcher = DispatcherService::new(
+        matching.clone(),
+        pool.clone(),
+        NodeId::new(),
+        event_tx,
+        log_tx,
+    );
+    let router = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    (dispatcher, matching, router)
+}
+
+fn start_reader(pool: &PgPool, matching: &MatchingService) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let config = MatchingConfig {
+        task_reader_poll_busy_ms: 20,
+        task_reader_poll_idle_ms: 20,
+        ..MatchingConfig::default()
+    };
+    let reader = TaskReader::new(
+        pool.clone(),
+        matching.clone(),
+        QUEUE.to_string(),
+        PartitionId(0),
+        config,
+        shutdown_rx,
+    );
+    tokio::spawn(reader.run());
+    shutdown_tx
+}
+
+async fn create_task(pool: &PgPool) -> TaskRow {
+    let mut params = default_task_params(QUEUE, "t");
+    params.partition_id = 0;
+    create_test_task_full(pool, params).await
+}
+
+async fn force_sampling(app: &Router, ttl_secs: u64) -> serde_json::Value {
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/debug/dispatch-decisions/sampling/{QUEUE}"))
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(serde_json::json!({
+                    "ttl_secs": ttl_secs
+                }))))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    parse_response_json(resp).await
+}
+
+async fn get_decisions(app: &Router) -> serde_json::Value {
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/v1/debug/dispatch-decisions?queue={QUEUE}&limit=200"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    parse_response_json(resp).await
+}
+
+/// Outcomes recorded for a task, oldest first, with the worker if any.
+fn outcomes_for(body: &serde_json::Value, task_id: &str) -> Vec<(String, Option<String>)> {
+    let mut outcomes: Vec<_> = body["decisions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|d| d["task_id"] == task_id)
+        .map(|d| {
+            (
+                d["outcome"].as_str().unwrap().to_string(),
+                d["worker_id"].as_str().map(str::to_string),
+            )
+        })
+        .collect();
+    outcomes.reverse();
+    outcomes
+}
+
+async fn wait_for_outcome(app: &Router, task_id: &str, outcome: &str) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !outcomes_for(&get_decisions(app).await, task_id)
+        .iter()
+        .any(|(o, _)| o == outcome)
+    {
+        assert!(Instant::now() < deadline, "{task_id} never {outcome}");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_forced_sampling_records_buffered_and_matched_tasks(pool: PgPool) {
+    let (dispatcher, matching, app) = make_services(&pool);
+    let body = force_sampling(&app, 600).await;
+    assert_eq!(body["full_sampling"][0]["queue_name"], QUEUE);
+    let _reader = start_reader(&pool, &matching);
+
+    // No worker yet: the reader buffers it
+    let buffered = create_task(&pool).await;
+    wait_for_outcome(&app, &buffered.id, "buffered").await;
+
+    // The worker takes the buffered task, then is matched to the next one
+    let (worker_id, mut rx) = start_worker(&dispatcher, QUEUE, 1).await;
+    assert_eq!(recv_assignment(&mut rx).await.task_id, buffered.id);
+    let task = valka_db::queries::tasks::get_task(&pool, &buffered.id)
+        .await
+        .unwrap()
+        .unwrap();
+    let run = valka_db::queries::task_runs::get_runs_for_task(&pool, &task.id)
+        .await
+        .unwrap()
+        .remove(0);
+    dispatcher
+        .handle_task_result(
+            &worker_id,
+            valka_proto::TaskResult {
+                task_id: task.id.clone(),
+                task_run_id: run.id,
+                success: true,
+                output: String::new(),
+                error_message: String::new(),
+                retryable: false,
+                traceparent: String::new(),
+                rejected: false,
+                cancelled: false,
+                retry_after_ms: 0,
+            },
+        )
+        .await;
+    let matched = create_task(&pool).await;
+    assert_eq!(recv_assignment(&mut rx).await.task_id, matched.id);
+    wait_for_outcome(&app, &matched.id, "dispatched").await;
+
+    let body = get_decisions(&app).await;
+    let worker = Some(worker_id.0.clone());
+    assert_eq!(
+        outcomes_for(&body, &buffered.id),
+        [
+            ("dequeued".to_string(), None),
+            ("unmatched".to_string(), None),
+            ("buffered".to_string(), None),
+            ("matched".to_string(), worker.clone()),
+            ("dispatched".to_string(), worker.clone()),
+        ]
+    );
+    // Buffered too if the reader got there before the worker's slot came back
+    let outcomes = outcomes_for(&body, &matched.id);
+    assert_eq!(outcomes[0].0, "dequeued");
+    assert_eq!(
+        outcomes[outcomes.len() - 2..],
+        [
+            ("matched".to_string(), worker.clone()),
+            ("dispatched".to_string(), worker),
+        ]
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_forced_sampling_expires(pool: PgPool) {
+    let (_dispatcher, matching, app) = make_services(&pool);
+    force_sampling(&app, 1).await;
+    assert!(matching.decisions().is_sampled(QUEUE, "any-task"));
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(!matching.decisions().is_sampled(QUEUE, "any-task"));
+    let _reader = start_reader(&pool, &matching);
+    let task = create_task(&pool).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let body = get_decisions(&app).await;
+    assert_eq!(body["full_sampling"], serde_json::json!([]));
+    assert!(outcomes_for(&body, &task.id).is_empty());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_forced_sampling_rejects_zero_ttl(pool: PgPool) {
+    let (_dispatcher, _matching, app) = make_services(&pool);
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/debug/dispatch-decisions/sampling/{QUEUE}"))
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(serde_json::json!({ "ttl_secs": 0 }))))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "ttl_secs",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_debug_matching_reports_partitions(pool: PgPool) {
+    let (_dispatcher, matching, app) = make_services(&pool);
+    for _ in 0..2 {
+        let task = create_task(&pool).await;
+        assert!(
+            matching
+                .buffer_task(QUEUE, PartitionId(0), envelope_for(&task))
+                .is_buffered()
+        );
+    }
+    let _rx = matching.register_worker(QUEUE, PartitionId(1), valka_core::WorkerId::new());
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/debug/matching")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["buffered_tasks"], 2);
+    assert_eq!(body["waiting_workers"], 1);
+
+    let partition = |pid: i32| {
+        body["partitions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["queue_name"] == QUEUE && p["partition_id"] == pid)
+            .cloned()
+            .unwrap()
+    };
+    assert_eq!(partition(0)["buffered_tasks"], 2);
+    assert_eq!(partition(0)["waiting_workers"], 0);
+    assert_eq!(partition(1)["buffered_tasks"], 0);
+    assert_eq!(partition(1)["waiting_workers"], 1);
+}
@@ -1,6 +1,6 @@
 use sqlx::PgPool;
 use tokio::sync::{broadcast, mpsc};
-use valka_core::{MatchingConfig, NodeId, WorkerId};
+use valka_core::{MatchingConfig, NodeId, ResultWriterConfig, WorkerId};
 use valka_db::queries::{task_runs, tasks};
 use valka_dispatcher::DispatcherService;
 use valka_dispatcher::worker_handle::WorkerHandle;
@@ -9,15 +9,6 @@ use valka_proto::WorkerResponse;
 
 use super::helpers::*;
 
-fn make_dispatcher(pool: PgPool) -> (DispatcherService, MatchingService) {
-    let matching = MatchingService::new(MatchingConfig::default());
-    let node_id = NodeId::new();
-    let (event_tx, _) = broadcast::channel::<valka_proto::TaskEvent>(128);
-    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
-    let dispatcher = DispatcherService::new(matching.clone(), pool, node_id, event_tx, log_tx);
-    (dispatcher, matching)
-}
-
 fn make_worker_handle(concurrency: i32) -> (WorkerHandle, mpsc::Receiver<WorkerResponse>) {
     let (tx, rx) = mpsc::channel::<WorkerResponse>(16);
     let handle = WorkerHandle::new(
@@ -55,7 +46,7 @@ async fn test_dispatcher_handle_task_result_success(pool: PgPool) {
 
     // Assign the task to the worker so complete_task removes it
     if let Some(mut h) = dispatcher.workers().get_mut(worker_id.as_ref()) {
-        h.assign_task(task.id.clone());
+        h.assign_task(task.id.clone(), &task.queue_name);
     }
 
     let result = valka_proto::TaskResult {
@@ -65,6 +56,10 @@ async fn test_dispatcher_handle_task_result_success(pool: PgPool) {
         output: serde_json::json!({"done": true}).to_string(),
         error_message: String::new(),
         retryable: false,
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
     };
     dispatcher.handle_task_result(&worker_id, result).await;
 
@@ -89,6 +84,7 @@ async fn test_dispatcher_handle_task_result_failure_retryable(pool: PgPool) {
     let (handle, _rx) = make_worker_handle(2);
     let worker_id = handle.worker_id.clone();
     dispatcher.register_worker(handle).await;
+    let mut events = dispatcher.events().subscribe();
 
     let result = valka_proto::TaskResult {
         task_id: task.id.clone(),
@@ -97,6 +93,10 @@ async fn test_dispatcher_handle_task_result_failure_retryable(pool: PgPool) {
         output: String::new(),
         error_message: "timeout".to_string(),
         retryable: true,
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
     };
     dispatcher.handle_task_result(&worker_id, result).await;
 
@@ -109,6 +109,37 @@ async fn test_dispatcher_handle_task_result_failure_retryable(pool: PgPool) {
         .unwrap();
     assert_eq!(run_after.status, "FAILED");
     assert_eq!(run_after.error_message.as_deref(), Some("timeout"));
+
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.new_status, 6); // RETRY
+    assert_eq!(event.queue_name, "demo");
+    assert_eq!(event.worker_id, worker_id.0);
+    assert_eq!(event.attempt_number, run.attempt_number);
+    assert_eq!(event.error_message, "timeout");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_handle_task_result_records_retry_after(pool: PgPool) {
+    let (task, run) = create_running_task(&pool, "demo").await;
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+
+    let (handle, _rx) = make_worker_handle(2);
+    let worker_id = handle.worker_id.clone();
+    dispatcher.register_worker(handle).await;
+
+    let result = valka_proto::TaskResult {
+        task_id: task.id.clone(),
+        task_run_id: run.id.clone(),
+        error_message: "rate limited".to_string(),
+        retryable: true,
+        retry_after_ms: 45_000,
+        ..Default::default()
+    };
+    dispatcher.handle_task_result(&worker_id, result).await;
+
+    let task_after = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(task_after.status, "RETRY");
+    assert_eq!(task_after.retry_after_ms, Some(45_000));
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -119,6 +150,7 @@ async fn test_dispatcher_handle_task_result_failure_non_retryable(pool: PgPool)
     let (handle, _rx) = make_worker_handle(2);
     let worker_id = handle.worker_id.clone();
     dispatcher.register_worker(handle).await;
+    let mut events = dispatcher.events().subscribe();
 
     let result = valka_proto::TaskResult {
         task_id: task.id.clone(),
@@ -127,12 +159,22 @@ async fn test_dispatcher_handle_task_result_failure_non_retryable(pool: PgPool)
         output: String::new(),
         error_message: "fatal".to_string(),
         retryable: false,
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
     };
     dispatcher.handle_task_result(&worker_id, result).await;
 
     let task_after = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
     assert_eq!(task_after.status, "FAILED");
     assert_eq!(task_after.error_message.as_deref(), Some("fatal"));
+
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.new_status, 5); // FAILED
+    assert_eq!(event.queue_name, "demo");
+    assert_eq!(event.worker_id, worker_id.0);
+    assert_eq!(event.attempt_number, run.attempt_number);
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -146,11 +188,18 @@ async fn test_dispatcher_cancel_task_on_worker(pool: PgPool) {
     // Assign a task
     let task_id = "cancel-me";
     if let Some(mut h) = dispatcher.workers().get_mut(worker_id.as_ref()) {
-        h.assign_task(task_id.to_string());
+        h.assign_task(task_id.to_string(), "default");
     }
 
     let cancelled = dispatcher.cancel_task_on_worker(task_id).await;
     assert!(cancelled, "Should find and cancel the task");
+    // No result will arrive for it, so the slot is freed right away
+    let slots = dispatcher
+        .workers()
+        .get(worker_id.as_ref())
+        .unwrap()
+        .available_slots();
+    assert_eq!(slots, 2);
 
     // Verify cancellation message sent to worker
     let msg = rx.recv().await.unwrap();
@@ -233,6 +282,10 @@ async fn test_dispatcher_emit_event(pool: PgPool) {
         output: String::new(),
         error_message: String::new(),
         retryable: false,
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
     };
     dispatcher.handle_task_result(&worker_id, result).await;
 
@@ -240,6 +293,9 @@ async fn test_dispatcher_emit_event(pool: PgPool) {
     let event = event_rx.recv().await.unwrap();
     assert_eq!(event.task_id, task.id);
     assert_eq!(event.new_status, 4); // COMPLETED
+    assert_eq!(event.queue_name, "q");
+    assert_eq!(event.worker_id, worker_id.0);
+    assert_eq!(event.attempt_number, run.attempt_number);
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -287,3 +343,841 @@ async fn test_dispatcher_multiple_workers(pool: PgPool) {
     assert_eq!(dispatcher.workers().len(), 1);
     assert!(dispatcher.workers().contains_key(id2.as_ref()));
 }
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_worker_logs_pinned_to_session(pool: PgPool) {
+    let matching = MatchingService::new(MatchingConfig::default());
+    let (event_tx, _) = broadcast::channel::<valka_proto::TaskEvent>(128);
+    let (log_tx, mut log_rx) = mpsc::channel::<valka_proto::LogEntry>(128);
+    let dispatcher = DispatcherService::new(matching, pool, NodeId::new(), event_tx, log_tx);
+    let worker_id = WorkerId::new();
+
+    let entry = |run_id: &str| valka_proto::LogEntry {
+        task_run_id: run_id.to_string(),
+        timestamp_ms: 1,
+        level: 3,
+        message: "m".to_string(),
+        metadata: String::new(),
+    };
+    let batch = valka_proto::LogBatch {
+        entries: vec![entry("worker:someone-else"), entry("run-1")],
+    };
+    dispatcher.handle_log_batch(&worker_id, batch).await;
+
+    let first = log_rx.recv().await.unwrap();
+    assert_eq!(first.task_run_id, format!("worker:{worker_id}"));
+    let second = log_rx.recv().await.unwrap();
+    assert_eq!(second.task_run_id, "run-1");
+}
+
+/// Buffer the task and run a single worker's match loop against it.
+async fn dispatch_one(
+    pool: PgPool,
+    task: &tasks::TaskRow,
+) -> (DispatcherService, mpsc::Receiver<WorkerResponse>) {
+    let (dispatcher, matching) = make_dispatcher(pool);
+    let (handle, rx) = make_worker_handle(1);
+    let worker_id = handle.worker_id.clone();
+    dispatcher.register_worker(handle).await;
+
+    let partition = valka_core::PartitionId(task.partition_id);
+    assert!(
+        matching
+            .buffer_task(&task.queue_name, partition, envelope_for(task))
+            .is_buffered()
+    );
+
+    let d = dispatcher.clone();
+    tokio::spawn(async move {
+        d.run_worker_match_loop(worker_id, vec!["default".to_string()])
+            .await
+    });
+    (dispatcher, rx)
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_assignment_carries_remaining_budget(pool: PgPool) {
+    let mut params = default_task_params("default", "t");
+    params.deadline_at = Some(chrono::Utc::now() + chrono::Duration::seconds(5));
+    let task = create_test_task_full(&pool, params).await;
+
+    let (_dispatcher, mut rx) = dispatch_one(pool.clone(), &task).await;
+
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .expect("assignment not received")
+        .unwrap();
+    let Some(valka_proto::worker_response::Response::TaskAssignment(assignment)) =
+        response.response
+    else {
+        panic!("Expected TaskAssignment");
+    };
+    assert_eq!(assignment.timeout_seconds, 300);
+    assert!(assignment.remaining_budget_ms > 0);
+    assert!(assignment.remaining_budget_ms <= 5000);
+
+    // The lease follows the shortened budget rather than the 300s timeout
+    let runs = task_runs::get_runs_for_task(&pool, &task.id).await.unwrap();
+    assert_eq!(runs.len(), 1);
+    assert!(runs[0].lease_expires_at < chrono::Utc::now() + chrono::Duration::seconds(60));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_assignment_carries_priority_and_times(pool: PgPool) {
+    let scheduled_at = chrono::Utc::now() - chrono::Duration::seconds(30);
+    let mut params = default_task_params("default", "t");
+    params.priority = 7;
+    params.scheduled_at = Some(scheduled_at);
+    let task = create_test_task_full(&pool, params).await;
+
+    let (_dispatcher, mut rx) = dispatch_one(pool.clone(), &task).await;
+
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .expect("assignment not received")
+        .unwrap();
+    let Some(valka_proto::worker_response::Response::TaskAssignment(assignment)) =
+        response.response
+    else {
+        panic!("Expected TaskAssignment");
+    };
+    assert_eq!(assignment.priority, 7);
+    assert_eq!(assignment.created_at_ms, task.created_at.timestamp_millis());
+    assert_eq!(
+        assignment.scheduled_at_ms,
+        task.scheduled_at.unwrap().timestamp_millis()
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_records_queue_wait(pool: PgPool) {
+    let task = create_test_task(&pool, "default", "t").await;
+    // Waiting for two minutes by the time a worker takes it
+    let task: tasks::TaskRow = sqlx::query_as(
+        "UPDATE tasks SET created_at = NOW() - INTERVAL '2 minutes' WHERE id = $1 RETURNING *",
+    )
+    .bind(&task.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+    let (_dispatcher, mut rx) = dispatch_one(pool.clone(), &task).await;
+    tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .expect("assignment not received")
+        .unwrap();
+
+    let rendered = recorder.handle().render();
+    assert!(
+        rendered.contains("valka_queue_wait_ms_count{queue=\"default\"} 1"),
+        "{rendered}"
+    );
+    let sum: f64 = rendered
+        .lines()
+        .find_map(|l| l.strip_prefix("valka_queue_wait_ms_sum{queue=\"default\"} "))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((120_000.0..130_000.0).contains(&sum), "{sum}");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_records_assignment_metrics(pool: PgPool) {
+    let sent = create_test_task(&pool, "default", "t").await;
+    let lost = create_test_task(&pool, "default", "t").await;
+
+    let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+    let (_dispatcher, mut rx) = dispatch_one(pool.clone(), &sent).await;
+    tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .expect("assignment not received")
+        .unwrap();
+    // A worker whose stream is already gone
+    let (_dispatcher, rx) = dispatch_one(pool.clone(), &lost).await;
+    drop(rx);
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let rendered = recorder.handle().render();
+    assert!(
+        rendered.contains("valka_task_assignments_sent_total{queue=\"default\"} 1"),
+        "{rendered}"
+    );
+    assert!(
+        rendered.contains("valka_worker_send_failures_total{message=\"assignment\"} 1"),
+        "{rendered}"
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_expired_deadline_skips_worker(pool: PgPool) {
+    let mut params = default_task_params("default", "t");
+    params.deadline_at = Some(chrono::Utc::now() - chrono::Duration::seconds(1));
+    let task = create_test_task_full(&pool, params).await;
+
+    let (_dispatcher, mut rx) = dispatch_one(pool.clone(), &task).await;
+
+    let mut status = String::new();
+    for _ in 0..100 {
+        status = tasks::get_task(&pool, &task.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status;
+        if status == "FAILED" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(status, "FAILED");
+    assert!(rx.try_recv().is_err(), "Worker must not get an assignment");
+
+    let runs = task_runs::get_runs_for_task(&pool, &task.id).await.unwrap();
+    assert!(runs.is_empty());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_min_worker_version_excludes_old_workers(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+
+    let mut receivers = Vec::new();
+    for version in ["1.0.0", "2.1.0"] {
+        let (handle, rx) = make_worker_handle(4);
+        let handle = handle.with_version(version.to_string());
+        let worker_id = handle.worker_id.clone();
+        dispatcher.register_worker(handle).await;
+        let d = dispatcher.clone();
+        tokio::spawn(async move {
+            d.run_worker_match_loop(worker_id, vec!["default".to_string()])
+                .await
+        });
+        receivers.push(rx);
+    }
+    let (mut old_rx, mut new_rx) = (receivers.remove(0), receivers.remove(0));
+
+    // Applied while both loops are already parked on the queue
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    dispatcher
+        .queue_policies()
+        .set_min_worker_version("default", Some(semver::Version::new(2, 0, 0)));
+
+    let old_worker = dispatcher
+        .workers()
+        .iter()
+        .find(|w| w.version == "1.0.0")
+        .map(|w| dispatcher.excluded_queues(&w))
+        .unwrap();
+    assert_eq!(
+        old_worker,
+        vec![("default".to_string(), "version < 2.0.0".to_string())]
+    );
+
+    for i in 0..3 {
+        let task = create_test_task(&pool, "default", &format!("t{i}")).await;
+        let partition = valka_core::PartitionId(task.partition_id);
+        if let Err(envelope) = matching.offer_task("default", partition, envelope_for(&task)) {
+            matching.buffer_task("default", partition, envelope);
+        }
+    }
+
+    for _ in 0..3 {
+        let response = tokio::time::timeout(std::time::Duration::from_secs(5), new_rx.recv())
+            .await
+            .expect("newer worker should receive the task")
+            .unwrap();
+        assert!(matches!(
+            response.response,
+            Some(valka_proto::worker_response::Response::TaskAssignment(_))
+        ));
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(old_rx.try_recv().is_err(), "Old worker must not get tasks");
+}
+
+// ─── Slot reconciliation ────────────────────────────────────────────
+
+fn offer(matching: &MatchingService, task: &tasks::TaskRow) {
+    let partition = valka_core::PartitionId(task.partition_id);
+    if let Err(envelope) = matching.offer_task(&task.queue_name, partition, envelope_for(task)) {
+        matching.buffer_task(&task.queue_name, partition, envelope);
+    }
+}
+
+fn available_slots(dispatcher: &DispatcherService, worker_id: &WorkerId) -> i32 {
+    dispatcher
+        .workers()
+        .get(worker_id.as_ref())
+        .unwrap()
+        .available_slots()
+}
+
+async fn heartbeat(dispatcher: &DispatcherService, worker_id: &WorkerId, active: Vec<String>) {
+    let heartbeat = valka_proto::Heartbeat {
+        active_task_ids: active,
+        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+    };
+    dispatcher.handle_heartbeat(worker_id, heartbeat).await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_heartbeat_reclaims_slot_of_lost_result(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, "q", 1).await;
+
+    let first = create_test_task(&pool, "q", "t").await;
+    offer(&matching, &first);
+    assert_eq!(recv_assignment(&mut rx).await.task_id, first.id);
+
+    // The worker finished it but the result was lost; the next task waits
+    let second = create_test_task(&pool, "q", "t").await;
+    offer(&matching, &second);
+    heartbeat(&dispatcher, &worker_id, vec![]).await;
+    assert_eq!(available_slots(&dispatcher, &worker_id), 0);
+
+    heartbeat(&dispatcher, &worker_id, vec![]).await;
+    assert_eq!(recv_assignment(&mut rx).await.task_id, second.id);
+
+    // Still claimed by the worker: kept across heartbeats
+    for _ in 0..3 {
+        heartbeat(&dispatcher, &worker_id, vec![second.id.clone()]).await;
+    }
+    assert_eq!(available_slots(&dispatcher, &worker_id), 0);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_reaped_lease_frees_worker_slot(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, "q", 1).await;
+
+    let first = create_test_task(&pool, "q", "t").await;
+    offer(&matching, &first);
+    let assignment = recv_assignment(&mut rx).await;
+    let second = create_test_task(&pool, "q", "t").await;
+    offer(&matching, &second);
+
+    sqlx::query(
+        "UPDATE task_runs SET lease_expires_at = NOW() - INTERVAL '1 second' WHERE id = $1",
+    )
+    .bind(&assignment.task_run_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    let reaped = valka_scheduler::reaper::reap_expired_runs(&pool, dispatcher.event_tx())
+        .await
+        .unwrap();
+    assert_eq!(reaped.len(), 1);
+    assert_eq!(reaped[0].worker_id, worker_id.0);
+
+    // What the scheduler loop does with the reaped runs
+    for run in &reaped {
+        assert!(dispatcher.release_task(&run.worker_id, &run.task_id, "reaped"));
+    }
+    assert_eq!(recv_assignment(&mut rx).await.task_id, second.id);
+    assert!(!dispatcher.release_task(&worker_id.0, &first.id, "reaped"));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_match_loop_honours_queue_concurrency(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let queues = vec!["video".to_string(), "email".to_string()];
+    let (tx, mut rx) = mpsc::channel::<WorkerResponse>(16);
+    let worker_id = WorkerId::new();
+    let handle = WorkerHandle::new(
+        worker_id.clone(),
+        "test-worker".to_string(),
+        queues.clone(),
+        4,
+        tx,
+        String::new(),
+    )
+    .with_queue_concurrency([("video".to_string(), 1)].into());
+    dispatcher.register_worker(handle).await;
+    let d = dispatcher.clone();
+    let id = worker_id.clone();
+    tokio::spawn(async move { d.run_worker_match_loop(id, queues).await });
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    let first = create_test_task(&pool, "video", "encode").await;
+    offer(&matching, &first);
+    assert_eq!(recv_assignment(&mut rx).await.task_id, first.id);
+
+    // "video" is at its cap; "email" still has room
+    let second = create_test_task(&pool, "video", "encode").await;
+    offer(&matching, &second);
+    let email = create_test_task(&pool, "email", "send").await;
+    offer(&matching, &email);
+    assert_eq!(recv_assignment(&mut rx).await.task_id, email.id);
+    tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+    assert!(rx.try_recv().is_err(), "video is capped at 1");
+
+    assert!(dispatcher.release_task(&worker_id.0, &first.id, "done"));
+    assert_eq!(recv_assignment(&mut rx).await.task_id, second.id);
+}
+
+// ─── Input templating ───────────────────────────────────────────────
+
+async fn create_via_rest(app: &axum::Router, queue: &str, input: serde_json::Value) -> String {
+    use tower::ServiceExt;
+
+    let body = serde_json::json!({
+        "queue_name": queue,
+        "task_name": "report",
+        "input": input,
+        "metadata": {"tenant": "acme"},
+    });
+    let resp = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(json_body(body)))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), axum::http::StatusCode::CREATED);
+    parse_response_json(resp).await["id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_templates_input_on_opted_in_queue(pool: PgPool) {
+    use tower::ServiceExt;
+
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+    let resp = app
+        .clone()
+        .oneshot(
+            axum::http::Request::builder()
+                .method("PUT")
+                .uri("/api/v1/queues/reports")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(r#"{"template_input": true}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(parse_response_json(resp).await["template_input"], true);
+
+    let input = serde_json::json!({
+        "nonce": "{{valka.run_id}}",
+        "request": {"tenant": "{{meta.tenant}}", "attempt": "{{valka.attempt}}"},
+    });
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "reports", 1).await;
+    let task_id = create_via_rest(&app, "reports", input.clone()).await;
+
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task_id);
+    let sent: serde_json::Value = serde_json::from_str(&assignment.input).unwrap();
+    assert_eq!(sent["nonce"], assignment.task_run_id.as_str());
+    assert_eq!(
+        sent["request"],
+        serde_json::json!({"tenant": "acme", "attempt": "1"})
+    );
+
+    // Only the dispatched copy is rendered
+    let stored = tasks::get_task(&pool, &task_id).await.unwrap().unwrap();
+    assert_eq!(stored.input, Some(input.clone()));
+
+    // Queues that haven't opted in get the input as stored
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "plain", 1).await;
+    create_via_rest(&app, "plain", input.clone()).await;
+    let assignment = recv_assignment(&mut rx).await;
+    let sent: serde_json::Value = serde_json::from_str(&assignment.input).unwrap();
+    assert_eq!(sent, input);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_handle_task_progress(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let mut events = dispatcher.events().subscribe();
+    let (worker_id, mut rx) = start_worker(&dispatcher, "progress-q", 1).await;
+    let task = create_test_task(&pool, "progress-q", "t").await;
+    let _ = matching.offer_task(
+        "progress-q",
+        valka_core::PartitionId(task.partition_id),
+        envelope_for(&task),
+    );
+    let assignment = recv_assignment(&mut rx).await;
+    while events.try_recv().is_ok() {}
+
+    let progress = |percent: f32, message: &str| valka_proto::TaskProgress {
+        task_id: assignment.task_id.clone(),
+        task_run_id: assignment.task_run_id.clone(),
+        percent,
+        message: message.to_string(),
+        timestamp_ms: 0,
+    };
+
+    // Out-of-range percents are clamped
+    dispatcher
+        .handle_task_progress(&worker_id, progress(150.0, "almost"))
+        .await;
+    let run = task_runs::get_task_run(&pool, &assignment.task_run_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(run.progress, Some(100.0));
+    assert_eq!(run.progress_message.as_deref(), Some("almost"));
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.task_id, task.id);
+    assert_eq!(event.queue_name, "progress-q");
+    assert_eq!(event.new_status, 3);
+    assert_eq!(event.progress, Some(100.0));
+    assert_eq!(event.progress_message, "almost");
+
+    // Progress isn't coalesced like repeated statuses
+    dispatcher
+        .handle_task_progress(&worker_id, progress(42.0, "halfway"))
+        .await;
+    assert_eq!(events.try_recv().unwrap().progress, Some(42.0));
+
+    // Only the worker holding the task can report on it
+    dispatcher
+        .handle_task_progress(&WorkerId::new(), progress(7.0, "spoofed"))
+        .await;
+    let run = task_runs::get_task_run(&pool, &assignment.task_run_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(run.progress, Some(42.0));
+    assert!(events.try_recv().is_err());
+
+    // Progress for a finished run is dropped
+    dispatcher
+        .handle_task_result(
+            &worker_id,
+            valka_proto::TaskResult {
+                task_id: assignment.task_id.clone(),
+                task_run_id: assignment.task_run_id.clone(),
+                success: true,
+                output: "{}".to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+    dispatcher
+        .handle_task_progress(&worker_id, progress(99.0, "late"))
+        .await;
+    let run = task_runs::get_task_run(&pool, &assignment.task_run_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(run.progress, Some(42.0));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_batches_result_writes(pool: PgPool) {
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let dispatcher = dispatcher.with_result_writer_config(&ResultWriterConfig {
+        batch_size: 64,
+        flush_interval_ms: 5,
+    });
+    let (handle, _rx) = make_worker_handle(500);
+    let worker_id = handle.worker_id.clone();
+    dispatcher.register_worker(handle).await;
+
+    let mut runs = Vec::with_capacity(500);
+    for _ in 0..500 {
+        runs.push(create_running_task(&pool, "batched").await);
+    }
+    for (task, run) in &runs {
+        let result = valka_proto::TaskResult {
+            task_id: task.id.clone(),
+            task_run_id: run.id.clone(),
+            success: true,
+            output: r#"{"ok":true}"#.to_string(),
+            ..Default::default()
+        };
+        dispatcher.submit_task_result(&worker_id, result);
+    }
+    dispatcher.flush_results().await;
+
+    let completed: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM tasks WHERE queue_name = 'batched' AND status = 'COMPLETED' \
+         AND output = '{\"ok\":true}'::jsonb",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(completed, 500);
+    let open: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM task_runs WHERE status <> 'COMPLETED'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(open, 0);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_result_batch_mixes_outcomes(pool: PgPool) {
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let dispatcher = dispatcher.with_result_writer_config(&ResultWriterConfig {
+        batch_size: 16,
+        flush_interval_ms: 200,
+    });
+    let (done, done_run) = create_running_task(&pool, "mixed").await;
+    let (retry, retry_run) = create_running_task(&pool, "mixed").await;
+    let (failed, failed_run) = create_running_task(&pool, "mixed").await;
+    let (mut handle, _rx) = make_worker_handle(4);
+    for task in [&done, &retry, &failed] {
+        handle.assign_task(task.id.clone(), &task.queue_name);
+    }
+    let worker_id = handle.worker_id.clone();
+    dispatcher.register_worker(handle).await;
+    assert_eq!(available_slots(&dispatcher, &worker_id), 1);
+    let mut events = dispatcher.events().subscribe();
+
+    let results = [
+        valka_proto::TaskResult {
+            task_id: done.id.clone(),
+            task_run_id: done_run.id.clone(),
+            success: true,
+            output: "42".to_string(),
+            ..Default::default()
+        },
+        valka_proto::TaskResult {
+            task_id: retry.id.clone(),
+            task_run_id: retry_run.id.clone(),
+            error_message: "busy".to_string(),
+            retryable: true,
+            retry_after_ms: 1_000,
+            ..Default::default()
+        },
+        valka_proto::TaskResult {
+            task_id: failed.id.clone(),
+            task_run_id: failed_run.id.clone(),
+            error_message: "fatal".to_string(),
+            ..Default::default()
+        },
+        // A second result for the same run lands in the same batch
+        valka_proto::TaskResult {
+            task_id: failed.id.clone(),
+            task_run_id: failed_run.id.clone(),
+            success: true,
+            ..Default::default()
+        },
+    ];
+    for result in results {
+        dispatcher.submit_task_result(&worker_id, result);
+    }
+    // Slots are freed before the batch is written
+    assert_eq!(available_slots(&dispatcher, &worker_id), 4);
+    dispatcher.flush_results().await;
+
+    let done = tasks::get_task(&pool, &done.id).await.unwrap().unwrap();
+    assert_eq!(done.status, "COMPLETED");
+    assert_eq!(done.output, Some(serde_json::json!(42)));
+    let retry = tasks::get_task(&pool, &retry.id).await.unwrap().unwrap();
+    assert_eq!(retry.status, "RETRY");
+    assert_eq!(retry.retry_after_ms, Some(1_000));
+    let failed = tasks::get_task(&pool, &failed.id).await.unwrap().unwrap();
+    assert_eq!(failed.status, "FAILED");
+    assert_eq!(failed.error_message.as_deref(), Some("fatal"));
+    let runs = task_runs::get_runs_for_task(&pool, &retry.id)
+        .await
+        .unwrap();
+    assert_eq!(runs[0].status, "FAILED");
+    assert_eq!(runs[0].error_message.as_deref(), Some("busy"));
+
+    let mut statuses = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        assert_eq!(event.queue_name, "mixed");
+        statuses.push(event.new_status);
+    }
+    statuses.sort();
+    assert_eq!(statuses, vec![4, 5, 6]);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dispatcher_result_batch_falls_back_to_rows(pool: PgPool) {
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let dispatcher = dispatcher.with_result_writer_config(&ResultWriterConfig {
+        batch_size: 16,
+        flush_interval_ms: 200,
+    });
+    let (done, done_run) = create_running_task(&pool, "fallback").await;
+    let (bad, bad_run) = create_running_task(&pool, "fallback").await;
+    let (handle, _rx) = make_worker_handle(4);
+    let worker_id = handle.worker_id.clone();
+    dispatcher.register_worker(handle).await;
+    let mut events = dispatcher.events().subscribe();
+
+    // PG text can't hold a NUL byte, so this result fails any batch it is in
+    let results = [
+        valka_proto::TaskResult {
+            task_id: done.id.clone(),
+            task_run_id: done_run.id.clone(),
+            success: true,
+            output: "1".to_string(),
+            ..Default::default()
+        },
+        valka_proto::TaskResult {
+            task_id: bad.id.clone(),
+            task_run_id: bad_run.id.clone(),
+            error_message: "bad\0byte".to_string(),
+            ..Default::default()
+        },
+    ];
+    for result in results {
+        dispatcher.submit_task_result(&worker_id, result);
+    }
+    dispatcher.flush_results().await;
+
+    let done = tasks::get_task(&pool, &done.id).await.unwrap().unwrap();
+    assert_eq!(done.status, "COMPLETED");
+    // The unwritable result leaves its run for the lease reaper
+    let bad = tasks::get_task(&pool, &bad.id).await.unwrap().unwrap();
+    assert_eq!(bad.status, "RUNNING");
+
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.task_id, done.id);
+    assert_eq!(event.queue_name, "fallback");
+    assert!(events.try_recv().is_err());
+}
+
+// ─── Match loop registrations ───────────────────────────────────────
+
+/// Wait until the matching service holds exactly `expected` waiting slots.
+async fn wait_for_waiting_slots(matching: &MatchingService, expected: usize) {
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(2);
+    while matching.stats().waiting_workers != expected {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "{} waiting slots, expected {expected}",
+            matching.stats().waiting_workers
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_match_loop_keeps_registrations_across_passes(pool: PgPool) {
+    let matching = MatchingService::new(MatchingConfig {
+        num_partitions: 16,
+        ..Default::default()
+    });
+    let (event_tx, _) = broadcast::channel(128);
+    let (log_tx, _) = mpsc::channel(128);
+    let dispatcher = DispatcherService::new(
+        matching.clone(),
+        pool.clone(),
+        NodeId::new(),
+        event_tx,
+        log_tx,
+    );
+    let queues: Vec<String> = (0..4).map(|n| format!("churn-{n}")).collect();
+    let (tx, mut rx) = mpsc::channel::<WorkerResponse>(64);
+    let worker_id = WorkerId::new();
+    let handle = WorkerHandle::new(
+        worker_id.clone(),
+        "test-worker".to_string(),
+        queues.clone(),
+        50,
+        tx,
+        String::new(),
+    );
+    dispatcher.register_worker(handle).await;
+    let d = dispatcher.clone();
+    let (id, loop_queues) = (worker_id.clone(), queues.clone());
+    tokio::spawn(async move { d.run_worker_match_loop(id, loop_queues).await });
+
+    // One registration per queue and partition, left alone while idle
+    wait_for_waiting_slots(&matching, 64).await;
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert_eq!(matching.stats().waiting_workers, 64);
+
+    for round in 0..40 {
+        let task = create_test_task(&pool, &queues[round % 4], "t").await;
+        let partition = valka_core::PartitionId(task.partition_id);
+        // Matched on offer, not on a later pass
+        assert!(
+            matching
+                .offer_task(&task.queue_name, partition, envelope_for(&task))
+                .is_ok(),
+            "round {round}: no registration waiting"
+        );
+        let assignment = recv_assignment(&mut rx).await;
+        assert_eq!(assignment.task_id, task.id);
+        // Only the slot that delivered is renewed
+        wait_for_waiting_slots(&matching, 64).await;
+    }
+    assert_eq!(matching.stats().buffered_tasks, 0);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_match_loop_withdraws_registrations_when_full(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let queue = "full-worker";
+    let (worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    wait_for_waiting_slots(&matching, 4).await;
+
+    let first = create_test_task(&pool, queue, "t").await;
+    offer(&matching, &first);
+    let assignment = recv_assignment(&mut rx).await;
+    // At capacity the worker holds no slots other workers' tasks could land in
+    wait_for_waiting_slots(&matching, 0).await;
+
+    let second = create_test_task(&pool, queue, "t").await;
+    offer(&matching, &second);
+    assert_eq!(matching.stats().buffered_tasks, 1);
+
+    let result = valka_proto::TaskResult {
+        task_id: first.id.clone(),
+        task_run_id: assignment.task_run_id,
+        success: true,
+        ..Default::default()
+    };
+    dispatcher.handle_task_result(&worker_id, result).await;
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, second.id);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_buffered_task_spills_back_to_pending_after_ttl(pool: PgPool) {
+    let matching = MatchingService::new(MatchingConfig {
+        buffered_task_ttl_ms: 100,
+        ..Default::default()
+    });
+    let (event_tx, _) = broadcast::channel(128);
+    let (log_tx, _) = mpsc::channel(128);
+    let dispatcher = DispatcherService::new(
+        matching.clone(),
+        pool.clone(),
+        NodeId::new(),
+        event_tx,
+        log_tx,
+    );
+    let (_shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let (_sweeper, mut spilled_rx) = matching.start_buffer_sweeper(shutdown_rx);
+
+    // Buffered as the TaskReader leaves it: DISPATCHING with no worker around
+    let task = create_test_task(&pool, "spill", "t").await;
+    tasks::update_task_status(&pool, &task.id, "DISPATCHING")
+        .await
+        .unwrap();
+    let partition = valka_core::PartitionId(task.partition_id);
+    assert!(
+        matching
+            .buffer_task(&task.queue_name, partition, envelope_for(&task))
+            .is_buffered()
+    );
+
+    let spilled = tokio::time::timeout(std::time::Duration::from_secs(2), spilled_rx.recv())
+        .await
+        .expect("buffered task was not spilled")
+        .unwrap();
+    assert_eq!(spilled.task_id, task.id);
+    assert_eq!(matching.stats().buffered_tasks, 0);
+
+    dispatcher.release_unbuffered(spilled).await;
+    let row = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(row.status, "PENDING");
+}
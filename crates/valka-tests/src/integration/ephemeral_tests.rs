@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use valka_core::{PartitionId, WorkerId};
+use valka_dispatcher::DispatcherService;
+use valka_proto::TaskResult;
+
+use super::helpers::*;
+
+fn json_req(method: &str, uri: &str, body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(json_body(body)))
+        .unwrap()
+}
+
+fn create_req(queue: &str, durability: &str) -> Request<Body> {
+    json_req(
+        "POST",
+        "/api/v1/tasks",
+        serde_json::json!({"queue_name": queue, "task_name": "warm", "durability": durability}),
+    )
+}
+
+async fn allow_ephemeral(app: &Router, queue: &str) {
+    let resp = app
+        .clone()
+        .oneshot(json_req(
+            "PUT",
+            &format!("/api/v1/queues/{queue}"),
+            serde_json::json!({"allow_ephemeral": true}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await["allow_ephemeral"], true);
+}
+
+async fn complete(
+    dispatcher: &DispatcherService,
+    worker_id: &WorkerId,
+    task_id: &str,
+    run_id: &str,
+) {
+    let result = TaskResult {
+        task_id: task_id.to_string(),
+        task_run_id: run_id.to_string(),
+        success: true,
+        retryable: false,
+        output: r#"{"ok":true}"#.to_string(),
+        error_message: String::new(),
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
+    };
+    dispatcher.handle_task_result(worker_id, result).await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_ephemeral_assigns_before_any_write(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    allow_ephemeral(&app, "cache-warm").await;
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "cache-warm", 1).await;
+
+    // Block every write to tasks; reads still go through
+    let mut lock = pool.begin().await.unwrap();
+    sqlx::query("LOCK TABLE tasks IN SHARE MODE")
+        .execute(&mut *lock)
+        .await
+        .unwrap();
+
+    // Skipping the insert and the dispatch transaction, the task reaches the
+    // worker while nothing can be written
+    let resp = app
+        .oneshot(create_req("cache-warm", "ephemeral"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let task_id = parse_response_json(resp).await["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task_id);
+    assert!(
+        valka_db::queries::tasks::get_task(&pool, &task_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // The write-behind lands once writes are possible again
+    lock.rollback().await.unwrap();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while valka_db::queries::tasks::get_task(&pool, &task_id)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        assert!(tokio::time::Instant::now() < deadline, "task never written");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_ephemeral_write_behind_records_task_and_run(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    allow_ephemeral(&app, "cache-warm").await;
+    let (worker_id, mut rx) = start_worker(&dispatcher, "cache-warm", 1).await;
+
+    let resp = app
+        .oneshot(create_req("cache-warm", "ephemeral"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["durability"], "ephemeral");
+    assert_eq!(body["status"], "DISPATCHING");
+    let task_id = body["id"].as_str().unwrap().to_string();
+
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task_id);
+    assert_eq!(assignment.attempt_number, 1);
+
+    // The result waits for the write-behind instead of updating a missing row
+    complete(&dispatcher, &worker_id, &task_id, &assignment.task_run_id).await;
+
+    let task = valka_db::queries::tasks::get_task(&pool, &task_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.status, "COMPLETED");
+    assert_eq!(task.durability, "ephemeral");
+    assert_eq!(task.attempt_count, 1);
+    assert_eq!(task.output, Some(serde_json::json!({"ok": true})));
+
+    let runs = valka_db::queries::task_runs::get_runs_for_task(&pool, &task_id)
+        .await
+        .unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].id, assignment.task_run_id);
+    assert_eq!(runs[0].status, "COMPLETED");
+    assert_eq!(runs[0].worker_id, worker_id.0);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_ephemeral_unmatched_falls_back_to_pending_row(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+    allow_ephemeral(&app, "cache-warm").await;
+
+    // No worker waiting: the task is persisted like a durable one
+    let resp = app
+        .oneshot(create_req("cache-warm", "ephemeral"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["status"], "PENDING");
+    assert_eq!(body["durability"], "ephemeral");
+
+    let task = valka_db::queries::tasks::get_task(&pool, body["id"].as_str().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.status, "PENDING");
+    assert_eq!(task.durability, "ephemeral");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_ephemeral_task_lost_if_node_dies_before_dispatch(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher);
+    allow_ephemeral(&app, "cache-warm").await;
+
+    // A bare slot stands in for a worker whose node crashes before dispatching
+    let mut slots: Vec<_> = (0..matching.config().num_partitions)
+        .map(|p| matching.register_worker("cache-warm", PartitionId(p), WorkerId::new()))
+        .collect();
+
+    let resp = app
+        .oneshot(create_req("cache-warm", "ephemeral"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let task_id = parse_response_json(resp).await["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let envelope = slots
+        .iter_mut()
+        .find_map(|rx| rx.try_recv().ok())
+        .expect("task offered to the waiting slot");
+    assert_eq!(envelope.task_id, task_id);
+    drop(envelope);
+
+    // Unlike durable tasks, nothing in PG lets another node recover it
+    let task = valka_db::queries::tasks::get_task(&pool, &task_id)
+        .await
+        .unwrap();
+    assert!(task.is_none());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_ephemeral_requires_queue_opt_in(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+
+    let resp = app
+        .clone()
+        .oneshot(create_req("cache-warm", "ephemeral"))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "does not allow ephemeral",
+    )
+    .await;
+
+    allow_ephemeral(&app, "cache-warm").await;
+    let resp = app
+        .clone()
+        .oneshot(json_req(
+            "POST",
+            "/api/v1/tasks",
+            serde_json::json!({
+                "queue_name": "cache-warm",
+                "task_name": "warm",
+                "durability": "ephemeral",
+                "scheduled_at": "2030-01-01T00:00:00Z",
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "cannot be scheduled",
+    )
+    .await;
+
+    // Durable creation on the same queue is unaffected
+    let resp = app
+        .oneshot(create_req("cache-warm", "durable"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(parse_response_json(resp).await["durability"], "durable");
+}
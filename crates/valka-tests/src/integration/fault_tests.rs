@@ -0,0 +1,344 @@
+//! Failure scenarios driven by fault points. The registry is process-wide, so
+//! every test uses its own queue (the key faults are matched on).
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tower::ServiceExt;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::faults::{self, Fault};
+use valka_core::{MatchingConfig, NodeId, PartitionId};
+use valka_db::queries::tasks::TaskRow;
+use valka_matching::MatchingService;
+use valka_matching::task_reader::TaskReader;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+fn offer(matching: &MatchingService, task: &TaskRow) {
+    let partition = PartitionId(task.partition_id);
+    if let Err(envelope) = matching.offer_task(&task.queue_name, partition, envelope_for(task)) {
+        matching.buffer_task(&task.queue_name, partition, envelope);
+    }
+}
+
+async fn get_task(pool: &PgPool, task_id: &str) -> TaskRow {
+    valka_db::queries::tasks::get_task(pool, task_id)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+async fn wait_for_status(pool: &PgPool, task_id: &str, status: &str) -> TaskRow {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let task = get_task(pool, task_id).await;
+        if task.status == status {
+            return task;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "task {task_id} is {}, expected {status}",
+            task.status
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Run a TaskReader for `task`'s queue and partition, polling every 20ms.
+fn start_reader(pool: &PgPool, matching: &MatchingService, task: &TaskRow) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let config = MatchingConfig {
+        task_reader_poll_busy_ms: 20,
+        task_reader_poll_idle_ms: 20,
+        ..MatchingConfig::default()
+    };
+    let reader = TaskReader::new(
+        pool.clone(),
+        matching.clone(),
+        task.queue_name.clone(),
+        PartitionId(task.partition_id),
+        config,
+        shutdown_rx,
+    );
+    tokio::spawn(reader.run());
+    shutdown_tx
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fault_commit_failure_after_run_insert_releases_task(pool: PgPool) {
+    const POINT: &str = "dispatcher.record_dispatch.before_commit";
+    let queue = "fault-commit";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (_worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+
+    let task = create_test_task(&pool, queue, "t").await;
+    // Claimed by the TaskReader's dequeue
+    let claimed = valka_db::queries::tasks::dequeue_tasks(&pool, queue, task.partition_id, 10)
+        .await
+        .unwrap();
+    assert_eq!(claimed[0].status, "DISPATCHING");
+
+    let _fault = faults::inject(POINT, queue, Fault::error().once());
+    offer(&matching, &task);
+
+    // Rolled back and handed back rather than left DISPATCHING
+    let released = wait_for_status(&pool, &task.id, "PENDING").await;
+    assert_eq!(faults::hits(POINT, queue), 1);
+    assert_eq!(released.attempt_count, 0);
+    let runs = valka_db::queries::task_runs::get_runs_for_task(&pool, &task.id)
+        .await
+        .unwrap();
+    assert!(runs.is_empty());
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .is_err(),
+        "assignment sent for an unrecorded dispatch"
+    );
+
+    // The next dispatch goes through
+    offer(&matching, &released);
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task.id);
+    assert_eq!(assignment.attempt_number, 1);
+    assert_eq!(get_task(&pool, &task.id).await.status, "RUNNING");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fault_forward_error_retried_once(pool: PgPool) {
+    const POINT: &str = "forwarder.forward_task";
+    let queue = "fault-forward-retry";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19883".parse().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let (_worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let task = create_test_task(&pool, queue, "t").await;
+
+    let _fault = faults::inject(POINT, queue, Fault::error().once());
+    let accepted = NodeForwarder::new()
+        .forward_task(&addr.to_string(), &task.id, queue, task.partition_id)
+        .await
+        .unwrap();
+
+    // The first attempt failed, the retry landed on the waiting worker
+    assert!(accepted);
+    assert_eq!(faults::hits(POINT, queue), 2);
+    assert_eq!(recv_assignment(&mut rx).await.task_id, task.id);
+    let _ = shutdown_tx.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fault_forward_failure_falls_back_to_task_reader(pool: PgPool) {
+    const POINT: &str = "forwarder.forward_task";
+    let queue = "fault-forward-fallback";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (_worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let task = create_test_task(&pool, queue, "t").await;
+
+    // Never reaches the (nonexistent) owner
+    let _fault = faults::inject(POINT, queue, Fault::error());
+    let forwarded = NodeForwarder::new()
+        .forward_task("127.0.0.1:1", &task.id, queue, task.partition_id)
+        .await;
+    assert!(forwarded.is_err());
+    assert_eq!(faults::hits(POINT, queue), 2);
+    assert_eq!(get_task(&pool, &task.id).await.status, "PENDING");
+
+    // The stored task is still picked up by polling
+    let reader = start_reader(&pool, &matching, &task);
+    assert_eq!(recv_assignment(&mut rx).await.task_id, task.id);
+    let _ = reader.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fault_rest_insert_failure_returns_500(pool: PgPool) {
+    const POINT: &str = "rest.create_task.before_insert";
+    let queue = "fault-rest-insert";
+    let app = build_test_router(pool.clone());
+    let create = || {
+        axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/v1/tasks")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(json_body(
+                serde_json::json!({"queue_name": queue, "task_name": "t"}),
+            )))
+            .unwrap()
+    };
+
+    let fault = faults::inject(POINT, queue, Fault::error());
+    let resp = app.clone().oneshot(create()).await.unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "INTERNAL_ERROR",
+        "Injected fault",
+    )
+    .await;
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tasks WHERE queue_name = $1")
+        .bind(queue)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    drop(fault);
+    let resp = app.oneshot(create()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(faults::hits(POINT, queue), 2);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fault_result_commit_failure_keeps_run_open(pool: PgPool) {
+    const POINT: &str = "dispatcher.task_result.before_commit";
+    let queue = "fault-result-commit";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let task = create_test_task(&pool, queue, "t").await;
+    offer(&matching, &task);
+    let assignment = recv_assignment(&mut rx).await;
+
+    let result = valka_proto::TaskResult {
+        task_id: task.id.clone(),
+        task_run_id: assignment.task_run_id.clone(),
+        success: true,
+        retryable: false,
+        output: r#"{"ok":true}"#.to_string(),
+        error_message: String::new(),
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
+    };
+    let fault = faults::inject(POINT, &task.id, Fault::error());
+    dispatcher
+        .handle_task_result(&worker_id, result.clone())
+        .await;
+
+    // Neither half of the completion was written
+    assert_eq!(get_task(&pool, &task.id).await.status, "RUNNING");
+    let runs = valka_db::queries::task_runs::get_runs_for_task(&pool, &task.id)
+        .await
+        .unwrap();
+    assert_eq!(runs[0].status, "RUNNING");
+
+    // A redelivered result completes it
+    drop(fault);
+    dispatcher.handle_task_result(&worker_id, result).await;
+    let task = get_task(&pool, &task.id).await;
+    assert_eq!(task.status, "COMPLETED");
+    assert_eq!(task.output, Some(serde_json::json!({"ok": true})));
+    assert_eq!(faults::hits(POINT, &task.id), 2);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fault_stream_lost_after_assignment_is_reaped(pool: PgPool) {
+    const POINT: &str = "dispatcher.before_send";
+    let queue = "fault-send";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let first = create_test_task(&pool, queue, "t").await;
+
+    let fault = faults::inject(POINT, queue, Fault::error().once());
+    offer(&matching, &first);
+    wait_for_status(&pool, &first.id, "RUNNING").await;
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .is_err(),
+        "assignment delivered despite the lost stream"
+    );
+    drop(fault);
+
+    // The run outlives the lost assignment until its lease is reaped
+    let second = create_test_task(&pool, queue, "t").await;
+    offer(&matching, &second);
+    sqlx::query(
+        "UPDATE task_runs SET lease_expires_at = NOW() - INTERVAL '1 second' WHERE task_id = $1",
+    )
+    .bind(&first.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    let reaped = valka_scheduler::reaper::reap_expired_runs(&pool, dispatcher.event_tx())
+        .await
+        .unwrap();
+    assert_eq!(reaped.len(), 1);
+    assert_eq!(get_task(&pool, &first.id).await.status, "RETRY");
+    for run in &reaped {
+        assert!(dispatcher.release_task(&run.worker_id, &run.task_id, "reaped"));
+    }
+    assert_eq!(reaped[0].worker_id, worker_id.0);
+    assert_eq!(recv_assignment(&mut rx).await.task_id, second.id);
+    assert_eq!(faults::hits(POINT, queue), 2);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fault_task_reader_survives_poll_errors(pool: PgPool) {
+    const POINT: &str = "task_reader.poll";
+    let queue = "fault-reader-poll";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (_worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let task = create_test_task(&pool, queue, "t").await;
+
+    let _fault = faults::inject(POINT, queue, Fault::error().times(3));
+    let reader = start_reader(&pool, &matching, &task);
+
+    assert_eq!(recv_assignment(&mut rx).await.task_id, task.id);
+    assert!(faults::hits(POINT, queue) >= 4);
+    let _ = reader.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fault_slow_reject_commit_still_requeues(pool: PgPool) {
+    const POINT: &str = "dispatcher.requeue_rejected.before_commit";
+    let queue = "fault-reject-commit";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let task = create_test_task(&pool, queue, "t").await;
+    offer(&matching, &task);
+    let assignment = recv_assignment(&mut rx).await;
+
+    let _fault = faults::inject(
+        POINT,
+        &task.id,
+        Fault::delay(Duration::from_millis(300)).once(),
+    );
+    let started = tokio::time::Instant::now();
+    let rejected = valka_proto::TaskResult {
+        task_id: task.id.clone(),
+        task_run_id: assignment.task_run_id,
+        rejected: true,
+        cancelled: false,
+        ..Default::default()
+    };
+    dispatcher.handle_task_result(&worker_id, rejected).await;
+
+    // A slow commit still lands
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert_eq!(get_task(&pool, &task.id).await.status, "PENDING");
+    assert_eq!(faults::hits(POINT, &task.id), 1);
+}
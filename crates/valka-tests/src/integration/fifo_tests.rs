@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tower::ServiceExt;
+use valka_core::{MatchingConfig, PartitionId, WorkerId, partition_for_task};
+use valka_db::queries::queue_configs::QueueOwnershipUpdate;
+use valka_db::queries::tasks::{self, TaskRow};
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_matching::task_reader::TaskReader;
+use valka_proto::{TaskAssignment, TaskResult, WorkerResponse};
+
+use super::helpers::*;
+
+struct Worker {
+    id: WorkerId,
+    rx: mpsc::Receiver<WorkerResponse>,
+}
+
+async fn make_fifo_queue(pool: &PgPool, dispatcher: &DispatcherService, queue: &str) {
+    let update = QueueOwnershipUpdate {
+        fifo: Some(true),
+        ..Default::default()
+    };
+    let config = valka_db::queries::queue_configs::upsert_queue_ownership(pool, queue, &update)
+        .await
+        .unwrap();
+    dispatcher.queue_policies().apply_config(&config);
+}
+
+async fn create_keyed(pool: &PgPool, queue: &str, key: &str) -> TaskRow {
+    let mut params = default_task_params(queue, "ordered");
+    params.partition_id = 0;
+    params.fifo_key = Some(key.to_string());
+    create_test_task_full(pool, params).await
+}
+
+/// Run a TaskReader for one partition of `queue`, polling every 20ms.
+fn start_reader(
+    pool: &PgPool,
+    matching: &MatchingService,
+    queue: &str,
+    partition: i32,
+) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let config = MatchingConfig {
+        task_reader_poll_busy_ms: 20,
+        task_reader_poll_idle_ms: 20,
+        ..MatchingConfig::default()
+    };
+    let reader = TaskReader::new(
+        pool.clone(),
+        matching.clone(),
+        queue.to_string(),
+        PartitionId(partition),
+        config,
+        shutdown_rx,
+    );
+    tokio::spawn(reader.run());
+    shutdown_tx
+}
+
+async fn start_workers(dispatcher: &DispatcherService, queue: &str) -> [Worker; 2] {
+    let (id, rx) = start_worker(dispatcher, queue, 1).await;
+    let first = Worker { id, rx };
+    let (id, rx) = start_worker(dispatcher, queue, 1).await;
+    [first, Worker { id, rx }]
+}
+
+/// The next assignment on either worker, with the worker's index.
+async fn next_assignment(workers: &mut [Worker; 2]) -> (usize, TaskAssignment) {
+    let [a, b] = workers;
+    tokio::select! {
+        assignment = recv_assignment(&mut a.rx) => (0, assignment),
+        assignment = recv_assignment(&mut b.rx) => (1, assignment),
+    }
+}
+
+/// Give the readers a few polls, then check nothing else was handed out.
+async fn assert_no_assignment(workers: &mut [Worker; 2]) {
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    for worker in workers.iter_mut() {
+        assert!(worker.rx.try_recv().is_err(), "unexpected assignment");
+    }
+}
+
+async fn report(
+    dispatcher: &DispatcherService,
+    worker: &Worker,
+    assignment: &TaskAssignment,
+    success: bool,
+) {
+    let result = TaskResult {
+        task_id: assignment.task_id.clone(),
+        task_run_id: assignment.task_run_id.clone(),
+        success,
+        output: String::new(),
+        error_message: if success {
+            String::new()
+        } else {
+            "boom".to_string()
+        },
+        retryable: !success,
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
+    };
+    dispatcher.handle_task_result(&worker.id, result).await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fifo_same_key_runs_in_order_across_workers(pool: PgPool) {
+    let queue = "fifo-order";
+    let key = "customer-42";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    make_fifo_queue(&pool, &dispatcher, queue).await;
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    let mut workers = start_workers(&dispatcher, queue).await;
+
+    // Created over REST, so the first goes out on the hot path
+    let mut created = Vec::new();
+    for _ in 0..3 {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/tasks")
+                    .header("content-type", "application/json")
+                    .body(Body::from(json_body(serde_json::json!({
+                        "queue_name": queue,
+                        "task_name": "ordered",
+                        "fifo_key": key,
+                    }))))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let json = parse_response_json(resp).await;
+        assert_eq!(json["fifo_key"], key);
+        created.push(json["id"].as_str().unwrap().to_string());
+    }
+    let partition = partition_for_task(queue, key, matching.config().num_partitions);
+    let _reader = start_reader(&pool, &matching, queue, partition.0);
+
+    let mut order = Vec::new();
+    for _ in 0..3 {
+        let (idx, assignment) = next_assignment(&mut workers).await;
+        // One at a time: the other worker stays idle while this one runs
+        assert_no_assignment(&mut workers).await;
+        order.push(assignment.task_id.clone());
+        report(&dispatcher, &workers[idx], &assignment, true).await;
+    }
+    assert_eq!(order, created);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fifo_different_keys_interleave(pool: PgPool) {
+    let queue = "fifo-interleave";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    make_fifo_queue(&pool, &dispatcher, queue).await;
+    let a1 = create_keyed(&pool, queue, "a").await;
+    let a2 = create_keyed(&pool, queue, "a").await;
+    let b1 = create_keyed(&pool, queue, "b").await;
+    let b2 = create_keyed(&pool, queue, "b").await;
+    let mut workers = start_workers(&dispatcher, queue).await;
+    let _reader = start_reader(&pool, &matching, queue, 0);
+
+    // The head of each key runs at once; the second of each waits
+    let first = [
+        next_assignment(&mut workers).await,
+        next_assignment(&mut workers).await,
+    ];
+    let mut heads: Vec<_> = first.iter().map(|(_, a)| a.task_id.clone()).collect();
+    heads.sort();
+    let mut expected = vec![a1.id, b1.id];
+    expected.sort();
+    assert_eq!(heads, expected);
+    assert_no_assignment(&mut workers).await;
+
+    for (idx, assignment) in &first {
+        report(&dispatcher, &workers[*idx], assignment, true).await;
+    }
+    let mut tails = vec![
+        next_assignment(&mut workers).await.1.task_id,
+        next_assignment(&mut workers).await.1.task_id,
+    ];
+    tails.sort();
+    let mut expected = vec![a2.id, b2.id];
+    expected.sort();
+    assert_eq!(tails, expected);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fifo_retry_blocks_key_until_resolved(pool: PgPool) {
+    let queue = "fifo-retry";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    make_fifo_queue(&pool, &dispatcher, queue).await;
+    let first = create_keyed(&pool, queue, "k").await;
+    let second = create_keyed(&pool, queue, "k").await;
+    let mut workers = start_workers(&dispatcher, queue).await;
+    let _reader = start_reader(&pool, &matching, queue, 0);
+
+    let (idx, assignment) = next_assignment(&mut workers).await;
+    assert_eq!(assignment.task_id, first.id);
+    report(&dispatcher, &workers[idx], &assignment, false).await;
+
+    // Waiting on its retry still holds the key
+    assert_no_assignment(&mut workers).await;
+    let waiting = tasks::get_task(&pool, &second.id).await.unwrap().unwrap();
+    assert_eq!(waiting.status, "PENDING");
+
+    // What the scheduler does once the backoff has passed
+    tasks::schedule_retry(
+        &pool,
+        &first.id,
+        chrono::Utc::now() - chrono::Duration::seconds(1),
+    )
+    .await
+    .unwrap();
+    tasks::promote_delayed_tasks(&pool).await.unwrap();
+
+    let (idx, assignment) = next_assignment(&mut workers).await;
+    assert_eq!(assignment.task_id, first.id);
+    assert_eq!(assignment.attempt_number, 2);
+    assert_no_assignment(&mut workers).await;
+    report(&dispatcher, &workers[idx], &assignment, true).await;
+
+    let (_, assignment) = next_assignment(&mut workers).await;
+    assert_eq!(assignment.task_id, second.id);
+}
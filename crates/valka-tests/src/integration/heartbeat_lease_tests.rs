@@ -0,0 +1,112 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::Instant;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::{task_runs, tasks};
+use valka_sdk::ValkaWorker;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_worker_heartbeats_keep_long_task_leased(pool: PgPool) {
+    let queue = "heartbeat-lease";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19901".parse().unwrap();
+    let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let worker = ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&[queue])
+        .heartbeat_interval(Duration::from_secs(1))
+        .handler(|_ctx| async move {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(serde_json::json!({ "done": true }))
+        })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(worker.run());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let task = create_test_task(&pool, queue, "long").await;
+    let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope_for(&task));
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let run = loop {
+        let runs = task_runs::get_runs_for_task(&pool, &task.id).await.unwrap();
+        if let Some(run) = runs.into_iter().next() {
+            break run;
+        }
+        assert!(Instant::now() < deadline, "task never started");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+
+    // Shrink the lease well below the handler's runtime
+    sqlx::query(
+        "UPDATE task_runs SET lease_expires_at = NOW() + INTERVAL '1500 milliseconds' WHERE id = $1",
+    )
+    .bind(&run.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let mut last = None;
+    for _ in 0..3 {
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        let runs = task_runs::get_runs_for_task(&pool, &task.id).await.unwrap();
+        assert_eq!(runs.len(), 1, "task was requeued");
+        let lease = runs[0].lease_expires_at;
+        assert!(lease > chrono::Utc::now(), "lease lapsed at {lease}");
+        if let Some(previous) = last {
+            assert!(lease > previous, "lease stopped moving: {lease}");
+        }
+        last = Some(lease);
+
+        assert_eq!(
+            valka_scheduler::reaper::reap_expired_leases(&pool, &broadcast::channel(16).0)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let row = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+        if row.status == "COMPLETED" {
+            break;
+        }
+        assert_eq!(row.status, "RUNNING");
+        assert!(Instant::now() < deadline, "task never finished");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(
+        task_runs::get_runs_for_task(&pool, &task.id)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
@@ -3,14 +3,22 @@ use std::sync::Arc;
 use axum::Router;
 use axum::http::StatusCode;
 use chrono::{DateTime, Duration, Utc};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
 use sqlx::PgPool;
 use tokio::sync::{broadcast, mpsc};
+use tracing_subscriber::layer::SubscriberExt;
 use valka_cluster::{ClusterManager, NodeForwarder};
-use valka_core::{MatchingConfig, NodeId, TaskId, partition_for_task};
+use valka_core::{MatchingConfig, NodeId, ServerConfig, TaskId, WorkerId, partition_for_task};
 use valka_db::queries::task_runs::{CreateTaskRunParams, TaskRunRow};
 use valka_db::queries::tasks::{CreateTaskParams, TaskRow};
 use valka_dispatcher::DispatcherService;
+use valka_dispatcher::worker_handle::WorkerHandle;
 use valka_matching::MatchingService;
+use valka_matching::partition::TaskEnvelope;
+use valka_proto::{TaskAssignment, WorkerResponse, worker_response};
+use valka_server::admin::AdminAuth;
+use valka_server::reload::ConfigReloader;
 
 /// Create a task with sensible defaults. Returns the inserted TaskRow.
 pub async fn create_test_task(pool: &PgPool, queue: &str, name: &str) -> TaskRow {
@@ -30,6 +38,13 @@ pub async fn create_test_task(pool: &PgPool, queue: &str, name: &str) -> TaskRow
             idempotency_key: None,
             metadata: serde_json::json!({}),
             scheduled_at: None,
+            deadline_at: None,
+            traceparent: None,
+            durability: Default::default(),
+            fifo_key: None,
+            callback_url: None,
+            retry_backoff: Default::default(),
+            required_labels: Default::default(),
         },
     )
     .await
@@ -90,6 +105,41 @@ pub fn build_test_router(pool: PgPool) -> Router {
         log_tx,
     );
 
+    build_test_router_with(pool, matching, dispatcher)
+}
+
+/// Build the REST router around existing services, so a test can also drive
+/// the dispatcher the router hands tasks to.
+pub fn build_test_router_with(
+    pool: PgPool,
+    matching: MatchingService,
+    dispatcher: DispatcherService,
+) -> Router {
+    build_test_router_admin(pool, matching, dispatcher, AdminAuth::default())
+}
+
+/// [`build_test_router_with`], gating admin endpoints with `admin`.
+pub fn build_test_router_admin(
+    pool: PgPool,
+    matching: MatchingService,
+    dispatcher: DispatcherService,
+    admin: AdminAuth,
+) -> Router {
+    let reloader = ConfigReloader::new(ServerConfig::default(), || Ok(ServerConfig::default()));
+    build_test_router_reloader(pool, matching, dispatcher, admin, reloader)
+}
+
+/// [`build_test_router_admin`], reloading config through `reloader`.
+pub fn build_test_router_reloader(
+    pool: PgPool,
+    matching: MatchingService,
+    dispatcher: DispatcherService,
+    admin: AdminAuth,
+    reloader: ConfigReloader,
+) -> Router {
+    let node_id = NodeId::new();
+    let event_tx = dispatcher.event_tx().clone();
+
     let metrics_handle = metrics_exporter_prometheus::PrometheusBuilder::new()
         .build_recorder()
         .handle();
@@ -108,9 +158,34 @@ pub fn build_test_router(pool: PgPool) -> Router {
         metrics_handle,
         cluster,
         forwarder,
+        admin,
+        reloader,
     )
 }
 
+/// Minimal matching envelope for a stored task.
+pub fn envelope_for(task: &TaskRow) -> TaskEnvelope {
+    TaskEnvelope {
+        task_id: task.id.clone(),
+        task_run_id: String::new(),
+        queue_name: task.queue_name.clone(),
+        task_name: task.task_name.clone(),
+        input: None,
+        attempt_number: 1,
+        timeout_seconds: task.timeout_seconds,
+        metadata: "{}".to_string(),
+        priority: task.priority,
+        deadline_ms: task.deadline_at.map(|d| d.timestamp_millis()),
+        ready_at_ms: Some(task.ready_at().timestamp_millis()),
+        created_at_ms: Some(task.created_at.timestamp_millis()),
+        scheduled_at_ms: task.scheduled_at.map(|at| at.timestamp_millis()),
+        traceparent: task.traceparent.clone(),
+        fifo_key: task.fifo_key.clone(),
+        required_labels: task.required_labels(),
+        ephemeral: None,
+    }
+}
+
 /// Convert a serde_json::Value into an axum-compatible request body.
 pub fn json_body(value: serde_json::Value) -> String {
     serde_json::to_string(&value).unwrap()
@@ -139,13 +214,14 @@ pub async fn assert_error_response(
         .unwrap();
     let json: serde_json::Value =
         serde_json::from_slice(&body).expect("Error response should be valid JSON");
+    let error = &json["error"];
     assert_eq!(
-        json["code"].as_str().unwrap(),
+        error["code"].as_str().unwrap(),
         expected_code,
         "Expected error code {expected_code}, got {:?}",
-        json["code"]
+        error["code"]
     );
-    let error_msg = json["error"].as_str().unwrap();
+    let error_msg = error["message"].as_str().unwrap();
     assert!(
         error_msg.contains(message_contains),
         "Expected error message to contain '{message_contains}', got '{error_msg}'"
@@ -168,6 +244,13 @@ pub fn default_task_params(queue: &str, name: &str) -> CreateTaskParams {
         idempotency_key: None,
         metadata: serde_json::json!({}),
         scheduled_at: None,
+        deadline_at: None,
+        traceparent: None,
+        durability: Default::default(),
+        fifo_key: None,
+        callback_url: None,
+        retry_backoff: Default::default(),
+        required_labels: Default::default(),
     }
 }
 
@@ -192,3 +275,79 @@ pub async fn create_test_run(
     .await
     .expect("create_test_run failed")
 }
+
+/// Record spans from this thread into an in-memory exporter until the guard drops.
+/// `#[sqlx::test]` runs on a current-thread runtime, so spawned tasks are covered too.
+pub fn capture_spans() -> (
+    InMemorySpanExporter,
+    SdkTracerProvider,
+    tracing::subscriber::DefaultGuard,
+) {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("valka-tests")));
+    let guard = tracing::subscriber::set_default(subscriber);
+    (exporter, provider, guard)
+}
+
+/// Finished span named `name`; panics if it was not recorded.
+pub fn finished_span(spans: &[SpanData], name: &str) -> SpanData {
+    spans
+        .iter()
+        .find(|s| s.name == name)
+        .cloned()
+        .unwrap_or_else(|| {
+            let names: Vec<_> = spans.iter().map(|s| s.name.as_ref()).collect();
+            panic!("no {name} span in {names:?}")
+        })
+}
+
+/// A dispatcher and its matching service on a fresh node.
+pub fn make_dispatcher(pool: PgPool) -> (DispatcherService, MatchingService) {
+    let matching = MatchingService::new(MatchingConfig::default());
+    let (event_tx, _) = broadcast::channel::<valka_proto::TaskEvent>(128);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    let dispatcher =
+        DispatcherService::new(matching.clone(), pool, NodeId::new(), event_tx, log_tx);
+    (dispatcher, matching)
+}
+
+/// Register a worker on `queue` and start its match loop.
+pub async fn start_worker(
+    dispatcher: &DispatcherService,
+    queue: &str,
+    concurrency: i32,
+) -> (WorkerId, mpsc::Receiver<WorkerResponse>) {
+    let (tx, rx) = mpsc::channel::<WorkerResponse>(16);
+    let worker_id = WorkerId::new();
+    let handle = WorkerHandle::new(
+        worker_id.clone(),
+        "test-worker".to_string(),
+        vec![queue.to_string()],
+        concurrency,
+        tx,
+        String::new(),
+    );
+    dispatcher.register_worker(handle).await;
+
+    let d = dispatcher.clone();
+    let (id, queues) = (worker_id.clone(), vec![queue.to_string()]);
+    tokio::spawn(async move { d.run_worker_match_loop(id, queues).await });
+    // Let the loop register as a waiting worker
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    (worker_id, rx)
+}
+
+pub async fn recv_assignment(rx: &mut mpsc::Receiver<WorkerResponse>) -> TaskAssignment {
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .expect("assignment not received")
+        .unwrap();
+    let Some(worker_response::Response::TaskAssignment(assignment)) = response.response else {
+        panic!("Expected TaskAssignment");
+    };
+    assignment
+}
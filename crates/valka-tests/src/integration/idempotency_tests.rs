@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::sync::watch;
+use tonic::transport::Channel;
+use tower::ServiceExt;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::NodeId;
+use valka_proto::CreateTaskRequest;
+use valka_proto::api_service_client::ApiServiceClient;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+fn create_req(body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/api/v1/tasks")
+        .header("content-type", "application/json")
+        .body(Body::from(json_body(body)))
+        .unwrap()
+}
+
+async fn count_tasks(pool: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM tasks")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_duplicate_idempotency_key_returns_existing_task(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+
+    let resp = app
+        .clone()
+        .oneshot(create_req(serde_json::json!({
+            "queue_name": "billing",
+            "task_name": "charge",
+            "input": {"amount": 100},
+            "idempotency_key": "order-7",
+        })))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let first = parse_response_json(resp).await;
+    assert!(first.get("deduplicated").is_none());
+
+    // The retry differs in input; the original task comes back untouched
+    let resp = app
+        .oneshot(create_req(serde_json::json!({
+            "queue_name": "billing",
+            "task_name": "charge",
+            "input": {"amount": 200},
+            "idempotency_key": "order-7",
+        })))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let second = parse_response_json(resp).await;
+    assert_eq!(second["id"], first["id"]);
+    assert_eq!(second["input"]["amount"], 100);
+    assert_eq!(second["deduplicated"], true);
+    assert_eq!(count_tasks(&pool).await, 1);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_grpc_duplicate_idempotency_key_returns_existing_task(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let node_id = NodeId::new();
+    let addr = "127.0.0.1:19888".parse().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching,
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id, 4)),
+        NodeForwarder::new(),
+        dispatcher.log_tx().clone(),
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut api = ApiServiceClient::new(channel);
+    let request = CreateTaskRequest {
+        queue_name: "billing".to_string(),
+        task_name: "charge".to_string(),
+        idempotency_key: "order-8".to_string(),
+        ..Default::default()
+    };
+
+    let first = api.create_task(request.clone()).await.unwrap().into_inner();
+    assert!(!first.deduplicated);
+    let second = api.create_task(request).await.unwrap().into_inner();
+    assert!(second.deduplicated);
+    assert_eq!(second.task.unwrap().id, first.task.unwrap().id);
+    assert_eq!(count_tasks(&pool).await, 1);
+    let _ = shutdown_tx.send(true);
+}
@@ -83,6 +83,10 @@ async fn test_task_create_dispatch_complete(pool: PgPool) {
         output: serde_json::json!({"processed": true}).to_string(),
         error_message: String::new(),
         retryable: false,
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
     };
     dispatcher.handle_task_result(&worker_id, result).await;
 
@@ -227,7 +231,7 @@ async fn test_task_exhaust_retries_to_dlq(pool: PgPool) {
         .unwrap();
 
     // Process DLQ
-    let count = valka_scheduler::dlq::process_dead_letters(&pool)
+    let count = valka_scheduler::dlq::process_dead_letters(&pool, &broadcast::channel(16).0)
         .await
         .unwrap();
     assert_eq!(count, 1);
@@ -289,7 +293,7 @@ async fn test_cancel_running_task_via_dispatcher(pool: PgPool) {
 
     // Assign task to worker
     if let Some(mut h) = dispatcher.workers().get_mut(worker_id.as_ref()) {
-        h.assign_task(task.id.clone());
+        h.assign_task(task.id.clone(), &task.queue_name);
     }
 
     // Cancel via dispatcher
@@ -1,13 +1,47 @@
 mod helpers;
 
+mod auth_tests;
+mod batch_handler_tests;
+mod blob_storage_tests;
+mod client_tests;
+mod config_reload_tests;
+mod create_limits_tests;
 mod db_dead_letter_tests;
 mod db_signals_tests;
 mod db_task_logs_tests;
 mod db_task_runs_tests;
 mod db_tasks_tests;
+mod dependency_tests;
+mod dispatch_decision_tests;
 mod dispatcher_tests;
+mod ephemeral_tests;
+mod fault_tests;
+mod fifo_tests;
+mod health_tests;
+mod heartbeat_lease_tests;
+mod idempotency_tests;
 mod lifecycle_tests;
+mod queue_discovery_tests;
+mod reclaim_tests;
+mod result_limits_tests;
+mod retention_tests;
 mod rest_api_tests;
+mod schedule_tests;
 mod scheduler_tests;
+mod shutdown_tests;
+mod signal_delivery_tests;
+mod subscribe_logs_tests;
+mod task_cancellation_tests;
+mod task_log_tests;
+mod task_reader_tests;
+mod task_stream_tests;
+mod tracing_tests;
+mod typed_handler_tests;
+mod usage_tests;
+mod webhook_tests;
+mod worker_drain_tests;
+mod worker_list_tests;
+mod worker_protocol_tests;
+mod worker_shutdown_tests;
 
 mod cluster_tests;
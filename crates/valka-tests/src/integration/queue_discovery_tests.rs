@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use valka_cluster::ClusterManager;
+use valka_core::{MatchingConfig, NodeId};
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_server::server::{publish_queue_backlog, run_task_reader_manager};
+
+use super::helpers::*;
+
+/// Run the TaskReader manager with fast reader polling and wait until it is
+/// subscribed to new queue notifications.
+async fn start_manager(
+    pool: &PgPool,
+    dispatcher: &DispatcherService,
+    matching: &MatchingService,
+    fallback_ms: u64,
+) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let config = MatchingConfig {
+        task_reader_poll_busy_ms: 20,
+        task_reader_poll_idle_ms: 20,
+        queue_discovery_fallback_ms: fallback_ms,
+        ..MatchingConfig::default()
+    };
+    tokio::spawn(run_task_reader_manager(
+        pool.clone(),
+        matching.clone(),
+        dispatcher.clone(),
+        watch::channel(config).1,
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        shutdown_rx,
+    ));
+    wait_for_listener(pool, None).await;
+    shutdown_tx
+}
+
+/// Backends of this test database currently holding a LISTEN.
+async fn listener_pids(pool: &PgPool) -> Vec<i32> {
+    sqlx::query_scalar(
+        "SELECT pid FROM pg_stat_activity \
+         WHERE datname = current_database() AND query LIKE 'LISTEN%'",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+}
+
+/// Wait for a listening backend other than `previous`; returns its pid.
+async fn wait_for_listener(pool: &PgPool, previous: Option<i32>) -> i32 {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        if let Some(pid) = listener_pids(pool)
+            .await
+            .into_iter()
+            .find(|pid| Some(*pid) != previous)
+        {
+            return pid;
+        }
+        assert!(Instant::now() < deadline, "queue listener not subscribed");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_new_queue_is_read_within_a_second(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    // The fallback never fires during the test: only the push can find the queue
+    let shutdown = start_manager(&pool, &dispatcher, &matching, 60_000).await;
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "brand-new", 1).await;
+
+    let started = Instant::now();
+    let task = create_test_task(&pool, "brand-new", "t").await;
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task.id);
+    assert!(
+        started.elapsed() < Duration::from_secs(1),
+        "first task on a new queue took {:?}",
+        started.elapsed()
+    );
+
+    // Registered once, however many tasks it gets
+    create_test_task(&pool, "brand-new", "t").await;
+    let names = valka_db::queries::queues::list_queue_names(&pool)
+        .await
+        .unwrap();
+    assert_eq!(names, ["brand-new"]);
+    let _ = shutdown.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_fallback_discovers_queue_when_notifications_are_lost(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let shutdown = start_manager(&pool, &dispatcher, &matching, 300).await;
+
+    // Kill the LISTEN connection; the manager subscribes again on a new one
+    let killed = listener_pids(&pool).await[0];
+    let terminated: bool = sqlx::query_scalar("SELECT pg_terminate_backend($1)")
+        .bind(killed)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(terminated);
+    wait_for_listener(&pool, Some(killed)).await;
+
+    // With the trigger off no notification goes out, so only the fallback sees it
+    sqlx::query("ALTER TABLE queues DISABLE TRIGGER queues_notify_insert")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "unannounced", 1).await;
+    let task = create_test_task(&pool, "unannounced", "t").await;
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task.id);
+    let _ = shutdown.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_queue_backlog_gauges(pool: PgPool) {
+    let task = create_test_task(&pool, "aged-q", "t").await;
+    sqlx::query("UPDATE tasks SET created_at = NOW() - INTERVAL '10 minutes' WHERE id = $1")
+        .bind(&task.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let known = HashSet::from(["aged-q".to_string(), "drained-q".to_string()]);
+
+    let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+    publish_queue_backlog(&pool, &known).await;
+
+    let rendered = recorder.handle().render();
+    for line in [
+        "valka_pending_tasks{queue=\"aged-q\",state=\"pending\"} 1",
+        "valka_pending_tasks{queue=\"aged-q\",state=\"retry\"} 0",
+        "valka_pending_tasks{queue=\"aged-q\",state=\"scheduled\"} 0",
+        // A known queue with nothing waiting reports zeros, not a stale value
+        "valka_pending_tasks{queue=\"drained-q\",state=\"pending\"} 0",
+        "valka_queue_oldest_pending_age_seconds{queue=\"drained-q\"} 0",
+    ] {
+        assert!(rendered.contains(line), "missing {line} in:\n{rendered}");
+    }
+    let age: f64 = rendered
+        .lines()
+        .find_map(|l| l.strip_prefix("valka_queue_oldest_pending_age_seconds{queue=\"aged-q\"} "))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((600.0..660.0).contains(&age), "{age}");
+}
@@ -0,0 +1,212 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::sync::watch;
+use tower::ServiceExt;
+use valka_core::{MatchingConfig, PartitionId};
+use valka_db::queries::task_runs;
+use valka_db::queries::tasks::{self, TaskRow};
+use valka_matching::MatchingService;
+use valka_matching::task_reader::TaskReader;
+use valka_proto::{TaskResult, WorkerResponse, worker_response};
+use valka_server::admin::AdminAuth;
+
+use super::helpers::*;
+
+fn reclaim_request(task_id: &str, query: &str, token: Option<&str>, reason: &str) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method("POST")
+        .uri(format!("/api/v1/tasks/{task_id}/reclaim{query}"))
+        .header("content-type", "application/json");
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {token}"));
+    }
+    builder
+        .body(Body::from(json_body(
+            serde_json::json!({ "reason": reason }),
+        )))
+        .unwrap()
+}
+
+/// Run a TaskReader for `task`'s queue and partition, polling every 20ms.
+fn start_reader(pool: &PgPool, matching: &MatchingService, task: &TaskRow) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let config = MatchingConfig {
+        task_reader_poll_busy_ms: 20,
+        task_reader_poll_idle_ms: 20,
+        ..MatchingConfig::default()
+    };
+    let reader = TaskReader::new(
+        pool.clone(),
+        matching.clone(),
+        task.queue_name.clone(),
+        PartitionId(task.partition_id),
+        config,
+        shutdown_rx,
+    );
+    tokio::spawn(reader.run());
+    shutdown_tx
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_reclaim_redispatches_and_ignores_late_result(pool: PgPool) {
+    let queue = "reclaim-wedged";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_admin(
+        pool.clone(),
+        matching.clone(),
+        dispatcher.clone(),
+        AdminAuth::new(Some("s3cret".to_string())),
+    );
+    let (wedged_id, mut wedged_rx) = start_worker(&dispatcher, queue, 1).await;
+    let task = create_test_task(&pool, queue, "t").await;
+    let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope_for(&task));
+    let first = recv_assignment(&mut wedged_rx).await;
+
+    // Keep the wedged worker from picking the task up again
+    dispatcher.drain_worker(&wedged_id.0, None);
+    let (_healthy_id, mut healthy_rx) = start_worker(&dispatcher, queue, 1).await;
+
+    for token in [None, Some("wrong")] {
+        let resp = app
+            .clone()
+            .oneshot(reclaim_request(
+                &task.id,
+                "?immediate=true",
+                token,
+                "wedged",
+            ))
+            .await
+            .unwrap();
+        assert_error_response(
+            resp,
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "Admin token",
+        )
+        .await;
+    }
+    assert_eq!(
+        tasks::get_task(&pool, &task.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status,
+        "RUNNING"
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(reclaim_request(
+            &task.id,
+            "?immediate=true",
+            Some("s3cret"),
+            "wedged worker",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = parse_response_json(resp).await;
+    assert_eq!(json["task"]["status"], "PENDING");
+    assert_eq!(json["worker_id"], wedged_id.0);
+    assert_eq!(json["abandoned_run_id"], first.task_run_id);
+    assert_eq!(json["worker_notified"], true);
+
+    match wedged_rx.recv().await {
+        Some(WorkerResponse {
+            response: Some(worker_response::Response::TaskCancellation(cancel)),
+        }) => {
+            assert_eq!(cancel.task_id, task.id);
+            assert_eq!(cancel.reason, "wedged worker");
+        }
+        other => panic!("expected TaskCancellation, got {other:?}"),
+    }
+    let run = task_runs::get_task_run(&pool, &first.task_run_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(run.status, "ABANDONED");
+    assert_eq!(run.error_message.as_deref(), Some("wedged worker"));
+
+    let _reader = start_reader(&pool, &matching, &task);
+    let second = recv_assignment(&mut healthy_rx).await;
+    assert_eq!(second.task_id, task.id);
+    assert_eq!(second.attempt_number, 2);
+
+    // The wedged worker finally reports; its run is closed, so nothing changes
+    let late = TaskResult {
+        task_id: task.id.clone(),
+        task_run_id: first.task_run_id.clone(),
+        success: true,
+        output: r#"{"from":"wedged"}"#.to_string(),
+        ..Default::default()
+    };
+    dispatcher.handle_task_result(&wedged_id, late).await;
+    let current = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(current.status, "RUNNING");
+    assert!(current.output.is_none());
+    let run = task_runs::get_task_run(&pool, &first.task_run_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(run.status, "ABANDONED");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_reclaim_defaults_to_retry_and_rejects_non_running(pool: PgPool) {
+    let queue = "reclaim-retry";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    // No admin token configured: open like the rest of the API
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    let (_worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let task = create_test_task(&pool, queue, "t").await;
+
+    let resp = app
+        .clone()
+        .oneshot(reclaim_request(&task.id, "", None, "stuck"))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "INVALID_STATE",
+        "PENDING, not RUNNING",
+    )
+    .await;
+    let resp = app
+        .clone()
+        .oneshot(reclaim_request("missing", "", None, "stuck"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope_for(&task));
+    recv_assignment(&mut rx).await;
+    let resp = app
+        .clone()
+        .oneshot(reclaim_request(&task.id, "", None, "  "))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "reason is required",
+    )
+    .await;
+
+    let resp = app
+        .oneshot(reclaim_request(&task.id, "", None, "stuck"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = parse_response_json(resp).await;
+    assert_eq!(json["task"]["status"], "RETRY");
+    // Its slot is free again
+    assert!(
+        dispatcher
+            .workers()
+            .iter()
+            .all(|w| w.active_tasks.is_empty())
+    );
+}
@@ -23,6 +23,15 @@ fn post_json(uri: &str, body: serde_json::Value) -> Request<Body> {
         .unwrap()
 }
 
+fn put_json(uri: &str, body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .method("PUT")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(json_body(body)))
+        .unwrap()
+}
+
 fn get_req(uri: &str) -> Request<Body> {
     Request::builder().uri(uri).body(Body::empty()).unwrap()
 }
@@ -127,415 +136,2534 @@ async fn test_rest_create_task_with_scheduled_at(pool: PgPool) {
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_create_task_defaults(pool: PgPool) {
+async fn test_rest_create_task_with_deadline(pool: PgPool) {
     let app = build_test_router(pool);
+    let before = Utc::now().timestamp_millis();
 
     let resp = app
         .oneshot(post_json(
             "/api/v1/tasks",
             serde_json::json!({
                 "queue_name": "q",
-                "task_name": "t"
+                "task_name": "t",
+                "deadline_in_ms": 60_000
             }),
         ))
         .await
         .unwrap();
 
+    assert_eq!(resp.status(), StatusCode::CREATED);
     let body = parse_response_json(resp).await;
-    assert_eq!(body["priority"], 0);
-    assert_eq!(body["max_retries"], 3);
-    assert_eq!(body["timeout_seconds"], 300);
+    assert_eq!(body["status"], "PENDING");
+    assert_eq!(body["deadline_expired"], false);
+    let deadline = body["deadline_ms"].as_i64().unwrap();
+    assert!(deadline >= before + 60_000);
 }
 
-// ─── GET /api/v1/tasks/{id} ─────────────────────────────────────────
-
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_get_task(pool: PgPool) {
-    let task = create_test_task(&pool, "q", "t").await;
-    let app = build_test_router(pool);
+async fn test_rest_create_task_expired_deadline_fails_fast(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+    let past = Utc::now().timestamp_millis() - 1_000;
 
     let resp = app
-        .oneshot(get_req(&format!("/api/v1/tasks/{}", task.id)))
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({
+                "queue_name": "q",
+                "task_name": "t",
+                "deadline_ms": past
+            }),
+        ))
         .await
         .unwrap();
 
-    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.status(), StatusCode::CREATED);
     let body = parse_response_json(resp).await;
-    assert_eq!(body["id"], task.id);
-    assert_eq!(body["queue_name"], "q");
+    assert_eq!(body["status"], "FAILED");
+    assert_eq!(
+        body["error_message"],
+        valka_core::DEADLINE_EXCEEDED_BEFORE_DISPATCH
+    );
+    assert_eq!(body["deadline_ms"], past);
+    assert_eq!(body["deadline_expired"], true);
+    assert_eq!(body["attempt_count"], 0);
+
+    let task_id = body["id"].as_str().unwrap();
+    let runs = valka_db::queries::task_runs::get_runs_for_task(&pool, task_id)
+        .await
+        .unwrap();
+    assert!(runs.is_empty(), "Expired task must never run");
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_get_task_not_found(pool: PgPool) {
+async fn test_rest_create_task_deadline_validation(pool: PgPool) {
     let app = build_test_router(pool);
 
     let resp = app
-        .oneshot(get_req("/api/v1/tasks/nonexistent-id"))
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({
+                "queue_name": "q",
+                "task_name": "t",
+                "deadline_ms": Utc::now().timestamp_millis() + 60_000,
+                "deadline_in_ms": 60_000
+            }),
+        ))
         .await
         .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "deadline",
+    )
+    .await;
 
-    assert_error_response(resp, StatusCode::NOT_FOUND, "NOT_FOUND", "Task not found").await;
+    let resp = app
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({
+                "queue_name": "q",
+                "task_name": "t",
+                "deadline_in_ms": -5
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "deadline",
+    )
+    .await;
 }
 
-// ─── GET /api/v1/tasks ──────────────────────────────────────────────
-
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_list_tasks(pool: PgPool) {
-    for i in 0..5 {
-        create_test_task(&pool, "q", &format!("t{i}")).await;
-    }
+async fn test_rest_create_task_callback_url(pool: PgPool) {
     let app = build_test_router(pool);
 
-    let resp = app.oneshot(get_req("/api/v1/tasks")).await.unwrap();
-
-    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = app
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({
+                "queue_name": "q",
+                "task_name": "t",
+                "callback_url": "https://example.com/hooks/valka"
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
     let body = parse_response_json(resp).await;
-    assert_eq!(body.as_array().unwrap().len(), 5);
+    assert_eq!(body["callback_url"], "https://example.com/hooks/valka");
+
+    for url in [
+        "ftp://example.com/hook",
+        "not a url",
+        "http://127.0.0.1:8080/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://localhost/hook",
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(post_json(
+                "/api/v1/tasks",
+                serde_json::json!({"queue_name": "q", "task_name": "t", "callback_url": url}),
+            ))
+            .await
+            .unwrap();
+        assert_error_response(
+            resp,
+            StatusCode::BAD_REQUEST,
+            "VALIDATION_ERROR",
+            "callback_url",
+        )
+        .await;
+    }
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_list_tasks_filter_queue(pool: PgPool) {
-    create_test_task(&pool, "demo", "t1").await;
-    create_test_task(&pool, "demo", "t2").await;
-    create_test_task(&pool, "other", "t3").await;
+async fn test_rest_create_task_retry_backoff(pool: PgPool) {
     let app = build_test_router(pool);
 
     let resp = app
-        .oneshot(get_req("/api/v1/tasks?queue_name=demo"))
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({
+                "queue_name": "q",
+                "task_name": "t",
+                "retry_backoff": {"base_seconds": 1, "multiplier": 1.5, "jitter": true}
+            }),
+        ))
         .await
         .unwrap();
-
+    assert_eq!(resp.status(), StatusCode::CREATED);
     let body = parse_response_json(resp).await;
-    let tasks = body.as_array().unwrap();
-    assert_eq!(tasks.len(), 2);
-    assert!(tasks.iter().all(|t| t["queue_name"] == "demo"));
+    assert_eq!(
+        body["retry_backoff"],
+        serde_json::json!({
+            "base_seconds": 1,
+            "max_seconds": null,
+            "multiplier": 1.5,
+            "jitter": true
+        })
+    );
+
+    for backoff in [
+        serde_json::json!({"base_seconds": 0}),
+        serde_json::json!({"base_seconds": 30, "max_seconds": 10}),
+        serde_json::json!({"multiplier": 0.5}),
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(post_json(
+                "/api/v1/tasks",
+                serde_json::json!({"queue_name": "q", "task_name": "t", "retry_backoff": backoff}),
+            ))
+            .await
+            .unwrap();
+        assert_error_response(
+            resp,
+            StatusCode::BAD_REQUEST,
+            "VALIDATION_ERROR",
+            "retry_backoff",
+        )
+        .await;
+    }
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_list_tasks_filter_status(pool: PgPool) {
-    let t1 = create_test_task(&pool, "q", "t1").await;
-    create_test_task(&pool, "q", "t2").await;
-    valka_db::queries::tasks::complete_task(&pool, &t1.id, None)
-        .await
-        .unwrap();
+async fn test_rest_create_task_defaults(pool: PgPool) {
     let app = build_test_router(pool);
 
     let resp = app
-        .oneshot(get_req("/api/v1/tasks?status=PENDING"))
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({
+                "queue_name": "q",
+                "task_name": "t"
+            }),
+        ))
         .await
         .unwrap();
 
     let body = parse_response_json(resp).await;
-    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body["priority"], 0);
+    assert_eq!(body["max_retries"], 3);
+    assert_eq!(body["timeout_seconds"], 300);
+}
+
+fn post_text(content_type: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/api/v1/tasks")
+        .header("content-type", content_type)
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+async fn get_row(pool: &PgPool, body: &serde_json::Value) -> valka_db::queries::tasks::TaskRow {
+    valka_db::queries::tasks::get_task(pool, body["id"].as_str().unwrap())
+        .await
+        .unwrap()
+        .unwrap()
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_list_tasks_pagination(pool: PgPool) {
-    for i in 0..5 {
-        create_test_task(&pool, "q", &format!("t{i}")).await;
-    }
-    let app = build_test_router(pool);
+async fn test_rest_create_task_yaml(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+    let spec = r#"
+queue_name: reports
+task_name: monthly.render
+priority: 5
+timeout_seconds: 60
+input:
+  month: 2026-04
+  formats: [pdf, csv]
+  recipients:
+    - name: Ops
+      email: ops@example.com
+metadata:
+  source: backstage
+"#;
 
     let resp = app
-        .oneshot(get_req("/api/v1/tasks?limit=2&offset=2"))
+        .clone()
+        .oneshot(post_text("application/yaml", spec))
         .await
         .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let yaml_body = parse_response_json(resp).await;
 
-    let body = parse_response_json(resp).await;
-    assert_eq!(body.as_array().unwrap().len(), 2);
+    // The same task sent as JSON is stored identically
+    let resp = app
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({
+                "queue_name": "reports",
+                "task_name": "monthly.render",
+                "priority": 5,
+                "timeout_seconds": 60,
+                "input": {
+                    "month": "2026-04",
+                    "formats": ["pdf", "csv"],
+                    "recipients": [{"name": "Ops", "email": "ops@example.com"}],
+                },
+                "metadata": {"source": "backstage"},
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let json_body = parse_response_json(resp).await;
+
+    let (from_yaml, from_json) = (
+        get_row(&pool, &yaml_body).await,
+        get_row(&pool, &json_body).await,
+    );
+    assert_eq!(from_yaml.input, from_json.input);
+    assert_eq!(from_yaml.metadata, from_json.metadata);
+    assert_eq!(from_yaml.priority, 5);
+    assert_eq!(from_yaml.timeout_seconds, 60);
+    assert_eq!(from_yaml.max_retries, from_json.max_retries);
+    assert_eq!(from_yaml.status, "PENDING");
+    for key in [
+        "queue_name",
+        "task_name",
+        "input",
+        "metadata",
+        "priority",
+        "status",
+    ] {
+        assert_eq!(yaml_body[key], json_body[key], "{key}");
+    }
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_list_tasks_empty(pool: PgPool) {
-    let app = build_test_router(pool);
+async fn test_rest_create_task_form(pool: PgPool) {
+    let app = build_test_router(pool.clone());
 
-    let resp = app.oneshot(get_req("/api/v1/tasks")).await.unwrap();
+    // input as JSON, metadata as YAML
+    let form = "queue_name=q&task_name=email.send&priority=2&max_retries=1\
+                &input=%7B%22to%22%3A%22a%40example.com%22%7D\
+                &metadata=team%3A%20growth%0Aretry%3A%20false";
+    let resp = app
+        .clone()
+        .oneshot(post_text("application/x-www-form-urlencoded", form))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let row = get_row(&pool, &parse_response_json(resp).await).await;
+    assert_eq!(row.input, Some(serde_json::json!({"to": "a@example.com"})));
+    assert_eq!(
+        row.metadata,
+        serde_json::json!({"team": "growth", "retry": false})
+    );
+    assert_eq!(row.priority, 2);
+    assert_eq!(row.max_retries, 1);
+
+    // A bare word could be a broken document; strings have to be JSON-quoted
+    let resp = app
+        .clone()
+        .oneshot(post_text(
+            "application/x-www-form-urlencoded",
+            "queue_name=q&task_name=t&input=hello",
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "input is neither a JSON nor a YAML document",
+    )
+    .await;
+    let resp = app
+        .clone()
+        .oneshot(post_text(
+            "application/x-www-form-urlencoded",
+            "queue_name=q&task_name=t&input=%22hello%22",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
 
-    assert_eq!(resp.status(), StatusCode::OK);
-    let body = parse_response_json(resp).await;
-    assert_eq!(body.as_array().unwrap().len(), 0);
+    // Repeated fields are ambiguous
+    let resp = app
+        .oneshot(post_text(
+            "application/x-www-form-urlencoded",
+            "queue_name=q&task_name=t&queue_name=other",
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "duplicate field",
+    )
+    .await;
 }
 
-// ─── POST /api/v1/tasks/{id}/cancel ─────────────────────────────────
-
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_cancel_task_pending(pool: PgPool) {
-    let task = create_test_task(&pool, "q", "t").await;
-    let app = build_test_router(pool);
+async fn test_rest_create_task_yaml_rejected(pool: PgPool) {
+    let app = build_test_router(pool.clone());
 
     let resp = app
-        .oneshot(post_json(
-            &format!("/api/v1/tasks/{}/cancel", task.id),
-            serde_json::json!({}),
+        .clone()
+        .oneshot(post_text(
+            "application/yaml",
+            "queue_name: q\ntask_name: [unclosed\n",
         ))
         .await
         .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "Invalid YAML body",
+    )
+    .await;
+
+    // Several documents or repeated keys don't say which task is meant
+    for ambiguous in [
+        "queue_name: q\ntask_name: a\n---\nqueue_name: q\ntask_name: b\n",
+        "queue_name: q\ntask_name: a\ntask_name: b\n",
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(post_text("application/yaml", ambiguous))
+            .await
+            .unwrap();
+        assert_error_response(
+            resp,
+            StatusCode::BAD_REQUEST,
+            "VALIDATION_ERROR",
+            "Invalid YAML body",
+        )
+        .await;
+    }
+
+    let oversized = format!(
+        "queue_name: q\ntask_name: t\ninput:\n  blob: {}\n",
+        "x".repeat(300 * 1024)
+    );
+    let resp = app
+        .oneshot(post_text("application/yaml", &oversized))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "PAYLOAD_TOO_LARGE",
+        "Body exceeds",
+    )
+    .await;
 
-    assert_eq!(resp.status(), StatusCode::OK);
-    let body = parse_response_json(resp).await;
-    assert_eq!(body["status"], "CANCELLED");
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tasks")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_cancel_task_not_found(pool: PgPool) {
+async fn test_rest_create_task_json_rejections_unchanged(pool: PgPool) {
     let app = build_test_router(pool);
 
+    // No content type is still axum's JSON rejection, not a YAML or form attempt
     let resp = app
-        .oneshot(post_json(
-            "/api/v1/tasks/nonexistent/cancel",
-            serde_json::json!({}),
-        ))
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .body(Body::from(r#"{"queue_name":"q","task_name":"t"}"#))
+                .unwrap(),
+        )
         .await
         .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        "UNSUPPORTED_MEDIA_TYPE",
+        "Expected request with `Content-Type: application/json`",
+    )
+    .await;
 
+    let resp = app
+        .oneshot(post_text("application/json", "queue_name: q"))
+        .await
+        .unwrap();
     assert_error_response(
         resp,
-        StatusCode::UNPROCESSABLE_ENTITY,
-        "INVALID_STATE",
-        "not in cancellable state",
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "Failed to parse the request body as JSON",
     )
     .await;
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_cancel_task_already_completed(pool: PgPool) {
-    let task = create_test_task(&pool, "q", "t").await;
-    valka_db::queries::tasks::complete_task(&pool, &task.id, None)
+async fn test_rest_error_envelope_shape(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/tasks/nonexistent"))
         .await
         .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        parse_response_json(resp).await,
+        serde_json::json!({
+            "error": {"code": "NOT_FOUND", "message": "Task not found"}
+        })
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_invalid_json_names_the_field(pool: PgPool) {
     let app = build_test_router(pool);
 
     let resp = app
+        .clone()
         .oneshot(post_json(
-            &format!("/api/v1/tasks/{}/cancel", task.id),
-            serde_json::json!({}),
+            "/api/v1/tasks",
+            serde_json::json!({"queue_name": "q", "task_name": "t", "priority": "high"}),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "priority: invalid type: string \"high\", expected i32",
+    )
+    .await;
+
+    let resp = app
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({"queue_name": "q"}),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "missing field `task_name`",
+    )
+    .await;
+
+    // Handlers taking a plain JSON body reject the same way
+    let resp = app
+        .oneshot(post_json(
+            "/api/v1/schedules",
+            serde_json::json!({"cron_expression": 5, "queue_name": "q", "task_name": "t"}),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "cron_expression: invalid type: integer `5`, expected a string",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_invalid_query_is_validation_error(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/tasks?limit=ten"))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "limit: invalid digit found in string",
+    )
+    .await;
+}
+
+// ─── GET /api/v1/tasks/{id} ─────────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_task(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req(&format!("/api/v1/tasks/{}", task.id)))
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["id"], task.id);
+    assert_eq!(body["queue_name"], "q");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_task_not_found(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/tasks/nonexistent-id"))
+        .await
+        .unwrap();
+
+    assert_error_response(resp, StatusCode::NOT_FOUND, "NOT_FOUND", "Task not found").await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_task_yaml(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/v1/tasks/{}", task.id))
+                .header("accept", "application/yaml")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "application/yaml");
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let yaml: serde_json::Value = serde_yaml::from_slice(&body).unwrap();
+    assert_eq!(yaml["id"], task.id);
+    assert_eq!(yaml["input"], serde_json::json!({"key": "value"}));
+}
+
+/// Put a task into RETRY after `attempts` runs, with no retry time yet.
+async fn fail_for_retry(pool: &PgPool, task_id: &str, attempts: i32) {
+    sqlx::query(
+        "UPDATE tasks SET status = 'RETRY', attempt_count = $2, scheduled_at = NULL \
+         WHERE id = $1",
+    )
+    .bind(task_id)
+    .bind(attempts)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+async fn get_retry_plan(app: &axum::Router, task_id: &str) -> serde_json::Value {
+    let resp = app
+        .clone()
+        .oneshot(get_req(&format!("/api/v1/tasks/{task_id}")))
+        .await
+        .unwrap();
+    parse_response_json(resp).await["retry_plan"].clone()
+}
+
+fn parse_time(value: &serde_json::Value) -> chrono::DateTime<Utc> {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_retry_plan_matches_process_retries(pool: PgPool) {
+    let mut params = default_task_params("q", "flaky");
+    params.max_retries = 5;
+    let task = create_test_task_full(&pool, params).await;
+    let app = build_test_router(pool.clone());
+    assert!(get_retry_plan(&app, &task.id).await.is_null());
+
+    // 2 of 5 attempts used, under the default 1s base backoff
+    fail_for_retry(&pool, &task.id, 2).await;
+    let plan = get_retry_plan(&app, &task.id).await;
+    assert_eq!(plan["attempts_remaining"], 3);
+    assert_eq!(plan["dead_letter"], true);
+    let schedule: Vec<_> = plan["schedule"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(parse_time)
+        .collect();
+    assert_eq!(schedule.len(), 3);
+    assert_eq!(parse_time(&plan["next_attempt_at"]), schedule[0]);
+    assert_eq!(schedule[1] - schedule[0], Duration::seconds(8));
+    assert_eq!(schedule[2] - schedule[1], Duration::seconds(16));
+
+    // Each retry time the scheduler assigns is the planned one: the first
+    // counted from the GET just now, the rest one planned gap after the
+    // previous attempt failed
+    let slack = Duration::milliseconds(1);
+    for (i, planned) in schedule.iter().enumerate() {
+        if i > 0 {
+            fail_for_retry(&pool, &task.id, 2 + i as i32).await;
+        }
+        let before = Utc::now();
+        valka_scheduler::retry::process_retries(&pool, 1, 3600)
+            .await
+            .unwrap();
+        let after = Utc::now();
+        let assigned = valka_db::queries::tasks::get_task(&pool, &task.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .scheduled_at
+            .unwrap();
+        if i == 0 {
+            assert!(assigned >= *planned - slack);
+            assert!(assigned - *planned < Duration::seconds(1));
+        } else {
+            let gap = *planned - schedule[i - 1];
+            assert!(assigned >= before + gap - slack && assigned <= after + gap + slack);
+        }
+
+        // Once assigned, the plan leads with the stored time
+        let plan = get_retry_plan(&app, &task.id).await;
+        assert_eq!(parse_time(&plan["next_attempt_at"]), assigned);
+        assert_eq!(plan["attempts_remaining"], 3 - i);
+    }
+
+    // Out of attempts: no plan, the task is headed for the DLQ
+    fail_for_retry(&pool, &task.id, 5).await;
+    assert!(get_retry_plan(&app, &task.id).await.is_null());
+}
+
+// ─── GET /api/v1/tasks ──────────────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_tasks(pool: PgPool) {
+    for i in 0..5 {
+        create_test_task(&pool, "q", &format!("t{i}")).await;
+    }
+    let app = build_test_router(pool);
+
+    let resp = app.oneshot(get_req("/api/v1/tasks")).await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 5);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_tasks_filter_queue(pool: PgPool) {
+    create_test_task(&pool, "demo", "t1").await;
+    create_test_task(&pool, "demo", "t2").await;
+    create_test_task(&pool, "other", "t3").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/tasks?queue_name=demo"))
+        .await
+        .unwrap();
+
+    let body = parse_response_json(resp).await;
+    let tasks = body.as_array().unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert!(tasks.iter().all(|t| t["queue_name"] == "demo"));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_tasks_filter_status(pool: PgPool) {
+    let t1 = create_test_task(&pool, "q", "t1").await;
+    create_test_task(&pool, "q", "t2").await;
+    valka_db::queries::tasks::complete_task(&pool, &t1.id, None)
+        .await
+        .unwrap();
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/tasks?status=PENDING"))
+        .await
+        .unwrap();
+
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_tasks_pagination(pool: PgPool) {
+    for i in 0..5 {
+        create_test_task(&pool, "q", &format!("t{i}")).await;
+    }
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/tasks?limit=2&offset=2"))
+        .await
+        .unwrap();
+
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_tasks_empty(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app.oneshot(get_req("/api/v1/tasks")).await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 0);
+}
+
+// ─── GET /api/v1/tasks/search ───────────────────────────────────────
+
+async fn search_ids(app: &axum::Router, query: &str) -> Vec<String> {
+    let resp = app
+        .clone()
+        .oneshot(get_req(&format!("/api/v1/tasks/search?{query}")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK, "{query}");
+    let body = parse_response_json(resp).await;
+    body.as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_search_tasks_filters(pool: PgPool) {
+    use valka_db::queries::tasks;
+
+    let timed_out = create_test_task(&pool, "search", "email.send").await;
+    tasks::fail_task(&pool, &timed_out.id, "SMTP 50%_timeout")
+        .await
+        .unwrap();
+    let retrying = create_test_task(&pool, "search", "email.bounce").await;
+    tasks::update_task_status(&pool, &retrying.id, "RETRY")
+        .await
+        .unwrap();
+    let sms = create_test_task(&pool, "search", "sms.send").await;
+    tasks::fail_task(&pool, &sms.id, "smtp relay down")
+        .await
+        .unwrap();
+    let mut params = default_task_params("search", "email.digest");
+    params.priority = 5;
+    params.idempotency_key = Some("digest-1".to_string());
+    let digest = create_test_task_full(&pool, params).await;
+    let app = build_test_router(pool);
+
+    let mut found = search_ids(&app, "status=FAILED,RETRY&task_name_prefix=email.").await;
+    found.sort();
+    let mut expected = vec![timed_out.id.clone(), retrying.id.clone()];
+    expected.sort();
+    assert_eq!(found, expected);
+
+    // Case-insensitive, and LIKE wildcards in the text match literally
+    assert_eq!(search_ids(&app, "error_contains=smtp").await.len(), 2);
+    assert_eq!(
+        search_ids(&app, "error_contains=50%25_").await,
+        [timed_out.id.as_str()]
+    );
+    assert!(search_ids(&app, "error_contains=5%25t").await.is_empty());
+    assert_eq!(
+        search_ids(&app, "idempotency_key=digest-1").await,
+        [digest.id.as_str()]
+    );
+
+    let by_priority = search_ids(&app, "order=priority&queue_name=search").await;
+    assert_eq!(by_priority.len(), 4);
+    assert_eq!(by_priority[0], digest.id);
+    assert_eq!(by_priority[1], timed_out.id);
+    let oldest_first = search_ids(&app, "order=created_asc&limit=2").await;
+    assert_eq!(oldest_first, [timed_out.id.clone(), retrying.id.clone()]);
+
+    let later =
+        (Utc::now() + Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    assert!(
+        search_ids(&app, &format!("created_after={later}"))
+            .await
+            .is_empty()
+    );
+    assert_eq!(
+        search_ids(&app, &format!("created_before={later}&status="))
+            .await
+            .len(),
+        4
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_search_tasks_rejects_bad_filters(pool: PgPool) {
+    let app = build_test_router(pool);
+    for (query, contains) in [
+        ("status=FAILED,BOGUS", "BOGUS"),
+        ("order=random", "random"),
+        ("created_after=yesterday", "created_after"),
+        ("limit=0", "limit"),
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(get_req(&format!("/api/v1/tasks/search?{query}")))
+            .await
+            .unwrap();
+        assert_error_response(resp, StatusCode::BAD_REQUEST, "VALIDATION_ERROR", contains).await;
+    }
+}
+
+// ─── POST /api/v1/tasks/{id}/cancel ─────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_cancel_task_pending(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(post_json(
+            &format!("/api/v1/tasks/{}/cancel", task.id),
+            serde_json::json!({}),
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["status"], "CANCELLED");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_cancel_task_not_found(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(post_json(
+            "/api/v1/tasks/nonexistent/cancel",
+            serde_json::json!({}),
+        ))
+        .await
+        .unwrap();
+
+    assert_error_response(
+        resp,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "INVALID_STATE",
+        "not in cancellable state",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_cancel_task_already_completed(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    valka_db::queries::tasks::complete_task(&pool, &task.id, None)
+        .await
+        .unwrap();
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(post_json(
+            &format!("/api/v1/tasks/{}/cancel", task.id),
+            serde_json::json!({}),
+        ))
+        .await
+        .unwrap();
+
+    assert_error_response(
+        resp,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "INVALID_STATE",
+        "not in cancellable state",
+    )
+    .await;
+}
+
+// ─── GET /api/v1/tasks/{id}/runs ────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_task_runs(pool: PgPool) {
+    let (task, _run) = create_running_task(&pool, "q").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req(&format!("/api/v1/tasks/{}/runs", task.id)))
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    let runs = body.as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0]["task_id"], task.id);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_task_runs_includes_progress(pool: PgPool) {
+    let (task, run) = create_running_task(&pool, "q").await;
+    valka_db::queries::task_runs::update_progress(&pool, &run.id, 37.5, "copying")
+        .await
+        .unwrap();
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req(&format!("/api/v1/tasks/{}/runs", task.id)))
+        .await
+        .unwrap();
+
+    let body = parse_response_json(resp).await;
+    assert_eq!(body[0]["progress"], 37.5);
+    assert_eq!(body[0]["progress_message"], "copying");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_task_runs_empty(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req(&format!("/api/v1/tasks/{}/runs", task.id)))
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 0);
+}
+
+// ─── GET /api/v1/tasks/{task_id}/runs/{run_id}/logs ─────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_run_logs(pool: PgPool) {
+    let (task, run) = create_running_task(&pool, "q").await;
+    let entries = vec![valka_db::queries::task_logs::InsertLogEntry {
+        task_run_id: run.id.clone(),
+        timestamp_ms: 1000,
+        level: "INFO".to_string(),
+        message: "hello".to_string(),
+        metadata: None,
+    }];
+    valka_db::queries::task_logs::batch_insert_logs(&pool, &entries)
+        .await
+        .unwrap();
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req(&format!(
+            "/api/v1/tasks/{}/runs/{}/logs",
+            task.id, run.id
+        )))
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    let logs = body.as_array().unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0]["message"], "hello");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_run_logs_with_after_id(pool: PgPool) {
+    let (task, run) = create_running_task(&pool, "q").await;
+    let entries: Vec<valka_db::queries::task_logs::InsertLogEntry> = (0..5)
+        .map(|i| valka_db::queries::task_logs::InsertLogEntry {
+            task_run_id: run.id.clone(),
+            timestamp_ms: 1000 + i,
+            level: "INFO".to_string(),
+            message: format!("msg-{i}"),
+            metadata: None,
+        })
+        .collect();
+    valka_db::queries::task_logs::batch_insert_logs(&pool, &entries)
+        .await
+        .unwrap();
+
+    // Get all logs to find an ID for cursor
+    let app = build_test_router(pool.clone());
+    let resp = app
+        .oneshot(get_req(&format!(
+            "/api/v1/tasks/{}/runs/{}/logs?limit=2",
+            task.id, run.id
+        )))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    let logs = body.as_array().unwrap();
+    let after_id = logs.last().unwrap()["id"].as_i64().unwrap();
+
+    // Page 2 using after_id
+    let app2 = build_test_router(pool);
+    let resp2 = app2
+        .oneshot(get_req(&format!(
+            "/api/v1/tasks/{}/runs/{}/logs?limit=2&after_id={after_id}",
+            task.id, run.id
+        )))
+        .await
+        .unwrap();
+    let body2 = parse_response_json(resp2).await;
+    let page2 = body2.as_array().unwrap();
+    assert_eq!(page2.len(), 2);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_run_logs_empty(pool: PgPool) {
+    let (task, run) = create_running_task(&pool, "q").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req(&format!(
+            "/api/v1/tasks/{}/runs/{}/logs",
+            task.id, run.id
+        )))
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 0);
+}
+
+// ─── GET /api/v1/tasks/{task_id}/runs/{run_id}/logs/stream ──────────
+
+/// Read SSE frames until the stream ends, returning (log ids, end status).
+async fn read_log_stream(body: &mut Body) -> (Vec<i64>, Option<serde_json::Value>) {
+    use http_body_util::BodyExt;
+
+    let mut ids = Vec::new();
+    let mut end = None;
+    while let Some(frame) = tokio::time::timeout(std::time::Duration::from_secs(5), body.frame())
+        .await
+        .expect("log stream stalled")
+    {
+        let text = String::from_utf8(frame.unwrap().into_data().unwrap().to_vec()).unwrap();
+        for event in text.split("\n\n").filter(|e| !e.is_empty()) {
+            let data = event.lines().find_map(|l| l.strip_prefix("data: "));
+            if event.lines().any(|l| l == "event: end") {
+                end = data.map(|d| serde_json::from_str(d).unwrap());
+            } else if let Some(id) = event.lines().find_map(|l| l.strip_prefix("id: ")) {
+                ids.push(id.parse().unwrap());
+            }
+        }
+    }
+    (ids, end)
+}
+
+async fn insert_run_logs(pool: &PgPool, run_id: &str, n: i64) -> Vec<i64> {
+    let entries: Vec<valka_db::queries::task_logs::InsertLogEntry> = (0..n)
+        .map(|i| valka_db::queries::task_logs::InsertLogEntry {
+            task_run_id: run_id.to_string(),
+            timestamp_ms: 1000 + i,
+            level: "INFO".to_string(),
+            message: format!("msg-{i}"),
+            metadata: None,
+        })
+        .collect();
+    valka_db::queries::task_logs::batch_insert_logs_returning(pool, &entries)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.id)
+        .collect()
+}
+
+/// Complete the run far enough in the past that its stream needn't wait for a flush.
+async fn complete_run_earlier(pool: &PgPool, run_id: &str) {
+    valka_db::queries::task_runs::complete_task_run(pool, run_id, None)
+        .await
+        .unwrap()
+        .unwrap();
+    sqlx::query("UPDATE task_runs SET completed_at = NOW() - INTERVAL '1 minute' WHERE id = $1")
+        .bind(run_id)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_log_stream_replays_finished_run_and_ends(pool: PgPool) {
+    let (task, run) = create_running_task(&pool, "q").await;
+    let ids = insert_run_logs(&pool, &run.id, 3).await;
+    complete_run_earlier(&pool, &run.id).await;
+    let app = build_test_router(pool);
+
+    let uri = format!("/api/v1/tasks/{}/runs/{}/logs/stream", task.id, run.id);
+    let resp = app.clone().oneshot(get_req(&uri)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let (streamed, end) = read_log_stream(&mut resp.into_body()).await;
+    assert_eq!(streamed, ids);
+    assert_eq!(end.unwrap()["status"], "COMPLETED");
+
+    // Resuming from Last-Event-ID skips what was already seen
+    let req = Request::builder()
+        .uri(&uri)
+        .header("last-event-id", ids[0].to_string())
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    let (streamed, _) = read_log_stream(&mut resp.into_body()).await;
+    assert_eq!(streamed, ids[1..]);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_log_stream_tails_live_logs(pool: PgPool) {
+    use http_body_util::BodyExt;
+
+    let (task, run) = create_running_task(&pool, "q").await;
+    let replayed = insert_run_logs(&pool, &run.id, 1).await;
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+
+    let resp = app
+        .oneshot(get_req(&format!(
+            "/api/v1/tasks/{}/runs/{}/logs/stream",
+            task.id, run.id
+        )))
+        .await
+        .unwrap();
+    let mut body = resp.into_body();
+    let frame = body.frame().await.unwrap().unwrap();
+    let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    assert!(text.contains(&format!("id: {}", replayed[0])), "{text:?}");
+
+    // Published by the ingester once flushed; other runs' entries are filtered out
+    let entry = valka_db::queries::task_logs::InsertLogEntry {
+        task_run_id: run.id.clone(),
+        timestamp_ms: 2000,
+        level: "INFO".to_string(),
+        message: "live".to_string(),
+        metadata: None,
+    };
+    let live = valka_db::queries::task_logs::batch_insert_logs_returning(&pool, &[entry])
+        .await
+        .unwrap()
+        .remove(0);
+    let live_id = live.id;
+    let mut other = live.clone();
+    other.task_run_id = "other-run".to_string();
+    other.id += 1;
+    dispatcher.log_stream().send(other).unwrap();
+    dispatcher.log_stream().send(live).unwrap();
+    let frame = body.frame().await.unwrap().unwrap();
+    let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    assert!(text.contains(&format!("id: {live_id}")), "{text:?}");
+
+    complete_run_earlier(&pool, &run.id).await;
+    let (streamed, end) = read_log_stream(&mut body).await;
+    assert!(streamed.is_empty(), "{streamed:?}");
+    assert_eq!(end.unwrap()["status"], "COMPLETED");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_log_stream_unknown_run(pool: PgPool) {
+    let (_task, run) = create_running_task(&pool, "q").await;
+    let other = create_test_task(&pool, "q", "other").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req(&format!(
+            "/api/v1/tasks/{}/runs/{}/logs/stream",
+            other.id, run.id
+        )))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::NOT_FOUND,
+        "NOT_FOUND",
+        "Task run not found",
+    )
+    .await;
+}
+
+// ─── GET /api/v1/workers ────────────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_workers_empty(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app.oneshot(get_req("/api/v1/workers")).await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 0);
+}
+
+// ─── GET /api/v1/dead-letters ───────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_dead_letters(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    valka_db::queries::dead_letter::insert_dead_letter(
+        &pool,
+        &uuid::Uuid::now_v7().to_string(),
+        &task.id,
+        "q",
+        "t",
+        None,
+        Some("error"),
+        None,
+        3,
+        &serde_json::json!({}),
+    )
+    .await
+    .unwrap();
+    let app = build_test_router(pool);
+
+    let resp = app.oneshot(get_req("/api/v1/dead-letters")).await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["task_id"], task.id);
+    assert!(body[0]["owner"].is_null());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_dead_letters_filter_queue(pool: PgPool) {
+    let task_a = create_test_task(&pool, "queue-a", "t").await;
+    let task_b = create_test_task(&pool, "queue-b", "t").await;
+
+    for (task, queue) in [(&task_a, "queue-a"), (&task_b, "queue-b")] {
+        valka_db::queries::dead_letter::insert_dead_letter(
+            &pool,
+            &uuid::Uuid::now_v7().to_string(),
+            &task.id,
+            queue,
+            "t",
+            None,
+            None,
+            None,
+            1,
+            &serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+    }
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/dead-letters?queue_name=queue-a"))
+        .await
+        .unwrap();
+
+    let body = parse_response_json(resp).await;
+    let dls = body.as_array().unwrap();
+    assert_eq!(dls.len(), 1);
+    assert_eq!(dls[0]["queue_name"], "queue-a");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_dead_letters_includes_owner(pool: PgPool) {
+    let task = create_test_task(&pool, "payments.charge", "t").await;
+    valka_db::queries::dead_letter::insert_dead_letter(
+        &pool,
+        &uuid::Uuid::now_v7().to_string(),
+        &task.id,
+        "payments.charge",
+        "t",
+        None,
+        Some("card declined"),
+        None,
+        3,
+        &serde_json::json!({}),
+    )
+    .await
+    .unwrap();
+    let app = build_test_router(pool);
+
+    app.clone()
+        .oneshot(put_json(
+            "/api/v1/queues/payments.charge",
+            serde_json::json!({
+                "owner_team": "payments",
+                "contact": "#payments-oncall",
+                "runbook_url": "https://runbooks.example.com/payments",
+                "labels": {"tier": "1"},
+            }),
+        ))
+        .await
+        .unwrap();
+
+    let resp = app.oneshot(get_req("/api/v1/dead-letters")).await.unwrap();
+
+    let body = parse_response_json(resp).await;
+    let owner = &body[0]["owner"];
+    assert_eq!(owner["owner_team"], "payments");
+    assert_eq!(owner["contact"], "#payments-oncall");
+    assert_eq!(
+        owner["runbook_url"],
+        "https://runbooks.example.com/payments"
+    );
+    assert_eq!(owner["labels"]["tier"], "1");
+}
+
+// ─── POST /api/v1/dead-letters/{id}/retry ───────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_retry_dead_letter(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let mut events = dispatcher.event_tx().subscribe();
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+    let mut params = default_task_params("dlq-retry", "t");
+    params.input = Some(serde_json::json!({"order": 7}));
+    let task = create_test_task_full(&pool, params).await;
+    valka_db::queries::tasks::move_to_dead_letter(&pool, &task.id)
+        .await
+        .unwrap();
+    let dl_id = uuid::Uuid::now_v7().to_string();
+    valka_db::queries::dead_letter::insert_dead_letter(
+        &pool,
+        &dl_id,
+        &task.id,
+        "dlq-retry",
+        "t",
+        task.input.as_ref(),
+        Some("boom"),
+        None,
+        3,
+        &serde_json::json!({}),
+    )
+    .await
+    .unwrap();
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "dlq-retry", 1).await;
+
+    let uri = format!("/api/v1/dead-letters/{dl_id}/retry");
+    let resp = app
+        .clone()
+        .oneshot(post_json(&uri, serde_json::json!({})))
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["id"], task.id);
+    assert_eq!(body["status"], "PENDING");
+    assert_eq!(body["attempt_count"], 0);
+
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.task_id, task.id);
+    assert_eq!((event.previous_status, event.new_status), (7, 1));
+
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task.id);
+    assert_eq!(assignment.attempt_number, 1);
+    assert_eq!(assignment.input, r#"{"order":7}"#);
+
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/dead-letters"))
+        .await
+        .unwrap();
+    assert_eq!(parse_response_json(resp).await, serde_json::json!([]));
+
+    // Already requeued
+    let resp = app
+        .oneshot(post_json(&uri, serde_json::json!({})))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+// ─── DELETE /api/v1/dead-letters ────────────────────────────────────
+
+async fn insert_dead_letter_for(pool: &PgPool, queue: &str) -> String {
+    let task = create_test_task(pool, queue, "t").await;
+    let id = uuid::Uuid::now_v7().to_string();
+    valka_db::queries::dead_letter::insert_dead_letter(
+        pool,
+        &id,
+        &task.id,
+        queue,
+        "t",
+        None,
+        Some("error"),
+        None,
+        3,
+        &serde_json::json!({}),
+    )
+    .await
+    .unwrap();
+    id
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_delete_dead_letter(pool: PgPool) {
+    let id = insert_dead_letter_for(&pool, "q").await;
+    let app = build_test_router(pool);
+
+    let uri = format!("/api/v1/dead-letters/{id}");
+    let resp = app.clone().oneshot(delete_req(&uri)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await["deleted"], true);
+
+    let resp = app.oneshot(delete_req(&uri)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_purge_dead_letters(pool: PgPool) {
+    for queue in ["queue-a", "queue-a", "queue-b"] {
+        insert_dead_letter_for(&pool, queue).await;
+    }
+    let app = build_test_router(pool);
+
+    // Nothing is older than an hour ago
+    let cutoff =
+        (Utc::now() - Duration::hours(1)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let resp = app
+        .clone()
+        .oneshot(delete_req(&format!(
+            "/api/v1/dead-letters?queue_name=queue-a&older_than={cutoff}"
+        )))
+        .await
+        .unwrap();
+    assert_eq!(parse_response_json(resp).await["deleted_count"], 0);
+
+    let resp = app
+        .clone()
+        .oneshot(delete_req("/api/v1/dead-letters?queue_name=queue-a"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await["deleted_count"], 2);
+
+    let resp = app
+        .clone()
+        .oneshot(delete_req("/api/v1/dead-letters?older_than=yesterday"))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "older_than",
+    )
+    .await;
+
+    let resp = app.oneshot(get_req("/api/v1/dead-letters")).await.unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["queue_name"], "queue-b");
+}
+
+// ─── /api/v1/queues ─────────────────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_set_and_get_queue_config(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/queues/emails",
+            serde_json::json!({
+                "owner_team": "growth",
+                "contact": "growth@example.com",
+                "runbook_url": "https://wiki.example.com/emails",
+                "labels": {"env": "prod"},
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app.oneshot(get_req("/api/v1/queues/emails")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["queue_name"], "emails");
+    assert_eq!(body["owner_team"], "growth");
+    assert_eq!(body["contact"], "growth@example.com");
+    assert_eq!(body["runbook_url"], "https://wiki.example.com/emails");
+    assert_eq!(body["labels"], serde_json::json!({"env": "prod"}));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_update_queue_config_partial(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    app.clone()
+        .oneshot(put_json(
+            "/api/v1/queues/emails",
+            serde_json::json!({"owner_team": "growth", "contact": "#growth", "labels": {"a": "1"}}),
+        ))
+        .await
+        .unwrap();
+
+    // Omitted fields are kept, empty strings clear
+    let resp = app
+        .oneshot(put_json(
+            "/api/v1/queues/emails",
+            serde_json::json!({"contact": ""}),
+        ))
+        .await
+        .unwrap();
+
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["owner_team"], "growth");
+    assert!(body["contact"].is_null());
+    assert_eq!(body["labels"]["a"], "1");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_queue_min_worker_version(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/queues/emails",
+            serde_json::json!({"min_worker_version": "2.1.0"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/queues/emails"))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["min_worker_version"], "2.1.0");
+
+    // Other updates keep the pin; an empty string removes it
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/queues/emails",
+            serde_json::json!({"owner_team": "growth"}),
+        ))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["min_worker_version"], "2.1.0");
+
+    let resp = app
+        .oneshot(put_json(
+            "/api/v1/queues/emails",
+            serde_json::json!({"min_worker_version": ""}),
+        ))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert!(body["min_worker_version"].is_null());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_hold_and_release_scheduled(pool: PgPool) {
+    let app = build_test_router(pool.clone());
+
+    let mut ids = Vec::new();
+    for i in 0..25 {
+        let mut params = default_task_params("campaign", &format!("t{i}"));
+        params.scheduled_at = Some(Utc::now() + Duration::hours(1));
+        ids.push(create_test_task_full(&pool, params).await.id);
+    }
+
+    let resp = app
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/queues/campaign/hold-scheduled",
+            serde_json::json!({}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await["scheduled_hold"], true);
+
+    // The release window arrives while held
+    sqlx::query("UPDATE tasks SET scheduled_at = NOW() - INTERVAL '1 minute' WHERE id = ANY($1)")
+        .bind(&ids)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let promoted = valka_scheduler::delayed::promote_delayed_tasks(&pool)
+        .await
+        .unwrap();
+    assert_eq!(promoted, 0);
+
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/queues/campaign"))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["held_past_due"], 25);
+
+    let resp = app
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/queues/campaign/release-scheduled",
+            serde_json::json!({"max_per_tick": 10}),
+        ))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["scheduled_hold"], false);
+    assert_eq!(body["release_max_per_tick"], 10);
+
+    let mut steps = Vec::new();
+    for _ in 0..3 {
+        steps.push(
+            valka_scheduler::delayed::promote_delayed_tasks(&pool)
+                .await
+                .unwrap(),
+        );
+    }
+    assert_eq!(steps, vec![10, 10, 5]);
+
+    // The ramp ends once the backlog is drained
+    let resp = app
+        .oneshot(get_req("/api/v1/queues/campaign"))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert!(body["release_max_per_tick"].is_null());
+    assert_eq!(body["held_past_due"], 0);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_release_scheduled_validation(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(post_json(
+            "/api/v1/queues/campaign/release-scheduled",
+            serde_json::json!({"max_per_tick": 0}),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "max_per_tick must be greater than 0",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_list_queue_configs_filter_owner(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    for (queue, team) in [
+        ("charge", "payments"),
+        ("refund", "payments"),
+        ("emails", "growth"),
+    ] {
+        app.clone()
+            .oneshot(put_json(
+                &format!("/api/v1/queues/{queue}"),
+                serde_json::json!({"owner_team": team}),
+            ))
+            .await
+            .unwrap();
+    }
+
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/queues?owner_team=payments"))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    let names: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|q| q["queue_name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["charge", "refund"]);
+
+    let resp = app.oneshot(get_req("/api/v1/queues")).await.unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 3);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_queue_config_not_found(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/queues/unknown"))
+        .await
+        .unwrap();
+
+    assert_error_response(
+        resp,
+        StatusCode::NOT_FOUND,
+        "NOT_FOUND",
+        "Queue config not found",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_update_queue_config_validation(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let cases = [
+        (
+            serde_json::json!({"runbook_url": "ftp://example.com"}),
+            "http(s) URL",
+        ),
+        (
+            serde_json::json!({"runbook_url": "https://"}),
+            "not a valid URL",
+        ),
+        (
+            serde_json::json!({"owner_team": "x".repeat(129)}),
+            "owner_team exceeds",
+        ),
+        (
+            serde_json::json!({"contact": "x".repeat(257)}),
+            "contact exceeds",
+        ),
+        (serde_json::json!({"labels": {"": "v"}}), "label key"),
+        (
+            serde_json::json!({"min_worker_version": "2.x"}),
+            "min_worker_version is not valid semver",
+        ),
+    ];
+
+    for (body, message) in cases {
+        let resp = app
+            .clone()
+            .oneshot(put_json("/api/v1/queues/q", body))
+            .await
+            .unwrap();
+        assert_error_response(resp, StatusCode::BAD_REQUEST, "VALIDATION_ERROR", message).await;
+    }
+
+    // Nothing was stored
+    let resp = app.oneshot(get_req("/api/v1/queues/q")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_create_task_takes_queue_defaults(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/queues/reports",
+            serde_json::json!({"default_max_retries": 7, "default_timeout_seconds": 45}),
+        ))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["default_max_retries"], 7);
+    assert_eq!(body["default_timeout_seconds"], 45);
+    assert_eq!(body["paused"], false);
+
+    let create = |body: serde_json::Value| app.clone().oneshot(post_json("/api/v1/tasks", body));
+    let resp = create(serde_json::json!({"queue_name": "reports", "task_name": "t"}))
+        .await
+        .unwrap();
+    let task = parse_response_json(resp).await;
+    assert_eq!(task["max_retries"], 7);
+    assert_eq!(task["timeout_seconds"], 45);
+
+    // An explicit value wins over the queue's default
+    let resp = create(serde_json::json!({
+        "queue_name": "reports",
+        "task_name": "t",
+        "max_retries": 1,
+        "timeout_seconds": 10,
+    }))
+    .await
+    .unwrap();
+    let task = parse_response_json(resp).await;
+    assert_eq!(task["max_retries"], 1);
+    assert_eq!(task["timeout_seconds"], 10);
+
+    // 0 clears the queue default, leaving the global one
+    app.clone()
+        .oneshot(put_json(
+            "/api/v1/queues/reports",
+            serde_json::json!({"default_max_retries": 0}),
+        ))
+        .await
+        .unwrap();
+    let resp = create(serde_json::json!({"queue_name": "reports", "task_name": "t"}))
+        .await
+        .unwrap();
+    let task = parse_response_json(resp).await;
+    assert_eq!(task["max_retries"], 3);
+    assert_eq!(task["timeout_seconds"], 45);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_paused_queue_holds_new_tasks(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "paused", 1).await;
+
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/queues/paused",
+            serde_json::json!({"paused": true}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(parse_response_json(resp).await["paused"], true);
+
+    // A waiting worker does not get the task; it stays PENDING for the reader
+    let resp = app
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({"queue_name": "paused", "task_name": "t"}),
+        ))
+        .await
+        .unwrap();
+    let task = parse_response_json(resp).await;
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(200), rx.recv())
+            .await
+            .is_err(),
+        "paused queue dispatched a new task"
+    );
+    let stored = valka_db::queries::tasks::get_task(&pool, task["id"].as_str().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.status, "PENDING");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_pause_and_resume_queue(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "pausable", 1).await;
+
+    let resp = app
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/queues/pausable/pause",
+            serde_json::json!({}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(parse_response_json(resp).await["paused"], true);
+    let stored = valka_db::queries::queue_configs::get_queue_config(&pool, "pausable")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stored.paused);
+
+    let mut params = default_task_params("pausable", "t");
+    params.partition_id = 0;
+    let task = create_test_task_full(&pool, params).await;
+
+    // The reader's own poll interval is far longer than the test: only the
+    // resume can get it to read the task
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let config = valka_core::MatchingConfig {
+        task_reader_poll_busy_ms: 60_000,
+        task_reader_poll_idle_ms: 60_000,
+        ..valka_core::MatchingConfig::default()
+    };
+    tokio::spawn(
+        valka_matching::task_reader::TaskReader::new(
+            pool.clone(),
+            matching,
+            "pausable".to_string(),
+            valka_core::PartitionId(0),
+            config,
+            shutdown_rx,
+        )
+        .run(),
+    );
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(200), rx.recv())
+            .await
+            .is_err(),
+        "paused queue dispatched a task"
+    );
+
+    let resp = app
+        .oneshot(post_json(
+            "/api/v1/queues/pausable/resume",
+            serde_json::json!({}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(parse_response_json(resp).await["paused"], false);
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task.id);
+    let _ = shutdown_tx.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_queue_defaults_reject_negative(pool: PgPool) {
+    let app = build_test_router(pool);
+    let resp = app
+        .oneshot(put_json(
+            "/api/v1/queues/q",
+            serde_json::json!({"default_timeout_seconds": -1}),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "default_timeout_seconds must not be negative",
+    )
+    .await;
+}
+
+// ─── /api/v1/quotas ─────────────────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_quota_rejects_over_active_limit(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/quotas/billing",
+            serde_json::json!({"max_active_tasks": 10}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let mut rejected = Vec::new();
+    for i in 0..12 {
+        let resp = app
+            .clone()
+            .oneshot(post_json(
+                "/api/v1/tasks",
+                serde_json::json!({"queue_name": "billing.invoices", "task_name": format!("t{i}")}),
+            ))
+            .await
+            .unwrap();
+        if resp.status() != StatusCode::CREATED {
+            rejected.push(resp);
+        }
+    }
+    assert_eq!(rejected.len(), 2);
+    for resp in rejected {
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = parse_response_json(resp).await;
+        let error = &body["error"];
+        assert_eq!(error["code"], "QUOTA_EXCEEDED");
+        assert_eq!(error["quota"]["key"], "billing");
+        assert_eq!(error["quota"]["limit_kind"], "max_active_tasks");
+        assert_eq!(error["quota"]["usage"], 10);
+        assert_eq!(error["quota"]["limit"], 10);
+    }
+
+    // Other namespaces stay unlimited
+    let resp = app
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/tasks",
+            serde_json::json!({"queue_name": "shipping", "task_name": "t"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/quotas/billing/usage"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["max_active_tasks"], 10);
+    assert!(body["max_creations_per_hour"].is_null());
+    assert_eq!(body["active_tasks"], 10);
+    assert_eq!(body["created_this_hour"], 10);
+
+    let resp = app.oneshot(get_req("/api/v1/quotas")).await.unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["key"], "billing");
+    assert_eq!(body[0]["active_tasks"], 10);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_quota_counts_existing_tasks(pool: PgPool) {
+    create_test_task(&pool, "billing.invoices", "t").await;
+    create_test_task(&pool, "billing", "t").await;
+    create_test_task(&pool, "shipping", "t").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/quotas/billing",
+            serde_json::json!({"max_creations_per_hour": 3}),
+        ))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["active_tasks"], 2);
+    assert_eq!(body["created_this_hour"], 2);
+
+    let create = || {
+        post_json(
+            "/api/v1/tasks",
+            serde_json::json!({"queue_name": "billing", "task_name": "t"}),
+        )
+    };
+    let resp = app.clone().oneshot(create()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let resp = app.clone().oneshot(create()).await.unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::TOO_MANY_REQUESTS,
+        "QUOTA_EXCEEDED",
+        "max_creations_per_hour",
+    )
+    .await;
+
+    // Removing the quota lifts the limit
+    let resp = app
+        .clone()
+        .oneshot(delete_req("/api/v1/quotas/billing"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = app.clone().oneshot(create()).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/quotas/billing/usage"))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    assert!(body["max_creations_per_hour"].is_null());
+    assert_eq!(body["created_this_hour"], 4);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_quota_validation(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/quotas/billing",
+            serde_json::json!({"max_active_tasks": 0}),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "max_active_tasks",
+    )
+    .await;
+
+    let resp = app
+        .clone()
+        .oneshot(put_json(
+            "/api/v1/quotas/billing.invoices",
+            serde_json::json!({"max_active_tasks": 5}),
+        ))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "namespace",
+    )
+    .await;
+
+    let resp = app
+        .oneshot(delete_req("/api/v1/quotas/missing"))
+        .await
+        .unwrap();
+    assert_error_response(resp, StatusCode::NOT_FOUND, "NOT_FOUND", "missing").await;
+}
+
+// ─── /api/v1/schedules ──────────────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_schedule_lifecycle(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .clone()
+        .oneshot(post_json(
+            "/api/v1/schedules",
+            serde_json::json!({
+                "cron_expression": "*/5 * * * *",
+                "queue_name": "reports",
+                "task_name": "nightly",
+                "input": {"full": true}
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created = parse_response_json(resp).await;
+    let id = created["id"].as_str().unwrap().to_string();
+    assert_eq!(created["input"]["full"], true);
+    assert_eq!(created["max_retries"], 3);
+    assert!(created["last_fired_at"].is_null());
+    let next: chrono::DateTime<chrono::Utc> =
+        created["next_fire_at"].as_str().unwrap().parse().unwrap();
+    assert!(next > chrono::Utc::now());
+
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/schedules?queue=reports"))
+        .await
+        .unwrap();
+    let listed = parse_response_json(resp).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/schedules?queue=other"))
+        .await
+        .unwrap();
+    assert!(
+        parse_response_json(resp)
+            .await
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(delete_req(&format!("/api/v1/schedules/{id}")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = app
+        .oneshot(get_req(&format!("/api/v1/schedules/{id}")))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::NOT_FOUND,
+        "NOT_FOUND",
+        "Schedule not found",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_unique_violation_is_conflict(pool: PgPool) {
+    // Schedules have no natural key; add one so a second create collides
+    sqlx::query("CREATE UNIQUE INDEX schedules_task_name ON schedules (queue_name, task_name)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let app = build_test_router(pool);
+    let body = serde_json::json!({
+        "cron_expression": "0 * * * *",
+        "queue_name": "reports",
+        "task_name": "hourly",
+    });
+
+    let resp = app
+        .clone()
+        .oneshot(post_json("/api/v1/schedules", body.clone()))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let resp = app
+        .oneshot(post_json("/api/v1/schedules", body))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::CONFLICT,
+        "CONFLICT",
+        "duplicate key value violates unique constraint \"schedules_task_name\"",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_schedule_rejects_invalid_cron(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(post_json(
+            "/api/v1/schedules",
+            serde_json::json!({
+                "cron_expression": "every five minutes",
+                "queue_name": "reports",
+                "task_name": "nightly"
+            }),
         ))
         .await
         .unwrap();
-
     assert_error_response(
         resp,
-        StatusCode::UNPROCESSABLE_ENTITY,
-        "INVALID_STATE",
-        "not in cancellable state",
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "Invalid cron expression",
     )
     .await;
 }
 
-// ─── GET /api/v1/tasks/{id}/runs ────────────────────────────────────
+// ─── GET /api/v1/stats ──────────────────────────────────────────────
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_get_task_runs(pool: PgPool) {
-    let (task, _run) = create_running_task(&pool, "q").await;
-    let app = build_test_router(pool);
-
-    let resp = app
-        .oneshot(get_req(&format!("/api/v1/tasks/{}/runs", task.id)))
+async fn test_rest_stats_counts(pool: PgPool) {
+    for i in 0..3 {
+        create_test_task(&pool, "emails", &format!("t{i}")).await;
+    }
+    let done = create_test_task(&pool, "reports", "r").await;
+    valka_db::queries::tasks::complete_task(&pool, &done.id, None)
         .await
         .unwrap();
+    let failed = create_test_task(&pool, "reports", "r").await;
+    valka_db::queries::tasks::fail_task(&pool, &failed.id, "boom")
+        .await
+        .unwrap();
+    valka_db::queries::dead_letter::insert_dead_letter(
+        &pool,
+        &uuid::Uuid::now_v7().to_string(),
+        &failed.id,
+        "reports",
+        "r",
+        None,
+        Some("boom"),
+        None,
+        3,
+        &serde_json::json!({}),
+    )
+    .await
+    .unwrap();
+    let app = build_test_router(pool);
+
+    let resp = app.oneshot(get_req("/api/v1/stats")).await.unwrap();
 
     assert_eq!(resp.status(), StatusCode::OK);
     let body = parse_response_json(resp).await;
-    let runs = body.as_array().unwrap();
-    assert_eq!(runs.len(), 1);
-    assert_eq!(runs[0]["task_id"], task.id);
+    assert_eq!(body["total_tasks"], 5);
+    assert_eq!(
+        body["by_status"],
+        serde_json::json!({"PENDING": 3, "COMPLETED": 1, "FAILED": 1})
+    );
+    assert_eq!(
+        body["by_queue"],
+        serde_json::json!([
+            {"queue_name": "emails", "total": 3, "by_status": {"PENDING": 3}},
+            {"queue_name": "reports", "total": 2, "by_status": {"COMPLETED": 1, "FAILED": 1}},
+        ])
+    );
+    assert_eq!(body["active_workers"], 0);
+    assert_eq!(body["dead_letters"], 1);
+    assert_eq!(body["created_last_hour"], 5);
+    assert_eq!(body["completed_last_hour"], 1);
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_get_task_runs_empty(pool: PgPool) {
-    let task = create_test_task(&pool, "q", "t").await;
+async fn test_rest_stats_empty(pool: PgPool) {
     let app = build_test_router(pool);
 
-    let resp = app
-        .oneshot(get_req(&format!("/api/v1/tasks/{}/runs", task.id)))
-        .await
-        .unwrap();
+    let resp = app.oneshot(get_req("/api/v1/stats")).await.unwrap();
 
-    assert_eq!(resp.status(), StatusCode::OK);
     let body = parse_response_json(resp).await;
-    assert_eq!(body.as_array().unwrap().len(), 0);
+    assert_eq!(body["total_tasks"], 0);
+    assert_eq!(body["by_status"], serde_json::json!({}));
+    assert_eq!(body["by_queue"], serde_json::json!([]));
 }
 
-// ─── GET /api/v1/tasks/{task_id}/runs/{run_id}/logs ─────────────────
+// ─── GET /api/v1/events ─────────────────────────────────────────────
 
-#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_get_run_logs(pool: PgPool) {
-    let (task, run) = create_running_task(&pool, "q").await;
-    let entries = vec![valka_db::queries::task_logs::InsertLogEntry {
-        task_run_id: run.id.clone(),
-        timestamp_ms: 1000,
-        level: "INFO".to_string(),
-        message: "hello".to_string(),
-        metadata: None,
-    }];
-    valka_db::queries::task_logs::batch_insert_logs(&pool, &entries)
-        .await
-        .unwrap();
-    let app = build_test_router(pool);
+/// Read SSE frames until `n` event ids have arrived.
+async fn read_sse_ids(body: &mut Body, n: usize) -> Vec<String> {
+    use http_body_util::BodyExt;
 
-    let resp = app
-        .oneshot(get_req(&format!(
-            "/api/v1/tasks/{}/runs/{}/logs",
-            task.id, run.id
-        )))
-        .await
-        .unwrap();
+    let mut ids = Vec::new();
+    while ids.len() < n {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(2), body.frame())
+            .await
+            .expect("no SSE frame within 2s")
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+        ids.extend(
+            text.lines()
+                .filter_map(|line| line.strip_prefix("id: "))
+                .map(str::to_string),
+        );
+    }
+    ids
+}
 
-    assert_eq!(resp.status(), StatusCode::OK);
-    let body = parse_response_json(resp).await;
-    let logs = body.as_array().unwrap();
-    assert_eq!(logs.len(), 1);
-    assert_eq!(logs[0]["message"], "hello");
+fn events_req(last_event_id: Option<&str>) -> Request<Body> {
+    let mut req = Request::builder().uri("/api/v1/events");
+    if let Some(id) = last_event_id {
+        req = req.header("last-event-id", id);
+    }
+    req.body(Body::empty()).unwrap()
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_get_run_logs_with_after_id(pool: PgPool) {
-    let (task, run) = create_running_task(&pool, "q").await;
-    let entries: Vec<valka_db::queries::task_logs::InsertLogEntry> = (0..5)
-        .map(|i| valka_db::queries::task_logs::InsertLogEntry {
-            task_run_id: run.id.clone(),
-            timestamp_ms: 1000 + i,
-            level: "INFO".to_string(),
-            message: format!("msg-{i}"),
-            metadata: None,
-        })
-        .collect();
-    valka_db::queries::task_logs::batch_insert_logs(&pool, &entries)
-        .await
-        .unwrap();
+async fn test_rest_events_replay_after_last_event_id(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool, matching, dispatcher.clone());
 
-    // Get all logs to find an ID for cursor
-    let app = build_test_router(pool.clone());
-    let resp = app
-        .oneshot(get_req(&format!(
-            "/api/v1/tasks/{}/runs/{}/logs?limit=2",
-            task.id, run.id
-        )))
-        .await
-        .unwrap();
-    let body = parse_response_json(resp).await;
-    let logs = body.as_array().unwrap();
-    let after_id = logs.last().unwrap()["id"].as_i64().unwrap();
+    let resp = app.clone().oneshot(events_req(None)).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut first = resp.into_body();
+    for task_id in ["a", "b", "c"] {
+        dispatcher.events().emit(valka_proto::TaskEvent {
+            event_id: uuid::Uuid::now_v7().to_string(),
+            task_id: task_id.to_string(),
+            queue_name: "q".to_string(),
+            new_status: 4,
+            ..Default::default()
+        });
+    }
+    let ids = read_sse_ids(&mut first, 3).await;
+    drop(first);
+    // Let the history catch up with the channel
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    // Reconnecting after the first event gets the other two
+    let resp = app.oneshot(events_req(Some(&ids[0]))).await.unwrap();
+    let mut second = resp.into_body();
+    assert_eq!(read_sse_ids(&mut second, 2).await, ids[1..]);
+}
 
-    // Page 2 using after_id
-    let app2 = build_test_router(pool);
-    let resp2 = app2
-        .oneshot(get_req(&format!(
-            "/api/v1/tasks/{}/runs/{}/logs?limit=2&after_id={after_id}",
-            task.id, run.id
-        )))
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_events_sends_keep_alive(pool: PgPool) {
+    use http_body_util::BodyExt;
+
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let dispatcher = dispatcher.with_events_config(&valka_core::EventsConfig {
+        sse_keep_alive_secs: 1,
+        ..Default::default()
+    });
+    // Held so the event channel outlives the consumed router
+    let app = build_test_router_with(pool, matching, dispatcher.clone());
+
+    let mut body = app.oneshot(events_req(None)).await.unwrap().into_body();
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(3), body.frame())
         .await
+        .expect("no keep-alive within 3s")
+        .unwrap()
         .unwrap();
-    let body2 = parse_response_json(resp2).await;
-    let page2 = body2.as_array().unwrap();
-    assert_eq!(page2.len(), 2);
+    let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    assert!(text.starts_with(':'), "{text:?}");
 }
 
+// ─── GET /debug/internal ────────────────────────────────────────────
+
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_get_run_logs_empty(pool: PgPool) {
-    let (task, run) = create_running_task(&pool, "q").await;
+async fn test_rest_debug_internal(pool: PgPool) {
     let app = build_test_router(pool);
 
-    let resp = app
-        .oneshot(get_req(&format!(
-            "/api/v1/tasks/{}/runs/{}/logs",
-            task.id, run.id
-        )))
-        .await
-        .unwrap();
+    let resp = app.oneshot(get_req("/debug/internal")).await.unwrap();
 
     assert_eq!(resp.status(), StatusCode::OK);
     let body = parse_response_json(resp).await;
-    assert_eq!(body.as_array().unwrap().len(), 0);
+    assert_eq!(body["dispatcher"]["workers"], 0);
+    assert_eq!(body["log_channel"]["capacity"], 128);
+    assert_eq!(body["matching"]["buffered_tasks"], 0);
+    assert_eq!(body["forwarder"]["open_circuits"], 0);
+    assert!(body["runtime"]["workers"].as_u64().unwrap() >= 1);
 }
 
-// ─── GET /api/v1/workers ────────────────────────────────────────────
+// ─── GET /healthz ───────────────────────────────────────────────────
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_list_workers_empty(pool: PgPool) {
+async fn test_rest_healthz(pool: PgPool) {
     let app = build_test_router(pool);
 
-    let resp = app.oneshot(get_req("/api/v1/workers")).await.unwrap();
+    let resp = app.oneshot(get_req("/healthz")).await.unwrap();
 
     assert_eq!(resp.status(), StatusCode::OK);
-    let body = parse_response_json(resp).await;
-    assert_eq!(body.as_array().unwrap().len(), 0);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.as_ref(), b"ok");
 }
 
-// ─── GET /api/v1/dead-letters ───────────────────────────────────────
+// ─── GET /api/v1/cluster/status ─────────────────────────────────────
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_list_dead_letters(pool: PgPool) {
-    let task = create_test_task(&pool, "q", "t").await;
-    valka_db::queries::dead_letter::insert_dead_letter(
-        &pool,
-        &uuid::Uuid::now_v7().to_string(),
-        &task.id,
-        "q",
-        "t",
-        None,
-        Some("error"),
-        3,
-        &serde_json::json!({}),
-    )
-    .await
-    .unwrap();
+async fn test_rest_cluster_status(pool: PgPool) {
     let app = build_test_router(pool);
 
-    let resp = app.oneshot(get_req("/api/v1/dead-letters")).await.unwrap();
+    let resp = app
+        .oneshot(get_req("/api/v1/cluster/status"))
+        .await
+        .unwrap();
 
     assert_eq!(resp.status(), StatusCode::OK);
     let body = parse_response_json(resp).await;
-    assert_eq!(body.as_array().unwrap().len(), 1);
-    assert_eq!(body[0]["task_id"], task.id);
+    let node_id = body["node_id"].as_str().unwrap();
+    assert_eq!(body["members"], serde_json::json!([node_id]));
+    assert_eq!(body["clustered"], false);
+    // The test router runs no scheduler
+    assert_eq!(body["is_leader"], false);
+    assert!(body["leader_since"].is_null());
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_list_dead_letters_filter_queue(pool: PgPool) {
-    let task_a = create_test_task(&pool, "queue-a", "t").await;
-    let task_b = create_test_task(&pool, "queue-b", "t").await;
-
-    for (task, queue) in [(&task_a, "queue-a"), (&task_b, "queue-b")] {
-        valka_db::queries::dead_letter::insert_dead_letter(
-            &pool,
-            &uuid::Uuid::now_v7().to_string(),
-            &task.id,
-            queue,
-            "t",
-            None,
-            None,
-            1,
-            &serde_json::json!({}),
-        )
-        .await
-        .unwrap();
-    }
+async fn test_rest_cluster_members(pool: PgPool) {
     let app = build_test_router(pool);
 
     let resp = app
-        .oneshot(get_req("/api/v1/dead-letters?queue_name=queue-a"))
+        .oneshot(get_req("/api/v1/cluster/members"))
         .await
         .unwrap();
 
+    assert_eq!(resp.status(), StatusCode::OK);
     let body = parse_response_json(resp).await;
-    let dls = body.as_array().unwrap();
-    assert_eq!(dls.len(), 1);
-    assert_eq!(dls[0]["queue_name"], "queue-a");
+    let members = body["members"].as_array().unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0]["live"], true);
+    assert_eq!(members[0]["is_self"], true);
+    assert!(members[0]["joined_at"].is_string());
+    // A single node gossips no address
+    assert!(members[0]["grpc_addr"].is_null());
 }
 
-// ─── GET /healthz ───────────────────────────────────────────────────
-
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
-async fn test_rest_healthz(pool: PgPool) {
+async fn test_rest_cluster_partitions(pool: PgPool) {
     let app = build_test_router(pool);
 
-    let resp = app.oneshot(get_req("/healthz")).await.unwrap();
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/cluster/members"))
+        .await
+        .unwrap();
+    let members = parse_response_json(resp).await;
+    let node_id = members["members"][0]["node_id"].clone();
 
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/cluster/partitions?queue_name=q"))
+        .await
+        .unwrap();
     assert_eq!(resp.status(), StatusCode::OK);
-    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["queue_name"], "q");
+    assert_eq!(body["num_partitions"], 4);
+    let partitions = body["partitions"].as_array().unwrap();
+    assert_eq!(partitions.len(), 4);
+    for (i, partition) in partitions.iter().enumerate() {
+        assert_eq!(partition["partition_id"], i as i64);
+        assert_eq!(partition["node_id"], node_id);
+    }
+
+    let resp = app
+        .oneshot(get_req("/api/v1/cluster/partitions"))
         .await
         .unwrap();
-    assert_eq!(body.as_ref(), b"ok");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 }
 
 // ─── POST /api/v1/tasks/{id}/signal ─────────────────────────────────
@@ -831,6 +2959,142 @@ async fn test_rest_send_multiple_signals_same_name(pool: PgPool) {
     assert!(signals.iter().all(|s| s["signal_name"] == "approve"));
 }
 
+// ─── GET /api/v1/workers/{id}/logs ──────────────────────────────────
+
+async fn insert_worker_logs(pool: &PgPool, worker_id: &str) {
+    use valka_db::queries::worker_logs::{InsertWorkerLogEntry, batch_insert_worker_logs};
+
+    let entries: Vec<InsertWorkerLogEntry> = [("INFO", 1000), ("WARN", 2000), ("ERROR", 3000)]
+        .into_iter()
+        .map(|(level, ts)| InsertWorkerLogEntry {
+            worker_id: worker_id.to_string(),
+            timestamp_ms: ts,
+            level: level.to_string(),
+            message: format!("{level} message"),
+            metadata: Some(serde_json::json!({"worker_name": "w"})),
+        })
+        .collect();
+    batch_insert_worker_logs(pool, &entries).await.unwrap();
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_worker_logs(pool: PgPool) {
+    insert_worker_logs(&pool, "w-1").await;
+    insert_worker_logs(&pool, "w-2").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .oneshot(get_req("/api/v1/workers/w-1/logs"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    let logs = body.as_array().unwrap();
+    assert_eq!(logs.len(), 3);
+    // Newest first
+    assert_eq!(logs[0]["level"], "ERROR");
+    assert!(logs.iter().all(|l| l["worker_id"] == "w-1"));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_get_worker_logs_filters(pool: PgPool) {
+    insert_worker_logs(&pool, "w-1").await;
+    let app = build_test_router(pool);
+
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/workers/w-1/logs?level=warn"))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    let levels: Vec<&str> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| l["level"].as_str().unwrap())
+        .collect();
+    assert_eq!(levels, vec!["ERROR", "WARN"]);
+
+    let resp = app
+        .clone()
+        .oneshot(get_req("/api/v1/workers/w-1/logs?from_ms=1500&to_ms=3000"))
+        .await
+        .unwrap();
+    let body = parse_response_json(resp).await;
+    let logs = body.as_array().unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0]["level"], "WARN");
+
+    let resp = app
+        .oneshot(get_req("/api/v1/workers/w-1/logs?level=LOUD"))
+        .await
+        .unwrap();
+    assert_error_response(
+        resp,
+        StatusCode::BAD_REQUEST,
+        "VALIDATION_ERROR",
+        "Unknown log level",
+    )
+    .await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_log_ingester_routes_worker_logs(pool: PgPool) {
+    let (log_tx, log_rx) = tokio::sync::mpsc::channel(16);
+    let (log_stream, mut stream_rx) = tokio::sync::broadcast::channel(16);
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let ingester = tokio::spawn(valka_server::server::run_log_ingester(
+        pool.clone(),
+        tokio::sync::watch::channel(valka_core::LogIngesterConfig::default()).1,
+        log_rx,
+        log_stream,
+        shutdown_rx,
+    ));
+
+    for run_id in ["worker:w-9", "run-9"] {
+        log_tx
+            .send(valka_proto::LogEntry {
+                task_run_id: run_id.to_string(),
+                timestamp_ms: 1,
+                level: 3,
+                message: "hello".to_string(),
+                metadata: String::new(),
+            })
+            .await
+            .unwrap();
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    shutdown_tx.send(true).unwrap();
+    ingester.await.unwrap();
+
+    let worker_logs = valka_db::queries::worker_logs::get_worker_logs(
+        &pool,
+        "w-9",
+        &valka_db::queries::worker_logs::WorkerLogFilter {
+            limit: 10,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(worker_logs.len(), 1);
+    assert_eq!(worker_logs[0].level, "WARN");
+
+    let run_logs = valka_db::queries::task_logs::get_logs_for_run(&pool, "run-9", 10, None)
+        .await
+        .unwrap();
+    assert_eq!(run_logs.len(), 1);
+    let misrouted = valka_db::queries::task_logs::get_logs_for_run(&pool, "worker:w-9", 10, None)
+        .await
+        .unwrap();
+    assert!(misrouted.is_empty());
+
+    // Only task logs are published to live streams, with their ids
+    let published = stream_rx.try_recv().unwrap();
+    assert_eq!(published.id, run_logs[0].id);
+    assert!(stream_rx.try_recv().is_err());
+}
+
 // ─── DELETE /api/v1/tasks/{id} error ─────────────────────────────────
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -0,0 +1,153 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{LimitsConfig, NodeId, PartitionId};
+use valka_db::queries::{task_runs, tasks};
+use valka_sdk::ValkaWorker;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+fn result(task_id: &str, run_id: &str, success: bool) -> valka_proto::TaskResult {
+    valka_proto::TaskResult {
+        task_id: task_id.to_string(),
+        task_run_id: run_id.to_string(),
+        success,
+        ..Default::default()
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_server_drops_oversize_output(pool: PgPool) {
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let dispatcher = dispatcher.with_limits(LimitsConfig {
+        max_output_bytes: 32,
+        ..Default::default()
+    });
+    let worker_id = valka_core::WorkerId::new();
+
+    let (task, run) = create_running_task(&pool, "limits-output").await;
+    let output = serde_json::json!({ "blob": "x".repeat(64) }).to_string();
+    dispatcher
+        .handle_task_result(
+            &worker_id,
+            valka_proto::TaskResult {
+                output,
+                ..result(&task.id, &run.id, true)
+            },
+        )
+        .await;
+
+    let task = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(task.status, "COMPLETED");
+    assert!(task.output.is_none());
+    let run = task_runs::get_task_run(&pool, &run.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(run.output_truncated);
+    assert!(run.output.is_none());
+
+    // Within the limit it is stored as before
+    let (task, run) = create_running_task(&pool, "limits-output").await;
+    dispatcher
+        .handle_task_result(
+            &worker_id,
+            valka_proto::TaskResult {
+                output: r#"{"ok":true}"#.to_string(),
+                ..result(&task.id, &run.id, true)
+            },
+        )
+        .await;
+    let run = task_runs::get_task_run(&pool, &run.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!run.output_truncated);
+    assert_eq!(run.output.unwrap()["ok"], true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_server_cuts_long_error_message(pool: PgPool) {
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let dispatcher = dispatcher.with_limits(LimitsConfig {
+        max_error_message_bytes: 10,
+        ..Default::default()
+    });
+
+    let (task, run) = create_running_task(&pool, "limits-error").await;
+    dispatcher
+        .handle_task_result(
+            &valka_core::WorkerId::new(),
+            valka_proto::TaskResult {
+                // 'é' spans bytes 9 and 10, so the cut lands before it
+                error_message: "boom boomé and then some".to_string(),
+                ..result(&task.id, &run.id, false)
+            },
+        )
+        .await;
+
+    let task = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(task.status, "FAILED");
+    assert_eq!(task.error_message.as_deref(), Some("boom boom"));
+    let run = task_runs::get_task_run(&pool, &run.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(run.error_message.as_deref(), Some("boom boom"));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_worker_fails_task_with_oversize_output(pool: PgPool) {
+    let queue = "limits-worker";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19916".parse().unwrap();
+    let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        dispatcher.log_tx().clone(),
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let worker = ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&[queue])
+        .max_output_bytes(64)
+        .handler(|_ctx| async move { Ok(serde_json::json!({ "blob": "x".repeat(1024) })) })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(worker.run());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let task = create_test_task(&pool, queue, "big").await;
+    let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope_for(&task));
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let task = loop {
+        let task = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+        if task.status == "FAILED" {
+            break task;
+        }
+        assert!(Instant::now() < deadline, "task is {}", task.status);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    let message = task.error_message.unwrap();
+    assert!(message.contains("over the 64 byte limit"), "{message}");
+    assert!(task.output.is_none());
+}
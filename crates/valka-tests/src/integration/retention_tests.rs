@@ -0,0 +1,246 @@
+use chrono::Utc;
+use sqlx::PgPool;
+use valka_core::RetentionConfig;
+use valka_db::queries::queue_configs::{self, QueueOwnershipUpdate};
+use valka_db::queries::task_logs::{self, InsertLogEntry};
+use valka_db::queries::{dead_letter, task_runs, tasks};
+use valka_scheduler::retention::{self, RetentionStats};
+
+use super::helpers::*;
+
+/// A task in `status`, last updated `days_ago`, with one run and one log line.
+async fn aged_task(pool: &PgPool, status: &str, days_ago: i32) -> (String, String) {
+    aged_task_in(pool, "q", status, days_ago).await
+}
+
+async fn aged_task_in(pool: &PgPool, queue: &str, status: &str, days_ago: i32) -> (String, String) {
+    let task = create_test_task(pool, queue, "t").await;
+    let run = create_test_run(pool, &task.id, 1, Utc::now()).await;
+    task_logs::batch_insert_logs(
+        pool,
+        &[InsertLogEntry {
+            task_run_id: run.id.clone(),
+            timestamp_ms: 0,
+            level: "INFO".to_string(),
+            message: "hello".to_string(),
+            metadata: None,
+        }],
+    )
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"
+        UPDATE tasks SET status = $2, updated_at = NOW() - make_interval(days => $3)
+        WHERE id = $1
+        "#,
+    )
+    .bind(&task.id)
+    .bind(status)
+    .bind(days_ago)
+    .execute(pool)
+    .await
+    .unwrap();
+    (task.id, run.id)
+}
+
+async fn backdate_logs(pool: &PgPool, run_id: &str, days_ago: i32) {
+    sqlx::query(
+        r#"
+        UPDATE task_logs SET created_at = NOW() - make_interval(days => $2)
+        WHERE task_run_id = $1
+        "#,
+    )
+    .bind(run_id)
+    .bind(days_ago)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+async fn exists(pool: &PgPool, task_id: &str) -> bool {
+    tasks::get_task(pool, task_id).await.unwrap().is_some()
+}
+
+async fn log_count(pool: &PgPool, run_id: &str) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM task_logs WHERE task_run_id = $1")
+        .bind(run_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_retention_deletes_expired_completed_tasks(pool: PgPool) {
+    let (old_a, old_a_run) = aged_task(&pool, "COMPLETED", 40).await;
+    let (old_b, _) = aged_task(&pool, "COMPLETED", 31).await;
+    let (recent, recent_run) = aged_task(&pool, "COMPLETED", 1).await;
+    let (pending, _) = aged_task(&pool, "PENDING", 40).await;
+    let (failed, _) = aged_task(&pool, "FAILED", 40).await;
+
+    let config = RetentionConfig {
+        completed_task_ttl_days: 30,
+        // One task per batch, so the cleanup has to loop
+        batch_size: 1,
+        ..Default::default()
+    };
+    let stats = retention::cleanup(&pool, &config).await.unwrap();
+
+    assert_eq!(
+        stats,
+        RetentionStats {
+            tasks: 2,
+            task_runs: 2,
+            task_logs: 2,
+            ..Default::default()
+        }
+    );
+    assert!(!exists(&pool, &old_a).await);
+    assert!(!exists(&pool, &old_b).await);
+    assert!(
+        task_runs::get_runs_for_task(&pool, &old_a)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(log_count(&pool, &old_a_run).await, 0);
+
+    assert!(exists(&pool, &recent).await);
+    assert_eq!(log_count(&pool, &recent_run).await, 1);
+    // Not finished, and failed tasks have no TTL here
+    assert!(exists(&pool, &pending).await);
+    assert!(exists(&pool, &failed).await);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_retention_deletes_expired_failed_tasks_and_dead_letters(pool: PgPool) {
+    let (dead, _) = aged_task(&pool, "DEAD_LETTER", 10).await;
+    let row = tasks::get_task(&pool, &dead).await.unwrap().unwrap();
+    dead_letter::insert_dead_letter(
+        &pool,
+        &uuid::Uuid::now_v7().to_string(),
+        &dead,
+        &row.queue_name,
+        &row.task_name,
+        None,
+        Some("boom"),
+        None,
+        3,
+        &row.metadata,
+    )
+    .await
+    .unwrap();
+    let (cancelled, _) = aged_task(&pool, "CANCELLED", 10).await;
+    let (recent, _) = aged_task(&pool, "FAILED", 2).await;
+    let (completed, _) = aged_task(&pool, "COMPLETED", 10).await;
+
+    let config = RetentionConfig {
+        failed_task_ttl_days: 7,
+        ..Default::default()
+    };
+    let stats = retention::cleanup(&pool, &config).await.unwrap();
+
+    assert_eq!(stats.tasks, 2);
+    assert_eq!(stats.dead_letters, 1);
+    assert!(!exists(&pool, &dead).await);
+    assert!(!exists(&pool, &cancelled).await);
+    assert!(exists(&pool, &recent).await);
+    assert!(exists(&pool, &completed).await);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_retention_deletes_expired_logs_only(pool: PgPool) {
+    let (task, old_run) = aged_task(&pool, "RUNNING", 0).await;
+    backdate_logs(&pool, &old_run, 20).await;
+    let (_, recent_run) = aged_task(&pool, "COMPLETED", 20).await;
+
+    let config = RetentionConfig {
+        log_ttl_days: 14,
+        ..Default::default()
+    };
+    let stats = retention::cleanup(&pool, &config).await.unwrap();
+
+    assert_eq!(stats.task_logs, 1);
+    assert_eq!(stats.tasks, 0);
+    assert_eq!(log_count(&pool, &old_run).await, 0);
+    assert_eq!(log_count(&pool, &recent_run).await, 1);
+    assert!(exists(&pool, &task).await);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_retention_zero_ttls_delete_nothing(pool: PgPool) {
+    let (task, run) = aged_task(&pool, "COMPLETED", 400).await;
+    backdate_logs(&pool, &run, 400).await;
+
+    let stats = retention::cleanup(&pool, &RetentionConfig::default())
+        .await
+        .unwrap();
+
+    assert_eq!(stats, RetentionStats::default());
+    assert!(exists(&pool, &task).await);
+    assert_eq!(log_count(&pool, &run).await, 1);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_retention_applies_queue_retention_days(pool: PgPool) {
+    for (queue, days) in [("short", 3), ("long", 60)] {
+        queue_configs::upsert_queue_ownership(
+            &pool,
+            queue,
+            &QueueOwnershipUpdate {
+                retention_days: Some(days),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+    let (short_done, _) = aged_task_in(&pool, "short", "COMPLETED", 5).await;
+    let (short_failed, _) = aged_task_in(&pool, "short", "FAILED", 5).await;
+    let (short_recent, _) = aged_task_in(&pool, "short", "COMPLETED", 1).await;
+    let (short_running, _) = aged_task_in(&pool, "short", "RUNNING", 5).await;
+    let (long_done, _) = aged_task_in(&pool, "long", "COMPLETED", 40).await;
+    let (global_done, _) = aged_task(&pool, "COMPLETED", 40).await;
+    let (global_recent, _) = aged_task(&pool, "COMPLETED", 5).await;
+
+    // The global TTL only covers queues without their own
+    let config = RetentionConfig {
+        completed_task_ttl_days: 30,
+        ..Default::default()
+    };
+    let stats = retention::cleanup(&pool, &config).await.unwrap();
+
+    assert_eq!(stats.tasks, 3);
+    assert!(!exists(&pool, &short_done).await);
+    assert!(!exists(&pool, &short_failed).await);
+    assert!(!exists(&pool, &global_done).await);
+    assert!(exists(&pool, &short_recent).await);
+    assert!(exists(&pool, &short_running).await);
+    assert!(exists(&pool, &long_done).await);
+    assert!(exists(&pool, &global_recent).await);
+
+    // Applied with no global TTLs at all
+    let (short_done, _) = aged_task_in(&pool, "short", "CANCELLED", 5).await;
+    let stats = retention::cleanup(&pool, &RetentionConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(stats.tasks, 1);
+    assert!(!exists(&pool, &short_done).await);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_retention_caps_batches_per_run(pool: PgPool) {
+    for _ in 0..retention::MAX_BATCHES_PER_RUN + 2 {
+        aged_task(&pool, "COMPLETED", 40).await;
+    }
+    let config = RetentionConfig {
+        completed_task_ttl_days: 30,
+        batch_size: 1,
+        ..Default::default()
+    };
+
+    // The rest is left for the next run
+    let stats = retention::cleanup(&pool, &config).await.unwrap();
+    assert_eq!(stats.tasks, retention::MAX_BATCHES_PER_RUN as u64);
+    let stats = retention::cleanup(&pool, &config).await.unwrap();
+    assert_eq!(stats.tasks, 2);
+}
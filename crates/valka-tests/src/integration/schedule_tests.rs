@@ -0,0 +1,107 @@
+use chrono::{Duration, SubsecRound, Utc};
+use sqlx::PgPool;
+use valka_db::queries::schedules::{self, CreateScheduleParams, ScheduleRow};
+use valka_db::queries::tasks;
+use valka_scheduler::cron::fire_due_schedules;
+
+async fn create_schedule(
+    pool: &PgPool,
+    cron: &str,
+    next_fire_at: chrono::DateTime<Utc>,
+) -> ScheduleRow {
+    schedules::create_schedule(
+        pool,
+        CreateScheduleParams {
+            id: valka_core::TaskId::new().0,
+            cron_expression: cron.to_string(),
+            queue_name: "cron".to_string(),
+            task_name: "report".to_string(),
+            input: Some(serde_json::json!({"kind": "daily"})),
+            priority: 2,
+            max_retries: 1,
+            timeout_seconds: 60,
+            next_fire_at,
+        },
+    )
+    .await
+    .unwrap()
+}
+
+async fn tasks_on_queue(pool: &PgPool) -> Vec<tasks::TaskRow> {
+    tasks::list_tasks(pool, Some("cron"), None, 100, 0)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_due_schedule_creates_task(pool: PgPool) {
+    // Whole seconds, so the slot survives the round trip through PG
+    let now = Utc::now().trunc_subsecs(0);
+    let slot = now - Duration::seconds(5);
+    let schedule = create_schedule(&pool, "* * * * * *", slot).await;
+
+    let fired = fire_due_schedules(&pool, 4, now).await.unwrap();
+    assert_eq!(fired.len(), 1);
+    let task = &fired[0];
+    assert_eq!(task.task_name, "report");
+    assert_eq!(task.status, "PENDING");
+    assert_eq!(task.priority, 2);
+    assert_eq!(task.max_retries, 1);
+    assert_eq!(task.input, Some(serde_json::json!({"kind": "daily"})));
+    assert_eq!(task.metadata["schedule_id"], schedule.id);
+
+    let schedule = schedules::get_schedule(&pool, &schedule.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(schedule.last_fired_at, Some(slot));
+    assert!(schedule.next_fire_at > now);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_schedule_not_yet_due_is_left_alone(pool: PgPool) {
+    let now = Utc::now();
+    create_schedule(&pool, "* * * * *", now + Duration::minutes(1)).await;
+
+    assert!(fire_due_schedules(&pool, 4, now).await.unwrap().is_empty());
+    assert!(tasks_on_queue(&pool).await.is_empty());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_missed_firings_fire_once_on_recovery(pool: PgPool) {
+    // Every minute, with the last hour missed while no leader ran
+    let now = Utc::now();
+    let schedule = create_schedule(&pool, "* * * * *", now - Duration::hours(1)).await;
+
+    assert_eq!(fire_due_schedules(&pool, 4, now).await.unwrap().len(), 1);
+    assert!(fire_due_schedules(&pool, 4, now).await.unwrap().is_empty());
+    assert_eq!(tasks_on_queue(&pool).await.len(), 1);
+
+    let schedule = schedules::get_schedule(&pool, &schedule.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(schedule.next_fire_at > now);
+    assert!(schedule.next_fire_at <= now + Duration::minutes(1));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_firing_taken_by_previous_leader_is_not_repeated(pool: PgPool) {
+    let now = Utc::now();
+    let stale = create_schedule(&pool, "* * * * *", now - Duration::seconds(1)).await;
+    assert_eq!(fire_due_schedules(&pool, 4, now).await.unwrap().len(), 1);
+
+    // A leader still holding the pre-firing row must not create the task again
+    let task = task_params_for(&stale);
+    let again = schedules::fire_schedule(&pool, &stale, &task, now + Duration::minutes(1))
+        .await
+        .unwrap();
+    assert!(again.is_none());
+    assert_eq!(tasks_on_queue(&pool).await.len(), 1);
+}
+
+fn task_params_for(schedule: &ScheduleRow) -> tasks::CreateTaskParams {
+    let mut params = super::helpers::default_task_params(&schedule.queue_name, &schedule.task_name);
+    params.metadata = serde_json::json!({"schedule_id": schedule.id});
+    params
+}
@@ -1,6 +1,13 @@
+use std::sync::Arc;
+
 use chrono::{Duration, Utc};
 use sqlx::PgPool;
-use valka_db::queries::tasks;
+use tokio::sync::{broadcast, watch};
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::retry::RetryBackoff;
+use valka_core::{NodeId, PartitionId, RetentionConfig, SchedulerConfig, WorkerId};
+use valka_db::queries::{queue_configs, task_runs, tasks};
+use valka_proto::TaskStatus;
 
 use super::helpers::*;
 
@@ -81,6 +88,168 @@ async fn test_process_retries_respects_attempt_count(pool: PgPool) {
     );
 }
 
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_process_retries_fails_when_deadline_cannot_be_met(pool: PgPool) {
+    let mut params = default_task_params("q", "t");
+    params.deadline_at = Some(Utc::now() + Duration::seconds(30));
+    let tight = create_test_task_full(&pool, params).await;
+    let mut params = default_task_params("q", "t");
+    params.deadline_at = Some(Utc::now() + Duration::hours(1));
+    let roomy = create_test_task_full(&pool, params).await;
+    for task in [&tight, &roomy] {
+        tasks::update_task_status(&pool, &task.id, "RETRY")
+            .await
+            .unwrap();
+    }
+
+    // 60s backoff lands after the tight deadline but before the roomy one
+    valka_scheduler::retry::process_retries(&pool, 60, 3600)
+        .await
+        .unwrap();
+
+    let tight = tasks::get_task(&pool, &tight.id).await.unwrap().unwrap();
+    assert_eq!(tight.status, "FAILED");
+    assert_eq!(
+        tight.error_message.as_deref(),
+        Some(valka_core::DEADLINE_EXCEEDED_BEFORE_RETRY)
+    );
+
+    let roomy = tasks::get_task(&pool, &roomy.id).await.unwrap().unwrap();
+    assert_eq!(roomy.status, "RETRY");
+    assert!(roomy.scheduled_at.is_some());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_process_retries_uses_handler_retry_after(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    let mut params = default_task_params("q", "t");
+    params.deadline_at = Some(Utc::now() + Duration::seconds(30));
+    let tight = create_test_task_full(&pool, params).await;
+    for task in [&task, &tight] {
+        sqlx::query("UPDATE tasks SET status = 'RETRY', retry_after_ms = 45000 WHERE id = $1")
+            .bind(&task.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let before = Utc::now();
+    valka_scheduler::retry::process_retries(&pool, 1, 3600)
+        .await
+        .unwrap();
+
+    // 45s instead of the 1s backoff, and only for this attempt
+    let task = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    let scheduled_at = task.scheduled_at.unwrap();
+    assert!(scheduled_at >= before + Duration::seconds(45));
+    assert!(scheduled_at < Utc::now() + Duration::seconds(46));
+    assert_eq!(task.retry_after_ms, None);
+
+    // The requested delay still can't outlast the deadline
+    let tight = tasks::get_task(&pool, &tight.id).await.unwrap().unwrap();
+    assert_eq!(tight.status, "FAILED");
+    assert_eq!(
+        tight.error_message.as_deref(),
+        Some(valka_core::DEADLINE_EXCEEDED_BEFORE_RETRY)
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_process_retries_caps_handler_retry_after(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    sqlx::query("UPDATE tasks SET status = 'RETRY', retry_after_ms = $2 WHERE id = $1")
+        .bind(&task.id)
+        .bind(i64::MAX)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // Previewing and scheduling it don't overflow, and it waits no longer than backoff could
+    let row = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    let policy = valka_core::retry::RetryPolicy {
+        max_delay_secs: 60,
+        ..Default::default()
+    };
+    let plan = row.retry_plan(&policy, Utc::now()).unwrap();
+    assert!(plan.next_attempt_at.unwrap() <= row.updated_at + Duration::seconds(60));
+
+    valka_scheduler::retry::process_retries(&pool, 1, 60)
+        .await
+        .unwrap();
+    let task = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(task.status, "RETRY");
+    assert!(task.scheduled_at.unwrap() <= Utc::now() + Duration::seconds(60));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_process_retries_uses_task_backoff(pool: PgPool) {
+    let global = create_test_task(&pool, "q", "t").await;
+    let mut params = default_task_params("q", "t");
+    params.retry_backoff = RetryBackoff {
+        base_seconds: Some(1),
+        multiplier: Some(3.0),
+        ..Default::default()
+    };
+    let quick = create_test_task_full(&pool, params).await;
+    for task in [&global, &quick] {
+        sqlx::query("UPDATE tasks SET status = 'RETRY', attempt_count = 1 WHERE id = $1")
+            .bind(&task.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let before = Utc::now();
+    valka_scheduler::retry::process_retries(&pool, 60, 3600)
+        .await
+        .unwrap();
+
+    // 60s * 2^1 from the scheduler's settings
+    let global = tasks::get_task(&pool, &global.id).await.unwrap().unwrap();
+    assert!(global.scheduled_at.unwrap() >= before + Duration::seconds(120));
+    // 1s * 3^1 from the task's own, with the scheduler's cap
+    let quick = tasks::get_task(&pool, &quick.id).await.unwrap().unwrap();
+    let scheduled_at = quick.scheduled_at.unwrap();
+    assert!(scheduled_at >= before + Duration::seconds(3));
+    assert!(scheduled_at < Utc::now() + Duration::seconds(4));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_process_retries_jitters_task_backoff(pool: PgPool) {
+    let mut ids = Vec::new();
+    for _ in 0..5 {
+        let mut params = default_task_params("q", "t");
+        params.retry_backoff = RetryBackoff {
+            base_seconds: Some(100),
+            jitter: true,
+            ..Default::default()
+        };
+        let task = create_test_task_full(&pool, params).await;
+        tasks::update_task_status(&pool, &task.id, "RETRY")
+            .await
+            .unwrap();
+        ids.push(task.id);
+    }
+
+    let before = Utc::now();
+    valka_scheduler::retry::process_retries(&pool, 1, 3600)
+        .await
+        .unwrap();
+
+    let mut delays = Vec::new();
+    for id in &ids {
+        let task = tasks::get_task(&pool, id).await.unwrap().unwrap();
+        let delay = task.scheduled_at.unwrap() - before;
+        // Between half and all of the 100s delay
+        assert!(delay >= Duration::seconds(50), "{delay}");
+        assert!(delay <= Duration::seconds(101), "{delay}");
+        delays.push(delay.num_milliseconds());
+    }
+    delays.sort();
+    delays.dedup();
+    assert!(delays.len() > 1, "every task got the same delay");
+}
+
 // ─── Delayed Task Promotion ─────────────────────────────────────────
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -121,6 +290,31 @@ async fn test_promote_delayed_tasks_future(pool: PgPool) {
     assert_eq!(unchanged.status, "RETRY");
 }
 
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_promote_delayed_tasks_expires_past_deadline(pool: PgPool) {
+    let mut params = default_task_params("q", "t");
+    params.scheduled_at = Some(Utc::now() - Duration::seconds(10));
+    params.deadline_at = Some(Utc::now() - Duration::seconds(1));
+    let task = create_test_task_full(&pool, params).await;
+    let live = create_test_task(&pool, "q", "live").await;
+
+    let count = valka_scheduler::delayed::promote_delayed_tasks(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0, "Expired task must not be promoted");
+
+    let expired = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(expired.status, "FAILED");
+    assert_eq!(
+        expired.error_message.as_deref(),
+        Some(valka_core::DEADLINE_EXCEEDED_BEFORE_DISPATCH)
+    );
+    assert!(expired.deadline_expired(Utc::now()));
+
+    let live = tasks::get_task(&pool, &live.id).await.unwrap().unwrap();
+    assert_eq!(live.status, "PENDING");
+}
+
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
 async fn test_promote_delayed_tasks_none(pool: PgPool) {
     // No RETRY tasks at all
@@ -132,11 +326,48 @@ async fn test_promote_delayed_tasks_none(pool: PgPool) {
     assert_eq!(count, 0);
 }
 
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_scheduled_hold_skips_only_held_queue(pool: PgPool) {
+    let held = create_test_task(&pool, "campaign", "t").await;
+    let other = create_test_task(&pool, "q", "t").await;
+    for task in [&held, &other] {
+        tasks::schedule_retry(&pool, &task.id, Utc::now() - Duration::seconds(10))
+            .await
+            .unwrap();
+    }
+    let mut params = default_task_params("campaign", "t");
+    params.scheduled_at = Some(Utc::now() - Duration::seconds(10));
+    let scheduled = create_test_task_full(&pool, params).await;
+
+    queue_configs::set_scheduled_hold(&pool, "campaign", true, None)
+        .await
+        .unwrap();
+
+    let count = valka_scheduler::delayed::promote_delayed_tasks(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+    let held = tasks::get_task(&pool, &held.id).await.unwrap().unwrap();
+    assert_eq!(held.status, "RETRY");
+
+    // Due scheduled PENDING tasks are not dequeued while the hold is on
+    let claimed = tasks::dequeue_tasks(&pool, "campaign", scheduled.partition_id, 10)
+        .await
+        .unwrap();
+    assert!(claimed.is_empty());
+    assert_eq!(
+        tasks::count_past_due_scheduled(&pool, "campaign")
+            .await
+            .unwrap(),
+        2
+    );
+}
+
 // ─── Lease Reaping ──────────────────────────────────────────────────
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
 async fn test_reap_expired_leases_retries(pool: PgPool) {
-    let (task, _run) = create_running_task(&pool, "q").await;
+    let (task, run) = create_running_task(&pool, "q").await;
 
     // Set the lease to be expired
     sqlx::query(
@@ -147,7 +378,8 @@ async fn test_reap_expired_leases_retries(pool: PgPool) {
     .await
     .unwrap();
 
-    let count = valka_scheduler::reaper::reap_expired_leases(&pool)
+    let (events, mut event_rx) = broadcast::channel(16);
+    let count = valka_scheduler::reaper::reap_expired_leases(&pool, &events)
         .await
         .unwrap();
     assert_eq!(count, 1);
@@ -155,6 +387,18 @@ async fn test_reap_expired_leases_retries(pool: PgPool) {
     // task.attempt_count=0, max_retries=3 → should RETRY
     let updated = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
     assert_eq!(updated.status, "RETRY");
+
+    let reason = format!("Lease expired on worker {}", run.worker_id);
+    let event = event_rx.try_recv().unwrap();
+    assert_eq!(event.task_id, task.id);
+    assert_eq!(event.previous_status, TaskStatus::Running as i32);
+    assert_eq!(event.new_status, TaskStatus::Retry as i32);
+    assert_eq!(event.worker_id, run.worker_id);
+    assert_eq!(event.error_message, reason);
+
+    let runs = task_runs::get_runs_for_task(&pool, &task.id).await.unwrap();
+    assert_eq!(runs[0].status, "FAILED");
+    assert_eq!(runs[0].error_message.as_deref(), Some(reason.as_str()));
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -177,7 +421,8 @@ async fn test_reap_expired_leases_dlq(pool: PgPool) {
     .await
     .unwrap();
 
-    let count = valka_scheduler::reaper::reap_expired_leases(&pool)
+    let (events, mut event_rx) = broadcast::channel(16);
+    let count = valka_scheduler::reaper::reap_expired_leases(&pool, &events)
         .await
         .unwrap();
     assert_eq!(count, 1);
@@ -185,6 +430,43 @@ async fn test_reap_expired_leases_dlq(pool: PgPool) {
     // Should be DEAD_LETTER
     let updated = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
     assert_eq!(updated.status, "DEAD_LETTER");
+    let event = event_rx.try_recv().unwrap();
+    assert_eq!(event.new_status, TaskStatus::DeadLetter as i32);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_reap_expired_leases_dlq_disabled(pool: PgPool) {
+    let update = queue_configs::QueueOwnershipUpdate {
+        dead_letter_enabled: Some(false),
+        ..Default::default()
+    };
+    queue_configs::upsert_queue_ownership(&pool, "no-dlq", &update)
+        .await
+        .unwrap();
+    let (task, _run) = create_running_task(&pool, "no-dlq").await;
+    sqlx::query("UPDATE tasks SET attempt_count = max_retries WHERE id = $1")
+        .bind(&task.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        "UPDATE task_runs SET lease_expires_at = NOW() - INTERVAL '1 minute' WHERE task_id = $1",
+    )
+    .bind(&task.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let (events, mut event_rx) = broadcast::channel(16);
+    let count = valka_scheduler::reaper::reap_expired_leases(&pool, &events)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    let updated = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(updated.status, "FAILED");
+    let event = event_rx.try_recv().unwrap();
+    assert_eq!(event.new_status, TaskStatus::Failed as i32);
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -192,7 +474,7 @@ async fn test_reap_expired_leases_none(pool: PgPool) {
     // No expired leases
     let (_task, _run) = create_running_task(&pool, "q").await;
 
-    let count = valka_scheduler::reaper::reap_expired_leases(&pool)
+    let count = valka_scheduler::reaper::reap_expired_leases(&pool, &broadcast::channel(16).0)
         .await
         .unwrap();
     assert_eq!(count, 0);
@@ -203,7 +485,7 @@ async fn test_reap_expired_leases_valid_lease_untouched(pool: PgPool) {
     let (task, _run) = create_running_task(&pool, "q").await;
     // Lease is far in the future (default from create_running_task)
 
-    valka_scheduler::reaper::reap_expired_leases(&pool)
+    valka_scheduler::reaper::reap_expired_leases(&pool, &broadcast::channel(16).0)
         .await
         .unwrap();
 
@@ -219,6 +501,10 @@ async fn test_reap_expired_leases_valid_lease_untouched(pool: PgPool) {
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
 async fn test_process_dead_letters(pool: PgPool) {
     let task = create_test_task(&pool, "q", "t").await;
+    let run = create_test_run(&pool, &task.id, 3, Utc::now()).await;
+    task_runs::fail_task_run(&pool, &run.id, "out of memory")
+        .await
+        .unwrap();
 
     // Set to FAILED with attempt_count >= max_retries
     tasks::fail_task(&pool, &task.id, "fatal error")
@@ -230,11 +516,19 @@ async fn test_process_dead_letters(pool: PgPool) {
         .await
         .unwrap();
 
-    let count = valka_scheduler::dlq::process_dead_letters(&pool)
+    let (events, mut event_rx) = broadcast::channel(16);
+    let count = valka_scheduler::dlq::process_dead_letters(&pool, &events)
         .await
         .unwrap();
     assert_eq!(count, 1);
 
+    let event = event_rx.try_recv().unwrap();
+    assert_eq!(event.task_id, task.id);
+    assert_eq!(event.previous_status, TaskStatus::Failed as i32);
+    assert_eq!(event.new_status, TaskStatus::DeadLetter as i32);
+    assert_eq!(event.worker_id, run.worker_id);
+    assert_eq!(event.error_message, "out of memory");
+
     // Task should be DEAD_LETTER
     let updated = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
     assert_eq!(updated.status, "DEAD_LETTER");
@@ -245,6 +539,43 @@ async fn test_process_dead_letters(pool: PgPool) {
         .unwrap();
     assert_eq!(dls.len(), 1);
     assert_eq!(dls[0].task_id, task.id);
+    // The last run's error, not the task's
+    assert_eq!(dls[0].error_message.as_deref(), Some("out of memory"));
+    assert_eq!(dls[0].worker_id.as_deref(), Some(run.worker_id.as_str()));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_process_dead_letters_disabled_for_queue(pool: PgPool) {
+    let update = queue_configs::QueueOwnershipUpdate {
+        dead_letter_enabled: Some(false),
+        ..Default::default()
+    };
+    queue_configs::upsert_queue_ownership(&pool, "no-dlq", &update)
+        .await
+        .unwrap();
+    let task = create_test_task(&pool, "no-dlq", "t").await;
+    tasks::fail_task(&pool, &task.id, "fatal error")
+        .await
+        .unwrap();
+    sqlx::query("UPDATE tasks SET attempt_count = max_retries WHERE id = $1")
+        .bind(&task.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let count = valka_scheduler::dlq::process_dead_letters(&pool, &broadcast::channel(16).0)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    let unchanged = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(unchanged.status, "FAILED");
+    assert_eq!(
+        valka_db::queries::dead_letter::count_dead_letters(&pool)
+            .await
+            .unwrap(),
+        0
+    );
 }
 
 #[sqlx::test(migrations = "../../crates/valka-db/migrations")]
@@ -254,7 +585,7 @@ async fn test_process_dead_letters_under_max(pool: PgPool) {
     // FAILED but attempt_count=0 < max_retries=3 → should NOT be moved
     tasks::fail_task(&pool, &task.id, "error").await.unwrap();
 
-    let count = valka_scheduler::dlq::process_dead_letters(&pool)
+    let count = valka_scheduler::dlq::process_dead_letters(&pool, &broadcast::channel(16).0)
         .await
         .unwrap();
     assert_eq!(count, 0);
@@ -268,8 +599,95 @@ async fn test_process_dead_letters_none(pool: PgPool) {
     // No FAILED tasks
     create_test_task(&pool, "q", "t").await;
 
-    let count = valka_scheduler::dlq::process_dead_letters(&pool)
+    let count = valka_scheduler::dlq::process_dead_letters(&pool, &broadcast::channel(16).0)
         .await
         .unwrap();
     assert_eq!(count, 0);
 }
+
+// ─── Scheduler Loop ─────────────────────────────────────────────────
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_scheduler_runs_jobs_on_acquiring_leadership(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    tasks::update_task_status(&pool, &task.id, "RETRY")
+        .await
+        .unwrap();
+
+    // Periods far longer than the test, so only the first tick can run the pass
+    let config = SchedulerConfig {
+        reaper_interval_secs: 3600,
+        retry_check_interval_secs: 3600,
+        ..Default::default()
+    };
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let cluster = Arc::new(ClusterManager::new_single_node(NodeId::new(), 4));
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let scheduler = tokio::spawn(valka_server::server::run_scheduler(
+        pool.clone(),
+        watch::channel(config).1,
+        watch::channel(RetentionConfig::default()).1,
+        4,
+        dispatcher,
+        cluster.clone(),
+        NodeForwarder::new(),
+        shutdown_rx,
+    ));
+
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(1);
+    loop {
+        let row = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+        if row.scheduled_at.is_some() || row.status != "RETRY" {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "retry pass did not run within a second of taking leadership"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(cluster.leader_since().await.is_some());
+
+    shutdown_tx.send(true).unwrap();
+    scheduler.await.unwrap();
+    assert!(cluster.leader_since().await.is_none());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_scheduler_offers_promoted_tasks_to_waiting_workers(pool: PgPool) {
+    let task = create_test_task(&pool, "q", "t").await;
+    tasks::schedule_retry(&pool, &task.id, Utc::now() - Duration::seconds(1))
+        .await
+        .unwrap();
+
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let worker = matching.register_worker("q", PartitionId(task.partition_id), WorkerId::new());
+
+    // No TaskReaders run here, so only the promotion itself can reach the worker
+    let config = SchedulerConfig {
+        delayed_check_interval_secs: 3600,
+        ..Default::default()
+    };
+    let cluster = Arc::new(ClusterManager::new_single_node(NodeId::new(), 4));
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let scheduler = tokio::spawn(valka_server::server::run_scheduler(
+        pool.clone(),
+        watch::channel(config).1,
+        watch::channel(RetentionConfig::default()).1,
+        4,
+        dispatcher,
+        cluster,
+        NodeForwarder::new(),
+        shutdown_rx,
+    ));
+
+    let envelope = tokio::time::timeout(std::time::Duration::from_millis(500), worker)
+        .await
+        .expect("promoted task did not reach the waiting worker")
+        .unwrap();
+    assert_eq!(envelope.task_id, task.id);
+    assert_eq!(envelope.attempt_number, task.attempt_count + 1);
+
+    shutdown_tx.send(true).unwrap();
+    scheduler.await.unwrap();
+}
@@ -0,0 +1,322 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use tower::ServiceExt;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::tasks;
+use valka_dispatcher::ShutdownPhase;
+use valka_proto::api_service_client::ApiServiceClient;
+use valka_proto::worker_service_client::WorkerServiceClient;
+use valka_proto::*;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+use valka_server::server::run_shutdown_sequence;
+
+use super::helpers::*;
+
+fn get(uri: &str) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_shutdown_refuses_new_work_and_drains_in_flight(pool: PgPool) {
+    let queue = "shutdown-drain";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let running = create_test_task(&pool, queue, "t").await;
+    let _ = matching.offer_task(
+        queue,
+        PartitionId(running.partition_id),
+        envelope_for(&running),
+    );
+    let assignment = recv_assignment(&mut rx).await;
+
+    let resp = app.clone().oneshot(get("/readyz")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let (readers_tx, mut readers_rx) = watch::channel(false);
+    let (listeners_tx, listeners_rx) = watch::channel(false);
+    let mut phase_rx = dispatcher.shutdown().subscribe();
+    let sequence = tokio::spawn({
+        let dispatcher = dispatcher.clone();
+        let deadline = Instant::now() + Duration::from_secs(30);
+        async move { run_shutdown_sequence(&dispatcher, &readers_tx, &listeners_tx, deadline).await }
+    });
+    readers_rx.wait_for(|stopped| *stopped).await.unwrap();
+    phase_rx
+        .wait_for(|p| *p == ShutdownPhase::Draining)
+        .await
+        .unwrap();
+    // Workers are warned as the drain starts, with the time they have left
+    match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+        Ok(Some(WorkerResponse {
+            response: Some(worker_response::Response::ServerShutdown(notice)),
+        })) => {
+            assert_eq!(notice.reason, "Server shutting down");
+            assert!((20..=25).contains(&notice.drain_seconds), "{notice:?}");
+        }
+        other => panic!("expected a ServerShutdown notice, got {other:?}"),
+    }
+
+    // Creates are refused so the client retries elsewhere; reads still work
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(serde_json::json!({
+                    "queue_name": queue,
+                    "task_name": "t",
+                }))))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.headers()[header::RETRY_AFTER], "5");
+    assert_error_response(
+        resp,
+        StatusCode::SERVICE_UNAVAILABLE,
+        "UNAVAILABLE",
+        "shutting down",
+    )
+    .await;
+    let resp = app
+        .clone()
+        .oneshot(get(&format!("/api/v1/tasks/{}", running.id)))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = app.oneshot(get("/readyz")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(!*listeners_rx.borrow(), "listeners closed before the drain");
+
+    // The in-flight task still reports in, then the worker is let go
+    dispatcher
+        .handle_task_result(
+            &worker_id,
+            TaskResult {
+                task_id: assignment.task_id.clone(),
+                task_run_id: assignment.task_run_id.clone(),
+                success: true,
+                output: r#"{"done":true}"#.to_string(),
+                ..Default::default()
+            },
+        )
+        .await;
+    match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+        Ok(Some(WorkerResponse {
+            response: Some(worker_response::Response::ServerShutdown(shutdown)),
+        })) => assert_eq!(shutdown.drain_seconds, 0),
+        other => panic!("expected ServerShutdown, got {other:?}"),
+    }
+    tokio::time::timeout(Duration::from_secs(5), sequence)
+        .await
+        .expect("shutdown sequence did not finish")
+        .unwrap();
+
+    assert!(*listeners_rx.borrow());
+    assert_eq!(dispatcher.shutdown().phase(), ShutdownPhase::Stopped);
+    let task = tasks::get_task(&pool, &running.id).await.unwrap().unwrap();
+    assert_eq!(task.status, "COMPLETED");
+    assert_eq!(task.output, Some(serde_json::json!({"done": true})));
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_shutdown_spills_buffered_tasks_to_pending(pool: PgPool) {
+    let queue = "shutdown-spill";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let task = create_test_task(&pool, queue, "t").await;
+    tasks::update_task_status(&pool, &task.id, "DISPATCHING")
+        .await
+        .unwrap();
+    assert!(
+        matching
+            .buffer_task(queue, PartitionId(task.partition_id), envelope_for(&task))
+            .is_buffered()
+    );
+
+    let (readers_tx, _readers_rx) = watch::channel(false);
+    let (listeners_tx, _listeners_rx) = watch::channel(false);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    run_shutdown_sequence(&dispatcher, &readers_tx, &listeners_tx, deadline).await;
+
+    // Left for another node's TaskReaders instead of lost with this one
+    assert_eq!(matching.stats().buffered_tasks, 0);
+    let row = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(row.status, "PENDING");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_grpc_refuses_creates_and_sessions_while_quiescing(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let node_id = NodeId::new();
+    let addr = "127.0.0.1:19887".parse().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching,
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id, 4)),
+        NodeForwarder::new(),
+        dispatcher.log_tx().clone(),
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut api = ApiServiceClient::new(channel.clone());
+    let existing = create_test_task(&pool, "shutdown-grpc", "t").await;
+
+    dispatcher.shutdown().advance(ShutdownPhase::Quiescing);
+
+    let err = api
+        .create_task(CreateTaskRequest {
+            queue_name: "shutdown-grpc".to_string(),
+            task_name: "t".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::Unavailable);
+    let task = api
+        .get_task(GetTaskRequest {
+            task_id: existing.id.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(task.task.unwrap().id, existing.id);
+
+    let (_tx, session_rx) = mpsc::channel::<WorkerRequest>(1);
+    let err = WorkerServiceClient::new(channel)
+        .session(ReceiverStream::new(session_rx))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::Unavailable);
+
+    // The phase only moves forward
+    dispatcher.shutdown().advance(ShutdownPhase::Running);
+    assert_eq!(dispatcher.shutdown().phase(), ShutdownPhase::Quiescing);
+    let _ = shutdown_tx.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_grpc_waits_for_in_flight_tasks_before_closing(pool: PgPool) {
+    let queue = "shutdown-wait";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let node_id = NodeId::new();
+    let addr = "127.0.0.1:19894".parse().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let server = tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id, 4)),
+        NodeForwarder::new(),
+        dispatcher.log_tx().clone(),
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let (worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let running = create_test_task(&pool, queue, "t").await;
+    let _ = matching.offer_task(
+        queue,
+        PartitionId(running.partition_id),
+        envelope_for(&running),
+    );
+    let assignment = recv_assignment(&mut rx).await;
+
+    dispatcher
+        .shutdown()
+        .set_deadline(Instant::now() + Duration::from_secs(10));
+    let _ = shutdown_tx.send(true);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!server.is_finished(), "closed with a task in flight");
+
+    dispatcher
+        .handle_task_result(
+            &worker_id,
+            TaskResult {
+                task_id: assignment.task_id,
+                task_run_id: assignment.task_run_id,
+                success: true,
+                ..Default::default()
+            },
+        )
+        .await;
+    tokio::time::timeout(Duration::from_secs(2), server)
+        .await
+        .expect("gRPC server did not close once idle")
+        .unwrap()
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_grpc_closes_at_shutdown_deadline(pool: PgPool) {
+    let queue = "shutdown-deadline";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let node_id = NodeId::new();
+    let addr = "127.0.0.1:19895".parse().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let server = tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id, 4)),
+        NodeForwarder::new(),
+        dispatcher.log_tx().clone(),
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let (_worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let running = create_test_task(&pool, queue, "t").await;
+    let _ = matching.offer_task(
+        queue,
+        PartitionId(running.partition_id),
+        envelope_for(&running),
+    );
+    recv_assignment(&mut rx).await;
+
+    // A stuck task doesn't hold the listener past the deadline
+    dispatcher
+        .shutdown()
+        .set_deadline(Instant::now() + Duration::from_millis(500));
+    let _ = shutdown_tx.send(true);
+    tokio::time::timeout(Duration::from_secs(3), server)
+        .await
+        .expect("gRPC server outlived the shutdown deadline")
+        .unwrap()
+        .unwrap();
+    assert_eq!(dispatcher.stats().active_tasks, 1);
+}
@@ -0,0 +1,147 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use tower::ServiceExt;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::{signals, tasks};
+use valka_sdk::ValkaWorker;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+fn signal_request(task_id: &str, body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(format!("/api/v1/tasks/{task_id}/signal"))
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_signal_reaches_running_handler(pool: PgPool) {
+    let queue = "signal-e2e";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19900".parse().unwrap();
+    let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let app = build_test_router_with(pool.clone(), matching.clone(), dispatcher.clone());
+
+    // Waits for approval; the "note" that arrives first stays buffered
+    let worker = ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&[queue])
+        .handler(|mut ctx| async move {
+            let approve = ctx.wait_for_signal("approve").await.expect("approve");
+            let note = ctx.receive_signal().await.expect("note");
+            Ok(serde_json::json!({
+                "approved_by": approve
+                    .parse_payload::<serde_json::Value>()
+                    .map_err(|e| e.to_string())?["by"],
+                "note": note.name,
+            }))
+        })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(worker.run());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let task = create_test_task(&pool, queue, "approval").await;
+    let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope_for(&task));
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while tasks::get_task(&pool, &task.id)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+        != "RUNNING"
+    {
+        assert!(Instant::now() < deadline, "task never started");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    for body in [
+        serde_json::json!({ "signal_name": "note" }),
+        serde_json::json!({ "signal_name": "approve", "payload": { "by": "ops" } }),
+    ] {
+        let resp = app
+            .clone()
+            .oneshot(signal_request(&task.id, body))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(parse_response_json(resp).await["delivered"], true);
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let row = loop {
+        let row = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+        if row.status == "COMPLETED" {
+            break row;
+        }
+        assert!(Instant::now() < deadline, "task is {}", row.status);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    assert_eq!(
+        row.output,
+        Some(serde_json::json!({ "approved_by": "ops", "note": "note" }))
+    );
+
+    // Both acks are written before the result
+    let sent = signals::list_signals(&pool, &task.id, None).await.unwrap();
+    assert_eq!(sent.len(), 2);
+    assert!(
+        sent.iter().all(|s| s.status == "ACKNOWLEDGED"),
+        "{:?}",
+        sent.iter().map(|s| &s.status).collect::<Vec<_>>()
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_signal_ack_before_delivered_mark(pool: PgPool) {
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let task = create_test_task(&pool, "signal-ack", "t").await;
+    signals::create_signal(&pool, "sig-early-ack", &task.id, "approve", None)
+        .await
+        .unwrap();
+
+    // The worker acks before the sender has marked the signal DELIVERED
+    dispatcher
+        .handle_signal_ack(&valka_proto::SignalAck {
+            signal_id: "sig-early-ack".to_string(),
+        })
+        .await;
+    assert!(
+        !signals::mark_delivered(&pool, "sig-early-ack")
+            .await
+            .unwrap()
+    );
+
+    let sent = signals::list_signals(&pool, &task.id, None).await.unwrap();
+    assert_eq!(sent[0].status, "ACKNOWLEDGED");
+    assert!(sent[0].delivered_at.is_some());
+}
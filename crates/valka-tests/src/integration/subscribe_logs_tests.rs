@@ -0,0 +1,192 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use sqlx::PgPool;
+use tokio::sync::{broadcast, mpsc, watch};
+use tonic::transport::Channel;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{LogIngesterConfig, MatchingConfig, NodeId, WorkerId};
+use valka_db::queries::task_logs::{InsertLogEntry, batch_insert_logs};
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_proto::api_service_client::ApiServiceClient;
+use valka_proto::{LogBatch, LogEntry, SubscribeLogsRequest};
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+/// gRPC server plus log ingester on `port`, sharing one dispatcher. Hold the
+/// returned shutdown sender for as long as they should run.
+async fn start_server(
+    pool: &PgPool,
+    port: u16,
+) -> (
+    DispatcherService,
+    ApiServiceClient<Channel>,
+    watch::Sender<bool>,
+) {
+    let node_id = NodeId::new();
+    let matching = MatchingService::new(MatchingConfig::default());
+    let (event_tx, _) = broadcast::channel::<valka_proto::TaskEvent>(128);
+    let (log_tx, log_rx) = mpsc::channel::<LogEntry>(128);
+    let dispatcher = DispatcherService::new(
+        matching.clone(),
+        pool.clone(),
+        node_id.clone(),
+        event_tx,
+        log_tx.clone(),
+    );
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::server::run_log_ingester(
+        pool.clone(),
+        watch::channel(LogIngesterConfig {
+            flush_interval_ms: 20,
+            ..Default::default()
+        })
+        .1,
+        log_rx,
+        dispatcher.log_stream().clone(),
+        shutdown_rx.clone(),
+    ));
+    let addr: SocketAddr = format!("127.0.0.1:{port}").parse().unwrap();
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching,
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id, 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    (dispatcher, ApiServiceClient::new(channel), shutdown_tx)
+}
+
+fn entry(run_id: &str, message: &str) -> LogEntry {
+    LogEntry {
+        task_run_id: run_id.to_string(),
+        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+        level: 2,
+        message: message.to_string(),
+        metadata: String::new(),
+    }
+}
+
+async fn next_message(stream: &mut tonic::Streaming<LogEntry>) -> Option<String> {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("no log entry within 5s")
+        .map(|entry| entry.unwrap().message)
+}
+
+/// Complete the run far enough in the past that the tail needn't wait for a flush.
+async fn complete_run_earlier(pool: &PgPool, run_id: &str) {
+    sqlx::query(
+        "UPDATE task_runs SET status = 'COMPLETED', completed_at = NOW() - INTERVAL '1 minute'
+         WHERE id = $1",
+    )
+    .bind(run_id)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_subscribe_logs_streams_history_then_live_until_run_ends(pool: PgPool) {
+    let (dispatcher, mut client, _shutdown) = start_server(&pool, 19921).await;
+    let (_task, run) = create_running_task(&pool, "logs").await;
+    batch_insert_logs(
+        &pool,
+        &[InsertLogEntry {
+            task_run_id: run.id.clone(),
+            timestamp_ms: 1,
+            level: "INFO".to_string(),
+            message: "earlier".to_string(),
+            metadata: None,
+        }],
+    )
+    .await
+    .unwrap();
+
+    let mut stream = client
+        .subscribe_logs(SubscribeLogsRequest {
+            task_run_id: run.id.clone(),
+            include_history: true,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(next_message(&mut stream).await.as_deref(), Some("earlier"));
+
+    // Through the worker path: handle_log_batch -> ingester -> live stream
+    dispatcher
+        .handle_log_batch(
+            &WorkerId::new(),
+            LogBatch {
+                entries: vec![entry("other-run", "elsewhere"), entry(&run.id, "now")],
+            },
+        )
+        .await;
+    assert_eq!(next_message(&mut stream).await.as_deref(), Some("now"));
+
+    complete_run_earlier(&pool, &run.id).await;
+    assert_eq!(next_message(&mut stream).await, None);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_subscribe_logs_without_history_starts_at_live(pool: PgPool) {
+    let (dispatcher, mut client, _shutdown) = start_server(&pool, 19892).await;
+
+    let status = client
+        .subscribe_logs(SubscribeLogsRequest {
+            task_run_id: "no-such-run".to_string(),
+            include_history: false,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    let (_task, run) = create_running_task(&pool, "logs").await;
+    let worker = WorkerId::new();
+    dispatcher
+        .handle_log_batch(
+            &worker,
+            LogBatch {
+                entries: vec![entry(&run.id, "before")],
+            },
+        )
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let mut stream = client
+        .subscribe_logs(SubscribeLogsRequest {
+            task_run_id: run.id.clone(),
+            include_history: false,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    dispatcher
+        .handle_log_batch(
+            &worker,
+            LogBatch {
+                entries: vec![entry(&run.id, "after")],
+            },
+        )
+        .await;
+    assert_eq!(next_message(&mut stream).await.as_deref(), Some("after"));
+}
@@ -0,0 +1,103 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::tasks;
+use valka_sdk::{ValkaClient, ValkaWorker};
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+async fn wait_for_status(pool: &PgPool, task_id: &str, status: &str) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let task = tasks::get_task(pool, task_id).await.unwrap().unwrap();
+        if task.status == status {
+            return;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "task {task_id} is {}, expected {status}",
+            task.status
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_cancel_stops_running_handler(pool: PgPool) {
+    let queue = "cancel-handler";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19899".parse().unwrap();
+    let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Sleeps unless cancelled, and reports how long it ran once it stops
+    let (stopped_tx, mut stopped_rx) = mpsc::channel::<Duration>(1);
+    let worker = ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&[queue])
+        .handler(move |ctx| {
+            let stopped_tx = stopped_tx.clone();
+            async move {
+                let started = Instant::now();
+                tokio::select! {
+                    () = tokio::time::sleep(Duration::from_secs(60)) => {}
+                    () = ctx.cancelled() => {}
+                }
+                let _ = stopped_tx.send(started.elapsed()).await;
+                Ok(serde_json::json!({ "cancelled": ctx.is_cancelled() }))
+            }
+        })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(worker.run());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let task = create_test_task(&pool, queue, "sleep").await;
+    let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope_for(&task));
+    wait_for_status(&pool, &task.id, "RUNNING").await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let mut client = ValkaClient::connect(&format!("http://{addr}"))
+        .await
+        .unwrap();
+    client.cancel_task(&task.id).await.unwrap();
+    let ran = tokio::time::timeout(Duration::from_secs(2), stopped_rx.recv())
+        .await
+        .expect("handler kept running after cancel")
+        .unwrap();
+    assert!(ran < Duration::from_secs(5), "{ran:?}");
+
+    // The handler's output is reported as a cancellation, not a completion,
+    // and the worker's slot is free for the next task
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let row = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(row.status, "CANCELLED");
+    assert_eq!(row.output, None);
+    let next = create_test_task(&pool, queue, "sleep").await;
+    let _ = matching.offer_task(queue, PartitionId(next.partition_id), envelope_for(&next));
+    wait_for_status(&pool, &next.id, "RUNNING").await;
+}
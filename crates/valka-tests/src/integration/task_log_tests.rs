@@ -0,0 +1,127 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::Instant;
+use tracing_subscriber::layer::SubscriberExt;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{MatchingConfig, NodeId, PartitionId};
+use valka_db::queries::tasks;
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_proto::LogLevel;
+use valka_sdk::ValkaWorker;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_handler_logs_and_tracing_events_reach_run_logs(pool: PgPool) {
+    // Current-thread runtime: the default subscriber covers the worker's tasks
+    let subscriber = tracing_subscriber::registry()
+        .with(valka_sdk::task_logs::task_log_layer(tracing::Level::INFO));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let matching = MatchingService::new(MatchingConfig::default());
+    let (event_tx, _) = broadcast::channel::<valka_proto::TaskEvent>(128);
+    let (log_tx, mut log_rx) = mpsc::channel::<valka_proto::LogEntry>(128);
+    let dispatcher = DispatcherService::new(
+        matching.clone(),
+        pool.clone(),
+        NodeId::new(),
+        event_tx,
+        log_tx,
+    );
+    let addr: SocketAddr = "127.0.0.1:19903".parse().unwrap();
+    let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (server_log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        server_log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let worker = ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&["task-logs"])
+        .handler(|ctx| async move {
+            ctx.log("starting").await;
+            ctx.log_with(
+                LogLevel::Warn,
+                "slow upstream",
+                serde_json::json!({ "elapsed_ms": 900 }),
+            )
+            .await;
+            tracing::info!(items = 3, "processed batch");
+            tracing::debug!("below the layer's level");
+            Ok(serde_json::json!({}))
+        })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(worker.run());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let task = create_test_task(&pool, "task-logs", "logged").await;
+    let mut envelope = envelope_for(&task);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while let Err(returned) =
+        matching.offer_task("task-logs", PartitionId(task.partition_id), envelope)
+    {
+        assert!(Instant::now() < deadline, "task never matched");
+        envelope = returned;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while tasks::get_task(&pool, &task.id)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+        != "COMPLETED"
+    {
+        assert!(Instant::now() < deadline, "task never completed");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    // Flushed ahead of the result, so nothing is still in flight
+    let mut entries = Vec::new();
+    while let Ok(entry) = log_rx.try_recv() {
+        entries.push(entry);
+    }
+    let messages: Vec<_> = entries.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(messages, ["starting", "slow upstream", "processed batch"]);
+    let run_id = &entries[0].task_run_id;
+    assert!(entries.iter().all(|e| &e.task_run_id == run_id));
+    let run = valka_db::queries::task_runs::get_task_run(&pool, run_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(run.task_id, task.id);
+
+    assert_eq!(entries[1].level, LogLevel::Warn as i32);
+    let metadata: serde_json::Value = serde_json::from_str(&entries[1].metadata).unwrap();
+    assert_eq!(metadata["elapsed_ms"], 900);
+    assert_eq!(entries[2].level, LogLevel::Info as i32);
+    let metadata: serde_json::Value = serde_json::from_str(&entries[2].metadata).unwrap();
+    assert_eq!(metadata["items"], 3);
+    assert!(
+        metadata["target"]
+            .as_str()
+            .unwrap()
+            .starts_with("valka_tests")
+    );
+}
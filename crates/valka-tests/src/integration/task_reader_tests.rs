@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::watch;
+use valka_core::{MatchingConfig, PartitionId, WorkerId};
+use valka_db::queries::queue_configs::{self, QueueOwnershipUpdate};
+use valka_matching::MatchingService;
+use valka_matching::task_reader::TaskReader;
+
+use super::helpers::*;
+
+/// Create `n` PENDING tasks on partition 0 of `queue`.
+async fn create_backlog(pool: &PgPool, queue: &str, n: usize) {
+    for _ in 0..n {
+        let mut params = default_task_params(queue, "t");
+        params.partition_id = 0;
+        create_test_task_full(pool, params).await;
+    }
+}
+
+fn start_reader(
+    pool: &PgPool,
+    matching: &MatchingService,
+    queue: &str,
+    config: MatchingConfig,
+) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let reader = TaskReader::new(
+        pool.clone(),
+        matching.clone(),
+        queue.to_string(),
+        PartitionId(0),
+        config,
+        shutdown_rx,
+    );
+    tokio::spawn(reader.run());
+    shutdown_tx
+}
+
+/// Tasks the reader has handed to matching; with no workers they all wait in
+/// the buffer.
+fn dispatched(matching: &MatchingService) -> usize {
+    matching.stats().buffered_tasks
+}
+
+async fn count_with_status(pool: &PgPool, queue: &str, status: &str) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM tasks WHERE queue_name = $1 AND status = $2")
+        .bind(queue)
+        .bind(status)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_task_reader_grows_batches_while_polls_come_back_full(pool: PgPool) {
+    create_backlog(&pool, "bulk", 600).await;
+    let matching = MatchingService::new(MatchingConfig::default());
+
+    // At a fixed 10 tasks per 100ms poll the backlog would take six seconds
+    let config = MatchingConfig {
+        task_reader_batch_size: 10,
+        task_reader_max_batch_size: 200,
+        task_reader_poll_busy_ms: 100,
+        task_reader_poll_idle_ms: 100,
+        ..MatchingConfig::default()
+    };
+    let shutdown = start_reader(&pool, &matching, "bulk", config);
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(dispatched(&matching), 600);
+    shutdown.send(true).unwrap();
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_task_reader_rate_limit_caps_dispatch(pool: PgPool) {
+    create_backlog(&pool, "fragile", 100).await;
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let limit = |per_second| QueueOwnershipUpdate {
+        max_dispatch_per_second: Some(per_second),
+        ..Default::default()
+    };
+    let config = queue_configs::upsert_queue_ownership(&pool, "fragile", &limit(20))
+        .await
+        .unwrap();
+    assert_eq!(config.max_dispatch_per_second, Some(20));
+    dispatcher.queue_policies().apply_config(&config);
+
+    let reader_config = MatchingConfig {
+        task_reader_poll_busy_ms: 10,
+        task_reader_poll_idle_ms: 10,
+        ..MatchingConfig::default()
+    };
+    let shutdown = start_reader(&pool, &matching, "fragile", reader_config);
+
+    // A second's worth up front, then 20 per second
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let count = dispatched(&matching);
+    assert!(
+        (40..=55).contains(&count),
+        "dispatched {count} tasks in 1.5s at 20/s"
+    );
+
+    // Clearing the limit lets the rest through
+    let config = queue_configs::upsert_queue_ownership(&pool, "fragile", &limit(0))
+        .await
+        .unwrap();
+    assert_eq!(config.max_dispatch_per_second, None);
+    dispatcher.queue_policies().apply_config(&config);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(dispatched(&matching), 100);
+    shutdown.send(true).unwrap();
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_task_reader_dequeues_only_what_the_buffer_can_hold(pool: PgPool) {
+    create_backlog(&pool, "tight", 10).await;
+    let config = MatchingConfig {
+        max_buffer_per_partition: 2,
+        task_reader_poll_busy_ms: 10,
+        task_reader_poll_idle_ms: 20,
+        ..MatchingConfig::default()
+    };
+    let matching = MatchingService::new(config.clone());
+    let shutdown = start_reader(&pool, &matching, "tight", config);
+
+    // Without workers the reader fills the buffer and leaves the rest alone
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(dispatched(&matching), 2);
+    assert_eq!(count_with_status(&pool, "tight", "DISPATCHING").await, 2);
+    assert_eq!(count_with_status(&pool, "tight", "PENDING").await, 8);
+
+    // Workers arriving one at a time get every task; none is stranded
+    let mut received = HashSet::new();
+    for _ in 0..10 {
+        let slot = matching.register_worker("tight", PartitionId(0), WorkerId::new());
+        let task = tokio::time::timeout(Duration::from_secs(2), slot)
+            .await
+            .expect("a worker waited on a stranded task")
+            .unwrap();
+        received.insert(task.task_id);
+    }
+    assert_eq!(received.len(), 10);
+    assert_eq!(count_with_status(&pool, "tight", "PENDING").await, 0);
+    shutdown.send(true).unwrap();
+}
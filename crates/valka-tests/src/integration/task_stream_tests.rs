@@ -0,0 +1,180 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::tasks::TaskRow;
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_sdk::{IncomingTask, ValkaTaskStream, ValkaWorker};
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+/// Serve the worker gRPC API on `addr` until the returned sender fires.
+async fn start_server(
+    pool: &PgPool,
+    dispatcher: &DispatcherService,
+    matching: &MatchingService,
+    addr: SocketAddr,
+) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown_tx
+}
+
+async fn connect_stream(addr: SocketAddr, queue: &str, concurrency: i32) -> ValkaTaskStream {
+    ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&[queue])
+        .concurrency(concurrency)
+        .build_stream()
+        .await
+        .unwrap()
+}
+
+fn offer(matching: &MatchingService, task: &TaskRow, attempt_number: i32) {
+    let mut envelope = envelope_for(task);
+    envelope.attempt_number = attempt_number;
+    let partition = PartitionId(task.partition_id);
+    if let Err(envelope) = matching.offer_task(&task.queue_name, partition, envelope) {
+        matching.buffer_task(&task.queue_name, partition, envelope);
+    }
+}
+
+async fn next_task(stream: &mut ValkaTaskStream) -> IncomingTask {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("task not yielded")
+        .expect("stream ended")
+}
+
+async fn wait_for_status(pool: &PgPool, task_id: &str, status: &str) -> TaskRow {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let task = valka_db::queries::tasks::get_task(pool, task_id)
+            .await
+            .unwrap()
+            .unwrap();
+        if task.status == status {
+            return task;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "task {task_id} is {}, expected {status}",
+            task.status
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_task_stream_resolves_and_bounds_outstanding(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19881".parse().unwrap();
+    let shutdown = start_server(&pool, &dispatcher, &matching, addr).await;
+    let mut stream = connect_stream(addr, "gpu", 2).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let mut tasks = Vec::new();
+    for _ in 0..3 {
+        let task = create_test_task(&pool, "gpu", "embed").await;
+        offer(&matching, &task, 1);
+        tasks.push(task);
+    }
+
+    let first = next_task(&mut stream).await;
+    let second = next_task(&mut stream).await;
+    assert_eq!(first.context().task_name, "embed");
+
+    // Both slots are taken by unresolved tasks
+    let third = tokio::time::timeout(Duration::from_millis(500), stream.next()).await;
+    assert!(
+        third.is_err(),
+        "third task yielded while two are unresolved"
+    );
+
+    let (completed_id, failed_id) = (
+        first.assignment().task_id.clone(),
+        second.assignment().task_id.clone(),
+    );
+    first
+        .complete(serde_json::json!({"vectors": 3}))
+        .await
+        .unwrap();
+    let task = wait_for_status(&pool, &completed_id, "COMPLETED").await;
+    assert_eq!(task.output, Some(serde_json::json!({"vectors": 3})));
+
+    // Resolving one frees a slot for the third
+    let third = next_task(&mut stream).await;
+    let yielded: Vec<_> = tasks.iter().map(|t| t.id.as_str()).collect();
+    assert!(yielded.contains(&third.assignment().task_id.as_str()));
+    assert_ne!(third.assignment().task_id, completed_id);
+    assert_ne!(third.assignment().task_id, failed_id);
+
+    second.fail("out of GPU memory", false).await.unwrap();
+    let task = wait_for_status(&pool, &failed_id, "FAILED").await;
+    assert_eq!(task.error_message.as_deref(), Some("out of GPU memory"));
+    let runs = valka_db::queries::task_runs::get_runs_for_task(&pool, &failed_id)
+        .await
+        .unwrap();
+    assert_eq!(runs[0].status, "FAILED");
+    assert_eq!(runs[0].worker_id, stream.worker_id());
+
+    third.complete(serde_json::json!(null)).await.unwrap();
+    let _ = shutdown.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_task_stream_reject_requeues_task(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19882".parse().unwrap();
+    let shutdown = start_server(&pool, &dispatcher, &matching, addr).await;
+    let mut stream = connect_stream(addr, "gpu", 1).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let task = create_test_task(&pool, "gpu", "embed").await;
+    offer(&matching, &task, 1);
+    let incoming = next_task(&mut stream).await;
+    wait_for_status(&pool, &task.id, "RUNNING").await;
+    incoming.reject().await.unwrap();
+
+    // Back to PENDING right away rather than RETRY or FAILED
+    let requeued = wait_for_status(&pool, &task.id, "PENDING").await;
+    let runs = valka_db::queries::task_runs::get_runs_for_task(&pool, &task.id)
+        .await
+        .unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].error_message.as_deref(), Some("Rejected by worker"));
+
+    // The rejected slot is free again and the task can be dispatched anew
+    offer(&matching, &requeued, requeued.attempt_count + 1);
+    let retried = next_task(&mut stream).await;
+    assert_eq!(retried.assignment().task_id, task.id);
+    assert_eq!(retried.assignment().attempt_number, 2);
+
+    // Dropping an unresolved task hands it back as well
+    drop(retried);
+    wait_for_status(&pool, &task.id, "PENDING").await;
+    let _ = shutdown.send(true);
+}
@@ -0,0 +1,243 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SpanData};
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tower::ServiceExt;
+use tracing::Instrument;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, WorkerId};
+use valka_dispatcher::DispatcherService;
+use valka_proto::{TaskAssignment, TaskResult};
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+/// Run the handler span the SDK would, then report success with its traceparent.
+async fn complete_like_sdk(
+    dispatcher: &DispatcherService,
+    worker_id: &WorkerId,
+    assignment: &TaskAssignment,
+) {
+    let span = valka_sdk::telemetry::task_span(assignment);
+    let traceparent = valka_sdk::telemetry::traceparent(&span);
+    drop(span);
+    assert!(!traceparent.is_empty());
+
+    let result = TaskResult {
+        task_id: assignment.task_id.clone(),
+        task_run_id: assignment.task_run_id.clone(),
+        success: true,
+        retryable: false,
+        output: "{}".to_string(),
+        error_message: String::new(),
+        traceparent,
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
+    };
+    dispatcher.handle_task_result(worker_id, result).await;
+}
+
+/// Wait until every span in `names` has finished, then return all finished spans.
+async fn wait_for_spans(exporter: &InMemorySpanExporter, names: &[&str]) -> Vec<SpanData> {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    loop {
+        let spans = exporter.get_finished_spans().unwrap();
+        if names.iter().all(|n| spans.iter().any(|s| s.name == *n)) {
+            return spans;
+        }
+        if tokio::time::Instant::now() > deadline {
+            let got: Vec<_> = spans.iter().map(|s| s.name.to_string()).collect();
+            panic!("expected spans {names:?}, got {got:?}");
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Assert each span in `chain` is the parent of the next, all in one trace.
+fn assert_parent_chain(spans: &[SpanData], chain: &[&str]) {
+    let chain: Vec<SpanData> = chain.iter().map(|n| finished_span(spans, n)).collect();
+    let trace_id = chain[0].span_context.trace_id();
+    for pair in chain.windows(2) {
+        assert_eq!(
+            pair[1].span_context.trace_id(),
+            trace_id,
+            "{}",
+            pair[1].name
+        );
+        assert_eq!(
+            pair[1].parent_span_id,
+            pair[0].span_context.span_id(),
+            "{} should be parented under {}",
+            pair[1].name,
+            pair[0].name
+        );
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_trace_spans_rest_create_to_result(pool: PgPool) {
+    let (exporter, _provider, _guard) = capture_spans();
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, "traced", 1).await;
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(
+                    serde_json::json!({"queue_name": "traced", "task_name": "t"}),
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let task_id = parse_response_json(resp).await["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task_id);
+    complete_like_sdk(&dispatcher, &worker_id, &assignment).await;
+
+    let chain = ["create_task", "dispatch_task", "handle_task", "task_result"];
+    let spans = wait_for_spans(&exporter, &chain).await;
+    assert_parent_chain(&spans, &chain);
+
+    // The stored context lets the TaskReader re-join the trace after a restart
+    let task = valka_db::queries::tasks::get_task(&pool, &task_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let create = finished_span(&spans, "create_task");
+    assert!(
+        task.traceparent
+            .unwrap()
+            .contains(&create.span_context.trace_id().to_string())
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_trace_rest_traceparent_header_reaches_handler(pool: PgPool) {
+    let (exporter, _provider, _guard) = capture_spans();
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool.clone(), matching, dispatcher.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, "traced-header", 1).await;
+
+    let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .header("content-type", "application/json")
+                .header("traceparent", format!("00-{trace_id}-00f067aa0ba902b7-01"))
+                .body(Body::from(json_body(
+                    serde_json::json!({"queue_name": "traced-header", "task_name": "t"}),
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let assignment = recv_assignment(&mut rx).await;
+    assert!(assignment.traceparent.contains(trace_id));
+    complete_like_sdk(&dispatcher, &worker_id, &assignment).await;
+
+    // The caller's trace continues all the way into the worker's handler span
+    let chain = ["create_task", "dispatch_task", "handle_task"];
+    let spans = wait_for_spans(&exporter, &chain).await;
+    for name in chain {
+        let span = finished_span(&spans, name);
+        assert_eq!(span.span_context.trace_id().to_string(), trace_id, "{name}");
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_trace_spans_untraced_without_layer(pool: PgPool) {
+    let task = create_test_task(&pool, "untraced", "t").await;
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (_worker_id, mut rx) = start_worker(&dispatcher, "untraced", 1).await;
+
+    let mut envelope = envelope_for(&task);
+    envelope.traceparent = Some("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".into());
+    let partition = valka_core::PartitionId(task.partition_id);
+    if let Err(envelope) = matching.offer_task(&task.queue_name, partition, envelope) {
+        matching.buffer_task(&task.queue_name, partition, envelope);
+    }
+
+    // No OpenTelemetry layer installed: nothing is propagated
+    let assignment = recv_assignment(&mut rx).await;
+    assert_eq!(assignment.task_id, task.id);
+    assert!(assignment.traceparent.is_empty());
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_trace_spans_forwarded_to_owner(pool: PgPool) {
+    let (exporter, _provider, _guard) = capture_spans();
+    let task = create_test_task(&pool, "forwarded", "t").await;
+
+    // Owning node: internal gRPC service plus a waiting worker
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, "forwarded", 1).await;
+    let addr: SocketAddr = "127.0.0.1:19871".parse().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    let server = tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Originating node forwards from inside its create span
+    let accepted = NodeForwarder::new()
+        .forward_task(
+            &addr.to_string(),
+            &task.id,
+            &task.queue_name,
+            task.partition_id,
+        )
+        .instrument(tracing::info_span!("create_task"))
+        .await
+        .unwrap();
+    assert!(accepted);
+
+    let assignment = recv_assignment(&mut rx).await;
+    complete_like_sdk(&dispatcher, &worker_id, &assignment).await;
+
+    let chain = [
+        "create_task",
+        "forward_task",
+        "accept_forwarded_task",
+        "dispatch_task",
+        "handle_task",
+        "task_result",
+    ];
+    let spans = wait_for_spans(&exporter, &chain).await;
+    assert_parent_chain(&spans, &chain);
+
+    let _ = shutdown_tx.send(true);
+    let _ = tokio::time::timeout(Duration::from_secs(5), server).await;
+}
@@ -0,0 +1,207 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::tasks::{self, TaskRow};
+use valka_matching::MatchingService;
+use valka_sdk::{TaskError, ValkaWorker};
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+#[derive(Deserialize)]
+struct EmailInput {
+    to: String,
+    subject: String,
+}
+
+#[derive(Serialize)]
+struct EmailOutput {
+    message_id: String,
+}
+
+async fn offer(
+    pool: &PgPool,
+    matching: &MatchingService,
+    queue: &str,
+    name: &str,
+    input: serde_json::Value,
+) -> TaskRow {
+    let mut params = default_task_params(queue, name);
+    params.input = Some(input);
+    let task = create_test_task_full(pool, params).await;
+    let mut envelope = envelope_for(&task);
+    envelope.input = task.input.as_ref().map(|v| v.to_string());
+    // Matches only while the worker's loop is waiting between dispatches
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while let Err(returned) = matching.offer_task(queue, PartitionId(task.partition_id), envelope) {
+        assert!(Instant::now() < deadline, "{name} never matched");
+        envelope = returned;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    task
+}
+
+async fn wait_until_done(pool: &PgPool, task_id: &str) -> TaskRow {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let task = tasks::get_task(pool, task_id).await.unwrap().unwrap();
+        if matches!(task.status.as_str(), "COMPLETED" | "FAILED" | "RETRY") {
+            return task;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "task {} is {}",
+            task.task_name,
+            task.status
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_registered_handlers_route_by_task_name(pool: PgPool) {
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19902".parse().unwrap();
+    let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let url = format!("http://{addr}");
+
+    let routed = ValkaWorker::builder()
+        .server_addr(&url)
+        .queues(&["typed"])
+        .concurrency(4)
+        .register("email.send", |_ctx, input: EmailInput| async move {
+            Ok(EmailOutput {
+                message_id: format!("{}:{}", input.to, input.subject),
+            })
+        })
+        .register("sum", |_ctx, numbers: Vec<i64>| async move {
+            if numbers.is_empty() {
+                return Err(TaskError::fatal("Nothing to sum"));
+            }
+            Ok(numbers.iter().sum::<i64>())
+        })
+        .register("throttled", |_ctx, _: serde_json::Value| async move {
+            Err::<(), _>(TaskError::retry_after("429", Duration::from_secs(90)))
+        })
+        .handler(|ctx| async move { Ok(serde_json::json!({ "fallback": ctx.task_name })) })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(routed.run());
+    // No fallback: unknown names fail
+    let strict = ValkaWorker::builder()
+        .server_addr(&url)
+        .queues(&["typed-strict"])
+        .register("sum", |_ctx, numbers: Vec<i64>| async move {
+            Ok(numbers.iter().sum::<i64>())
+        })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(strict.run());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let email = offer(
+        &pool,
+        &matching,
+        "typed",
+        "email.send",
+        serde_json::json!({ "to": "a@example.com", "subject": "hi" }),
+    )
+    .await;
+    let sum = offer(
+        &pool,
+        &matching,
+        "typed",
+        "sum",
+        serde_json::json!([1, 2, 3]),
+    )
+    .await;
+    let bad_input = offer(
+        &pool,
+        &matching,
+        "typed",
+        "email.send",
+        serde_json::json!({ "to": "a@example.com" }),
+    )
+    .await;
+    let empty_sum = offer(&pool, &matching, "typed", "sum", serde_json::json!([])).await;
+    let other = offer(&pool, &matching, "typed", "other", serde_json::json!({})).await;
+    let throttled = offer(
+        &pool,
+        &matching,
+        "typed",
+        "throttled",
+        serde_json::json!({}),
+    )
+    .await;
+
+    let row = wait_until_done(&pool, &email.id).await;
+    assert_eq!(row.status, "COMPLETED");
+    assert_eq!(
+        row.output,
+        Some(serde_json::json!({ "message_id": "a@example.com:hi" }))
+    );
+    let row = wait_until_done(&pool, &sum.id).await;
+    assert_eq!(row.output, Some(serde_json::json!(6)));
+
+    // Bad input and fatal errors fail without retry
+    let row = wait_until_done(&pool, &bad_input.id).await;
+    assert_eq!(row.status, "FAILED");
+    let error = row.error_message.unwrap();
+    assert!(
+        error.contains("Invalid input for task 'email.send'") && error.contains("subject"),
+        "{error}"
+    );
+    let row = wait_until_done(&pool, &empty_sum.id).await;
+    assert_eq!(row.status, "FAILED");
+    assert_eq!(row.error_message.as_deref(), Some("Nothing to sum"));
+
+    let row = wait_until_done(&pool, &other.id).await;
+    assert_eq!(row.status, "COMPLETED");
+    assert_eq!(row.output, Some(serde_json::json!({ "fallback": "other" })));
+
+    // The handler's delay is kept for the retry processor
+    let row = wait_until_done(&pool, &throttled.id).await;
+    assert_eq!(row.status, "RETRY");
+    assert_eq!(row.retry_after_ms, Some(90_000));
+
+    let unknown = offer(
+        &pool,
+        &matching,
+        "typed-strict",
+        "other",
+        serde_json::json!({}),
+    )
+    .await;
+    let row = wait_until_done(&pool, &unknown.id).await;
+    assert_eq!(row.status, "FAILED");
+    assert_eq!(
+        row.error_message.as_deref(),
+        Some("No handler registered for task 'other'")
+    );
+}
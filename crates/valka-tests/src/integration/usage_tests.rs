@@ -0,0 +1,306 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use http_body_util::BodyExt;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use valka_db::queries::usage::{self, UsageDailyRow};
+
+use super::helpers::*;
+
+fn day() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, 2).unwrap()
+}
+
+/// `day()` at `h:m:s` UTC, plus `extra_ms`.
+fn at(h: u32, m: u32, s: u32, extra_ms: i64) -> DateTime<Utc> {
+    day().and_hms_opt(h, m, s).unwrap().and_utc() + Duration::milliseconds(extra_ms)
+}
+
+async fn task_created_at(pool: &PgPool, queue: &str, created_at: DateTime<Utc>) -> String {
+    let task = create_test_task(pool, queue, "bill").await;
+    sqlx::query("UPDATE tasks SET created_at = $2 WHERE id = $1")
+        .bind(&task.id)
+        .bind(created_at)
+        .execute(pool)
+        .await
+        .unwrap();
+    task.id
+}
+
+async fn finished_run(
+    pool: &PgPool,
+    task_id: &str,
+    status: &str,
+    started_at: DateTime<Utc>,
+    execution_ms: i64,
+) -> String {
+    let run = create_test_run(pool, task_id, 1, Utc::now() + Duration::minutes(5)).await;
+    sqlx::query(
+        "UPDATE task_runs SET status = $2, started_at = $3, completed_at = $4 WHERE id = $1",
+    )
+    .bind(&run.id)
+    .bind(status)
+    .bind(started_at)
+    .bind(started_at + Duration::milliseconds(execution_ms))
+    .execute(pool)
+    .await
+    .unwrap();
+    run.id
+}
+
+async fn task_log(pool: &PgPool, run_id: &str, message: &str, created_at: DateTime<Utc>) {
+    sqlx::query(
+        "INSERT INTO task_logs (task_run_id, timestamp_ms, level, message, created_at) \
+         VALUES ($1, $2, 'INFO', $3, $4)",
+    )
+    .bind(run_id)
+    .bind(created_at.timestamp_millis())
+    .bind(message)
+    .bind(created_at)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// A day of synthetic activity on two queues; one run finishes after midnight.
+async fn seed_day(pool: &PgPool) {
+    sqlx::query("INSERT INTO queue_configs (queue_name, owner_team) VALUES ($1, $2)")
+        .bind("billing.invoices")
+        .bind("payments")
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let t1 = task_created_at(pool, "billing.invoices", at(9, 0, 0, 0)).await;
+    let t2 = task_created_at(pool, "billing.invoices", at(13, 0, 0, 0)).await;
+    let t3 = task_created_at(pool, "billing.refunds", at(23, 59, 59, 0)).await;
+    let t4 = task_created_at(pool, "search", at(18, 0, 0, 0)).await;
+
+    let r1 = finished_run(pool, &t1, "COMPLETED", at(9, 0, 1, 0), 2000).await;
+    task_log(pool, &r1, "hello", at(9, 0, 2, 0)).await;
+    task_log(pool, &r1, "wörld", at(9, 0, 2, 500)).await;
+    finished_run(pool, &t2, "FAILED", at(13, 0, 1, 0), 1500).await;
+    finished_run(pool, &t3, "COMPLETED", at(23, 59, 59, 800), 700).await;
+    finished_run(pool, &t4, "COMPLETED", at(18, 0, 1, 0), 250).await;
+}
+
+async fn usage_rows(pool: &PgPool) -> Vec<UsageDailyRow> {
+    sqlx::query_as::<_, UsageDailyRow>("SELECT * FROM usage_daily ORDER BY date, queue_name")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+/// (date, queue, created, completed, failed, execution_ms, log_bytes)
+fn counters(rows: &[UsageDailyRow]) -> Vec<(String, String, i64, i64, i64, i64, i64)> {
+    rows.iter()
+        .map(|r| {
+            (
+                r.date.to_string(),
+                r.queue_name.clone(),
+                r.tasks_created,
+                r.tasks_completed,
+                r.tasks_failed,
+                r.total_execution_ms,
+                r.total_log_bytes,
+            )
+        })
+        .collect()
+}
+
+fn expected_day() -> Vec<(String, String, i64, i64, i64, i64, i64)> {
+    let d = |s: &str| s.to_string();
+    vec![
+        (d("2026-03-02"), d("billing.invoices"), 2, 1, 1, 3500, 11),
+        (d("2026-03-02"), d("billing.refunds"), 1, 0, 0, 0, 0),
+        (d("2026-03-02"), d("search"), 1, 1, 0, 250, 0),
+        (d("2026-03-03"), d("billing.refunds"), 0, 1, 0, 700, 0),
+    ]
+}
+
+async fn get(app: &axum::Router, uri: &str, accept: Option<&str>) -> axum::response::Response {
+    let mut req = Request::builder().uri(uri);
+    if let Some(accept) = accept {
+        req = req.header(header::ACCEPT, accept);
+    }
+    app.clone()
+        .oneshot(req.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_usage_rollup_incremental_and_idempotent(pool: PgPool) {
+    seed_day(&pool).await;
+
+    // Two windows split mid-day add up to the whole day
+    let first = usage::rollup_usage(&pool, at(12, 0, 0, 0)).await.unwrap();
+    assert_eq!(first.to, at(12, 0, 0, 0));
+    assert_eq!(counters(&usage_rows(&pool).await).len(), 1);
+
+    let end = at(0, 0, 0, 0) + Duration::days(2);
+    usage::rollup_usage(&pool, end).await.unwrap();
+    let after_first = usage_rows(&pool).await;
+    assert_eq!(counters(&after_first), expected_day());
+
+    // A rerun (e.g. after a scheduler restart) starts at the stored mark
+    let again = usage::rollup_usage(&pool, end).await.unwrap();
+    assert_eq!(again.rows_upserted, 0);
+    let stale = usage::rollup_usage(&pool, at(12, 0, 0, 0)).await.unwrap();
+    assert_eq!(stale.rows_upserted, 0);
+    assert_eq!(counters(&usage_rows(&pool).await), expected_day());
+    assert_eq!(usage::get_high_water_mark(&pool).await.unwrap(), end);
+
+    let invoices = &after_first[0];
+    assert_eq!(invoices.namespace, "billing");
+    assert_eq!(invoices.owner_team.as_deref(), Some("payments"));
+    assert_eq!(after_first[1].owner_team, None);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_usage_backfill_recomputes_range(pool: PgPool) {
+    seed_day(&pool).await;
+    let end = at(0, 0, 0, 0) + Duration::days(2);
+    usage::rollup_usage(&pool, end).await.unwrap();
+
+    // A task landing behind the mark is missed by the rollup
+    task_created_at(&pool, "search", at(20, 0, 0, 0)).await;
+    usage::rollup_usage(&pool, end).await.unwrap();
+    assert_eq!(counters(&usage_rows(&pool).await), expected_day());
+
+    // Backfill recounts it, and is itself idempotent
+    let next = day().succ_opt().unwrap();
+    for _ in 0..2 {
+        usage::backfill_usage(&pool, day(), next).await.unwrap();
+        let mut expected = expected_day();
+        expected[2].2 = 2;
+        assert_eq!(counters(&usage_rows(&pool).await), expected);
+    }
+
+    // Days past the mark are left to the rollup
+    assert_eq!(
+        usage::backfill_usage(&pool, next + Duration::days(1), next + Duration::days(1))
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_scheduler_usage_rollup_leaves_lag(pool: PgPool) {
+    create_test_task(&pool, "recent", "t").await;
+
+    // Too recent to be folded in yet
+    valka_scheduler::usage::rollup_usage(&pool, Duration::seconds(30))
+        .await
+        .unwrap();
+    assert!(usage_rows(&pool).await.is_empty());
+
+    valka_scheduler::usage::rollup_usage(&pool, Duration::seconds(-1))
+        .await
+        .unwrap();
+    let rows = usage_rows(&pool).await;
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].tasks_created, 1);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_usage_endpoint_json_and_csv(pool: PgPool) {
+    seed_day(&pool).await;
+    usage::rollup_usage(&pool, at(0, 0, 0, 0) + Duration::days(2))
+        .await
+        .unwrap();
+    let app = build_test_router(pool);
+
+    let resp = get(&app, "/api/v1/usage?from=2026-03-02&to=2026-03-02", None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["group_by"], "queue_name");
+    assert_eq!(body["complete_through"], "2026-03-04T00:00:00+00:00");
+    let rows = body["rows"].as_array().unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["queue_name"], "billing.invoices");
+    assert_eq!(rows[0]["tasks_created"], 2);
+    assert_eq!(rows[0]["total_execution_ms"], 3500);
+    assert_eq!(rows[0]["total_log_bytes"], 11);
+
+    let resp = get(
+        &app,
+        "/api/v1/usage?from=2026-03-01&to=2026-03-31&group_by=namespace",
+        Some("text/csv"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers()[header::CONTENT_TYPE],
+        "text/csv; charset=utf-8"
+    );
+    let csv = resp.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        std::str::from_utf8(&csv).unwrap(),
+        "date,namespace,tasks_created,tasks_completed,tasks_failed,total_execution_ms,total_log_bytes\n\
+         2026-03-02,billing,3,1,1,3500,11\n\
+         2026-03-02,search,1,1,0,250,0\n\
+         2026-03-03,billing,0,1,0,700,0\n"
+    );
+
+    let resp = get(
+        &app,
+        "/api/v1/usage?from=2026-03-02&to=2026-03-02&group_by=owner_team",
+        None,
+    )
+    .await;
+    let body = parse_response_json(resp).await;
+    assert_eq!(body["rows"][0]["owner_team"], "payments");
+    assert_eq!(body["rows"][1]["owner_team"], serde_json::Value::Null);
+    assert_eq!(body["rows"][1]["tasks_created"], 2);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_usage_endpoint_validation(pool: PgPool) {
+    let app = build_test_router(pool);
+
+    for (uri, message) in [
+        ("/api/v1/usage?to=2026-03-02", "required"),
+        ("/api/v1/usage?from=2026-03-03&to=2026-03-02", "after"),
+        ("/api/v1/usage?from=2025-01-01&to=2026-03-02", "exceed"),
+        (
+            "/api/v1/usage?from=2026-03-01&to=2026-03-02&group_by=task",
+            "Invalid group_by",
+        ),
+    ] {
+        let resp = get(&app, uri, None).await;
+        assert_error_response(resp, StatusCode::BAD_REQUEST, "VALIDATION_ERROR", message).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_usage_backfill_endpoint(pool: PgPool) {
+    seed_day(&pool).await;
+    usage::rollup_usage(&pool, at(0, 0, 0, 0) + Duration::days(2))
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM usage_daily")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let app = build_test_router(pool.clone());
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/usage/backfill")
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(
+                    serde_json::json!({"from": "2026-03-02", "to": "2026-03-03"}),
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(parse_response_json(resp).await["rows"], 4);
+    assert_eq!(counters(&usage_rows(&pool).await), expected_day());
+}
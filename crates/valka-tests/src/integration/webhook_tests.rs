@@ -0,0 +1,235 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use valka_core::{WebhooksConfig, WorkerId};
+use valka_db::queries::tasks;
+use valka_dispatcher::DispatcherService;
+use valka_dispatcher::webhooks::WebhookSender;
+use valka_sdk::webhooks::{TaskWebhookEvent, WebhookVerifier};
+
+use super::helpers::*;
+
+const SECRET: &str = "whsec_test";
+
+#[derive(Clone)]
+struct Receiver {
+    /// Requests answered with a 500 before the endpoint starts accepting
+    fail_first: usize,
+    seen: Arc<AtomicUsize>,
+    tx: mpsc::UnboundedSender<(HeaderMap, Bytes)>,
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    let n = receiver.seen.fetch_add(1, Ordering::SeqCst);
+    let _ = receiver.tx.send((headers, body));
+    if n < receiver.fail_first {
+        StatusCode::INTERNAL_SERVER_ERROR
+    } else {
+        StatusCode::NO_CONTENT
+    }
+}
+
+/// Serve a callback endpoint on a free port; returns its URL and the
+/// requests it gets.
+async fn start_receiver(
+    fail_first: usize,
+) -> (String, mpsc::UnboundedReceiver<(HeaderMap, Bytes)>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new()
+        .route("/hook", post(receive))
+        .with_state(Receiver {
+            fail_first,
+            seen: Arc::default(),
+            tx,
+        });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("http://{addr}/hook"), rx)
+}
+
+fn webhook_dispatcher(pool: &PgPool) -> DispatcherService {
+    let config = WebhooksConfig {
+        retry_base_delay_ms: 10,
+        signing_secret: Some(SECRET.to_string()),
+        // The test receivers listen on loopback
+        allowed_hosts: vec!["127.0.0.1".to_string()],
+        ..WebhooksConfig::default()
+    };
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    dispatcher.with_webhooks(WebhookSender::start(pool.clone(), &config))
+}
+
+async fn set_callback_url(pool: &PgPool, task_id: &str, url: &str) {
+    sqlx::query("UPDATE tasks SET callback_url = $2 WHERE id = $1")
+        .bind(task_id)
+        .bind(url)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+async fn recv_event(rx: &mut mpsc::UnboundedReceiver<(HeaderMap, Bytes)>) -> TaskWebhookEvent {
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("no webhook within 5s")
+        .unwrap();
+    WebhookVerifier::new(SECRET)
+        .verify_and_parse(&headers, &body)
+        .expect("webhook signature")
+}
+
+fn task_result(
+    task_id: &str,
+    run_id: &str,
+    success: bool,
+    retryable: bool,
+) -> valka_proto::TaskResult {
+    valka_proto::TaskResult {
+        task_id: task_id.to_string(),
+        task_run_id: run_id.to_string(),
+        success,
+        output: if success {
+            r#"{"done":true}"#.to_string()
+        } else {
+            String::new()
+        },
+        error_message: if success {
+            String::new()
+        } else {
+            "boom".to_string()
+        },
+        retryable,
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_completed_task_posts_signed_callback(pool: PgPool) {
+    let (url, mut rx) = start_receiver(0).await;
+    let dispatcher = webhook_dispatcher(&pool);
+    let (task, run) = create_running_task(&pool, "hooks").await;
+    set_callback_url(&pool, &task.id, &url).await;
+
+    dispatcher
+        .handle_task_result(
+            &WorkerId::new(),
+            task_result(&task.id, &run.id, true, false),
+        )
+        .await;
+
+    let event = recv_event(&mut rx).await;
+    assert_eq!(event.event_type, "task.completed");
+    assert_eq!(event.task_id, task.id);
+    assert_eq!(event.status, "COMPLETED");
+    assert_eq!(event.output.unwrap()["done"], true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_callback_to_internal_address_is_refused(pool: PgPool) {
+    let (url, mut rx) = start_receiver(0).await;
+    let config = WebhooksConfig {
+        retry_base_delay_ms: 10,
+        ..WebhooksConfig::default()
+    };
+    let (dispatcher, _matching) = make_dispatcher(pool.clone());
+    let dispatcher = dispatcher.with_webhooks(WebhookSender::start(pool.clone(), &config));
+    assert!(dispatcher.webhooks().validate_callback_url(&url).is_err());
+
+    // Stored without going through CreateTask, it is still not delivered
+    let (task, run) = create_running_task(&pool, "hooks").await;
+    set_callback_url(&pool, &task.id, &url).await;
+    dispatcher
+        .handle_task_result(
+            &WorkerId::new(),
+            task_result(&task.id, &run.id, true, false),
+        )
+        .await;
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(rx.try_recv().is_err(), "webhook reached a loopback address");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_failed_callback_is_retried(pool: PgPool) {
+    let (url, mut rx) = start_receiver(2).await;
+    let dispatcher = webhook_dispatcher(&pool);
+    let (task, run) = create_running_task(&pool, "hooks").await;
+    set_callback_url(&pool, &task.id, &url).await;
+
+    dispatcher
+        .handle_task_result(
+            &WorkerId::new(),
+            task_result(&task.id, &run.id, false, false),
+        )
+        .await;
+
+    // Two 500s, then accepted; every attempt carries the same body
+    let events = [
+        recv_event(&mut rx).await,
+        recv_event(&mut rx).await,
+        recv_event(&mut rx).await,
+    ];
+    for event in &events {
+        assert_eq!(event.event_type, "task.failed");
+        assert_eq!(event.error_message.as_deref(), Some("boom"));
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(rx.try_recv().is_err(), "delivered webhook was sent again");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_retryable_failure_posts_no_callback(pool: PgPool) {
+    let (url, mut rx) = start_receiver(0).await;
+    let dispatcher = webhook_dispatcher(&pool);
+    let (task, run) = create_running_task(&pool, "hooks").await;
+    set_callback_url(&pool, &task.id, &url).await;
+
+    dispatcher
+        .handle_task_result(
+            &WorkerId::new(),
+            task_result(&task.id, &run.id, false, true),
+        )
+        .await;
+
+    let task = tasks::get_task(&pool, &task.id).await.unwrap().unwrap();
+    assert_eq!(task.status, "RETRY");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(rx.try_recv().is_err(), "RETRY is not terminal");
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_dead_lettered_task_posts_callback(pool: PgPool) {
+    let (url, mut rx) = start_receiver(0).await;
+    let dispatcher = webhook_dispatcher(&pool);
+    let task = create_test_task(&pool, "hooks", "t").await;
+    set_callback_url(&pool, &task.id, &url).await;
+    tasks::fail_task(&pool, &task.id, "fatal").await.unwrap();
+    sqlx::query("UPDATE tasks SET attempt_count = max_retries WHERE id = $1")
+        .bind(&task.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let moved = valka_scheduler::dlq::dead_letter_tasks(&pool, dispatcher.event_tx())
+        .await
+        .unwrap();
+    assert_eq!(moved.len(), 1);
+    assert_eq!(moved[0].callback_url.as_deref(), Some(url.as_str()));
+    dispatcher.webhooks().notify(&moved[0].id, &url);
+
+    let event = recv_event(&mut rx).await;
+    assert_eq!(event.event_type, "task.dead_lettered");
+    assert_eq!(event.status, "DEAD_LETTER");
+}
@@ -0,0 +1,284 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::Request;
+use futures::StreamExt;
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use tower::ServiceExt;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::tasks::TaskRow;
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_proto::api_service_client::ApiServiceClient;
+use valka_proto::worker_service_client::WorkerServiceClient;
+use valka_proto::*;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+/// Serve gRPC on `addr` and return a channel to it plus the shutdown sender.
+async fn serve(
+    pool: &PgPool,
+    dispatcher: &DispatcherService,
+    matching: &MatchingService,
+    addr: SocketAddr,
+) -> (Channel, watch::Sender<bool>) {
+    let node_id = NodeId::new();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id, 4)),
+        NodeForwarder::new(),
+        dispatcher.log_tx().clone(),
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    (channel, shutdown_tx)
+}
+
+/// Open a worker session on `queue`; returns its id, outbound sender and inbound stream.
+async fn connect_worker(
+    channel: Channel,
+    queue: &str,
+) -> (
+    String,
+    mpsc::Sender<WorkerRequest>,
+    tonic::Streaming<WorkerResponse>,
+) {
+    let (tx, rx) = mpsc::channel::<WorkerRequest>(16);
+    let inbound = WorkerServiceClient::new(channel)
+        .session(ReceiverStream::new(rx))
+        .await
+        .unwrap()
+        .into_inner();
+    let worker_id = uuid::Uuid::now_v7().to_string();
+    tx.send(WorkerRequest {
+        request: Some(worker_request::Request::Hello(WorkerHello {
+            worker_id: worker_id.clone(),
+            worker_name: "drain-test-worker".to_string(),
+            queues: vec![queue.to_string()],
+            concurrency: 2,
+            metadata: String::new(),
+            version: String::new(),
+            labels: Default::default(),
+            queue_concurrency: Default::default(),
+            protocol_version: 0,
+            capabilities: vec![],
+            heartbeat_interval_ms: 0,
+        })),
+    })
+    .await
+    .unwrap();
+    // Let the server register it and start its match loop
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    (worker_id, tx, inbound)
+}
+
+fn offer(matching: &MatchingService, task: &TaskRow) {
+    let partition = PartitionId(task.partition_id);
+    if let Err(envelope) = matching.offer_task(&task.queue_name, partition, envelope_for(task)) {
+        matching.buffer_task(&task.queue_name, partition, envelope);
+    }
+}
+
+async fn next_response(
+    inbound: &mut tonic::Streaming<WorkerResponse>,
+) -> Option<worker_response::Response> {
+    tokio::time::timeout(Duration::from_secs(5), inbound.next())
+        .await
+        .expect("timed out waiting on the worker stream")
+        .map(|msg| msg.unwrap().response.unwrap())
+}
+
+async fn get_status(pool: &PgPool, task_id: &str) -> String {
+    valka_db::queries::tasks::get_task(pool, task_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_drain_waits_for_active_tasks_then_shuts_down(pool: PgPool) {
+    let queue = "drain-graceful";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (channel, shutdown_tx) = serve(
+        &pool,
+        &dispatcher,
+        &matching,
+        "127.0.0.1:19885".parse().unwrap(),
+    )
+    .await;
+    let (worker_id, tx, mut inbound) = connect_worker(channel.clone(), queue).await;
+    let mut api = ApiServiceClient::new(channel);
+
+    let running = create_test_task(&pool, queue, "t").await;
+    offer(&matching, &running);
+    let Some(worker_response::Response::TaskAssignment(assignment)) =
+        next_response(&mut inbound).await
+    else {
+        panic!("expected an assignment");
+    };
+
+    let resp = api
+        .drain_worker(DrainWorkerRequest {
+            worker_id: worker_id.clone(),
+            timeout_seconds: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resp.active_tasks, 1);
+    let workers = api
+        .list_workers(ListWorkersRequest { local_only: true })
+        .await
+        .unwrap()
+        .into_inner()
+        .workers;
+    assert_eq!(workers[0].status, "DRAINING");
+
+    // A free slot, but nothing new while draining
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let waiting = create_test_task(&pool, queue, "t").await;
+    offer(&matching, &waiting);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), inbound.next())
+            .await
+            .is_err(),
+        "draining worker was sent a message"
+    );
+
+    tx.send(WorkerRequest {
+        request: Some(worker_request::Request::TaskResult(TaskResult {
+            task_id: assignment.task_id.clone(),
+            task_run_id: assignment.task_run_id,
+            success: true,
+            ..Default::default()
+        })),
+    })
+    .await
+    .unwrap();
+
+    // Told to shut down once idle, then the stream ends
+    match next_response(&mut inbound).await {
+        Some(worker_response::Response::ServerShutdown(shutdown)) => {
+            assert_eq!(shutdown.reason, "Worker drained");
+        }
+        other => panic!("expected ServerShutdown, got {other:?}"),
+    }
+    assert!(next_response(&mut inbound).await.is_none());
+    assert_eq!(get_status(&pool, &running.id).await, "COMPLETED");
+    assert_eq!(get_status(&pool, &waiting.id).await, "PENDING");
+    assert!(dispatcher.workers().is_empty());
+
+    let missing = api
+        .drain_worker(DrainWorkerRequest {
+            worker_id,
+            timeout_seconds: 0,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(missing.code(), tonic::Code::NotFound);
+    let _ = shutdown_tx.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_drain_timeout_closes_stream_and_leaves_task_to_lease(pool: PgPool) {
+    let queue = "drain-timeout";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (channel, shutdown_tx) = serve(
+        &pool,
+        &dispatcher,
+        &matching,
+        "127.0.0.1:19886".parse().unwrap(),
+    )
+    .await;
+    let (worker_id, _tx, mut inbound) = connect_worker(channel.clone(), queue).await;
+
+    let task = create_test_task(&pool, queue, "t").await;
+    offer(&matching, &task);
+    assert!(matches!(
+        next_response(&mut inbound).await,
+        Some(worker_response::Response::TaskAssignment(_))
+    ));
+
+    let started = tokio::time::Instant::now();
+    ApiServiceClient::new(channel)
+        .drain_worker(DrainWorkerRequest {
+            worker_id,
+            timeout_seconds: 1,
+        })
+        .await
+        .unwrap();
+
+    // Closed without a ServerShutdown; the run is left for the reaper
+    assert!(next_response(&mut inbound).await.is_none());
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(get_status(&pool, &task.id).await, "RUNNING");
+    assert!(dispatcher.workers().is_empty());
+    let _ = shutdown_tx.send(true);
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_rest_workers_show_draining_status(pool: PgPool) {
+    let queue = "drain-rest";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let app = build_test_router_with(pool, matching, dispatcher.clone());
+    let (worker_id, mut rx) = start_worker(&dispatcher, queue, 1).await;
+    let (_idle_id, _idle_rx) = start_worker(&dispatcher, queue, 1).await;
+
+    assert_eq!(dispatcher.drain_worker(&worker_id.0, None), Some(0));
+    assert_eq!(dispatcher.drain_worker("no-such-worker", None), None);
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/v1/workers")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let json = parse_response_json(resp).await;
+    let statuses: Vec<(&str, &str)> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|w| (w["id"].as_str().unwrap(), w["status"].as_str().unwrap()))
+        .collect();
+    for (id, status) in statuses {
+        let expected = if id == worker_id.0 {
+            "DRAINING"
+        } else {
+            "CONNECTED"
+        };
+        assert_eq!(status, expected);
+    }
+
+    // Idle already, so it is shut down straight away
+    match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+        Ok(Some(WorkerResponse {
+            response: Some(worker_response::Response::ServerShutdown(_)),
+        })) => {}
+        other => panic!("expected ServerShutdown, got {other:?}"),
+    }
+}
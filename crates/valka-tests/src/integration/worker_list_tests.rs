@@ -0,0 +1,101 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::{broadcast, mpsc, watch};
+use tonic::transport::Channel;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{MatchingConfig, NodeId};
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_proto::ListWorkersRequest;
+use valka_proto::api_service_client::ApiServiceClient;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_list_workers_rpc(pool: PgPool) {
+    let node_id = NodeId::new();
+    let matching = MatchingService::new(MatchingConfig::default());
+    let (event_tx, _) = broadcast::channel::<valka_proto::TaskEvent>(128);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    let dispatcher = DispatcherService::new(
+        matching.clone(),
+        pool.clone(),
+        node_id.clone(),
+        event_tx,
+        log_tx.clone(),
+    );
+    let addr: SocketAddr = "127.0.0.1:19884".parse().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id.clone(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let (busy_id, mut rx) = start_worker(&dispatcher, "emails", 4).await;
+    let (idle_id, _idle_rx) = start_worker(&dispatcher, "reports", 2).await;
+    let task = create_test_task(&pool, "emails", "send").await;
+    let _ = matching.offer_task(
+        "emails",
+        valka_core::PartitionId(task.partition_id),
+        envelope_for(&task),
+    );
+    recv_assignment(&mut rx).await;
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let response = ApiServiceClient::new(channel)
+        .list_workers(ListWorkersRequest { local_only: false })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.node_id, node_id.0);
+    assert!(response.unreachable_nodes.is_empty());
+    assert_eq!(response.workers.len(), 2);
+    let busy = response
+        .workers
+        .iter()
+        .find(|w| w.worker_id == busy_id.0)
+        .unwrap();
+    assert_eq!(busy.queues, vec!["emails".to_string()]);
+    assert_eq!(busy.concurrency, 4);
+    assert_eq!(busy.active_tasks, 1);
+    assert_eq!(busy.node_id, node_id.0);
+    assert!(chrono::DateTime::parse_from_rfc3339(&busy.last_heartbeat).is_ok());
+    assert!(chrono::DateTime::parse_from_rfc3339(&busy.connected_at).is_ok());
+    let idle = response
+        .workers
+        .iter()
+        .find(|w| w.worker_id == idle_id.0)
+        .unwrap();
+    assert_eq!(idle.active_tasks, 0);
+
+    // What a peer fetches when fanning out
+    let remote = NodeForwarder::new()
+        .list_workers(&addr.to_string())
+        .await
+        .unwrap();
+    assert_eq!(remote.len(), 2);
+    assert!(remote.iter().all(|w| w.node_id == node_id.0));
+
+    let _ = shutdown_tx.send(true);
+}
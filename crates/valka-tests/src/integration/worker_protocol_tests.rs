@@ -0,0 +1,194 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::signals;
+use valka_db::queries::tasks::TaskRow;
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_proto::worker_service_client::WorkerServiceClient;
+use valka_proto::*;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+async fn serve(
+    pool: &PgPool,
+    dispatcher: &DispatcherService,
+    matching: &MatchingService,
+    addr: SocketAddr,
+) -> (Channel, watch::Sender<bool>) {
+    let node_id = NodeId::new();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        node_id.clone(),
+        Arc::new(ClusterManager::new_single_node(node_id, 4)),
+        NodeForwarder::new(),
+        dispatcher.log_tx().clone(),
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    (channel, shutdown_tx)
+}
+
+/// Open a worker session on `queue` with the given protocol fields.
+async fn open_session(
+    channel: Channel,
+    queue: &str,
+    protocol_version: i32,
+    capabilities: &[&str],
+    heartbeat_interval_ms: i64,
+) -> (
+    mpsc::Sender<WorkerRequest>,
+    tonic::Streaming<WorkerResponse>,
+) {
+    let (tx, rx) = mpsc::channel::<WorkerRequest>(16);
+    let inbound = WorkerServiceClient::new(channel)
+        .session(ReceiverStream::new(rx))
+        .await
+        .unwrap()
+        .into_inner();
+    tx.send(WorkerRequest {
+        request: Some(worker_request::Request::Hello(WorkerHello {
+            worker_id: uuid::Uuid::now_v7().to_string(),
+            worker_name: "protocol-test-worker".to_string(),
+            queues: vec![queue.to_string()],
+            concurrency: 1,
+            protocol_version,
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+            heartbeat_interval_ms,
+            ..Default::default()
+        })),
+    })
+    .await
+    .unwrap();
+    (tx, inbound)
+}
+
+fn offer(matching: &MatchingService, task: &TaskRow) {
+    let partition = PartitionId(task.partition_id);
+    if let Err(envelope) = matching.offer_task(&task.queue_name, partition, envelope_for(task)) {
+        matching.buffer_task(&task.queue_name, partition, envelope);
+    }
+}
+
+async fn next_response(
+    inbound: &mut tonic::Streaming<WorkerResponse>,
+) -> worker_response::Response {
+    tokio::time::timeout(Duration::from_secs(5), inbound.next())
+        .await
+        .expect("timed out waiting on the worker stream")
+        .expect("stream closed")
+        .unwrap()
+        .response
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_hello_ack_opens_versioned_sessions_only(pool: PgPool) {
+    let queue = "protocol-ack";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (channel, _shutdown_tx) = serve(
+        &pool,
+        &dispatcher,
+        &matching,
+        "127.0.0.1:19914".parse().unwrap(),
+    )
+    .await;
+
+    // Asking for a heartbeat faster than allowed gets the floor
+    let (_tx, mut inbound) =
+        open_session(channel.clone(), queue, PROTOCOL_VERSION, &["signals"], 100).await;
+    let worker_response::Response::HelloAck(ack) = next_response(&mut inbound).await else {
+        panic!("expected HelloAck first");
+    };
+    assert_eq!(ack.protocol_version, PROTOCOL_VERSION);
+    assert!(!ack.session_id.is_empty());
+    assert_eq!(ack.heartbeat_interval_ms, 1_000);
+
+    // A worker that predates versioning is sent nothing it can't parse
+    let legacy_queue = "protocol-legacy";
+    let (_legacy_tx, mut legacy) = open_session(channel, legacy_queue, 0, &[], 0).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let task = create_test_task(&pool, legacy_queue, "t").await;
+    offer(&matching, &task);
+    match next_response(&mut legacy).await {
+        worker_response::Response::TaskAssignment(assignment) => {
+            assert_eq!(assignment.task_id, task.id);
+        }
+        other => panic!("expected the assignment first, got {other:?}"),
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_signals_held_for_worker_without_capability(pool: PgPool) {
+    let queue = "protocol-nosignals";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let (channel, _shutdown_tx) = serve(
+        &pool,
+        &dispatcher,
+        &matching,
+        "127.0.0.1:19915".parse().unwrap(),
+    )
+    .await;
+
+    let (_tx, mut inbound) = open_session(channel, queue, PROTOCOL_VERSION, &["progress"], 0).await;
+    let worker_response::Response::HelloAck(ack) = next_response(&mut inbound).await else {
+        panic!("expected HelloAck first");
+    };
+    assert_eq!(ack.heartbeat_interval_ms, 10_000);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let task = create_test_task(&pool, queue, "t").await;
+    signals::create_signal(&pool, "sig-held", &task.id, "approve", None)
+        .await
+        .unwrap();
+    offer(&matching, &task);
+    let worker_response::Response::TaskAssignment(_) = next_response(&mut inbound).await else {
+        panic!("expected the assignment");
+    };
+
+    // The pending signal stays pending instead of being pushed
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), inbound.next())
+            .await
+            .is_err(),
+        "worker without the signals capability was sent a message"
+    );
+    let held = signals::get_pending_signals(&pool, &task.id).await.unwrap();
+    assert_eq!(held.len(), 1);
+    assert!(
+        !dispatcher
+            .send_signal_to_worker(
+                &task.id,
+                TaskSignal {
+                    signal_id: "sig-live".to_string(),
+                    task_id: task.id.clone(),
+                    signal_name: "approve".to_string(),
+                    payload: String::new(),
+                    timestamp_ms: 0,
+                },
+            )
+            .await
+    );
+}
@@ -0,0 +1,165 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::sync::{mpsc, watch};
+use valka_cluster::{ClusterManager, NodeForwarder};
+use valka_core::{NodeId, PartitionId};
+use valka_db::queries::{task_runs, tasks};
+use valka_dispatcher::DispatcherService;
+use valka_matching::MatchingService;
+use valka_sdk::ValkaWorker;
+use valka_sdk::worker::ShutdownHandle;
+use valka_server::admin::AdminAuth;
+use valka_server::auth::ApiAuth;
+
+use super::helpers::*;
+
+async fn start_server(
+    pool: &PgPool,
+    dispatcher: &DispatcherService,
+    matching: &MatchingService,
+    addr: SocketAddr,
+) -> watch::Sender<bool> {
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (log_tx, _) = mpsc::channel::<valka_proto::LogEntry>(128);
+    tokio::spawn(valka_server::grpc::serve_grpc(
+        addr,
+        pool.clone(),
+        dispatcher.clone(),
+        matching.clone(),
+        dispatcher.event_tx().clone(),
+        NodeId::new(),
+        Arc::new(ClusterManager::new_single_node(NodeId::new(), 4)),
+        NodeForwarder::new(),
+        log_tx,
+        AdminAuth::default(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown_tx
+}
+
+/// Start a worker whose handler takes `work` per task, hand it one task and
+/// wait until its handler is running. Returns the task id, the worker's
+/// shutdown handle and its run loop.
+async fn run_one_task(
+    pool: &PgPool,
+    matching: &MatchingService,
+    addr: SocketAddr,
+    queue: &str,
+    work: Duration,
+    drain_timeout: Duration,
+) -> (
+    String,
+    ShutdownHandle,
+    tokio::task::JoinHandle<Result<(), valka_sdk::SdkError>>,
+) {
+    let (started_tx, mut started_rx) = mpsc::channel::<()>(1);
+    let worker = ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&[queue])
+        .drain_timeout(drain_timeout)
+        .handler(move |_ctx| {
+            let started_tx = started_tx.clone();
+            async move {
+                let _ = started_tx.send(()).await;
+                tokio::time::sleep(work).await;
+                Ok(serde_json::json!({ "ok": true }))
+            }
+        })
+        .build()
+        .await
+        .unwrap();
+    let handle = worker.shutdown_handle();
+    let run = tokio::spawn(worker.run());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let task = create_test_task(pool, queue, "t").await;
+    let _ = matching.offer_task(queue, PartitionId(task.partition_id), envelope_for(&task));
+    // Wait for the handler rather than the row, which is RUNNING before the
+    // worker has the task
+    tokio::time::timeout(Duration::from_secs(5), started_rx.recv())
+        .await
+        .expect("task never started");
+    (task.id, handle, run)
+}
+
+async fn wait_for_no_workers(dispatcher: &DispatcherService) {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while !dispatcher.workers().is_empty() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "worker never deregistered"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_worker_shutdown_finishes_in_flight_task(pool: PgPool) {
+    let queue = "worker-shutdown-finish";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19896".parse().unwrap();
+    let _server = start_server(&pool, &dispatcher, &matching, addr).await;
+    let (task_id, handle, run) = run_one_task(
+        &pool,
+        &matching,
+        addr,
+        queue,
+        Duration::from_millis(500),
+        Duration::from_secs(10),
+    )
+    .await;
+
+    // The result lands even though the worker announced its shutdown first
+    handle.shutdown();
+    tokio::time::timeout(Duration::from_secs(5), run)
+        .await
+        .expect("worker did not stop")
+        .unwrap()
+        .unwrap();
+    let task = tasks::get_task(&pool, &task_id).await.unwrap().unwrap();
+    assert_eq!(task.status, "COMPLETED");
+    assert_eq!(task.output, Some(serde_json::json!({ "ok": true })));
+    wait_for_no_workers(&dispatcher).await;
+}
+
+#[sqlx::test(migrations = "../../crates/valka-db/migrations")]
+async fn test_worker_shutdown_hands_back_tasks_after_drain_timeout(pool: PgPool) {
+    let queue = "worker-shutdown-handback";
+    let (dispatcher, matching) = make_dispatcher(pool.clone());
+    let addr: SocketAddr = "127.0.0.1:19897".parse().unwrap();
+    let _server = start_server(&pool, &dispatcher, &matching, addr).await;
+    let (task_id, handle, run) = run_one_task(
+        &pool,
+        &matching,
+        addr,
+        queue,
+        Duration::from_secs(60),
+        Duration::from_millis(200),
+    )
+    .await;
+
+    let started = tokio::time::Instant::now();
+    handle.shutdown();
+    tokio::time::timeout(Duration::from_secs(3), run)
+        .await
+        .expect("worker did not stop after its drain timeout")
+        .unwrap()
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    // Requeued now rather than after the lease runs out
+    let task = tasks::get_task(&pool, &task_id).await.unwrap().unwrap();
+    assert_eq!(task.status, "RETRY");
+    let runs = task_runs::get_runs_for_task(&pool, &task_id).await.unwrap();
+    assert_eq!(runs[0].status, "FAILED");
+    assert_eq!(
+        runs[0].error_message.as_deref(),
+        Some("Worker shut down before the task finished")
+    );
+    wait_for_no_workers(&dispatcher).await;
+}
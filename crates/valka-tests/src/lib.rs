@@ -6,6 +6,8 @@ mod cluster_tests;
 #[cfg(test)]
 mod config_tests;
 #[cfg(test)]
+mod cron_tests;
+#[cfg(test)]
 mod dispatcher_tests;
 #[cfg(test)]
 mod error_tests;
@@ -14,10 +16,26 @@ mod heartbeat_tests;
 #[cfg(test)]
 mod lifecycle_tests;
 #[cfg(test)]
+mod limits_tests;
+#[cfg(test)]
+mod logging_tests;
+#[cfg(test)]
 mod matching_tests;
 #[cfg(test)]
+mod metrics_tests;
+#[cfg(test)]
+mod partitioning_tests;
+#[cfg(test)]
 mod proto_tests;
 #[cfg(test)]
+mod reload_tests;
+#[cfg(test)]
 mod retry_tests;
 #[cfg(test)]
 mod sdk_tests;
+#[cfg(all(test, feature = "sqlite"))]
+mod sqlite_tests;
+#[cfg(test)]
+mod telemetry_tests;
+#[cfg(test)]
+mod templating_tests;
@@ -51,6 +51,7 @@ fn test_task_status_roundtrip() {
         TaskStatus::Retry,
         TaskStatus::DeadLetter,
         TaskStatus::Cancelled,
+        TaskStatus::Waiting,
     ];
 
     for status in &statuses {
@@ -170,3 +171,28 @@ fn test_id_display_and_as_ref() {
     assert_eq!(display, as_ref);
     assert_eq!(display, task_id.0);
 }
+
+#[test]
+fn test_resolve_deadline_ms() {
+    use valka_core::resolve_deadline_ms;
+
+    let now = 1_700_000_000_000;
+    assert_eq!(resolve_deadline_ms(None, None, now), Ok(None));
+    assert_eq!(
+        resolve_deadline_ms(Some(now + 5), None, now),
+        Ok(Some(now + 5))
+    );
+    assert_eq!(
+        resolve_deadline_ms(None, Some(250), now),
+        Ok(Some(now + 250))
+    );
+    // A deadline in the past is accepted; the task just expires immediately
+    assert_eq!(
+        resolve_deadline_ms(Some(now - 1), None, now),
+        Ok(Some(now - 1))
+    );
+
+    assert!(resolve_deadline_ms(Some(now), Some(1), now).is_err());
+    assert!(resolve_deadline_ms(Some(-1), None, now).is_err());
+    assert!(resolve_deadline_ms(None, Some(-1), now).is_err());
+}
@@ -0,0 +1,149 @@
+use serde_json::json;
+use valka_core::LimitsConfig;
+use valka_core::limits::{NewTask, truncate_utf8};
+
+fn valid_task() -> NewTask<'static> {
+    NewTask {
+        queue_name: "billing.invoices",
+        task_name: "send-reminder_v2:eu",
+        input: None,
+        metadata: None,
+        priority: 0,
+        max_retries: 0,
+        timeout_seconds: 0,
+    }
+}
+
+#[test]
+fn test_valid_task_passes() {
+    let limits = LimitsConfig::default();
+    assert_eq!(limits.check_task(&valid_task()), Ok(()));
+
+    let input = json!({"amount": 100});
+    let at_bounds = NewTask {
+        input: Some(&input),
+        priority: limits.max_priority,
+        max_retries: limits.max_retries,
+        timeout_seconds: limits.max_timeout_seconds,
+        ..valid_task()
+    };
+    assert_eq!(limits.check_task(&at_bounds), Ok(()));
+}
+
+#[test]
+fn test_names_are_checked() {
+    let limits = LimitsConfig::default();
+    let long = "q".repeat(256);
+    for (task, message) in [
+        (
+            NewTask {
+                queue_name: "",
+                ..valid_task()
+            },
+            "queue_name is required",
+        ),
+        (
+            NewTask {
+                queue_name: &long,
+                ..valid_task()
+            },
+            "queue_name is longer than 255 characters",
+        ),
+        (
+            NewTask {
+                task_name: "send email",
+                ..valid_task()
+            },
+            "task_name may only contain ASCII letters, digits, '.', '_', '-' and ':'",
+        ),
+        (
+            NewTask {
+                task_name: "ünïcödé",
+                ..valid_task()
+            },
+            "task_name may only contain ASCII letters, digits, '.', '_', '-' and ':'",
+        ),
+    ] {
+        assert_eq!(limits.check_task(&task), Err(message.to_string()));
+    }
+}
+
+#[test]
+fn test_payload_size_is_capped() {
+    let limits = LimitsConfig {
+        max_payload_bytes: 18,
+        ..LimitsConfig::default()
+    };
+    // Measured as compact JSON: {"blob":"0123456"} is 18 bytes
+    let input = json!({"blob": "0123456"});
+    let task = NewTask {
+        input: Some(&input),
+        ..valid_task()
+    };
+    assert_eq!(limits.check_task(&task), Ok(()));
+
+    let metadata = json!({"blob": "01234567"});
+    let task = NewTask {
+        metadata: Some(&metadata),
+        ..valid_task()
+    };
+    assert_eq!(
+        limits.check_task(&task),
+        Err("metadata is 19 bytes, over the 18 byte limit".to_string())
+    );
+}
+
+#[test]
+fn test_numbers_are_bounded() {
+    let limits = LimitsConfig::default();
+    let task = NewTask {
+        priority: i32::MAX,
+        max_retries: -1,
+        timeout_seconds: -5,
+        ..valid_task()
+    };
+    assert_eq!(
+        limits.check_task(&task),
+        Err("priority must be between -1000 and 1000, got 2147483647; \
+             max_retries must be between 0 and 100, got -1; \
+             timeout_seconds must be between 0 and 604800, got -5"
+            .to_string())
+    );
+}
+
+#[test]
+fn test_every_offending_field_is_reported() {
+    let limits = LimitsConfig::default();
+    let task = NewTask {
+        queue_name: "",
+        task_name: "",
+        priority: -1001,
+        ..valid_task()
+    };
+    let err = limits.check_task(&task).unwrap_err();
+    let messages: Vec<&str> = err.split("; ").collect();
+    assert_eq!(
+        messages,
+        [
+            "queue_name is required",
+            "task_name is required",
+            "priority must be between -1000 and 1000, got -1001"
+        ]
+    );
+}
+
+#[test]
+fn test_truncate_utf8_cuts_on_char_boundary() {
+    let mut short = "hello".to_string();
+    assert!(!truncate_utf8(&mut short, 5));
+    assert_eq!(short, "hello");
+
+    // 'é' is two bytes; cutting inside it drops the whole character
+    let mut accented = "héllo".to_string();
+    assert!(truncate_utf8(&mut accented, 2));
+    assert_eq!(accented, "h");
+
+    let mut long = "héllo".to_string();
+    assert!(truncate_utf8(&mut long, 3));
+    assert_eq!(long, "hé");
+}
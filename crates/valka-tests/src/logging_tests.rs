@@ -0,0 +1,94 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use valka_core::LogFormat;
+use valka_server::otel::{fmt_layer, log_file_appender};
+
+/// Collects everything a fmt layer writes.
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Buffer {
+    type Writer = Buffer;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+fn emit() {
+    let outer = tracing::info_span!("dispatch", queue = "emails", attempt = 2);
+    let _outer = outer.enter();
+    let inner = tracing::info_span!("send", task_id = "t-1");
+    let _inner = inner.enter();
+    tracing::warn!(worker_id = "w-9", retry = true, "Worker slow to ack");
+}
+
+fn capture(format: LogFormat) -> String {
+    let buffer = Buffer::default();
+    let subscriber = tracing_subscriber::registry().with(fmt_layer(format, buffer.clone(), false));
+    tracing::subscriber::with_default(subscriber, emit);
+    let bytes = buffer.0.lock().unwrap().clone();
+    String::from_utf8(bytes).unwrap()
+}
+
+#[test]
+fn test_json_log_format_flattens_span_fields() {
+    let out = capture(LogFormat::Json);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 1, "{out}");
+    let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(line["level"], "WARN");
+    assert_eq!(line["message"], "Worker slow to ack");
+    assert_eq!(line["worker_id"], "w-9");
+    assert_eq!(line["retry"], true);
+    // Fields of both enclosing spans sit at the top level
+    assert_eq!(line["queue"], "emails");
+    assert_eq!(line["attempt"], 2);
+    assert_eq!(line["task_id"], "t-1");
+    assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+}
+
+#[test]
+fn test_pretty_log_format_is_plain_text() {
+    let out = capture(LogFormat::Pretty);
+    assert!(!out.starts_with('{'), "{out}");
+    assert!(out.contains(" WARN "), "{out}");
+    assert!(
+        out.contains("dispatch{queue=\"emails\" attempt=2}:send{task_id=\"t-1\"}"),
+        "{out}"
+    );
+    assert!(
+        out.contains("Worker slow to ack worker_id=\"w-9\" retry=true"),
+        "{out}"
+    );
+}
+
+#[test]
+fn test_log_file_rotates_daily() {
+    let dir = std::env::temp_dir().join(format!("valka-logs-{}", std::process::id()));
+    let path = dir.join("valka.log");
+    let appender = log_file_appender(path.to_str().unwrap()).unwrap();
+    let subscriber =
+        tracing_subscriber::registry().with(fmt_layer(LogFormat::Json, appender, false));
+    tracing::subscriber::with_default(subscriber, emit);
+
+    // The directory is created and today's file carries the date suffix
+    let today = chrono::Utc::now().format("%Y-%m-%d");
+    let contents = std::fs::read_to_string(dir.join(format!("valka.log.{today}"))).unwrap();
+    assert!(contents.contains("\"task_id\":\"t-1\""), "{contents}");
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,6 +1,6 @@
 use valka_core::{MatchingConfig, PartitionId, WorkerId};
 use valka_matching::MatchingService;
-use valka_matching::partition::TaskEnvelope;
+use valka_matching::partition::{BufferOutcome, TaskEnvelope};
 
 fn make_envelope(task_id: &str, queue: &str) -> TaskEnvelope {
     TaskEnvelope {
@@ -13,6 +13,14 @@ fn make_envelope(task_id: &str, queue: &str) -> TaskEnvelope {
         timeout_seconds: 300,
         metadata: "{}".to_string(),
         priority: 0,
+        deadline_ms: None,
+        ready_at_ms: None,
+        created_at_ms: None,
+        scheduled_at_ms: None,
+        traceparent: None,
+        fifo_key: None,
+        required_labels: Default::default(),
+        ephemeral: None,
     }
 }
 
@@ -61,7 +69,7 @@ async fn test_buffer_task_and_match_on_worker_register() {
 
     let envelope = make_envelope("task-1", queue);
     let buffered = service.buffer_task(queue, PartitionId(0), envelope);
-    assert!(buffered, "Task should be buffered");
+    assert!(buffered.is_buffered(), "Task should be buffered");
 
     let worker_id = WorkerId::new();
     let rx = service.register_worker(queue, PartitionId(0), worker_id);
@@ -136,6 +144,44 @@ async fn test_partition_tree_forwarding() {
     assert_eq!(received.task_id, "task-1");
 }
 
+#[tokio::test]
+async fn test_tree_forwarding_stays_within_queue() {
+    let mut config = MatchingConfig::default();
+    config.num_partitions = 4;
+    config.branching_factor = 2;
+    let service = MatchingService::new(config);
+    service.ensure_queue("queue-a");
+    service.ensure_queue("queue-b");
+
+    // Work for another queue, buffered where the worker registers
+    service.buffer_task(
+        "queue-b",
+        PartitionId(0),
+        make_envelope("b-buffered", "queue-b"),
+    );
+    let mut rx = service.register_worker("queue-a", PartitionId(0), WorkerId::new());
+    assert!(rx.try_recv().is_err());
+
+    // Nor does forwarding up from a leaf cross queues
+    let result = service.offer_task(
+        "queue-b",
+        PartitionId(3),
+        make_envelope("b-leaf", "queue-b"),
+    );
+    assert_eq!(result.unwrap_err().task_id, "b-leaf");
+    assert!(rx.try_recv().is_err());
+
+    let result = service.offer_task(
+        "queue-a",
+        PartitionId(3),
+        make_envelope("a-leaf", "queue-a"),
+    );
+    assert!(result.is_ok());
+    let received = rx.await.unwrap();
+    assert_eq!(received.queue_name, "queue-a");
+    assert_eq!(received.task_id, "a-leaf");
+}
+
 #[tokio::test]
 async fn test_buffer_overflow() {
     let mut config = MatchingConfig::default();
@@ -145,14 +191,59 @@ async fn test_buffer_overflow() {
     let queue = "test.queue";
     service.ensure_queue(queue);
 
-    assert!(service.buffer_task(queue, PartitionId(0), make_envelope("t1", queue)));
-    assert!(service.buffer_task(queue, PartitionId(0), make_envelope("t2", queue)));
     assert!(
-        !service.buffer_task(queue, PartitionId(0), make_envelope("t3", queue)),
+        service
+            .buffer_task(queue, PartitionId(0), make_envelope("t1", queue))
+            .is_buffered()
+    );
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), make_envelope("t2", queue))
+            .is_buffered()
+    );
+    assert!(
+        !service
+            .buffer_task(queue, PartitionId(0), make_envelope("t3", queue))
+            .is_buffered(),
         "Buffer should be full"
     );
 }
 
+#[tokio::test]
+async fn test_resize_partition_buffers() {
+    let mut config = MatchingConfig::default();
+    config.max_buffer_per_partition = 2;
+    let service = MatchingService::new(config);
+
+    let queue = "test.queue";
+    for i in 0..2 {
+        let envelope = make_envelope(&format!("t{i}"), queue);
+        assert!(
+            service
+                .buffer_task(queue, PartitionId(0), envelope)
+                .is_buffered()
+        );
+    }
+
+    // Grown: existing partitions take more, new queues start at the new size
+    service.set_max_buffer_per_partition(3);
+    assert_eq!(service.free_buffer_capacity(queue, PartitionId(0)), 1);
+    assert_eq!(
+        service.free_buffer_capacity("other.queue", PartitionId(0)),
+        3
+    );
+
+    // Shrunk below what is buffered: nothing is dropped, nothing more fits
+    service.set_max_buffer_per_partition(1);
+    assert_eq!(service.stats().buffered_tasks, 2);
+    assert_eq!(service.free_buffer_capacity(queue, PartitionId(0)), 0);
+    assert!(
+        !service
+            .buffer_task(queue, PartitionId(0), make_envelope("t9", queue))
+            .is_buffered()
+    );
+}
+
 #[tokio::test]
 async fn test_offer_task_nonexistent_queue() {
     let config = MatchingConfig::default();
@@ -226,9 +317,21 @@ async fn test_buffer_then_multiple_workers() {
     service.ensure_queue(queue);
 
     // Buffer 3 tasks
-    assert!(service.buffer_task(queue, PartitionId(0), make_envelope("t1", queue)));
-    assert!(service.buffer_task(queue, PartitionId(0), make_envelope("t2", queue)));
-    assert!(service.buffer_task(queue, PartitionId(0), make_envelope("t3", queue)));
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), make_envelope("t1", queue))
+            .is_buffered()
+    );
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), make_envelope("t2", queue))
+            .is_buffered()
+    );
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), make_envelope("t3", queue))
+            .is_buffered()
+    );
 
     // Register 3 workers — each should get one buffered task
     let rx1 = service.register_worker(queue, PartitionId(0), WorkerId::new());
@@ -244,6 +347,315 @@ async fn test_buffer_then_multiple_workers() {
     assert_eq!(ids, vec!["t1", "t2", "t3"]);
 }
 
+fn prioritized(task_id: &str, queue: &str, priority: i32) -> TaskEnvelope {
+    TaskEnvelope {
+        priority,
+        ..make_envelope(task_id, queue)
+    }
+}
+
+#[tokio::test]
+async fn test_buffer_then_multiple_workers_by_priority() {
+    let config = MatchingConfig::default();
+    let service = MatchingService::new(config);
+
+    let queue = "test.queue";
+    service.ensure_queue(queue);
+
+    // Buffer mixed priorities; equal ones keep their arrival order
+    for (id, priority) in [("low", 0), ("mid-1", 5), ("high", 10), ("mid-2", 5)] {
+        assert!(
+            service
+                .buffer_task(queue, PartitionId(0), prioritized(id, queue, priority))
+                .is_buffered()
+        );
+    }
+
+    // Each registering worker gets the most urgent task left
+    let mut ids = Vec::new();
+    for _ in 0..4 {
+        let rx = service.register_worker(queue, PartitionId(0), WorkerId::new());
+        ids.push(rx.await.unwrap().task_id);
+    }
+    assert_eq!(ids, vec!["high", "mid-1", "mid-2", "low"]);
+}
+
+#[tokio::test]
+async fn test_match_buffered_by_priority() {
+    let service = MatchingService::new(MatchingConfig::default());
+    let queue = "paused.queue";
+    service.paused().set_paused(queue, true);
+
+    for (id, priority) in [("t1", 1), ("t2", 3), ("t3", 2)] {
+        let envelope = prioritized(id, queue, priority);
+        assert!(
+            service
+                .buffer_task(queue, PartitionId(0), envelope)
+                .is_buffered()
+        );
+    }
+    let rx1 = service.register_worker(queue, PartitionId(0), WorkerId::new());
+    let rx2 = service.register_worker(queue, PartitionId(0), WorkerId::new());
+
+    service.paused().set_paused(queue, false);
+    assert_eq!(service.match_buffered(queue, PartitionId(0)), 2);
+    assert_eq!(rx1.await.unwrap().task_id, "t2");
+    assert_eq!(rx2.await.unwrap().task_id, "t3");
+    assert_eq!(service.stats().buffered_tasks, 1);
+}
+
+#[tokio::test]
+async fn test_full_buffer_evicts_lowest_priority() {
+    let mut config = MatchingConfig::default();
+    config.max_buffer_per_partition = 3;
+    let service = MatchingService::new(config);
+
+    let queue = "test.queue";
+    service.ensure_queue(queue);
+
+    for (id, priority) in [("low-1", 0), ("low-2", 0), ("mid", 5)] {
+        assert!(matches!(
+            service.buffer_task(queue, PartitionId(0), prioritized(id, queue, priority)),
+            BufferOutcome::Buffered
+        ));
+    }
+
+    // Not more urgent than the lowest buffered task: rejected
+    match service.buffer_task(queue, PartitionId(0), prioritized("low-3", queue, 0)) {
+        BufferOutcome::Rejected(task) => assert_eq!(task.task_id, "low-3"),
+        other => panic!("expected rejection, got {other:?}"),
+    }
+
+    // More urgent: the newest of the lowest priority makes room
+    match service.buffer_task(queue, PartitionId(0), prioritized("high", queue, 10)) {
+        BufferOutcome::Evicted(task) => assert_eq!(task.task_id, "low-2"),
+        other => panic!("expected eviction, got {other:?}"),
+    }
+    assert_eq!(service.stats().buffered_tasks, 3);
+
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let rx = service.register_worker(queue, PartitionId(0), WorkerId::new());
+        ids.push(rx.await.unwrap().task_id);
+    }
+    assert_eq!(ids, vec!["high", "mid", "low-1"]);
+}
+
+fn requiring(task_id: &str, queue: &str, key: &str, value: &str) -> TaskEnvelope {
+    TaskEnvelope {
+        required_labels: [(key.to_string(), value.to_string())].into(),
+        ..make_envelope(task_id, queue)
+    }
+}
+
+fn labelled_worker(service: &MatchingService, key: &str, value: &str) -> WorkerId {
+    let worker_id = WorkerId::new();
+    service.set_worker_labels(&worker_id, [(key.to_string(), value.to_string())].into());
+    worker_id
+}
+
+#[tokio::test]
+async fn test_offer_skips_workers_without_required_labels() {
+    let service = MatchingService::new(MatchingConfig::default());
+    let queue = "labels.queue";
+
+    let mut plain_rx = service.register_worker(queue, PartitionId(0), WorkerId::new());
+    let gpu = labelled_worker(&service, "gpu", "true");
+    let gpu_rx = service.register_worker(queue, PartitionId(0), gpu);
+
+    // The plain worker is first in line but lacks the label
+    assert!(
+        service
+            .offer_task(
+                queue,
+                PartitionId(0),
+                requiring("gpu-task", queue, "gpu", "true")
+            )
+            .is_ok()
+    );
+    assert_eq!(gpu_rx.await.unwrap().task_id, "gpu-task");
+    assert!(plain_rx.try_recv().is_err());
+
+    // Nobody left with the label; the plain worker kept its place
+    let task = service
+        .offer_task(
+            queue,
+            PartitionId(0),
+            requiring("gpu-2", queue, "gpu", "true"),
+        )
+        .unwrap_err();
+    assert_eq!(task.task_id, "gpu-2");
+    assert!(
+        service
+            .offer_task(queue, PartitionId(0), make_envelope("plain", queue))
+            .is_ok()
+    );
+    assert_eq!(plain_rx.await.unwrap().task_id, "plain");
+}
+
+#[tokio::test]
+async fn test_buffered_tasks_wait_for_a_labelled_worker() {
+    let service = MatchingService::new(MatchingConfig::default());
+    let queue = "labels.queue";
+    service.ensure_queue(queue);
+
+    let gpu_task = TaskEnvelope {
+        priority: 10,
+        ..requiring("gpu-task", queue, "gpu", "true")
+    };
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), gpu_task)
+            .is_buffered()
+    );
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), make_envelope("plain", queue))
+            .is_buffered()
+    );
+
+    // A worker without the label gets the less urgent task it can run
+    let rx = service.register_worker(queue, PartitionId(0), WorkerId::new());
+    assert_eq!(rx.await.unwrap().task_id, "plain");
+    let mut no_gpu_rx = service.register_worker(
+        queue,
+        PartitionId(0),
+        labelled_worker(&service, "gpu", "false"),
+    );
+    assert!(no_gpu_rx.try_recv().is_err());
+
+    let gpu = labelled_worker(&service, "gpu", "true");
+    let rx = service.register_worker(queue, PartitionId(0), gpu);
+    assert_eq!(rx.await.unwrap().task_id, "gpu-task");
+}
+
+#[tokio::test]
+async fn test_match_buffered_passes_over_unrunnable_tasks() {
+    let service = MatchingService::new(MatchingConfig::default());
+    let queue = "labels.queue";
+    service.paused().set_paused(queue, true);
+
+    let mut rx = service.register_worker(queue, PartitionId(0), WorkerId::new());
+    for envelope in [
+        prioritized("plain", queue, 0),
+        TaskEnvelope {
+            priority: 5,
+            ..requiring("gpu-task", queue, "gpu", "true")
+        },
+    ] {
+        assert!(
+            service
+                .buffer_task(queue, PartitionId(0), envelope)
+                .is_buffered()
+        );
+    }
+
+    // The more urgent task needs a label the only waiting worker lacks
+    service.paused().set_paused(queue, false);
+    assert_eq!(service.match_buffered(queue, PartitionId(0)), 1);
+    assert_eq!(rx.try_recv().unwrap().task_id, "plain");
+    assert_eq!(service.stats().buffered_tasks, 1);
+}
+
+#[tokio::test]
+async fn test_expire_and_drain_buffered() {
+    let mut config = MatchingConfig::default();
+    config.buffered_task_ttl_ms = 100;
+    let service = MatchingService::new(config);
+
+    let queue = "test.queue";
+    let buffer = |id: &str, pid: i32| {
+        assert!(
+            service
+                .buffer_task(queue, PartitionId(pid), make_envelope(id, queue))
+                .is_buffered()
+        );
+    };
+    buffer("old-1", 0);
+    buffer("old-2", 1);
+    assert!(service.expire_buffered().is_empty());
+
+    tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+    buffer("new", 0);
+    let mut expired: Vec<_> = service
+        .expire_buffered()
+        .into_iter()
+        .map(|t| t.task_id)
+        .collect();
+    expired.sort();
+    assert_eq!(expired, vec!["old-1", "old-2"]);
+    assert_eq!(service.stats().buffered_tasks, 1);
+
+    // Shutdown takes everything, expired or not
+    let drained = service.drain_buffered();
+    assert_eq!(drained.len(), 1);
+    assert_eq!(drained[0].task_id, "new");
+    assert_eq!(service.stats().buffered_tasks, 0);
+}
+
+#[tokio::test]
+async fn test_zero_ttl_never_expires_buffered() {
+    let mut config = MatchingConfig::default();
+    config.buffered_task_ttl_ms = 0;
+    let service = MatchingService::new(config);
+
+    let queue = "test.queue";
+    let envelope = make_envelope("t1", queue);
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), envelope)
+            .is_buffered()
+    );
+    assert!(service.expire_buffered().is_empty());
+    assert_eq!(service.stats().buffered_tasks, 1);
+}
+
+#[tokio::test]
+async fn test_stats_per_partition() {
+    let mut config = MatchingConfig::default();
+    config.num_partitions = 2;
+    let service = MatchingService::new(config);
+
+    let queue = "test.queue";
+    for id in ["t1", "t2"] {
+        let envelope = make_envelope(id, queue);
+        assert!(
+            service
+                .buffer_task(queue, PartitionId(0), envelope)
+                .is_buffered()
+        );
+    }
+    let _rx1 = service.register_worker(queue, PartitionId(1), WorkerId::new());
+    let _rx2 = service.register_worker(queue, PartitionId(1), WorkerId::new());
+    service.ensure_queue("other.queue");
+
+    let stats = service.stats();
+    assert_eq!(stats.partitions, 4);
+    assert_eq!(stats.buffered_tasks, 2);
+    assert_eq!(stats.waiting_workers, 2);
+    let by_partition: Vec<_> = stats
+        .by_partition
+        .iter()
+        .map(|p| {
+            (
+                p.queue_name.as_str(),
+                p.partition_id,
+                p.buffered_tasks,
+                p.waiting_workers,
+            )
+        })
+        .collect();
+    assert_eq!(
+        by_partition,
+        vec![
+            ("other.queue", 0, 0, 0),
+            ("other.queue", 1, 0, 0),
+            (queue, 0, 2, 0),
+            (queue, 1, 0, 2),
+        ]
+    );
+}
+
 #[tokio::test]
 async fn test_tree_forwarding_deep_4_levels() {
     let mut config = MatchingConfig::default();
@@ -360,6 +772,131 @@ async fn test_ensure_queue_idempotent() {
     assert_eq!(received.task_id, "t1");
 }
 
+#[tokio::test]
+async fn test_fifo_key_in_flight_blocks_offer_and_buffer() {
+    let service = MatchingService::new(MatchingConfig::default());
+    let queue = "fifo.queue";
+    service.fifo().set_fifo(queue, true);
+    let keyed = |id: &str, key: &str| TaskEnvelope {
+        fifo_key: Some(key.to_string()),
+        ..make_envelope(id, queue)
+    };
+
+    let rx = service.register_worker(queue, PartitionId(0), WorkerId::new());
+    assert!(
+        service
+            .offer_task(queue, PartitionId(0), keyed("t1", "k"))
+            .is_ok()
+    );
+    assert_eq!(rx.await.unwrap().task_id, "t1");
+
+    // t1 holds "k" until released; other keys are unaffected
+    let _rx = service.register_worker(queue, PartitionId(0), WorkerId::new());
+    assert!(
+        service
+            .offer_task(queue, PartitionId(0), keyed("t2", "k"))
+            .is_err()
+    );
+    assert!(
+        !service
+            .buffer_task(queue, PartitionId(0), keyed("t2", "k"))
+            .is_buffered()
+    );
+    assert!(
+        service
+            .offer_task(queue, PartitionId(0), keyed("o1", "other"))
+            .is_ok()
+    );
+
+    service.fifo().release("t1");
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), keyed("t2", "k"))
+            .is_buffered()
+    );
+    assert!(service.fifo().is_held(queue, "k"));
+
+    // Keys are ignored on queues that aren't FIFO
+    let plain = "plain.queue";
+    for id in ["p1", "p2"] {
+        let envelope = TaskEnvelope {
+            fifo_key: Some("k".to_string()),
+            ..make_envelope(id, plain)
+        };
+        assert!(
+            service
+                .buffer_task(plain, PartitionId(0), envelope)
+                .is_buffered()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_paused_queue_buffers_until_resumed() {
+    let service = MatchingService::new(MatchingConfig::default());
+    let queue = "paused.queue";
+    let mut resumed = service.paused().subscribe_resumed();
+    service.paused().set_paused(queue, true);
+
+    // Offers come back to be buffered, and a registering worker only waits
+    let envelope = service
+        .offer_task(queue, PartitionId(0), make_envelope("task-1", queue))
+        .unwrap_err();
+    assert!(
+        service
+            .buffer_task(queue, PartitionId(0), envelope)
+            .is_buffered()
+    );
+    let mut rx = service.register_worker(queue, PartitionId(0), WorkerId::new());
+    assert!(rx.try_recv().is_err());
+    assert_eq!(service.match_buffered(queue, PartitionId(0)), 0);
+
+    service.paused().set_paused(queue, false);
+    assert_eq!(resumed.try_recv().unwrap(), queue);
+    assert_eq!(service.match_buffered(queue, PartitionId(0)), 1);
+    assert_eq!(rx.await.unwrap().task_id, "task-1");
+}
+
+#[test]
+fn test_paused_refresh_announces_resumed_queues() {
+    let service = MatchingService::new(MatchingConfig::default());
+    let paused = service.paused();
+    paused.replace_queues(["a".to_string(), "b".to_string()].into());
+    let mut resumed = paused.subscribe_resumed();
+
+    paused.replace_queues(["b".to_string()].into());
+    assert!(!paused.is_paused("a"));
+    assert!(paused.is_paused("b"));
+    assert_eq!(resumed.try_recv().unwrap(), "a");
+    assert!(resumed.try_recv().is_err());
+}
+
+#[test]
+fn test_rate_limit_grants_a_seconds_burst() {
+    let service = MatchingService::new(MatchingConfig::default());
+    let limits = service.rate_limits();
+    // Unlimited queues get everything
+    assert_eq!(limits.acquire("q", 50), 50);
+
+    limits.set_limit("q", Some(10));
+    assert_eq!(limits.acquire("q", 4), 4);
+    assert_eq!(limits.acquire("q", 50), 6);
+    assert_eq!(limits.acquire("q", 50), 0);
+    let wait = limits.until_available("q");
+    assert!(!wait.is_zero() && wait <= std::time::Duration::from_millis(100));
+
+    // Unused grants go back to the bucket
+    limits.release("q", 3);
+    assert_eq!(limits.acquire("q", 50), 3);
+
+    // The refresh keeps an unchanged limit's bucket and lifts missing ones
+    limits.replace_limits([("q".to_string(), 10)].into());
+    assert_eq!(limits.acquire("q", 50), 0);
+    limits.replace_limits(Default::default());
+    assert!(!limits.is_limited("q"));
+    assert_eq!(limits.acquire("q", 50), 50);
+}
+
 #[test]
 fn test_config_accessor() {
     let config = MatchingConfig {
@@ -367,10 +904,63 @@ fn test_config_accessor() {
         branching_factor: 4,
         max_buffer_per_partition: 500,
         task_reader_batch_size: 25,
+        task_reader_max_batch_size: 200,
         task_reader_poll_busy_ms: 5,
         task_reader_poll_idle_ms: 100,
+        queue_policy_refresh_ms: 1000,
+        queue_discovery_fallback_ms: 60000,
+        decision_log_size: 1000,
+        decision_sample_rate: 100,
+        buffered_task_ttl_ms: 30000,
     };
     let service = MatchingService::new(config.clone());
     assert_eq!(service.config().num_partitions, 8);
     assert_eq!(service.config().branching_factor, 4);
 }
+
+#[test]
+fn test_decision_log_keeps_newest_entries() {
+    use valka_matching::decisions::{DecisionLog, DecisionOutcome};
+
+    let log = DecisionLog::new(3, 1);
+    for i in 0..4 {
+        let queue = if i % 2 == 0 { "a" } else { "b" };
+        log.record(
+            queue,
+            PartitionId(0),
+            &format!("t{i}"),
+            DecisionOutcome::Buffered,
+        );
+    }
+
+    let ids: Vec<_> = log
+        .recent(None, 10)
+        .into_iter()
+        .map(|d| d.task_id)
+        .collect();
+    assert_eq!(ids, ["t3", "t2", "t1"]);
+    let ids: Vec<_> = log
+        .recent(Some("a"), 10)
+        .into_iter()
+        .map(|d| d.task_id)
+        .collect();
+    assert_eq!(ids, ["t2"]);
+    assert_eq!(log.recent(None, 1).len(), 1);
+}
+
+#[test]
+fn test_decision_log_forced_sampling_expires() {
+    use valka_matching::decisions::DecisionLog;
+
+    let log = DecisionLog::new(10, 0);
+    assert!(!log.is_sampled("q", "t1"));
+
+    log.force_sampling("q", std::time::Duration::from_millis(50));
+    assert!(log.is_sampled("q", "t1"));
+    assert!(!log.is_sampled("other", "t1"));
+    assert_eq!(log.forced_queues().len(), 1);
+
+    std::thread::sleep(std::time::Duration::from_millis(60));
+    assert!(!log.is_sampled("q", "t1"));
+    assert!(log.forced_queues().is_empty());
+}
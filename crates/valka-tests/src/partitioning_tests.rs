@@ -0,0 +1,67 @@
+use valka_core::partitioning::{
+    FIXTURE_PATH, FixtureError, generate_fixture, load_fixture, verify_entries, verify_fixture,
+    write_fixture,
+};
+
+#[test]
+fn test_partition_routing_matches_fixture() {
+    // If this fails, partition_for_task changed output. See valka_core::partitioning
+    // for the regenerate + repartition procedure before updating the fixture.
+    let checked = verify_fixture(FIXTURE_PATH).expect("partition routing drifted");
+    assert_eq!(checked, 1000);
+}
+
+#[test]
+fn test_perturbed_fixture_reports_drift() {
+    let mut entries = load_fixture(FIXTURE_PATH).unwrap();
+    let victim = entries
+        .iter()
+        .position(|e| e.num_partitions > 1)
+        .expect("fixture should have multi-partition entries");
+    let expected = entries[victim].partition_id;
+    entries[victim].partition_id = (expected + 1) % entries[victim].num_partitions;
+
+    match verify_entries(&entries) {
+        Err(FixtureError::Drift { mismatches, total }) => {
+            assert_eq!(total, entries.len());
+            assert_eq!(mismatches.len(), 1);
+            assert_eq!(mismatches[0].actual, expected);
+        }
+        other => panic!("Expected drift, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_generated_fixture_roundtrip() {
+    let path = std::env::temp_dir().join(format!("valka-fixture-{}.json", uuid::Uuid::now_v7()));
+    write_fixture(&path, 50).unwrap();
+    assert_eq!(verify_fixture(&path).unwrap(), 50);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_generated_fixture_covers_partition_counts() {
+    let entries = generate_fixture(100);
+    let counts: std::collections::HashSet<i32> = entries.iter().map(|e| e.num_partitions).collect();
+    assert!(counts.len() > 1);
+    assert!(
+        entries
+            .iter()
+            .all(|e| e.partition_id >= 0 && e.partition_id < e.num_partitions)
+    );
+}
+
+#[test]
+fn test_verify_fixture_missing_or_empty() {
+    assert!(matches!(
+        verify_fixture("/nonexistent/fixture.json"),
+        Err(FixtureError::Io(_))
+    ));
+    assert!(matches!(verify_entries(&[]), Err(FixtureError::Empty)));
+}
+
+#[test]
+#[ignore = "rewrites the checked-in fixture; run only for intentional routing changes"]
+fn regenerate_partition_fixture() {
+    write_fixture(FIXTURE_PATH, 1000).unwrap();
+}
@@ -11,10 +11,17 @@ fn test_task_assignment_construction() {
         attempt_number: 1,
         timeout_seconds: 300,
         metadata: "{}".to_string(),
+        remaining_budget_ms: 0,
+        traceparent: String::new(),
+        priority: 5,
+        created_at_ms: 1700000000000,
+        scheduled_at_ms: 0,
     };
     assert_eq!(assignment.task_id, "task-123");
     assert_eq!(assignment.queue_name, "emails");
     assert_eq!(assignment.timeout_seconds, 300);
+    assert_eq!(assignment.priority, 5);
+    assert_eq!(assignment.created_at_ms, 1700000000000);
 }
 
 #[test]
@@ -29,6 +36,11 @@ fn test_worker_response_task_assignment_variant() {
             attempt_number: 1,
             timeout_seconds: 60,
             metadata: String::new(),
+            remaining_budget_ms: 0,
+            traceparent: String::new(),
+            priority: 0,
+            created_at_ms: 0,
+            scheduled_at_ms: 0,
         })),
     };
 
@@ -73,6 +85,9 @@ fn test_task_event_construction() {
         attempt_number: 2,
         error_message: String::new(),
         timestamp_ms: 1700000000000,
+        suppressed_count: 0,
+        progress: None,
+        progress_message: String::new(),
     };
     assert_eq!(event.event_id, "evt-1");
     assert_eq!(event.previous_status, 1);
@@ -90,6 +105,10 @@ fn test_task_result_success() {
         retryable: false,
         output: r#"{"result": 42}"#.to_string(),
         error_message: String::new(),
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
     };
     assert!(result.success);
     assert!(!result.retryable);
@@ -105,6 +124,10 @@ fn test_task_result_failure() {
         retryable: true,
         output: String::new(),
         error_message: "Connection timeout".to_string(),
+        traceparent: String::new(),
+        rejected: false,
+        cancelled: false,
+        retry_after_ms: 0,
     };
     assert!(!result.success);
     assert!(result.retryable);
@@ -119,9 +142,36 @@ fn test_worker_hello_construction() {
         queues: vec!["q1".to_string(), "q2".to_string()],
         concurrency: 4,
         metadata: "{\"env\":\"prod\"}".to_string(),
+        version: "1.2.3".to_string(),
+        labels: Default::default(),
+        queue_concurrency: Default::default(),
+        protocol_version: PROTOCOL_VERSION,
+        capabilities: vec![CAPABILITY_SIGNALS.to_string()],
+        heartbeat_interval_ms: 5000,
     };
     assert_eq!(hello.queues.len(), 2);
     assert_eq!(hello.concurrency, 4);
+    assert_eq!(hello.protocol_version, 1);
+    assert_eq!(hello.capabilities, ["signals"]);
+}
+
+#[test]
+fn test_worker_response_hello_ack_variant() {
+    let response = WorkerResponse {
+        response: Some(worker_response::Response::HelloAck(HelloAck {
+            protocol_version: PROTOCOL_VERSION,
+            session_id: "s1".to_string(),
+            heartbeat_interval_ms: 10_000,
+        })),
+    };
+
+    match response.response {
+        Some(worker_response::Response::HelloAck(ack)) => {
+            assert_eq!(ack.session_id, "s1");
+            assert_eq!(ack.heartbeat_interval_ms, 10_000);
+        }
+        _ => panic!("Expected HelloAck variant"),
+    }
 }
 
 #[test]
@@ -0,0 +1,171 @@
+use std::sync::{Arc, Mutex};
+
+use valka_core::ServerConfig;
+use valka_server::reload::{ConfigReloader, changed_settings, is_reloadable};
+
+fn running_config() -> ServerConfig {
+    ServerConfig {
+        node_id: "node-1".to_string(),
+        ..ServerConfig::default()
+    }
+}
+
+/// A reloader whose config "file" is the returned handle.
+fn reloader_over(running: ServerConfig) -> (ConfigReloader, Arc<Mutex<ServerConfig>>) {
+    let file = Arc::new(Mutex::new(running.clone()));
+    let source = file.clone();
+    let reloader = ConfigReloader::new(running, move || Ok(source.lock().unwrap().clone()));
+    (reloader, file)
+}
+
+#[test]
+fn test_changed_settings_lists_dotted_paths() {
+    let running = running_config();
+    assert!(changed_settings(&running, &running).is_empty());
+
+    let mut loaded = running.clone();
+    loaded.http_addr = "0.0.0.0:9999".to_string();
+    loaded.matching.task_reader_poll_idle_ms = 1000;
+    loaded.gossip.seed_nodes = vec!["10.0.0.2:7946".to_string()];
+    assert_eq!(
+        changed_settings(&running, &loaded),
+        [
+            "gossip.seed_nodes",
+            "http_addr",
+            "matching.task_reader_poll_idle_ms"
+        ]
+    );
+}
+
+#[test]
+fn test_reloadable_settings() {
+    for path in [
+        "scheduler.reaper_interval_secs",
+        "matching.max_buffer_per_partition",
+        "matching.task_reader_poll_busy_ms",
+        "log_ingester.batch_size",
+        "retention.log_ttl_days",
+    ] {
+        assert!(is_reloadable(path), "{path}");
+    }
+    for path in [
+        "grpc_addr",
+        "http_addr",
+        "database_url",
+        "matching.num_partitions",
+        "log_ingester.stream_buffer_size",
+        "scheduler.retry_base_delay_secs",
+    ] {
+        assert!(!is_reloadable(path), "{path}");
+    }
+}
+
+#[test]
+fn test_reload_publishes_changed_sections() {
+    let (reloader, file) = reloader_over(running_config());
+    let mut scheduler = reloader.scheduler();
+    let retention = reloader.retention();
+    let log_ingester = reloader.log_ingester();
+    let mut matching = reloader.matching();
+
+    {
+        let mut file = file.lock().unwrap();
+        file.scheduler.reaper_interval_secs = 7;
+        file.matching.task_reader_poll_idle_ms = 1000;
+        file.matching.max_buffer_per_partition = 50;
+    }
+    let outcome = reloader.reload().unwrap();
+    assert_eq!(
+        outcome.applied,
+        [
+            "matching.max_buffer_per_partition",
+            "matching.task_reader_poll_idle_ms",
+            "scheduler.reaper_interval_secs"
+        ]
+    );
+    assert!(outcome.rejected.is_empty());
+
+    assert!(scheduler.has_changed().unwrap());
+    assert_eq!(scheduler.borrow_and_update().reaper_interval_secs, 7);
+    assert!(matching.has_changed().unwrap());
+    assert_eq!(matching.borrow_and_update().task_reader_poll_idle_ms, 1000);
+    // Untouched sections don't wake their loops
+    assert!(!retention.has_changed().unwrap());
+    assert!(!log_ingester.has_changed().unwrap());
+
+    // Reloading the same file again changes nothing
+    assert_eq!(reloader.reload().unwrap(), Default::default());
+    assert!(!scheduler.has_changed().unwrap());
+}
+
+#[test]
+fn test_reload_rejects_listen_addresses_and_database_url() {
+    let (reloader, file) = reloader_over(running_config());
+    let mut log_ingester = reloader.log_ingester();
+
+    {
+        let mut file = file.lock().unwrap();
+        file.grpc_addr = "0.0.0.0:60051".to_string();
+        file.http_addr = "0.0.0.0:9090".to_string();
+        file.database_url = "postgres://other:other@db:5432/other".to_string();
+        file.matching.num_partitions = 16;
+        file.log_ingester.batch_size = 10;
+    }
+    let outcome = reloader.reload().unwrap();
+    assert_eq!(outcome.applied, ["log_ingester.batch_size"]);
+    assert_eq!(
+        outcome.rejected,
+        [
+            "database_url",
+            "grpc_addr",
+            "http_addr",
+            "matching.num_partitions"
+        ]
+    );
+
+    let running = reloader.running();
+    let defaults = ServerConfig::default();
+    assert_eq!(running.grpc_addr, defaults.grpc_addr);
+    assert_eq!(running.http_addr, defaults.http_addr);
+    assert_eq!(running.database_url, defaults.database_url);
+    assert_eq!(
+        running.matching.num_partitions,
+        defaults.matching.num_partitions
+    );
+    assert_eq!(running.log_ingester.batch_size, 10);
+    assert_eq!(log_ingester.borrow_and_update().batch_size, 10);
+}
+
+#[test]
+fn test_reload_keeps_generated_node_id() {
+    let (reloader, file) = reloader_over(running_config());
+    file.lock().unwrap().node_id = String::new();
+
+    let outcome = reloader.reload().unwrap();
+    assert!(outcome.rejected.is_empty(), "{outcome:?}");
+    assert_eq!(reloader.running().node_id, "node-1");
+}
+
+#[test]
+fn test_failed_reload_changes_nothing() {
+    let (reloader, file) = reloader_over(running_config());
+    let scheduler = reloader.scheduler();
+
+    {
+        let mut file = file.lock().unwrap();
+        file.scheduler.reaper_interval_secs = 7;
+        file.retention.batch_size = 0;
+    }
+    let err = reloader.reload().unwrap_err().to_string();
+    assert!(err.contains("retention.batch_size"), "{err}");
+    assert!(!scheduler.has_changed().unwrap());
+    assert_eq!(
+        reloader.running().scheduler.reaper_interval_secs,
+        ServerConfig::default().scheduler.reaper_interval_secs
+    );
+
+    let unreadable = ConfigReloader::new(running_config(), || {
+        Err(std::io::Error::other("config file is gone").into())
+    });
+    assert!(unreadable.reload().is_err());
+}
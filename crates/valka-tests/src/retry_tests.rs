@@ -153,3 +153,123 @@ fn test_sdk_retry_reset_restarts_sequence() {
         after_reset.as_millis()
     );
 }
+
+#[test]
+fn test_retry_plan_projects_remaining_attempts() {
+    let policy = valka_core::retry::RetryPolicy {
+        base_delay_secs: 10,
+        max_delay_secs: 60,
+        ..Default::default()
+    };
+    let now = chrono::Utc::now();
+    let at = |secs| now + chrono::Duration::seconds(secs);
+
+    // 1 of 4 attempts used: 20s, then 40s, then capped at 60s
+    let plan = policy.plan(1, 4, None, None, now).unwrap();
+    assert_eq!(plan.attempts_remaining, 3);
+    assert_eq!(plan.schedule, [at(20), at(60), at(120)]);
+    assert_eq!(plan.next_attempt_at, Some(at(20)));
+    assert!(plan.dead_letter);
+
+    // An assigned retry time is kept and the rest follow from it
+    let plan = policy.plan(1, 4, Some(at(5)), None, now).unwrap();
+    assert_eq!(plan.schedule, [at(5), at(45), at(105)]);
+
+    // Attempts past the deadline are dropped and the task fails instead
+    let plan = policy.plan(1, 4, None, Some(at(60)), now).unwrap();
+    assert_eq!(plan.schedule, [at(20)]);
+    assert!(!plan.dead_letter);
+    let plan = policy.plan(1, 4, None, Some(at(20)), now).unwrap();
+    assert_eq!(plan.next_attempt_at, None);
+    assert_eq!(plan.attempts_remaining, 3);
+
+    assert!(policy.plan(4, 4, None, None, now).is_none());
+}
+
+#[test]
+fn test_retry_policy_with_task_backoff() {
+    use valka_core::retry::{RetryBackoff, RetryPolicy};
+
+    let global = RetryPolicy {
+        base_delay_secs: 60,
+        max_delay_secs: 600,
+        ..Default::default()
+    };
+    // Unset fields keep the scheduler's settings
+    let policy = global.with_backoff(&RetryBackoff {
+        base_seconds: Some(1),
+        multiplier: Some(1.5),
+        ..Default::default()
+    });
+    assert_eq!(policy.max_delay_secs, 600);
+    assert_eq!(policy.delay(0), chrono::Duration::seconds(1));
+    assert_eq!(policy.delay(2), chrono::Duration::milliseconds(2250));
+    assert_eq!(global.with_backoff(&RetryBackoff::default()), global);
+
+    let jittered = global.with_backoff(&RetryBackoff {
+        jitter: true,
+        ..Default::default()
+    });
+    for _ in 0..20 {
+        let d = jittered.jittered_delay(1);
+        assert!(d >= chrono::Duration::seconds(60) && d <= chrono::Duration::seconds(120));
+    }
+    assert_eq!(global.jittered_delay(1), chrono::Duration::seconds(120));
+}
+
+#[test]
+fn test_retry_backoff_validation() {
+    use valka_core::retry::{MAX_BACKOFF_SECONDS, RetryBackoff};
+
+    assert!(RetryBackoff::default().validate().is_ok());
+    for invalid in [
+        RetryBackoff {
+            base_seconds: Some(0),
+            ..Default::default()
+        },
+        RetryBackoff {
+            base_seconds: Some(10),
+            max_seconds: Some(5),
+            ..Default::default()
+        },
+        RetryBackoff {
+            multiplier: Some(0.5),
+            ..Default::default()
+        },
+        RetryBackoff {
+            multiplier: Some(f64::NAN),
+            ..Default::default()
+        },
+        RetryBackoff {
+            max_seconds: Some(u64::MAX),
+            ..Default::default()
+        },
+        RetryBackoff {
+            base_seconds: Some(MAX_BACKOFF_SECONDS + 1),
+            ..Default::default()
+        },
+    ] {
+        assert!(invalid.validate().is_err(), "{invalid:?}");
+    }
+    let longest = RetryBackoff {
+        base_seconds: Some(MAX_BACKOFF_SECONDS),
+        max_seconds: Some(MAX_BACKOFF_SECONDS),
+        ..Default::default()
+    };
+    assert!(longest.validate().is_ok());
+}
+
+#[test]
+fn test_retry_policy_far_future_does_not_overflow() {
+    use valka_core::retry::RetryPolicy;
+
+    let policy = RetryPolicy {
+        base_delay_secs: u64::MAX,
+        max_delay_secs: u64::MAX,
+        ..Default::default()
+    };
+    let at = policy.next_attempt_at(3, chrono::Utc::now());
+    assert_eq!(at, chrono::DateTime::<chrono::Utc>::MAX_UTC);
+    let plan = policy.plan(0, 3, None, None, chrono::Utc::now()).unwrap();
+    assert_eq!(plan.schedule.len(), 3);
+}
@@ -171,6 +171,21 @@ fn make_signal(id: &str, name: &str, payload: &str) -> TaskSignal {
     }
 }
 
+#[test]
+fn test_context_schedule_accessors() {
+    let (ctx, _signal_tx, _request_rx) = make_test_context();
+    assert_eq!(ctx.priority(), 0);
+    assert!(ctx.created_at().is_none());
+    assert!(ctx.scheduled_at().is_none());
+
+    let (ctx, _signal_tx, _request_rx) = make_test_context();
+    let ctx = ctx.with_schedule(3, 1700000000000, 0);
+    assert_eq!(ctx.priority(), 3);
+    assert_eq!(ctx.created_at().unwrap().timestamp_millis(), 1700000000000);
+    // 0 means the task was not scheduled
+    assert!(ctx.scheduled_at().is_none());
+}
+
 #[tokio::test]
 async fn test_context_receive_signal() {
     let (mut ctx, signal_tx, _request_rx) = make_test_context();
@@ -304,3 +319,515 @@ async fn test_context_signal_sends_ack() {
         other => panic!("Expected SignalAck, got {other:?}"),
     }
 }
+
+// ─── Worker log forwarding ──────────────────────────────────────────
+
+fn capture_worker_logs(
+    config: valka_sdk::logging::WorkerLogConfig,
+    emit: impl FnOnce(),
+) -> valka_sdk::logging::WorkerLogSink {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let (layer, sink) = valka_sdk::logging::worker_log_layer(config);
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, emit);
+    sink
+}
+
+async fn ship_worker_logs(sink: valka_sdk::logging::WorkerLogSink) -> Vec<valka_proto::LogEntry> {
+    let (tx, mut rx) = mpsc::channel::<WorkerRequest>(16);
+    let ship = tokio::spawn(sink.ship("w-1".to_string(), "worker-one".to_string(), tx));
+
+    let mut entries = Vec::new();
+    while let Ok(Some(req)) =
+        tokio::time::timeout(std::time::Duration::from_millis(100), rx.recv()).await
+    {
+        match req.request {
+            Some(worker_request::Request::LogBatch(batch)) => entries.extend(batch.entries),
+            other => panic!("Expected LogBatch, got {other:?}"),
+        }
+    }
+    ship.abort();
+    entries
+}
+
+#[tokio::test]
+async fn test_worker_log_layer_forwards_warn_and_above() {
+    let sink = capture_worker_logs(Default::default(), || {
+        tracing::info!("not forwarded");
+        tracing::warn!(attempt = 3, "disk almost full");
+        tracing::error!("db unreachable");
+    });
+
+    let entries = ship_worker_logs(sink).await;
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| e.task_run_id == "worker:w-1"));
+    assert_eq!(entries[0].level, 3);
+    assert_eq!(entries[0].message, "disk almost full");
+    assert_eq!(entries[1].level, 4);
+
+    let meta: serde_json::Value = serde_json::from_str(&entries[0].metadata).unwrap();
+    assert_eq!(meta["worker_name"], "worker-one");
+    assert_eq!(meta["attempt"], 3);
+    assert!(meta["target"].as_str().unwrap().starts_with("valka_tests"));
+}
+
+#[tokio::test]
+async fn test_worker_log_layer_configurable_level() {
+    let config = valka_sdk::logging::WorkerLogConfig {
+        level: tracing::Level::INFO,
+        ..Default::default()
+    };
+    let sink = capture_worker_logs(config, || {
+        tracing::debug!("not forwarded");
+        tracing::info!("forwarded");
+    });
+
+    let entries = ship_worker_logs(sink).await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].level, 2);
+}
+
+#[tokio::test]
+async fn test_worker_log_layer_rate_limited() {
+    let config = valka_sdk::logging::WorkerLogConfig {
+        max_per_second: 5,
+        ..Default::default()
+    };
+    let sink = capture_worker_logs(config, || {
+        for i in 0..50 {
+            tracing::warn!(i, "burst");
+        }
+    });
+
+    assert_eq!(sink.dropped_count(), 45);
+    let entries = ship_worker_logs(sink).await;
+    assert_eq!(entries.len(), 5);
+}
+
+#[tokio::test]
+async fn test_worker_log_layer_bounded_buffer() {
+    let config = valka_sdk::logging::WorkerLogConfig {
+        max_per_second: 1000,
+        buffer_size: 3,
+        ..Default::default()
+    };
+    let sink = capture_worker_logs(config, || {
+        for _ in 0..10 {
+            tracing::error!("offline");
+        }
+    });
+
+    assert_eq!(sink.dropped_count(), 7);
+    assert_eq!(ship_worker_logs(sink).await.len(), 3);
+}
+
+#[test]
+fn test_execution_timeout_uses_tighter_limit() {
+    use std::time::Duration;
+    use valka_sdk::worker::execution_timeout;
+
+    // No deadline: the task timeout applies
+    assert_eq!(execution_timeout(300, 0), Some(Duration::from_secs(300)));
+    // A tight deadline shortens the worker-side timeout
+    assert_eq!(
+        execution_timeout(300, 1500),
+        Some(Duration::from_millis(1500))
+    );
+    // A roomy deadline leaves the timeout alone
+    assert_eq!(execution_timeout(2, 60_000), Some(Duration::from_secs(2)));
+    assert_eq!(execution_timeout(0, 800), Some(Duration::from_millis(800)));
+    assert_eq!(execution_timeout(0, 0), None);
+}
+
+// ─── Webhook verification ───────────────────────────────────────────
+
+const WEBHOOK_SECRET: &str = "whsec_test";
+
+fn webhook_body() -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!({
+        "event_type": "task.completed",
+        "task_id": "task-1",
+        "queue_name": "emails",
+        "task_name": "send",
+        "status": "COMPLETED",
+        "attempt": 2,
+        "output": {"sent": true},
+        "created_at": "2026-01-01T00:00:00Z",
+        "updated_at": "2026-01-01T00:00:05Z",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_webhook_valid_signature_parses_event() {
+    use valka_sdk::webhooks::{WebhookVerifier, signed_headers};
+
+    let now = chrono::Utc::now();
+    let body = webhook_body();
+    let headers = signed_headers(WEBHOOK_SECRET, now, &body);
+
+    let event = WebhookVerifier::new(WEBHOOK_SECRET)
+        .verify_and_parse_at(&headers, &body, now + chrono::Duration::seconds(30))
+        .unwrap();
+    assert_eq!(event.event_type, "task.completed");
+    assert_eq!(event.task_id, "task-1");
+    assert_eq!(event.status, "COMPLETED");
+    assert_eq!(event.attempt, 2);
+    assert_eq!(event.output, Some(serde_json::json!({"sent": true})));
+    assert_eq!(event.error_message, None);
+
+    // The free function uses the wall clock
+    let event = valka_sdk::webhooks::verify_and_parse(&headers, &body, WEBHOOK_SECRET).unwrap();
+    assert_eq!(event.queue_name, "emails");
+}
+
+#[test]
+fn test_webhook_tampered_body_rejected() {
+    use valka_sdk::webhooks::{WebhookError, signed_headers, verify_and_parse};
+
+    let body = webhook_body();
+    let headers = signed_headers(WEBHOOK_SECRET, chrono::Utc::now(), &body);
+    let tampered = String::from_utf8(body)
+        .unwrap()
+        .replace("COMPLETED", "FAILED");
+
+    let err = verify_and_parse(&headers, tampered.as_bytes(), WEBHOOK_SECRET).unwrap_err();
+    assert!(matches!(err, WebhookError::InvalidSignature));
+}
+
+#[test]
+fn test_webhook_wrong_secret_rejected() {
+    use valka_sdk::webhooks::{SIGNATURE_HEADER, WebhookError, sign, signed_headers};
+
+    let now = chrono::Utc::now();
+    let body = webhook_body();
+    let headers = signed_headers("whsec_other", now, &body);
+    let err = valka_sdk::webhooks::verify_and_parse(&headers, &body, WEBHOOK_SECRET).unwrap_err();
+    assert!(matches!(err, WebhookError::InvalidSignature));
+
+    // During rotation any listed signature may match
+    let mut headers = headers;
+    let rotated = format!(
+        "{}, {}",
+        headers[SIGNATURE_HEADER].to_str().unwrap(),
+        sign(WEBHOOK_SECRET, now, &body)
+    );
+    headers.insert(SIGNATURE_HEADER, rotated.parse().unwrap());
+    assert!(valka_sdk::webhooks::verify_and_parse(&headers, &body, WEBHOOK_SECRET).is_ok());
+}
+
+#[test]
+fn test_webhook_stale_timestamp_rejected() {
+    use std::time::Duration;
+    use valka_sdk::webhooks::{WebhookError, WebhookVerifier, signed_headers};
+
+    let signed_at = chrono::Utc::now() - chrono::Duration::minutes(10);
+    let body = webhook_body();
+    let headers = signed_headers(WEBHOOK_SECRET, signed_at, &body);
+
+    let err = valka_sdk::webhooks::verify_and_parse(&headers, &body, WEBHOOK_SECRET).unwrap_err();
+    assert!(matches!(
+        err,
+        WebhookError::Stale {
+            age_secs: 600,
+            tolerance_secs: 300
+        }
+    ));
+
+    // A wider tolerance accepts it; timestamps from the future are bounded too
+    let verifier = WebhookVerifier::new(WEBHOOK_SECRET).with_tolerance(Duration::from_secs(900));
+    assert!(verifier.verify_and_parse(&headers, &body).is_ok());
+    let early = signed_at - chrono::Duration::minutes(20);
+    assert!(matches!(
+        verifier.verify_and_parse_at(&headers, &body, early),
+        Err(WebhookError::Stale { .. })
+    ));
+}
+
+#[test]
+fn test_webhook_missing_or_malformed_headers() {
+    use valka_sdk::webhooks::{
+        SIGNATURE_HEADER, TIMESTAMP_HEADER, WebhookError, signed_headers, verify_and_parse,
+    };
+
+    let body = webhook_body();
+    let headers = signed_headers(WEBHOOK_SECRET, chrono::Utc::now(), &body);
+
+    let mut missing = headers.clone();
+    missing.remove(TIMESTAMP_HEADER);
+    assert!(matches!(
+        verify_and_parse(&missing, &body, WEBHOOK_SECRET),
+        Err(WebhookError::MissingHeader(TIMESTAMP_HEADER))
+    ));
+
+    let mut malformed = headers;
+    malformed.insert(SIGNATURE_HEADER, "sha1=abc".parse().unwrap());
+    assert!(matches!(
+        verify_and_parse(&malformed, &body, WEBHOOK_SECRET),
+        Err(WebhookError::MalformedHeader(SIGNATURE_HEADER))
+    ));
+}
+
+#[test]
+fn test_bearer_token_keeps_existing_authorization() {
+    use tonic::service::Interceptor;
+    use valka_sdk::auth::BearerToken;
+
+    let mut token = BearerToken::new("api");
+    let req = token.call(tonic::Request::new(())).unwrap();
+    assert_eq!(req.metadata().get("authorization").unwrap(), "Bearer api");
+
+    let mut req = tonic::Request::new(());
+    req.metadata_mut()
+        .insert("authorization", "Bearer admin".parse().unwrap());
+    let req = token.call(req).unwrap();
+    assert_eq!(req.metadata().get("authorization").unwrap(), "Bearer admin");
+
+    let req = BearerToken::new("").call(tonic::Request::new(())).unwrap();
+    assert!(req.metadata().get("authorization").is_none());
+}
+
+#[tokio::test]
+async fn test_context_cancellation() {
+    let (ctx, _signal_tx, _request_rx) = make_test_context();
+    assert!(!ctx.is_cancelled());
+
+    let token = ctx.cancellation_token();
+    let waiter = tokio::spawn(async move {
+        ctx.cancelled().await;
+        ctx.is_cancelled()
+    });
+    token.cancel();
+    let seen = tokio::time::timeout(std::time::Duration::from_secs(1), waiter)
+        .await
+        .expect("cancelled() did not resolve")
+        .unwrap();
+    assert!(seen);
+}
+
+fn expect_progress(request: Option<WorkerRequest>) -> valka_proto::TaskProgress {
+    match request.and_then(|r| r.request) {
+        Some(worker_request::Request::Progress(progress)) => progress,
+        other => panic!("Expected TaskProgress, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_context_report_progress_coalesces_updates() {
+    let (ctx, _signal_tx, mut request_rx) = make_test_context();
+
+    ctx.report_progress(10.0, "started").await;
+    let first = expect_progress(request_rx.try_recv().ok());
+    assert_eq!(first.task_id, "task-1");
+    assert_eq!(first.task_run_id, "run-1");
+    assert_eq!(first.percent, 10.0);
+    assert_eq!(first.message, "started");
+
+    // Held back within the interval; only the latest is sent when it ends
+    for pct in [20.0, 30.0, 40.0] {
+        ctx.report_progress(pct, &format!("at {pct}")).await;
+    }
+    assert!(request_rx.try_recv().is_err());
+    let latest = tokio::time::timeout(std::time::Duration::from_secs(2), request_rx.recv())
+        .await
+        .expect("coalesced update not sent");
+    let latest = expect_progress(latest);
+    assert_eq!(latest.percent, 40.0);
+    assert_eq!(latest.message, "at 40");
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(request_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_worker_builder_register_without_handler() {
+    let result = valka_sdk::ValkaWorker::builder()
+        .queues(&["demo"])
+        .register("double", |_ctx, n: i64| async move { Ok(n * 2) })
+        .build()
+        .await;
+    assert!(result.is_ok(), "Registered handlers alone should be enough");
+}
+
+#[tokio::test]
+async fn test_worker_builder_register_rejects_batch_handler() {
+    let result = valka_sdk::ValkaWorker::builder()
+        .queues(&["demo"])
+        .register("double", |_ctx, n: i64| async move { Ok(n * 2) })
+        .batch_handler(
+            4,
+            std::time::Duration::from_millis(10),
+            |batch| async move { batch.iter().map(|_| Ok(serde_json::json!({}))).collect() },
+        )
+        .build()
+        .await;
+    let Err(err) = result else {
+        panic!("A batch handler with registered handlers should fail");
+    };
+    assert!(err.to_string().contains("batch handler"), "{err}");
+}
+
+#[tokio::test]
+async fn test_worker_builder_queue_concurrency() {
+    let result = valka_sdk::ValkaWorker::builder()
+        .queues(&[("video.encode", 2), ("email.send", 10)])
+        .concurrency(12)
+        .handler(|_ctx| async { Ok(serde_json::json!({})) })
+        .build()
+        .await;
+    assert!(result.is_ok());
+
+    let result = valka_sdk::ValkaWorker::builder()
+        .queues(&[("video.encode", 0)])
+        .handler(|_ctx| async { Ok(serde_json::json!({})) })
+        .build()
+        .await;
+    let Err(err) = result else {
+        panic!("A queue concurrency of 0 should fail");
+    };
+    assert!(err.to_string().contains("video.encode"), "{err}");
+}
+
+#[test]
+fn test_task_error_retryability() {
+    let err: valka_sdk::TaskError = "flaky".into();
+    assert!(err.retryable);
+    let err: valka_sdk::TaskError = format!("code {}", 503).into();
+    assert!(err.retryable);
+    assert_eq!(err.to_string(), "code 503");
+    assert!(!valka_sdk::TaskError::fatal("bad").retryable);
+    assert_eq!(err.retry_after, None);
+
+    let err = valka_sdk::TaskError::retry_after("429", std::time::Duration::from_secs(30));
+    assert!(err.retryable);
+    assert_eq!(err.retry_after, Some(std::time::Duration::from_secs(30)));
+}
+
+fn expect_log_batch(req: Option<WorkerRequest>) -> Vec<valka_proto::LogEntry> {
+    match req.and_then(|r| r.request) {
+        Some(worker_request::Request::LogBatch(batch)) => batch.entries,
+        other => panic!("Expected LogBatch, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_context_logs_are_batched() {
+    use valka_sdk::context::LogLevel;
+
+    let (ctx, _signal_tx, mut request_rx) = make_test_context();
+
+    ctx.log("fetching").await;
+    ctx.log_with(
+        LogLevel::Warn,
+        "slow upstream",
+        serde_json::json!({"elapsed_ms": 900}),
+    )
+    .await;
+    ctx.error("gave up").await;
+    tokio::task::yield_now().await;
+    assert!(
+        request_rx.try_recv().is_err(),
+        "shipped before the interval"
+    );
+
+    let batch = tokio::time::timeout(std::time::Duration::from_secs(2), request_rx.recv())
+        .await
+        .expect("batch not sent");
+    let entries = expect_log_batch(batch);
+    let messages: Vec<_> = entries.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(messages, ["fetching", "slow upstream", "gave up"]);
+    assert!(entries.iter().all(|e| e.task_run_id == "run-1"));
+    assert_eq!(entries[1].level, LogLevel::Warn as i32);
+    assert_eq!(entries[2].level, LogLevel::Error as i32);
+    let metadata: serde_json::Value = serde_json::from_str(&entries[1].metadata).unwrap();
+    assert_eq!(metadata["elapsed_ms"], 900);
+}
+
+#[tokio::test]
+async fn test_context_logs_ship_full_batches_and_remainder_on_drop() {
+    let (ctx, _signal_tx, mut request_rx) = make_test_context();
+
+    for i in 0..120 {
+        ctx.debug(&format!("line {i}")).await;
+    }
+    drop(ctx);
+
+    let mut sizes = Vec::new();
+    let mut last = String::new();
+    while let Ok(Some(req)) =
+        tokio::time::timeout(std::time::Duration::from_millis(200), request_rx.recv()).await
+    {
+        let entries = expect_log_batch(Some(req));
+        sizes.push(entries.len());
+        last = entries.last().unwrap().message.clone();
+    }
+    assert_eq!(sizes, [50, 50, 20]);
+    assert_eq!(last, "line 119");
+}
+
+#[test]
+fn test_task_from_meta_parses_json_and_timestamps() {
+    use valka_sdk::task::{Task, TaskStatus};
+
+    let meta = valka_proto::TaskMeta {
+        id: "t-1".to_string(),
+        status: TaskStatus::Completed as i32,
+        input: r#"{"n":1}"#.to_string(),
+        output: "2".to_string(),
+        metadata: "{}".to_string(),
+        created_at: "2026-01-02T03:04:05+00:00".to_string(),
+        updated_at: "2026-01-02T03:04:06.5+00:00".to_string(),
+        deadline_ms: 1_767_323_045_000,
+        ..Default::default()
+    };
+    let task = Task::try_from(meta.clone()).unwrap();
+    assert_eq!(task.status, TaskStatus::Completed);
+    assert!(task.is_terminal());
+    assert_eq!(task.input, Some(serde_json::json!({ "n": 1 })));
+    assert_eq!(task.output, Some(serde_json::json!(2)));
+    assert_eq!(task.created_at.to_rfc3339(), "2026-01-02T03:04:05+00:00");
+    assert_eq!(task.deadline.unwrap().timestamp_millis(), 1_767_323_045_000);
+    assert_eq!(task.scheduled_at, None);
+    assert_eq!(task.idempotency_key, None);
+    assert_eq!(task.error_message, None);
+
+    let err = Task::try_from(valka_proto::TaskMeta {
+        created_at: "yesterday".to_string(),
+        ..meta
+    })
+    .unwrap_err();
+    assert!(
+        matches!(&err, valka_sdk::SdkError::InvalidResponse(m) if m.contains("yesterday")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_event_filter_matches() {
+    use valka_sdk::task::{EventFilter, TaskEvent, TaskStatus};
+
+    let event = TaskEvent {
+        task_id: "t-1".to_string(),
+        queue_name: "emails".to_string(),
+        new_status: TaskStatus::Failed as i32,
+        ..Default::default()
+    };
+    assert!(EventFilter::new().matches(&event));
+    assert!(
+        EventFilter::new()
+            .queue("emails")
+            .task("t-1")
+            .matches(&event)
+    );
+    assert!(!EventFilter::new().queue("sms").matches(&event));
+    assert!(!EventFilter::new().task("t-2").matches(&event));
+    let terminal = EventFilter::new()
+        .status(TaskStatus::Completed)
+        .status(TaskStatus::Failed);
+    assert!(terminal.matches(&event));
+    assert!(
+        !EventFilter::new()
+            .status(TaskStatus::Running)
+            .matches(&event)
+    );
+}
@@ -0,0 +1,475 @@
+//! Reduced lifecycle suite against the SQLite development store. Runs fully
+//! in memory: `cargo test -p valka-tests --features sqlite sqlite`.
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use chrono::{Duration, Utc};
+use http_body_util::BodyExt;
+use sqlx::SqlitePool;
+use tokio::sync::watch;
+use tower::ServiceExt;
+use valka_core::{TaskId, partition_for_task};
+use valka_db::queries::task_logs::InsertLogEntry;
+use valka_db::queries::tasks::CreateTaskParams;
+use valka_db::sqlite::{self, queries};
+use valka_proto::worker_service_client::WorkerServiceClient;
+use valka_proto::{
+    PROTOCOL_VERSION, TaskResult, WorkerHello, WorkerRequest, WorkerResponse, worker_request,
+    worker_response,
+};
+use valka_sdk::ValkaWorker;
+use valka_server::auth::ApiAuth;
+use valka_server::dev::{self, DevState};
+
+async fn dev_pool() -> SqlitePool {
+    let pool = sqlite::create_pool("sqlite::memory:").await.unwrap();
+    sqlite::run_migrations(&pool).await.unwrap();
+    pool
+}
+
+fn task_params(queue: &str, max_retries: i32) -> CreateTaskParams {
+    let id = TaskId::new().0;
+    CreateTaskParams {
+        partition_id: partition_for_task(queue, &id, 1).0,
+        id,
+        queue_name: queue.to_string(),
+        task_name: "t".to_string(),
+        input: Some(serde_json::json!({"key": "value"})),
+        priority: 0,
+        max_retries,
+        timeout_seconds: 300,
+        idempotency_key: None,
+        metadata: serde_json::json!({"source": "dev"}),
+        scheduled_at: None,
+        deadline_at: None,
+        traceparent: None,
+        durability: Default::default(),
+        fifo_key: None,
+        callback_url: None,
+        retry_backoff: Default::default(),
+        required_labels: Default::default(),
+    }
+}
+
+/// Claim the task and start a run for it, as the dispatcher would.
+async fn dispatch(pool: &SqlitePool, queue: &str) -> (String, String) {
+    let claimed = queries::claim_tasks(pool, queue, 0, 10).await.unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].status, "DISPATCHING");
+
+    let run_id = uuid::Uuid::now_v7().to_string();
+    let lease = Utc::now() + Duration::seconds(330);
+    let run = queries::start_task_run(pool, &claimed[0].id, &run_id, "w1", "node", lease)
+        .await
+        .unwrap();
+    assert_eq!(run.status, "RUNNING");
+    (claimed[0].id.clone(), run.id)
+}
+
+#[tokio::test]
+async fn test_sqlite_create_dispatch_complete() {
+    let pool = dev_pool().await;
+    let created = queries::create_task(&pool, task_params("q", 3))
+        .await
+        .unwrap();
+    assert_eq!(created.status, "PENDING");
+    assert_eq!(created.input, Some(serde_json::json!({"key": "value"})));
+    assert_eq!(created.metadata["source"], "dev");
+
+    let (task_id, run_id) = dispatch(&pool, "q").await;
+    assert!(
+        queries::claim_tasks(&pool, "q", 0, 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let entries = vec![InsertLogEntry {
+        task_run_id: run_id.clone(),
+        timestamp_ms: 1,
+        level: "INFO".to_string(),
+        message: "working".to_string(),
+        metadata: Some(serde_json::json!({"step": 1})),
+    }];
+    assert_eq!(
+        queries::batch_insert_logs(&pool, &entries).await.unwrap(),
+        1
+    );
+
+    let output = serde_json::json!({"ok": true});
+    queries::complete_task_run(&pool, &task_id, &run_id, Some(&output))
+        .await
+        .unwrap();
+
+    let task = queries::get_task(&pool, &task_id).await.unwrap().unwrap();
+    assert_eq!(task.status, "COMPLETED");
+    assert_eq!(task.attempt_count, 1);
+    assert_eq!(task.output, Some(output));
+
+    let logs = queries::get_logs_for_run(&pool, &run_id, 10).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].metadata, Some(serde_json::json!({"step": 1})));
+}
+
+#[tokio::test]
+async fn test_sqlite_claim_respects_priority_and_schedule() {
+    let pool = dev_pool().await;
+    let mut later = task_params("q", 3);
+    later.scheduled_at = Some(Utc::now() + Duration::hours(1));
+    queries::create_task(&pool, later).await.unwrap();
+    let low = queries::create_task(&pool, task_params("q", 3))
+        .await
+        .unwrap();
+    let mut high = task_params("q", 3);
+    high.priority = 10;
+    let high = queries::create_task(&pool, high).await.unwrap();
+
+    let claimed = queries::claim_tasks(&pool, "q", 0, 10).await.unwrap();
+    let ids: Vec<_> = claimed.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, [high.id.as_str(), low.id.as_str()]);
+}
+
+#[tokio::test]
+async fn test_sqlite_retry_then_promote() {
+    let pool = dev_pool().await;
+    queries::create_task(&pool, task_params("q", 3))
+        .await
+        .unwrap();
+    let (task_id, run_id) = dispatch(&pool, "q").await;
+
+    let retry_at = Utc::now() - Duration::seconds(1);
+    let task = queries::fail_task_run(&pool, &task_id, &run_id, "boom", true, retry_at)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.status, "RETRY");
+    assert!(task.scheduled_at.is_some());
+
+    assert_eq!(queries::promote_delayed_tasks(&pool).await.unwrap(), 1);
+    let task = queries::get_task(&pool, &task_id).await.unwrap().unwrap();
+    assert_eq!(task.status, "PENDING");
+    assert!(task.scheduled_at.is_none());
+
+    // Second attempt is claimable again
+    let (_, run_id) = dispatch(&pool, "q").await;
+    queries::complete_task_run(&pool, &task_id, &run_id, None)
+        .await
+        .unwrap();
+    let task = queries::get_task(&pool, &task_id).await.unwrap().unwrap();
+    assert_eq!(task.status, "COMPLETED");
+    assert_eq!(task.attempt_count, 2);
+}
+
+#[tokio::test]
+async fn test_sqlite_future_retry_not_promoted() {
+    let pool = dev_pool().await;
+    queries::create_task(&pool, task_params("q", 3))
+        .await
+        .unwrap();
+    let (task_id, run_id) = dispatch(&pool, "q").await;
+
+    let retry_at = Utc::now() + Duration::hours(1);
+    queries::fail_task_run(&pool, &task_id, &run_id, "boom", true, retry_at)
+        .await
+        .unwrap();
+    assert_eq!(queries::promote_delayed_tasks(&pool).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_sqlite_exhausted_retries_dead_letter() {
+    let pool = dev_pool().await;
+    queries::create_task(&pool, task_params("q", 1))
+        .await
+        .unwrap();
+    let (task_id, run_id) = dispatch(&pool, "q").await;
+
+    let task = queries::fail_task_run(&pool, &task_id, &run_id, "fatal", true, Utc::now())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.status, "DEAD_LETTER");
+
+    let dead = queries::list_dead_letters(&pool, Some("q"), 10)
+        .await
+        .unwrap();
+    assert_eq!(dead.len(), 1);
+    assert_eq!(dead[0].task_id, task_id);
+    assert_eq!(dead[0].error_message.as_deref(), Some("fatal"));
+    assert_eq!(dead[0].input, Some(serde_json::json!({"key": "value"})));
+    assert!(
+        queries::list_dead_letters(&pool, Some("other"), 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_sqlite_non_retryable_fails() {
+    let pool = dev_pool().await;
+    queries::create_task(&pool, task_params("q", 3))
+        .await
+        .unwrap();
+    let (task_id, run_id) = dispatch(&pool, "q").await;
+
+    let task = queries::fail_task_run(&pool, &task_id, &run_id, "bad input", false, Utc::now())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.status, "FAILED");
+    assert_eq!(task.error_message.as_deref(), Some("bad input"));
+    assert!(task.scheduled_at.is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_idempotency_key_unique() {
+    let pool = dev_pool().await;
+    let mut first = task_params("q", 3);
+    first.idempotency_key = Some("k".to_string());
+    queries::create_task(&pool, first).await.unwrap();
+
+    let mut second = task_params("q", 3);
+    second.idempotency_key = Some("k".to_string());
+    assert!(queries::create_task(&pool, second).await.is_err());
+}
+
+async fn dev_get(app: &axum::Router, uri: &str) -> serde_json::Value {
+    let resp = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK, "{uri}");
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_dev_server_runs_task_end_to_end() {
+    let state = DevState::new(dev_pool().await, &valka_core::ServerConfig::default());
+    let addr: std::net::SocketAddr = "127.0.0.1:19922".parse().unwrap();
+    let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(dev::serve_grpc(
+        addr,
+        state.clone(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    let app = dev::build_dev_router(state);
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let worker = ValkaWorker::builder()
+        .server_addr(&format!("http://{addr}"))
+        .queues(&["dev"])
+        .handler(|ctx| async move {
+            ctx.log("handling").await;
+            let input: serde_json::Value = ctx.input().unwrap();
+            Ok(serde_json::json!({ "echo": input["n"] }))
+        })
+        .build()
+        .await
+        .unwrap();
+    tokio::spawn(worker.run());
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/tasks")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "queue_name": "dev",
+                        "task_name": "echo",
+                        "input": { "n": 7 },
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let created: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let task_uri = format!("/api/v1/tasks/{}", created["id"].as_str().unwrap());
+
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    let task = loop {
+        let task = dev_get(&app, &task_uri).await;
+        if task["status"] == "COMPLETED" {
+            break task;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "task is {}",
+            task["status"]
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    };
+    assert_eq!(task["output"], serde_json::json!({ "echo": 7 }));
+
+    let runs = dev_get(&app, &format!("{task_uri}/runs")).await;
+    let run_id = runs[0]["id"].as_str().unwrap();
+    let logs = dev_get(&app, &format!("{task_uri}/runs/{run_id}/logs")).await;
+    assert_eq!(logs[0]["message"], "handling");
+    assert_eq!(
+        dev_get(&app, "/api/v1/workers")
+            .await
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_dev_reaper_fails_expired_lease() {
+    let state = DevState::new(dev_pool().await, &valka_core::ServerConfig::default());
+    let pool = state.pool();
+    queries::create_task(pool, task_params("q", 3))
+        .await
+        .unwrap();
+    let claimed = queries::claim_tasks(pool, "q", 0, 10).await.unwrap();
+    let run_id = uuid::Uuid::now_v7().to_string();
+    let expired = Utc::now() - Duration::seconds(1);
+    queries::start_task_run(pool, &claimed[0].id, &run_id, "w1", "node", expired)
+        .await
+        .unwrap();
+
+    assert_eq!(state.reap_expired_runs().await.unwrap(), 1);
+    let task = queries::get_task(pool, &claimed[0].id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(task.status, "RETRY");
+    let runs = queries::get_runs_for_task(pool, &task.id).await.unwrap();
+    assert_eq!(runs[0].status, "FAILED");
+    assert_eq!(
+        runs[0].error_message.as_deref(),
+        Some("Lease expired on worker w1")
+    );
+    assert_eq!(state.reap_expired_runs().await.unwrap(), 0);
+}
+
+async fn dev_session(
+    addr: std::net::SocketAddr,
+    worker_id: &str,
+) -> (
+    tokio::sync::mpsc::Sender<WorkerRequest>,
+    tonic::Streaming<WorkerResponse>,
+) {
+    let (tx, rx) = tokio::sync::mpsc::channel::<WorkerRequest>(16);
+    let inbound = WorkerServiceClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap()
+        .session(tokio_stream::wrappers::ReceiverStream::new(rx))
+        .await
+        .unwrap()
+        .into_inner();
+    tx.send(WorkerRequest {
+        request: Some(worker_request::Request::Hello(WorkerHello {
+            worker_id: worker_id.to_string(),
+            worker_name: "dev-test-worker".to_string(),
+            queues: vec!["dup".to_string()],
+            concurrency: 1,
+            protocol_version: PROTOCOL_VERSION,
+            ..Default::default()
+        })),
+    })
+    .await
+    .unwrap();
+    (tx, inbound)
+}
+
+async fn next_response(
+    inbound: &mut tonic::Streaming<WorkerResponse>,
+) -> worker_response::Response {
+    tokio::time::timeout(std::time::Duration::from_secs(5), inbound.message())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .response
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_dev_reconnect_keeps_tasks_of_new_session() {
+    let state = DevState::new(dev_pool().await, &valka_core::ServerConfig::default());
+    let addr: std::net::SocketAddr = "127.0.0.1:19923".parse().unwrap();
+    let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(dev::serve_grpc(
+        addr,
+        state.clone(),
+        ApiAuth::default(),
+        shutdown_rx,
+    ));
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let (old_tx, mut old_inbound) = dev_session(addr, "dup-worker").await;
+    assert!(matches!(
+        next_response(&mut old_inbound).await,
+        worker_response::Response::HelloAck(_)
+    ));
+    let task = queries::create_task(state.pool(), task_params("dup", 3))
+        .await
+        .unwrap();
+    let worker_response::Response::TaskAssignment(assignment) =
+        next_response(&mut old_inbound).await
+    else {
+        panic!("expected an assignment");
+    };
+    assert_eq!(assignment.task_id, task.id);
+
+    // The reconnect takes over the run and tells the old session to go
+    let (new_tx, mut new_inbound) = dev_session(addr, "dup-worker").await;
+    assert!(matches!(
+        next_response(&mut new_inbound).await,
+        worker_response::Response::HelloAck(_)
+    ));
+    assert!(matches!(
+        next_response(&mut old_inbound).await,
+        worker_response::Response::ServerShutdown(_)
+    ));
+
+    // The old stream ending must not release the new session's run
+    drop(old_tx);
+    drop(old_inbound);
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    let current = queries::get_task(state.pool(), &task.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(current.status, "RUNNING");
+
+    new_tx
+        .send(WorkerRequest {
+            request: Some(worker_request::Request::TaskResult(TaskResult {
+                task_id: task.id.clone(),
+                task_run_id: assignment.task_run_id,
+                success: true,
+                output: r#"{"ok":true}"#.to_string(),
+                ..Default::default()
+            })),
+        })
+        .await
+        .unwrap();
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    loop {
+        let current = queries::get_task(state.pool(), &task.id)
+            .await
+            .unwrap()
+            .unwrap();
+        if current.status == "COMPLETED" {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "task is {}",
+            current.status
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::{TimeZone, Utc};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use serde_json::{Value, json};
+use valka_dispatcher::templating::{MAX_TEMPLATE_DEPTH, TemplateValues, render_input};
+
+fn values(metadata: &str) -> TemplateValues<'_> {
+    TemplateValues {
+        queue_name: "reports",
+        task_id: "task-1",
+        run_id: "run-9",
+        attempt: 3,
+        dispatched_at: Utc.with_ymd_and_hms(2026, 5, 4, 12, 30, 0).unwrap(),
+        metadata,
+    }
+}
+
+fn render(input: Value, metadata: &str) -> Option<Value> {
+    render_input(&input.to_string(), &values(metadata)).map(|s| serde_json::from_str(&s).unwrap())
+}
+
+#[test]
+fn test_template_each_placeholder() {
+    let rendered = render(
+        json!({
+            "attempt": "{{valka.attempt}}",
+            "task": "{{valka.task_id}}",
+            "run": "{{valka.run_id}}",
+            "at": "{{valka.dispatched_at}}",
+            "region": "{{meta.region}}",
+            "nonce": "{{valka.task_id}}-{{valka.attempt}}",
+        }),
+        r#"{"region": "eu-west-1"}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        rendered,
+        json!({
+            "attempt": "3",
+            "task": "task-1",
+            "run": "run-9",
+            "at": "2026-05-04T12:30:00.000Z",
+            "region": "eu-west-1",
+            "nonce": "task-1-3",
+        })
+    );
+}
+
+#[test]
+fn test_template_nested_values_and_metadata_paths() {
+    let rendered = render(
+        json!({
+            "report": {
+                "sections": [{"title": "Run {{valka.run_id}}"}, "{{meta.owner.team}}"],
+                "limit": 10,
+            },
+        }),
+        r#"{"owner": {"team": "billing"}, "retries": 2}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        rendered,
+        json!({
+            "report": {
+                "sections": [{"title": "Run run-9"}, "billing"],
+                "limit": 10,
+            },
+        })
+    );
+
+    // Non-string metadata is inserted as JSON text
+    let rendered = render(json!(["{{meta.retries}}"]), r#"{"retries": 2}"#).unwrap();
+    assert_eq!(rendered, json!(["2"]));
+}
+
+#[test]
+fn test_template_unknown_placeholders_pass_through() {
+    let rendered = render(
+        json!({
+            "known": "{{valka.attempt}}",
+            "unknown": "{{valka.nope}}",
+            "missing": "{{meta.absent}} {{meta.region.deeper}}",
+            "foreign": "{{ user.name }}",
+            "unclosed": "{{valka.attempt",
+        }),
+        r#"{"region": "eu-west-1"}"#,
+    )
+    .unwrap();
+
+    assert_eq!(rendered["known"], "3");
+    assert_eq!(rendered["unknown"], "{{valka.nope}}");
+    assert_eq!(
+        rendered["missing"],
+        "{{meta.absent}} {{meta.region.deeper}}"
+    );
+    assert_eq!(rendered["foreign"], "{{ user.name }}");
+    assert_eq!(rendered["unclosed"], "{{valka.attempt");
+
+    // Nothing resolvable: sent as stored
+    assert!(render(json!({"a": "{{valka.nope}}"}), "{}").is_none());
+    assert!(render_input("{{valka.attempt}} not json", &values("{}")).is_none());
+}
+
+#[test]
+fn test_template_depth_limited() {
+    let mut input = json!("{{valka.attempt}}");
+    for _ in 0..MAX_TEMPLATE_DEPTH + 1 {
+        input = json!({ "inner": input });
+    }
+    let shallow = json!({"top": "{{valka.attempt}}", "deep": input.clone()});
+
+    let rendered = render(shallow, "{}").unwrap();
+    assert_eq!(rendered["top"], "3");
+    // Past the limit the input is left as stored
+    assert_eq!(rendered["deep"], input);
+}
+
+#[test]
+fn test_template_fast_path_counted() {
+    let recorder = PrometheusBuilder::new().build_recorder();
+    let handle = recorder.handle();
+
+    metrics::with_local_recorder(&recorder, || {
+        let plain = json!({"user": "{{ not a valka placeholder }}", "n": 1}).to_string();
+        assert!(render_input(&plain, &values("{}")).is_none());
+        assert!(render_input("", &values("{}")).is_none());
+        assert!(render_input(r#"{"a": "{{valka.attempt}}"}"#, &values("{}")).is_some());
+    });
+
+    let rendered = handle.render();
+    assert!(
+        rendered.contains(r#"valka_input_templates_total{queue="reports",outcome="fast_path"} 2"#),
+        "{rendered}"
+    );
+    assert!(
+        rendered.contains(r#"valka_input_templates_total{queue="reports",outcome="rendered"} 1"#),
+        "{rendered}"
+    );
+}
@@ -5,7 +5,9 @@
 //!
 //! Requires a running Valka server at http://127.0.0.1:50051.
 
-use valka_sdk::{TaskContext, ValkaClient, ValkaWorker};
+use std::time::Duration;
+
+use valka_sdk::{CreateTaskOptions, TaskContext, ValkaClient, ValkaWorker, WaitError};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -42,7 +44,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     });
 
     // Give the worker a moment to connect
-    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
 
     // Create some tasks
     let mut client = ValkaClient::connect(server_addr).await?;
@@ -51,43 +53,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut task_ids = Vec::new();
     for i in 1..=5 {
         let task = client
-            .create_task("demo", "multiply", Some(serde_json::json!({ "n": i })))
+            .create_task(
+                CreateTaskOptions::new("demo", "multiply").input(serde_json::json!({ "n": i })),
+            )
             .await?;
         println!("  Created task {} with n={i}", task.id);
         task_ids.push(task.id);
     }
 
-    // Poll until all created tasks complete (or timeout)
+    // Wait for each task to finish and print its output
     println!("\nWaiting for tasks to complete...");
-    let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(30);
-    loop {
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-        let mut completed = 0;
-        for id in &task_ids {
-            let task = client.get_task(id).await?;
-            // 4 = COMPLETED, 5 = FAILED
-            if task.status == 4 || task.status == 5 {
-                completed += 1;
-            }
-        }
-
-        let total = task_ids.len();
-        println!("  {completed}/{total} tasks completed");
-
-        if completed >= total || tokio::time::Instant::now() > deadline {
-            break;
-        }
-    }
-
-    // Print final status
-    println!("\nFinal task statuses:");
     for id in &task_ids {
-        let task = client.get_task(id).await?;
-        println!(
-            "  {} - status={} output={}",
-            task.id, task.status, task.output
-        );
+        match client
+            .wait_for_completion(id, Duration::from_secs(30))
+            .await
+        {
+            Ok(task) => println!("  {} - output={:?}", task.id, task.output),
+            Err(WaitError::Unsuccessful(task)) => println!(
+                "  {} - {} ({})",
+                task.id,
+                task.status.as_str_name(),
+                task.error_message.unwrap_or_default()
+            ),
+            Err(e) => println!("  {id} - {e}"),
+        }
     }
 
     println!("\nShutting down worker...");
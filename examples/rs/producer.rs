@@ -5,7 +5,8 @@
 //!
 //! Requires a running Valka server at http://127.0.0.1:50051
 
-use valka_sdk::ValkaClient;
+use valka_sdk::task::TaskFilter;
+use valka_sdk::{CreateTaskOptions, ValkaClient};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -16,9 +17,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a task with JSON input
     let task = client
         .create_task(
-            "emails",
-            "send-welcome",
-            Some(serde_json::json!({
+            CreateTaskOptions::new("emails", "send-welcome").input(serde_json::json!({
                 "to": "user@example.com",
                 "subject": "Welcome!",
                 "body": "Thanks for signing up."
@@ -28,17 +27,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Created task: {}", task.id);
     println!("  Queue:  {}", task.queue_name);
-    println!("  Status: {}", task.status);
+    println!("  Status: {}", task.status.as_str_name());
 
     // Retrieve the task by ID
     let fetched = client.get_task(&task.id).await?;
-    println!("\nFetched task: {} (status={})", fetched.id, fetched.status);
+    println!(
+        "\nFetched task: {} (status={})",
+        fetched.id,
+        fetched.status.as_str_name()
+    );
 
     // List tasks in the "emails" queue
-    let tasks = client.list_tasks(Some("emails"), None, 10).await?;
-    println!("\nTasks in 'emails' queue: {}", tasks.len());
-    for t in &tasks {
-        println!("  {} - {} (status={})", t.id, t.task_name, t.status);
+    let page = client
+        .list_tasks(TaskFilter::new().queue("emails").page_size(10))
+        .await?;
+    println!("\nTasks in 'emails' queue: {}", page.tasks.len());
+    for t in &page.tasks {
+        println!(
+            "  {} - {} (status={})",
+            t.id,
+            t.task_name,
+            t.status.as_str_name()
+        );
     }
 
     Ok(())
@@ -7,7 +7,7 @@
 use std::collections::HashMap;
 
 use tokio::time::{Duration, timeout};
-use valka_sdk::{TaskContext, ValkaClient, ValkaWorker};
+use valka_sdk::{CreateTaskOptions, TaskContext, TaskError, ValkaClient, ValkaWorker};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -18,9 +18,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create task with initial a=4, b=5
     let task = client
         .create_task(
-            "experiments",
-            "signal-demo",
-            Some(serde_json::json!({"a": 4, "b": 5})),
+            CreateTaskOptions::new("experiments", "signal-demo")
+                .input(serde_json::json!({"a": 4, "b": 5})),
         )
         .await?;
     println!("Created task {}", task.id);
@@ -69,7 +68,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn handle_task(mut ctx: TaskContext) -> Result<serde_json::Value, String> {
+async fn handle_task(mut ctx: TaskContext) -> Result<serde_json::Value, TaskError> {
     let input: HashMap<String, f64> = ctx.input().map_err(|e| e.to_string())?;
     let mut a = input["a"] as i64;
     let mut b = input["b"] as i64;
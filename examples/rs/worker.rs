@@ -6,7 +6,7 @@
 //! Requires a running Valka server at http://127.0.0.1:50051.
 //! Run the producer example to enqueue tasks.
 
-use valka_sdk::{TaskContext, ValkaWorker};
+use valka_sdk::{TaskContext, TaskError, ValkaWorker};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -27,17 +27,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn handle_task(ctx: TaskContext) -> Result<serde_json::Value, String> {
+async fn handle_task(ctx: TaskContext) -> Result<serde_json::Value, TaskError> {
     ctx.log(&format!(
         "Processing task rust '{}' (attempt {})",
         ctx.task_name, ctx.attempt_number
     ))
     .await;
 
-    // Parse the input JSON
+    // Malformed input won't parse on a retry either
     let input: serde_json::Value = ctx
         .input()
-        .map_err(|e| format!("Failed to parse input: {e}"))?;
+        .map_err(|e| TaskError::fatal(format!("Failed to parse input: {e}")))?;
 
     let to = input
         .get("to")
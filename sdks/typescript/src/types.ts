@@ -80,6 +80,7 @@ export interface DeadLetter {
   task_name: string;
   input: unknown | null;
   error_message: string | null;
+  worker_id: string | null;
   attempt_count: number;
   metadata: unknown | null;
   created_at: string;
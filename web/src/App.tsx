@@ -8,6 +8,8 @@ import { TaskDetailPage } from "@/pages/task-detail";
 import { WorkersPage } from "@/pages/workers";
 import { EventsPage } from "@/pages/events";
 import { DeadLettersPage } from "@/pages/dead-letters";
+import { QueuesPage } from "@/pages/queues";
+import { QueueDetailPage } from "@/pages/queue-detail";
 
 function App() {
   return (
@@ -18,6 +20,8 @@ function App() {
             <Route path="/" element={<DashboardPage />} />
             <Route path="/tasks" element={<TasksPage />} />
             <Route path="/tasks/:taskId" element={<TaskDetailPage />} />
+            <Route path="/queues" element={<QueuesPage />} />
+            <Route path="/queues/:queueName" element={<QueueDetailPage />} />
             <Route path="/workers" element={<WorkersPage />} />
             <Route path="/events" element={<EventsPage />} />
             <Route path="/dead-letters" element={<DeadLettersPage />} />
@@ -8,24 +8,65 @@ export class ApiError extends Error {
   }
 }
 
-export async function fetchAPI<T>(
-  path: string,
-  options: RequestInit = {},
-): Promise<T> {
-  const response = await fetch(path, {
+const TOKEN_KEY = "valka.apiToken";
+
+/** API token for servers with `[auth]` tokens configured. */
+export function getApiToken(): string | null {
+  return localStorage.getItem(TOKEN_KEY);
+}
+
+export function setApiToken(token: string | null) {
+  if (token) {
+    localStorage.setItem(TOKEN_KEY, token);
+  } else {
+    localStorage.removeItem(TOKEN_KEY);
+  }
+}
+
+/** `EventSource` can't send headers, so streams take the token as a query parameter. */
+export function withAccessToken(path: string): string {
+  const token = getApiToken();
+  if (!token) return path;
+  const sep = path.includes("?") ? "&" : "?";
+  return `${path}${sep}access_token=${encodeURIComponent(token)}`;
+}
+
+function send(path: string, options: RequestInit): Promise<Response> {
+  const token = getApiToken();
+  return fetch(path, {
+    ...options,
     headers: {
       "Content-Type": "application/json",
+      ...(token ? { Authorization: `Bearer ${token}` } : {}),
       ...options.headers,
     },
-    ...options,
   });
+}
+
+export async function fetchAPI<T>(
+  path: string,
+  options: RequestInit = {},
+): Promise<T> {
+  const sentWith = getApiToken();
+  let response = await send(path, options);
+
+  // Ask for a token and retry, unless a concurrent request already did
+  if (response.status === 401) {
+    if (getApiToken() === sentWith) {
+      const token = window.prompt("This server requires an API token");
+      if (token) setApiToken(token.trim());
+    }
+    if (getApiToken() !== sentWith) {
+      response = await send(path, options);
+    }
+  }
 
   if (!response.ok) {
     let message = `Request failed with status ${response.status}`;
     try {
       const body = await response.json();
-      if (body.error) {
-        message = body.error;
+      if (body.error?.message) {
+        message = body.error.message;
       } else if (body.message) {
         message = body.message;
       }
@@ -1,3 +1,4 @@
+import { withAccessToken } from "./client";
 import type { TaskEvent, TaskStatus, RawTaskEvent } from "./types";
 
 // Proto status enum values → string status
@@ -11,6 +12,7 @@ const STATUS_MAP: Record<number, TaskStatus> = {
   6: "RETRY",
   7: "DEAD_LETTER",
   8: "CANCELLED",
+  9: "WAITING",
 };
 
 function parseRawEvent(raw: RawTaskEvent): TaskEvent {
@@ -27,7 +29,7 @@ export function subscribeEvents(
   onEvent: (event: TaskEvent) => void,
   onError?: (error: Event) => void,
 ): () => void {
-  const eventSource = new EventSource("/api/v1/events");
+  const eventSource = new EventSource(withAccessToken("/api/v1/events"));
 
   eventSource.onmessage = (event) => {
     try {
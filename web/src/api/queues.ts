@@ -0,0 +1,47 @@
+import { fetchAPI } from "./client";
+import type { QueueConfig, UpdateQueueConfigRequest } from "./types";
+
+export const queuesApi = {
+  list(ownerTeam?: string): Promise<QueueConfig[]> {
+    const params = ownerTeam
+      ? `?owner_team=${encodeURIComponent(ownerTeam)}`
+      : "";
+    return fetchAPI<QueueConfig[]>(`/api/v1/queues${params}`);
+  },
+
+  get(queueName: string): Promise<QueueConfig> {
+    return fetchAPI<QueueConfig>(
+      `/api/v1/queues/${encodeURIComponent(queueName)}`,
+    );
+  },
+
+  update(
+    queueName: string,
+    request: UpdateQueueConfigRequest,
+  ): Promise<QueueConfig> {
+    return fetchAPI<QueueConfig>(
+      `/api/v1/queues/${encodeURIComponent(queueName)}`,
+      {
+        method: "PUT",
+        body: JSON.stringify(request),
+      },
+    );
+  },
+
+  holdScheduled(queueName: string): Promise<QueueConfig> {
+    return fetchAPI<QueueConfig>(
+      `/api/v1/queues/${encodeURIComponent(queueName)}/hold-scheduled`,
+      { method: "POST" },
+    );
+  },
+
+  releaseScheduled(queueName: string, maxPerTick?: number): Promise<QueueConfig> {
+    return fetchAPI<QueueConfig>(
+      `/api/v1/queues/${encodeURIComponent(queueName)}/release-scheduled`,
+      {
+        method: "POST",
+        body: JSON.stringify({ max_per_tick: maxPerTick }),
+      },
+    );
+  },
+};
@@ -0,0 +1,8 @@
+import { fetchAPI } from "./client";
+import type { Stats } from "./types";
+
+export const statsApi = {
+  get(): Promise<Stats> {
+    return fetchAPI<Stats>("/api/v1/stats");
+  },
+};
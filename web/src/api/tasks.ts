@@ -1,4 +1,4 @@
-import { fetchAPI } from "./client";
+import { fetchAPI, withAccessToken } from "./client";
 import type {
   Task,
   TaskRun,
@@ -8,6 +8,7 @@ import type {
   SendSignalRequest,
   SendSignalResponse,
   ListTasksParams,
+  SearchTasksParams,
   DeadLetter,
   ListDeadLettersParams,
 } from "./types";
@@ -26,6 +27,17 @@ export const tasksApi = {
     return fetchAPI<Task[]>(`/api/v1/tasks${query ? `?${query}` : ""}`);
   },
 
+  search(params: SearchTasksParams = {}): Promise<Task[]> {
+    const searchParams = new URLSearchParams();
+    for (const [key, value] of Object.entries(params)) {
+      if (value !== undefined && value !== "")
+        searchParams.set(key, String(value));
+    }
+
+    const query = searchParams.toString();
+    return fetchAPI<Task[]>(`/api/v1/tasks/search${query ? `?${query}` : ""}`);
+  },
+
   get(taskId: string): Promise<Task> {
     return fetchAPI<Task>(`/api/v1/tasks/${taskId}`);
   },
@@ -91,3 +103,40 @@ export const tasksApi = {
     );
   },
 };
+
+/**
+ * Tail a run's logs: persisted entries first, then live ones. `onEnd` gets
+ * the run status once the run is over and the stream closes; reconnects
+ * resume after the last received entry.
+ */
+export function subscribeRunLogs(
+  taskId: string,
+  runId: string,
+  onLog: (log: TaskLog) => void,
+  onEnd?: (status: string) => void,
+): () => void {
+  const eventSource = new EventSource(
+    withAccessToken(`/api/v1/tasks/${taskId}/runs/${runId}/logs/stream`),
+  );
+
+  eventSource.onmessage = (event) => {
+    try {
+      onLog(JSON.parse(event.data) as TaskLog);
+    } catch {
+      // Ignore parse errors for malformed entries
+    }
+  };
+
+  eventSource.addEventListener("end", (event) => {
+    eventSource.close();
+    try {
+      onEnd?.((JSON.parse((event as MessageEvent).data) as { status: string }).status);
+    } catch {
+      onEnd?.("");
+    }
+  });
+
+  return () => {
+    eventSource.close();
+  };
+}
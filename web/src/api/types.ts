@@ -6,7 +6,8 @@ export type TaskStatus =
   | "FAILED"
   | "RETRY"
   | "DEAD_LETTER"
-  | "CANCELLED";
+  | "CANCELLED"
+  | "WAITING";
 
 export interface Task {
   id: string;
@@ -23,8 +24,28 @@ export interface Task {
   output: Record<string, unknown> | null;
   error_message: string | null;
   scheduled_at: string | null;
+  deadline_ms: number | null;
+  deadline_expired: boolean;
   created_at: string;
   updated_at: string;
+  /** Only on task detail, for RETRY tasks with attempts left */
+  retry_plan?: RetryPlan;
+  /** Only on task detail: the tasks this one waits on */
+  dependencies?: TaskDependency[];
+  /** Only on task detail: dependencies not yet COMPLETED */
+  unmet_dependencies?: number;
+}
+
+export interface TaskDependency {
+  task_id: string;
+  status: TaskStatus;
+}
+
+export interface RetryPlan {
+  next_attempt_at: string | null;
+  attempts_remaining: number;
+  schedule: string[];
+  dead_letter: boolean;
 }
 
 export interface TaskRun {
@@ -40,6 +61,8 @@ export interface TaskRun {
   completed_at: string | null;
   lease_expires_at: string;
   last_heartbeat: string;
+  progress: number | null;
+  progress_message: string | null;
 }
 
 export interface TaskLog {
@@ -51,6 +74,25 @@ export interface TaskLog {
   metadata: Record<string, unknown> | null;
 }
 
+export interface QueueStats {
+  queue_name: string;
+  total: number;
+  by_status: Partial<Record<TaskStatus, number>>;
+}
+
+export interface Stats {
+  total_tasks: number;
+  by_status: Partial<Record<TaskStatus, number>>;
+  /** Largest queue first */
+  by_queue: QueueStats[];
+  /** Connected to the node that answered, not draining */
+  active_workers: number;
+  dead_letters: number;
+  created_last_hour: number;
+  completed_last_hour: number;
+  generated_at: string;
+}
+
 export interface Worker {
   id: string;
   name: string;
@@ -60,6 +102,8 @@ export interface Worker {
   status: string;
   last_heartbeat: string;
   connected_at: string;
+  version: string;
+  excluded_queues: { queue: string; reason: string }[];
 }
 
 export interface DeadLetter {
@@ -68,10 +112,38 @@ export interface DeadLetter {
   queue_name: string;
   task_name: string;
   error_message: string | null;
+  worker_id: string | null;
   created_at: string;
   attempt_count: number;
   input: Record<string, unknown> | null;
   metadata: Record<string, unknown> | null;
+  owner: QueueOwner | null;
+}
+
+export interface QueueOwner {
+  owner_team: string | null;
+  contact: string | null;
+  runbook_url: string | null;
+  labels: Record<string, string>;
+}
+
+export interface QueueConfig extends QueueOwner {
+  queue_name: string;
+  min_worker_version: string | null;
+  scheduled_hold: boolean;
+  release_max_per_tick: number | null;
+  /** Only on the detail endpoint: due scheduled tasks waiting on a hold or ramp */
+  held_past_due?: number;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface UpdateQueueConfigRequest {
+  owner_team?: string;
+  contact?: string;
+  runbook_url?: string;
+  labels?: Record<string, string>;
+  min_worker_version?: string;
 }
 
 // Raw SSE event from backend (numeric status)
@@ -111,6 +183,27 @@ export interface ListTasksParams {
   offset?: number;
 }
 
+export type TaskOrder = "created_desc" | "created_asc" | "priority";
+
+export interface TaskSearchFilters {
+  queue_name?: string;
+  /** One status or several, comma-separated (e.g. "FAILED,RETRY") */
+  status?: string;
+  task_name_prefix?: string;
+  /** RFC 3339, inclusive */
+  created_after?: string;
+  /** RFC 3339, exclusive */
+  created_before?: string;
+  idempotency_key?: string;
+  error_contains?: string;
+  order?: TaskOrder;
+}
+
+export interface SearchTasksParams extends TaskSearchFilters {
+  limit?: number;
+  offset?: number;
+}
+
 export interface ListDeadLettersParams {
   queue_name?: string;
   limit?: number;
@@ -1,5 +1,5 @@
 import { Inbox } from "lucide-react";
-import type { Task } from "@/api/types";
+import type { QueueStats as QueueStatsResponse } from "@/api/types";
 import {
   Card,
   CardHeader,
@@ -11,7 +11,7 @@ import { Skeleton } from "@/components/ui/skeleton";
 import { Separator } from "@/components/ui/separator";
 
 interface QueueOverviewProps {
-  tasks: Task[];
+  queues?: QueueStatsResponse[];
   isLoading: boolean;
 }
 
@@ -113,34 +113,19 @@ function QueueOverviewSkeleton() {
   );
 }
 
-export function QueueOverview({ tasks, isLoading }: QueueOverviewProps) {
-  if (isLoading) {
+export function QueueOverview({ queues: counts, isLoading }: QueueOverviewProps) {
+  if (isLoading || !counts) {
     return <QueueOverviewSkeleton />;
   }
 
-  const queueMap = new Map<string, QueueStats>();
-
-  for (const task of tasks) {
-    const existing = queueMap.get(task.queue_name);
-    if (existing) {
-      existing.total++;
-      if (task.status === "PENDING") existing.pending++;
-      if (task.status === "RUNNING") existing.running++;
-      if (task.status === "COMPLETED") existing.completed++;
-      if (task.status === "FAILED" || task.status === "DEAD_LETTER") existing.failed++;
-    } else {
-      queueMap.set(task.queue_name, {
-        name: task.queue_name,
-        total: 1,
-        pending: task.status === "PENDING" ? 1 : 0,
-        running: task.status === "RUNNING" ? 1 : 0,
-        completed: task.status === "COMPLETED" ? 1 : 0,
-        failed: task.status === "FAILED" || task.status === "DEAD_LETTER" ? 1 : 0,
-      });
-    }
-  }
-
-  const queues = Array.from(queueMap.values()).sort((a, b) => b.total - a.total);
+  const queues: QueueStats[] = counts.map(({ queue_name, total, by_status }) => ({
+    name: queue_name,
+    total,
+    pending: by_status.PENDING ?? 0,
+    running: by_status.RUNNING ?? 0,
+    completed: by_status.COMPLETED ?? 0,
+    failed: (by_status.FAILED ?? 0) + (by_status.DEAD_LETTER ?? 0),
+  }));
 
   return (
     <Card>
@@ -5,7 +5,7 @@ import {
   CheckCircle,
   XCircle,
 } from "lucide-react";
-import type { Task } from "@/api/types";
+import type { Stats } from "@/api/types";
 import { cn } from "@/lib/utils";
 import {
   Card,
@@ -14,7 +14,7 @@ import {
 import { Skeleton } from "@/components/ui/skeleton";
 
 interface StatsCardsProps {
-  tasks: Task[];
+  stats?: Stats;
   isLoading: boolean;
 }
 
@@ -26,7 +26,7 @@ interface StatCardProps {
   iconBg: string;
 }
 
-const stats = [
+const statCards = [
   {
     key: "total",
     title: "Total Tasks",
@@ -94,8 +94,8 @@ function StatCardSkeleton() {
   );
 }
 
-export function StatsCards({ tasks, isLoading }: StatsCardsProps) {
-  if (isLoading) {
+export function StatsCards({ stats, isLoading }: StatsCardsProps) {
+  if (isLoading || !stats) {
     return (
       <div className="grid grid-cols-2 gap-4 lg:grid-cols-5">
         {Array.from({ length: 5 }).map((_, i) => (
@@ -105,19 +105,18 @@ export function StatsCards({ tasks, isLoading }: StatsCardsProps) {
     );
   }
 
+  const byStatus = stats.by_status;
   const counts: Record<string, number> = {
-    total: tasks.length,
-    pending: tasks.filter((t) => t.status === "PENDING").length,
-    running: tasks.filter((t) => t.status === "RUNNING").length,
-    completed: tasks.filter((t) => t.status === "COMPLETED").length,
-    failed: tasks.filter(
-      (t) => t.status === "FAILED" || t.status === "DEAD_LETTER"
-    ).length,
+    total: stats.total_tasks,
+    pending: byStatus.PENDING ?? 0,
+    running: byStatus.RUNNING ?? 0,
+    completed: byStatus.COMPLETED ?? 0,
+    failed: (byStatus.FAILED ?? 0) + (byStatus.DEAD_LETTER ?? 0),
   };
 
   return (
     <div className="grid grid-cols-2 gap-4 lg:grid-cols-5">
-      {stats.map((stat) => (
+      {statCards.map((stat) => (
         <StatCard
           key={stat.key}
           title={stat.title}
@@ -5,6 +5,7 @@ import {
   Users,
   Activity,
   AlertTriangle,
+  Layers,
 } from "lucide-react";
 import { cn } from "@/lib/utils";
 import { Badge } from "@/components/ui/badge";
@@ -13,6 +14,7 @@ import { Separator } from "@/components/ui/separator";
 const navigation = [
   { name: "Dashboard", href: "/", icon: LayoutDashboard },
   { name: "Tasks", href: "/tasks", icon: ListTodo },
+  { name: "Queues", href: "/queues", icon: Layers },
   { name: "Workers", href: "/workers", icon: Users },
   { name: "Events", href: "/events", icon: Activity },
   { name: "Dead Letters", href: "/dead-letters", icon: AlertTriangle },
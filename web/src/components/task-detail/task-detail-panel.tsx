@@ -6,6 +6,7 @@ import {
   Timer,
   Key,
   Calendar,
+  Link,
 } from "lucide-react";
 import type { Task } from "@/api/types";
 import { formatDate } from "@/lib/utils";
@@ -137,6 +138,71 @@ export function TaskDetailPanel({ task }: TaskDetailPanelProps) {
         </CardContent>
       </Card>
 
+      {/* Retry Plan */}
+      {task.retry_plan && (
+        <Card>
+          <CardHeader className="pb-0">
+            <CardTitle className="text-xs font-semibold uppercase tracking-wider text-muted-foreground">
+              Retry Plan
+            </CardTitle>
+          </CardHeader>
+          <CardContent>
+            <div className="divide-y divide-border">
+              <DetailRow
+                icon={Calendar}
+                label="Next Attempt"
+                value={
+                  task.retry_plan.next_attempt_at
+                    ? formatDate(task.retry_plan.next_attempt_at)
+                    : "None (deadline first)"
+                }
+              />
+              <DetailRow
+                icon={RefreshCw}
+                label="Attempts Left"
+                value={task.retry_plan.attempts_remaining}
+              />
+              {task.retry_plan.schedule.map((at, i) => (
+                <DetailRow
+                  key={at}
+                  icon={Clock}
+                  label={`Attempt ${task.attempt_count + i + 1}`}
+                  value={formatDate(at)}
+                />
+              ))}
+              <DetailRow
+                icon={XCircle}
+                label="Then"
+                value={task.retry_plan.dead_letter ? "Dead letter queue" : "Failed (deadline)"}
+              />
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
+      {/* Dependencies */}
+      {task.dependencies && task.dependencies.length > 0 && (
+        <Card>
+          <CardHeader className="pb-0">
+            <CardTitle className="text-xs font-semibold uppercase tracking-wider text-muted-foreground">
+              Depends On ({task.unmet_dependencies ?? 0} unmet)
+            </CardTitle>
+          </CardHeader>
+          <CardContent>
+            <div className="divide-y divide-border">
+              {task.dependencies.map((dep) => (
+                <DetailRow
+                  key={dep.task_id}
+                  icon={Link}
+                  label={dep.task_id.slice(0, 8)}
+                  value={<TaskStatusBadge status={dep.status} />}
+                />
+              ))}
+            </div>
+          </CardContent>
+        </Card>
+      )}
+
       {/* Error Section */}
       {task.error_message && (
         <Card className="border-destructive/30 bg-destructive/5">
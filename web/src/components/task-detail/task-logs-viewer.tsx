@@ -1,6 +1,6 @@
 import { useEffect, useRef } from "react";
 import { Terminal } from "lucide-react";
-import { useTaskRunLogs } from "@/hooks/use-tasks";
+import { useTaskRunLogStream } from "@/hooks/use-tasks";
 import { cn } from "@/lib/utils";
 import { ScrollArea } from "@/components/ui/scroll-area";
 import { Badge } from "@/components/ui/badge";
@@ -37,7 +37,7 @@ function formatTimestamp(timestampMs: number): string {
 }
 
 export function TaskLogsViewer({ taskId, runId }: TaskLogsViewerProps) {
-  const { data: logs, isLoading } = useTaskRunLogs(taskId, runId);
+  const { data: logs, isLoading, live } = useTaskRunLogStream(taskId, runId);
   const bottomRef = useRef<HTMLDivElement>(null);
 
   useEffect(() => {
@@ -61,7 +61,9 @@ export function TaskLogsViewer({ taskId, runId }: TaskLogsViewerProps) {
     return (
       <div className="flex h-48 flex-col items-center justify-center rounded-lg border border-border bg-black">
         <Terminal className="h-8 w-8 text-muted-foreground/40" />
-        <p className="mt-2 text-sm text-muted-foreground">No logs available</p>
+        <p className="mt-2 text-sm text-muted-foreground">
+          {live ? "Waiting for logs..." : "No logs available"}
+        </p>
       </div>
     );
   }
@@ -86,6 +88,9 @@ export function TaskLogsViewer({ taskId, runId }: TaskLogsViewerProps) {
             <span className="text-zinc-300">{log.message}</span>
           </div>
         ))}
+        {live && (
+          <div className="py-0.5 leading-5 text-zinc-600">Streaming...</div>
+        )}
         <div ref={bottomRef} />
       </div>
     </ScrollArea>
@@ -19,6 +19,21 @@ interface TaskRunsTableProps {
   onSelectRun: (runId: string) => void;
 }
 
+function RunProgress({ run }: { run: TaskRun }) {
+  if (run.progress == null) {
+    return <span className="text-xs text-muted-foreground">--</span>;
+  }
+  const pct = Math.round(run.progress);
+  return (
+    <div className="flex items-center gap-2" title={run.progress_message ?? undefined}>
+      <div className="h-1.5 w-16 overflow-hidden rounded-full bg-muted">
+        <div className="h-full rounded-full bg-primary" style={{ width: `${pct}%` }} />
+      </div>
+      <span className="text-xs tabular-nums text-muted-foreground">{pct}%</span>
+    </div>
+  );
+}
+
 function SkeletonRows() {
   return (
     <>
@@ -27,6 +42,7 @@ function SkeletonRows() {
           <TableCell><Skeleton className="h-4 w-16" /></TableCell>
           <TableCell><Skeleton className="h-4 w-8" /></TableCell>
           <TableCell><Skeleton className="h-5 w-20 rounded-full" /></TableCell>
+          <TableCell><Skeleton className="h-4 w-24" /></TableCell>
           <TableCell><Skeleton className="h-4 w-16" /></TableCell>
           <TableCell><Skeleton className="h-4 w-28" /></TableCell>
           <TableCell><Skeleton className="h-4 w-28" /></TableCell>
@@ -66,6 +82,9 @@ export function TaskRunsTable({
             <TableHead className="px-4 text-xs uppercase tracking-wider text-muted-foreground">
               Status
             </TableHead>
+            <TableHead className="px-4 text-xs uppercase tracking-wider text-muted-foreground">
+              Progress
+            </TableHead>
             <TableHead className="px-4 text-xs uppercase tracking-wider text-muted-foreground">
               Worker
             </TableHead>
@@ -101,6 +120,9 @@ export function TaskRunsTable({
                 <TableCell className="px-4">
                   <TaskStatusBadge status={run.status} />
                 </TableCell>
+                <TableCell className="px-4">
+                  <RunProgress run={run} />
+                </TableCell>
                 <TableCell className="px-4 font-mono text-xs text-muted-foreground">
                   {run.worker_id ? truncateId(run.worker_id) : "--"}
                 </TableCell>
@@ -1,8 +1,15 @@
 import { useState } from "react";
-import { Search } from "lucide-react";
+import { ChevronDown, Search, X } from "lucide-react";
+import type { TaskOrder, TaskSearchFilters } from "@/api/types";
 import { STATUS_OPTIONS } from "@/lib/utils";
 import { Input } from "@/components/ui/input";
 import { Button } from "@/components/ui/button";
+import {
+  DropdownMenu,
+  DropdownMenuCheckboxItem,
+  DropdownMenuContent,
+  DropdownMenuTrigger,
+} from "@/components/ui/dropdown-menu";
 import {
   Select,
   SelectContent,
@@ -11,54 +18,178 @@ import {
   SelectValue,
 } from "@/components/ui/select";
 
+const ORDER_OPTIONS: { value: TaskOrder; label: string }[] = [
+  { value: "created_desc", label: "Newest first" },
+  { value: "created_asc", label: "Oldest first" },
+  { value: "priority", label: "Priority" },
+];
+
+/** RFC 3339 to the value of a datetime-local input, in local time. */
+function toLocalInput(iso?: string): string {
+  if (!iso) return "";
+  const date = new Date(iso);
+  if (Number.isNaN(date.getTime())) return "";
+  const offset = date.getTimezoneOffset() * 60_000;
+  return new Date(date.getTime() - offset).toISOString().slice(0, 16);
+}
+
+function fromLocalInput(value: string): string | undefined {
+  return value ? new Date(value).toISOString() : undefined;
+}
+
 interface TaskFiltersProps {
-  onFilter: (params: { queue_name?: string; status?: string }) => void;
-  initialQueue?: string;
-  initialStatus?: string;
+  onFilter: (filters: TaskSearchFilters) => void;
+  initial?: TaskSearchFilters;
 }
 
-export function TaskFilters({
-  onFilter,
-  initialQueue = "",
-  initialStatus = "",
-}: TaskFiltersProps) {
-  const [queueName, setQueueName] = useState(initialQueue);
-  const [status, setStatus] = useState(initialStatus);
+export function TaskFilters({ onFilter, initial = {} }: TaskFiltersProps) {
+  const [queueName, setQueueName] = useState(initial.queue_name ?? "");
+  const [statuses, setStatuses] = useState<string[]>(
+    initial.status ? initial.status.split(",") : [],
+  );
+  const [namePrefix, setNamePrefix] = useState(initial.task_name_prefix ?? "");
+  const [createdAfter, setCreatedAfter] = useState(
+    toLocalInput(initial.created_after),
+  );
+  const [createdBefore, setCreatedBefore] = useState(
+    toLocalInput(initial.created_before),
+  );
+  const [idempotencyKey, setIdempotencyKey] = useState(
+    initial.idempotency_key ?? "",
+  );
+  const [errorText, setErrorText] = useState(initial.error_contains ?? "");
+  const [order, setOrder] = useState<TaskOrder>(
+    initial.order ?? "created_desc",
+  );
+
+  function toggleStatus(status: string, checked: boolean) {
+    setStatuses((prev) =>
+      checked ? [...prev, status] : prev.filter((s) => s !== status),
+    );
+  }
 
   function handleSubmit(e: React.FormEvent) {
     e.preventDefault();
-    const resolvedStatus = status && status !== "__all__" ? status : undefined;
     onFilter({
       queue_name: queueName || undefined,
-      status: resolvedStatus,
+      status: statuses.length ? statuses.join(",") : undefined,
+      task_name_prefix: namePrefix || undefined,
+      created_after: fromLocalInput(createdAfter),
+      created_before: fromLocalInput(createdBefore),
+      idempotency_key: idempotencyKey || undefined,
+      error_contains: errorText || undefined,
+      order: order === "created_desc" ? undefined : order,
     });
   }
 
+  function handleReset() {
+    setQueueName("");
+    setStatuses([]);
+    setNamePrefix("");
+    setCreatedAfter("");
+    setCreatedBefore("");
+    setIdempotencyKey("");
+    setErrorText("");
+    setOrder("created_desc");
+    onFilter({});
+  }
+
+  const statusLabel =
+    statuses.length === 0
+      ? "All Statuses"
+      : statuses.length === 1
+        ? STATUS_OPTIONS.find((o) => o.value === statuses[0])?.label ??
+          statuses[0]
+        : `${statuses.length} statuses`;
+
   return (
-    <form onSubmit={handleSubmit} className="flex items-center gap-3">
-      <Input
-        type="text"
-        placeholder="Filter by queue..."
-        value={queueName}
-        onChange={(e) => setQueueName(e.target.value)}
-        className="w-48"
-      />
-      <Select value={status} onValueChange={setStatus}>
-        <SelectTrigger className="w-44">
-          <SelectValue placeholder="All Statuses" />
-        </SelectTrigger>
-        <SelectContent>
-          {STATUS_OPTIONS.map((opt) => (
-            <SelectItem key={opt.value} value={opt.value || "__all__"}>
-              {opt.label}
-            </SelectItem>
-          ))}
-        </SelectContent>
-      </Select>
-      <Button type="submit" variant="outline" size="default">
-        <Search className="h-4 w-4" />
-        Filter
-      </Button>
+    <form onSubmit={handleSubmit} className="space-y-3">
+      <div className="flex flex-wrap items-center gap-3">
+        <Input
+          type="text"
+          placeholder="Filter by queue..."
+          value={queueName}
+          onChange={(e) => setQueueName(e.target.value)}
+          className="w-48"
+        />
+        <Input
+          type="text"
+          placeholder="Task name prefix..."
+          value={namePrefix}
+          onChange={(e) => setNamePrefix(e.target.value)}
+          className="w-48"
+        />
+        <DropdownMenu>
+          <DropdownMenuTrigger asChild>
+            <Button type="button" variant="outline" className="w-44 justify-between">
+              {statusLabel}
+              <ChevronDown className="h-4 w-4 opacity-50" />
+            </Button>
+          </DropdownMenuTrigger>
+          <DropdownMenuContent align="start">
+            {STATUS_OPTIONS.filter((opt) => opt.value).map((opt) => (
+              <DropdownMenuCheckboxItem
+                key={opt.value}
+                checked={statuses.includes(opt.value)}
+                onCheckedChange={(checked) => toggleStatus(opt.value, checked)}
+                onSelect={(e) => e.preventDefault()}
+              >
+                {opt.label}
+              </DropdownMenuCheckboxItem>
+            ))}
+          </DropdownMenuContent>
+        </DropdownMenu>
+        <Select value={order} onValueChange={(v) => setOrder(v as TaskOrder)}>
+          <SelectTrigger className="w-40">
+            <SelectValue />
+          </SelectTrigger>
+          <SelectContent>
+            {ORDER_OPTIONS.map((opt) => (
+              <SelectItem key={opt.value} value={opt.value}>
+                {opt.label}
+              </SelectItem>
+            ))}
+          </SelectContent>
+        </Select>
+      </div>
+      <div className="flex flex-wrap items-center gap-3">
+        <Input
+          type="datetime-local"
+          title="Created after"
+          value={createdAfter}
+          onChange={(e) => setCreatedAfter(e.target.value)}
+          className="w-52"
+        />
+        <Input
+          type="datetime-local"
+          title="Created before"
+          value={createdBefore}
+          onChange={(e) => setCreatedBefore(e.target.value)}
+          className="w-52"
+        />
+        <Input
+          type="text"
+          placeholder="Idempotency key..."
+          value={idempotencyKey}
+          onChange={(e) => setIdempotencyKey(e.target.value)}
+          className="w-48"
+        />
+        <Input
+          type="text"
+          placeholder="Error contains..."
+          value={errorText}
+          onChange={(e) => setErrorText(e.target.value)}
+          className="w-56"
+        />
+        <Button type="submit" variant="outline" size="default">
+          <Search className="h-4 w-4" />
+          Search
+        </Button>
+        <Button type="button" variant="ghost" size="default" onClick={handleReset}>
+          <X className="h-4 w-4" />
+          Reset
+        </Button>
+      </div>
     </form>
   );
 }
@@ -153,6 +153,7 @@ export function WorkerTable({ workers, isLoading }: WorkerTableProps) {
                   <p className="text-sm font-medium text-foreground">{worker.name}</p>
                   <p className="font-mono text-xs text-muted-foreground">
                     {truncateId(worker.id)}
+                    {worker.version && <span> · v{worker.version}</span>}
                   </p>
                 </div>
               </TableCell>
@@ -169,11 +170,22 @@ export function WorkerTable({ workers, isLoading }: WorkerTableProps) {
               </TableCell>
               <TableCell>
                 <div className="flex flex-wrap gap-1">
-                  {worker.queues.map((queue) => (
-                    <Badge key={queue} variant="secondary" className="text-xs font-normal">
-                      {queue}
-                    </Badge>
-                  ))}
+                  {worker.queues.map((queue) => {
+                    const excluded = worker.excluded_queues?.find((e) => e.queue === queue);
+                    return (
+                      <Badge
+                        key={queue}
+                        variant={excluded ? "outline" : "secondary"}
+                        className={cn(
+                          "text-xs font-normal",
+                          excluded && "text-muted-foreground line-through",
+                        )}
+                        title={excluded ? `Excluded: ${excluded.reason}` : undefined}
+                      >
+                        {queue}
+                      </Badge>
+                    );
+                  })}
                 </div>
               </TableCell>
               <TableCell className="text-foreground">{worker.concurrency}</TableCell>
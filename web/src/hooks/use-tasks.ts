@@ -1,7 +1,10 @@
+import { useEffect, useState } from "react";
 import { useQuery, useMutation, useQueryClient } from "@tanstack/react-query";
-import { tasksApi } from "@/api/tasks";
+import { subscribeRunLogs, tasksApi } from "@/api/tasks";
 import type {
+  TaskLog,
   ListTasksParams,
+  SearchTasksParams,
   CreateTaskRequest,
   SendSignalRequest,
   ListDeadLettersParams,
@@ -14,6 +17,13 @@ export function useTasks(params: ListTasksParams = {}) {
   });
 }
 
+export function useTaskSearch(params: SearchTasksParams = {}) {
+  return useQuery({
+    queryKey: ["tasks", "search", params],
+    queryFn: () => tasksApi.search(params),
+  });
+}
+
 export function useTask(taskId: string) {
   return useQuery({
     queryKey: ["tasks", taskId],
@@ -69,6 +79,9 @@ export function useTaskRuns(taskId: string) {
     queryKey: ["tasks", taskId, "runs"],
     queryFn: () => tasksApi.getRuns(taskId),
     enabled: !!taskId,
+    // Keep progress of a running attempt fresh
+    refetchInterval: (query) =>
+      query.state.data?.some((run) => run.status === "RUNNING") ? 2_000 : false,
   });
 }
 
@@ -81,6 +94,44 @@ export function useTaskRunLogs(taskId: string, runId: string) {
   });
 }
 
+/** Live tail of a run's logs; `live` is false once the run has ended. */
+export function useTaskRunLogStream(taskId: string, runId: string) {
+  const [logs, setLogs] = useState<TaskLog[]>([]);
+  const [isLoading, setIsLoading] = useState(true);
+  const [live, setLive] = useState(true);
+
+  useEffect(() => {
+    if (!taskId || !runId) return;
+    setLogs([]);
+    setIsLoading(true);
+    setLive(true);
+
+    const seen = new Set<number>();
+    const timer = setTimeout(() => setIsLoading(false), 500);
+    const unsubscribe = subscribeRunLogs(
+      taskId,
+      runId,
+      (log) => {
+        if (seen.has(log.id)) return;
+        seen.add(log.id);
+        setIsLoading(false);
+        setLogs((prev) => [...prev, log]);
+      },
+      () => {
+        setIsLoading(false);
+        setLive(false);
+      },
+    );
+
+    return () => {
+      clearTimeout(timer);
+      unsubscribe();
+    };
+  }, [taskId, runId]);
+
+  return { data: logs, isLoading, live };
+}
+
 export function useTaskSignals(taskId: string) {
   return useQuery({
     queryKey: ["tasks", taskId, "signals"],
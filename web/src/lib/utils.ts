@@ -28,6 +28,7 @@ export const STATUS_OPTIONS = [
   { value: "RETRY", label: "Retry" },
   { value: "DEAD_LETTER", label: "Dead Letter" },
   { value: "CANCELLED", label: "Cancelled" },
+  { value: "WAITING", label: "Waiting" },
 ] as const;
 
 export function statusColor(status: string): string {
@@ -48,6 +49,8 @@ export function statusColor(status: string): string {
       return "bg-rose-500/10 text-rose-400 border-rose-500/20";
     case "CANCELLED":
       return "bg-neutral-500/10 text-neutral-400 border-neutral-500/20";
+    case "WAITING":
+      return "bg-violet-500/10 text-violet-400 border-violet-500/20";
     default:
       return "bg-zinc-500/10 text-zinc-400 border-zinc-500/20";
   }
@@ -71,6 +74,8 @@ export function statusDotColor(status: string): string {
       return "bg-rose-400";
     case "CANCELLED":
       return "bg-neutral-400";
+    case "WAITING":
+      return "bg-violet-400";
     default:
       return "bg-zinc-400";
   }
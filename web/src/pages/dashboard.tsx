@@ -1,11 +1,29 @@
-import { useTasks } from "@/hooks/use-tasks";
+import { useQuery } from "@tanstack/react-query";
+import { statsApi } from "@/api/stats";
+import type { Stats } from "@/api/types";
 import { useEvents } from "@/hooks/use-events";
 import { StatsCards } from "@/components/dashboard/stats-cards";
 import { QueueOverview } from "@/components/dashboard/queue-overview";
 import { EventStream } from "@/components/events/event-stream";
 
+function plural(count: number, noun: string): string {
+  return `${count.toLocaleString()} ${noun}${count !== 1 ? "s" : ""}`;
+}
+
+function activitySummary(stats: Stats): string {
+  return [
+    `${stats.created_last_hour.toLocaleString()} created and ${stats.completed_last_hour.toLocaleString()} completed in the last hour`,
+    plural(stats.active_workers, "active worker"),
+    plural(stats.dead_letters, "dead letter"),
+  ].join(" · ");
+}
+
 export function DashboardPage() {
-  const { data: tasks = [], isLoading } = useTasks({ limit: 500 });
+  const { data: stats, isLoading } = useQuery({
+    queryKey: ["stats"],
+    queryFn: statsApi.get,
+    refetchInterval: 5_000,
+  });
   const { events, connected, clear } = useEvents();
 
   return (
@@ -14,13 +32,14 @@ export function DashboardPage() {
         <h1 className="text-2xl font-semibold tracking-tight text-foreground">Dashboard</h1>
         <p className="mt-1 text-sm text-muted-foreground">
           Overview of your task queue system
+          {stats && ` · ${activitySummary(stats)}`}
         </p>
       </div>
 
-      <StatsCards tasks={tasks} isLoading={isLoading} />
+      <StatsCards stats={stats} isLoading={isLoading} />
 
       <div className="grid gap-6 lg:grid-cols-2">
-        <QueueOverview tasks={tasks} isLoading={isLoading} />
+        <QueueOverview queues={stats?.by_queue} isLoading={isLoading} />
         <EventStream
           events={events.slice(0, 50)}
           connected={connected}
@@ -22,6 +22,26 @@ function DeadLetterExpandedRow({ dl }: { dl: DeadLetter }) {
   return (
     <TableRow className="hover:bg-transparent">
       <TableCell colSpan={7} className="bg-muted/30 px-8 py-4">
+        {dl.owner && (
+          <div className="mb-4 flex flex-wrap items-center gap-x-6 gap-y-1 text-xs">
+            <span className="text-muted-foreground">
+              Owner: <span className="font-medium text-foreground">{dl.owner.owner_team || "--"}</span>
+            </span>
+            <span className="text-muted-foreground">
+              Contact: <span className="font-medium text-foreground">{dl.owner.contact || "--"}</span>
+            </span>
+            {dl.owner.runbook_url && (
+              <a
+                href={dl.owner.runbook_url}
+                target="_blank"
+                rel="noreferrer"
+                className="text-primary hover:underline"
+              >
+                Runbook
+              </a>
+            )}
+          </div>
+        )}
         <div className="grid gap-4 lg:grid-cols-2">
           <div>
             <p className="mb-2 text-xs font-medium uppercase tracking-wider text-muted-foreground">Input</p>
@@ -0,0 +1,127 @@
+import { useParams, Link } from "react-router-dom";
+import { useQuery } from "@tanstack/react-query";
+import { ArrowLeft, BookOpen, Mail, ShieldCheck, Users } from "lucide-react";
+import { queuesApi } from "@/api/queues";
+import { formatDate } from "@/lib/utils";
+import { Card, CardContent } from "@/components/ui/card";
+import { Badge } from "@/components/ui/badge";
+
+export function QueueDetailPage() {
+  const { queueName = "" } = useParams<{ queueName: string }>();
+
+  const { data: queue, isLoading, error } = useQuery({
+    queryKey: ["queues", "detail", queueName],
+    queryFn: () => queuesApi.get(queueName),
+    enabled: !!queueName,
+    retry: false,
+  });
+
+  const labels = Object.entries(queue?.labels ?? {});
+
+  return (
+    <div className="space-y-6">
+      <div>
+        <Link
+          to="/queues"
+          className="mb-3 inline-flex items-center gap-1 text-xs text-muted-foreground hover:text-foreground"
+        >
+          <ArrowLeft className="h-3 w-3" />
+          Queues
+        </Link>
+        <h1 className="text-2xl font-semibold tracking-tight text-foreground">
+          {queueName}
+        </h1>
+      </div>
+
+      {isLoading ? (
+        <div className="flex h-32 items-center justify-center text-sm text-muted-foreground">
+          Loading...
+        </div>
+      ) : error || !queue ? (
+        <div className="flex h-32 items-center justify-center rounded-lg border text-sm text-muted-foreground">
+          No ownership configured for this queue
+        </div>
+      ) : (
+        <>
+          <div className="grid grid-cols-4 gap-4">
+            <Card className="gap-0 py-0">
+              <CardContent className="p-5">
+                <p className="flex items-center gap-1.5 text-sm text-muted-foreground">
+                  <Users className="h-3.5 w-3.5" /> Owner Team
+                </p>
+                <p className="mt-1 text-xl font-semibold tracking-tight text-foreground">
+                  {queue.owner_team || "--"}
+                </p>
+              </CardContent>
+            </Card>
+            <Card className="gap-0 py-0">
+              <CardContent className="p-5">
+                <p className="flex items-center gap-1.5 text-sm text-muted-foreground">
+                  <Mail className="h-3.5 w-3.5" /> Contact
+                </p>
+                <p className="mt-1 text-xl font-semibold tracking-tight text-foreground">
+                  {queue.contact || "--"}
+                </p>
+              </CardContent>
+            </Card>
+            <Card className="gap-0 py-0">
+              <CardContent className="p-5">
+                <p className="flex items-center gap-1.5 text-sm text-muted-foreground">
+                  <BookOpen className="h-3.5 w-3.5" /> Runbook
+                </p>
+                {queue.runbook_url ? (
+                  <a
+                    href={queue.runbook_url}
+                    target="_blank"
+                    rel="noreferrer"
+                    className="mt-1 block truncate text-sm text-primary hover:underline"
+                  >
+                    {queue.runbook_url}
+                  </a>
+                ) : (
+                  <p className="mt-1 text-xl font-semibold text-foreground">--</p>
+                )}
+              </CardContent>
+            </Card>
+            <Card className="gap-0 py-0">
+              <CardContent className="p-5">
+                <p className="flex items-center gap-1.5 text-sm text-muted-foreground">
+                  <ShieldCheck className="h-3.5 w-3.5" /> Min Worker Version
+                </p>
+                <p className="mt-1 font-mono text-xl font-semibold tracking-tight text-foreground">
+                  {queue.min_worker_version || "--"}
+                </p>
+              </CardContent>
+            </Card>
+          </div>
+
+          {(queue.scheduled_hold || queue.release_max_per_tick) && (
+            <div className="rounded-lg border border-amber-500/30 bg-amber-500/5 px-4 py-3 text-sm text-foreground">
+              {queue.scheduled_hold
+                ? "Scheduled promotion is held"
+                : `Releasing scheduled tasks at ${queue.release_max_per_tick} per tick`}
+              <span className="text-muted-foreground">
+                {" "}
+                · {queue.held_past_due ?? 0} past-due task(s) waiting
+              </span>
+            </div>
+          )}
+
+          {labels.length > 0 && (
+            <div className="flex flex-wrap gap-2">
+              {labels.map(([key, value]) => (
+                <Badge key={key} variant="outline" className="font-mono text-xs">
+                  {key}={value}
+                </Badge>
+              ))}
+            </div>
+          )}
+
+          <p className="text-xs text-muted-foreground">
+            Updated {formatDate(queue.updated_at)}
+          </p>
+        </>
+      )}
+    </div>
+  );
+}
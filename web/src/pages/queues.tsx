@@ -0,0 +1,137 @@
+import { useState } from "react";
+import { useQuery } from "@tanstack/react-query";
+import { useNavigate } from "react-router-dom";
+import { Layers, RefreshCw } from "lucide-react";
+import { queuesApi } from "@/api/queues";
+import { formatDate } from "@/lib/utils";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import {
+  Table,
+  TableHeader,
+  TableBody,
+  TableRow,
+  TableHead,
+  TableCell,
+} from "@/components/ui/table";
+
+export function QueuesPage() {
+  const [ownerFilter, setOwnerFilter] = useState("");
+  const navigate = useNavigate();
+
+  const {
+    data: queues = [],
+    isLoading,
+    refetch,
+  } = useQuery({
+    queryKey: ["queues", ownerFilter],
+    queryFn: () => queuesApi.list(ownerFilter || undefined),
+  });
+
+  return (
+    <div className="space-y-6">
+      <div className="flex items-center justify-between">
+        <div>
+          <h1 className="text-2xl font-semibold tracking-tight text-foreground">
+            Queues
+          </h1>
+          <p className="mt-1 text-sm text-muted-foreground">
+            Queue ownership and on-call routing
+          </p>
+        </div>
+        <Button variant="outline" size="icon" onClick={() => refetch()}>
+          <RefreshCw className="h-4 w-4" />
+        </Button>
+      </div>
+
+      <div className="flex items-center gap-3">
+        <Input
+          type="text"
+          placeholder="Filter by owner team..."
+          value={ownerFilter}
+          onChange={(e) => setOwnerFilter(e.target.value)}
+          className="max-w-xs"
+        />
+      </div>
+
+      {isLoading ? (
+        <div className="flex h-64 items-center justify-center text-sm text-muted-foreground">
+          Loading...
+        </div>
+      ) : queues.length === 0 ? (
+        <div className="flex h-64 flex-col items-center justify-center rounded-lg border">
+          <div className="mb-3 flex h-10 w-10 items-center justify-center rounded-lg bg-muted">
+            <Layers className="h-5 w-5 text-muted-foreground" />
+          </div>
+          <p className="text-sm text-muted-foreground">No queue configs</p>
+          <p className="mt-1 text-xs text-muted-foreground/60">
+            Set ownership with `valka queue set`
+          </p>
+        </div>
+      ) : (
+        <div className="rounded-lg border">
+          <Table>
+            <TableHeader>
+              <TableRow className="hover:bg-transparent">
+                <TableHead className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+                  Queue
+                </TableHead>
+                <TableHead className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+                  Owner
+                </TableHead>
+                <TableHead className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+                  Contact
+                </TableHead>
+                <TableHead className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+                  Runbook
+                </TableHead>
+                <TableHead className="text-xs font-medium uppercase tracking-wider text-muted-foreground">
+                  Updated
+                </TableHead>
+              </TableRow>
+            </TableHeader>
+            <TableBody>
+              {queues.map((q) => (
+                <TableRow
+                  key={q.queue_name}
+                  className="cursor-pointer"
+                  onClick={() =>
+                    navigate(`/queues/${encodeURIComponent(q.queue_name)}`)
+                  }
+                >
+                  <TableCell className="font-medium text-foreground">
+                    {q.queue_name}
+                  </TableCell>
+                  <TableCell className="text-foreground">
+                    {q.owner_team || "--"}
+                  </TableCell>
+                  <TableCell className="text-muted-foreground">
+                    {q.contact || "--"}
+                  </TableCell>
+                  <TableCell className="text-xs">
+                    {q.runbook_url ? (
+                      <a
+                        href={q.runbook_url}
+                        target="_blank"
+                        rel="noreferrer"
+                        className="text-primary hover:underline"
+                        onClick={(e) => e.stopPropagation()}
+                      >
+                        Open
+                      </a>
+                    ) : (
+                      "--"
+                    )}
+                  </TableCell>
+                  <TableCell className="text-xs text-muted-foreground">
+                    {formatDate(q.updated_at)}
+                  </TableCell>
+                </TableRow>
+              ))}
+            </TableBody>
+          </Table>
+        </div>
+      )}
+    </div>
+  );
+}
@@ -1,6 +1,8 @@
 import { useState } from "react";
+import { useSearchParams } from "react-router-dom";
 import { Plus, RefreshCw, Trash2 } from "lucide-react";
-import { useTasks, useClearAllTasks } from "@/hooks/use-tasks";
+import type { TaskOrder, TaskSearchFilters } from "@/api/types";
+import { useTaskSearch, useClearAllTasks } from "@/hooks/use-tasks";
 import { TaskFilters } from "@/components/tasks/task-filters";
 import { TaskTable } from "@/components/tasks/task-table";
 import { TaskCreateDialog } from "@/components/tasks/task-create-dialog";
@@ -8,19 +10,41 @@ import { Button } from "@/components/ui/button";
 
 const PAGE_SIZE = 25;
 
+const FILTER_KEYS = [
+  "queue_name",
+  "status",
+  "task_name_prefix",
+  "created_after",
+  "created_before",
+  "idempotency_key",
+  "error_contains",
+  "order",
+] as const;
+
+/** Search filters and page live in the URL so a search can be shared. */
+function filtersFromParams(params: URLSearchParams): TaskSearchFilters {
+  const filters: TaskSearchFilters = {};
+  for (const key of FILTER_KEYS) {
+    const value = params.get(key);
+    if (value) {
+      if (key === "order") filters.order = value as TaskOrder;
+      else filters[key] = value;
+    }
+  }
+  return filters;
+}
+
 export function TasksPage() {
-  const [filters, setFilters] = useState<{
-    queue_name?: string;
-    status?: string;
-  }>({});
-  const [offset, setOffset] = useState(0);
+  const [searchParams, setSearchParams] = useSearchParams();
+  const filters = filtersFromParams(searchParams);
+  const offset = Number(searchParams.get("offset")) || 0;
   const [createOpen, setCreateOpen] = useState(false);
 
   const {
     data: tasks = [],
     isLoading,
     refetch,
-  } = useTasks({
+  } = useTaskSearch({
     ...filters,
     limit: PAGE_SIZE,
     offset,
@@ -28,9 +52,22 @@ export function TasksPage() {
 
   const clearAll = useClearAllTasks();
 
-  function handleFilter(params: { queue_name?: string; status?: string }) {
-    setFilters(params);
-    setOffset(0);
+  function updateParams(next: TaskSearchFilters, nextOffset: number) {
+    const params = new URLSearchParams();
+    for (const key of FILTER_KEYS) {
+      const value = next[key];
+      if (value) params.set(key, value);
+    }
+    if (nextOffset > 0) params.set("offset", String(nextOffset));
+    setSearchParams(params);
+  }
+
+  function handleFilter(next: TaskSearchFilters) {
+    updateParams(next, 0);
+  }
+
+  function setOffset(nextOffset: number) {
+    updateParams(filters, nextOffset);
   }
 
   function handleClearAll() {
@@ -72,9 +109,9 @@ export function TasksPage() {
       </div>
 
       <TaskFilters
+        key={FILTER_KEYS.map((key) => searchParams.get(key) ?? "").join("|")}
         onFilter={handleFilter}
-        initialQueue={filters.queue_name}
-        initialStatus={filters.status}
+        initial={filters}
       />
 
       <TaskTable